@@ -1,9 +1,8 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use pacm_store::{get_store_path, StoreManager};
+use pacm_store::get_store_path;
 use std::time::Duration;
 
 fn cache_lookup_operations(c: &mut Criterion) {
-    let store_manager = StoreManager::new();
     let store_path = get_store_path();
 
     let mut group = c.benchmark_group("cache_lookup");
@@ -21,7 +20,7 @@ fn cache_lookup_operations(c: &mut Criterion) {
             &(package, version),
             |b, &(pkg_name, pkg_version)| {
                 b.iter(|| {
-                    let package_key = format!("{}@{}", pkg_name, pkg_version);
+                    let _package_key = format!("{}@{}", pkg_name, pkg_version);
                     let safe_name = if pkg_name.starts_with('@') {
                         pkg_name.replace('@', "_at_").replace('/', "_slash_")
                     } else {
@@ -159,11 +158,61 @@ fn cache_batch_operations(c: &mut Criterion) {
     group.finish();
 }
 
+fn manifest_dependency_reads(c: &mut Criterion) {
+    use pacm_core::install::manifest_cache;
+
+    let mut group = c.benchmark_group("manifest_dependencies");
+
+    let store = tempfile::tempdir().expect("failed to create temp store");
+    let store_paths: Vec<_> = (0..500)
+        .map(|i| {
+            let store_path = store.path().join(format!("pkg-{}", i));
+            let package_dir = store_path.join("package");
+            std::fs::create_dir_all(&package_dir).unwrap();
+            let dependencies: serde_json::Map<String, serde_json::Value> = (0..20)
+                .map(|d| (format!("dep-{}", d), serde_json::json!("^1.0.0")))
+                .collect();
+            std::fs::write(
+                package_dir.join("package.json"),
+                serde_json::json!({
+                    "name": format!("pkg-{}", i),
+                    "version": "1.0.0",
+                    "dependencies": dependencies,
+                })
+                .to_string(),
+            )
+            .unwrap();
+            store_path
+        })
+        .collect();
+
+    group.bench_function("cold_parse", |b| {
+        b.iter(|| {
+            manifest_cache::clear();
+            for store_path in &store_paths {
+                let _ = manifest_cache::load(store_path);
+            }
+        });
+    });
+
+    manifest_cache::clear();
+    group.bench_function("memoized", |b| {
+        b.iter(|| {
+            for store_path in &store_paths {
+                let _ = manifest_cache::load(store_path);
+            }
+        });
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     cache_lookup_operations,
     cache_store_operations,
     cache_index_building,
-    cache_batch_operations
+    cache_batch_operations,
+    manifest_dependency_reads
 );
 criterion_main!(benches);
@@ -1,7 +1,6 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use pacm_core::download::PackageDownloader;
 use pacm_resolver::ResolvedPackage;
-use std::collections::HashMap;
 use std::time::Duration;
 
 fn download_small_packages(c: &mut Criterion) {
@@ -41,10 +40,12 @@ fn download_small_packages(c: &mut Criterion) {
                             version: pkg_version.to_string(),
                             resolved: pkg_url.to_string(),
                             integrity: "sha512-mock-integrity".to_string(),
-                            dependencies: HashMap::new(),
+                            ..Default::default()
                         };
 
-                        let _ = downloader.download_single(&resolved_package, false).await;
+                        let _ = downloader
+                            .download_parallel(std::slice::from_ref(&resolved_package), false)
+                            .await;
                     });
                 });
             },
@@ -59,7 +60,7 @@ fn download_parallel_packages(c: &mut Criterion) {
     let mut group = c.benchmark_group("download_parallel");
     group.measurement_time(Duration::from_secs(60));
 
-    let package_batches = vec![
+    let package_batches = [
         vec![(
             "lodash",
             "4.17.21",
@@ -108,7 +109,7 @@ fn download_parallel_packages(c: &mut Criterion) {
                             version: version.to_string(),
                             resolved: url.to_string(),
                             integrity: "sha512-mock-integrity".to_string(),
-                            dependencies: HashMap::new(),
+                            ..Default::default()
                         })
                         .collect();
 
@@ -129,7 +130,7 @@ fn download_with_concurrency_limits(c: &mut Criterion) {
     group.measurement_time(Duration::from_secs(45));
 
     let concurrency_levels = vec![1, 5, 10, 20];
-    let test_packages: Vec<ResolvedPackage> = vec![
+    let test_packages: Vec<ResolvedPackage> = [
         "lodash",
         "chalk",
         "debug",
@@ -142,13 +143,12 @@ fn download_with_concurrency_limits(c: &mut Criterion) {
         "commander",
     ]
     .iter()
-    .enumerate()
-    .map(|(i, name)| ResolvedPackage {
+    .map(|name| ResolvedPackage {
         name: name.to_string(),
         version: "1.0.0".to_string(),
         resolved: format!("https://registry.npmjs.org/{}/-/{}-1.0.0.tgz", name, name),
         integrity: "sha512-mock-integrity".to_string(),
-        dependencies: HashMap::new(),
+        ..Default::default()
     })
     .collect();
 
@@ -176,15 +176,14 @@ fn download_retry_mechanisms(c: &mut Criterion) {
         b.iter(|| {
             rt.block_on(async {
                 let downloader = PackageDownloader::new();
-                let _ = downloader.download_single(&failing_package, false).await;
                 let failing_package = ResolvedPackage {
                     name: "nonexistent-test-package".to_string(),
                     version: "1.0.0".to_string(),
                     resolved: "https://registry.npmjs.org/nonexistent-test-package/-/nonexistent-test-package-1.0.0.tgz".to_string(),
                     integrity: "sha512-mock-integrity".to_string(),
-                    dependencies: HashMap::new(),
+                    ..Default::default()
                 };
-                let _ = downloader.download_single(&failing_package, false).await;
+                let _ = downloader.download_parallel(std::slice::from_ref(&failing_package), false).await;
             });
         });
     });
@@ -218,10 +217,12 @@ fn download_different_sizes(c: &mut Criterion) {
                                 pkg_name, pkg_name, pkg_version
                             ),
                             integrity: "sha512-mock-integrity".to_string(),
-                            dependencies: HashMap::new(),
+                            ..Default::default()
                         };
 
-                        let _ = downloader.download_single(&resolved_package, false).await;
+                        let _ = downloader
+                            .download_parallel(std::slice::from_ref(&resolved_package), false)
+                            .await;
                     });
                 });
             },
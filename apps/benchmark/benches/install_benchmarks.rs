@@ -24,11 +24,11 @@ fn install_small_package(c: &mut Criterion) {
             let temp_dir = create_temp_project();
             let manager = InstallManager::new();
 
-            let _ = manager.install_single_dependency(
+            let _ = manager.install_single(
                 temp_dir.path().to_str().unwrap(),
                 "lodash",
                 "latest",
-                DependencyType::Dependency,
+                DependencyType::Dependencies,
                 false,
                 true, // no save
                 false,
@@ -44,11 +44,11 @@ fn install_medium_package(c: &mut Criterion) {
             let temp_dir = create_temp_project();
             let manager = InstallManager::new();
 
-            let _ = manager.install_single_dependency(
+            let _ = manager.install_single(
                 temp_dir.path().to_str().unwrap(),
                 "express",
                 "latest",
-                DependencyType::Dependency,
+                DependencyType::Dependencies,
                 false,
                 true,
                 false,
@@ -59,7 +59,7 @@ fn install_medium_package(c: &mut Criterion) {
 }
 
 fn install_multiple_packages(c: &mut Criterion) {
-    let packages = vec!["lodash", "chalk", "debug"];
+    let packages = ["lodash", "chalk", "debug"];
 
     let mut group = c.benchmark_group("install_multiple");
     group.measurement_time(Duration::from_secs(60));
@@ -73,12 +73,12 @@ fn install_multiple_packages(c: &mut Criterion) {
                     let temp_dir = create_temp_project();
                     let manager = InstallManager::new();
 
-                    for i in 0..(package_count.min(packages.len())) {
-                        let _ = manager.install_single_dependency(
+                    for package in packages.iter().take(package_count) {
+                        let _ = manager.install_single(
                             temp_dir.path().to_str().unwrap(),
-                            packages[i],
+                            package,
                             "latest",
-                            DependencyType::Dependency,
+                            DependencyType::Dependencies,
                             false,
                             true,
                             false,
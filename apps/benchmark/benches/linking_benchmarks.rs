@@ -0,0 +1,72 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use pacm_core::linker::manager::in_link_pool;
+use pacm_core::linker::project::ProjectLinker;
+use pacm_resolver::ResolvedPackage;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const PACKAGE_COUNT: usize = 500;
+
+/// A fake store with `count` extracted packages, a tenth of them scoped.
+fn build_store(store_base: &Path, count: usize) -> HashMap<String, (ResolvedPackage, PathBuf)> {
+    (0..count)
+        .map(|index| {
+            let name = if index % 10 == 0 {
+                format!("@bench/pkg-{}", index)
+            } else {
+                format!("pkg-{}", index)
+            };
+            let store_path = store_base.join(name.replace('/', "+"));
+            std::fs::create_dir_all(store_path.join("package")).unwrap();
+            std::fs::write(store_path.join("package").join("index.js"), "").unwrap();
+
+            let pkg = ResolvedPackage {
+                name: name.clone(),
+                version: "1.0.0".to_string(),
+                ..Default::default()
+            };
+            (format!("{}@1.0.0", name), (pkg, store_path))
+        })
+        .collect()
+}
+
+fn link_node_modules(c: &mut Criterion) {
+    pacm_logger::init_logger(true);
+    let store_base = tempfile::tempdir().unwrap();
+    let stored = build_store(store_base.path(), PACKAGE_COUNT);
+
+    let mut group = c.benchmark_group("link_node_modules");
+    group.measurement_time(Duration::from_secs(20));
+
+    group.bench_with_input(
+        BenchmarkId::new("sequential", PACKAGE_COUNT),
+        &stored,
+        |b, stored| {
+            let project = tempfile::tempdir().unwrap();
+            let node_modules = project.path().join("node_modules");
+            b.iter(|| {
+                for (pkg, store_path) in stored.values() {
+                    pacm_store::link_package(&node_modules, &pkg.name, store_path).unwrap();
+                }
+            });
+        },
+    );
+
+    group.bench_with_input(
+        BenchmarkId::new("parallel", PACKAGE_COUNT),
+        &stored,
+        |b, stored| {
+            let project = tempfile::tempdir().unwrap();
+            b.iter(|| {
+                in_link_pool(|| ProjectLinker::link_all_deps(project.path(), stored, false))
+                    .unwrap();
+            });
+        },
+    );
+
+    group.finish();
+}
+
+criterion_group!(benches, link_node_modules);
+criterion_main!(benches);
@@ -1,5 +1,5 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use pacm_resolver::{resolve_full_tree_async, ResolvedPackage};
+use pacm_resolver::resolve_full_tree_async;
 use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
@@ -63,7 +63,7 @@ fn resolve_multiple_dependencies(c: &mut Criterion) {
     let mut group = c.benchmark_group("resolve_multiple");
     group.measurement_time(Duration::from_secs(60));
 
-    let dependency_sets = vec![
+    let dependency_sets = [
         vec![("lodash", "^4.17.21")],
         vec![("lodash", "^4.17.21"), ("chalk", "^4.1.2")],
         vec![
@@ -1,8 +1,7 @@
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use pacm_core::InstallManager;
 use pacm_project::DependencyType;
-use std::time::Duration;
-use sysinfo::{PidExt, ProcessExt, System, SystemExt};
+use sysinfo::System;
 use tempfile::TempDir;
 
 fn create_temp_project() -> TempDir {
@@ -20,7 +19,6 @@ fn create_temp_project() -> TempDir {
 }
 
 fn memory_usage_benchmark(c: &mut Criterion) {
-    let rt = tokio::runtime::Runtime::new().unwrap();
     let mut system = System::new_all();
 
     c.bench_function("memory_usage_small_install", |b| {
@@ -31,18 +29,16 @@ fn memory_usage_benchmark(c: &mut Criterion) {
             let temp_dir = create_temp_project();
             let manager = InstallManager::new();
 
-            rt.block_on(async {
-                let _ = manager.install_single_dependency(
-                    temp_dir.path().to_str().unwrap(),
-                    "lodash",
-                    "4.17.21",
-                    DependencyType::Dependency,
-                    false,
-                    true, // no save
-                    false,
-                    false,
-                );
-            });
+            let _ = manager.install_single(
+                temp_dir.path().to_str().unwrap(),
+                "lodash",
+                "4.17.21",
+                DependencyType::Dependencies,
+                false,
+                true, // no save
+                false,
+                false,
+            );
 
             system.refresh_all();
             let final_memory = get_current_memory_usage(&system);
@@ -54,8 +50,6 @@ fn memory_usage_benchmark(c: &mut Criterion) {
 }
 
 fn concurrent_operations_benchmark(c: &mut Criterion) {
-    let rt = tokio::runtime::Runtime::new().unwrap();
-
     c.bench_function("concurrent_small_installs", |b| {
         b.iter(|| {
             let packages = vec![
@@ -64,39 +58,35 @@ fn concurrent_operations_benchmark(c: &mut Criterion) {
                 ("debug", "^4.3.2"),
             ];
 
-            rt.block_on(async {
-                let futures: Vec<_> = packages
-                    .into_iter()
-                    .map(|(name, version)| {
+            let handles: Vec<_> = packages
+                .into_iter()
+                .map(|(name, version)| {
+                    std::thread::spawn(move || {
                         let temp_dir = create_temp_project();
                         let manager = InstallManager::new();
 
-                        async move {
-                            manager
-                                .install_single_dependency(
-                                    temp_dir.path().to_str().unwrap(),
-                                    name,
-                                    version,
-                                    DependencyType::Dependency,
-                                    false,
-                                    true, // no save
-                                    false,
-                                    false,
-                                )
-                                .await
-                        }
+                        let _ = manager.install_single(
+                            temp_dir.path().to_str().unwrap(),
+                            name,
+                            version,
+                            DependencyType::Dependencies,
+                            false,
+                            true, // no save
+                            false,
+                            false,
+                        );
                     })
-                    .collect();
+                })
+                .collect();
 
-                futures::future::join_all(futures).await;
-            });
+            for handle in handles {
+                let _ = handle.join();
+            }
         })
     });
 }
 
 fn stress_test_many_packages(c: &mut Criterion) {
-    let rt = tokio::runtime::Runtime::new().unwrap();
-
     let package_counts = vec![5, 10, 20];
 
     for count in package_counts {
@@ -109,22 +99,18 @@ fn stress_test_many_packages(c: &mut Criterion) {
                     let temp_dir = create_temp_project();
                     let manager = InstallManager::new();
 
-                    rt.block_on(async {
-                        for (name, version) in packages {
-                            let _ = manager
-                                .install_single_dependency(
-                                    temp_dir.path().to_str().unwrap(),
-                                    name,
-                                    version,
-                                    DependencyType::Dependency,
-                                    false,
-                                    true, // no save
-                                    false,
-                                    false,
-                                )
-                                .await;
-                        }
-                    });
+                    for (name, version) in packages {
+                        let _ = manager.install_single(
+                            temp_dir.path().to_str().unwrap(),
+                            name,
+                            version,
+                            DependencyType::Dependencies,
+                            false,
+                            true, // no save
+                            false,
+                            false,
+                        );
+                    }
                 })
             },
         );
@@ -132,44 +118,34 @@ fn stress_test_many_packages(c: &mut Criterion) {
 }
 
 fn cache_performance_benchmark(c: &mut Criterion) {
-    let rt = tokio::runtime::Runtime::new().unwrap();
-
     c.bench_function("cache_hit_performance", |b| {
         let temp_dir = create_temp_project();
         let manager = InstallManager::new();
 
-        rt.block_on(async {
-            let _ = manager
-                .install_single_dependency(
-                    temp_dir.path().to_str().unwrap(),
-                    "lodash",
-                    "4.17.21",
-                    DependencyType::Dependency,
-                    false,
-                    true,
-                    false,
-                    false,
-                )
-                .await;
-        });
+        let _ = manager.install_single(
+            temp_dir.path().to_str().unwrap(),
+            "lodash",
+            "4.17.21",
+            DependencyType::Dependencies,
+            false,
+            true,
+            false,
+            false,
+        );
 
         b.iter(|| {
             let temp_dir2 = create_temp_project();
 
-            rt.block_on(async {
-                let _ = manager
-                    .install_single_dependency(
-                        temp_dir2.path().to_str().unwrap(),
-                        "lodash",
-                        "4.17.21",
-                        DependencyType::Dependency,
-                        false,
-                        true,
-                        false,
-                        false,
-                    )
-                    .await;
-            });
+            let _ = manager.install_single(
+                temp_dir2.path().to_str().unwrap(),
+                "lodash",
+                "4.17.21",
+                DependencyType::Dependencies,
+                false,
+                true,
+                false,
+                false,
+            );
         })
     });
 }
@@ -46,7 +46,7 @@ impl PerformanceMonitor {
 
             self.durations
                 .entry(operation.to_string())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(duration);
 
             Some(duration)
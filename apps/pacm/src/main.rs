@@ -1,3 +1,6 @@
 fn main() {
-    let _ = pacm_cli::run_cli();
+    if let Err(e) = pacm_cli::run_cli() {
+        pacm_logger::error(&e.to_string());
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,208 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Runs the pacm binary in `project` with a home directory of its own, so nothing is read
+/// from or written to the real one.
+fn pacm(project: &Path, args: &[&str]) -> Output {
+    let home = tempfile::tempdir().unwrap();
+    Command::new(env!("CARGO_BIN_EXE_pacm"))
+        .args(args)
+        .arg("--no-update-notifier")
+        .current_dir(project)
+        .env("HOME", home.path())
+        .env("USERPROFILE", home.path())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_quiet_and_silent_print_nothing_but_keep_exit_code() {
+    let project = tempfile::tempdir().unwrap();
+    std::fs::create_dir(project.path().join("local-dep")).unwrap();
+    std::fs::write(
+        project.path().join("local-dep/package.json"),
+        r#"{ "name": "local-dep", "version": "1.0.0" }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        project.path().join("package.json"),
+        r#"{ "name": "quiet-test", "version": "1.0.0", "dependencies": { "local-dep": "file:./local-dep" } }"#,
+    )
+    .unwrap();
+
+    let output = pacm(project.path(), &["install", "--quiet"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stdout.is_empty(), "{output:?}");
+    assert!(project.path().join("node_modules/local-dep").exists());
+
+    // Without a package.json the install fails.
+    let empty = tempfile::tempdir().unwrap();
+    let output = pacm(empty.path(), &["install", "-q"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stdout)
+            .to_lowercase()
+            .contains("package.json")
+    );
+
+    let output = pacm(empty.path(), &["install", "--silent"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty(), "{output:?}");
+    assert!(output.stderr.is_empty(), "{output:?}");
+}
+
+#[test]
+fn test_run_exits_non_zero_when_the_script_does_not_succeed() {
+    let project = tempfile::tempdir().unwrap();
+    let manifest = |scripts: &str| {
+        std::fs::write(
+            project.path().join("package.json"),
+            format!(r#"{{ "name": "run-test", "version": "1.0.0"{scripts} }}"#),
+        )
+        .unwrap();
+    };
+
+    manifest("");
+    let output = pacm(project.path(), &["run", "build"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+
+    manifest(r#", "scripts": { "build": "exit 0", "test": "exit 3" }"#);
+    let output = pacm(project.path(), &["run", "build"]);
+    assert!(output.status.success(), "{output:?}");
+
+    let output = pacm(project.path(), &["run", "test"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+
+    let output = pacm(project.path(), &["run", "lint"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+
+    let output = pacm(project.path(), &["run", "build", "--workspaces"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+}
+
+#[test]
+fn test_global_install_resolves_local_paths_against_the_working_directory() {
+    let home = tempfile::tempdir().unwrap();
+    let work = tempfile::tempdir().unwrap();
+    std::fs::create_dir(work.path().join("tool")).unwrap();
+    std::fs::write(
+        work.path().join("tool/package.json"),
+        r#"{ "name": "global-tool", "version": "1.0.0" }"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pacm"))
+        .args(["install", "-g", "link:./tool", "--no-update-notifier"])
+        .current_dir(work.path())
+        .env("HOME", home.path())
+        .env("USERPROFILE", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let prefix = home.path().join(".pacm/global");
+    assert!(
+        prefix
+            .join("node_modules/global-tool/package.json")
+            .exists()
+    );
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(prefix.join("package.json")).unwrap())
+            .unwrap();
+    let recorded = manifest["dependencies"]["global-tool"].as_str().unwrap();
+    let recorded = recorded.strip_prefix("link:").unwrap();
+    assert!(Path::new(recorded).is_absolute(), "{recorded}");
+}
+
+#[test]
+fn test_color_choice_applies_to_all_output() {
+    let project = tempfile::tempdir().unwrap();
+    std::fs::write(
+        project.path().join("package.json"),
+        r#"{ "name": "color-test", "version": "1.0.0" }"#,
+    )
+    .unwrap();
+
+    for args in [&["install"][..], &["install", "--color", "never"]] {
+        let output = pacm(project.path(), args);
+        assert!(output.status.success(), "{output:?}");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(!stdout.contains('\x1b'), "{stdout:?}");
+    }
+
+    // Colors can be forced into a pipe, but the progress line is still not redrawn there.
+    let output = pacm(project.path(), &["install", "--color", "always"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b["), "{stdout:?}");
+    assert!(!stdout.contains("\x1b[2K"), "{stdout:?}");
+}
+
+#[test]
+fn test_save_prod_moves_a_dev_dependency_to_dependencies() {
+    let registry = pacm_testutil::MockRegistry::start();
+    registry.publish("prod-dep", "1.0.0", &[]);
+    let home = tempfile::tempdir().unwrap();
+    std::fs::write(
+        home.path().join(".npmrc"),
+        format!("registry={}\n", registry.url()),
+    )
+    .unwrap();
+    let project = tempfile::tempdir().unwrap();
+    std::fs::write(
+        project.path().join("package.json"),
+        r#"{ "name": "save-prod-test", "version": "1.0.0", "devDependencies": { "prod-dep": "^1.0.0" } }"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_pacm"))
+        .args(["install", "-P", "prod-dep", "--no-update-notifier"])
+        .current_dir(project.path())
+        .env("HOME", home.path())
+        .env("USERPROFILE", home.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let manifest: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(project.path().join("package.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(manifest["dependencies"]["prod-dep"], "^1.0.0");
+    assert!(manifest["devDependencies"].get("prod-dep").is_none());
+}
+
+#[test]
+fn test_install_json_is_rejected_without_a_lockfile() {
+    let project = tempfile::tempdir().unwrap();
+    std::fs::write(
+        project.path().join("package.json"),
+        r#"{ "name": "json-nolock-test", "version": "1.0.0" }"#,
+    )
+    .unwrap();
+    std::fs::write(project.path().join(".npmrc"), "lockfile=false\n").unwrap();
+
+    let output = pacm(project.path(), &["install", "--json"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("lockfile=false"),
+        "{output:?}"
+    );
+
+    let output = pacm(project.path(), &["install", "--json", "--no-lockfile"]);
+    assert!(!output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_failing_commands_print_their_error() {
+    let empty = tempfile::tempdir().unwrap();
+
+    let output = pacm(empty.path(), &["remove", "left-pad"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stdout)
+            .to_lowercase()
+            .contains("package.json"),
+        "{output:?}"
+    );
+}
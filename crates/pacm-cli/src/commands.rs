@@ -11,6 +11,49 @@ use clap::{Parser, Subcommand};
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    /// Don't check for or announce newer pacm releases
+    #[arg(long, global = true)]
+    pub no_update_notifier: bool,
+    /// Output format: pretty (the default), json (one event per line) or github (workflow annotations)
+    #[arg(
+        long,
+        global = true,
+        default_value = "pretty",
+        value_parser = ["pretty", "json", "github"]
+    )]
+    pub reporter: String,
+    /// When to color output: auto (a terminal without NO_COLOR), always or never
+    #[arg(
+        long,
+        global = true,
+        default_value = "auto",
+        value_parser = ["always", "auto", "never"]
+    )]
+    pub color: String,
+    /// Don't color output, same as --color=never
+    #[arg(long, global = true, conflicts_with = "color")]
+    pub no_color: bool,
+    /// Only print errors
+    #[arg(short = 'q', long, global = true)]
+    pub quiet: bool,
+    /// Print nothing, not even errors; only the exit code tells how the command went
+    #[arg(long, global = true, conflicts_with = "quiet")]
+    pub silent: bool,
+    /// Fail instead of warning when package.json's packageManager pins another pacm version
+    #[arg(long, global = true)]
+    pub strict_version: bool,
+    /// Fetch full registry documents instead of the abbreviated install metadata
+    #[arg(long, global = true)]
+    pub full_metadata: bool,
+    /// How long saved registry metadata is trusted before revalidating, e.g. 30s, 10m, 1h
+    #[arg(long, global = true, value_name = "DURATION")]
+    pub cache_max_age: Option<String>,
+    /// Verify registry TLS certificates; `--strict-ssl=false` accepts any certificate
+    #[arg(long, global = true, value_name = "BOOL", value_parser = ["true", "false"])]
+    pub strict_ssl: Option<String>,
+    /// PEM file of extra root certificates to trust, e.g. an internal registry's CA
+    #[arg(long, global = true, value_name = "PATH")]
+    pub cafile: Option<String>,
 }
 
 #[derive(Subcommand)]
@@ -28,8 +71,15 @@ pub enum Commands {
         #[arg(short = 'O', long = "optional")]
         optional: bool,
         /// Install as peerDependency
-        #[arg(short = 'P', long = "peer")]
+        #[arg(long = "peer", alias = "save-peer")]
         peer: bool,
+        /// Install as dependency, moving it there from another section
+        #[arg(
+            short = 'P',
+            long = "save-prod",
+            conflicts_with_all = ["dev", "optional", "peer"]
+        )]
+        save_prod: bool,
         /// Install globally
         #[arg(short = 'g', long = "global")]
         global: bool,
@@ -39,9 +89,75 @@ pub enum Commands {
         /// Don't save to package.json
         #[arg(long = "no-save")]
         no_save: bool,
+        /// Also list the packages in bundledDependencies, for publishing
+        #[arg(long = "bundle", alias = "save-bundle", conflicts_with = "no_save")]
+        bundle: bool,
         /// Force reinstall even if already installed
         #[arg(short = 'f', long = "force")]
         force: bool,
+        /// Dependency types to install: dev, optional, peer (wins over --omit)
+        #[arg(long, value_delimiter = ',', value_parser = ["dev", "optional", "peer"])]
+        include: Vec<String>,
+        /// Dependency types to skip: dev, optional, peer
+        #[arg(long, value_delimiter = ',', value_parser = ["dev", "optional", "peer"])]
+        omit: Vec<String>,
+        /// Skip devDependencies, same as --omit=dev
+        #[arg(long = "production", alias = "prod")]
+        production: bool,
+        /// Only resolve N levels of transitive dependencies (0 = direct only); not a complete install
+        #[arg(long, value_name = "N")]
+        depth: Option<usize>,
+        /// node_modules layout: hoisted (flat, the default) or isolated (direct deps only)
+        #[arg(long, value_name = "MODE", value_parser = ["hoisted", "isolated"])]
+        node_linker: Option<String>,
+        /// Resolve from the store only and never contact the registry
+        #[arg(long, conflicts_with = "prefer_offline")]
+        offline: bool,
+        /// With --offline, go to the registry for packages the store cannot satisfy
+        #[arg(long, requires = "offline")]
+        fallback_online: bool,
+        /// Resolve from saved metadata and the store, and use the registry only on a miss
+        #[arg(long)]
+        prefer_offline: bool,
+        /// Drop docs, tests and source maps from newly stored packages
+        #[arg(long)]
+        prune_store: bool,
+        /// On full installs, also install missing peers of direct dependencies (like npm's auto-install-peers)
+        #[arg(long)]
+        install_peers: bool,
+        /// Keep every resolved version instead of collapsing duplicates during install
+        #[arg(long)]
+        no_dedupe: bool,
+        /// Warn when a direct dependency's `main` or `exports` points to a missing file
+        #[arg(long)]
+        verify_exports: bool,
+        /// Verify registry signatures of downloaded packages and fail on invalid ones
+        #[arg(long)]
+        audit_signatures: bool,
+        /// Print a one-line vulnerability summary once installed (config: audit=true)
+        #[arg(long, conflicts_with = "check")]
+        audit: bool,
+        /// Install without creating or updating pacm.lock
+        #[arg(long)]
+        no_lockfile: bool,
+        /// Fail instead of resolving again when package.json no longer matches pacm.lock
+        #[arg(long, conflicts_with = "no_lockfile")]
+        frozen_lockfile: bool,
+        /// Copy file: and link: directories into node_modules, not symlinks (config: install-links=true)
+        #[arg(long)]
+        install_links: bool,
+        /// Also write node_modules/.package-lock.json for tools that read npm's lockfile (config: npm-compat=true)
+        #[arg(long)]
+        npm_compat: bool,
+        /// Verify that node_modules matches pacm.lock without installing anything
+        #[arg(long, conflicts_with = "packages")]
+        check: bool,
+        /// Print how long each install phase took (also emitted by --reporter json)
+        #[arg(long, conflicts_with = "check")]
+        timing: bool,
+        /// Print the packages the install added, updated and removed in pacm.lock as JSON
+        #[arg(long, conflicts_with_all = ["check", "global", "no_lockfile"])]
+        json: bool,
         /// Enable debug mode for verbose output
         #[arg(long)]
         debug: bool,
@@ -52,24 +168,42 @@ pub enum Commands {
         /// Skips interactive prompts
         #[arg(short = 'y', long = "yes")]
         yes: bool,
+        /// Overwrite an existing package.json
+        #[arg(short = 'f', long = "force")]
+        force: bool,
+        /// Add the missing fields to an existing package.json, keeping the ones it has
+        #[arg(long = "merge", conflicts_with = "force")]
+        merge: bool,
     },
     /// Runs a script defined in package.json
     #[command(alias = "r")]
     Run {
-        /// The name of the script (e.g. build, test, etc.)
-        script: String,
+        /// The name of the script (e.g. build, test, etc.); lists the scripts when omitted
+        script: Option<String>,
+        /// Run the script in every workspace member that defines it
+        #[arg(long = "workspaces", alias = "ws")]
+        workspaces: bool,
+        /// Only run in workspace members whose name matches the glob (implies --workspaces)
+        #[arg(long = "filter")]
+        filter: Option<String>,
+        /// Keep running the remaining workspaces after a failure
+        #[arg(long = "no-bail")]
+        no_bail: bool,
     },
     /// Starts the application (runs start script or main entry point)
     Start,
     /// Removes packages
     #[command(aliases = ["rm", "uninstall"])]
     Remove {
-        /// List of packages to remove
+        /// List of packages to remove, or globs such as '@acme/*'
         #[arg(required = true)]
         packages: Vec<String>,
         /// Remove from devDependencies only
         #[arg(short = 'D', long = "dev")]
         dev: bool,
+        /// Remove globally installed packages
+        #[arg(short = 'g', long = "global")]
+        global: bool,
         /// Only remove direct packages, not their transitive dependencies
         #[arg(
             long = "direct-only",
@@ -92,6 +226,12 @@ pub enum Commands {
         /// List of packages to update (if empty, updates all)
         #[arg()]
         packages: Vec<String>,
+        /// Update globally installed packages
+        #[arg(short = 'g', long = "global")]
+        global: bool,
+        /// Keep dependencies at versions already in the lockfile when their ranges allow it
+        #[arg(long)]
+        prefer_dedupe: bool,
         /// Enable debug mode for verbose output
         #[arg(long)]
         debug: bool,
@@ -105,6 +245,47 @@ pub enum Commands {
         /// Show only top-level dependencies
         #[arg(long)]
         depth: Option<u32>,
+        /// List locked packages whose version does not satisfy every dependent's range
+        /// and exit non-zero if any exist
+        #[arg(long, alias = "duplicates", conflicts_with = "tree")]
+        conflicts: bool,
+        /// List only production dependencies and what they install
+        #[arg(long, conflicts_with_all = ["dev", "conflicts"])]
+        prod: bool,
+        /// List only dev dependencies and what they install
+        #[arg(long, conflicts_with = "conflicts")]
+        dev: bool,
+        /// List globally installed packages and the commands they provide
+        #[arg(
+            short = 'g',
+            long = "global",
+            conflicts_with_all = ["tree", "conflicts", "prod", "dev", "json"]
+        )]
+        global: bool,
+        /// Print the dependency tree as JSON
+        #[arg(long, conflicts_with = "conflicts")]
+        json: bool,
+    },
+    /// Lists dependencies that have newer versions available
+    Outdated {
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
+        /// Enable debug mode for verbose output
+        #[arg(long)]
+        debug: bool,
+    },
+    /// Shows which dependency chains pull in a package
+    Why {
+        /// The package to explain (e.g. loose-envify)
+        #[arg(required_unless_present = "orphans")]
+        package: Option<String>,
+        /// List locked packages that no dependency leads to, failing if there are any
+        #[arg(long, conflicts_with = "package")]
+        orphans: bool,
+        /// Print the chains, or the orphans, as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Cleans package cache and optionally local node_modules
     Clean {
@@ -121,6 +302,73 @@ pub enum Commands {
         #[arg(long)]
         debug: bool,
     },
+    /// Inspects the global package store, or adds packages to it
+    #[command(alias = "cache")]
+    Store {
+        #[command(subcommand)]
+        command: StoreCommands,
+    },
+    /// Checks installed packages for known vulnerabilities
+    Audit {
+        /// Use `fix` to upgrade vulnerable packages within their allowed ranges
+        #[arg(value_parser = ["fix"])]
+        action: Option<String>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+        /// Minimum severity that makes the command exit with a non-zero code
+        #[arg(
+            long = "audit-level",
+            default_value = "low",
+            value_parser = ["info", "low", "moderate", "high", "critical"]
+        )]
+        audit_level: String,
+        /// Enable debug mode for verbose output
+        #[arg(long)]
+        debug: bool,
+    },
+    /// Packs the project into a tarball, as it would be published
+    Pack {
+        /// Only list the files that would be packed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Publishes the project to its registry
+    Publish {
+        /// The dist-tag to point at the published version
+        #[arg(long, default_value = "latest")]
+        tag: String,
+        /// Who can install the package, overriding publishConfig.access
+        #[arg(long, value_parser = ["public", "restricted"])]
+        access: Option<String>,
+        /// Pack and report what would be published without uploading
+        #[arg(long)]
+        dry_run: bool,
+        /// Publish even if the registry already has this version
+        #[arg(long)]
+        force: bool,
+    },
+    /// Seeds pacm.lock from an npm or Yarn 1 lockfile
+    Import {
+        /// The lockfile to import; package-lock.json, npm-shrinkwrap.json or yarn.lock when omitted
+        #[arg()]
+        file: Option<String>,
+        /// Replace an existing pacm.lock
+        #[arg(long)]
+        force: bool,
+    },
+    /// Writes pacm.lock as a package-lock.json or yarn.lock
+    Export {
+        /// The lockfile format to write
+        #[arg(long, value_parser = ["npm", "yarn"])]
+        format: String,
+        /// Where to write it; package-lock.json or yarn.lock when omitted
+        #[arg(long, short)]
+        output: Option<String>,
+        /// Replace an existing file
+        #[arg(long)]
+        force: bool,
+    },
     /// Shows help information for pacm or a specific command
     Help {
         /// The command to show help for (optional)
@@ -128,3 +376,44 @@ pub enum Commands {
         command: Option<String>,
     },
 }
+
+#[derive(Subcommand)]
+pub enum StoreCommands {
+    /// Prints the store directory
+    Path,
+    /// Shows the store size and how many packages and versions it holds
+    Stats,
+    /// Lists the stored versions of a package
+    Ls {
+        /// The package to look up (e.g. react or @types/node)
+        package: String,
+    },
+    /// Downloads a package into the store without installing it anywhere
+    Add {
+        /// The package to store (e.g. react@18.2.0)
+        package: String,
+        /// Also store its whole dependency tree
+        #[arg(long)]
+        deps: bool,
+        /// Enable debug mode for verbose output
+        #[arg(long)]
+        debug: bool,
+    },
+    /// Removes stored packages no project has linked for a number of days. Packages that
+    /// a project installed from this store still locks or links are always kept
+    Gc {
+        /// Remove packages unused for at least this many days
+        #[arg(long, default_value_t = 30)]
+        days: u64,
+        /// Also keep every package locked by this project, for projects installed before
+        /// pacm recorded them with the store (repeatable)
+        #[arg(long = "project", value_name = "DIR")]
+        projects: Vec<String>,
+        /// Only list what would be removed
+        #[arg(long)]
+        dry_run: bool,
+        /// Skip the confirmation prompt
+        #[arg(short = 'y', long = "yes")]
+        yes: bool,
+    },
+}
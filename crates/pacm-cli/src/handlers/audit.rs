@@ -0,0 +1,37 @@
+use anyhow::Result;
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
+
+use pacm_core;
+
+pub struct AuditHandler;
+
+impl AuditHandler {
+    pub fn handle_audit(
+        action: Option<&str>,
+        json: bool,
+        audit_level: &str,
+        debug: bool,
+    ) -> Result<()> {
+        if !json {
+            Self::print_audit_header(action);
+        }
+
+        match action {
+            Some("fix") => pacm_core::audit_fix(".", json, audit_level, debug),
+            _ => pacm_core::audit(".", json, audit_level, debug),
+        }
+    }
+
+    fn print_audit_header(action: Option<&str>) {
+        println!(
+            "{} {}",
+            "pacm".if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold())),
+            match action {
+                Some("fix") => "audit fix",
+                _ => "audit",
+            }
+            .if_supports_color(Stdout, |text| text.bright_white())
+        );
+        println!();
+    }
+}
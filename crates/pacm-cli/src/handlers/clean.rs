@@ -1,5 +1,5 @@
 use anyhow::Result;
-use owo_colors::OwoColorize;
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
 
 use pacm_core;
 use pacm_logger;
@@ -31,17 +31,19 @@ impl CleanHandler {
             println!();
             println!(
                 "{} {}",
-                "⚠️ ".bright_yellow(),
-                "CACHE CLEANING WARNING".bright_yellow().bold()
+                "⚠️ ".if_supports_color(Stdout, |text| text.bright_yellow()),
+                "CACHE CLEANING WARNING".if_supports_color(Stdout, |text| text
+                    .style(Style::new().bright_yellow().bold()))
             );
             println!();
             println!(
                 "{}",
-                "This will remove ALL cached packages from the global store.".bright_red()
+                "This will remove everything in the cache directory, which is the global store unless cache-dir is set.".if_supports_color(Stdout, |text| text.bright_red())
             );
             println!(
                 "{}",
-                "You will need to re-download packages for future installations.".bright_red()
+                "You will need to re-download packages for future installations."
+                    .if_supports_color(Stdout, |text| text.bright_red())
             );
             println!();
 
@@ -58,17 +60,20 @@ impl CleanHandler {
             println!();
             println!(
                 "{} {}",
-                "⚠️ ".bright_yellow(),
-                "NODE_MODULES CLEANING WARNING".bright_yellow().bold()
+                "⚠️ ".if_supports_color(Stdout, |text| text.bright_yellow()),
+                "NODE_MODULES CLEANING WARNING".if_supports_color(Stdout, |text| text
+                    .style(Style::new().bright_yellow().bold()))
             );
             println!();
             println!(
                 "{}",
-                "This will remove the local node_modules directory.".bright_red()
+                "This will remove the local node_modules directory."
+                    .if_supports_color(Stdout, |text| text.bright_red())
             );
             println!(
                 "{}",
-                "You will need to run 'pacm install' to restore dependencies.".bright_red()
+                "You will need to run 'pacm install' to restore dependencies."
+                    .if_supports_color(Stdout, |text| text.bright_red())
             );
             println!();
 
@@ -81,7 +86,11 @@ impl CleanHandler {
     }
 
     fn print_clean_header() {
-        println!("{} {}", "pacm".bright_cyan().bold(), "clean".bright_white());
+        println!(
+            "{} {}",
+            "pacm".if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold())),
+            "clean".if_supports_color(Stdout, |text| text.bright_white())
+        );
         println!();
     }
 }
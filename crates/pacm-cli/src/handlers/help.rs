@@ -1,6 +1,6 @@
 use anyhow::Result;
 use clap::CommandFactory;
-use owo_colors::OwoColorize;
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
 
 use crate::commands::Cli;
 use pacm_constants::{BIN_NAME, COMMANDS, DESCRIPTION, EXAMPLES, REPOSITORY_URL, VERSION};
@@ -28,7 +28,8 @@ impl HelpHandler {
         } else {
             println!(
                 "{}: Unknown command '{}'",
-                "Error".bright_red().bold(),
+                "Error"
+                    .if_supports_color(Stdout, |text| text.style(Style::new().bright_red().bold())),
                 command
             );
             println!();
@@ -41,27 +42,44 @@ impl HelpHandler {
 
     fn show_custom_help() {
         // Header
-        println!("{}", DESCRIPTION.bright_white().bold());
+        println!(
+            "{}",
+            DESCRIPTION.if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_white().bold()))
+        );
         println!(
             "{} {}",
-            "Version:".bright_white().bold(),
-            VERSION.bright_black().bold()
+            "Version:".if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_white().bold())),
+            VERSION.if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_black().bold()))
         );
         println!();
 
         // Usage
-        println!("{}", "Usage:".bright_magenta().bold());
+        println!(
+            "{}",
+            "Usage:".if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_magenta().bold()))
+        );
         println!(
             "  {} {} {} {}",
-            BIN_NAME.bright_cyan().bold(),
-            "<COMMAND>".bright_white(),
-            "<OPTIONS>".bright_black().bold(),
-            "[ARGS]".bright_black().bold()
+            BIN_NAME
+                .if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold())),
+            "<COMMAND>".if_supports_color(Stdout, |text| text.bright_white()),
+            "<OPTIONS>".if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_black().bold())),
+            "[ARGS]".if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_black().bold()))
         );
         println!();
 
         // Commands
-        println!("{}", "Commands:".bright_magenta().bold());
+        println!(
+            "{}",
+            "Commands:".if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_magenta().bold()))
+        );
         let commands = COMMANDS
             .iter()
             .map(|(cmd, desc, aliases)| (cmd.to_string(), desc.to_string(), aliases.to_vec()))
@@ -89,13 +107,15 @@ impl HelpHandler {
             let plain_cmd = format!("{}{}", cmd, alias_str);
             let colored_cmd = format!(
                 "{}{}",
-                cmd.bright_cyan().bold(),
-                alias_str.bright_black().bold()
+                cmd.if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold())),
+                alias_str.if_supports_color(Stdout, |text| text
+                    .style(Style::new().bright_black().bold()))
             );
             println!(
                 "  {:width$}  # {}",
                 colored_cmd,
-                desc.bright_black().bold(),
+                desc.if_supports_color(Stdout, |text| text
+                    .style(Style::new().bright_black().bold())),
                 width = max_cmd_width + (colored_cmd.len() - plain_cmd.len())
             );
         }
@@ -103,13 +123,22 @@ impl HelpHandler {
         println!();
 
         // Options
-        println!("{}", "Options:".bright_magenta().bold());
+        println!(
+            "{}",
+            "Options:".if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_magenta().bold()))
+        );
         let option_cmd = "-V, --version";
-        let colored_option_str = format!("{}", option_cmd.bright_cyan().bold());
+        let colored_option_str = format!(
+            "{}",
+            option_cmd
+                .if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold()))
+        );
         println!(
             "  {}           # {}",
             colored_option_str,
-            "Print version".bright_black().bold(),
+            "Print version".if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_black().bold())),
         );
         println!();
 
@@ -117,7 +146,11 @@ impl HelpHandler {
     }
 
     fn show_additional_info() {
-        println!("{}", "Examples:".bright_magenta().bold());
+        println!(
+            "{}",
+            "Examples:".if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_magenta().bold()))
+        );
 
         let examples = EXAMPLES
             .iter()
@@ -129,19 +162,35 @@ impl HelpHandler {
         for (cmd, desc) in examples {
             let parts: Vec<&str> = cmd.split_whitespace().collect();
             let formatted_cmd = if parts.len() > 1 {
-                let mut formatted_parts = vec![parts[0].bright_cyan().bold().to_string()];
+                let mut formatted_parts = vec![
+                    parts[0]
+                        .if_supports_color(Stdout, |text| {
+                            text.style(Style::new().bright_cyan().bold())
+                        })
+                        .to_string(),
+                ];
 
                 for part in &parts[1..] {
                     if part.starts_with('-') {
-                        formatted_parts.push(part.bright_black().bold().to_string());
+                        formatted_parts.push(
+                            part.if_supports_color(Stdout, |text| {
+                                text.style(Style::new().bright_black().bold())
+                            })
+                            .to_string(),
+                        );
                     } else {
-                        formatted_parts.push(part.bright_white().to_string());
+                        formatted_parts.push(
+                            part.if_supports_color(Stdout, |text| text.bright_white())
+                                .to_string(),
+                        );
                     }
                 }
 
                 formatted_parts.join(" ")
             } else {
-                parts[0].bright_cyan().bold().to_string()
+                parts[0]
+                    .if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold()))
+                    .to_string()
             };
 
             let visual_width_diff = formatted_cmd.len() - cmd.len();
@@ -149,7 +198,8 @@ impl HelpHandler {
             println!(
                 "  {:width$}  # {}",
                 formatted_cmd,
-                desc.bright_black().bold(),
+                desc.if_supports_color(Stdout, |text| text
+                    .style(Style::new().bright_black().bold())),
                 width = max_example_width + visual_width_diff
             );
         }
@@ -158,15 +208,17 @@ impl HelpHandler {
         println!();
         println!(
             "{}",
-            "For more information about a specific command, use:".bright_magenta()
+            "For more information about a specific command, use:"
+                .if_supports_color(Stdout, |text| text.bright_magenta())
         );
 
         let help_cmd = "pacm help <command>";
         let formatted_help_cmd = format!(
             "{} {} {}",
-            "pacm".bright_cyan().bold(),
-            "help".bright_white(),
-            "<command>".bright_black().bold()
+            "pacm".if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold())),
+            "help".if_supports_color(Stdout, |text| text.bright_white()),
+            "<command>".if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_black().bold()))
         );
         let help_desc = "Show help for specific command";
         let visual_width_diff = formatted_help_cmd.len() - help_cmd.len();
@@ -174,14 +226,16 @@ impl HelpHandler {
         println!(
             "  {:width$}  # {}",
             formatted_help_cmd,
-            help_desc.bright_black().bold(),
+            help_desc.if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_black().bold())),
             width = max_example_width + visual_width_diff
         );
         println!();
         println!();
         println!(
             "Visit {} for more information",
-            REPOSITORY_URL.bright_cyan().underline()
+            REPOSITORY_URL.if_supports_color(Stdout, |text| text
+                .style(Style::new().bright_cyan().underline()))
         );
     }
 }
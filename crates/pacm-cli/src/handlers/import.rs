@@ -0,0 +1,19 @@
+use anyhow::Result;
+
+use pacm_core::{self, ForeignLockfile};
+
+pub struct ImportHandler;
+
+impl ImportHandler {
+    pub fn handle_import(file: Option<&str>, force: bool) -> Result<()> {
+        pacm_core::import_lockfile(".", file, force)
+    }
+
+    pub fn handle_export(format: &str, output: Option<&str>, force: bool) -> Result<()> {
+        let format = match format {
+            "yarn" => ForeignLockfile::Yarn,
+            _ => ForeignLockfile::Npm,
+        };
+        pacm_core::export_lockfile(".", format, output, force)
+    }
+}
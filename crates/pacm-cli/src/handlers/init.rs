@@ -1,18 +1,35 @@
 use anyhow::Result;
-use owo_colors::OwoColorize;
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
+use std::path::Path;
 
 use pacm_core;
 
 pub struct InitHandler;
 
 impl InitHandler {
-    pub fn init_project(yes: bool) -> Result<()> {
+    pub fn init_project(yes: bool, force: bool, merge: bool) -> Result<()> {
         Self::print_init_header();
-        pacm_core::init_interactive(".", yes)
+        pacm_core::InitManager::new()
+            .with_force(force)
+            .with_merge(merge)
+            .init_interactive(".", yes)
+            .map_err(|e| {
+                if !force && !merge && Path::new("package.json").exists() {
+                    anyhow::anyhow!(
+                        "{e}. Run `pacm init --force` to overwrite it, or `pacm init --merge` to add the missing fields"
+                    )
+                } else {
+                    anyhow::anyhow!(e)
+                }
+            })
     }
 
     fn print_init_header() {
-        println!("{} {}", "pacm".bright_cyan().bold(), "init".bright_white());
+        println!(
+            "{} {}",
+            "pacm".if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold())),
+            "init".if_supports_color(Stdout, |text| text.bright_white())
+        );
         println!();
     }
 }
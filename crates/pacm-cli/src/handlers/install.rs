@@ -1,106 +1,165 @@
 use anyhow::Result;
-use owo_colors::OwoColorize;
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
 
-use pacm_core;
+use pacm_core::{self, InstallOptions};
 use pacm_logger;
-use pacm_project::DependencyType;
-use pacm_utils::parse_pkg_spec;
+use pacm_project::{DependencySelection, DependencyType};
+use pacm_utils::{PackageSpec, absolute_spec, parse_package_spec};
 
 pub struct InstallHandler;
 
 impl InstallHandler {
-    pub fn install_all(debug: bool) -> Result<()> {
+    pub fn install_all(
+        include: &[String],
+        omit: &[String],
+        production: bool,
+        depth: Option<usize>,
+        debug: bool,
+    ) -> Result<()> {
+        let selection = DependencySelection::from_flags(include, omit, production)
+            .map_err(anyhow::Error::msg)?;
+
+        let rt = tokio::runtime::Runtime::new()?;
+        rt.block_on(async {
+            let cancel = pacm_core::CancellationToken::new();
+            let on_interrupt = cancel.clone();
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    on_interrupt.cancel();
+                }
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    std::process::exit(130);
+                }
+            });
+
+            let options = InstallOptions::new()
+                .selection(selection)
+                .max_depth(depth)
+                .debug(debug);
+            pacm_core::install_all_async(".", &options, Some(&cancel)).await
+        })
+    }
+
+    pub fn check() -> Result<()> {
         println!(
             "{} {}",
-            "pacm".bright_cyan().bold(),
-            "install".bright_white()
+            "pacm".if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold())),
+            "install --check".if_supports_color(Stdout, |text| text.bright_white())
         );
         println!();
-        pacm_core::install_all(".", debug)
+
+        pacm_core::verify_install(".")
     }
 
     pub fn install_pkgs(
         packages: &[String],
-        dev: bool,
-        optional: bool,
-        peer: bool,
+        section: Option<DependencyType>,
         global: bool,
         save_exact: bool,
         no_save: bool,
+        bundle: bool,
         force: bool,
         debug: bool,
     ) -> Result<()> {
-        let dep_type = Self::get_dep_type(dev, optional, peer);
+        let dep_type = section.unwrap_or(DependencyType::Dependencies);
+
+        let project_dir = if global {
+            pacm_core::global_dir()?
+        } else {
+            ".".to_string()
+        };
+        // Paths are given relative to where pacm runs, not to the global prefix the
+        // packages are installed into.
+        let cwd = std::env::current_dir()?;
 
-        if global {
-            pacm_logger::error("Global installation is not yet supported");
-            return Ok(());
+        let mut registry_packages = Vec::new();
+        let mut tarballs = Vec::new();
+        for spec in packages {
+            match parse_package_spec(spec) {
+                PackageSpec::Registry {
+                    name,
+                    version_range,
+                } => registry_packages.push((name, version_range)),
+                PackageSpec::Tarball(source) if global => {
+                    tarballs.push(absolute_spec(&source, &cwd));
+                }
+                PackageSpec::Tarball(source) => tarballs.push(source),
+            }
         }
 
-        if packages.len() == 1 {
-            let (name, version_range) = parse_pkg_spec(&packages[0]);
-            Self::print_header(&packages[0]);
-
-            pacm_core::install_enhanced(
-                ".",
-                &name,
-                &version_range,
-                dep_type,
-                save_exact,
-                no_save,
-                force,
-                debug,
-            )?;
-        } else {
-            let parsed_packages: Vec<(String, String)> =
-                packages.iter().map(|pkg| parse_pkg_spec(pkg)).collect();
-
-            Self::print_batch_header(packages);
-
-            pacm_core::install_multiple(
-                ".",
-                &parsed_packages,
-                dep_type,
-                save_exact,
-                no_save,
-                force,
-                debug,
-            )?;
+        let options = InstallOptions::new()
+            .dep_type(dep_type)
+            .move_existing(section.is_some())
+            .save_exact(save_exact)
+            .no_save(no_save)
+            .force(force)
+            .debug(debug);
+        if registry_packages.len() == 1 {
+            let (name, version_range) = &registry_packages[0];
+            pacm_core::install_single_with(&project_dir, name, version_range, &options)?;
+        } else if !registry_packages.is_empty() {
+            pacm_core::install_multiple_with(&project_dir, &registry_packages, &options)?;
+        }
+
+        for source in &tarballs {
+            pacm_core::install_tarball(&project_dir, source, dep_type, no_save, debug)?;
+        }
+
+        if bundle {
+            if !tarballs.is_empty() {
+                pacm_logger::warn("Only registry packages can be bundled, skipping tarballs");
+            }
+            let names: Vec<String> = registry_packages
+                .iter()
+                .map(|(name, _)| name.clone())
+                .collect();
+            pacm_core::bundle_deps(&project_dir, &names)?;
         }
 
         Ok(())
     }
 
-    fn get_dep_type(dev: bool, optional: bool, peer: bool) -> DependencyType {
+    /// The section asked for with `--dev`, `--optional`, `--peer` or `--save-prod`, if
+    /// any. Only an explicit section moves a package that is already saved elsewhere.
+    pub fn section(
+        dev: bool,
+        optional: bool,
+        peer: bool,
+        save_prod: bool,
+    ) -> Option<DependencyType> {
         if dev {
-            DependencyType::DevDependencies
+            Some(DependencyType::DevDependencies)
         } else if optional {
-            DependencyType::OptionalDependencies
+            Some(DependencyType::OptionalDependencies)
         } else if peer {
-            DependencyType::PeerDependencies
+            Some(DependencyType::PeerDependencies)
+        } else if save_prod {
+            Some(DependencyType::Dependencies)
         } else {
-            DependencyType::Dependencies
+            None
         }
     }
 
-    fn print_header(package: &str) {
-        println!(
-            "{} {} {}",
-            "pacm".bright_cyan().bold(),
-            "install".bright_white(),
-            package.bright_white()
-        );
-        println!();
-    }
-
-    fn print_batch_header(packages: &[String]) {
-        let package_list = packages.join(" ");
-        println!(
-            "{} {} {}",
-            "pacm".bright_cyan().bold(),
-            "install".bright_white(),
-            package_list.bright_white()
-        );
+    /// Prints `pacm install` followed by the packages being installed, if any.
+    pub fn print_header(packages: &[String]) {
+        if packages.is_empty() {
+            println!(
+                "{} {}",
+                "pacm".if_supports_color(Stdout, |text| text
+                    .style(Style::new().bright_cyan().bold())),
+                "install".if_supports_color(Stdout, |text| text.bright_white())
+            );
+        } else {
+            println!(
+                "{} {} {}",
+                "pacm".if_supports_color(Stdout, |text| text
+                    .style(Style::new().bright_cyan().bold())),
+                "install".if_supports_color(Stdout, |text| text.bright_white()),
+                packages
+                    .join(" ")
+                    .if_supports_color(Stdout, |text| text.bright_white())
+            );
+        }
         println!();
     }
 }
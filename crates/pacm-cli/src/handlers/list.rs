@@ -1,11 +1,37 @@
 use anyhow::Result;
 
 use pacm_core;
+use pacm_project::DependencyType;
 
 pub struct ListHandler;
 
 impl ListHandler {
-    pub fn handle_list_dependencies(tree: bool, depth: Option<u32>) -> Result<()> {
-        pacm_core::list_deps(".", tree, depth)
+    pub fn handle_list_dependencies(
+        tree: bool,
+        depth: Option<u32>,
+        section: Option<DependencyType>,
+        json: bool,
+    ) -> Result<()> {
+        pacm_core::list_deps(".", tree, depth, section, json)
+    }
+
+    pub fn handle_list_conflicts() -> Result<()> {
+        pacm_core::list_range_conflicts(".")
+    }
+
+    pub fn handle_list_global() -> Result<()> {
+        pacm_core::list_global()
+    }
+
+    pub fn handle_outdated(json: bool, debug: bool) -> Result<()> {
+        pacm_core::outdated(".", json, debug)
+    }
+
+    pub fn handle_why(package: &str, json: bool) -> Result<()> {
+        pacm_core::why(".", package, json)
+    }
+
+    pub fn handle_why_orphans(json: bool) -> Result<()> {
+        pacm_core::why_orphans(".", json)
     }
 }
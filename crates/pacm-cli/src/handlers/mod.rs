@@ -1,19 +1,27 @@
+pub mod audit;
 pub mod clean;
 pub mod help;
+pub mod import;
 pub mod init;
 pub mod install;
 pub mod list;
+pub mod publish;
 pub mod remove;
 pub mod run;
 pub mod start;
+pub mod store;
 pub mod update;
 
+pub use audit::AuditHandler;
 pub use clean::CleanHandler;
 pub use help::HelpHandler;
+pub use import::ImportHandler;
 pub use init::InitHandler;
 pub use install::InstallHandler;
 pub use list::ListHandler;
+pub use publish::PublishHandler;
 pub use remove::RemoveHandler;
 pub use run::RunHandler;
 pub use start::StartHandler;
+pub use store::StoreHandler;
 pub use update::UpdateHandler;
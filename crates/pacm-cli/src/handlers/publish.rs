@@ -0,0 +1,26 @@
+use anyhow::Result;
+
+use pacm_core::{self, PublishOptions};
+
+pub struct PublishHandler;
+
+impl PublishHandler {
+    pub fn handle_pack(dry_run: bool) -> Result<()> {
+        pacm_core::pack(".", dry_run)
+    }
+
+    pub fn handle_publish(
+        tag: &str,
+        access: Option<&str>,
+        dry_run: bool,
+        force: bool,
+    ) -> Result<()> {
+        let options = PublishOptions {
+            tag: tag.to_string(),
+            access: access.map(str::to_string),
+            dry_run,
+            force,
+        };
+        pacm_core::publish(".", &options)
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use owo_colors::OwoColorize;
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
 
 use pacm_core;
 
@@ -9,6 +9,7 @@ impl RemoveHandler {
     pub fn handle_remove_packages(
         packages: &[String],
         dev: bool,
+        global: bool,
         direct_only: bool,
         dry_run: bool,
         debug: bool,
@@ -17,42 +18,59 @@ impl RemoveHandler {
             return Ok(());
         }
 
+        let project_dir = if global {
+            pacm_core::global_dir()?
+        } else {
+            ".".to_string()
+        };
+
         Self::print_remove_header(packages, direct_only, dry_run);
 
         if dry_run {
-            pacm_core::remove_multiple_deps_dry_run(".", packages, dev, direct_only, debug)?;
+            pacm_core::remove_multiple_deps_dry_run(
+                &project_dir,
+                packages,
+                dev,
+                direct_only,
+                debug,
+            )?;
         } else if direct_only {
-            pacm_core::remove_multiple_deps_direct_only(".", packages, dev, debug)?;
+            pacm_core::remove_multiple_deps_direct_only(&project_dir, packages, dev, debug)?;
         } else {
-            pacm_core::remove_multiple_deps(".", packages, dev, debug)?;
+            pacm_core::remove_multiple_deps(&project_dir, packages, dev, debug)?;
         }
 
         Ok(())
     }
 
     fn print_remove_header(packages: &[String], direct_only: bool, dry_run: bool) {
-        let mode_text = if dry_run {
-            " (dry run)".dimmed()
+        let mode = if dry_run {
+            " (dry run)"
         } else if direct_only {
-            " (direct only)".dimmed()
+            " (direct only)"
         } else {
-            "".dimmed()
+            ""
         };
+        let mode_text = mode.if_supports_color(Stdout, |text| text.dimmed());
 
         if packages.len() == 1 {
             println!(
                 "{} {} {}{}",
-                "pacm".bright_cyan().bold(),
-                "remove".bright_white(),
-                packages[0].bright_white(),
+                "pacm".if_supports_color(Stdout, |text| text
+                    .style(Style::new().bright_cyan().bold())),
+                "remove".if_supports_color(Stdout, |text| text.bright_white()),
+                packages[0].if_supports_color(Stdout, |text| text.bright_white()),
                 mode_text
             );
         } else {
             println!(
                 "{} {} {}{}",
-                "pacm".bright_cyan().bold(),
-                "remove".bright_white(),
-                packages.join(" ").bright_white(),
+                "pacm".if_supports_color(Stdout, |text| text
+                    .style(Style::new().bright_cyan().bold())),
+                "remove".if_supports_color(Stdout, |text| text.bright_white()),
+                packages
+                    .join(" ")
+                    .if_supports_color(Stdout, |text| text.bright_white()),
                 mode_text
             );
         }
@@ -8,4 +8,12 @@ impl RunHandler {
     pub fn handle_run_script(script: &str) -> Result<()> {
         pacm_runtime::run_script(".", script)
     }
+
+    pub fn handle_list_scripts() -> Result<()> {
+        pacm_runtime::list_scripts(".")
+    }
+
+    pub fn handle_run_workspaces(script: &str, filter: Option<&str>, no_bail: bool) -> Result<()> {
+        pacm_runtime::run_workspaces_script(".", script, filter, !no_bail)
+    }
 }
@@ -0,0 +1,94 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use anyhow::Result;
+use owo_colors::{OwoColorize, Stream::Stdout};
+
+use pacm_core;
+use pacm_logger;
+use pacm_utils::{PackageSpec, parse_package_spec};
+
+pub struct StoreHandler;
+
+impl StoreHandler {
+    pub fn handle_path() -> Result<()> {
+        pacm_core::store_path()
+    }
+
+    pub fn handle_stats() -> Result<()> {
+        pacm_core::store_stats()
+    }
+
+    pub fn handle_ls(package: &str) -> Result<()> {
+        pacm_core::store_versions(package)
+    }
+
+    pub fn handle_add(package: &str, deps: bool, debug: bool) -> Result<()> {
+        match parse_package_spec(package) {
+            PackageSpec::Registry {
+                name,
+                version_range,
+            } => pacm_core::store_add(&name, &version_range, deps, debug),
+            PackageSpec::Tarball(source) => Err(anyhow::anyhow!(
+                "only registry packages can be added to the store, not {}",
+                source
+            )),
+        }
+    }
+
+    pub fn handle_gc(days: u64, projects: &[String], dry_run: bool, yes: bool) -> Result<()> {
+        let entries = pacm_core::store_gc_candidates(days, projects)?;
+        if entries.is_empty() {
+            pacm_logger::info(&format!("No stored packages unused for {} days", days));
+            return Ok(());
+        }
+
+        let total: u64 = entries.iter().map(|entry| entry.size).sum();
+        for entry in &entries {
+            println!(
+                "{}@{}  {}",
+                entry.name,
+                entry
+                    .version
+                    .if_supports_color(Stdout, |text| text.bright_cyan()),
+                format_size(entry.size).if_supports_color(Stdout, |text| text.bright_black())
+            );
+        }
+        println!();
+        let summary = format!(
+            "{} packages unused for {} days, {}",
+            entries.len(),
+            days,
+            format_size(total)
+        );
+        if dry_run {
+            pacm_logger::info(&summary);
+            return Ok(());
+        }
+
+        if !yes {
+            // The store is shared by every project on this machine, so never remove
+            // anything without an answer.
+            if !io::stdin().is_terminal() {
+                return Err(anyhow::anyhow!(
+                    "Refusing to remove store entries without --yes"
+                ));
+            }
+            print!("{}. Remove them from the store? [y/N] ", summary);
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().lock().read_line(&mut answer)?;
+            if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                pacm_logger::info("Nothing removed");
+                return Ok(());
+            }
+        }
+
+        let reclaimed = pacm_core::store_gc(days, &entries)?;
+        pacm_logger::finish(&format!("Reclaimed {}", format_size(reclaimed)));
+        Ok(())
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.2} MB", bytes as f64 / 1024.0 / 1024.0)
+}
@@ -1,21 +1,27 @@
 use anyhow::Result;
-use owo_colors::OwoColorize;
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
 
 use pacm_core;
 
 pub struct UpdateHandler;
 
 impl UpdateHandler {
-    pub fn handle_update_packages(packages: &[String], debug: bool) -> Result<()> {
+    pub fn handle_update_packages(packages: &[String], global: bool, debug: bool) -> Result<()> {
+        let project_dir = if global {
+            pacm_core::global_dir()?
+        } else {
+            ".".to_string()
+        };
+
         Self::print_update_header();
-        pacm_core::update_deps(".", packages, debug)
+        pacm_core::update_deps(&project_dir, packages, debug)
     }
 
     fn print_update_header() {
         println!(
             "{} {}",
-            "pacm".bright_cyan().bold(),
-            "update".bright_white()
+            "pacm".if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold())),
+            "update".if_supports_color(Stdout, |text| text.bright_white())
         );
         println!();
     }
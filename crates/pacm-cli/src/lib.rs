@@ -4,9 +4,13 @@ pub mod handlers;
 use anyhow::Result;
 use clap::Parser;
 use std::env;
+use std::path::Path;
+use std::time::Instant;
 
-use commands::{Cli, Commands};
+use commands::{Cli, Commands, StoreCommands};
 use handlers::*;
+use pacm_logger::Verbosity;
+use pacm_project::DependencyType;
 
 pub fn run_cli() -> Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -15,10 +19,7 @@ pub fn run_cli() -> Result<()> {
         let potential_command = &args[1];
 
         match Cli::try_parse() {
-            Ok(cli) => {
-                pacm_logger::init_logger(false);
-                handle_known_command(&cli.command)
-            }
+            Ok(cli) => run_known_command(&cli),
             Err(_) => {
                 if !potential_command.starts_with('-') && !potential_command.starts_with("--") {
                     if potential_command == "help" {
@@ -34,9 +35,7 @@ pub fn run_cli() -> Result<()> {
                         RunHandler::handle_run_script(potential_command)
                     }
                 } else {
-                    let cli = Cli::parse();
-                    pacm_logger::init_logger(false);
-                    handle_known_command(&cli.command)
+                    run_known_command(&Cli::parse())
                 }
             }
         }
@@ -46,55 +45,303 @@ pub fn run_cli() -> Result<()> {
     }
 }
 
-fn handle_known_command(command: &Commands) -> Result<()> {
+fn run_known_command(cli: &Cli) -> Result<()> {
+    let color = if cli.no_color {
+        pacm_logger::ColorChoice::Never
+    } else {
+        cli.color.parse().unwrap_or_default()
+    };
+    // `install --json` keeps stdout for the JSON document.
+    let verbosity = if cli.silent {
+        Verbosity::Silent
+    } else if cli.quiet || matches!(cli.command, Commands::Install { json: true, .. }) {
+        Verbosity::Quiet
+    } else {
+        Verbosity::Normal
+    };
+    pacm_logger::init_reporter(cli.reporter.parse().unwrap_or_default(), verbosity, color);
+    apply_config_overrides(cli);
+    pacm_core::check_pinned_version(std::path::Path::new(".")).map_err(|e| anyhow::anyhow!(e))?;
+
+    let notifier = if cli.no_update_notifier || verbosity != Verbosity::Normal {
+        None
+    } else {
+        pacm_core::UpdateNotifier::start()
+    };
+
+    let result = handle_known_command(&cli.command, verbosity != Verbosity::Normal);
+
+    if let Some(notifier) = notifier {
+        notifier.print_notice();
+    }
+    result
+}
+
+/// Feeds command-line flags that mirror config keys into the configuration before
+/// anything reads it.
+fn apply_config_overrides(cli: &Cli) {
+    let command = &cli.command;
+    let mut overrides = Vec::new();
+    if cli.strict_version {
+        overrides.push(("strict-version", "true"));
+    }
+    if cli.full_metadata {
+        overrides.push(("full-metadata", "true"));
+    }
+    if let Some(cache_max_age) = &cli.cache_max_age {
+        overrides.push(("cache-max-age", cache_max_age.as_str()));
+    }
+    if let Some(strict_ssl) = &cli.strict_ssl {
+        overrides.push(("strict-ssl", strict_ssl.as_str()));
+    }
+    if let Some(cafile) = &cli.cafile {
+        overrides.push(("cafile", cafile.as_str()));
+    }
+    if let Commands::Install {
+        node_linker,
+        prune_store,
+        install_peers,
+        no_dedupe,
+        verify_exports,
+        audit_signatures,
+        audit,
+        no_lockfile,
+        frozen_lockfile,
+        install_links,
+        npm_compat,
+        offline,
+        fallback_online,
+        prefer_offline,
+        ..
+    } = command
+    {
+        let network_mode = match (*offline, *fallback_online, *prefer_offline) {
+            (true, true, _) => Some("offline-then-online"),
+            (true, false, _) => Some("offline"),
+            (false, _, true) => Some("prefer-offline"),
+            _ => None,
+        };
+        if let Some(network_mode) = network_mode {
+            overrides.push(("network-mode", network_mode));
+        }
+        if let Some(node_linker) = node_linker {
+            overrides.push(("node-linker", node_linker.as_str()));
+        }
+        if *prune_store {
+            overrides.push(("prune-store", "true"));
+        }
+        if *install_peers {
+            overrides.push(("install-peers", "true"));
+        }
+        if *no_dedupe {
+            overrides.push(("dedupe", "false"));
+        }
+        if *verify_exports {
+            overrides.push(("verify-exports", "true"));
+        }
+        if *audit_signatures {
+            overrides.push(("audit-signatures", "true"));
+        }
+        if *audit {
+            overrides.push(("audit", "true"));
+        }
+        if *no_lockfile {
+            overrides.push(("lockfile", "false"));
+        }
+        if *frozen_lockfile {
+            overrides.push(("frozen-lockfile", "true"));
+        }
+        if *install_links {
+            overrides.push(("install-links", "true"));
+        }
+        if *npm_compat {
+            overrides.push(("npm-compat", "true"));
+        }
+    }
+    if let Commands::Update {
+        prefer_dedupe: true,
+        ..
+    } = command
+    {
+        overrides.push(("prefer-dedupe", "true"));
+    }
+
+    pacm_config::PacmConfig::init_with_overrides(&overrides);
+}
+
+/// Runs `command`. `quiet` leaves out the output that is not an error or asked for.
+fn handle_known_command(command: &Commands, quiet: bool) -> Result<()> {
     match command {
         Commands::Install {
             packages,
             dev,
             optional,
             peer,
+            save_prod,
             global,
             save_exact,
             no_save,
+            bundle,
             force,
+            include,
+            omit,
+            production,
+            depth,
+            check,
+            timing,
+            json,
             debug,
+            ..
         } => {
-            if packages.is_empty() {
-                InstallHandler::install_all(*debug)
+            let started = Instant::now();
+            if *json && !pacm_config::PacmConfig::get().lockfile() {
+                let message = format!(
+                    "--json reports what the install changed in {}, which lockfile=false turns off",
+                    pacm_config::PacmConfig::get().lockfile_name()
+                );
+                return Err(anyhow::anyhow!(message));
+            }
+            let before = (!*check && !*global && pacm_config::PacmConfig::get().lockfile())
+                .then(|| pacm_core::LockfileChanges::locked_versions(Path::new(".")));
+            if !*check && !quiet {
+                InstallHandler::print_header(packages);
+            }
+            let result = if *check {
+                InstallHandler::check()
+            } else if packages.is_empty() {
+                InstallHandler::install_all(include, omit, *production, *depth, *debug)
             } else {
                 InstallHandler::install_pkgs(
                     packages,
-                    *dev,
-                    *optional,
-                    *peer,
+                    InstallHandler::section(*dev, *optional, *peer, *save_prod),
                     *global,
                     *save_exact,
                     *no_save,
+                    *bundle,
                     *force,
                     *debug,
                 )
+            };
+
+            let result = match before {
+                Some(before) if result.is_ok() && (*json || !quiet) => {
+                    pacm_core::print_lockfile_changes(".", &before, *json).map(|_| ())
+                }
+                _ => result,
+            };
+            if *timing && result.is_ok() {
+                pacm_core::timing::report(started.elapsed());
+            }
+            if result.is_ok() && !*check && !*global && pacm_config::PacmConfig::get().audit() {
+                pacm_core::print_audit_summary(".", *debug);
             }
+            result
         }
-        Commands::Init { yes } => InitHandler::init_project(*yes),
-        Commands::Run { script } => RunHandler::handle_run_script(script),
+        Commands::Init { yes, force, merge } => InitHandler::init_project(*yes, *force, *merge),
+        Commands::Run {
+            script,
+            workspaces,
+            filter,
+            no_bail,
+        } => match script {
+            None => RunHandler::handle_list_scripts(),
+            Some(script) if *workspaces || filter.is_some() => {
+                RunHandler::handle_run_workspaces(script, filter.as_deref(), *no_bail)
+            }
+            Some(script) => RunHandler::handle_run_script(script),
+        },
         Commands::Start => StartHandler::handle_start(),
         Commands::Remove {
             packages,
             dev,
+            global,
             direct_only,
             dry_run,
             debug,
-        } => RemoveHandler::handle_remove_packages(packages, *dev, *direct_only, *dry_run, *debug),
-        Commands::Update { packages, debug } => {
-            UpdateHandler::handle_update_packages(packages, *debug)
+        } => RemoveHandler::handle_remove_packages(
+            packages,
+            *dev,
+            *global,
+            *direct_only,
+            *dry_run,
+            *debug,
+        ),
+        Commands::Update {
+            packages,
+            global,
+            debug,
+            ..
+        } => UpdateHandler::handle_update_packages(packages, *global, *debug),
+        Commands::List {
+            tree,
+            depth,
+            conflicts,
+            prod,
+            dev,
+            global,
+            json,
+        } => {
+            if *global {
+                ListHandler::handle_list_global()
+            } else if *conflicts {
+                ListHandler::handle_list_conflicts()
+            } else {
+                let section = if *prod {
+                    Some(DependencyType::Dependencies)
+                } else if *dev {
+                    Some(DependencyType::DevDependencies)
+                } else {
+                    None
+                };
+                ListHandler::handle_list_dependencies(*tree, *depth, section, *json)
+            }
         }
-        Commands::List { tree, depth } => ListHandler::handle_list_dependencies(*tree, *depth),
+        Commands::Outdated { json, debug } => ListHandler::handle_outdated(*json, *debug),
+        Commands::Why { package, json, .. } => match package {
+            Some(package) => ListHandler::handle_why(package, *json),
+            None => ListHandler::handle_why_orphans(*json),
+        },
         Commands::Clean {
             cache,
             modules,
             yes,
             debug,
         } => CleanHandler::handle_clean(*cache, *modules, *yes, *debug),
+        Commands::Store { command } => match command {
+            StoreCommands::Path => StoreHandler::handle_path(),
+            StoreCommands::Stats => StoreHandler::handle_stats(),
+            StoreCommands::Ls { package } => StoreHandler::handle_ls(package),
+            StoreCommands::Add {
+                package,
+                deps,
+                debug,
+            } => StoreHandler::handle_add(package, *deps, *debug),
+            StoreCommands::Gc {
+                days,
+                projects,
+                dry_run,
+                yes,
+            } => StoreHandler::handle_gc(*days, projects, *dry_run, *yes),
+        },
+        Commands::Audit {
+            action,
+            json,
+            audit_level,
+            debug,
+        } => AuditHandler::handle_audit(action.as_deref(), *json, audit_level, *debug),
+        Commands::Pack { dry_run } => PublishHandler::handle_pack(*dry_run),
+        Commands::Publish {
+            tag,
+            access,
+            dry_run,
+            force,
+        } => PublishHandler::handle_publish(tag, access.as_deref(), *dry_run, *force),
+        Commands::Import { file, force } => ImportHandler::handle_import(file.as_deref(), *force),
+        Commands::Export {
+            format,
+            output,
+            force,
+        } => ImportHandler::handle_export(format, output.as_deref(), *force),
         Commands::Help { command } => HelpHandler::handle_help(command.as_deref()),
     }
 }
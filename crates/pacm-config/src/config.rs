@@ -0,0 +1,801 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use pacm_constants::{DEFAULT_LOCKFILE_NAME, DEFAULT_REGISTRY, RC_FILES};
+
+use crate::rc_file::parse_rc;
+
+#[derive(Debug, Clone)]
+pub struct PacmConfig {
+    pub registry: String,
+    pub scoped_registries: HashMap<String, String>,
+    pub auth_tokens: HashMap<String, String>,
+    pub basic_auth: HashMap<String, String>,
+    pub proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub values: HashMap<String, String>,
+}
+
+/// How `node_modules` is laid out, from `node-linker`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NodeLinker {
+    /// Every package of the tree is linked straight into `node_modules`.
+    #[default]
+    Hoisted,
+    /// Only direct dependencies are linked into `node_modules`; each package sees just
+    /// its own dependencies through `node_modules/.pacm`.
+    Isolated,
+}
+
+impl NodeLinker {
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "hoisted" => Some(Self::Hoisted),
+            "isolated" => Some(Self::Isolated),
+            _ => None,
+        }
+    }
+}
+
+/// Where package metadata may come from, from `network-mode`. Packages already in the
+/// store are never downloaded again in any mode; the mode decides whether the registry
+/// or the store has the final say on which versions exist.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NetworkMode {
+    /// Always ask the registry, so ranges resolve to the newest published match.
+    #[default]
+    Online,
+    /// Resolve from metadata saved by an earlier fetch, however old, or from the store,
+    /// and ask the registry only for packages neither has a matching version of.
+    PreferOffline,
+    /// Resolve from the store only. Anything the store cannot satisfy is an error, and no
+    /// request is ever made.
+    Offline,
+    /// Resolve from the store, and go to the registry only for packages the store has
+    /// no matching version of.
+    OfflineThenOnline,
+}
+
+impl NetworkMode {
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "online" => Some(Self::Online),
+            "prefer-offline" => Some(Self::PreferOffline),
+            "offline" => Some(Self::Offline),
+            "offline-then-online" => Some(Self::OfflineThenOnline),
+            _ => None,
+        }
+    }
+}
+
+static CONFIG: OnceLock<PacmConfig> = OnceLock::new();
+/// The project directory whose rc files [`CONFIG`] was loaded with.
+static PROJECT_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Environment variables that override a config key, applied after the rc files.
+const ENV_OVERRIDES: [(&str, &str); 2] = [
+    ("PACM_STORE_DIR", "store-dir"),
+    ("PACM_CACHE_DIR", "cache-dir"),
+];
+
+impl Default for PacmConfig {
+    fn default() -> Self {
+        Self {
+            registry: DEFAULT_REGISTRY.to_string(),
+            scoped_registries: HashMap::new(),
+            auth_tokens: HashMap::new(),
+            basic_auth: HashMap::new(),
+            proxy: None,
+            https_proxy: None,
+            values: HashMap::new(),
+        }
+    }
+}
+
+impl PacmConfig {
+    /// Returns the process-wide configuration, loading it once. Unless
+    /// [`PacmConfig::init_for_project`] or [`PacmConfig::init_with_overrides`] ran first,
+    /// the project rc files are read from the current working directory.
+    pub fn get() -> &'static PacmConfig {
+        CONFIG.get_or_init(|| Self::load_global(Path::new("."), &[]))
+    }
+
+    /// Loads the configuration with `overrides`, usually command-line flags, applied on top
+    /// of the rc files in the current working directory. Does nothing if the configuration
+    /// has already been read.
+    pub fn init_with_overrides(overrides: &[(&str, &str)]) {
+        let _ = CONFIG.set(Self::load_global(Path::new("."), overrides));
+    }
+
+    /// Loads the configuration with the rc files of `project_dir` rather than the working
+    /// directory's, for embedders installing into another directory. The configuration
+    /// is process-wide and loaded once, so this fails when it was already loaded for
+    /// another directory and `project_dir` has an `.npmrc` or `.pacmrc` that would be
+    /// ignored.
+    pub fn init_for_project(project_dir: &Path) -> Result<&'static PacmConfig, String> {
+        let config = CONFIG.get_or_init(|| Self::load_global(project_dir, &[]));
+        let Some(loaded_from) = PROJECT_DIR.get() else {
+            return Ok(config);
+        };
+        let same_dir = |a: &Path, b: &Path| {
+            a == b
+                || fs::canonicalize(a)
+                    .ok()
+                    .is_some_and(|a| fs::canonicalize(b).ok() == Some(a))
+        };
+        let ignored_rc = RC_FILES
+            .iter()
+            .map(|file_name| project_dir.join(file_name))
+            .find(|path| path.is_file());
+        let is_home = dirs::home_dir().is_some_and(|home| same_dir(&home, project_dir));
+        match ignored_rc {
+            Some(path) if !is_home && !same_dir(loaded_from, project_dir) => Err(format!(
+                "the configuration was already loaded for {}, so {} would be ignored; \
+                 call PacmConfig::init_for_project before anything else reads the configuration",
+                loaded_from.display(),
+                path.display()
+            )),
+            _ => Ok(config),
+        }
+    }
+
+    fn load_global(project_dir: &Path, overrides: &[(&str, &str)]) -> Self {
+        let _ = PROJECT_DIR.set(project_dir.to_path_buf());
+        let mut config = Self::load(project_dir);
+        for (key, value) in overrides {
+            config.set(key, value.to_string());
+        }
+        config
+    }
+
+    /// Loads `~/.npmrc`, `~/.pacmrc`, `<project>/.npmrc` and `<project>/.pacmrc` in that
+    /// order, later files overriding earlier ones, then applies [`ENV_OVERRIDES`].
+    #[must_use]
+    pub fn load(project_dir: &Path) -> Self {
+        let mut config = Self::default();
+
+        for dir in Self::search_dirs(project_dir) {
+            for file_name in RC_FILES {
+                let path = dir.join(file_name);
+                if let Ok(content) = fs::read_to_string(&path) {
+                    config.apply(&content);
+                }
+            }
+        }
+
+        config.apply_env(|var| std::env::var(var).ok());
+        config
+    }
+
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) {
+        for (name, key) in ENV_OVERRIDES {
+            if let Some(value) = var(name).filter(|value| !value.is_empty()) {
+                self.set(key, value);
+            }
+        }
+    }
+
+    fn search_dirs(project_dir: &Path) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        if let Some(home) = dirs::home_dir() {
+            dirs.push(home);
+        }
+
+        let project_dir = project_dir.to_path_buf();
+        let same_as_home = dirs
+            .first()
+            .is_some_and(|home| fs::canonicalize(home).ok() == fs::canonicalize(&project_dir).ok());
+        if !same_as_home {
+            dirs.push(project_dir);
+        }
+
+        dirs
+    }
+
+    pub fn apply(&mut self, content: &str) {
+        for (key, value) in parse_rc(content) {
+            self.set(&key, value);
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: String) {
+        if let Some(scope_key) = key.strip_suffix(":registry")
+            && scope_key.starts_with('@')
+        {
+            self.scoped_registries
+                .insert(scope_key.to_string(), normalize_registry(&value));
+            return;
+        }
+
+        if let Some(host_key) = key.strip_suffix(":_authToken")
+            && host_key.starts_with("//")
+        {
+            if !value.is_empty() {
+                self.auth_tokens.insert(normalize_host_key(host_key), value);
+            }
+            return;
+        }
+
+        if let Some(host_key) = key.strip_suffix(":_auth")
+            && host_key.starts_with("//")
+        {
+            if !value.is_empty() {
+                self.basic_auth.insert(normalize_host_key(host_key), value);
+            }
+            return;
+        }
+
+        match key {
+            "registry" => self.registry = normalize_registry(&value),
+            "proxy" => self.proxy = Some(value).filter(|v| !v.is_empty()),
+            "https-proxy" => self.https_proxy = Some(value).filter(|v| !v.is_empty()),
+            _ => {
+                self.values.insert(key.to_string(), value);
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn get_value(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Returns the registry that serves `package_name`, honouring `@scope:registry`.
+    #[must_use]
+    pub fn registry_for(&self, package_name: &str) -> &str {
+        if package_name.starts_with('@')
+            && let Some((scope, _)) = package_name.split_once('/')
+            && let Some(registry) = self.scoped_registries.get(scope)
+        {
+            return registry;
+        }
+        &self.registry
+    }
+
+    #[must_use]
+    pub fn package_url(&self, package_name: &str, encoded_name: &str) -> String {
+        format!("{}{}", self.registry_for(package_name), encoded_name)
+    }
+
+    /// Builds the conventional `<registry>/<name>/-/<basename>-<version>.tgz` tarball URL.
+    #[must_use]
+    pub fn tarball_url(&self, package_name: &str, version: &str) -> String {
+        let basename = package_name.rsplit('/').next().unwrap_or(package_name);
+        format!(
+            "{}{}/-/{}-{}.tgz",
+            self.registry_for(package_name),
+            package_name,
+            basename,
+            version
+        )
+    }
+
+    /// Whether `lockfile-resolved=relative` asks for registry-relative `resolved` URLs.
+    #[must_use]
+    pub fn relative_lock_urls(&self) -> bool {
+        self.get_value("lockfile-resolved") == Some("relative")
+    }
+
+    /// The configured `node-linker`. Unrecognised values fall back to hoisted.
+    #[must_use]
+    pub fn node_linker(&self) -> NodeLinker {
+        self.get_value("node-linker")
+            .and_then(NodeLinker::parse)
+            .unwrap_or_default()
+    }
+
+    /// The configured `network-mode`. npm's `offline=true` and `prefer-offline=true` are
+    /// understood too when no mode is set.
+    #[must_use]
+    pub fn network_mode(&self) -> NetworkMode {
+        if let Some(mode) = self.get_value("network-mode").and_then(NetworkMode::parse) {
+            return mode;
+        }
+        if self.get_value("offline") == Some("true") {
+            NetworkMode::Offline
+        } else if self.get_value("prefer-offline") == Some("true") {
+            NetworkMode::PreferOffline
+        } else {
+            NetworkMode::Online
+        }
+    }
+
+    /// Where extracted packages are kept, from `store-dir` or `PACM_STORE_DIR`. `None`
+    /// means the default under the home directory. The store is content-addressed and
+    /// linked into every project, so removing it breaks existing `node_modules`.
+    #[must_use]
+    pub fn store_dir(&self) -> Option<PathBuf> {
+        self.get_value("store-dir").map(PathBuf::from)
+    }
+
+    /// Where data that can simply be fetched again is kept, such as registry metadata
+    /// and saved resolutions, from `cache-dir` or `PACM_CACHE_DIR`. `None` means the
+    /// store directory, as before the two were separate. Removing it only costs time.
+    #[must_use]
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.get_value("cache-dir").map(PathBuf::from)
+    }
+
+    /// Whether `prune-store=true` asks for docs, tests and source maps to be dropped
+    /// from packages as they are extracted into the store.
+    #[must_use]
+    pub fn prune_store(&self) -> bool {
+        self.get_value("prune-store") == Some("true")
+    }
+
+    /// Whether `install-peers=true` asks for missing peer dependencies of direct
+    /// dependencies to be installed, like npm's auto-install of peers. Off by default.
+    #[must_use]
+    pub fn install_peers(&self) -> bool {
+        self.get_value("install-peers") == Some("true")
+    }
+
+    /// Whether installs collapse packages resolved at several versions into as few
+    /// versions as their ranges allow. On unless `dedupe=false`.
+    #[must_use]
+    pub fn dedupe(&self) -> bool {
+        self.get_value("dedupe") != Some("false")
+    }
+
+    /// Whether installs write `pacm.lock`. On unless `lockfile=false`, which leaves an
+    /// existing lockfile untouched and creates none.
+    #[must_use]
+    pub fn lockfile(&self) -> bool {
+        self.get_value("lockfile") != Some("false")
+    }
+
+    /// The lockfile's file name, from `lockfile-name`. Defaults to `pacm.lock`.
+    #[must_use]
+    pub fn lockfile_name(&self) -> &str {
+        self.get_value("lockfile-name")
+            .filter(|name| !name.is_empty())
+            .unwrap_or(DEFAULT_LOCKFILE_NAME)
+    }
+
+    /// Where the project at `project_dir` keeps its lockfile, see [`Self::lockfile_name`].
+    #[must_use]
+    pub fn lockfile_path(&self, project_dir: &Path) -> PathBuf {
+        project_dir.join(self.lockfile_name())
+    }
+
+    /// Whether `frozen-lockfile=true` makes installs fail when `package.json` asks for
+    /// versions `pacm.lock` does not have, instead of resolving those dependencies again.
+    #[must_use]
+    pub fn frozen_lockfile(&self) -> bool {
+        self.get_value("frozen-lockfile") == Some("true")
+    }
+
+    /// Whether `install-links=true` makes installs copy `file:` and `link:` package
+    /// directories into `node_modules`, with their dependencies, instead of symlinking
+    /// them. Off by default.
+    #[must_use]
+    pub fn install_links(&self) -> bool {
+        self.get_value("install-links") == Some("true")
+    }
+
+    /// Whether `npm-compat=true` makes installs also write npm's hidden lockfile,
+    /// `node_modules/.package-lock.json`, for tools that read it. Off by default.
+    #[must_use]
+    pub fn npm_compat(&self) -> bool {
+        self.get_value("npm-compat") == Some("true")
+    }
+
+    /// Whether `verify-exports=true` asks installs to check that the `main` and `exports`
+    /// entry points of direct dependencies exist. Off by default to keep installs fast.
+    #[must_use]
+    pub fn verify_exports(&self) -> bool {
+        self.get_value("verify-exports") == Some("true")
+    }
+
+    /// Whether `prefer-dedupe=true` asks updates to resolve ranges to versions the
+    /// lockfile already has when they allow one, instead of the highest version.
+    #[must_use]
+    pub fn prefer_dedupe(&self) -> bool {
+        self.get_value("prefer-dedupe") == Some("true")
+    }
+
+    /// Whether `audit-signatures=true` asks installs to verify downloaded packages against
+    /// the registry's signing keys, failing on a signature that does not match.
+    #[must_use]
+    pub fn audit_signatures(&self) -> bool {
+        self.get_value("audit-signatures") == Some("true")
+    }
+
+    /// Whether `strict-version=true` turns a mismatch between the running pacm and the
+    /// version `packageManager` pins into an error instead of a warning.
+    #[must_use]
+    pub fn strict_version(&self) -> bool {
+        self.get_value("strict-version") == Some("true")
+    }
+
+    /// Whether `audit=true` has installs end with a one-line vulnerability summary.
+    #[must_use]
+    pub fn audit(&self) -> bool {
+        self.get_value("audit") == Some("true")
+    }
+
+    /// Whether `full-metadata=true` asks the registry for complete package documents
+    /// rather than the abbreviated metadata installs need, for registries that serve a
+    /// broken abbreviated form.
+    #[must_use]
+    pub fn full_metadata(&self) -> bool {
+        self.get_value("full-metadata") == Some("true")
+    }
+
+    /// How many registry requests in a row may fail before pacm stops contacting the
+    /// registry for a while, from `fetch-failure-threshold` (default 8).
+    #[must_use]
+    pub fn fetch_failure_threshold(&self) -> u32 {
+        self.get_value("fetch-failure-threshold")
+            .and_then(|value| value.parse().ok())
+            .filter(|threshold| *threshold > 0)
+            .unwrap_or(8)
+    }
+
+    /// How many threads scan the store when an install indexes what it already holds,
+    /// from `index-concurrency`. `None` leaves it to the number of CPU cores.
+    #[must_use]
+    pub fn index_concurrency(&self) -> Option<usize> {
+        self.get_value("index-concurrency")
+            .and_then(|value| value.parse().ok())
+            .filter(|threads| *threads > 0)
+    }
+
+    /// How many levels of dependencies an install resolves before giving up on the tree,
+    /// from `max-resolution-depth` (default 64). Real trees stay well below it, and each
+    /// level takes stack while the tree is resolved, so much higher values can overflow it.
+    #[must_use]
+    pub fn max_resolution_depth(&self) -> usize {
+        self.get_value("max-resolution-depth")
+            .and_then(|value| value.parse().ok())
+            .filter(|depth| *depth > 0)
+            .unwrap_or(64)
+    }
+
+    /// How many distinct package versions an install resolves before giving up on the
+    /// tree, from `max-packages` (default 100000).
+    #[must_use]
+    pub fn max_packages(&self) -> usize {
+        self.get_value("max-packages")
+            .and_then(|value| value.parse().ok())
+            .filter(|count| *count > 0)
+            .unwrap_or(100_000)
+    }
+
+    /// How long requests fail fast once the failure threshold is reached, from
+    /// `fetch-cooldown` in seconds (default 30).
+    #[must_use]
+    pub fn fetch_cooldown(&self) -> Duration {
+        let seconds = self
+            .get_value("fetch-cooldown")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(30);
+        Duration::from_secs(seconds)
+    }
+
+    /// How long saved registry metadata is used without asking the registry again, from
+    /// `cache-max-age` (default 5 minutes). Dist-tags move often, so a longer window means
+    /// fewer requests but a later look at new releases; `0` always revalidates. Exact
+    /// version pins are served from the cache regardless, since published versions never
+    /// change.
+    #[must_use]
+    pub fn cache_max_age(&self) -> Duration {
+        self.get_value("cache-max-age")
+            .and_then(parse_duration)
+            .unwrap_or(Duration::from_secs(5 * 60))
+    }
+
+    /// The range operator written in front of saved versions: `^` (the default), `~`, or
+    /// nothing for exact pins. Unrecognised values fall back to `^`.
+    #[must_use]
+    pub fn save_prefix(&self) -> &str {
+        match self.get_value("save-prefix") {
+            Some(prefix @ ("^" | "~" | "")) => prefix,
+            _ => "^",
+        }
+    }
+
+    /// Builds the range saved to `package.json` for `version`. `save_exact` always pins.
+    #[must_use]
+    pub fn save_range(&self, version: &str, save_exact: bool) -> String {
+        if save_exact {
+            version.to_string()
+        } else {
+            format!("{}{}", self.save_prefix(), version)
+        }
+    }
+
+    /// Converts a tarball URL into the form stored in the lockfile. In relative mode the
+    /// registry prefix is stripped; URLs from other hosts are kept as they are.
+    #[must_use]
+    pub fn lock_resolved(&self, package_name: &str, url: &str) -> String {
+        if self.relative_lock_urls()
+            && let Some(relative) = url.strip_prefix(self.registry_for(package_name))
+        {
+            return relative.to_string();
+        }
+        url.to_string()
+    }
+
+    /// Rebuilds an absolute tarball URL from a lockfile `resolved` entry against the
+    /// active registry.
+    #[must_use]
+    pub fn absolute_resolved(&self, package_name: &str, resolved: &str) -> String {
+        if resolved.is_empty()
+            || resolved.contains("://")
+            || resolved.starts_with("file:")
+            || resolved.starts_with("link:")
+        {
+            resolved.to_string()
+        } else {
+            format!("{}{}", self.registry_for(package_name), resolved)
+        }
+    }
+
+    /// Builds the `Authorization` header value for a request to `url`, choosing the
+    /// most specific `//host/path/` entry that prefixes it.
+    #[must_use]
+    pub fn auth_header_for(&self, url: &str) -> Option<String> {
+        let target = strip_scheme(url);
+
+        let best_match = |entries: &HashMap<String, String>| {
+            entries
+                .iter()
+                .filter(|(key, _)| target.starts_with(key.as_str()))
+                .max_by_key(|(key, _)| key.len())
+                .map(|(key, value)| (key.len(), value.clone()))
+        };
+
+        let bearer = best_match(&self.auth_tokens);
+        let basic = best_match(&self.basic_auth);
+
+        match (bearer, basic) {
+            (Some((bearer_len, token)), Some((basic_len, _))) if bearer_len >= basic_len => {
+                Some(format!("Bearer {token}"))
+            }
+            (_, Some((_, auth))) => Some(format!("Basic {auth}")),
+            (Some((_, token)), None) => Some(format!("Bearer {token}")),
+            (None, None) => None,
+        }
+    }
+
+    /// Whether TLS certificates are verified. Only `strict-ssl=false` turns verification
+    /// off, for registries behind self-signed certificates.
+    #[must_use]
+    pub fn strict_ssl(&self) -> bool {
+        self.get_value("strict-ssl") != Some("false")
+    }
+
+    /// A PEM file of extra root certificates to trust, from `cafile`.
+    #[must_use]
+    pub fn cafile(&self) -> Option<PathBuf> {
+        self.get_value("cafile").map(PathBuf::from)
+    }
+
+    #[must_use]
+    pub fn proxy_for(&self, url: &str) -> Option<&str> {
+        if url.starts_with("https://") {
+            self.https_proxy.as_deref().or(self.proxy.as_deref())
+        } else {
+            self.proxy.as_deref()
+        }
+    }
+}
+
+fn normalize_registry(url: &str) -> String {
+    if url.ends_with('/') {
+        url.to_string()
+    } else {
+        format!("{url}/")
+    }
+}
+
+fn normalize_host_key(key: &str) -> String {
+    if key.ends_with('/') {
+        key.to_string()
+    } else {
+        format!("{key}/")
+    }
+}
+
+/// Parses a duration written as a number with an optional `s`, `m`, `h` or `d` unit, such
+/// as `90`, `10m` or `1d`. A bare number counts seconds.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (amount, unit_secs) = match value.char_indices().last()? {
+        (at, 's') => (&value[..at], 1),
+        (at, 'm') => (&value[..at], 60),
+        (at, 'h') => (&value[..at], 60 * 60),
+        (at, 'd') => (&value[..at], 24 * 60 * 60),
+        _ => (value, 1),
+    };
+    let amount: u64 = amount.trim().parse().ok()?;
+    Some(Duration::from_secs(amount.checked_mul(unit_secs)?))
+}
+
+fn strip_scheme(url: &str) -> &str {
+    match url.find("//") {
+        Some(pos) => &url[pos..],
+        None => url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacmrc_overrides_npmrc() {
+        let dir = std::env::temp_dir().join(format!("pacm-config-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(".npmrc"),
+            "registry=https://npm.example.com\n@corp:registry=https://corp.example.com/\n",
+        )
+        .unwrap();
+        fs::write(dir.join(".pacmrc"), "registry=https://pacm.example.com/\n").unwrap();
+
+        let config = PacmConfig::load(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(config.registry, "https://pacm.example.com/");
+        assert_eq!(
+            config.registry_for("@corp/utils"),
+            "https://corp.example.com/"
+        );
+        assert_eq!(config.registry_for("lodash"), "https://pacm.example.com/");
+    }
+
+    #[test]
+    fn test_auth_header_uses_most_specific_host_key() {
+        let mut config = PacmConfig::default();
+        config.apply(
+            "//registry.example.com/:_authToken=root\n//registry.example.com/team/:_authToken=team\n",
+        );
+
+        assert_eq!(
+            config
+                .auth_header_for("https://registry.example.com/team/pkg")
+                .as_deref(),
+            Some("Bearer team")
+        );
+        assert_eq!(
+            config
+                .auth_header_for("https://registry.example.com/other")
+                .as_deref(),
+            Some("Bearer root")
+        );
+        assert_eq!(
+            config.auth_header_for("https://registry.npmjs.org/lodash"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_relative_lock_urls_round_trip() {
+        let mut config = PacmConfig::default();
+        let url = "https://registry.npmjs.org/lodash/-/lodash-4.17.21.tgz";
+        assert_eq!(config.lock_resolved("lodash", url), url);
+
+        config.apply("lockfile-resolved=relative\nregistry=https://mirror.example.com/\n");
+        let mirrored = "https://mirror.example.com/lodash/-/lodash-4.17.21.tgz";
+        let stored = config.lock_resolved("lodash", mirrored);
+        assert_eq!(stored, "lodash/-/lodash-4.17.21.tgz");
+        assert_eq!(config.lock_resolved("lodash", url), url);
+
+        config.set("registry", "https://other.example.com".to_string());
+        assert_eq!(
+            config.absolute_resolved("lodash", &stored),
+            "https://other.example.com/lodash/-/lodash-4.17.21.tgz"
+        );
+        assert_eq!(config.absolute_resolved("lodash", url), url);
+    }
+
+    #[test]
+    fn test_node_linker_defaults_to_hoisted() {
+        let mut config = PacmConfig::default();
+        assert_eq!(config.node_linker(), NodeLinker::Hoisted);
+
+        config.apply("node-linker=isolated\n");
+        assert_eq!(config.node_linker(), NodeLinker::Isolated);
+
+        config.apply("node-linker=pnp\n");
+        assert_eq!(config.node_linker(), NodeLinker::Hoisted);
+    }
+
+    #[test]
+    fn test_cache_max_age_units() {
+        let mut config = PacmConfig::default();
+        assert_eq!(config.cache_max_age(), Duration::from_secs(300));
+
+        for (value, seconds) in [
+            ("0", 0),
+            ("90", 90),
+            ("45s", 45),
+            ("10m", 600),
+            ("2h", 7200),
+        ] {
+            config.apply(&format!("cache-max-age={value}\n"));
+            assert_eq!(
+                config.cache_max_age(),
+                Duration::from_secs(seconds),
+                "{value}"
+            );
+        }
+        config.apply("cache-max-age=1d\n");
+        assert_eq!(config.cache_max_age(), Duration::from_secs(86_400));
+
+        config.apply("cache-max-age=soon\n");
+        assert_eq!(config.cache_max_age(), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_network_mode_from_npm_keys() {
+        let mut config = PacmConfig::default();
+        assert_eq!(config.network_mode(), NetworkMode::Online);
+
+        config.apply("prefer-offline=true\n");
+        assert_eq!(config.network_mode(), NetworkMode::PreferOffline);
+
+        config.apply("offline=true\n");
+        assert_eq!(config.network_mode(), NetworkMode::Offline);
+
+        config.apply("network-mode=offline-then-online\n");
+        assert_eq!(config.network_mode(), NetworkMode::OfflineThenOnline);
+    }
+
+    #[test]
+    fn test_save_range_uses_save_prefix() {
+        let mut config = PacmConfig::default();
+        assert_eq!(config.save_range("1.2.3", false), "^1.2.3");
+
+        config.apply("save-prefix=~\n");
+        assert_eq!(config.save_range("1.2.3", false), "~1.2.3");
+
+        config.apply("save-prefix=\"\"\n");
+        assert_eq!(config.save_range("1.2.3", false), "1.2.3");
+
+        config.apply("save-prefix=^\n");
+        assert_eq!(config.save_range("1.2.3", false), "^1.2.3");
+
+        config.apply("save-prefix=>=\n");
+        assert_eq!(config.save_range("1.2.3", false), "^1.2.3");
+    }
+
+    #[test]
+    fn test_env_overrides_store_and_cache_dirs() {
+        let mut config = PacmConfig::default();
+        assert_eq!(config.store_dir(), None);
+        assert_eq!(config.cache_dir(), None);
+
+        config.apply("store-dir=/mnt/ssd/pacm-store\ncache-dir=/mnt/hdd/pacm-cache\n");
+        config.apply_env(|var| (var == "PACM_CACHE_DIR").then(|| "/tmp/pacm-cache".to_string()));
+
+        assert_eq!(
+            config.store_dir(),
+            Some(PathBuf::from("/mnt/ssd/pacm-store"))
+        );
+        assert_eq!(config.cache_dir(), Some(PathBuf::from("/tmp/pacm-cache")));
+    }
+
+    #[test]
+    fn test_save_exact_overrides_save_prefix() {
+        let mut config = PacmConfig::default();
+        config.apply("save-prefix=~\n");
+        assert_eq!(config.save_range("1.2.3", true), "1.2.3");
+    }
+
+    #[test]
+    fn test_tarball_url_for_scoped_package() {
+        let config = PacmConfig::default();
+        assert_eq!(
+            config.tarball_url("@types/node", "20.0.0"),
+            "https://registry.npmjs.org/@types/node/-/node-20.0.0.tgz"
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod config;
+pub mod rc_file;
+
+pub use config::{NetworkMode, NodeLinker, PacmConfig};
+pub use rc_file::{expand_env, parse_rc};
@@ -0,0 +1,101 @@
+use std::env;
+
+/// Parses the INI-style format shared by `.npmrc` and `.pacmrc`.
+///
+/// Section headers are ignored, `;` and `#` start comments, surrounding quotes are
+/// stripped and `${VAR}` references are expanded from the environment.
+#[must_use]
+pub fn parse_rc(content: &str) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        let key = expand_env(key.trim());
+        if key.is_empty() {
+            continue;
+        }
+
+        entries.push((key, expand_env(unquote(value.trim()))));
+    }
+
+    entries
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Expands `${VAR}` references. Unset variables expand to an empty string.
+#[must_use]
+pub fn expand_env(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start + 2..].find('}') else {
+            break;
+        };
+
+        result.push_str(&rest[..start]);
+        let var_name = &rest[start + 2..start + 2 + end];
+        result.push_str(&env::var(var_name).unwrap_or_default());
+        rest = &rest[start + 2 + end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_basic_entries() {
+        let entries = parse_rc(
+            "; comment\n# another\n[section]\nregistry = https://r.example.com/\nstrict-ssl=false\n",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                ("registry".to_string(), "https://r.example.com/".to_string()),
+                ("strict-ssl".to_string(), "false".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_auth_token_key_and_quotes() {
+        let entries = parse_rc("//npm.pkg.github.com/:_authToken=\"abc=def\"\n");
+        assert_eq!(entries[0].0, "//npm.pkg.github.com/:_authToken");
+        assert_eq!(entries[0].1, "abc=def");
+    }
+
+    #[test]
+    fn test_expand_env() {
+        unsafe { env::set_var("PACM_RC_TEST_TOKEN", "secret") };
+        assert_eq!(expand_env("${PACM_RC_TEST_TOKEN}"), "secret");
+        assert_eq!(expand_env("a-${PACM_RC_TEST_TOKEN}-b"), "a-secret-b");
+        assert_eq!(expand_env("${PACM_RC_TEST_UNSET_VAR}"), "");
+        assert_eq!(expand_env("${unterminated"), "${unterminated");
+    }
+}
@@ -1,6 +1,7 @@
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const DESCRIPTION: &str = "A super fast package manager for JavaScript/TypeScript";
 pub const REPOSITORY_URL: &str = "https://github.com/pacmjs/pacm";
+pub const RELEASES_API_URL: &str = "https://api.github.com/repos/pacmjs/pacm/releases/latest";
 pub const BIN_NAME: &str = "pacm";
 pub const COMMANDS: &[(&str, &str, &[&str])] = &[
     (
@@ -22,11 +23,30 @@ pub const COMMANDS: &[(&str, &str, &[&str])] = &[
         &["up", "upgrade"],
     ),
     ("list", "Lists installed packages", &["ls"]),
+    (
+        "why",
+        "Shows which dependency chains pull in a package",
+        &[],
+    ),
     (
         "clean",
         "Cleans package cache and optionally local node_modules",
         &[],
     ),
+    ("store", "Inspects the global package store", &[]),
+    (
+        "audit",
+        "Checks installed packages for known vulnerabilities",
+        &[],
+    ),
+    ("pack", "Packs the project into a tarball", &[]),
+    ("publish", "Publishes the project to its registry", &[]),
+    (
+        "import",
+        "Seeds pacm.lock from an npm or Yarn lockfile",
+        &[],
+    ),
+    ("export", "Writes pacm.lock as an npm or Yarn lockfile", &[]),
     (
         "help",
         "Shows help information for pacm or a specific command",
@@ -37,15 +57,34 @@ pub const EXAMPLES: &[(&str, &str)] = &[
     ("pacm install", "Install all dependencies"),
     ("pacm install axios", "Install a package"),
     ("pacm install typescript --dev", "Install dev dependency"),
+    (
+        "pacm install --production",
+        "Install without devDependencies",
+    ),
     ("pacm update", "Update all packages"),
+    (
+        "pacm run build --workspaces",
+        "Run a script in every workspace",
+    ),
+    (
+        "pacm audit --audit-level=high",
+        "Fail on high or critical vulnerabilities",
+    ),
     ("pacm remove axios", "Remove a package"),
     ("pacm list", "List dependencies"),
+    ("pacm list -g", "List global packages and their commands"),
+    ("pacm outdated --json", "Show available upgrades as JSON"),
+    ("pacm why loose-envify", "Show why a package is installed"),
     ("pacm init", "Initialize new project"),
     ("pacm clean --cache", "Clean package cache"),
 ];
 
 pub const USER_AGENT: &str = "pacm/0.1.0";
 pub const MAX_ATTEMPTS: u32 = 4;
+pub const MAX_REDIRECTS: usize = 10;
+pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org/";
+pub const RC_FILES: &[&str] = &[".npmrc", ".pacmrc"];
+pub const DEFAULT_LOCKFILE_NAME: &str = "pacm.lock";
 pub const POPULAR_PACKAGES: &[&str] = &[
     "react",
     "vue",
@@ -0,0 +1,58 @@
+//! Times `CacheManager::build_index` over a store of a few thousand packages, both the
+//! full scan and the reuse of an index an earlier install in the process built. Run with
+//! `cargo bench -p pacm-core --bench cache_index`.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use pacm_core::install::cache::CacheManager;
+
+const PACKAGES: usize = 4000;
+const VERSIONS: usize = 3;
+const RUNS: u32 = 20;
+
+fn populate(store: &Path) {
+    for package in 0..PACKAGES {
+        for version in 0..VERSIONS {
+            let dir = store
+                .join("npm")
+                .join(format!("bench-package-{package}"))
+                .join(format!("1.{version}.0"))
+                .join("package");
+            std::fs::create_dir_all(&dir).unwrap();
+        }
+    }
+}
+
+fn time(runtime: &tokio::runtime::Runtime, mut before: impl FnMut(u32)) -> Duration {
+    let mut total = Duration::ZERO;
+    for run in 0..RUNS {
+        before(run);
+        let start = Instant::now();
+        runtime
+            .block_on(CacheManager::new().build_index(false))
+            .unwrap();
+        total += start.elapsed();
+    }
+    total / RUNS
+}
+
+fn main() {
+    let store = tempfile::tempdir().unwrap();
+    populate(store.path());
+    // SAFETY: set before anything reads the config, while no other thread is running.
+    unsafe { std::env::set_var("PACM_STORE_DIR", store.path()) };
+    pacm_logger::init_logger(false);
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    // A new package directory changes the store's mtime, so every run scans.
+    let scan = time(&runtime, |run| {
+        std::fs::create_dir_all(store.path().join("npm").join(format!("bench-new-{run}"))).unwrap();
+    });
+    let reuse = time(&runtime, |_| {});
+
+    println!(
+        "build_index over {} stored versions: scan {scan:?}, reuse {reuse:?}",
+        PACKAGES * VERSIONS
+    );
+}
@@ -0,0 +1,610 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
+use serde::Serialize;
+
+use crate::install::InstallManager;
+use pacm_config::PacmConfig;
+use pacm_error::{PackageManagerError, Result};
+use pacm_lock::{LockPackage, PacmLock};
+use pacm_logger;
+use pacm_project::read_package_json;
+use pacm_registry::{Advisory, fetch_advisories_async, fetch_package_info_async};
+use pacm_resolver::satisfies;
+
+/// How long `pacm install --audit` waits for advisories before leaving out its summary.
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+impl Severity {
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "info" => Some(Self::Info),
+            "low" => Some(Self::Low),
+            "moderate" => Some(Self::Moderate),
+            "high" => Some(Self::High),
+            "critical" => Some(Self::Critical),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Low => "low",
+            Self::Moderate => "moderate",
+            Self::High => "high",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vulnerability {
+    pub id: u64,
+    pub severity: Severity,
+    pub package: String,
+    pub version: String,
+    pub range: String,
+    pub title: String,
+    pub url: String,
+    pub fix_available: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub vulnerabilities: Vec<Vulnerability>,
+    pub metadata: AuditMetadata,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditMetadata {
+    pub dependencies: usize,
+    pub vulnerabilities: BTreeMap<Severity, usize>,
+}
+
+impl AuditMetadata {
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.vulnerabilities.values().sum()
+    }
+
+    /// Counts per severity, most severe first, e.g. `1 high, 1 moderate`.
+    #[must_use]
+    pub fn breakdown(&self) -> String {
+        let counts: Vec<String> = self
+            .vulnerabilities
+            .iter()
+            .rev()
+            .map(|(severity, count)| format!("{count} {severity}"))
+            .collect();
+        counts.join(", ")
+    }
+
+    /// The line `pacm install --audit` prints once the install is done.
+    #[must_use]
+    pub fn summary_line(&self) -> String {
+        match self.total() {
+            0 => "found 0 vulnerabilities".to_string(),
+            total => format!(
+                "found {total} vulnerabilities ({}), run `pacm audit` for details",
+                self.breakdown()
+            ),
+        }
+    }
+}
+
+impl AuditReport {
+    /// Number of vulnerabilities at or above `level`.
+    #[must_use]
+    pub fn count_at_or_above(&self, level: Severity) -> usize {
+        self.vulnerabilities
+            .iter()
+            .filter(|v| v.severity >= level)
+            .count()
+    }
+}
+
+pub struct AuditManager {
+    client: Arc<reqwest::Client>,
+}
+
+impl AuditManager {
+    pub fn new() -> Self {
+        let client = pacm_registry::configure_client(reqwest::Client::builder())
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+
+        Self {
+            client: Arc::new(client),
+        }
+    }
+
+    /// Audits the packages recorded in the lockfile and prints the report. Fails when a
+    /// vulnerability at or above `audit_level` is found so the process exits non-zero.
+    pub fn audit(
+        &self,
+        project_dir: &str,
+        json: bool,
+        audit_level: Severity,
+        debug: bool,
+    ) -> Result<AuditReport> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+        let report = rt.block_on(self.build_report(Path::new(project_dir), json, true, debug))?;
+
+        if json {
+            let output = serde_json::to_string_pretty(&report)
+                .map_err(|e| PackageManagerError::IoError(e.to_string()))?;
+            println!("{output}");
+        } else {
+            Self::print_report(&report);
+        }
+
+        let failing = report.count_at_or_above(audit_level);
+        if failing > 0 {
+            return Err(PackageManagerError::VulnerabilitiesFound(
+                failing,
+                audit_level.to_string(),
+            ));
+        }
+
+        Ok(report)
+    }
+
+    /// Bumps every vulnerable package to the nearest safe version its dependents allow,
+    /// reinstalls, and audits again.
+    pub fn fix(
+        &self,
+        project_dir: &str,
+        json: bool,
+        audit_level: Severity,
+        debug: bool,
+    ) -> Result<AuditReport> {
+        let path = Path::new(project_dir);
+        let lock_path = PacmConfig::get().lockfile_path(path);
+
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+        let report = rt.block_on(self.build_report(path, json, true, debug))?;
+
+        let fixes: BTreeMap<&str, &str> = report
+            .vulnerabilities
+            .iter()
+            .filter_map(|v| {
+                v.fix_available
+                    .as_deref()
+                    .map(|fix| (v.package.as_str(), fix))
+            })
+            .collect();
+
+        if fixes.is_empty() {
+            if !json {
+                pacm_logger::info("No fixes available");
+            }
+            return self.audit(project_dir, json, audit_level, debug);
+        }
+
+        let mut lockfile = PacmLock::load(&lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+
+        for (name, version) in &fixes {
+            let package = rt
+                .block_on(self.locked_package(name, version))
+                .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+            lockfile.update_package(name, package);
+
+            for workspace in lockfile.workspaces.values_mut() {
+                for deps in [
+                    &mut workspace.dependencies,
+                    &mut workspace.dev_dependencies,
+                    &mut workspace.peer_dependencies,
+                    &mut workspace.optional_dependencies,
+                ] {
+                    if let Some(locked) = deps.get_mut(*name) {
+                        *locked = (*version).to_string();
+                    }
+                }
+            }
+
+            let installed = path.join("node_modules").join(name);
+            if installed.exists() || installed.is_symlink() {
+                let _ = std::fs::remove_dir_all(&installed)
+                    .or_else(|_| std::fs::remove_file(&installed));
+            }
+
+            if !json {
+                pacm_logger::status(&format!("Updating {name} to {version}..."));
+            }
+        }
+
+        lockfile
+            .save(&lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+
+        InstallManager::new().install_all(project_dir, debug)?;
+
+        if !json {
+            pacm_logger::finish(&format!("fixed {} packages", fixes.len()));
+        }
+
+        self.audit(project_dir, json, audit_level, debug)
+    }
+
+    /// Counts the vulnerabilities of the installed tree for `pacm install --audit`. Only
+    /// the advisory request is made, without looking up fixes, and it is given up on after
+    /// a few seconds. The summary is best-effort, so `None` means it could not be made.
+    pub fn summary(&self, project_dir: &str, debug: bool) -> Option<AuditMetadata> {
+        let rt = tokio::runtime::Runtime::new().ok()?;
+        let report = rt.block_on(async {
+            let report = self.build_report(Path::new(project_dir), true, false, debug);
+            tokio::time::timeout(SUMMARY_TIMEOUT, report).await
+        });
+
+        match report {
+            Ok(Ok(report)) => Some(report.metadata),
+            Ok(Err(e)) => {
+                pacm_logger::debug(&format!("Skipping audit summary: {e}"), debug);
+                None
+            }
+            Err(_) => {
+                pacm_logger::debug("Skipping audit summary: advisories timed out", debug);
+                None
+            }
+        }
+    }
+
+    /// Matches the locked packages against the registry's advisories. With `fixes`, each
+    /// vulnerable package's metadata is fetched to find the nearest safe version.
+    async fn build_report(
+        &self,
+        path: &Path,
+        quiet: bool,
+        fixes: bool,
+        debug: bool,
+    ) -> Result<AuditReport> {
+        let lock_path = PacmConfig::get().lockfile_path(path);
+        if !lock_path.exists() {
+            return Err(PackageManagerError::LockfileError(format!(
+                "No {} found, run `pacm install` first",
+                PacmConfig::get().lockfile_name()
+            )));
+        }
+
+        let lockfile = PacmLock::load(&lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+
+        let installed: BTreeMap<String, Vec<String>> = lockfile
+            .packages
+            .iter()
+            .map(|(name, pkg)| (name.clone(), vec![pkg.version.clone()]))
+            .collect();
+
+        if !quiet {
+            pacm_logger::status(&format!("Auditing {} packages...", installed.len()));
+        }
+
+        let advisories = fetch_advisories_async(self.client.clone(), &installed)
+            .await
+            .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+
+        pacm_logger::debug(
+            &format!("Received advisories for {} packages", advisories.len()),
+            debug,
+        );
+
+        let package_json = read_package_json(path).ok();
+        let mut vulnerabilities = Vec::new();
+
+        for (name, package_advisories) in &advisories {
+            let Some(locked) = lockfile.packages.get(name) else {
+                continue;
+            };
+
+            let matching: Vec<&Advisory> = package_advisories
+                .iter()
+                .filter(|a| satisfies(&locked.version, &a.vulnerable_versions))
+                .collect();
+            if matching.is_empty() {
+                continue;
+            }
+
+            let ranges = declared_ranges(
+                name,
+                &locked.version,
+                package_json
+                    .as_ref()
+                    .and_then(|pkg| pkg.get_all_dependencies().get(name).cloned()),
+                &lockfile.packages,
+            );
+
+            let fix_available = if !fixes {
+                None
+            } else {
+                match fetch_package_info_async(self.client.clone(), name).await {
+                    Ok(info) => {
+                        let candidates: Vec<String> = info
+                            .versions
+                            .as_object()
+                            .map(|versions| versions.keys().cloned().collect())
+                            .unwrap_or_default();
+                        find_fix(&locked.version, &candidates, &ranges, package_advisories)
+                    }
+                    Err(e) => {
+                        pacm_logger::debug(
+                            &format!("Could not look up fixes for {name}: {e}"),
+                            debug,
+                        );
+                        None
+                    }
+                }
+            };
+
+            for advisory in matching {
+                vulnerabilities.push(Vulnerability {
+                    id: advisory.id,
+                    severity: Severity::parse(&advisory.severity).unwrap_or(Severity::Info),
+                    package: name.clone(),
+                    version: locked.version.clone(),
+                    range: advisory.vulnerable_versions.clone(),
+                    title: advisory.title.clone(),
+                    url: advisory.url.clone(),
+                    fix_available: fix_available.clone(),
+                });
+            }
+        }
+
+        vulnerabilities.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then_with(|| a.package.cmp(&b.package))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let mut counts = BTreeMap::new();
+        for vulnerability in &vulnerabilities {
+            *counts.entry(vulnerability.severity).or_insert(0) += 1;
+        }
+
+        Ok(AuditReport {
+            vulnerabilities,
+            metadata: AuditMetadata {
+                dependencies: installed.len(),
+                vulnerabilities: counts,
+            },
+        })
+    }
+
+    async fn locked_package(&self, name: &str, version: &str) -> anyhow::Result<LockPackage> {
+        let info = fetch_package_info_async(self.client.clone(), name).await?;
+        let data = info
+            .versions
+            .get(version)
+            .ok_or_else(|| anyhow::anyhow!("{name}@{version} not found in registry"))?;
+
+        let string_map = |key: &str| -> HashMap<String, String> {
+            data.get(key)
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default()
+        };
+        let dist = data.get("dist");
+        let dist_field = |key: &str| {
+            dist.and_then(|d| d.get(key))
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        Ok(LockPackage {
+            version: version.to_string(),
+            resolved: PacmConfig::get().lock_resolved(name, &dist_field("tarball")),
+            integrity: dist_field("integrity"),
+            dependencies: string_map("dependencies"),
+            optional_dependencies: string_map("optionalDependencies"),
+            peer_dependencies: pacm_resolver::peer_dependencies(data),
+            optional_peer_dependencies: pacm_resolver::optional_peer_dependencies(data),
+        })
+    }
+
+    fn print_report(report: &AuditReport) {
+        for v in &report.vulnerabilities {
+            let severity = match v.severity {
+                Severity::Critical | Severity::High => v
+                    .severity
+                    .as_str()
+                    .if_supports_color(Stdout, |text| text.style(Style::new().red().bold()))
+                    .to_string(),
+                Severity::Moderate => v
+                    .severity
+                    .as_str()
+                    .if_supports_color(Stdout, |text| text.style(Style::new().yellow().bold()))
+                    .to_string(),
+                Severity::Low | Severity::Info => v
+                    .severity
+                    .as_str()
+                    .if_supports_color(Stdout, |text| text.dimmed())
+                    .to_string(),
+            };
+
+            println!(
+                "{}  {}@{}  {}",
+                severity,
+                v.package.if_supports_color(Stdout, |text| text.bold()),
+                v.version,
+                v.title
+            );
+            println!("    vulnerable versions: {}", v.range);
+            match &v.fix_available {
+                Some(fix) => println!(
+                    "    fix available: {}",
+                    fix.if_supports_color(Stdout, |text| text.green())
+                ),
+                None => println!(
+                    "    fix available: {}",
+                    "none".if_supports_color(Stdout, |text| text.dimmed())
+                ),
+            }
+            if !v.url.is_empty() {
+                println!(
+                    "    {}",
+                    v.url.if_supports_color(Stdout, |text| text.dimmed())
+                );
+            }
+        }
+
+        if report.vulnerabilities.is_empty() {
+            pacm_logger::finish(&format!(
+                "audited {} packages, found 0 vulnerabilities",
+                report.metadata.dependencies
+            ));
+            return;
+        }
+
+        pacm_logger::warn(&format!(
+            "audited {} packages, found {} vulnerabilities ({})",
+            report.metadata.dependencies,
+            report.vulnerabilities.len(),
+            report.metadata.breakdown()
+        ));
+    }
+}
+
+impl Default for AuditManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collects every range that constrains `name`: the project's own declaration and the
+/// ranges other locked packages request. Falls back to `^current` when nothing does.
+fn declared_ranges(
+    name: &str,
+    current: &str,
+    direct_range: Option<String>,
+    packages: &HashMap<String, LockPackage>,
+) -> Vec<String> {
+    let mut ranges: Vec<String> = direct_range.into_iter().collect();
+
+    for pkg in packages.values() {
+        if let Some(range) = pkg
+            .dependencies
+            .get(name)
+            .or_else(|| pkg.optional_dependencies.get(name))
+        {
+            ranges.push(range.clone());
+        }
+    }
+
+    if ranges.is_empty() {
+        ranges.push(format!("^{current}"));
+    }
+    ranges
+}
+
+/// Picks the lowest stable version above `current` that satisfies every range in
+/// `ranges` and is not matched by any advisory.
+fn find_fix(
+    current: &str,
+    candidates: &[String],
+    ranges: &[String],
+    advisories: &[Advisory],
+) -> Option<String> {
+    let current = semver::Version::parse(current).ok()?;
+
+    let mut versions: Vec<semver::Version> = candidates
+        .iter()
+        .filter_map(|v| semver::Version::parse(v).ok())
+        .filter(|v| v.pre.is_empty() && *v > current)
+        .collect();
+    versions.sort();
+
+    versions.into_iter().map(|v| v.to_string()).find(|v| {
+        ranges.iter().all(|range| satisfies(v, range))
+            && !advisories
+                .iter()
+                .any(|a| satisfies(v, &a.vulnerable_versions))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advisory(vulnerable_versions: &str) -> Advisory {
+        Advisory {
+            id: 1,
+            title: String::new(),
+            url: String::new(),
+            severity: "high".to_string(),
+            vulnerable_versions: vulnerable_versions.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_severity_order_and_parse() {
+        assert!(Severity::Critical > Severity::High);
+        assert!(Severity::Moderate > Severity::Low);
+        assert_eq!(Severity::parse("MODERATE"), Some(Severity::Moderate));
+        assert_eq!(Severity::parse("unknown"), None);
+    }
+
+    #[test]
+    fn test_summary_line_lists_severities_most_severe_first() {
+        let metadata = |counts: &[(Severity, usize)]| AuditMetadata {
+            dependencies: 12,
+            vulnerabilities: counts.iter().copied().collect(),
+        };
+
+        assert_eq!(metadata(&[]).summary_line(), "found 0 vulnerabilities");
+        assert_eq!(
+            metadata(&[(Severity::Moderate, 1), (Severity::High, 1)]).summary_line(),
+            "found 2 vulnerabilities (1 high, 1 moderate), run `pacm audit` for details"
+        );
+    }
+
+    #[test]
+    fn test_find_fix_respects_ranges_and_advisories() {
+        let candidates: Vec<String> = ["4.17.15", "4.17.19", "4.17.21", "5.0.0", "4.18.0-beta.1"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+
+        let fix = find_fix(
+            "4.17.15",
+            &candidates,
+            &["^4.17.0".to_string()],
+            &[advisory("<4.17.21")],
+        );
+        assert_eq!(fix.as_deref(), Some("4.17.21"));
+
+        let none = find_fix(
+            "4.17.15",
+            &candidates,
+            &["~4.17.15".to_string()],
+            &[advisory("<6.0.0")],
+        );
+        assert_eq!(none, None);
+    }
+}
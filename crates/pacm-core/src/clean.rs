@@ -3,38 +3,46 @@ use std::path::PathBuf;
 
 use pacm_error::{PackageManagerError, Result};
 use pacm_logger;
-use pacm_store::get_store_path;
+use pacm_store::get_cache_path;
 
 pub struct CleanManager;
 
+impl Default for CleanManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CleanManager {
     pub fn new() -> Self {
         Self
     }
 
+    /// Empties the cache directory. Unless `cache-dir` is set, that is the package
+    /// store itself.
     pub fn clean_cache(&self, debug: bool) -> Result<()> {
-        let store_path = get_store_path();
+        let cache_path = get_cache_path();
 
-        if !store_path.exists() {
+        if !cache_path.exists() {
             pacm_logger::info("No package cache found to clean.");
             return Ok(());
         }
 
         if debug {
-            pacm_logger::debug(&format!("Cleaning cache at: {:?}", store_path), debug);
+            pacm_logger::debug(&format!("Cleaning cache at: {:?}", cache_path), debug);
         }
 
         pacm_logger::status("Cleaning package cache...");
 
         // Calculate cache size before cleaning
-        let cache_size = self.calculate_directory_size(&store_path)?;
+        let cache_size = self.calculate_directory_size(&cache_path)?;
 
-        // Remove the entire store directory
-        fs::remove_dir_all(&store_path)
+        // Remove the entire cache directory
+        fs::remove_dir_all(&cache_path)
             .map_err(|e| PackageManagerError::IoError(format!("Failed to clean cache: {}", e)))?;
 
-        // Recreate the store directory structure
-        fs::create_dir_all(&store_path).map_err(|e| {
+        // Recreate the cache directory
+        fs::create_dir_all(&cache_path).map_err(|e| {
             PackageManagerError::IoError(format!("Failed to recreate cache directory: {}", e))
         })?;
 
@@ -76,7 +84,7 @@ impl CleanManager {
         Ok(())
     }
 
-    fn calculate_directory_size(&self, dir: &PathBuf) -> Result<u64> {
+    pub(crate) fn calculate_directory_size(&self, dir: &PathBuf) -> Result<u64> {
         let mut total_size = 0u64;
 
         if dir.is_dir() {
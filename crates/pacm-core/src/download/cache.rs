@@ -10,6 +10,12 @@ pub struct CacheIndex {
     index: Arc<Mutex<HashMap<String, PathBuf>>>,
 }
 
+impl Default for CacheIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CacheIndex {
     pub fn new() -> Self {
         Self {
@@ -35,13 +41,14 @@ impl CacheIndex {
 
         if let Ok(package_entries) = std::fs::read_dir(&npm_dir) {
             for package_entry in package_entries.flatten() {
-                if package_entry.file_type().map_or(false, |ft| ft.is_dir()) {
-                    let package_name =
-                        Self::unsanitize_package_name(&package_entry.file_name().to_string_lossy());
+                if package_entry.file_type().is_ok_and(|ft| ft.is_dir()) {
+                    let package_name = pacm_store::package_name_from_store(
+                        &package_entry.file_name().to_string_lossy(),
+                    );
 
                     if let Ok(version_entries) = std::fs::read_dir(package_entry.path()) {
                         for version_entry in version_entries.flatten() {
-                            if version_entry.file_type().map_or(false, |ft| ft.is_dir()) {
+                            if version_entry.file_type().is_ok_and(|ft| ft.is_dir()) {
                                 let version =
                                     version_entry.file_name().to_string_lossy().to_string();
                                 let package_dir = version_entry.path().join("package");
@@ -74,10 +81,6 @@ impl CacheIndex {
         cache.get(key).cloned()
     }
 
-    fn unsanitize_package_name(safe_name: &str) -> String {
-        safe_name.replace("_at_", "@").replace("_slash_", "/")
-    }
-
     pub async fn find_versions_for_package(&self, package_name: &str) -> Vec<(String, PathBuf)> {
         let cache = self.index.lock().await;
         let name_prefix = format!("{}@", package_name);
@@ -1,21 +1,50 @@
+use base64::Engine;
 use reqwest;
+use sha2::{Digest, Sha512};
 use std::sync::Arc;
 use tokio::sync::Semaphore;
 
+use pacm_config::{NetworkMode, PacmConfig};
 use pacm_constants::USER_AGENT;
 use pacm_error::{PackageManagerError, Result};
 use pacm_logger;
+use pacm_registry::CircuitBreaker;
 use pacm_resolver::ResolvedPackage;
 
+const MAX_DOWNLOAD_ATTEMPTS: usize = 3;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+enum FetchError {
+    Retryable(PackageManagerError),
+    Fatal(PackageManagerError),
+    /// The server answered `404`, which a package's `dist.tarball` may still recover from.
+    NotFound(PackageManagerError),
+}
+
+impl FetchError {
+    fn into_error(self) -> PackageManagerError {
+        match self {
+            Self::Retryable(e) | Self::Fatal(e) | Self::NotFound(e) => e,
+        }
+    }
+}
+
 pub struct DownloadClient {
     client: reqwest::Client,
     semaphore: Arc<Semaphore>,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl Default for DownloadClient {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DownloadClient {
     pub fn new() -> Self {
         Self {
-            client: reqwest::Client::builder()
+            client: pacm_registry::configure_client(reqwest::Client::builder())
                 .pool_max_idle_per_host(25)
                 .pool_idle_timeout(std::time::Duration::from_secs(90))
                 .timeout(std::time::Duration::from_secs(45))
@@ -26,9 +55,17 @@ impl DownloadClient {
                 .build()
                 .unwrap_or_else(|_| reqwest::Client::new()),
             semaphore: Arc::new(Semaphore::new(25)),
+            breaker: CircuitBreaker::global(),
         }
     }
 
+    /// Downloads through `client` instead of a client of its own.
+    #[must_use]
+    pub fn with_client(mut self, client: &reqwest::Client) -> Self {
+        self.client = client.clone();
+        self
+    }
+
     pub fn get_client(&self) -> &reqwest::Client {
         &self.client
     }
@@ -38,54 +75,224 @@ impl DownloadClient {
     }
 
     pub async fn download_tarball(&self, pkg: &ResolvedPackage, debug: bool) -> Result<Vec<u8>> {
-        let _permit = self.semaphore.acquire().await.unwrap();
-
         if !debug {
             pacm_logger::status(&format!("◦ Downloading {}@{}...", pkg.name, pkg.version));
         }
 
-        match self.client.get(&pkg.resolved).send().await {
-            Ok(resp) => {
-                if !resp.status().is_success() {
-                    return Err(PackageManagerError::NetworkError(format!(
-                        "HTTP {} for {}",
-                        resp.status(),
-                        pkg.resolved
-                    )));
+        let label = format!("{}@{}", pkg.name, pkg.version);
+        let fetched = match self
+            .fetch(&pkg.resolved, &label, &pkg.integrity, debug)
+            .await
+        {
+            Err(FetchError::NotFound(e)) => match self.metadata_tarball(pkg).await {
+                Some(url) => {
+                    pacm_logger::debug(
+                        &format!(
+                            "{} is not at {}, trying dist.tarball {}",
+                            label, pkg.resolved, url
+                        ),
+                        debug,
+                    );
+                    self.fetch(&url, &label, &pkg.integrity, debug).await
                 }
+                None => Err(FetchError::NotFound(e)),
+            },
+            fetched => fetched,
+        };
+        let (data, content_type) = fetched.map_err(FetchError::into_error)?;
+
+        if !is_gzip(&data) {
+            return Err(PackageManagerError::DownloadFailed(
+                pkg.name.clone(),
+                format!("{}: expected tarball, got {}", pkg.version, content_type),
+            ));
+        }
+        Ok(data)
+    }
+
+    /// Downloads a tarball that is addressed only by its URL, such as one passed
+    /// directly to `pacm install`.
+    pub async fn download_url(&self, url: &str, debug: bool) -> Result<Vec<u8>> {
+        if !debug {
+            pacm_logger::status(&format!("◦ Downloading {}...", url));
+        }
 
-                match resp.bytes().await {
-                    Ok(bytes) => {
-                        if debug {
-                            pacm_logger::debug(
-                                &format!(
-                                    "Downloaded {}@{} ({} bytes)",
-                                    pkg.name,
-                                    pkg.version,
-                                    bytes.len()
-                                ),
-                                debug,
-                            );
-                        }
-                        Ok(bytes.to_vec())
+        let (data, content_type) = self
+            .fetch(url, url, "", debug)
+            .await
+            .map_err(FetchError::into_error)?;
+
+        if !is_gzip(&data) {
+            return Err(PackageManagerError::NetworkError(format!(
+                "{}: expected tarball, got {}",
+                url, content_type
+            )));
+        }
+        Ok(data)
+    }
+
+    /// The `dist.tarball` the registry metadata gives for `pkg` when it differs from
+    /// `pkg.resolved`. Mirrors with their own tarball layout do not serve the conventional
+    /// URL that lockfiles and fast paths may carry. The metadata is usually already in the
+    /// in-process cache from resolution.
+    async fn metadata_tarball(&self, pkg: &ResolvedPackage) -> Option<String> {
+        let info =
+            pacm_registry::fetch_package_info_async(Arc::new(self.client.clone()), &pkg.name)
+                .await
+                .ok()?;
+        info.dist_tarball(&pkg.version)
+            .filter(|url| *url != pkg.resolved)
+            .map(str::to_string)
+    }
+
+    /// Downloads `url`, retrying failed transfers. A transfer that dies part way is
+    /// resumed with a `Range` request when the server answers `206`, and restarted
+    /// otherwise. Resumed downloads are checked against `integrity` since they are
+    /// stitched together from several responses. URLs on a registry whose metadata was
+    /// redirected to another host are fetched from that host. Returns the body along with
+    /// the `Content-Type` the server sent for it.
+    async fn fetch(
+        &self,
+        url: &str,
+        label: &str,
+        integrity: &str,
+        debug: bool,
+    ) -> std::result::Result<(Vec<u8>, String), FetchError> {
+        if PacmConfig::get().network_mode() == NetworkMode::Offline {
+            return Err(FetchError::Fatal(PackageManagerError::NetworkError(
+                format!(
+                    "{} is not in the store and offline mode does not allow downloading it",
+                    label
+                ),
+            )));
+        }
+
+        let _permit = self.semaphore.acquire().await.unwrap();
+        let url = &pacm_registry::redirected_url(url);
+
+        let mut data = Vec::new();
+        let mut resumed = false;
+        let mut attempt = 1;
+
+        let content_type = loop {
+            self.breaker
+                .check()
+                .map_err(|e| FetchError::Fatal(PackageManagerError::NetworkError(e.to_string())))?;
+
+            match self.fetch_into(url, label, &mut data, &mut resumed).await {
+                Ok(content_type) => {
+                    self.breaker.record_success();
+                    break content_type;
+                }
+                Err(error @ (FetchError::Fatal(_) | FetchError::NotFound(_))) => return Err(error),
+                Err(FetchError::Retryable(e)) => {
+                    if self.breaker.record_failure() {
+                        return Err(FetchError::Fatal(PackageManagerError::NetworkError(
+                            self.breaker.open_error().to_string(),
+                        )));
                     }
-                    Err(e) => {
-                        pacm_logger::debug(
-                            &format!("Failed to read response bytes for {}: {}", pkg.name, e),
-                            debug,
-                        );
-                        Err(PackageManagerError::NetworkError(e.to_string()))
+                    pacm_logger::debug(
+                        &format!(
+                            "Download of {} failed after {} bytes (attempt {}/{}): {}",
+                            label,
+                            data.len(),
+                            attempt,
+                            MAX_DOWNLOAD_ATTEMPTS,
+                            e
+                        ),
+                        debug,
+                    );
+                    if attempt == MAX_DOWNLOAD_ATTEMPTS {
+                        return Err(FetchError::Fatal(e));
                     }
+                    attempt += 1;
                 }
             }
-            Err(e) => {
-                pacm_logger::debug(
-                    &format!("Network request failed for {}: {}", pkg.name, e),
-                    debug,
-                );
-                Err(PackageManagerError::NetworkError(e.to_string()))
+        };
+
+        if resumed && !verify_integrity(&data, integrity) {
+            return Err(FetchError::Fatal(PackageManagerError::DownloadFailed(
+                label.to_string(),
+                "integrity check failed after resuming download".to_string(),
+            )));
+        }
+
+        if debug {
+            pacm_logger::debug(
+                &format!("Downloaded {} ({} bytes)", label, data.len()),
+                debug,
+            );
+        }
+        Ok((data, content_type))
+    }
+
+    /// Appends the remainder of `url` to `data`, starting over if the server ignores the
+    /// range request. Returns the response's `Content-Type`.
+    async fn fetch_into(
+        &self,
+        url: &str,
+        label: &str,
+        data: &mut Vec<u8>,
+        resumed: &mut bool,
+    ) -> std::result::Result<String, FetchError> {
+        let mut request = self.client.get(url);
+        if let Some(auth) = PacmConfig::get().auth_header_for(url) {
+            request = request.header("Authorization", auth);
+        }
+        if !data.is_empty() {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", data.len()));
+        }
+
+        pacm_registry::connections::record_request();
+        let mut resp = request.send().await.map_err(|e| {
+            // Not retried, but a registry stuck in a loop counts against the breaker just
+            // like one that is down, as it does for metadata.
+            if e.is_redirect() {
+                if self.breaker.record_failure() {
+                    return FetchError::Fatal(PackageManagerError::NetworkError(
+                        self.breaker.open_error().to_string(),
+                    ));
+                }
+                FetchError::Fatal(PackageManagerError::NetworkError(format!(
+                    "too many redirects fetching {}",
+                    label
+                )))
+            } else {
+                FetchError::Retryable(PackageManagerError::NetworkError(e.to_string()))
             }
+        })?;
+
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("no content type")
+            .to_string();
+
+        let status = resp.status();
+        if status == reqwest::StatusCode::PARTIAL_CONTENT && !data.is_empty() {
+            *resumed = true;
+        } else if status.is_success() {
+            data.clear();
+        } else if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(FetchError::NotFound(PackageManagerError::NetworkError(
+                format!("HTTP {} for {}", status, url),
+            )));
+        } else {
+            return Err(FetchError::Fatal(PackageManagerError::NetworkError(
+                format!("HTTP {} for {}", status, url),
+            )));
         }
+
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| FetchError::Retryable(PackageManagerError::NetworkError(e.to_string())))?
+        {
+            data.extend_from_slice(&chunk);
+        }
+
+        Ok(content_type)
     }
 
     pub fn download_tarball_sync(&self, pkg: &ResolvedPackage, debug: bool) -> Result<Vec<u8>> {
@@ -103,3 +310,152 @@ impl DownloadClient {
         rt.block_on(self.download_tarball(pkg, debug))
     }
 }
+
+/// Whether `data` starts like a gzip stream. Proxies and mirrors sometimes answer with
+/// an HTML error page and a `200`, which would otherwise fail deep inside extraction.
+fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&GZIP_MAGIC)
+}
+
+/// Checks `data` against a `sha512-` subresource integrity string. Other algorithms and
+/// missing integrity cannot be checked here and are accepted.
+pub(super) fn verify_integrity(data: &[u8], integrity: &str) -> bool {
+    match integrity.strip_prefix("sha512-") {
+        Some(expected) => {
+            let digest = Sha512::digest(data);
+            base64::engine::general_purpose::STANDARD.encode(digest) == expected
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_testutil::{MockServer, Response, build_tarball, integrity, resolved_package};
+    use std::sync::Mutex;
+
+    fn package(url: String, tarball: &[u8]) -> ResolvedPackage {
+        ResolvedPackage {
+            resolved: url,
+            integrity: integrity(tarball),
+            ..resolved_package("dropped", "1.0.0", &[])
+        }
+    }
+
+    fn tarball() -> Vec<u8> {
+        build_tarball(&serde_json::json!({ "name": "dropped", "version": "1.0.0" }))
+    }
+
+    #[tokio::test]
+    async fn test_resumes_dropped_download_with_range() {
+        pacm_logger::init_logger(true);
+        let body = tarball();
+        let half = body.len() / 2;
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+
+        let server = {
+            let body = body.clone();
+            let ranges = ranges.clone();
+            MockServer::start(Arc::new(move |req| match req.header("range") {
+                Some(range) => {
+                    ranges.lock().unwrap().push(range.to_string());
+                    let start: usize = range
+                        .trim_start_matches("bytes=")
+                        .trim_end_matches('-')
+                        .parse()
+                        .unwrap();
+                    Response::partial(body[start..].to_vec())
+                }
+                None => Response::bytes(body.clone()).truncated(half),
+            }))
+            .unwrap()
+        };
+
+        let pkg = package(format!("{}dropped.tgz", server.url()), &body);
+        let data = DownloadClient::new()
+            .download_tarball(&pkg, false)
+            .await
+            .unwrap();
+
+        assert_eq!(data, body);
+        assert_eq!(*ranges.lock().unwrap(), vec![format!("bytes={}-", half)]);
+    }
+
+    #[tokio::test]
+    async fn test_restarts_download_when_range_is_ignored() {
+        pacm_logger::init_logger(true);
+        let body = tarball();
+        let requests = Arc::new(Mutex::new(0));
+
+        let server = {
+            let body = body.clone();
+            let requests = requests.clone();
+            MockServer::start(Arc::new(move |_| {
+                let mut count = requests.lock().unwrap();
+                *count += 1;
+                if *count == 1 {
+                    Response::bytes(body.clone()).truncated(body.len() / 3)
+                } else {
+                    Response::bytes(body.clone())
+                }
+            }))
+            .unwrap()
+        };
+
+        let pkg = package(format!("{}dropped.tgz", server.url()), &body);
+        let data = DownloadClient::new()
+            .download_tarball(&pkg, false)
+            .await
+            .unwrap();
+
+        assert_eq!(data, body);
+        assert_eq!(*requests.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_html_response_is_not_a_tarball() {
+        pacm_logger::init_logger(true);
+        let server = MockServer::start(Arc::new(|_| Response {
+            content_type: "text/html",
+            ..Response::bytes(b"<html><body>Proxy error</body></html>".to_vec())
+        }))
+        .unwrap();
+
+        let pkg = package(format!("{}dropped.tgz", server.url()), b"");
+        let error = DownloadClient::new()
+            .download_tarball(&pkg, false)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Failed to download dropped@1.0.0: expected tarball, got text/html"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirect_loop_counts_against_the_breaker() {
+        pacm_logger::init_logger(true);
+        let server = MockServer::start(Arc::new(|req| {
+            if req.path == "/dropped.tgz" {
+                Response::redirect("/dropped.tgz/")
+            } else {
+                Response::redirect("/dropped.tgz")
+            }
+        }))
+        .unwrap();
+        let breaker = Arc::new(CircuitBreaker::new(3, std::time::Duration::from_secs(60)));
+        assert!(!breaker.record_failure());
+        let client = DownloadClient {
+            breaker: breaker.clone(),
+            ..DownloadClient::new()
+        };
+
+        let pkg = package(format!("{}dropped.tgz", server.url()), b"");
+        let error = client.download_tarball(&pkg, false).await.unwrap_err();
+
+        assert!(error.to_string().contains("too many redirects"));
+        assert!(breaker.record_failure());
+    }
+}
@@ -1,9 +1,13 @@
 use futures::future::join_all;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::sync::{Mutex, Semaphore};
+use tokio_util::sync::CancellationToken;
 
+use pacm_config::PacmConfig;
 use pacm_error::{PackageManagerError, Result};
 use pacm_logger;
 use pacm_resolver::ResolvedPackage;
@@ -11,11 +15,18 @@ use pacm_symcap::SystemCapabilities;
 
 use super::cache::CacheIndex;
 use super::client::DownloadClient;
+use super::signatures::SignatureAuditor;
+use crate::install::manifest_cache;
+use crate::timing::{self, Phase};
 
 pub struct PackageDownloader {
     cache: CacheIndex,
     client: DownloadClient,
     download_semaphore: Arc<Semaphore>,
+    deprecated: std::sync::Mutex<BTreeMap<String, String>>,
+    /// Optional packages left out because their download failed, with the reason.
+    skipped_optional: std::sync::Mutex<BTreeMap<String, String>>,
+    signatures: Option<SignatureAuditor>,
 }
 
 impl PackageDownloader {
@@ -26,6 +37,122 @@ impl PackageDownloader {
             cache: CacheIndex::new(),
             client: DownloadClient::new(),
             download_semaphore: Arc::new(Semaphore::new(system_caps.optimal_parallel_downloads)),
+            deprecated: std::sync::Mutex::new(BTreeMap::new()),
+            skipped_optional: std::sync::Mutex::new(BTreeMap::new()),
+            signatures: PacmConfig::get()
+                .audit_signatures()
+                .then(SignatureAuditor::new),
+        }
+    }
+
+    /// Downloads tarballs through `client` instead of a client of its own, sharing its
+    /// connection pool and TLS and proxy setup.
+    #[must_use]
+    pub fn with_client(mut self, client: Arc<reqwest::Client>) -> Self {
+        self.client = self.client.with_client(&client);
+        self
+    }
+
+    /// Verifies the registry signature of every package downloaded from now on.
+    /// Defaults to the `audit-signatures` config value.
+    #[must_use]
+    pub fn with_audit_signatures(mut self, audit_signatures: bool) -> Self {
+        self.signatures = audit_signatures.then(SignatureAuditor::new);
+        self
+    }
+
+    async fn audit_signature(&self, pkg: &ResolvedPackage, tarball: &[u8]) -> Result<()> {
+        match &self.signatures {
+            Some(auditor) => auditor.audit(self.client.get_client(), pkg, tarball).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Warns about each deprecated package the first time it is seen and remembers it
+    /// for [`Self::report`].
+    fn note_deprecated(&self, packages: &[ResolvedPackage]) {
+        let mut deprecated = self
+            .deprecated
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        for pkg in packages {
+            let Some(message) = &pkg.deprecated else {
+                continue;
+            };
+            let key = format!("{}@{}", pkg.name, pkg.version);
+            if let Entry::Vacant(entry) = deprecated.entry(key) {
+                pacm_logger::warn(&format!("{} is deprecated: {}", entry.key(), message));
+                entry.insert(message.clone());
+            }
+        }
+    }
+
+    /// Printed after an install's summary line: the space saved by `prune-store`, the
+    /// optional packages that could not be downloaded, and the deprecation warnings
+    /// collected along the way, so they are not lost in the scrollback. All are forgotten
+    /// afterwards.
+    pub fn report(&self) {
+        let pruned = pacm_store::StorePruner::take_pruned_bytes();
+        if pruned > 0 {
+            pacm_logger::info(&format!(
+                "Pruned {:.2} MB of docs, tests and source maps from the store",
+                pruned as f64 / 1024.0 / 1024.0
+            ));
+        }
+
+        self.report_skipped_optional();
+        self.report_deprecated();
+    }
+
+    fn report_skipped_optional(&self) {
+        let skipped = std::mem::take(
+            &mut *self
+                .skipped_optional
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+        if skipped.is_empty() {
+            return;
+        }
+
+        pacm_logger::warn(&format!(
+            "{} optional {} could not be downloaded and {} not installed:",
+            skipped.len(),
+            if skipped.len() == 1 {
+                "dependency"
+            } else {
+                "dependencies"
+            },
+            if skipped.len() == 1 { "was" } else { "were" }
+        ));
+        for (key, reason) in skipped {
+            pacm_logger::warn(&format!("  {}: {}", key, reason));
+        }
+    }
+
+    fn report_deprecated(&self) {
+        let deprecated = std::mem::take(
+            &mut *self
+                .deprecated
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+        if deprecated.is_empty() {
+            return;
+        }
+
+        pacm_logger::warn(&format!(
+            "{} deprecated {} installed:",
+            deprecated.len(),
+            if deprecated.len() == 1 {
+                "package"
+            } else {
+                "packages"
+            }
+        ));
+        for (key, message) in deprecated {
+            pacm_logger::warn(&format!("  {}: {}", key, message));
         }
     }
 
@@ -33,11 +160,52 @@ impl PackageDownloader {
         &self,
         packages: &[ResolvedPackage],
         debug: bool,
+    ) -> Result<HashMap<String, (ResolvedPackage, PathBuf)>> {
+        self.download_parallel_cancellable(packages, &HashSet::new(), debug, None)
+            .await
+    }
+
+    /// Downloads `pkg` even if it is already stored and replaces the stored copy, for
+    /// `pacm install --force`.
+    pub async fn redownload(&self, pkg: &ResolvedPackage, debug: bool) -> Result<PathBuf> {
+        self.note_deprecated(std::slice::from_ref(pkg));
+
+        let download_start = std::time::Instant::now();
+        let tarball_data = self.client.download_tarball(pkg, debug).await?;
+        timing::record(Phase::Download, download_start.elapsed());
+        timing::record_package_download(&pkg.name, download_start.elapsed(), tarball_data.len());
+        self.audit_signature(pkg, &tarball_data).await?;
+
+        let store_path = timing::timed(Phase::Extraction, || {
+            pacm_store::replace_package(&pkg.name, &pkg.version, &tarball_data)
+        })
+        .map_err(|e| {
+            PackageManagerError::StorageFailed(
+                format!("{}@{}", pkg.name, pkg.version),
+                format!("Failed to replace stored package: {}", e),
+            )
+        })?;
+        manifest_cache::invalidate(&store_path);
+        Ok(store_path)
+    }
+
+    /// Like [`Self::download_parallel`], but stops before the next download batch once
+    /// `cancel` fires. Batches already in flight are allowed to finish. The packages
+    /// keyed in `optional` are best-effort: one that fails to download is left out of
+    /// the result with a warning instead of failing the install.
+    pub async fn download_parallel_cancellable(
+        &self,
+        packages: &[ResolvedPackage],
+        optional: &HashSet<String>,
+        debug: bool,
+        cancel: Option<&CancellationToken>,
     ) -> Result<HashMap<String, (ResolvedPackage, PathBuf)>> {
         if packages.is_empty() {
             return Ok(HashMap::new());
         }
 
+        self.note_deprecated(packages);
+
         let system_caps = SystemCapabilities::get();
         let start_time = std::time::Instant::now();
         self.cache.build(debug).await?;
@@ -85,6 +253,8 @@ impl PackageDownloader {
         if !packages_to_download.is_empty() {
             let download_start = std::time::Instant::now();
 
+            let total = packages_to_download.len();
+            let downloaded = Arc::new(AtomicUsize::new(0));
             let batch_size = system_caps.get_network_batch_size(packages_to_download.len());
             let batches: Vec<_> = packages_to_download.chunks(batch_size).collect();
 
@@ -101,6 +271,10 @@ impl PackageDownloader {
             }
 
             for (batch_idx, batch) in batches.into_iter().enumerate() {
+                if cancel.is_some_and(CancellationToken::is_cancelled) {
+                    return Err(PackageManagerError::Cancelled);
+                }
+
                 if debug && batch.len() > 1 {
                     pacm_logger::debug(
                         &format!(
@@ -118,7 +292,8 @@ impl PackageDownloader {
                         let client = &self.client;
                         let stored_packages = stored_packages.clone();
                         let processed = processed.clone();
-                        let pkg = pkg.clone();
+                        let downloaded = downloaded.clone();
+                        let mut pkg = pkg.clone();
                         let semaphore = self.download_semaphore.clone();
 
                         async move {
@@ -134,15 +309,33 @@ impl PackageDownloader {
                                 proc.insert(key.clone());
                             }
 
+                            let package_start = std::time::Instant::now();
                             match client.download_tarball(&pkg, debug).await {
                                 Ok(tarball_data) => {
-                                    if let Ok(store_path) = pacm_store::store_package(
+                                    timing::record_package_download(
                                         &pkg.name,
-                                        &pkg.version,
-                                        &tarball_data,
-                                    ) {
+                                        package_start.elapsed(),
+                                        tarball_data.len(),
+                                    );
+                                    self.audit_signature(&pkg, &tarball_data).await?;
+                                    // Registries that predate `dist.integrity` leave it
+                                    // blank; the lockfile gets the tarball's own.
+                                    if pkg.integrity.is_empty() {
+                                        pkg.integrity = pacm_store::integrity_of(&tarball_data);
+                                    }
+                                    if let Ok(store_path) = timing::timed(Phase::Extraction, || {
+                                        pacm_store::store_package(
+                                            &pkg.name,
+                                            &pkg.version,
+                                            &tarball_data,
+                                        )
+                                    }) {
                                         let mut stored = stored_packages.lock().await;
                                         stored.insert(key.clone(), (pkg, store_path));
+                                        pacm_logger::download_progress(
+                                            downloaded.fetch_add(1, Ordering::Relaxed) + 1,
+                                            total,
+                                        );
 
                                         if debug {
                                             pacm_logger::debug(
@@ -161,6 +354,17 @@ impl PackageDownloader {
                                         ));
                                     }
                                 }
+                                Err(e) if optional.contains(&key) => {
+                                    pacm_logger::warn(&format!(
+                                        "Skipping optional dependency {}: {}",
+                                        key, e
+                                    ));
+                                    self.skipped_optional
+                                        .lock()
+                                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                                        .insert(key, e.to_string());
+                                    return Ok(());
+                                }
                                 Err(e) => {
                                     pacm_logger::error(&format!(
                                         "Failed to download {}: {}",
@@ -178,12 +382,11 @@ impl PackageDownloader {
                 let download_results = join_all(download_tasks).await;
 
                 for result in download_results {
-                    if let Err(e) = result {
-                        return Err(e);
-                    }
+                    result?
                 }
             }
 
+            timing::record(Phase::Download, download_start.elapsed());
             if debug {
                 pacm_logger::debug(
                     &format!("All downloads completed in {:?}", download_start.elapsed()),
@@ -207,6 +410,45 @@ impl PackageDownloader {
         Ok(final_stored)
     }
 
+    /// Reads a tarball from a local path (relative to `base_dir`) or fetches it from a
+    /// URL, then extracts it into the store. Returns the store path and the integrity of
+    /// the tarball.
+    pub async fn download_tarball_source(
+        &self,
+        source: &str,
+        base_dir: &Path,
+        debug: bool,
+    ) -> Result<(PathBuf, String)> {
+        let location = source.strip_prefix("file:").unwrap_or(source);
+
+        let tarball_data = if location.starts_with("http://") || location.starts_with("https://") {
+            let download_start = std::time::Instant::now();
+            let data = self.client.download_url(location, debug).await?;
+            timing::record(Phase::Download, download_start.elapsed());
+            data
+        } else {
+            let tarball_path = base_dir.join(location);
+            pacm_logger::debug(
+                &format!("Reading tarball {}", tarball_path.display()),
+                debug,
+            );
+            tokio::fs::read(&tarball_path).await.map_err(|e| {
+                PackageManagerError::IoError(format!(
+                    "Failed to read tarball {}: {}",
+                    tarball_path.display(),
+                    e
+                ))
+            })?
+        };
+
+        timing::timed(Phase::Extraction, || {
+            pacm_store::store_tarball(&tarball_data)
+        })
+        .map_err(|e| {
+            PackageManagerError::IoError(format!("Failed to store tarball {}: {}", source, e))
+        })
+    }
+
     async fn separate_cached(
         &self,
         packages: &[ResolvedPackage],
@@ -1,6 +1,7 @@
 pub mod cache;
 pub mod client;
 pub mod manager;
+pub mod signatures;
 pub mod storage;
 
 pub use manager::PackageDownloader;
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use pacm_config::PacmConfig;
+use pacm_error::{PackageManagerError, Result};
+use pacm_logger;
+use pacm_registry::{RegistryKeys, SignatureCheck};
+use pacm_resolver::ResolvedPackage;
+
+use super::client::verify_integrity;
+
+/// Checks downloaded tarballs against their registry signatures, for
+/// `--audit-signatures`. Each registry's keys are fetched once, the first time one of
+/// its packages is checked.
+#[derive(Default)]
+pub struct SignatureAuditor {
+    keys: Mutex<HashMap<String, Arc<RegistryKeys>>>,
+}
+
+impl SignatureAuditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails when `tarball` does not match the package's integrity or a signature over
+    /// that integrity does not verify. A package without signatures is only warned about.
+    pub async fn audit(
+        &self,
+        client: &reqwest::Client,
+        pkg: &ResolvedPackage,
+        tarball: &[u8],
+    ) -> Result<()> {
+        let key = format!("{}@{}", pkg.name, pkg.version);
+        if pkg.integrity.is_empty() || pkg.signatures.is_empty() {
+            pacm_logger::warn(&format!("{key} has no registry signature"));
+            return Ok(());
+        }
+        if !verify_integrity(tarball, &pkg.integrity) {
+            return Err(PackageManagerError::SignatureInvalid(
+                key,
+                "tarball does not match its signed integrity".to_string(),
+            ));
+        }
+
+        let keys = self.keys_for(client, &pkg.name).await?;
+        match keys.verify(&pkg.name, &pkg.version, &pkg.integrity, &pkg.signatures) {
+            SignatureCheck::Verified => Ok(()),
+            SignatureCheck::Unsigned => {
+                pacm_logger::warn(&format!(
+                    "{key} cannot be verified: its registry publishes no signing keys"
+                ));
+                Ok(())
+            }
+            SignatureCheck::Invalid(reason) => {
+                Err(PackageManagerError::SignatureInvalid(key, reason))
+            }
+        }
+    }
+
+    async fn keys_for(&self, client: &reqwest::Client, name: &str) -> Result<Arc<RegistryKeys>> {
+        let registry = PacmConfig::get().registry_for(name).to_string();
+        let mut keys = self.keys.lock().await;
+        if let Some(registry_keys) = keys.get(&registry) {
+            return Ok(registry_keys.clone());
+        }
+
+        let fetched = pacm_registry::fetch_signing_keys_async(client, &registry)
+            .await
+            .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+        let fetched = Arc::new(fetched);
+        keys.insert(registry, fetched.clone());
+        Ok(fetched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_registry::RegistrySignature;
+    use pacm_testutil::{build_tarball, integrity, resolved_package};
+
+    #[tokio::test]
+    async fn test_rejects_tarball_that_does_not_match_signed_integrity() {
+        let signed = build_tarball(&serde_json::json!({ "name": "audited", "version": "1.0.0" }));
+        let served = build_tarball(&serde_json::json!({ "name": "audited", "version": "6.6.6" }));
+        let pkg = ResolvedPackage {
+            integrity: integrity(&signed),
+            signatures: vec![RegistrySignature {
+                keyid: "SHA256:test".to_string(),
+                sig: "MEUC".to_string(),
+            }],
+            ..resolved_package("audited", "1.0.0", &[])
+        };
+
+        let error = SignatureAuditor::new()
+            .audit(&reqwest::Client::new(), &pkg, &served)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, PackageManagerError::SignatureInvalid(ref key, _) if key == "audited@1.0.0"),
+            "{error}"
+        );
+    }
+}
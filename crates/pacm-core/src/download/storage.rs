@@ -25,19 +25,10 @@ impl PackageStorage {
     }
 
     pub fn check_exists(pkg: &ResolvedPackage, debug: bool) -> Result<Option<PathBuf>> {
-        use pacm_store::get_store_path;
+        use pacm_store::{PathResolver, get_store_path};
 
-        let store_base = get_store_path();
-        let safe_package_name = if pkg.name.starts_with('@') {
-            pkg.name.replace('@', "_at_").replace('/', "_slash_")
-        } else {
-            pkg.name.to_string()
-        };
-
-        let package_path = store_base
-            .join("npm")
-            .join(&safe_package_name)
-            .join(&pkg.version);
+        let package_path =
+            PathResolver::get_package_path(&get_store_path(), &pkg.name, &pkg.version);
 
         if package_path.exists() {
             let package_dir = package_path.join("package");
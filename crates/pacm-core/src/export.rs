@@ -0,0 +1,262 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use serde_json::json;
+
+use pacm_config::{NodeLinker, PacmConfig};
+use pacm_error::{PackageManagerError, Result};
+use pacm_lock::PacmLock;
+use pacm_project::PackageJson;
+
+use crate::import::ForeignLockfile;
+use crate::linker::npm_compat::NpmCompat;
+
+/// Writes `pacm.lock` in the format of another package manager, for tools that still
+/// read `package-lock.json` or `yarn.lock`. Importing the result gives back the same
+/// versions, tarball URLs, integrity and dependency ranges; anything pacm.lock does not
+/// record, like npm's `dev` flags or `bin` entries, is missing from it.
+pub struct LockfileExporter;
+
+impl LockfileExporter {
+    pub fn export(
+        &self,
+        lockfile: &PacmLock,
+        pkg: &PackageJson,
+        format: ForeignLockfile,
+    ) -> Result<String> {
+        match format {
+            ForeignLockfile::Npm => self.npm(lockfile, pkg),
+            ForeignLockfile::Yarn => Ok(self.yarn(lockfile, pkg)),
+        }
+    }
+
+    /// A `package-lock.json` of version 3, with every package hoisted. JSON has no
+    /// comments, so the limitations cannot be noted in the file itself.
+    fn npm(&self, lockfile: &PacmLock, pkg: &PackageJson) -> Result<String> {
+        let name = pkg.name.as_deref().unwrap_or_default();
+        let version = pkg.version.as_deref().unwrap_or_default();
+        let mut document = NpmCompat::lockfile(lockfile, name, version, NodeLinker::Hoisted);
+
+        let mut root = json!({ "name": name, "version": version });
+        let sections = [
+            ("dependencies", &pkg.dependencies),
+            ("devDependencies", &pkg.dev_dependencies),
+            ("optionalDependencies", &pkg.optional_dependencies),
+            ("peerDependencies", &pkg.peer_dependencies),
+        ];
+        for (key, section) in sections {
+            if let Some(section) = section.as_ref().filter(|section| !section.is_empty()) {
+                root[key] = json!(section);
+            }
+        }
+        document["packages"][""] = root;
+
+        serde_json::to_string_pretty(&document)
+            .map(|content| content + "\n")
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))
+    }
+
+    /// A Yarn 1 `yarn.lock`. Each package is listed under every range that leads to it
+    /// from the project or another locked package.
+    fn yarn(&self, lockfile: &PacmLock, pkg: &PackageJson) -> String {
+        let mut specs: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+        let root_ranges = [
+            &pkg.dependencies,
+            &pkg.dev_dependencies,
+            &pkg.optional_dependencies,
+        ]
+        .into_iter()
+        .flatten()
+        .flatten();
+        let locked_ranges = lockfile.packages.values().flat_map(|package| {
+            package
+                .dependencies
+                .iter()
+                .chain(&package.optional_dependencies)
+        });
+        for (name, range) in root_ranges.chain(locked_ranges) {
+            if lockfile.packages.contains_key(name) {
+                specs
+                    .entry(name.as_str())
+                    .or_default()
+                    .insert(format!("{name}@{range}"));
+            }
+        }
+
+        let config = PacmConfig::get();
+        let mut entries: Vec<(String, String)> = Vec::with_capacity(lockfile.packages.len());
+        for (name, package) in &lockfile.packages {
+            let key = match specs.get(name.as_str()) {
+                Some(specs) => specs
+                    .iter()
+                    .map(|spec| yarn_string(spec))
+                    .collect::<Vec<_>>(),
+                None => vec![yarn_string(&format!("{name}@{}", package.version))],
+            }
+            .join(", ");
+
+            let mut entry = String::new();
+            let _ = writeln!(entry, "{key}:");
+            let _ = writeln!(entry, "  version {}", yarn_string(&package.version));
+            let resolved = config.absolute_resolved(name, &package.resolved);
+            if !resolved.is_empty() {
+                let _ = writeln!(entry, "  resolved {}", yarn_string(&resolved));
+            }
+            if !package.integrity.is_empty() {
+                let _ = writeln!(entry, "  integrity {}", yarn_string(&package.integrity));
+            }
+            for (section, deps) in [
+                ("dependencies", &package.dependencies),
+                ("optionalDependencies", &package.optional_dependencies),
+            ] {
+                if deps.is_empty() {
+                    continue;
+                }
+                let _ = writeln!(entry, "  {section}:");
+                for (dep, range) in deps.iter().collect::<BTreeMap<_, _>>() {
+                    let _ = writeln!(entry, "    {} {}", yarn_string(dep), yarn_string(range));
+                }
+            }
+            entries.push((key, entry));
+        }
+        entries.sort();
+
+        let mut content = String::from(
+            "# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.\n\
+             # yarn lockfile v1\n\
+             # Exported from pacm.lock by `pacm export`. pacm.lock holds one version per\n\
+             # package and does not record peer dependencies or the sha1 of each tarball.\n",
+        );
+        for (_, entry) in entries {
+            content.push_str("\n\n");
+            content.push_str(entry.trim_end());
+        }
+        content.push('\n');
+        content
+    }
+}
+
+/// `value` quoted the way Yarn 1 quotes it: unless it starts with a letter and holds
+/// nothing that would be read as syntax.
+fn yarn_string(value: &str) -> String {
+    let plain = value.starts_with(|c: char| c.is_ascii_alphabetic())
+        && !value.starts_with("true")
+        && !value.starts_with("false")
+        && !value.contains(|c: char| c.is_whitespace() || ":\\\",[]".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::LockfileImporter;
+
+    fn project() -> PackageJson {
+        serde_json::from_value(json!({
+            "name": "app",
+            "version": "1.0.0",
+            "dependencies": { "@scope/lib": "^1.0.0", "debug": "^2.6.9" },
+            "devDependencies": { "ms": "^2.0.0" },
+        }))
+        .unwrap()
+    }
+
+    fn lockfile() -> PacmLock {
+        let document = json!({
+            "lockfileVersion": 1,
+            "workspaces": {
+                "": {
+                    "dependencies": { "@scope/lib": "1.2.0", "debug": "2.6.9" },
+                    "dev_dependencies": { "ms": "2.0.0" }
+                }
+            },
+            "packages": {
+                "@scope/lib": {
+                    "version": "1.2.0",
+                    "resolved": "https://registry.npmjs.org/@scope/lib/-/lib-1.2.0.tgz",
+                    "integrity": "sha512-bGliCg==",
+                    "optional_dependencies": { "fsevents": "~2.3.2" }
+                },
+                "debug": {
+                    "version": "2.6.9",
+                    "resolved": "https://registry.npmjs.org/debug/-/debug-2.6.9.tgz",
+                    "integrity": "sha512-ZGVidWc=",
+                    "dependencies": { "ms": "2.0.0" }
+                },
+                "fsevents": {
+                    "version": "2.3.3",
+                    "resolved": "https://registry.npmjs.org/fsevents/-/fsevents-2.3.3.tgz",
+                    "integrity": "sha512-ZnNldmVudHM="
+                },
+                "ms": {
+                    "version": "2.0.0",
+                    "resolved": "https://registry.npmjs.org/ms/-/ms-2.0.0.tgz",
+                    "integrity": "sha512-bXM="
+                }
+            }
+        });
+        serde_json::from_value(document).unwrap()
+    }
+
+    /// The fields both formats carry, by package name.
+    fn common_fields(lockfile: &PacmLock) -> BTreeMap<String, serde_json::Value> {
+        lockfile
+            .packages
+            .iter()
+            .map(|(name, package)| {
+                let fields = json!([
+                    package.version,
+                    package.resolved,
+                    package.integrity,
+                    package.dependencies,
+                    package.optional_dependencies,
+                ]);
+                (name.clone(), fields)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_export_round_trips_through_import() {
+        let original = lockfile();
+        for format in [ForeignLockfile::Npm, ForeignLockfile::Yarn] {
+            let exported = LockfileExporter
+                .export(&original, &project(), format)
+                .unwrap();
+            let imported = LockfileImporter
+                .import(&exported, format, &project())
+                .unwrap();
+
+            assert_eq!(
+                common_fields(&imported.lockfile),
+                common_fields(&original),
+                "{format:?}"
+            );
+            assert_eq!(
+                imported.lockfile.workspaces[""].dev_dependencies["ms"],
+                "2.0.0"
+            );
+            let again = LockfileExporter
+                .export(&imported.lockfile, &project(), format)
+                .unwrap();
+            assert_eq!(again, exported, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_export_yarn_entries() {
+        let exported = LockfileExporter
+            .export(&lockfile(), &project(), ForeignLockfile::Yarn)
+            .unwrap();
+
+        assert!(exported.starts_with("# THIS IS AN AUTOGENERATED FILE"));
+        assert!(exported.contains(
+            "\n\n\"@scope/lib@^1.0.0\":\n  version \"1.2.0\"\n  resolved \"https://registry.npmjs.org/@scope/lib/-/lib-1.2.0.tgz\"\n  integrity sha512-bGliCg==\n  optionalDependencies:\n    fsevents \"~2.3.2\"\n"
+        ));
+        assert!(exported.contains("\n\nms@2.0.0, ms@^2.0.0:\n  version \"2.0.0\"\n"));
+    }
+}
@@ -0,0 +1,188 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use owo_colors::{OwoColorize, Stream::Stdout};
+
+use pacm_config::PacmConfig;
+use pacm_error::{PackageManagerError, Result};
+use pacm_lock::PacmLock;
+use pacm_logger;
+use pacm_project::read_package_json;
+use pacm_store::get_global_path;
+
+/// A package installed with `-g`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GlobalPackage {
+    pub name: String,
+    /// The locked version, or `None` when the lockfile does not know the package.
+    pub version: Option<String>,
+    /// The commands the package provides through its `bin` field.
+    pub bins: Vec<String>,
+}
+
+/// Global packages are installed into a project of their own under `~/.pacm/global`, so
+/// the install, update and remove managers work on it unchanged.
+pub struct GlobalManager;
+
+impl GlobalManager {
+    /// The global prefix, created with an empty `package.json` on first use.
+    pub fn prefix() -> Result<PathBuf> {
+        let prefix = get_global_path();
+        let package_json = prefix.join("package.json");
+        if !package_json.exists() {
+            fs::create_dir_all(&prefix).map_err(|e| {
+                PackageManagerError::IoError(format!(
+                    "Failed to create {}: {}",
+                    prefix.display(),
+                    e
+                ))
+            })?;
+            fs::write(
+                &package_json,
+                r#"{ "name": "pacm-global", "private": true }"#,
+            )
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        }
+        Ok(prefix)
+    }
+
+    pub fn list(&self) -> Result<()> {
+        let prefix = Self::prefix()?;
+        let packages = Self::installed(&prefix)?;
+
+        if packages.is_empty() {
+            pacm_logger::info("No global packages installed");
+            return Ok(());
+        }
+
+        pacm_logger::info(&format!("Global packages ({}):", prefix.display()));
+        for pkg in &packages {
+            let version = pkg.version.as_deref().unwrap_or("not installed");
+            if pkg.bins.is_empty() {
+                println!(
+                    "  {} {}",
+                    pkg.name,
+                    version.if_supports_color(Stdout, |text| text.dimmed())
+                );
+            } else {
+                println!(
+                    "  {} {} {}",
+                    pkg.name,
+                    version.if_supports_color(Stdout, |text| text.dimmed()),
+                    format!("[{}]", pkg.bins.join(", "))
+                        .if_supports_color(Stdout, |text| text.bright_cyan())
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The packages declared in the prefix's `package.json`, with versions from its
+    /// lockfile and bin names from the installed manifests.
+    pub fn installed(prefix: &Path) -> Result<Vec<GlobalPackage>> {
+        let pkg = read_package_json(prefix)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let lockfile = PacmLock::load(&PacmConfig::get().lockfile_path(prefix)).ok();
+
+        let mut names: Vec<String> = pkg.get_all_dependencies().into_keys().collect();
+        names.sort();
+
+        Ok(names
+            .into_iter()
+            .map(|name| GlobalPackage {
+                version: lockfile
+                    .as_ref()
+                    .and_then(|lock| lock.get_package(&name))
+                    .map(|locked| locked.version.clone()),
+                bins: Self::bin_names(&prefix.join("node_modules").join(&name), &name),
+                name,
+            })
+            .collect())
+    }
+
+    /// Command names from the `bin` field. A plain string names the command after the
+    /// package, without its scope.
+    fn bin_names(package_dir: &Path, name: &str) -> Vec<String> {
+        let Some(manifest) = fs::read_to_string(package_dir.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        else {
+            return Vec::new();
+        };
+
+        match manifest.get("bin") {
+            Some(serde_json::Value::String(_)) => {
+                vec![name.rsplit('/').next().unwrap_or(name).to_string()]
+            }
+            Some(serde_json::Value::Object(bins)) => {
+                let mut bins: Vec<String> = bins.keys().cloned().collect();
+                bins.sort();
+                bins
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_installed_reads_versions_and_bins() {
+        let prefix = tempfile::tempdir().unwrap();
+        let root = prefix.path();
+        write(
+            &root.join("package.json"),
+            r#"{ "dependencies": { "@scope/cli": "^2.0.0", "tools": "^1.0.0", "lib": "^3.0.0" } }"#,
+        );
+        write(
+            &PacmConfig::get().lockfile_path(root),
+            r#"{
+                "lockfileVersion": 1,
+                "workspaces": {},
+                "packages": {
+                    "@scope/cli": { "version": "2.1.0", "resolved": "", "integrity": "" },
+                    "tools": { "version": "1.0.4", "resolved": "", "integrity": "" }
+                }
+            }"#,
+        );
+        write(
+            &root.join("node_modules/@scope/cli/package.json"),
+            r#"{ "name": "@scope/cli", "bin": "./cli.js" }"#,
+        );
+        write(
+            &root.join("node_modules/tools/package.json"),
+            r#"{ "name": "tools", "bin": { "tool-b": "b.js", "tool-a": "a.js" } }"#,
+        );
+
+        let installed = GlobalManager::installed(root).unwrap();
+
+        assert_eq!(
+            installed,
+            vec![
+                GlobalPackage {
+                    name: "@scope/cli".to_string(),
+                    version: Some("2.1.0".to_string()),
+                    bins: vec!["cli".to_string()],
+                },
+                GlobalPackage {
+                    name: "lib".to_string(),
+                    version: None,
+                    bins: Vec::new(),
+                },
+                GlobalPackage {
+                    name: "tools".to_string(),
+                    version: Some("1.0.4".to_string()),
+                    bins: vec!["tool-a".to_string(), "tool-b".to_string()],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,601 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use pacm_config::PacmConfig;
+use pacm_error::{PackageManagerError, Result};
+use pacm_lock::{LockPackage, PacmLock};
+use pacm_project::{DependencySelection, DependencyType, PackageJson};
+
+/// A lockfile of another package manager that `pacm import` reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForeignLockfile {
+    /// `package-lock.json` or `npm-shrinkwrap.json`, lockfile version 2 or 3.
+    Npm,
+    /// `yarn.lock` written by Yarn 1.
+    Yarn,
+}
+
+impl ForeignLockfile {
+    /// The lockfiles looked for in a project, in order of preference.
+    const FILES: [(&'static str, ForeignLockfile); 3] = [
+        ("npm-shrinkwrap.json", ForeignLockfile::Npm),
+        ("package-lock.json", ForeignLockfile::Npm),
+        ("yarn.lock", ForeignLockfile::Yarn),
+    ];
+
+    /// The first lockfile of another package manager found in `project_dir`.
+    #[must_use]
+    pub fn detect(project_dir: &Path) -> Option<(PathBuf, Self)> {
+        Self::FILES
+            .iter()
+            .map(|(file, format)| (project_dir.join(file), *format))
+            .find(|(path, _)| path.is_file())
+    }
+
+    /// The format of the lockfile at `path`, judged by its file name.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?;
+        Self::FILES
+            .iter()
+            .find(|(file, _)| *file == file_name)
+            .map(|(_, format)| *format)
+    }
+}
+
+/// `pacm.lock` as seeded from another lockfile, with what could not be carried over.
+#[derive(Debug)]
+pub struct ImportedLock {
+    pub lockfile: PacmLock,
+    pub warnings: Vec<String>,
+}
+
+/// One locked version of a package as the other lockfile describes it.
+struct Candidate {
+    name: String,
+    package: LockPackage,
+    /// Whether npm installed it at the top of `node_modules`.
+    top_level: bool,
+    /// The `name@range` specs Yarn resolved to it.
+    specs: Vec<String>,
+}
+
+/// Fields of the other lockfiles that end up in `pacm.lock`.
+const NPM_FIELDS: [&str; 8] = [
+    "name",
+    "version",
+    "resolved",
+    "integrity",
+    "dependencies",
+    "optionalDependencies",
+    "peerDependencies",
+    "peerDependenciesMeta",
+];
+const YARN_FIELDS: [&str; 5] = [
+    "version",
+    "resolved",
+    "integrity",
+    "dependencies",
+    "optionalDependencies",
+];
+
+/// Translates npm and Yarn 1 lockfiles into a `pacm.lock`, so the first install after
+/// switching to pacm installs the versions the project already had. `pacm.lock` holds one
+/// version per package: the one npm hoisted, or for Yarn the one the project's own range
+/// resolved to, else the highest.
+pub struct LockfileImporter;
+
+impl LockfileImporter {
+    pub fn import(
+        &self,
+        content: &str,
+        format: ForeignLockfile,
+        pkg: &PackageJson,
+    ) -> Result<ImportedLock> {
+        let mut warnings = Vec::new();
+        let mut dropped_fields = BTreeSet::new();
+        let candidates = match format {
+            ForeignLockfile::Npm => parse_npm(content, &mut dropped_fields, &mut warnings)?,
+            ForeignLockfile::Yarn => parse_yarn(content, &mut dropped_fields, &mut warnings)?,
+        };
+        if !dropped_fields.is_empty() {
+            warnings.push(format!(
+                "Dropped fields pacm.lock does not record: {}",
+                dropped_fields.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+
+        let declared = pkg.get_dependencies(DependencySelection::default());
+        let mut by_name: HashMap<String, Vec<Candidate>> = HashMap::new();
+        for candidate in candidates {
+            by_name
+                .entry(candidate.name.clone())
+                .or_default()
+                .push(candidate);
+        }
+
+        let mut lockfile = PacmLock::default();
+        let mut dropped_versions = Vec::new();
+        for (name, mut versions) in by_name {
+            let root_spec = declared.get(&name).map(|range| format!("{name}@{range}"));
+            let kept = versions
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, candidate)| {
+                    (
+                        candidate.top_level,
+                        root_spec
+                            .as_ref()
+                            .is_some_and(|spec| candidate.specs.contains(spec)),
+                        semver::Version::parse(&candidate.package.version).ok(),
+                    )
+                })
+                .map(|(index, _)| index)
+                .unwrap_or_default();
+            let kept = versions.swap_remove(kept);
+            for other in versions {
+                if other.package.version != kept.package.version {
+                    dropped_versions.push(format!(
+                        "{}@{} (kept {})",
+                        name, other.package.version, kept.package.version
+                    ));
+                }
+            }
+            lockfile.update_package(&name, kept.package);
+        }
+        if !dropped_versions.is_empty() {
+            dropped_versions.sort();
+            dropped_versions.dedup();
+            warnings.push(format!(
+                "pacm.lock keeps one version per package; dropped {}",
+                dropped_versions.join(", ")
+            ));
+        }
+
+        let sections = [
+            (DependencyType::Dependencies, &pkg.dependencies),
+            (DependencyType::DevDependencies, &pkg.dev_dependencies),
+            (
+                DependencyType::OptionalDependencies,
+                &pkg.optional_dependencies,
+            ),
+        ];
+        for (dep_type, section) in sections {
+            let locked: HashMap<String, String> = section
+                .iter()
+                .flatten()
+                .filter_map(|(name, _)| {
+                    let package = lockfile.get_package(name)?;
+                    Some((name.clone(), package.version.clone()))
+                })
+                .collect();
+            lockfile.update_workspace_deps("", &locked, dep_type.as_str());
+        }
+
+        Ok(ImportedLock { lockfile, warnings })
+    }
+}
+
+/// The `resolved` entry to lock for `url`, or `None` for sources pacm.lock cannot
+/// point at, like git repositories.
+fn lock_resolved(name: &str, url: &str) -> Option<String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        // Yarn appends the tarball's SHA-1 as a fragment.
+        let url = url.split('#').next().unwrap_or(url);
+        Some(PacmConfig::get().lock_resolved(name, url))
+    } else if url.starts_with("file:") || url.is_empty() {
+        Some(url.to_string())
+    } else {
+        None
+    }
+}
+
+fn string_map(value: Option<&serde_json::Value>) -> HashMap<String, String> {
+    value
+        .and_then(serde_json::Value::as_object)
+        .map(|map| {
+            map.iter()
+                .filter_map(|(name, range)| Some((name.clone(), range.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_npm(
+    content: &str,
+    dropped_fields: &mut BTreeSet<String>,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Candidate>> {
+    let document: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| PackageManagerError::LockfileError(format!("Invalid npm lockfile: {e}")))?;
+    let version = document["lockfileVersion"].as_u64().unwrap_or_default();
+    let Some(packages) = document["packages"].as_object().filter(|_| version >= 2) else {
+        return Err(PackageManagerError::LockfileError(format!(
+            "npm lockfile version {version} cannot be imported; run npm install with npm 7 or later to upgrade it to version 3"
+        )));
+    };
+
+    let mut candidates = Vec::new();
+    for (path, entry) in packages {
+        // The root project, and workspace members outside node_modules.
+        let Some(installed) = path.rsplit_once("node_modules/").map(|(_, name)| name) else {
+            continue;
+        };
+        let name = entry["name"].as_str().unwrap_or(installed);
+        if entry["link"].as_bool() == Some(true) {
+            warnings.push(format!(
+                "{installed} links to {}; installs link it again",
+                entry["resolved"].as_str().unwrap_or("a local directory")
+            ));
+            continue;
+        }
+        if name != installed {
+            warnings.push(format!(
+                "{installed} is an alias of {name}; installs resolve it again"
+            ));
+            continue;
+        }
+        let resolved = entry["resolved"].as_str().unwrap_or_default();
+        let Some(resolved) = lock_resolved(name, resolved) else {
+            warnings.push(format!(
+                "{name} comes from {resolved}; installs resolve it again"
+            ));
+            continue;
+        };
+
+        if let Some(fields) = entry.as_object() {
+            dropped_fields.extend(
+                fields
+                    .keys()
+                    .filter(|field| !NPM_FIELDS.contains(&field.as_str()))
+                    .cloned(),
+            );
+        }
+        let optional_peers: BTreeSet<String> = entry["peerDependenciesMeta"]
+            .as_object()
+            .map(|meta| {
+                meta.iter()
+                    .filter(|(_, meta)| meta["optional"].as_bool() == Some(true))
+                    .map(|(peer, _)| peer.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let (optional_peer_dependencies, peer_dependencies) =
+            string_map(entry.get("peerDependencies"))
+                .into_iter()
+                .partition(|(peer, _)| optional_peers.contains(peer));
+
+        candidates.push(Candidate {
+            name: name.to_string(),
+            package: LockPackage {
+                version: entry["version"].as_str().unwrap_or_default().to_string(),
+                resolved,
+                integrity: entry["integrity"].as_str().unwrap_or_default().to_string(),
+                dependencies: string_map(entry.get("dependencies")),
+                optional_dependencies: string_map(entry.get("optionalDependencies")),
+                peer_dependencies,
+                optional_peer_dependencies,
+            },
+            top_level: path == &format!("node_modules/{name}"),
+            specs: Vec::new(),
+        });
+    }
+    Ok(candidates)
+}
+
+/// The name in a Yarn spec such as `@babel/core@^7.0.0`.
+fn spec_name(spec: &str) -> &str {
+    spec.get(1..)
+        .and_then(|rest| rest.find('@'))
+        .map_or(spec, |at| &spec[..at + 1])
+}
+
+fn unquote(text: &str) -> &str {
+    text.trim().trim_matches('"')
+}
+
+/// Splits `key value` lines, where either side may be quoted.
+fn split_pair(text: &str) -> (&str, &str) {
+    let split = if let Some(rest) = text.strip_prefix('"') {
+        rest.find('"').map(|end| end + 2)
+    } else {
+        text.find(' ')
+    };
+    match split {
+        Some(at) if at <= text.len() => (unquote(&text[..at]), unquote(&text[at..])),
+        _ => (unquote(text), ""),
+    }
+}
+
+fn parse_yarn(
+    content: &str,
+    dropped_fields: &mut BTreeSet<String>,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Candidate>> {
+    if content.contains("\n__metadata:") {
+        return Err(PackageManagerError::LockfileError(
+            "yarn.lock was written by Yarn 2 or later; only Yarn 1 lockfiles can be imported"
+                .to_string(),
+        ));
+    }
+
+    struct Entry {
+        specs: Vec<String>,
+        fields: HashMap<String, String>,
+        dependencies: HashMap<String, String>,
+        optional_dependencies: HashMap<String, String>,
+    }
+
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut section: Option<String> = None;
+    for (number, line) in content.lines().enumerate() {
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        if indent == 0 {
+            entries.push(Entry {
+                specs: text
+                    .trim_end_matches(':')
+                    .split(", ")
+                    .map(|spec| unquote(spec).to_string())
+                    .collect(),
+                fields: HashMap::new(),
+                dependencies: HashMap::new(),
+                optional_dependencies: HashMap::new(),
+            });
+            section = None;
+            continue;
+        }
+
+        let Some(entry) = entries.last_mut() else {
+            return Err(PackageManagerError::LockfileError(format!(
+                "yarn.lock line {}: field outside of a package entry",
+                number + 1
+            )));
+        };
+        if indent <= 2 {
+            if let Some(name) = text.strip_suffix(':') {
+                section = Some(name.to_string());
+            } else {
+                section = None;
+                let (key, value) = split_pair(text);
+                entry.fields.insert(key.to_string(), value.to_string());
+            }
+            continue;
+        }
+
+        let (name, range) = split_pair(text);
+        match section.as_deref() {
+            Some("dependencies") => {
+                entry
+                    .dependencies
+                    .insert(name.to_string(), range.to_string());
+            }
+            Some("optionalDependencies") => {
+                entry
+                    .optional_dependencies
+                    .insert(name.to_string(), range.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for entry in entries {
+        let Some(spec) = entry.specs.first() else {
+            continue;
+        };
+        let name = spec_name(spec);
+        let range = spec[name.len()..].trim_start_matches('@');
+        if range.starts_with("npm:") {
+            warnings.push(format!(
+                "{name} is an alias ({range}); installs resolve it again"
+            ));
+            continue;
+        }
+        let resolved = entry.fields.get("resolved").map_or("", String::as_str);
+        let Some(resolved) = lock_resolved(name, resolved) else {
+            warnings.push(format!(
+                "{name} comes from {resolved}; installs resolve it again"
+            ));
+            continue;
+        };
+
+        dropped_fields.extend(
+            entry
+                .fields
+                .keys()
+                .filter(|field| !YARN_FIELDS.contains(&field.as_str()))
+                .cloned(),
+        );
+        candidates.push(Candidate {
+            name: name.to_string(),
+            package: LockPackage {
+                version: entry.fields.get("version").cloned().unwrap_or_default(),
+                resolved,
+                integrity: entry.fields.get("integrity").cloned().unwrap_or_default(),
+                dependencies: entry.dependencies,
+                optional_dependencies: entry.optional_dependencies,
+                peer_dependencies: HashMap::new(),
+                optional_peer_dependencies: HashMap::new(),
+            },
+            top_level: false,
+            specs: entry.specs,
+        });
+    }
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> PackageJson {
+        serde_json::from_value(serde_json::json!({
+            "name": "app",
+            "version": "1.0.0",
+            "dependencies": { "debug": "^2.6.9", "chalk": "^4.1.0" },
+            "devDependencies": { "ms": "^2.1.3" },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_import_npm_lockfile_v3() {
+        let content = r#"{
+  "name": "app",
+  "version": "1.0.0",
+  "lockfileVersion": 3,
+  "requires": true,
+  "packages": {
+    "": {
+      "name": "app",
+      "version": "1.0.0",
+      "dependencies": { "chalk": "^4.1.0", "debug": "^2.6.9" },
+      "devDependencies": { "ms": "^2.1.3" }
+    },
+    "node_modules/chalk": {
+      "version": "4.1.2",
+      "resolved": "https://registry.npmjs.org/chalk/-/chalk-4.1.2.tgz",
+      "integrity": "sha512-oKnbhFyRIXpUuez8iBMmyEa4nbj4IOQyuhc/wy9kY7/WVPcwIO9VA668Pu8RkO7+0G76SLROeyw9CpQ061i4mA==",
+      "license": "MIT",
+      "dependencies": { "supports-color": "^7.1.0" },
+      "engines": { "node": ">=10" }
+    },
+    "node_modules/debug": {
+      "version": "2.6.9",
+      "resolved": "https://registry.npmjs.org/debug/-/debug-2.6.9.tgz",
+      "integrity": "sha512-bC7ElrdJaJnPbAP+1EotYvqZsb3ecl5wi6Bfi6BJTUcNowp6cvspg0jXznRTKDjm/E7AdgFBVeAPVMNcKGsHMA==",
+      "dependencies": { "ms": "2.0.0" }
+    },
+    "node_modules/debug/node_modules/ms": {
+      "version": "2.0.0",
+      "resolved": "https://registry.npmjs.org/ms/-/ms-2.0.0.tgz",
+      "integrity": "sha512-Tpp60P6IUJDTuOq/5Z8cdskzJujfwqfOTkrwIwj7IRISpnkJnT6SyJ4PCPnGMoFjC9ddhal5KVIYtAt97ix05A=="
+    },
+    "node_modules/ms": {
+      "version": "2.1.3",
+      "resolved": "https://registry.npmjs.org/ms/-/ms-2.1.3.tgz",
+      "integrity": "sha512-6FlzubTLZG3J2a/NVCAleEhjzq5oxgHyaCU9yYXvcLsvoVaHJq/s5xXI6/XXP6tz7R9xAOtHnSO/tXtF3WRTlA==",
+      "dev": true
+    },
+    "node_modules/supports-color": {
+      "version": "7.2.0",
+      "resolved": "https://registry.npmjs.org/supports-color/-/supports-color-7.2.0.tgz",
+      "integrity": "sha512-qpCAvRl9stuOHveKsn7HncJRvv501qIacKzQlO/+Lwxc9+0q2wLyv4Dfvt80/DPn2pqOBsJdDiogXGR9+OvwRw==",
+      "dependencies": { "has-flag": "^4.0.0" }
+    },
+    "node_modules/has-flag": {
+      "version": "4.0.0",
+      "resolved": "https://registry.npmjs.org/has-flag/-/has-flag-4.0.0.tgz",
+      "integrity": "sha512-EykJT/Q1KjTWctppgIAgfSO0tKVuZUjhgMr17kqTumMl6Afv3EISleU7qZUzoXDFTAHTDC4NOoG/ZxU3EvlMPQ=="
+    }
+  }
+}"#;
+        let imported = LockfileImporter
+            .import(content, ForeignLockfile::Npm, &project())
+            .unwrap();
+        let lockfile = &imported.lockfile;
+
+        assert_eq!(lockfile.packages.len(), 5);
+        let chalk = lockfile.get_package("chalk").unwrap();
+        assert_eq!(chalk.version, "4.1.2");
+        assert!(chalk.integrity.starts_with("sha512-oKnbhFy"));
+        assert_eq!(chalk.dependencies["supports-color"], "^7.1.0");
+        // The hoisted ms wins over the copy nested under debug.
+        assert_eq!(lockfile.get_package("ms").unwrap().version, "2.1.3");
+        let root = &lockfile.workspaces[""];
+        assert_eq!(root.dependencies["debug"], "2.6.9");
+        assert_eq!(root.dev_dependencies["ms"], "2.1.3");
+
+        assert_eq!(
+            imported.warnings,
+            [
+                "Dropped fields pacm.lock does not record: dev, engines, license",
+                "pacm.lock keeps one version per package; dropped ms@2.0.0 (kept 2.1.3)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_yarn_v1_lockfile() {
+        let content = r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@babel/code-frame@^7.0.0":
+  version "7.12.13"
+  resolved "https://registry.yarnpkg.com/@babel/code-frame/-/code-frame-7.12.13.tgz#dcfc826beef65e75c50e21d3837d7d95798dd658"
+  integrity sha512-HV1Cm0Q3ZrpCR93tkWOYiuYIgLxZXZFVG2VgK+MBWjUqZTundupbfx2aXarXuw5Ko5aMcjtJgbSs4vUGBS5v6g==
+  dependencies:
+    "@babel/highlight" "^7.12.13"
+
+chalk@^4.1.0:
+  version "4.1.2"
+  resolved "https://registry.yarnpkg.com/chalk/-/chalk-4.1.2.tgz#d957f370038b75ac572471e83be4c5ca9f8e8c45"
+  integrity sha512-oKnbhFyRIXpUuez8iBMmyEa4nbj4IOQyuhc/wy9kY7/WVPcwIO9VA668Pu8RkO7+0G76SLROeyw9CpQ061i4mA==
+  dependencies:
+    ansi-styles "^4.1.0"
+    supports-color "^7.1.0"
+
+debug@^2.6.9:
+  version "2.6.9"
+  resolved "https://registry.yarnpkg.com/debug/-/debug-2.6.9.tgz#5d128515df134ff327e90a4c93f4e077a536341f"
+  integrity sha512-bC7ElrdJaJnPbAP+1EotYvqZsb3ecl5wi6Bfi6BJTUcNowp6cvspg0jXznRTKDjm/E7AdgFBVeAPVMNcKGsHMA==
+  dependencies:
+    ms "2.0.0"
+
+ms@2.0.0:
+  version "2.0.0"
+  resolved "https://registry.yarnpkg.com/ms/-/ms-2.0.0.tgz#5608aeadfc00be6c2901df5f9861788de0d597c8"
+  integrity sha512-Tpp60P6IUJDTuOq/5Z8cdskzJujfwqfOTkrwIwj7IRISpnkJnT6SyJ4PCPnGMoFjC9ddhal5KVIYtAt97ix05A==
+
+ms@^2.1.1, ms@^2.1.3:
+  version "2.1.3"
+  resolved "https://registry.yarnpkg.com/ms/-/ms-2.1.3.tgz#574c8138ce1d2b5861f0b44579dbadd60c6615b2"
+  integrity sha512-6FlzubTLZG3J2a/NVCAleEhjzq5oxgHyaCU9yYXvcLsvoVaHJq/s5xXI6/XXP6tz7R9xAOtHnSO/tXtF3WRTlA==
+
+left-pad@^1.3.0:
+  version "1.3.0"
+  resolved "git+https://github.com/stevemao/left-pad.git#5ab8e6c8a4d7f0b3e5a7c4b8c9e4a1f3d2b6c8a7"
+"#;
+        let imported = LockfileImporter
+            .import(content, ForeignLockfile::Yarn, &project())
+            .unwrap();
+        let lockfile = &imported.lockfile;
+
+        assert_eq!(lockfile.packages.len(), 4);
+        let code_frame = lockfile.get_package("@babel/code-frame").unwrap();
+        assert_eq!(code_frame.version, "7.12.13");
+        assert_eq!(
+            code_frame.resolved,
+            "https://registry.yarnpkg.com/@babel/code-frame/-/code-frame-7.12.13.tgz"
+        );
+        assert_eq!(code_frame.dependencies["@babel/highlight"], "^7.12.13");
+        assert_eq!(lockfile.get_package("chalk").unwrap().dependencies.len(), 2);
+        // The version the project's own range resolved to wins.
+        assert_eq!(lockfile.get_package("ms").unwrap().version, "2.1.3");
+        assert_eq!(lockfile.workspaces[""].dependencies["chalk"], "4.1.2");
+
+        assert_eq!(
+            imported.warnings,
+            [
+                "left-pad comes from git+https://github.com/stevemao/left-pad.git#5ab8e6c8a4d7f0b3e5a7c4b8c9e4a1f3d2b6c8a7; installs resolve it again",
+                "pacm.lock keeps one version per package; dropped ms@2.0.0 (kept 2.1.3)",
+            ]
+        );
+
+        let berry = "__metadata:\n  version: 6\n";
+        assert!(
+            LockfileImporter
+                .import(
+                    &format!("# yarn\n{berry}"),
+                    ForeignLockfile::Yarn,
+                    &project()
+                )
+                .is_err()
+        );
+    }
+}
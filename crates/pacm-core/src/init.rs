@@ -3,13 +3,54 @@ use std::path::Path;
 
 use pacm_error::{PackageManagerError, Result};
 use pacm_logger;
-use pacm_project::PackageJson;
+use pacm_project::{PackageJson, read_package_json};
 
-pub struct InitManager;
+/// What `pacm init` does with a `package.json` that is already there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExistingPackageJson {
+    /// Fail with [`PackageManagerError::PackageJsonExists`].
+    #[default]
+    Refuse,
+    /// Replace it with a fresh one.
+    Overwrite,
+    /// Keep every field it has and add the ones a fresh `package.json` would have.
+    Merge,
+}
+
+#[derive(Default)]
+pub struct InitManager {
+    force: bool,
+    merge: bool,
+}
 
 impl InitManager {
     pub fn new() -> Self {
-        InitManager
+        Self::default()
+    }
+
+    /// Overwrites an existing `package.json` instead of refusing to, for `--force`.
+    #[must_use]
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Completes an existing `package.json` with the fields it lacks instead of refusing
+    /// to touch it, for `--merge`. `--force` wins over it.
+    #[must_use]
+    pub fn with_merge(mut self, merge: bool) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    fn existing(&self) -> ExistingPackageJson {
+        if self.force {
+            ExistingPackageJson::Overwrite
+        } else if self.merge {
+            ExistingPackageJson::Merge
+        } else {
+            ExistingPackageJson::Refuse
+        }
     }
 
     pub fn init_project(
@@ -19,15 +60,50 @@ impl InitManager {
         description: Option<&str>,
         version: Option<&str>,
         license: Option<&str>,
+    ) -> Result<()> {
+        self.init_project_with(
+            project_dir,
+            name,
+            description,
+            version,
+            license,
+            self.existing(),
+        )
+    }
+
+    /// Like [`InitManager::init_project`], with `existing` deciding what happens to a
+    /// `package.json` that is already there.
+    pub fn init_project_with(
+        &self,
+        project_dir: &str,
+        name: &str,
+        description: Option<&str>,
+        version: Option<&str>,
+        license: Option<&str>,
+        existing: ExistingPackageJson,
     ) -> Result<()> {
         let project_path = Path::new(project_dir);
         let package_json_path = project_path.join("package.json");
 
-        if package_json_path.exists() {
-            return Err(PackageManagerError::PackageJsonExists(
-                package_json_path.to_string_lossy().into_owned(),
-            ));
-        }
+        let current = if package_json_path.exists() {
+            match existing {
+                ExistingPackageJson::Refuse => {
+                    return Err(PackageManagerError::PackageJsonExists(
+                        package_json_path.to_string_lossy().into_owned(),
+                    ));
+                }
+                ExistingPackageJson::Overwrite => {
+                    pacm_logger::warn("Overwriting the existing package.json");
+                    None
+                }
+                ExistingPackageJson::Merge => Some(
+                    read_package_json(project_path)
+                        .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?,
+                ),
+            }
+        } else {
+            None
+        };
 
         pacm_logger::status("Initializing new package...");
 
@@ -69,6 +145,18 @@ impl InitManager {
             },
         };
 
+        let package_json = match current {
+            Some(current) => {
+                pacm_logger::info("Adding missing fields to the existing package.json");
+                merge_missing(current, package_json)
+            }
+            None => package_json,
+        };
+        let name = package_json
+            .name
+            .clone()
+            .unwrap_or_else(|| name.to_string());
+
         package_json
             .save(&package_json_path)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
@@ -82,7 +170,7 @@ impl InitManager {
         ));
 
         // Show next steps
-        self.show_next_steps(name)?;
+        self.show_next_steps(&name)?;
 
         Ok(())
     }
@@ -117,12 +205,13 @@ impl InitManager {
             .and_then(|n| n.to_str())
             .unwrap_or("my-package");
 
-        self.init_project(
+        self.init_project_with(
             project_dir,
             dir_name,
             Some("A new package"),
             Some("1.0.0"),
             Some("ISC"),
+            self.existing(),
         )
     }
 
@@ -163,20 +252,27 @@ impl InitManager {
     }
 
     fn show_next_steps(&self, _package_name: &str) -> Result<()> {
-        use owo_colors::OwoColorize;
+        use owo_colors::{OwoColorize, Stream::Stdout, Style};
 
         println!();
-        println!("{}", "Next steps:".bold().green());
+        println!(
+            "{}",
+            "Next steps:".if_supports_color(Stdout, |text| text.style(Style::new().bold().green()))
+        );
         println!(
             "  {} Install dependencies: {}",
-            "1.".cyan(),
-            "pacm install".yellow()
+            "1.".if_supports_color(Stdout, |text| text.cyan()),
+            "pacm install".if_supports_color(Stdout, |text| text.yellow())
+        );
+        println!(
+            "  {} Start developing: {}",
+            "2.".if_supports_color(Stdout, |text| text.cyan()),
+            "code .".if_supports_color(Stdout, |text| text.yellow())
         );
-        println!("  {} Start developing: {}", "2.".cyan(), "code .".yellow());
         println!(
             "  {} Run your package: {}",
-            "3.".cyan(),
-            "pacm start".yellow()
+            "3.".if_supports_color(Stdout, |text| text.cyan()),
+            "pacm start".if_supports_color(Stdout, |text| text.yellow())
         );
         println!();
         println!("Happy coding! 🚀");
@@ -186,6 +282,27 @@ impl InitManager {
     }
 }
 
+/// `current` with every field it lacks taken from `fresh`. Fields `current` already has
+/// are kept as they are, even when empty.
+fn merge_missing(mut current: PackageJson, fresh: PackageJson) -> PackageJson {
+    current.name = current.name.or(fresh.name);
+    current.version = current.version.or(fresh.version);
+    current.description = current.description.or(fresh.description);
+    current.license = current.license.or(fresh.license);
+    current.main = current.main.or(fresh.main);
+    current.scripts = current.scripts.or(fresh.scripts);
+    current.dependencies = current.dependencies.or(fresh.dependencies);
+    current.dev_dependencies = current.dev_dependencies.or(fresh.dev_dependencies);
+    current.peer_dependencies = current.peer_dependencies.or(fresh.peer_dependencies);
+    current.optional_dependencies = current
+        .optional_dependencies
+        .or(fresh.optional_dependencies);
+    for (key, value) in fresh.other {
+        current.other.entry(key).or_insert(value);
+    }
+    current
+}
+
 pub fn init_project(
     project_dir: &str,
     name: &str,
@@ -196,3 +313,100 @@ pub fn init_project(
     let manager = InitManager::new();
     manager.init_project(project_dir, name, description, version, license)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn project(manifest: &serde_json::Value) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("package.json"), manifest.to_string()).unwrap();
+        dir
+    }
+
+    fn package_json(dir: &tempfile::TempDir) -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("package.json")).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_existing_package_json_is_kept_without_force() {
+        let dir = project(&json!({ "name": "kept" }));
+        let error = InitManager::new()
+            .init_project(dir.path().to_str().unwrap(), "new", None, None, None)
+            .unwrap_err();
+
+        assert!(matches!(error, PackageManagerError::PackageJsonExists(_)));
+        assert_eq!(package_json(&dir), json!({ "name": "kept" }));
+    }
+
+    #[test]
+    fn test_force_overwrites_existing_package_json() {
+        let dir = project(&json!({ "name": "old", "private": true }));
+        InitManager::new()
+            .with_force(true)
+            .init_project(dir.path().to_str().unwrap(), "new", None, None, None)
+            .unwrap();
+
+        let written = package_json(&dir);
+        assert_eq!(written["name"], "new");
+        assert_eq!(written["version"], "1.0.0");
+        assert!(written.get("private").is_none());
+    }
+
+    #[test]
+    fn test_interactive_init_refuses_existing_package_json_unless_merging() {
+        let dir = project(&json!({ "name": "kept", "private": true }));
+        let project_dir = dir.path().to_str().unwrap();
+
+        let error = InitManager::new()
+            .init_interactive(project_dir, false)
+            .unwrap_err();
+        assert!(matches!(error, PackageManagerError::PackageJsonExists(_)));
+        assert_eq!(
+            package_json(&dir),
+            json!({ "name": "kept", "private": true })
+        );
+
+        InitManager::new()
+            .with_merge(true)
+            .init_interactive(project_dir, false)
+            .unwrap();
+        let written = package_json(&dir);
+        assert_eq!(written["name"], "kept");
+        assert_eq!(written["private"], true);
+        assert_eq!(written["main"], "index.js");
+    }
+
+    #[test]
+    fn test_merge_fills_in_missing_fields_only() {
+        let dir = project(&json!({
+            "name": "merged",
+            "version": "0.3.0",
+            "description": "",
+            "scripts": { "dev": "vite" },
+            "private": true,
+        }));
+        InitManager::new()
+            .init_project_with(
+                dir.path().to_str().unwrap(),
+                "ignored",
+                Some("A new package"),
+                None,
+                None,
+                ExistingPackageJson::Merge,
+            )
+            .unwrap();
+
+        let written = package_json(&dir);
+        assert_eq!(written["name"], "merged");
+        assert_eq!(written["version"], "0.3.0");
+        assert_eq!(written["description"], "");
+        assert_eq!(written["scripts"], json!({ "dev": "vite" }));
+        assert_eq!(written["private"], true);
+        assert_eq!(written["license"], "ISC");
+        assert_eq!(written["main"], "index.js");
+        assert_eq!(written["keywords"], json!([]));
+    }
+}
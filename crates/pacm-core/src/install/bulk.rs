@@ -1,71 +1,401 @@
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use super::cache::CacheManager;
+use super::dedupe::dedupe;
+use super::exports::warn_broken_entry_points;
+use super::journal::{InstallJournal, Stage};
+use super::optional::optional_only;
+use super::peers::PeerResolver;
+use super::resolution_cache::ResolutionCache;
 use super::resolver::DependencyResolver;
 use super::smart_analyzer::{PackageComplexity, SmartDependencyAnalyzer};
+use super::tarball::TarballInstaller;
 use super::types::CachedPackage;
+use super::utils::InstallUtils;
+use super::workspaces::WorkspaceInstall;
 use crate::download::PackageDownloader;
 use crate::linker::PackageLinker;
+use crate::linker::lockfile::LockfileManager;
+use crate::linker::npm_compat::NpmCompat;
+use crate::timing;
+use pacm_config::PacmConfig;
 use pacm_error::{PackageManagerError, Result};
-use pacm_lock::PacmLock;
+use pacm_lock::{PacmLock, WorkspaceInfo};
 use pacm_logger;
-use pacm_project::read_package_json;
-use pacm_resolver::{ResolvedPackage, is_platform_compatible};
+use pacm_project::{DependencyManager, DependencySelection, DependencyType, read_package_json};
+use pacm_resolver::semver::parse_npm_semver_ranges;
+use pacm_resolver::{
+    Overrides, ResolutionLimits, ResolvedPackage, is_platform_compatible, satisfies,
+};
+use pacm_store::{PathResolver, get_store_path};
+use tokio_util::sync::CancellationToken;
 
 pub struct BulkInstaller {
     downloader: PackageDownloader,
     linker: PackageLinker,
     cache: CacheManager,
     resolver: DependencyResolver,
+    resolutions: ResolutionCache,
     smart_analyzer: SmartDependencyAnalyzer,
+    tarball_installer: TarballInstaller,
+    selection: DependencySelection,
+    max_depth: Option<usize>,
+    install_peers: bool,
+    dedupe: bool,
+    verify_exports: bool,
+    npm_compat: bool,
+    lockfile: bool,
+    frozen_lockfile: bool,
 }
 
 impl BulkInstaller {
     pub fn new() -> Self {
+        Self::with_selection(DependencySelection::default())
+    }
+
+    pub fn with_selection(selection: DependencySelection) -> Self {
         let cache = CacheManager::new();
         let smart_analyzer = SmartDependencyAnalyzer::new(cache.clone());
 
         Self {
             downloader: PackageDownloader::new(),
-            linker: PackageLinker {},
+            linker: PackageLinker::new(),
             cache,
-            resolver: DependencyResolver::new(),
+            resolver: DependencyResolver::new().with_optional(selection.optional),
+            resolutions: ResolutionCache::new(),
             smart_analyzer,
+            tarball_installer: TarballInstaller::new(),
+            selection,
+            max_depth: None,
+            install_peers: PacmConfig::get().install_peers(),
+            dedupe: PacmConfig::get().dedupe(),
+            verify_exports: PacmConfig::get().verify_exports(),
+            npm_compat: PacmConfig::get().npm_compat(),
+            lockfile: PacmConfig::get().lockfile(),
+            frozen_lockfile: PacmConfig::get().frozen_lockfile(),
         }
     }
 
+    /// Limits resolution to `max_depth` levels below the direct dependencies. Such an
+    /// install bypasses the lockfile and marks the one it writes as partial.
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.resolver = self.resolver.with_max_depth(max_depth);
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Fails the install once its tree is deeper or holds more packages than `limits`
+    /// allow. Defaults to the `max-resolution-depth` and `max-packages` settings.
+    #[must_use]
+    pub fn with_resolution_limits(mut self, limits: ResolutionLimits) -> Self {
+        self.resolver = self.resolver.with_resolution_limits(Arc::new(limits));
+        self
+    }
+
+    /// Also installs missing peer dependencies of the direct dependencies. Defaults to
+    /// the `install-peers` setting.
+    #[must_use]
+    pub fn with_install_peers(mut self, install_peers: bool) -> Self {
+        self.install_peers = install_peers;
+        self
+    }
+
+    /// Collapses packages resolved at several versions before anything is downloaded.
+    /// Defaults to the `dedupe` setting.
+    #[must_use]
+    pub fn with_dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    /// Warns about direct dependencies whose `main` or `exports` name missing files once
+    /// they are linked. Defaults to the `verify-exports` setting.
+    #[must_use]
+    pub fn with_verify_exports(mut self, verify_exports: bool) -> Self {
+        self.verify_exports = verify_exports;
+        self
+    }
+
+    /// Also writes `node_modules/.package-lock.json` from `pacm.lock` once installed.
+    /// Defaults to the `npm-compat` setting.
+    #[must_use]
+    pub fn with_npm_compat(mut self, npm_compat: bool) -> Self {
+        self.npm_compat = npm_compat;
+        self
+    }
+
+    /// Resolves and downloads through `client` instead of clients of its own, including
+    /// for tarball dependencies.
+    #[must_use]
+    pub fn with_client(mut self, client: Arc<reqwest::Client>) -> Self {
+        self.downloader = self.downloader.with_client(client.clone());
+        self.resolver = self.resolver.with_client(client.clone());
+        self.tarball_installer = self.tarball_installer.with_client(client);
+        self
+    }
+
+    /// Writes the install's result to `pacm.lock`. Without it an existing lockfile is
+    /// left as it is and none is created. Defaults to the `lockfile` setting.
+    #[must_use]
+    pub fn with_lockfile(mut self, lockfile: bool) -> Self {
+        self.tarball_installer = self.tarball_installer.with_lockfile(lockfile);
+        self.lockfile = lockfile;
+        self
+    }
+
+    /// Copies `file:` and `link:` directory dependencies into `node_modules` instead of
+    /// symlinking them. Defaults to the `install-links` setting.
+    #[must_use]
+    pub fn with_install_links(mut self, install_links: bool) -> Self {
+        self.tarball_installer = self.tarball_installer.with_install_links(install_links);
+        self
+    }
+
+    /// Fails instead of resolving again when a direct dependency's range in
+    /// `package.json` no longer matches its locked version. Defaults to the
+    /// `frozen-lockfile` setting.
+    #[must_use]
+    pub fn with_frozen_lockfile(mut self, frozen_lockfile: bool) -> Self {
+        self.frozen_lockfile = frozen_lockfile;
+        self
+    }
+
     pub fn install_all(&self, project_dir: &str, debug: bool) -> Result<()> {
         let rt = tokio::runtime::Runtime::new().map_err(|e| {
             PackageManagerError::NetworkError(format!("Failed to create async runtime: {}", e))
         })?;
 
-        rt.block_on(self.install_all_async(project_dir, debug))
+        rt.block_on(self.install_all_async(project_dir, debug, None))
     }
 
-    async fn install_all_async(&self, project_dir: &str, debug: bool) -> Result<()> {
-        let start_time = std::time::Instant::now();
+    /// Installs every selected dependency of the project. When `cancel` fires, the
+    /// install stops at the next resolution or download batch and the project is left as
+    /// it was before the install started.
+    pub async fn install_all_async(
+        &self,
+        project_dir: &str,
+        debug: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<()> {
         let path = PathBuf::from(project_dir);
-        let _pkg = read_package_json(&path)
+        PacmConfig::init_for_project(&path).map_err(PackageManagerError::ConfigError)?;
+        let had_node_modules = path.join("node_modules").exists();
+
+        let mut result = self.install_all_inner(&path, debug, cancel).await;
+        if result.is_ok() {
+            result =
+                WorkspaceInstall::discover(&path).map(|workspaces| workspaces.run_scripts(debug));
+        }
+        if result.is_ok() && self.npm_compat {
+            result = NpmCompat::write(&path);
+        }
+        timing::report_slowest_packages(debug);
+        timing::report_connections(debug);
+        if let Err(PackageManagerError::Cancelled) = result {
+            InstallJournal::remove(&path);
+            InstallUtils::cleanup_cancelled_install(&path, had_node_modules);
+        }
+        result
+    }
+
+    async fn install_all_inner(
+        &self,
+        path: &Path,
+        debug: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<()> {
+        let start_time = std::time::Instant::now();
+        let path = path.to_path_buf();
+        let pkg = read_package_json(&path)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let overrides = pkg
+            .other
+            .get("overrides")
+            .map(Overrides::parse)
+            .unwrap_or_default();
+        let workspaces = WorkspaceInstall::discover(&path)?;
+        workspaces.check_cycles(&pkg)?;
+        workspaces.check_protocol_deps(&pkg, self.selection)?;
+
+        if let Some(depth) = self.max_depth {
+            pacm_logger::warn(&format!(
+                "Resolving only {} level(s) of transitive dependencies. This is not a complete install; do not use the resulting {} in production",
+                depth,
+                PacmConfig::get().lockfile_name()
+            ));
+        }
 
-        let (all_deps, use_lockfile) = self.load_deps(&path)?;
+        let (all_deps, use_lockfile) = self.load_deps(&path, &workspaces)?;
+
+        let mut tarball_deps = self.tarball_deps(&path)?;
+        let root_deps = pkg.get_dependencies(self.selection);
+        tarball_deps.extend(workspaces.member_tarball_deps(&root_deps, self.selection)?);
+        for (source, dep_type) in tarball_deps.values() {
+            InstallUtils::check_cancelled(cancel)?;
+            self.tarball_installer
+                .install_async(&path.to_string_lossy(), source, *dep_type, true, debug)
+                .await?;
+        }
+        workspaces.link(&path)?;
+        let mut all_deps: Vec<(String, String)> = all_deps
+            .into_iter()
+            .filter(|(name, _)| !tarball_deps.contains_key(name))
+            .collect();
+
+        if self.install_peers {
+            InstallUtils::check_cancelled(cancel)?;
+            let peers = self.missing_peers(&path, &tarball_deps, debug).await?;
+            let peers: Vec<_> = peers
+                .into_iter()
+                .filter(|(peer, _)| all_deps.iter().all(|(name, _)| name != peer))
+                .collect();
+            if !peers.is_empty() {
+                pacm_logger::status(&format!(
+                    "Adding {} missing peer dependencies...",
+                    peers.len()
+                ));
+                all_deps.extend(peers);
+            }
+        }
 
         if all_deps.is_empty() {
-            pacm_logger::finish("No dependencies to install");
+            if tarball_deps.is_empty() {
+                pacm_logger::finish("No dependencies to install");
+            }
             return Ok(());
         }
 
-        let deps = self.check_existing_pkgs(&path, &all_deps, use_lockfile, debug)?;
+        let journal_key = self
+            .max_depth
+            .is_none()
+            .then(|| ResolutionCache::key(&all_deps, use_lockfile, self.selection, &overrides));
+        if let Some(resolved) = journal_key
+            .as_deref()
+            .and_then(|key| InstallJournal::resume(&path, key))
+        {
+            pacm_logger::status(&format!(
+                "Resuming interrupted install of {} packages...",
+                resolved.len()
+            ));
+            InstallUtils::check_cancelled(cancel)?;
+            self.cache.build_index(debug).await?;
+            let direct_count = if use_lockfile {
+                self.get_actual_direct_dependencies(&path)?.len()
+            } else {
+                all_deps.len()
+            };
+            return self
+                .install_cached_resolution(
+                    resolved,
+                    &path,
+                    use_lockfile,
+                    direct_count,
+                    debug,
+                    cancel,
+                )
+                .await;
+        }
+
+        // Packages left by a depth-limited install look installed but lack their deeper
+        // dependencies, so a full install after one revisits everything. So does one
+        // that has to write the lockfile a `--no-lockfile` install left out.
+        let lockfile_missing = self.lockfile && !PacmConfig::get().lockfile_path(&path).exists();
+        let deps = if self.max_depth.is_none()
+            && (lockfile_missing || Self::has_partial_lockfile(&path))
+        {
+            all_deps.clone()
+        } else {
+            self.check_existing_pkgs(&path, &all_deps, use_lockfile, debug)?
+        };
 
         if deps.is_empty() {
+            InstallJournal::remove(&path);
             pacm_logger::finish("All dependencies are already installed");
             return Ok(());
         }
+        if let Some(key) = &journal_key {
+            InstallJournal::begin(&path, key);
+        }
 
+        InstallUtils::check_cancelled(cancel)?;
         self.cache.build_index(debug).await?;
+        pacm_logger::resolve_start(deps.len());
+
+        let direct_count = if use_lockfile {
+            self.get_actual_direct_dependencies(&path)?.len()
+        } else {
+            all_deps.len()
+        };
+
+        let resolution_key = self
+            .max_depth
+            .is_none()
+            .then(|| ResolutionCache::key(&deps, use_lockfile, self.selection, &overrides));
+        if let Some(resolved) = resolution_key
+            .as_deref()
+            .and_then(|key| self.resolutions.load(key))
+        {
+            pacm_logger::debug(
+                &format!(
+                    "Reusing the cached resolution of {} packages",
+                    resolved.len()
+                ),
+                debug,
+            );
+            return self
+                .install_cached_resolution(
+                    resolved,
+                    &path,
+                    use_lockfile,
+                    direct_count,
+                    debug,
+                    cancel,
+                )
+                .await;
+        }
+
+        // The cached and complexity-based paths look packages up by name and range alone,
+        // so overrides that depend on where a package sits in the tree need full resolution.
+        if !overrides.is_empty() {
+            InstallUtils::check_cancelled(cancel)?;
+            let overrides = Arc::new(overrides);
+            let resolver = DependencyResolver::new()
+                .with_client(self.resolver.get_client())
+                .with_optional(self.selection.optional)
+                .with_max_depth(self.max_depth)
+                .with_resolution_limits(self.resolver.resolution_limits())
+                .with_overrides(overrides.clone());
+            let (_, resolved) = resolver
+                .resolve_all_parallel(&deps, use_lockfile, debug)
+                .await?;
+            check_scoped_overrides(&overrides, &resolved)?;
+            let resolved: Vec<_> = resolved.into_values().collect();
+            if let Some(key) = &resolution_key {
+                self.resolutions.save(key, &resolved);
+            }
+            return self
+                .install_cached_resolution(
+                    resolved,
+                    &path,
+                    use_lockfile,
+                    direct_count,
+                    debug,
+                    cancel,
+                )
+                .await;
+        }
+
+        let cached_result = if self.max_depth.is_none() {
+            self.check_all_cached(&deps, use_lockfile, debug).await?
+        } else {
+            None
+        };
+        if let Some(cached_result) = cached_result {
+            InstallUtils::check_cancelled(cancel)?;
 
-        if let Some(cached_result) = self.check_all_cached(&deps, use_lockfile, debug).await? {
             let total_time = start_time.elapsed();
             pacm_logger::debug(
                 &format!(
@@ -75,11 +405,10 @@ impl BulkInstaller {
                 debug,
             );
 
-            let direct_count = if use_lockfile {
-                self.get_actual_direct_dependencies(&path)?.len()
-            } else {
-                all_deps.len()
-            };
+            if let Some(key) = &resolution_key {
+                let resolved: Vec<_> = cached_result.2.values().cloned().collect();
+                self.resolutions.save(key, &resolved);
+            }
 
             return self
                 .install_cached_only(cached_result, &path, use_lockfile, direct_count, debug)
@@ -88,10 +417,7 @@ impl BulkInstaller {
 
         let analysis_start = std::time::Instant::now();
 
-        if !debug {
-            pacm_logger::status(&format!("Analyzing {} dependencies...", deps.len()));
-        }
-
+        InstallUtils::check_cancelled(cancel)?;
         let package_analyses = self.smart_analyzer.analyze_packages(&deps, debug).await?;
 
         if debug {
@@ -135,52 +461,90 @@ impl BulkInstaller {
             );
         }
 
-        let direct_count = if use_lockfile {
-            self.get_actual_direct_dependencies(&path)?.len()
-        } else {
-            all_deps.len()
-        };
+        let resolved = self
+            .install_by_complexity(
+                trivial_packages,
+                simple_packages,
+                moderate_packages,
+                complex_packages,
+                use_lockfile,
+                &path,
+                direct_count,
+                debug,
+                cancel,
+            )
+            .await?;
 
-        self.install_by_complexity(
-            trivial_packages,
-            simple_packages,
-            moderate_packages,
-            complex_packages,
-            use_lockfile,
-            &path,
-            direct_count,
-            debug,
-        )
-        .await
+        if let Some(key) = &resolution_key {
+            self.resolutions.save(key, &resolved);
+        }
+        Ok(())
     }
 
-    fn load_deps(&self, path: &PathBuf) -> Result<(Vec<(String, String)>, bool)> {
-        let lock_path = path.join("pacm.lock");
+    fn load_deps(
+        &self,
+        path: &Path,
+        workspaces: &WorkspaceInstall,
+    ) -> Result<(Vec<(String, String)>, bool)> {
+        let lock_path = PacmConfig::get().lockfile_path(path);
+        let pkg = read_package_json(path)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        // Workspace members are linked, not installed; their registry dependencies are
+        // installed along with the project's.
+        let mut selected_deps: HashMap<String, String> = pkg
+            .get_dependencies(self.selection)
+            .into_iter()
+            .filter(|(name, spec)| {
+                !pacm_utils::is_workspace_spec(spec) && !workspaces.is_member(name)
+            })
+            .collect();
+        let member_deps = workspaces.member_dependencies(&selected_deps, self.selection)?;
+        selected_deps.extend(member_deps.clone());
 
-        if lock_path.exists() {
+        let lockfile = if lock_path.exists() {
+            Some(
+                PacmLock::load(&lock_path)
+                    .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?,
+            )
+        } else {
+            None
+        };
+
+        // A lockfile written by an install that omitted some sections does not know about
+        // them, so selecting them again has to go through resolution. The same goes for a
+        // partial lockfile, and a depth-limited install always resolves from scratch.
+        let lockfile = lockfile.filter(|lockfile| {
+            self.max_depth.is_none()
+                && !lockfile.partial
+                && (lockfile.packages.is_empty()
+                    || selected_deps
+                        .keys()
+                        .all(|name| lockfile.packages.contains_key(name)))
+        });
+
+        if let Some(lockfile) = lockfile {
             pacm_logger::status("Using existing lockfile...");
-            let lockfile = PacmLock::load(&lock_path)
-                .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
 
             let mut deps = Vec::new();
 
             if !lockfile.packages.is_empty() {
+                let drifted = self.check_drift(&lockfile, &selected_deps)?;
+                let selected = self.selected_lock_packages(&lockfile, member_deps.keys(), &drifted);
                 for (name, lock_package) in &lockfile.packages {
-                    deps.push((name.clone(), lock_package.version.clone()));
+                    if let Some(range) = drifted.get(name) {
+                        deps.push((name.clone(), range.clone()));
+                    } else if selected.as_ref().is_none_or(|names| names.contains(name)) {
+                        deps.push((name.clone(), lock_package.version.clone()));
+                    }
                 }
             } else {
                 if let Some(workspace_info) = lockfile.workspaces.get("") {
-                    for (name, version) in &workspace_info.dependencies {
-                        deps.push((name.clone(), version.clone()));
-                    }
-                    for (name, version) in &workspace_info.dev_dependencies {
-                        deps.push((name.clone(), version.clone()));
-                    }
-                    for (name, version) in &workspace_info.peer_dependencies {
-                        deps.push((name.clone(), version.clone()));
-                    }
-                    for (name, version) in &workspace_info.optional_dependencies {
-                        deps.push((name.clone(), version.clone()));
+                    for (dep_type, section) in Self::workspace_sections(workspace_info) {
+                        if self.selection.includes(dep_type) {
+                            for (name, version) in section {
+                                deps.push((name.clone(), version.clone()));
+                            }
+                        }
                     }
                 }
 
@@ -196,14 +560,168 @@ impl BulkInstaller {
             Ok((deps, true))
         } else {
             pacm_logger::status("Using package.json dependencies...");
-            let pkg = read_package_json(path)
-                .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
-            let all_deps = pkg.get_all_dependencies();
-            let deps: Vec<(String, String)> = all_deps.into_iter().collect();
+            let deps: Vec<(String, String)> = selected_deps.into_iter().collect();
             Ok((deps, false))
         }
     }
 
+    /// Peers of the selected direct dependencies that the project does not declare. Direct
+    /// dependencies are looked up at their locked version when there is one.
+    async fn missing_peers(
+        &self,
+        path: &Path,
+        tarball_deps: &HashMap<String, (String, DependencyType)>,
+        debug: bool,
+    ) -> Result<Vec<(String, String)>> {
+        let pkg = read_package_json(path)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let lockfile = PacmLock::load(&PacmConfig::get().lockfile_path(path)).ok();
+
+        let direct: HashMap<String, String> = pkg
+            .get_dependencies(self.selection)
+            .into_iter()
+            .filter(|(name, _)| !tarball_deps.contains_key(name))
+            .map(|(name, range)| {
+                let version = lockfile
+                    .as_ref()
+                    .and_then(|lock| lock.get_package(&name))
+                    .map_or(range, |locked| locked.version.clone());
+                (name, version)
+            })
+            .collect();
+
+        PeerResolver::new(self.resolver.get_client())
+            .missing_peers(&direct, lockfile.as_ref(), debug)
+            .await
+    }
+
+    fn has_partial_lockfile(path: &Path) -> bool {
+        PacmLock::load(&PacmConfig::get().lockfile_path(path))
+            .is_ok_and(|lockfile| lockfile.partial)
+    }
+
+    /// Selected direct dependencies declared as tarball paths or URLs, or as package
+    /// directories, keyed by name. These never go through registry resolution.
+    fn tarball_deps(&self, path: &Path) -> Result<HashMap<String, (String, DependencyType)>> {
+        let pkg = read_package_json(path)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+
+        Ok(pkg
+            .get_dependencies(self.selection)
+            .into_iter()
+            .filter(|(_, spec)| {
+                pacm_utils::is_tarball_spec(spec) || pacm_utils::is_directory_spec(spec)
+            })
+            .map(|(name, spec)| {
+                let dep_type = pkg
+                    .has_dependency(&name)
+                    .unwrap_or(DependencyType::Dependencies);
+                (name, (spec, dep_type))
+            })
+            .collect())
+    }
+
+    fn workspace_sections(
+        workspace_info: &WorkspaceInfo,
+    ) -> [(DependencyType, &HashMap<String, String>); 4] {
+        [
+            (DependencyType::Dependencies, &workspace_info.dependencies),
+            (
+                DependencyType::DevDependencies,
+                &workspace_info.dev_dependencies,
+            ),
+            (
+                DependencyType::PeerDependencies,
+                &workspace_info.peer_dependencies,
+            ),
+            (
+                DependencyType::OptionalDependencies,
+                &workspace_info.optional_dependencies,
+            ),
+        ]
+    }
+
+    /// The direct dependencies whose `package.json` range no longer matches their locked
+    /// version, mapped to that range. These are resolved again rather than installed at
+    /// the locked version, unless the lockfile is frozen, in which case the install fails.
+    fn check_drift(
+        &self,
+        lockfile: &PacmLock,
+        direct: &HashMap<String, String>,
+    ) -> Result<HashMap<String, String>> {
+        let drifted: BTreeMap<&String, (&String, &String)> = direct
+            .iter()
+            .filter_map(|(name, range)| {
+                let locked = &lockfile.packages.get(name)?.version;
+                let is_range = parse_npm_semver_ranges(range).is_ok();
+                (is_range && !satisfies(locked, range)).then_some((name, (locked, range)))
+            })
+            .collect();
+        if drifted.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let changes: Vec<String> = drifted
+            .iter()
+            .map(|(name, (locked, range))| format!("{name} (locked {locked}, wants {range})"))
+            .collect();
+        if self.frozen_lockfile {
+            return Err(PackageManagerError::LockfileError(format!(
+                "{} is out of date with package.json: {}",
+                PacmConfig::get().lockfile_name(),
+                changes.join(", ")
+            )));
+        }
+        pacm_logger::info(&format!(
+            "package.json changed since {} was written, resolving again: {}",
+            PacmConfig::get().lockfile_name(),
+            changes.join(", ")
+        ));
+        Ok(drifted
+            .into_iter()
+            .map(|(name, (_, range))| (name.clone(), range.clone()))
+            .collect())
+    }
+
+    /// Names of the locked packages reachable from the selected root sections and the
+    /// registry dependencies of workspace members. The locked dependencies of `drifted`
+    /// packages are not followed, as those are resolved again. Returns `None` when the
+    /// lockfile does not record its root dependencies, in which case everything is
+    /// installed.
+    fn selected_lock_packages<'a>(
+        &self,
+        lockfile: &PacmLock,
+        member_deps: impl Iterator<Item = &'a String>,
+        drifted: &HashMap<String, String>,
+    ) -> Option<HashSet<String>> {
+        let workspace_info = lockfile.workspaces.get("")?;
+
+        let mut pending: Vec<String> = Self::workspace_sections(workspace_info)
+            .into_iter()
+            .filter(|(dep_type, _)| self.selection.includes(*dep_type))
+            .flat_map(|(_, section)| section.keys().cloned())
+            .collect();
+        if pending.is_empty() {
+            return None;
+        }
+        pending.extend(member_deps.cloned());
+
+        let mut selected = HashSet::new();
+        while let Some(name) = pending.pop() {
+            if !selected.insert(name.clone()) || drifted.contains_key(&name) {
+                continue;
+            }
+            if let Some(package) = lockfile.packages.get(&name) {
+                pending.extend(package.dependencies.keys().cloned());
+                if self.selection.optional {
+                    pending.extend(package.optional_dependencies.keys().cloned());
+                }
+            }
+        }
+
+        Some(selected)
+    }
+
     async fn check_all_cached(
         &self,
         deps: &[(String, String)],
@@ -259,16 +777,19 @@ impl BulkInstaller {
 
     async fn install_cached_only(
         &self,
-        (cached_packages, direct_names, resolved_map): (
+        (cached_packages, direct_names, mut resolved_map): (
             Vec<CachedPackage>,
             HashSet<String>,
             HashMap<String, ResolvedPackage>,
         ),
-        path: &PathBuf,
+        path: &Path,
         use_lockfile: bool,
         direct_count: usize,
         debug: bool,
     ) -> Result<()> {
+        let (cached_packages, _) =
+            self.dedupe_resolved(path, &mut resolved_map, cached_packages, Vec::new(), debug)?;
+        pacm_logger::resolve_done(resolved_map.len());
         pacm_logger::status(&format!(
             "All {} packages found in cache",
             cached_packages.len()
@@ -282,6 +803,10 @@ impl BulkInstaller {
         super::utils::InstallUtils::run_postinstall_in_project(path, &stored_packages, debug)?;
 
         self.update_lock(path, &stored_packages, &direct_names, use_lockfile)?;
+        InstallJournal::remove(path);
+        if self.verify_exports {
+            warn_broken_entry_points(path, &direct_names);
+        }
 
         let total_count = cached_packages.len();
         let transitive_count = total_count.saturating_sub(direct_count);
@@ -306,15 +831,17 @@ impl BulkInstaller {
         moderate_packages: Vec<(String, String)>,
         complex_packages: Vec<(String, String)>,
         use_lockfile: bool,
-        path: &PathBuf,
+        path: &Path,
         direct_count: usize,
         debug: bool,
-    ) -> Result<()> {
+        cancel: Option<&CancellationToken>,
+    ) -> Result<Vec<ResolvedPackage>> {
         let mut all_cached = Vec::new();
         let mut all_downloaded = Vec::new();
         let mut all_resolved = HashMap::new();
 
         if !trivial_packages.is_empty() {
+            InstallUtils::check_cancelled(cancel)?;
             if debug {
                 pacm_logger::debug(
                     &format!("Processing {} trivial packages", trivial_packages.len()),
@@ -331,6 +858,7 @@ impl BulkInstaller {
         }
 
         if !simple_packages.is_empty() {
+            InstallUtils::check_cancelled(cancel)?;
             if debug {
                 pacm_logger::debug(
                     &format!("Processing {} simple packages", simple_packages.len()),
@@ -347,6 +875,7 @@ impl BulkInstaller {
         }
 
         if !moderate_packages.is_empty() {
+            InstallUtils::check_cancelled(cancel)?;
             if debug {
                 pacm_logger::debug(
                     &format!("Processing {} moderate packages", moderate_packages.len()),
@@ -363,6 +892,7 @@ impl BulkInstaller {
         }
 
         if !complex_packages.is_empty() {
+            InstallUtils::check_cancelled(cancel)?;
             if debug {
                 pacm_logger::debug(
                     &format!("Processing {} complex packages", complex_packages.len()),
@@ -378,13 +908,96 @@ impl BulkInstaller {
             all_resolved.extend(resolved);
         }
 
-        let compatible_packages_to_download: Vec<ResolvedPackage> = all_downloaded
+        let mut graph = all_resolved.clone();
+        for pkg in &all_downloaded {
+            graph
+                .entry(format!("{}@{}", pkg.name, pkg.version))
+                .or_insert_with(|| pkg.clone());
+        }
+
+        self.install_resolved(
+            all_cached,
+            all_downloaded,
+            all_resolved,
+            use_lockfile,
+            path,
+            direct_count,
+            debug,
+            cancel,
+        )
+        .await?;
+        Ok(graph.into_values().collect())
+    }
+
+    /// Installs a graph loaded from the resolution cache. Only packages missing from
+    /// the store are fetched; the registry metadata is not consulted at all.
+    async fn install_cached_resolution(
+        &self,
+        packages: Vec<ResolvedPackage>,
+        path: &Path,
+        use_lockfile: bool,
+        direct_count: usize,
+        debug: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<()> {
+        let mut cached = Vec::new();
+        let mut to_download = Vec::new();
+        let mut resolved = HashMap::new();
+
+        for pkg in packages {
+            let key = format!("{}@{}", pkg.name, pkg.version);
+            match self.cache.get(&key).await {
+                Some(cached_pkg) => cached.push(cached_pkg),
+                None => to_download.push(pkg.clone()),
+            }
+            resolved.insert(key, pkg);
+        }
+
+        self.install_resolved(
+            cached,
+            to_download,
+            resolved,
+            use_lockfile,
+            path,
+            direct_count,
+            debug,
+            cancel,
+        )
+        .await
+    }
+
+    /// Downloads what is not in the store yet, then links the whole graph into the
+    /// project and writes the lockfile.
+    async fn install_resolved(
+        &self,
+        all_cached: Vec<CachedPackage>,
+        all_downloaded: Vec<ResolvedPackage>,
+        mut all_resolved: HashMap<String, ResolvedPackage>,
+        use_lockfile: bool,
+        path: &Path,
+        direct_count: usize,
+        debug: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<()> {
+        let (all_cached, all_downloaded) =
+            self.dedupe_resolved(path, &mut all_resolved, all_cached, all_downloaded, debug)?;
+        pacm_logger::resolve_done(all_resolved.len());
+        let mut compatible_packages_to_download: Vec<ResolvedPackage> = all_downloaded
             .iter()
             .filter(|pkg| is_platform_compatible(&pkg.os, &pkg.cpu))
             .cloned()
             .collect();
 
+        if use_lockfile {
+            LockfileManager::apply_locked_urls(
+                &PacmConfig::get().lockfile_path(path),
+                &mut compatible_packages_to_download,
+            )?;
+        }
+
         let mut stored_packages = self.build_stored_map(&all_cached, &all_resolved);
+        InstallJournal::record_resolved(path, all_resolved.values());
+        InstallJournal::record_stage(path, stored_packages.keys(), Stage::Stored);
 
         if !compatible_packages_to_download.is_empty() {
             if debug {
@@ -397,32 +1010,140 @@ impl BulkInstaller {
                 );
             }
 
+            let optional = self.optional_keys(path, &all_resolved);
             let downloaded = self
                 .downloader
-                .download_parallel(&compatible_packages_to_download, debug)
-                .await?;
+                .download_parallel_cancellable(
+                    &compatible_packages_to_download,
+                    &optional,
+                    debug,
+                    cancel,
+                )
+                .await
+                .inspect_err(|_| record_stored_downloads(path, &compatible_packages_to_download))?;
+            InstallJournal::record_stage(path, downloaded.keys(), Stage::Stored);
             stored_packages.extend(downloaded);
         }
 
-        if !all_cached.is_empty() {
-            self.link_cached_deps(&all_cached, &stored_packages, debug)?;
+        InstallUtils::check_cancelled(cancel)?;
+
+        // A resumed install skips what the interrupted one already finished: the whole
+        // link step, since linking only part of the graph could leave the isolated
+        // layout's entries without their dependencies, and each package's postinstall.
+        let stages = InstallJournal::stages(path);
+        let reached = |key: &String, stage: Stage| stages.get(key).is_some_and(|s| *s >= stage);
+        if stored_packages
+            .keys()
+            .all(|key| reached(key, Stage::Linked))
+        {
+            pacm_logger::debug("Skipping the link step of the interrupted install", debug);
+        } else {
+            if !all_cached.is_empty() {
+                self.link_cached_deps(&all_cached, &stored_packages, debug)?;
+            }
+            self.link_all_to_project(path, &stored_packages, debug)?;
+            InstallJournal::record_stage(path, stored_packages.keys(), Stage::Linked);
         }
 
-        self.link_all_to_project(path, &stored_packages, debug)?;
-
-        if !stored_packages.is_empty() {
-            super::utils::InstallUtils::run_postinstall_in_project(path, &stored_packages, debug)?;
+        let unbuilt: HashMap<String, (ResolvedPackage, PathBuf)> = stored_packages
+            .iter()
+            .filter(|(key, _)| !reached(key, Stage::Built))
+            .map(|(key, stored)| (key.clone(), stored.clone()))
+            .collect();
+        if !unbuilt.is_empty() {
+            super::utils::InstallUtils::run_postinstall_in_project(path, &unbuilt, debug)?;
+            InstallJournal::record_stage(path, unbuilt.keys(), Stage::Built);
         }
 
         let direct_names = self.get_actual_direct_dependencies(path)?;
         self.update_lock(path, &stored_packages, &direct_names, use_lockfile)?;
+        InstallJournal::remove(path);
+        if self.verify_exports {
+            warn_broken_entry_points(path, &direct_names);
+        }
 
         let msg =
             self.build_finish_msg(&all_cached, &compatible_packages_to_download, direct_count);
         pacm_logger::finish(&msg);
+        self.downloader.report();
         Ok(())
     }
 
+    /// Keys of the resolved packages only needed through `optionalDependencies`, whose
+    /// downloads may fail without failing the install.
+    fn optional_keys(
+        &self,
+        path: &Path,
+        resolved: &HashMap<String, ResolvedPackage>,
+    ) -> HashSet<String> {
+        let Ok(pkg) = read_package_json(path) else {
+            return HashSet::new();
+        };
+        let names = |dep_type| {
+            DependencyManager::section(&pkg, dep_type)
+                .into_iter()
+                .flat_map(|section| section.keys().cloned())
+        };
+        let direct_optional: HashSet<String> =
+            names(DependencyType::OptionalDependencies).collect();
+        let direct_required: HashSet<String> = [
+            DependencyType::Dependencies,
+            DependencyType::DevDependencies,
+            DependencyType::PeerDependencies,
+        ]
+        .into_iter()
+        .filter(|dep_type| self.selection.includes(*dep_type))
+        .flat_map(names)
+        .collect();
+
+        optional_only(resolved, &direct_required, &direct_optional)
+    }
+
+    /// Applies [`dedupe`] to a resolved graph and drops the removed packages from the ones
+    /// about to be linked from the store or downloaded.
+    fn dedupe_resolved(
+        &self,
+        path: &Path,
+        resolved: &mut HashMap<String, ResolvedPackage>,
+        cached: Vec<CachedPackage>,
+        to_download: Vec<ResolvedPackage>,
+        debug: bool,
+    ) -> Result<(Vec<CachedPackage>, Vec<ResolvedPackage>)> {
+        if !self.dedupe {
+            return Ok((cached, to_download));
+        }
+
+        let direct = read_package_json(path)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?
+            .get_dependencies(self.selection);
+        let cached_keys: HashSet<String> = cached
+            .iter()
+            .map(|pkg| format!("{}@{}", pkg.name, pkg.version))
+            .collect();
+
+        let removed = dedupe(resolved, &direct, &cached_keys);
+        if removed == 0 {
+            return Ok((cached, to_download));
+        }
+        pacm_logger::debug(
+            &format!("Deduplication removed {} packages", removed),
+            debug,
+        );
+
+        let kept =
+            |name: &str, version: &str| resolved.contains_key(&format!("{}@{}", name, version));
+        Ok((
+            cached
+                .into_iter()
+                .filter(|pkg| kept(&pkg.name, &pkg.version))
+                .collect(),
+            to_download
+                .into_iter()
+                .filter(|pkg| kept(&pkg.name, &pkg.version))
+                .collect(),
+        ))
+    }
+
     async fn process_trivial_packages(
         &self,
         packages: &[(String, String)],
@@ -445,10 +1166,7 @@ impl BulkInstaller {
                     version: version.clone(),
                     resolved: cached.resolved.clone(),
                     integrity: cached.integrity.clone(),
-                    dependencies: HashMap::new(), // Trivial = no dependencies
-                    optional_dependencies: HashMap::new(),
-                    os: None,
-                    cpu: None,
+                    ..Default::default() // Trivial = no dependencies
                 };
                 resolved_map.insert(cache_key, resolved_pkg);
             }
@@ -505,7 +1223,7 @@ impl BulkInstaller {
 
     fn check_existing_pkgs(
         &self,
-        path: &PathBuf,
+        path: &Path,
         deps: &[(String, String)],
         use_lockfile: bool,
         debug: bool,
@@ -530,10 +1248,7 @@ impl BulkInstaller {
                     version: cached_pkg.version.clone(),
                     resolved: cached_pkg.resolved.clone(),
                     integrity: cached_pkg.integrity.clone(),
-                    dependencies: HashMap::new(),
-                    optional_dependencies: HashMap::new(),
-                    os: None,
-                    cpu: None,
+                    ..Default::default()
                 });
             stored.insert(key, (resolved_pkg, cached_pkg.store_path.clone()));
         }
@@ -552,7 +1267,7 @@ impl BulkInstaller {
 
     fn link_all_to_project(
         &self,
-        path: &PathBuf,
+        path: &Path,
         stored: &HashMap<String, (ResolvedPackage, PathBuf)>,
         debug: bool,
     ) -> Result<()> {
@@ -561,24 +1276,35 @@ impl BulkInstaller {
 
     fn update_lock(
         &self,
-        path: &PathBuf,
+        path: &Path,
         stored: &HashMap<String, (ResolvedPackage, PathBuf)>,
         _direct_names: &HashSet<String>,
         use_lockfile: bool,
     ) -> Result<()> {
-        let lock_path = path.join("pacm.lock");
+        if self.lockfile {
+            let lock_path = PacmConfig::get().lockfile_path(path);
 
-        if use_lockfile {
-            self.linker
-                .update_lock_from_lockfile_install(&lock_path, stored)
-        } else {
-            let actual_direct_names = self.get_actual_direct_dependencies(path)?;
-            self.linker
-                .update_lock_direct(&lock_path, stored, &actual_direct_names)
+            if use_lockfile {
+                self.linker
+                    .update_lock_from_lockfile_install(&lock_path, stored)?;
+            } else {
+                let actual_direct_names = self.get_actual_direct_dependencies(path)?;
+                self.linker
+                    .update_lock_direct(&lock_path, stored, &actual_direct_names)?;
+            }
+
+            LockfileManager::set_partial(&lock_path, self.max_depth.is_some())?;
+        }
+
+        let mut installed: Vec<_> = stored.values().map(|(pkg, _)| pkg).collect();
+        installed.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        for pkg in installed {
+            pacm_logger::package_installed(&pkg.name, &pkg.version);
         }
+        Ok(())
     }
 
-    fn get_actual_direct_dependencies(&self, path: &PathBuf) -> Result<HashSet<String>> {
+    fn get_actual_direct_dependencies(&self, path: &Path) -> Result<HashSet<String>> {
         use pacm_project::read_package_json;
 
         let pkg = read_package_json(path)
@@ -660,6 +1386,50 @@ impl BulkInstaller {
     }
 }
 
+/// Records the packages a failed download still got into the store, so a resumed
+/// install knows they need no download.
+fn record_stored_downloads(path: &Path, packages: &[ResolvedPackage]) {
+    let store = get_store_path();
+    let stored: Vec<String> = packages
+        .iter()
+        .filter(|pkg| {
+            PathResolver::get_package_path(&store, &pkg.name, &pkg.version)
+                .join("package")
+                .exists()
+        })
+        .map(|pkg| format!("{}@{}", pkg.name, pkg.version))
+        .collect();
+    InstallJournal::record_stage(path, &stored, Stage::Stored);
+}
+
+/// Fails when a path-scoped override leaves a package resolved at more than one version.
+/// The flat `node_modules` and the lockfile hold one version per name, so one of the
+/// paths would silently get the other's copy.
+fn check_scoped_overrides(
+    overrides: &Overrides,
+    resolved: &HashMap<String, ResolvedPackage>,
+) -> Result<()> {
+    for name in overrides.scoped_names() {
+        let mut versions: Vec<&str> = resolved
+            .values()
+            .filter(|pkg| pkg.name == name)
+            .map(|pkg| pkg.version.as_str())
+            .collect();
+        versions.sort_unstable();
+        versions.dedup();
+        if versions.len() > 1 {
+            return Err(PackageManagerError::DependencyConflict(
+                name.to_string(),
+                format!(
+                    "path-scoped overrides resolve it to {}, but node_modules can only hold one version of it; override it the same way on every path or at the top level",
+                    versions.join(" and ")
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
 impl Default for BulkInstaller {
     fn default() -> Self {
         Self::new()
@@ -1,15 +1,71 @@
 use rayon::prelude::*;
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::Mutex;
 
 use super::types::CachedPackage;
+use crate::timing::{self, Phase};
+use pacm_config::PacmConfig;
 use pacm_error::Result;
 use pacm_logger;
-use pacm_store::get_store_path;
+use pacm_resolver::satisfies;
+use pacm_resolver::semver::parse_npm_semver_ranges;
+use pacm_store::{StoreManager, get_store_path};
 use pacm_symcap::SystemCapabilities;
 
+/// How long an index of the store is handed to the next [`CacheManager`] of the same
+/// process instead of scanning again. Packages this process stores, or new package
+/// directories from other processes, invalidate it sooner; the window bounds how long a
+/// new version stored by another process can go unnoticed.
+const INDEX_REUSE_WINDOW: Duration = Duration::from_secs(5);
+
+/// The last index built in this process, with what it was built from.
+struct BuiltIndex {
+    npm_dir: PathBuf,
+    modified: Option<SystemTime>,
+    generation: u64,
+    built: Instant,
+    entries: Arc<HashMap<String, CachedPackage>>,
+}
+
+impl BuiltIndex {
+    fn reusable_for(&self, npm_dir: &Path, modified: Option<SystemTime>, generation: u64) -> bool {
+        self.npm_dir == npm_dir
+            && self.modified == modified
+            && self.generation == generation
+            && self.built.elapsed() < INDEX_REUSE_WINDOW
+    }
+}
+
+/// Whether a stored `version` can stand in for `range`. Dist-tags and other specs that
+/// are not ranges cannot be checked without the registry, so any stored version does.
+fn range_allows(version: &str, range: &str) -> bool {
+    parse_npm_semver_ranges(range).is_err() || satisfies(version, range)
+}
+
+static LAST_INDEX: std::sync::Mutex<Option<BuiltIndex>> = std::sync::Mutex::new(None);
+
+/// The threads that scan the store, `index-concurrency` of them when configured, kept
+/// apart from the global rayon pool like the linking pool. `None` when the pool could not
+/// be started, in which case the global pool is used.
+fn index_pool() -> Option<&'static rayon::ThreadPool> {
+    static POOL: OnceLock<Option<rayon::ThreadPool>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(
+                PacmConfig::get()
+                    .index_concurrency()
+                    .unwrap_or(SystemCapabilities::get().logical_cores),
+            )
+            .thread_name(|index| format!("pacm-index-{index}"))
+            .build()
+            .ok()
+    })
+    .as_ref()
+}
+
 #[derive(Clone)]
 pub struct CacheManager {
     index: Arc<Mutex<HashMap<String, CachedPackage>>>,
@@ -39,37 +95,57 @@ impl CacheManager {
             return Ok(());
         }
 
+        let start = Instant::now();
+        let modified = std::fs::metadata(&npm_dir)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let generation = StoreManager::generation();
+
+        let reused = LAST_INDEX
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .as_ref()
+            .filter(|last| last.reusable_for(&npm_dir, modified, generation))
+            .map(|last| last.entries.clone());
+        if let Some(entries) = reused {
+            let mut cache = self.index.lock().await;
+            cache.clone_from(&entries);
+            timing::record(Phase::CacheIndex, start.elapsed());
+            pacm_logger::debug(
+                &format!("Reusing cache index of {} entries", cache.len()),
+                debug,
+            );
+            return Ok(());
+        }
+
         if !debug {
             pacm_logger::status("Building package cache index...");
         } else {
             pacm_logger::debug("Building cache index...", debug);
         }
 
-        let start = std::time::Instant::now();
+        if let Ok(package_entries) = std::fs::read_dir(&npm_dir) {
+            let package_entries: Vec<_> = package_entries.flatten().collect();
 
-        match std::fs::read_dir(&npm_dir) {
-            Ok(package_entries) => {
-                let package_entries: Vec<_> = package_entries.flatten().collect();
-
-                if package_entries.is_empty() {
-                    return Ok(());
-                }
+            if package_entries.is_empty() {
+                return Ok(());
+            }
 
-                let system_caps = SystemCapabilities::get();
-                let chunk_size = (package_entries.len() / system_caps.logical_cores)
-                    .max(10)
-                    .min(50);
+            let system_caps = SystemCapabilities::get();
+            let chunk_size = (package_entries.len() / system_caps.logical_cores).clamp(10, 50);
 
-                let cached_packages: Vec<_> = package_entries
+            let scan = || -> Vec<_> {
+                package_entries
                     .par_chunks(chunk_size)
                     .flat_map(|chunk| {
                         chunk.par_iter().filter_map(|package_entry| {
                             if package_entry.file_type().ok()?.is_dir() {
-                                let package_name = Self::unsanitize_package_name(
+                                let package_name = pacm_store::package_name_from_store(
                                     &package_entry.file_name().to_string_lossy(),
                                 );
 
-                                if let Ok(version_entries) = std::fs::read_dir(package_entry.path()) {
+                                if let Ok(version_entries) = std::fs::read_dir(package_entry.path())
+                                {
                                     let versions: Vec<_> = version_entries
                                         .flatten()
                                         .filter_map(|version_entry| {
@@ -85,10 +161,8 @@ impl CacheManager {
                                                     let cached_pkg = CachedPackage {
                                                         name: package_name.clone(),
                                                         version: version.clone(),
-                                                        resolved: format!(
-                                                            "https://registry.npmjs.org/{}/-/{}-{}.tgz",
-                                                            package_name, package_name, version
-                                                        ),
+                                                        resolved: PacmConfig::get()
+                                                            .tarball_url(&package_name, &version),
                                                         integrity: String::new(), // We no longer store hash in path
                                                         store_path,
                                                     };
@@ -116,19 +190,32 @@ impl CacheManager {
                         })
                     })
                     .flatten()
-                    .collect();
-
-                let mut cache = self.index.lock().await;
-                cache.reserve(cached_packages.len());
-                for (key, cached_pkg) in cached_packages {
-                    cache.insert(key, cached_pkg);
-                }
+                    .collect()
+            };
+            let cached_packages = match index_pool() {
+                Some(pool) => pool.install(scan),
+                None => scan(),
+            };
+
+            let mut cache = self.index.lock().await;
+            cache.reserve(cached_packages.len());
+            for (key, cached_pkg) in cached_packages {
+                cache.insert(key, cached_pkg);
             }
-            Err(_) => {}
         }
 
         let cache = self.index.lock().await;
+        *LAST_INDEX
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(BuiltIndex {
+            npm_dir,
+            modified,
+            generation,
+            built: Instant::now(),
+            entries: Arc::new(cache.clone()),
+        });
         let duration = start.elapsed();
+        timing::record(Phase::CacheIndex, duration);
 
         if !debug {
             pacm_logger::debug(
@@ -179,16 +266,15 @@ impl CacheManager {
                     || (!version_range.chars().next().unwrap_or('0').is_ascii_digit())
                 {
                     let name_prefix = format!("{}@", name);
-
-                    let versions: Vec<_> = cache
+                    return cache
                         .iter()
-                        .filter(|(key, _)| key.starts_with(&name_prefix))
+                        .filter(|(key, cached_pkg)| {
+                            key.starts_with(&name_prefix)
+                                && range_allows(&cached_pkg.version, version_range)
+                        })
                         .map(|(_, cached_pkg)| cached_pkg)
-                        .collect();
-
-                    if let Some(cached_pkg) = versions.first() {
-                        return Some((*cached_pkg).clone());
-                    }
+                        .max_by_key(|cached_pkg| semver::Version::parse(&cached_pkg.version).ok())
+                        .cloned();
                 }
 
                 None
@@ -209,7 +295,9 @@ impl CacheManager {
             }
 
             let name_prefix = format!("{}@", name);
-            cache.keys().any(|key| key.starts_with(&name_prefix))
+            cache.iter().any(|(key, cached_pkg)| {
+                key.starts_with(&name_prefix) && range_allows(&cached_pkg.version, version_range)
+            })
         })
     }
 
@@ -230,6 +318,11 @@ impl CacheManager {
         cache.len()
     }
 
+    pub async fn is_empty(&self) -> bool {
+        let cache = self.index.lock().await;
+        cache.is_empty()
+    }
+
     pub async fn find_versions_for_package(&self, package_name: &str) -> Vec<(String, PathBuf)> {
         let cache = self.index.lock().await;
         cache
@@ -238,10 +331,6 @@ impl CacheManager {
             .map(|cached_pkg| (cached_pkg.version.clone(), cached_pkg.store_path.clone()))
             .collect()
     }
-
-    fn unsanitize_package_name(safe_name: &str) -> String {
-        safe_name.replace("_at_", "@").replace("_slash_", "/")
-    }
 }
 
 impl Default for CacheManager {
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use owo_colors::{OwoColorize, Stream::Stdout, Style};
+use serde::Serialize;
+
+use pacm_config::PacmConfig;
+use pacm_lock::PacmLock;
+
+/// A package that appeared in or disappeared from `pacm.lock`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+}
+
+/// A package whose locked version changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpdatedPackage {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// What an install changed in `pacm.lock`, as printed after `pacm install`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LockfileChanges {
+    pub added: Vec<LockedPackage>,
+    pub updated: Vec<UpdatedPackage>,
+    pub removed: Vec<LockedPackage>,
+    /// How many locked packages kept their version.
+    pub unchanged: usize,
+}
+
+impl LockfileChanges {
+    /// The version locked for each package of the project at `project_dir`, or nothing
+    /// when it has no readable `pacm.lock` yet.
+    pub fn locked_versions(project_dir: &Path) -> BTreeMap<String, String> {
+        PacmLock::load(&PacmConfig::get().lockfile_path(project_dir))
+            .map(|lockfile| {
+                lockfile
+                    .packages
+                    .into_iter()
+                    .map(|(name, package)| (name, package.version))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The difference between two results of [`Self::locked_versions`].
+    pub fn between(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> Self {
+        let mut changes = Self::default();
+        for (name, version) in after {
+            match before.get(name) {
+                None => changes.added.push(LockedPackage {
+                    name: name.clone(),
+                    version: version.clone(),
+                }),
+                Some(old) if old != version => changes.updated.push(UpdatedPackage {
+                    name: name.clone(),
+                    from: old.clone(),
+                    to: version.clone(),
+                }),
+                Some(_) => changes.unchanged += 1,
+            }
+        }
+        changes.removed = before
+            .iter()
+            .filter(|(name, _)| !after.contains_key(*name))
+            .map(|(name, version)| LockedPackage {
+                name: name.clone(),
+                version: version.clone(),
+            })
+            .collect();
+        changes
+    }
+
+    /// Lists every change, followed by a line that counts them.
+    pub fn print(&self) {
+        println!();
+        for package in &self.added {
+            println!(
+                "  {} {} {}",
+                "+".if_supports_color(Stdout, |text| text
+                    .style(Style::new().bright_green().bold())),
+                package.name,
+                package
+                    .version
+                    .if_supports_color(Stdout, |text| text.bright_green())
+            );
+        }
+        for package in &self.updated {
+            println!(
+                "  {} {} {} → {}",
+                "↑".if_supports_color(Stdout, |text| text.style(Style::new().bright_cyan().bold())),
+                package.name,
+                package
+                    .from
+                    .if_supports_color(Stdout, |text| text.bright_black()),
+                package
+                    .to
+                    .if_supports_color(Stdout, |text| text.bright_cyan())
+            );
+        }
+        for package in &self.removed {
+            println!(
+                "  {} {} {}",
+                "-".if_supports_color(Stdout, |text| text.style(Style::new().bright_red().bold())),
+                package.name,
+                package
+                    .version
+                    .if_supports_color(Stdout, |text| text.bright_black())
+            );
+        }
+        if !self.added.is_empty() || !self.updated.is_empty() || !self.removed.is_empty() {
+            println!();
+        }
+        println!(
+            "{}",
+            format!(
+                "{} added, {} updated, {} removed, {} unchanged",
+                self.added.len(),
+                self.updated.len(),
+                self.removed.len(),
+                self.unchanged
+            )
+            .if_supports_color(Stdout, |text| text.dimmed())
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_changes_between_locked_versions() {
+        let before = versions(&[("chalk", "4.1.0"), ("ms", "2.0.0"), ("old", "1.0.0")]);
+        let after = versions(&[("chalk", "5.3.0"), ("ms", "2.0.0"), ("new", "0.1.0")]);
+
+        let changes = LockfileChanges::between(&before, &after);
+        assert_eq!(
+            changes,
+            LockfileChanges {
+                added: vec![LockedPackage {
+                    name: "new".to_string(),
+                    version: "0.1.0".to_string(),
+                }],
+                updated: vec![UpdatedPackage {
+                    name: "chalk".to_string(),
+                    from: "4.1.0".to_string(),
+                    to: "5.3.0".to_string(),
+                }],
+                removed: vec![LockedPackage {
+                    name: "old".to_string(),
+                    version: "1.0.0".to_string(),
+                }],
+                unchanged: 1,
+            }
+        );
+        assert_eq!(
+            LockfileChanges::between(&after, &after),
+            LockfileChanges {
+                unchanged: 3,
+                ..LockfileChanges::default()
+            }
+        );
+    }
+}
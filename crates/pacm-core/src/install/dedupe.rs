@@ -0,0 +1,211 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use pacm_resolver::{ResolvedPackage, satisfies};
+
+/// Collapses packages resolved at several versions into the fewest of those versions that
+/// satisfy every range asking for them, preferring versions in `cached` and then newer
+/// ones. `direct` holds the project's own ranges. Packages that were only needed by a
+/// dropped version are removed as well. Returns how many packages were removed.
+pub fn dedupe(
+    resolved: &mut HashMap<String, ResolvedPackage>,
+    direct: &HashMap<String, String>,
+    cached: &HashSet<String>,
+) -> usize {
+    let mut ranges: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for (name, range) in direct {
+        ranges.entry(name).or_default().insert(range);
+    }
+    for pkg in resolved.values() {
+        for (name, range) in pkg.dependencies.iter().chain(&pkg.optional_dependencies) {
+            ranges.entry(name).or_default().insert(range);
+        }
+    }
+
+    let mut versions: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for pkg in resolved.values() {
+        versions.entry(&pkg.name).or_default().push(&pkg.version);
+    }
+
+    let mut redundant = Vec::new();
+    for (name, candidates) in versions.into_iter().filter(|(_, v)| v.len() > 1) {
+        let Some(ranges) = ranges.get(name) else {
+            continue;
+        };
+        if let Some(keep) = fewest_versions(name, &candidates, ranges, cached) {
+            redundant.extend(
+                candidates
+                    .into_iter()
+                    .filter(|version| !keep.contains(version))
+                    .map(|version| format!("{}@{}", name, version)),
+            );
+        }
+    }
+
+    if redundant.is_empty() {
+        return 0;
+    }
+
+    let before = resolved.len();
+    let depended_on: HashSet<String> = resolved
+        .values()
+        .flat_map(|pkg| {
+            pkg.dependencies
+                .keys()
+                .chain(pkg.optional_dependencies.keys())
+                .filter(move |name| **name != pkg.name)
+                .cloned()
+        })
+        .collect();
+    for key in &redundant {
+        resolved.remove(key);
+    }
+    prune_unreachable(resolved, direct, &depended_on);
+
+    before - resolved.len()
+}
+
+/// Greedily picks the candidate covering the most ranges still uncovered until all are
+/// covered. Returns `None` when some range is not met by any candidate, as nothing can be
+/// said about a dist-tag or URL from the versions alone.
+fn fewest_versions<'a>(
+    name: &str,
+    candidates: &[&'a str],
+    ranges: &HashSet<&str>,
+    cached: &HashSet<String>,
+) -> Option<Vec<&'a str>> {
+    let mut uncovered: Vec<&str> = ranges.iter().copied().collect();
+    let mut keep = Vec::new();
+
+    while !uncovered.is_empty() {
+        let best = candidates
+            .iter()
+            .filter(|version| !keep.contains(*version))
+            .map(|version| {
+                let covered = uncovered
+                    .iter()
+                    .filter(|range| satisfies(version, range))
+                    .count();
+                let is_cached = cached.contains(&format!("{}@{}", name, version));
+                (
+                    covered,
+                    is_cached,
+                    semver::Version::parse(version).ok(),
+                    *version,
+                )
+            })
+            .max()?;
+        if best.0 == 0 {
+            return None;
+        }
+
+        let version = best.3;
+        uncovered.retain(|range| !satisfies(version, range));
+        keep.push(version);
+    }
+
+    Some(keep)
+}
+
+/// Keeps only the packages reachable from the roots: direct dependencies and packages
+/// nothing depended on before deduplication.
+fn prune_unreachable(
+    resolved: &mut HashMap<String, ResolvedPackage>,
+    direct: &HashMap<String, String>,
+    depended_on: &HashSet<String>,
+) {
+    let mut by_name: HashMap<&str, Vec<&ResolvedPackage>> = HashMap::new();
+    for pkg in resolved.values() {
+        by_name.entry(&pkg.name).or_default().push(pkg);
+    }
+
+    let mut reachable = HashSet::new();
+    let mut queue: VecDeque<&ResolvedPackage> = resolved
+        .values()
+        .filter(|pkg| direct.contains_key(&pkg.name) || !depended_on.contains(&pkg.name))
+        .collect();
+
+    while let Some(pkg) = queue.pop_front() {
+        if !reachable.insert(format!("{}@{}", pkg.name, pkg.version)) {
+            continue;
+        }
+        for (name, range) in pkg.dependencies.iter().chain(&pkg.optional_dependencies) {
+            let Some(targets) = by_name.get(name.as_str()) else {
+                continue;
+            };
+            let matching: Vec<_> = targets
+                .iter()
+                .filter(|target| satisfies(&target.version, range))
+                .collect();
+            // A range no version meets cannot tell them apart, so all of them stay.
+            if matching.is_empty() {
+                queue.extend(targets.iter().copied());
+            } else {
+                queue.extend(matching.into_iter().copied());
+            }
+        }
+    }
+
+    resolved.retain(|key, _| reachable.contains(key));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_testutil::resolved_package as package;
+
+    fn graph(packages: Vec<ResolvedPackage>) -> HashMap<String, ResolvedPackage> {
+        packages
+            .into_iter()
+            .map(|pkg| (format!("{}@{}", pkg.name, pkg.version), pkg))
+            .collect()
+    }
+
+    fn keys(resolved: &HashMap<String, ResolvedPackage>) -> Vec<&str> {
+        let mut keys: Vec<_> = resolved.keys().map(String::as_str).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_reconcilable_ranges_collapse_to_one_version() {
+        let mut resolved = graph(vec![
+            package("a", "1.0.0", &[("shared", "^1.0.0")]),
+            package("b", "1.0.0", &[("shared", "~1.2.0")]),
+            package("shared", "1.5.0", &[("only-new", "^1.0.0")]),
+            package("shared", "1.2.9", &[]),
+            package("only-new", "1.0.0", &[]),
+        ]);
+        let direct = HashMap::from([
+            ("a".to_string(), "^1.0.0".to_string()),
+            ("b".to_string(), "^1.0.0".to_string()),
+        ]);
+
+        assert_eq!(dedupe(&mut resolved, &direct, &HashSet::new()), 2);
+        assert_eq!(keys(&resolved), vec!["a@1.0.0", "b@1.0.0", "shared@1.2.9"]);
+    }
+
+    #[test]
+    fn test_cached_version_wins_and_unreconcilable_ranges_are_kept() {
+        let mut resolved = graph(vec![
+            package("a", "1.0.0", &[("shared", "^1.0.0"), ("split", "^1.0.0")]),
+            package("b", "1.0.0", &[("shared", ">=1.1.0"), ("split", "^2.0.0")]),
+            package("shared", "1.4.0", &[]),
+            package("shared", "1.1.0", &[]),
+            package("split", "1.0.0", &[]),
+            package("split", "2.0.0", &[]),
+        ]);
+        let cached = HashSet::from(["shared@1.1.0".to_string()]);
+
+        assert_eq!(dedupe(&mut resolved, &HashMap::new(), &cached), 1);
+        assert_eq!(
+            keys(&resolved),
+            vec![
+                "a@1.0.0",
+                "b@1.0.0",
+                "shared@1.1.0",
+                "split@1.0.0",
+                "split@2.0.0"
+            ]
+        );
+    }
+}
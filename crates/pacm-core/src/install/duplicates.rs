@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use pacm_error::{PackageManagerError, Result};
+use pacm_logger;
+use pacm_resolver::package_info_async;
+use pacm_resolver::semver::resolve_version;
+
+/// The range of a package named without one, which any other request for it narrows.
+const UNSPECIFIED: [&str; 3] = ["latest", "*", ""];
+
+/// Folds packages requested more than once in one `pacm install`, such as
+/// `lodash lodash@4`, into a single request for the intersection of their ranges. Fails
+/// with `DependencyConflict` when no published version satisfies every request.
+/// Packages keep the position of their first request.
+pub async fn merge_duplicate_requests(
+    client: Arc<reqwest::Client>,
+    packages: &[(String, String)],
+) -> Result<Vec<(String, String)>> {
+    let mut order: Vec<&str> = Vec::new();
+    let mut requests: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (name, range) in packages {
+        let ranges = requests.entry(name).or_insert_with(|| {
+            order.push(name);
+            Vec::new()
+        });
+        if !ranges.contains(&range.as_str()) {
+            ranges.push(range);
+        }
+    }
+
+    let mut merged = Vec::with_capacity(order.len());
+    for name in order {
+        let ranges = &requests[name];
+        if ranges.len() == 1 {
+            merged.push((name.to_string(), ranges[0].to_string()));
+            continue;
+        }
+
+        let range = merge_ranges(client.clone(), name, ranges).await?;
+        pacm_logger::warn(&format!(
+            "{name} was requested more than once ({}), installing {name}@{range}",
+            ranges.join(", ")
+        ));
+        merged.push((name.to_string(), range));
+    }
+    Ok(merged)
+}
+
+async fn merge_ranges(client: Arc<reqwest::Client>, name: &str, ranges: &[&str]) -> Result<String> {
+    let specific: Vec<&str> = ranges
+        .iter()
+        .copied()
+        .filter(|range| !UNSPECIFIED.contains(range))
+        .collect();
+    match specific.as_slice() {
+        [] => return Ok(ranges[0].to_string()),
+        [range] => return Ok(range.to_string()),
+        _ => {}
+    }
+
+    let info = package_info_async(client, name, specific[0])
+        .await
+        .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+    // Dist-tags cannot be combined with ranges, so each stands for the version it points at.
+    let intersection = specific
+        .iter()
+        .map(|range| info.dist_tags.get(*range).map_or(*range, String::as_str))
+        .fold(String::new(), |acc, range| intersect_ranges(&acc, range));
+
+    if resolve_version(&info.versions, &intersection, &info.dist_tags).is_err() {
+        let requested: Vec<String> = specific
+            .iter()
+            .map(|range| format!("{name}@{range}"))
+            .collect();
+        return Err(PackageManagerError::DependencyConflict(
+            name.to_string(),
+            format!(
+                "requested as {}, which no published version satisfies together",
+                requested.join(" and ")
+            ),
+        ));
+    }
+    Ok(intersection)
+}
+
+/// The npm range matching the versions both `a` and `b` match. Within a `||` alternative
+/// comparators are all required, so each pair of alternatives is joined into one.
+pub fn intersect_ranges(a: &str, b: &str) -> String {
+    let alternatives = |range: &str| -> Vec<String> {
+        range
+            .split("||")
+            .map(str::trim)
+            .filter(|alternative| !alternative.is_empty() && *alternative != "*")
+            .map(str::to_string)
+            .collect()
+    };
+    let (a, b) = (alternatives(a), alternatives(b));
+    if a.is_empty() {
+        return b.join(" || ");
+    }
+    if b.is_empty() {
+        return a.join(" || ");
+    }
+
+    a.iter()
+        .flat_map(|left| b.iter().map(move |right| format!("{left} {right}")))
+        .collect::<Vec<_>>()
+        .join(" || ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intersect_ranges_pairs_alternatives() {
+        assert_eq!(intersect_ranges("", "^4.0.0"), "^4.0.0");
+        assert_eq!(intersect_ranges("^4.0.0", "*"), "^4.0.0");
+        assert_eq!(intersect_ranges("^4.0.0", ">=4.17.0"), "^4.0.0 >=4.17.0");
+        assert_eq!(
+            intersect_ranges("^1.0.0 || ^2.0.0", "~2.1.0"),
+            "^1.0.0 ~2.1.0 || ^2.0.0 ~2.1.0"
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::path::Path;
+
+use pacm_logger;
+use serde_json::Value;
+
+/// Extensions Node tries after a `main` path that does not name a file itself.
+const MAIN_EXTENSIONS: &[&str] = &["js", "json", "node"];
+
+/// Warns about each of `names`, linked under the project's `node_modules`, whose
+/// `main` or `exports` entry points name files the package does not contain, for
+/// `--verify-exports`. Such packages link fine but fail as soon as Node loads them.
+pub fn warn_broken_entry_points<'a>(
+    project_dir: &Path,
+    names: impl IntoIterator<Item = &'a String>,
+) {
+    let node_modules = project_dir.join("node_modules");
+    let mut names: Vec<&String> = names.into_iter().collect();
+    names.sort();
+
+    for name in names {
+        for entry in broken_entry_points(&node_modules.join(name)) {
+            pacm_logger::warn(&format!("{name}: {entry} does not exist in the package"));
+        }
+    }
+}
+
+/// The `main` and `exports` targets of the package in `package_dir` that do not resolve
+/// to a file, described as `main (./lib/index.js)` or `exports "./feature" (./feature.js)`.
+/// `main` is resolved the way Node does, with extensions and `index` files; `exports`
+/// targets must match exactly. Subpath patterns are not checked.
+pub fn broken_entry_points(package_dir: &Path) -> Vec<String> {
+    let Some(manifest) = fs::read_to_string(package_dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut broken = Vec::new();
+    if let Some(main) = manifest.get("main").and_then(Value::as_str)
+        && !main.is_empty()
+        && !main_resolves(package_dir, main)
+    {
+        broken.push(format!("main ({main})"));
+    }
+
+    if let Some(exports) = manifest.get("exports") {
+        let mut targets = Vec::new();
+        collect_export_targets(exports, ".", &mut targets);
+        for (subpath, target) in targets {
+            if !target.contains('*') && !package_dir.join(target).is_file() {
+                broken.push(format!("exports \"{subpath}\" ({target})"));
+            }
+        }
+    }
+    broken
+}
+
+fn main_resolves(package_dir: &Path, main: &str) -> bool {
+    let path = package_dir.join(main);
+    path.is_file()
+        || MAIN_EXTENSIONS
+            .iter()
+            .any(|ext| package_dir.join(format!("{main}.{ext}")).is_file())
+        || MAIN_EXTENSIONS
+            .iter()
+            .any(|ext| path.join(format!("index.{ext}")).is_file())
+}
+
+/// Collects `(subpath, target)` for every relative target in an `exports` value, which
+/// may be a path, a list of fallbacks, a map of conditions or a map of subpaths.
+fn collect_export_targets<'a>(
+    exports: &'a Value,
+    subpath: &'a str,
+    targets: &mut Vec<(&'a str, &'a str)>,
+) {
+    match exports {
+        Value::String(target) if target.starts_with("./") => targets.push((subpath, target)),
+        Value::Array(fallbacks) => {
+            for fallback in fallbacks {
+                collect_export_targets(fallback, subpath, targets);
+            }
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                let subpath = if key.starts_with('.') { key } else { subpath };
+                collect_export_targets(value, subpath, targets);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_testutil::TempProject;
+    use serde_json::json;
+
+    #[test]
+    fn test_reports_entry_points_missing_from_package() {
+        let project = TempProject::new(&json!({
+            "name": "exports-test",
+            "main": "./lib/main",
+            "exports": {
+                ".": { "import": "./esm/index.mjs", "require": "./lib/main.js" },
+                "./feature": "./feature.js",
+                "./utils/*": "./utils/*.js",
+                "./package.json": "./package.json",
+            },
+        }));
+        fs::create_dir_all(project.path().join("lib")).unwrap();
+        fs::write(project.path().join("lib/main.js"), "").unwrap();
+
+        assert_eq!(
+            broken_entry_points(project.path()),
+            vec![
+                "exports \".\" (./esm/index.mjs)".to_string(),
+                "exports \"./feature\" (./feature.js)".to_string(),
+            ]
+        );
+
+        fs::remove_file(project.path().join("lib/main.js")).unwrap();
+        assert!(broken_entry_points(project.path()).contains(&"main (./lib/main)".to_string()));
+    }
+}
@@ -108,10 +108,9 @@ impl FastPathAnalyzer {
                 if let Some(cached) = cached_opt {
                     if system_caps.should_skip_transitive_analysis(&name)
                         || self.is_likely_instant_package(&name)
+                        || self.is_simple_package_fast(&cached).await
                     {
                         instant_packages.push((name, version, cached));
-                    } else if self.is_simple_package_fast(&cached).await {
-                        instant_packages.push((name, version, cached));
                     } else {
                         cached_packages.push((name, version, cached));
                     }
@@ -170,8 +169,9 @@ impl FastPathAnalyzer {
                             .map(|deps| deps.len())
                             .unwrap_or(0);
 
-                        // Consider simple if has 3 or fewer total dependencies
-                        let is_simple = (deps + optional_deps) <= 3;
+                        // Instant linking skips dependency resolution, so only packages
+                        // without dependencies qualify
+                        let is_simple = (deps + optional_deps) == 0;
 
                         if debug && is_simple {
                             pacm_logger::debug(
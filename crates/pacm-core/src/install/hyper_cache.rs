@@ -129,7 +129,7 @@ impl HyperCache {
         let cache_size = cache_stats.0;
 
         if cache_size > 100 {
-            let sample_size = (cache_size / 10).min(50).max(10);
+            let sample_size = (cache_size / 10).clamp(10, 50);
 
             if debug {
                 pacm_logger::debug(
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use pacm_resolver::ResolvedPackage;
+
+/// How far an install got with one package. Later stages imply the earlier ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    /// Downloaded and extracted into the store.
+    Stored,
+    /// Linked into `node_modules`.
+    Linked,
+    /// Its postinstall script ran.
+    Built,
+}
+
+/// Progress of a `pacm install` that has not finished yet, kept in
+/// `node_modules/.pacm-journal`. A rerun with the same inputs picks up the resolved graph
+/// from the journal instead of resolving again, and skips the stages each package
+/// already passed: stored packages are not downloaded, a finished link step is not
+/// repeated, and postinstall scripts that ran do not run again. The journal is removed
+/// once the lockfile is written, so its presence means the last install did not
+/// complete.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InstallJournal {
+    /// The [`super::resolution_cache::ResolutionCache::key`] of the install's inputs.
+    key: String,
+    packages: Vec<ResolvedPackage>,
+    /// The stage each `name@version` reached.
+    #[serde(default)]
+    stages: BTreeMap<String, Stage>,
+}
+
+impl InstallJournal {
+    pub fn path(project_dir: &Path) -> PathBuf {
+        project_dir.join("node_modules").join(".pacm-journal")
+    }
+
+    /// Starts a journal for an install of the inputs hashed as `key`, replacing one left
+    /// by an earlier install.
+    pub fn begin(project_dir: &Path, key: &str) {
+        Self {
+            key: key.to_string(),
+            ..Self::default()
+        }
+        .save(project_dir);
+    }
+
+    /// The graph an interrupted install of the same inputs resolved.
+    pub fn resume(project_dir: &Path, key: &str) -> Option<Vec<ResolvedPackage>> {
+        let journal = Self::load(project_dir)?;
+        (journal.key == key && !journal.packages.is_empty()).then_some(journal.packages)
+    }
+
+    /// Records the resolved graph.
+    pub fn record_resolved<'a>(
+        project_dir: &Path,
+        packages: impl IntoIterator<Item = &'a ResolvedPackage>,
+    ) {
+        Self::update(project_dir, |journal| {
+            journal.packages = packages.into_iter().cloned().collect();
+        });
+    }
+
+    /// Records that the packages keyed `name@version` in `keys` reached `stage`.
+    pub fn record_stage<'a>(
+        project_dir: &Path,
+        keys: impl IntoIterator<Item = &'a String>,
+        stage: Stage,
+    ) {
+        Self::update(project_dir, |journal| {
+            for key in keys {
+                let reached = journal.stages.entry(key.clone()).or_insert(stage);
+                *reached = (*reached).max(stage);
+            }
+        });
+    }
+
+    /// The stage each package of the install in progress reached.
+    pub fn stages(project_dir: &Path) -> BTreeMap<String, Stage> {
+        Self::load(project_dir)
+            .map(|journal| journal.stages)
+            .unwrap_or_default()
+    }
+
+    /// Removes the journal, once the install it tracks has finished or been cancelled.
+    pub fn remove(project_dir: &Path) {
+        let _ = fs::remove_file(Self::path(project_dir));
+    }
+
+    fn load(project_dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(Self::path(project_dir)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Changes the journal of the install in progress. Installs that did not start one
+    /// keep none.
+    fn update(project_dir: &Path, change: impl FnOnce(&mut Self)) {
+        if let Some(mut journal) = Self::load(project_dir) {
+            change(&mut journal);
+            journal.save(project_dir);
+        }
+    }
+
+    /// The journal only saves a rerun some work, so failing to write it is ignored.
+    fn save(&self, project_dir: &Path) {
+        let Ok(content) = serde_json::to_string(self) else {
+            return;
+        };
+
+        // Renamed into place so an install killed mid-write leaves the previous journal.
+        let path = Self::path(project_dir);
+        let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        let written = fs::create_dir_all(project_dir.join("node_modules"))
+            .and_then(|_| fs::write(&temp_path, content))
+            .and_then(|_| fs::rename(&temp_path, &path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_only_move_forward() {
+        let project = tempfile::tempdir().unwrap();
+        let key = "left-pad@1.0.0".to_string();
+
+        InstallJournal::record_stage(project.path(), [&key], Stage::Linked);
+        assert!(InstallJournal::stages(project.path()).is_empty());
+
+        InstallJournal::begin(project.path(), "inputs");
+        InstallJournal::record_stage(project.path(), [&key], Stage::Linked);
+        InstallJournal::record_stage(project.path(), [&key], Stage::Stored);
+        assert_eq!(InstallJournal::stages(project.path())[&key], Stage::Linked);
+
+        InstallJournal::record_stage(project.path(), [&key], Stage::Built);
+        assert_eq!(InstallJournal::stages(project.path())[&key], Stage::Built);
+    }
+}
@@ -1,11 +1,16 @@
+use std::sync::Arc;
+
 use super::bulk::BulkInstaller;
 use super::single::SingleInstaller;
+use super::tarball::TarballInstaller;
 use pacm_error::Result;
-use pacm_project::DependencyType;
+use pacm_project::{DependencySelection, DependencyType};
+use tokio_util::sync::CancellationToken;
 
 pub struct InstallManager {
     bulk_installer: BulkInstaller,
     single_installer: SingleInstaller,
+    tarball_installer: TarballInstaller,
 }
 
 impl InstallManager {
@@ -13,13 +18,100 @@ impl InstallManager {
         Self {
             bulk_installer: BulkInstaller::new(),
             single_installer: SingleInstaller::new(),
+            tarball_installer: TarballInstaller::new(),
+        }
+    }
+
+    /// An install manager whose full installs only cover the sections in `selection`.
+    pub fn with_selection(selection: DependencySelection) -> Self {
+        Self {
+            bulk_installer: BulkInstaller::with_selection(selection),
+            single_installer: SingleInstaller::new(),
+            tarball_installer: TarballInstaller::new(),
         }
     }
 
+    /// Limits full installs to `max_depth` levels of transitive dependencies.
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.bulk_installer = self.bulk_installer.with_max_depth(max_depth);
+        self
+    }
+
+    /// Makes `--force` reinstalls, and so updates, keep dependencies at versions
+    /// `pacm.lock` already has when their ranges allow it.
+    #[must_use]
+    pub fn with_prefer_dedupe(mut self, prefer_dedupe: bool) -> Self {
+        self.single_installer = self.single_installer.with_prefer_dedupe(prefer_dedupe);
+        self
+    }
+
+    /// Sends every registry request and download through `client`, so an embedder can
+    /// configure TLS and proxies once and share one connection pool between installs.
+    ///
+    /// The configuration is process-wide and read once, from the first project installed
+    /// into (see [`pacm_config::PacmConfig::init_for_project`]). The installs below all
+    /// use it, so they suit projects of one repository that share its `.npmrc`; an install
+    /// into a project with an `.npmrc` or `.pacmrc` of its own fails with a
+    /// `ConfigError` and needs a process of its own.
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    ///
+    /// # async fn run() -> pacm_error::Result<()> {
+    /// let client = Arc::new(reqwest::Client::builder().build().unwrap());
+    /// let installs = pacm_core::InstallManager::new().with_client(client.clone());
+    /// installs.install_all_async("apps/web", false, None).await?;
+    /// installs.install_all_async("apps/api", false, None).await?;
+    ///
+    /// // Other installers can reuse the same pool.
+    /// let dedupe = pacm_core::InstallManager::new()
+    ///     .with_prefer_dedupe(true)
+    ///     .with_client(client);
+    /// dedupe.install_all_async("apps/admin", false, None).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn with_client(mut self, client: Arc<reqwest::Client>) -> Self {
+        self.bulk_installer = self.bulk_installer.with_client(client.clone());
+        self.single_installer = self.single_installer.with_client(client.clone());
+        self.tarball_installer = self.tarball_installer.with_client(client);
+        self
+    }
+
+    /// Whether full, single and tarball installs write `pacm.lock`.
+    #[must_use]
+    pub fn with_lockfile(mut self, lockfile: bool) -> Self {
+        self.bulk_installer = self.bulk_installer.with_lockfile(lockfile);
+        self.single_installer = self.single_installer.with_lockfile(lockfile);
+        self.tarball_installer = self.tarball_installer.with_lockfile(lockfile);
+        self
+    }
+
+    /// Lets single and batch installs move a package that is already declared into the
+    /// section they save to. Only an explicitly requested section should do that.
+    #[must_use]
+    pub fn with_move_existing(mut self, move_existing: bool) -> Self {
+        self.single_installer = self.single_installer.with_move_existing(move_existing);
+        self
+    }
+
     pub fn install_all(&self, project_dir: &str, debug: bool) -> Result<()> {
         self.bulk_installer.install_all(project_dir, debug)
     }
 
+    pub async fn install_all_async(
+        &self,
+        project_dir: &str,
+        debug: bool,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<()> {
+        self.bulk_installer
+            .install_all_async(project_dir, debug, cancel)
+            .await
+    }
+
     pub fn install_single(
         &self,
         project_dir: &str,
@@ -43,6 +135,18 @@ impl InstallManager {
         )
     }
 
+    pub fn install_tarball(
+        &self,
+        project_dir: &str,
+        source: &str,
+        dep_type: DependencyType,
+        no_save: bool,
+        debug: bool,
+    ) -> Result<()> {
+        self.tarball_installer
+            .install(project_dir, source, dep_type, no_save, debug)
+    }
+
     pub fn install_multiple(
         &self,
         project_dir: &str,
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use pacm_resolver::{bundled_dependencies, optional_peer_dependencies, peer_dependencies};
+
+/// The dependency sections of a stored package's `package.json`, with bundled
+/// dependencies and optional peers split out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoredDependencies {
+    pub dependencies: HashMap<String, String>,
+    pub optional_dependencies: HashMap<String, String>,
+    pub bundled: Vec<String>,
+    pub peer_dependencies: HashMap<String, String>,
+    pub optional_peer_dependencies: HashMap<String, String>,
+}
+
+/// Why a stored `package.json` could not be loaded.
+#[derive(Debug)]
+pub enum ManifestError {
+    Missing,
+    Read(std::io::Error),
+    Parse(serde_json::Error),
+}
+
+impl std::fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::Missing => write!(f, "no package.json"),
+            ManifestError::Read(e) => write!(f, "failed to read package.json: {}", e),
+            ManifestError::Parse(e) => write!(f, "failed to parse package.json: {}", e),
+        }
+    }
+}
+
+static CACHE: OnceLock<RwLock<HashMap<PathBuf, Arc<StoredDependencies>>>> = OnceLock::new();
+
+fn cache() -> &'static RwLock<HashMap<PathBuf, Arc<StoredDependencies>>> {
+    CACHE.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Reads the dependencies of the package stored at `store_path`. Store entries do not
+/// change once extracted, so the result is kept for the rest of the process and later
+/// installs skip the read and parse.
+pub fn load(store_path: &Path) -> Result<Arc<StoredDependencies>, ManifestError> {
+    if let Some(cached) = cache()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(store_path)
+    {
+        return Ok(cached.clone());
+    }
+
+    let package_json_path = store_path.join("package").join("package.json");
+    if !package_json_path.exists() {
+        return Err(ManifestError::Missing);
+    }
+    let content = std::fs::read_to_string(&package_json_path).map_err(ManifestError::Read)?;
+    let manifest: serde_json::Value =
+        serde_json::from_str(&content).map_err(ManifestError::Parse)?;
+
+    let parsed = Arc::new(parse(&manifest));
+    cache()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(store_path.to_path_buf(), parsed.clone());
+    Ok(parsed)
+}
+
+/// Forgets `store_path`, for when its store entry is replaced.
+pub fn invalidate(store_path: &Path) {
+    cache()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .remove(store_path);
+}
+
+/// Empties the cache. Used by benchmarks to measure cold reads.
+pub fn clear() {
+    cache()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+fn parse(manifest: &serde_json::Value) -> StoredDependencies {
+    let section = |key: &str| -> HashMap<String, String> {
+        manifest
+            .get(key)
+            .and_then(|d| d.as_object())
+            .map(|deps| {
+                deps.iter()
+                    .map(|(k, v)| (k.clone(), v.as_str().unwrap_or("*").to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut dependencies = section("dependencies");
+    let mut optional_dependencies = section("optionalDependencies");
+    let bundled = bundled_dependencies(manifest);
+    for name in &bundled {
+        dependencies.remove(name);
+        optional_dependencies.remove(name);
+    }
+
+    StoredDependencies {
+        dependencies,
+        optional_dependencies,
+        bundled,
+        peer_dependencies: peer_dependencies(manifest),
+        optional_peer_dependencies: optional_peer_dependencies(manifest),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_load_is_memoized_until_invalidated() {
+        let store = tempfile::tempdir().unwrap();
+        let package_dir = store.path().join("package");
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(
+            package_dir.join("package.json"),
+            r#"{ "dependencies": { "a": "^1.0.0", "b": "^2.0.0" }, "bundledDependencies": ["b"] }"#,
+        )
+        .unwrap();
+
+        let first = load(store.path()).unwrap();
+        assert_eq!(first.dependencies.len(), 1);
+        assert_eq!(first.bundled, vec!["b"]);
+
+        fs::write(package_dir.join("package.json"), "{}").unwrap();
+        assert!(Arc::ptr_eq(&first, &load(store.path()).unwrap()));
+
+        invalidate(store.path());
+        assert!(load(store.path()).unwrap().dependencies.is_empty());
+    }
+}
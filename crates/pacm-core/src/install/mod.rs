@@ -1,17 +1,32 @@
 pub mod bulk;
 pub mod cache;
+pub mod changes;
+pub mod dedupe;
+pub mod duplicates;
+pub mod exports;
 pub mod fast_path;
 pub mod hyper_cache;
+pub mod journal;
 pub mod manager;
+pub mod manifest_cache;
 pub mod optimizer;
+pub mod optional;
+pub mod options;
+pub mod peers;
+pub mod resolution_cache;
 pub mod resolver;
 pub mod single;
 pub mod smart_analyzer;
+pub mod tarball;
 pub mod types;
 pub mod utils;
+pub mod workspaces;
 
+pub use changes::LockfileChanges;
 pub use hyper_cache::HyperCache;
 pub use manager::InstallManager;
 pub use optimizer::DependencyOptimizer;
+pub use options::InstallOptions;
 pub use smart_analyzer::SmartDependencyAnalyzer;
+pub use tarball::TarballInstaller;
 pub use types::{CachedPackage, PackageSource};
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use pacm_config::PacmConfig;
 use pacm_constants::POPULAR_PACKAGES;
 use pacm_error::Result;
 use pacm_resolver::ResolvedPackage;
@@ -32,27 +33,22 @@ impl DependencyOptimizer {
 
                 async move {
                     if let Ok(pkg_data) =
-                        pacm_registry::fetch_package_info_async(client_clone, pkg_name).await
+                        pacm_resolver::package_info_async(client_clone, pkg_name, "latest").await
+                        && let Some(latest_version) = pkg_data.dist_tags.get("latest")
                     {
-                        if let Some(latest_version) = pkg_data.dist_tags.get("latest") {
-                            let key = format!("{}@latest", pkg_name);
-                            let resolved_pkg = ResolvedPackage {
-                                name: pkg_name.to_string(),
-                                version: latest_version.clone(),
-                                resolved: format!(
-                                    "https://registry.npmjs.org/{}/-/{}-{}.tgz",
-                                    pkg_name, pkg_name, latest_version
-                                ),
-                                integrity: String::new(),
-                                dependencies: HashMap::new(),
-                                optional_dependencies: HashMap::new(),
-                                os: None,
-                                cpu: None,
-                            };
+                        let key = format!("{}@latest", pkg_name);
+                        let resolved_pkg = ResolvedPackage {
+                            name: pkg_name.to_string(),
+                            version: latest_version.clone(),
+                            resolved: pkg_data.dist_tarball(latest_version).map_or_else(
+                                || PacmConfig::get().tarball_url(pkg_name, latest_version),
+                                str::to_string,
+                            ),
+                            ..Default::default()
+                        };
 
-                            let mut cache_write = cache.write().await;
-                            cache_write.insert(key, vec![resolved_pkg]);
-                        }
+                        let mut cache_write = cache.write().await;
+                        cache_write.insert(key, vec![resolved_pkg]);
                     }
                 }
             })
@@ -0,0 +1,112 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use pacm_resolver::{ResolvedPackage, satisfies};
+
+/// Keys of the resolved packages that are only needed through `optionalDependencies`,
+/// either the project's own (`direct_optional`) or those of another package. Everything
+/// reachable over plain `dependencies` from a required package is itself required; the
+/// roots are the project's `direct_required` dependencies and packages nothing depends
+/// on that the project does not list as optional.
+pub fn optional_only(
+    resolved: &HashMap<String, ResolvedPackage>,
+    direct_required: &HashSet<String>,
+    direct_optional: &HashSet<String>,
+) -> HashSet<String> {
+    let mut by_name: HashMap<&str, Vec<&ResolvedPackage>> = HashMap::new();
+    for pkg in resolved.values() {
+        by_name.entry(&pkg.name).or_default().push(pkg);
+    }
+    let depended_on: HashSet<&str> = resolved
+        .values()
+        .flat_map(|pkg| {
+            pkg.dependencies
+                .keys()
+                .chain(pkg.optional_dependencies.keys())
+                .filter(move |name| **name != pkg.name)
+                .map(String::as_str)
+        })
+        .collect();
+
+    let mut required = HashSet::new();
+    let mut queue: VecDeque<&ResolvedPackage> = resolved
+        .values()
+        .filter(|pkg| {
+            direct_required.contains(&pkg.name)
+                || (!depended_on.contains(pkg.name.as_str())
+                    && !direct_optional.contains(&pkg.name))
+        })
+        .collect();
+
+    while let Some(pkg) = queue.pop_front() {
+        if !required.insert(format!("{}@{}", pkg.name, pkg.version)) {
+            continue;
+        }
+        for (name, range) in &pkg.dependencies {
+            let Some(targets) = by_name.get(name.as_str()) else {
+                continue;
+            };
+            let matching: Vec<_> = targets
+                .iter()
+                .filter(|target| satisfies(&target.version, range))
+                .collect();
+            if matching.is_empty() {
+                queue.extend(targets.iter().copied());
+            } else {
+                queue.extend(matching.into_iter().copied());
+            }
+        }
+    }
+
+    resolved
+        .keys()
+        .filter(|key| !required.contains(*key))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_testutil::resolved_package;
+
+    fn package(
+        name: &str,
+        dependencies: &[&str],
+        optional_dependencies: &[&str],
+    ) -> (String, ResolvedPackage) {
+        let section = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| (name.to_string(), "^1.0.0".to_string()))
+                .collect()
+        };
+        let pkg = ResolvedPackage {
+            dependencies: section(dependencies),
+            optional_dependencies: section(optional_dependencies),
+            ..resolved_package(name, "1.0.0", &[])
+        };
+        (format!("{name}@1.0.0"), pkg)
+    }
+
+    #[test]
+    fn test_only_packages_behind_optional_edges_are_optional() {
+        let resolved = HashMap::from([
+            package("app-dep", &["shared"], &["native"]),
+            package("native", &["native-helper", "shared"], &[]),
+            package("native-helper", &[], &[]),
+            package("shared", &[], &[]),
+            package("extra", &[], &[]),
+        ]);
+        let direct_required = HashSet::from(["app-dep".to_string()]);
+        let direct_optional = HashSet::from(["extra".to_string()]);
+
+        let mut optional: Vec<_> = optional_only(&resolved, &direct_required, &direct_optional)
+            .into_iter()
+            .collect();
+        optional.sort();
+        assert_eq!(
+            optional,
+            vec!["extra@1.0.0", "native-helper@1.0.0", "native@1.0.0"]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use pacm_project::{DependencySelection, DependencyType};
+
+/// How the library install functions install and save packages. Start from
+/// [`InstallOptions::new`] and set only what differs from a plain `pacm install`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstallOptions {
+    /// The `package.json` section new packages are saved to.
+    pub dep_type: DependencyType,
+    /// Move packages that are already declared in another section to `dep_type`. Set
+    /// when the section was asked for explicitly, like `--dev`.
+    pub move_existing: bool,
+    /// Save the resolved version instead of a caret range.
+    pub save_exact: bool,
+    /// Install without touching `package.json`.
+    pub no_save: bool,
+    /// Download and relink packages even when they are already installed.
+    pub force: bool,
+    /// The sections a full install covers.
+    pub selection: DependencySelection,
+    /// How many levels of transitive dependencies a full install resolves.
+    pub max_depth: Option<usize>,
+    pub debug: bool,
+}
+
+impl Default for InstallOptions {
+    fn default() -> Self {
+        Self {
+            dep_type: DependencyType::Dependencies,
+            move_existing: false,
+            save_exact: false,
+            no_save: false,
+            force: false,
+            selection: DependencySelection::default(),
+            max_depth: None,
+            debug: false,
+        }
+    }
+}
+
+impl InstallOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn dep_type(mut self, dep_type: DependencyType) -> Self {
+        self.dep_type = dep_type;
+        self
+    }
+
+    #[must_use]
+    pub fn move_existing(mut self, move_existing: bool) -> Self {
+        self.move_existing = move_existing;
+        self
+    }
+
+    #[must_use]
+    pub fn save_exact(mut self, save_exact: bool) -> Self {
+        self.save_exact = save_exact;
+        self
+    }
+
+    #[must_use]
+    pub fn no_save(mut self, no_save: bool) -> Self {
+        self.no_save = no_save;
+        self
+    }
+
+    #[must_use]
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    #[must_use]
+    pub fn selection(mut self, selection: DependencySelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    #[must_use]
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    #[must_use]
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+}
@@ -0,0 +1,149 @@
+use futures::future::join_all;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use super::resolver::resolution_error;
+use pacm_error::{PackageManagerError, Result};
+use pacm_lock::PacmLock;
+use pacm_logger;
+use pacm_resolver::semver::resolve_version;
+use pacm_resolver::{package_info_async, peer_dependencies, satisfies};
+
+/// Finds the peer dependencies of direct dependencies that the project does not declare
+/// itself, for `install-peers=true`. Like npm's auto-install of peers, each one is added
+/// at the newest version that satisfies every direct dependency asking for it.
+pub struct PeerResolver {
+    client: Arc<reqwest::Client>,
+}
+
+impl PeerResolver {
+    pub fn new(client: Arc<reqwest::Client>) -> Self {
+        Self { client }
+    }
+
+    /// Returns `(name, version)` for each missing peer of `direct`, which maps the
+    /// project's dependencies to the range or locked version they install. A locked peer
+    /// is kept at its locked version while it still satisfies every requirer.
+    pub async fn missing_peers(
+        &self,
+        direct: &HashMap<String, String>,
+        lockfile: Option<&PacmLock>,
+        debug: bool,
+    ) -> Result<Vec<(String, String)>> {
+        let mut names: Vec<(&String, &String)> = direct.iter().collect();
+        names.sort();
+
+        let lookups = join_all(
+            names
+                .iter()
+                .map(|(name, range)| self.peers_of(name, range, debug)),
+        )
+        .await;
+
+        // Peer name => (requirer, range), in a stable order for error messages.
+        let mut wanted: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        for ((name, _), peers) in names.iter().zip(lookups) {
+            for (peer, range) in peers {
+                if !direct.contains_key(&peer) {
+                    wanted
+                        .entry(peer)
+                        .or_default()
+                        .push((name.to_string(), range));
+                }
+            }
+        }
+
+        let mut missing = Vec::with_capacity(wanted.len());
+        for (peer, requirers) in wanted {
+            let version = self.compatible_version(&peer, &requirers, lockfile).await?;
+            pacm_logger::debug(
+                &format!(
+                    "Installing missing peer {}@{} (required by {})",
+                    peer,
+                    version,
+                    requirers
+                        .iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                debug,
+            );
+            missing.push((peer, version));
+        }
+        Ok(missing)
+    }
+
+    /// The required peers of the version `name@range` resolves to. Dependencies that
+    /// cannot be resolved here are left for the main resolution to report.
+    async fn peers_of(&self, name: &str, range: &str, debug: bool) -> HashMap<String, String> {
+        let manifest = match package_info_async(self.client.clone(), name, range).await {
+            Ok(info) => resolve_version(&info.versions, range, &info.dist_tags)
+                .ok()
+                .and_then(|version| info.versions.get(&version).cloned()),
+            Err(e) => {
+                pacm_logger::debug(
+                    &format!("Skipping peer dependencies of {}: {}", name, e),
+                    debug,
+                );
+                None
+            }
+        };
+
+        manifest
+            .map(|manifest| peer_dependencies(&manifest))
+            .unwrap_or_default()
+    }
+
+    async fn compatible_version(
+        &self,
+        peer: &str,
+        requirers: &[(String, String)],
+        lockfile: Option<&PacmLock>,
+    ) -> Result<String> {
+        let satisfies_all =
+            |version: &str| requirers.iter().all(|(_, range)| satisfies(version, range));
+
+        if let Some(locked) = lockfile.and_then(|lock| lock.get_package(peer))
+            && satisfies_all(&locked.version)
+        {
+            return Ok(locked.version.clone());
+        }
+
+        let info = package_info_async(self.client.clone(), peer, &requirers[0].1)
+            .await
+            .map_err(|e| resolution_error(peer, e))?;
+
+        let mut candidates: Vec<semver::Version> = info
+            .versions
+            .as_object()
+            .map(|versions| {
+                versions
+                    .keys()
+                    .filter_map(|v| semver::Version::parse(v).ok())
+                    .filter(|v| v.pre.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        candidates.sort();
+
+        candidates
+            .iter()
+            .rev()
+            .map(ToString::to_string)
+            .find(|version| satisfies_all(version))
+            .ok_or_else(|| {
+                PackageManagerError::DependencyConflict(
+                    peer.to_string(),
+                    format!(
+                        "no version satisfies every peer range ({})",
+                        requirers
+                            .iter()
+                            .map(|(name, range)| format!("{} requires {}", name, range))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                )
+            })
+    }
+}
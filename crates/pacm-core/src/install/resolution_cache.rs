@@ -0,0 +1,179 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use pacm_config::PacmConfig;
+use pacm_constants::VERSION;
+use pacm_project::DependencySelection;
+use pacm_registry::MetadataCache;
+use pacm_resolver::{Overrides, ResolvedPackage};
+use pacm_store::get_cache_path;
+
+#[derive(Serialize, Deserialize)]
+struct StoredResolution {
+    /// [`MetadataCache::modified`] when the graph was saved.
+    metadata: Option<SystemTime>,
+    packages: Vec<ResolvedPackage>,
+}
+
+/// Resolved dependency graphs of earlier installs, kept as
+/// `<cache>/resolutions/<hash>.json`. The hash covers everything that feeds into
+/// resolution, so a later install with the same inputs can skip the registry entirely.
+/// A graph is only reused while the saved registry metadata it was resolved from is
+/// unchanged, so newly published versions are picked up as soon as any install saves
+/// them.
+pub struct ResolutionCache {
+    dir: PathBuf,
+    metadata: MetadataCache,
+}
+
+impl Default for ResolutionCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResolutionCache {
+    pub fn new() -> Self {
+        Self::in_dir(&get_cache_path())
+    }
+
+    /// The graphs saved under `cache_dir`, checked against the metadata saved there.
+    pub fn in_dir(cache_dir: &Path) -> Self {
+        Self {
+            dir: cache_dir.join("resolutions"),
+            metadata: MetadataCache::new(cache_dir.join("metadata")),
+        }
+    }
+
+    /// Hashes the requested dependencies together with the registries, `network-mode`,
+    /// the other configured values and `overrides` that change what they resolve to.
+    /// `deps` are ranges from `package.json`, or exact versions when installing from a
+    /// lockfile.
+    pub fn key(
+        deps: &[(String, String)],
+        use_lockfile: bool,
+        selection: DependencySelection,
+        overrides: &Overrides,
+    ) -> String {
+        let config = PacmConfig::get();
+        let mut deps: Vec<_> = deps.iter().collect();
+        deps.sort();
+        let mut scoped: Vec<_> = config.scoped_registries.iter().collect();
+        scoped.sort();
+        let mut values: Vec<_> = config.values.iter().collect();
+        values.sort();
+
+        let mut hasher = Sha256::new();
+        hasher.update(format!(
+            "{VERSION}\n{}\n{:?}\n{:?}\n{:?}\n{use_lockfile}\n{:?}\n{:?}\n",
+            config.registry,
+            scoped,
+            config.network_mode(),
+            values,
+            selection,
+            overrides
+        ));
+        for (name, range) in deps {
+            hasher.update(format!("{name}@{range}\n"));
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// The graph stored under `key`, unless it is missing or unreadable, or registry
+    /// metadata was saved since.
+    pub fn load(&self, key: &str) -> Option<Vec<ResolvedPackage>> {
+        let content = fs::read_to_string(self.path(key)).ok()?;
+        let stored: StoredResolution = serde_json::from_str(&content).ok()?;
+        (stored.metadata == self.metadata.modified()).then_some(stored.packages)
+    }
+
+    /// Stores `packages` under `key`. The cache is only an optimisation, so failures are
+    /// ignored.
+    pub fn save(&self, key: &str, packages: &[ResolvedPackage]) {
+        let stored = StoredResolution {
+            metadata: self.metadata.modified(),
+            packages: packages.to_vec(),
+        };
+        let Ok(content) = serde_json::to_string(&stored) else {
+            return;
+        };
+
+        // Written under a unique name and renamed so concurrent installs never read a
+        // half-written graph.
+        let path = self.path(key);
+        let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        let written = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&temp_path, content))
+            .and_then(|_| fs::rename(&temp_path, &path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_testutil::resolved_package;
+
+    #[test]
+    fn test_key_ignores_dependency_order() {
+        let a = ("a".to_string(), "^1.0.0".to_string());
+        let b = ("b".to_string(), "^2.0.0".to_string());
+        let selection = DependencySelection::default();
+        let none = Overrides::default();
+        let overrides = Overrides::parse(&serde_json::json!({ "a>c": "1.0.0" }));
+
+        assert_eq!(
+            ResolutionCache::key(&[a.clone(), b.clone()], false, selection, &none),
+            ResolutionCache::key(&[b.clone(), a.clone()], false, selection, &none)
+        );
+        assert_ne!(
+            ResolutionCache::key(&[a.clone(), b.clone()], false, selection, &none),
+            ResolutionCache::key(&[a.clone(), b.clone()], true, selection, &none)
+        );
+        assert_ne!(
+            ResolutionCache::key(&[a.clone(), b.clone()], false, selection, &none),
+            ResolutionCache::key(&[a, b], false, selection, &overrides)
+        );
+    }
+
+    #[test]
+    fn test_resolution_is_ignored_once_metadata_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResolutionCache::in_dir(dir.path());
+
+        cache.save("graph", &[resolved_package("cached-pkg", "1.0.0", &[])]);
+        let loaded = cache.load("graph").unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].name, "cached-pkg");
+        assert!(cache.load("missing").is_none());
+
+        MetadataCache::new(dir.path().join("metadata"))
+            .write(
+                "cached-pkg",
+                &pacm_registry::MetadataSource {
+                    url: "https://registry.npmjs.org/cached-pkg".to_string(),
+                    full: false,
+                },
+                &pacm_registry::PackageInfo {
+                    versions: serde_json::json!({}),
+                    dist_tags: Default::default(),
+                },
+                None,
+            )
+            .unwrap();
+        assert!(cache.load("graph").is_none());
+
+        cache.save("graph", &[resolved_package("cached-pkg", "1.0.0", &[])]);
+        assert!(cache.load("graph").is_some());
+    }
+}
@@ -4,17 +4,25 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 
 use super::cache::CacheManager;
+use super::manifest_cache::{self, StoredDependencies};
 use super::types::CachedPackage;
+use crate::timing::{self, Phase};
+use pacm_config::PacmConfig;
 use pacm_constants::USER_AGENT;
 use pacm_error::{PackageManagerError, Result};
 use pacm_logger;
 use pacm_registry;
-use pacm_resolver::{ResolvedPackage, resolve_full_tree_async};
+use pacm_resolver::{Overrides, ResolutionLimits, ResolvedPackage};
 use pacm_symcap::SystemCapabilities;
 
 pub struct DependencyResolver {
     client: Arc<reqwest::Client>,
     resolution_cache: Arc<Mutex<HashMap<String, Vec<ResolvedPackage>>>>,
+    include_optional: bool,
+    max_depth: Option<usize>,
+    overrides: Arc<Overrides>,
+    preferred: Arc<HashMap<String, Vec<String>>>,
+    limits: Arc<ResolutionLimits>,
 }
 
 impl DependencyResolver {
@@ -24,7 +32,7 @@ impl DependencyResolver {
 
         Self {
             client: Arc::new(
-                reqwest::Client::builder()
+                pacm_registry::configure_client(reqwest::Client::builder())
                     .pool_max_idle_per_host(pool_size)
                     .pool_idle_timeout(Some(std::time::Duration::from_secs(90)))
                     .timeout(std::time::Duration::from_secs(30)) // Reduced from 45s
@@ -36,96 +44,121 @@ impl DependencyResolver {
                     .unwrap_or_else(|_| reqwest::Client::new()),
             ),
             resolution_cache: Arc::new(Mutex::new(HashMap::with_capacity(2000))), // Increased capacity
+            include_optional: true,
+            max_depth: None,
+            overrides: Arc::new(Overrides::default()),
+            preferred: Arc::new(HashMap::new()),
+            limits: Arc::new(ResolutionLimits::from_config()),
         }
     }
 
+    /// Sends registry requests through `client` instead of a client of its own, so
+    /// several resolvers and downloaders can share one connection pool.
+    #[must_use]
+    pub fn with_client(mut self, client: Arc<reqwest::Client>) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Controls whether `optionalDependencies` are resolved and installed.
+    #[must_use]
+    pub fn with_optional(mut self, include_optional: bool) -> Self {
+        self.include_optional = include_optional;
+        self
+    }
+
+    /// Limits resolution to `max_depth` levels below each direct dependency.
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Applies the project's `overrides` while resolving.
+    #[must_use]
+    pub fn with_overrides(mut self, overrides: Arc<Overrides>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Resolves ranges to these versions, by name, when they allow one.
+    #[must_use]
+    pub fn with_preferred_versions(mut self, preferred: HashMap<String, Vec<String>>) -> Self {
+        self.preferred = Arc::new(preferred);
+        self
+    }
+
+    /// Stops resolving once the tree outgrows `limits`, counted over every resolution
+    /// this resolver runs. Defaults to the `max-resolution-depth` and `max-packages`
+    /// settings.
+    #[must_use]
+    pub fn with_resolution_limits(mut self, limits: Arc<ResolutionLimits>) -> Self {
+        self.limits = limits;
+        self
+    }
+
     pub fn get_client(&self) -> Arc<reqwest::Client> {
         self.client.clone()
     }
 
+    pub fn resolution_limits(&self) -> Arc<ResolutionLimits> {
+        self.limits.clone()
+    }
+
     fn read_dependencies_from_cached_package(
         cached_package: &CachedPackage,
         debug: bool,
-    ) -> (HashMap<String, String>, HashMap<String, String>) {
-        let package_json_path = cached_package
-            .store_path
-            .join("package")
-            .join("package.json");
+    ) -> StoredDependencies {
+        match manifest_cache::load(&cached_package.store_path) {
+            Ok(stored) => {
+                if debug
+                    && (!stored.dependencies.is_empty() || !stored.optional_dependencies.is_empty())
+                {
+                    pacm_logger::debug(
+                        &format!(
+                            "Read {} dependencies and {} optional dependencies from {}",
+                            stored.dependencies.len(),
+                            stored.optional_dependencies.len(),
+                            cached_package.name
+                        ),
+                        debug,
+                    );
+                }
 
-        if !package_json_path.exists() {
-            if debug {
-                pacm_logger::debug(
-                    &format!(
-                        "No package.json found for cached package {}",
-                        cached_package.name
-                    ),
-                    debug,
-                );
+                stored.as_ref().clone()
             }
-            return (HashMap::new(), HashMap::new());
-        }
-
-        match std::fs::read_to_string(&package_json_path) {
-            Ok(content) => match serde_json::from_str::<serde_json::Value>(&content) {
-                Ok(pkg_data) => {
-                    let dependencies: HashMap<String, String> = pkg_data
-                        .get("dependencies")
-                        .and_then(|d| d.as_object())
-                        .map(|deps| {
-                            deps.iter()
-                                .map(|(k, v)| (k.clone(), v.as_str().unwrap_or("*").to_string()))
-                                .collect()
-                        })
-                        .unwrap_or_default();
-
-                    let optional_dependencies: HashMap<String, String> = pkg_data
-                        .get("optionalDependencies")
-                        .and_then(|d| d.as_object())
-                        .map(|deps| {
-                            deps.iter()
-                                .map(|(k, v)| (k.clone(), v.as_str().unwrap_or("*").to_string()))
-                                .collect()
-                        })
-                        .unwrap_or_default();
-
-                    if debug && (!dependencies.is_empty() || !optional_dependencies.is_empty()) {
-                        pacm_logger::debug(
-                            &format!(
-                                "Read {} dependencies and {} optional dependencies from {}",
-                                dependencies.len(),
-                                optional_dependencies.len(),
-                                cached_package.name
-                            ),
-                            debug,
-                        );
-                    }
-
-                    (dependencies, optional_dependencies)
-                }
-                Err(e) => {
-                    if debug {
-                        pacm_logger::debug(
-                            &format!(
-                                "Failed to parse package.json for {}: {}",
-                                cached_package.name, e
-                            ),
-                            debug,
-                        );
-                    }
-                    (HashMap::new(), HashMap::new())
-                }
-            },
             Err(e) => {
                 if debug {
                     pacm_logger::debug(
-                        &format!(
-                            "Failed to read package.json for {}: {}",
-                            cached_package.name, e
-                        ),
+                        &format!("Cached package {}: {}", cached_package.name, e),
                         debug,
                     );
                 }
-                (HashMap::new(), HashMap::new())
+                StoredDependencies::default()
+            }
+        }
+    }
+
+    /// A cached direct dependency still needs its own dependencies installed, so they are
+    /// queued for resolution alongside the uncached direct dependencies.
+    fn queue_cached_children(
+        &self,
+        dependencies: &HashMap<String, String>,
+        optional_dependencies: &HashMap<String, String>,
+        packages_to_resolve: &mut Vec<(String, String)>,
+    ) {
+        if self.max_depth == Some(0) {
+            return;
+        }
+
+        let optional = optional_dependencies
+            .iter()
+            .filter(|_| self.include_optional);
+
+        for (name, range) in dependencies.iter().chain(optional) {
+            let entry = (name.clone(), range.clone());
+            if !packages_to_resolve.contains(&entry) {
+                packages_to_resolve.push(entry);
             }
         }
     }
@@ -189,18 +222,24 @@ impl DependencyResolver {
                 cached_packages.push(cached.clone());
                 let key = format!("{}@{}", cached.name, cached.version);
 
-                let (dependencies, optional_dependencies) =
-                    Self::read_dependencies_from_cached_package(&cached, debug);
+                let stored = Self::read_dependencies_from_cached_package(&cached, debug);
+                self.queue_cached_children(
+                    &stored.dependencies,
+                    &stored.optional_dependencies,
+                    &mut packages_to_resolve,
+                );
 
                 let resolved_pkg = ResolvedPackage {
                     name: cached.name.clone(),
                     version: cached.version.clone(),
                     resolved: cached.resolved.clone(),
                     integrity: cached.integrity.clone(),
-                    dependencies,
-                    optional_dependencies,
-                    os: None,
-                    cpu: None,
+                    dependencies: stored.dependencies,
+                    optional_dependencies: stored.optional_dependencies,
+                    bundled_dependencies: stored.bundled,
+                    peer_dependencies: stored.peer_dependencies,
+                    optional_peer_dependencies: stored.optional_peer_dependencies,
+                    ..Default::default()
                 };
                 all_resolved.insert(key, resolved_pkg);
             } else {
@@ -261,6 +300,7 @@ impl DependencyResolver {
         _use_lockfile: bool,
         debug: bool,
     ) -> Result<(HashSet<String>, HashMap<String, ResolvedPackage>)> {
+        let started = std::time::Instant::now();
         let system_caps = SystemCapabilities::get();
         let mut direct_package_names = HashSet::with_capacity(direct_deps.len());
         for (name, _) in direct_deps {
@@ -283,6 +323,11 @@ impl DependencyResolver {
         }
 
         let client = self.client.clone();
+        let include_optional = self.include_optional;
+        let max_depth = self.max_depth;
+        let overrides = self.overrides.clone();
+        let preferred = self.preferred.clone();
+        let limits = self.limits.clone();
         let resolution_cache = self.resolution_cache.clone();
 
         let mut all_resolved_packages = Vec::with_capacity(direct_deps.len() * 8);
@@ -303,6 +348,9 @@ impl DependencyResolver {
                 .iter()
                 .map(|(name, version_or_range)| {
                     let client = client.clone();
+                    let overrides = overrides.clone();
+                    let preferred = preferred.clone();
+                    let limits = limits.clone();
                     let resolution_cache = resolution_cache.clone();
                     let name = name.clone();
                     let version_or_range = version_or_range.clone();
@@ -317,43 +365,44 @@ impl DependencyResolver {
                             }
                         }
 
-                        if system_caps.should_skip_transitive_analysis(&name) {
-                            if let Ok(pkg_data) =
-                                pacm_registry::fetch_package_info_async(client.clone(), &name).await
-                            {
-                                if let Some(latest_version) = pkg_data.dist_tags.get("latest") {
-                                    let simple_pkg = ResolvedPackage {
-                                        name: name.clone(),
-                                        version: latest_version.clone(),
-                                        resolved: format!(
-                                            "https://registry.npmjs.org/{}/-/{}-{}.tgz",
-                                            name, name, latest_version
-                                        ),
-                                        integrity: String::new(),
-                                        dependencies: HashMap::new(), // Skip dependency resolution for simple packages
-                                        optional_dependencies: HashMap::new(),
-                                        os: None,
-                                        cpu: None,
-                                    };
-
-                                    let result = vec![simple_pkg];
-                                    let mut cache = resolution_cache.lock().await;
-                                    cache.insert(cache_key, result.clone());
-                                    return Ok(result);
-                                }
-                            }
+                        if system_caps.should_skip_transitive_analysis(&name)
+                            && overrides.range_for(&name, &[]).is_none()
+                            && !preferred.contains_key(&name)
+                            && let Ok(pkg_data) =
+                                pacm_resolver::package_info_async(client.clone(), &name, "latest")
+                                    .await
+                            && let Some(latest_version) = pkg_data.dist_tags.get("latest")
+                        {
+                            let simple_pkg = ResolvedPackage {
+                                name: name.clone(),
+                                version: latest_version.clone(),
+                                resolved: pkg_data.dist_tarball(latest_version).map_or_else(
+                                    || PacmConfig::get().tarball_url(&name, latest_version),
+                                    str::to_string,
+                                ),
+                                ..Default::default() // Skip dependency resolution for simple packages
+                            };
+
+                            let result = vec![simple_pkg];
+                            let mut cache = resolution_cache.lock().await;
+                            cache.insert(cache_key, result.clone());
+                            return Ok(result);
                         }
 
                         let mut seen = HashSet::with_capacity(100);
-                        let result =
-                            resolve_full_tree_async(client, &name, &version_or_range, &mut seen)
-                                .await
-                                .map_err(|e| {
-                                    PackageManagerError::VersionResolutionFailed(
-                                        name.clone(),
-                                        format!("Failed to resolve {}: {}", name, e),
-                                    )
-                                });
+                        let result = resolve_tree(
+                            client,
+                            &name,
+                            &version_or_range,
+                            &mut seen,
+                            include_optional,
+                            max_depth,
+                            overrides,
+                            preferred,
+                            limits,
+                        )
+                        .await
+                        .map_err(|e| resolution_error(&name, e));
 
                         if let Ok(ref packages) = result {
                             let mut cache = resolution_cache.lock().await;
@@ -406,6 +455,7 @@ impl DependencyResolver {
             );
         }
 
+        timing::record(Phase::Resolution, started.elapsed());
         Ok((direct_package_names, unique_packages))
     }
 
@@ -541,15 +591,24 @@ impl DependencyResolver {
                 }
                 cached_packages.push(cached.clone());
 
+                let stored = Self::read_dependencies_from_cached_package(&cached, debug);
+                self.queue_cached_children(
+                    &stored.dependencies,
+                    &stored.optional_dependencies,
+                    &mut packages_to_resolve,
+                );
+
                 let resolved_pkg = ResolvedPackage {
                     name: cached.name.clone(),
                     version: cached.version.clone(),
                     resolved: cached.resolved.clone(),
                     integrity: cached.integrity.clone(),
-                    dependencies: HashMap::new(), // Will be filled if needed
-                    optional_dependencies: HashMap::new(),
-                    os: None,
-                    cpu: None,
+                    dependencies: stored.dependencies,
+                    optional_dependencies: stored.optional_dependencies,
+                    bundled_dependencies: stored.bundled,
+                    peer_dependencies: stored.peer_dependencies,
+                    optional_peer_dependencies: stored.optional_peer_dependencies,
+                    ..Default::default()
                 };
 
                 let key = format!("{}@{}", cached.name, cached.version);
@@ -572,9 +631,11 @@ impl DependencyResolver {
             let batches: Vec<_> = packages_to_resolve.chunks(batch_size).collect();
 
             for batch in batches {
+                let batch_start = std::time::Instant::now();
                 let (additional_cached, to_download, additional_resolved) = self
                     .resolve_batch_optimized(batch, cache_manager, debug)
                     .await?;
+                timing::record(Phase::Resolution, batch_start.elapsed());
 
                 cached_packages.extend(additional_cached);
                 packages_to_download.extend(to_download);
@@ -621,12 +682,20 @@ impl DependencyResolver {
         }
 
         let client = self.client.clone();
+        let include_optional = self.include_optional;
+        let max_depth = self.max_depth;
+        let overrides = self.overrides.clone();
+        let preferred = self.preferred.clone();
+        let limits = self.limits.clone();
         let resolution_cache = self.resolution_cache.clone();
 
         let resolve_tasks: Vec<_> = packages
             .iter()
             .map(|(name, version_range)| {
                 let client = client.clone();
+                let overrides = overrides.clone();
+                let preferred = preferred.clone();
+                let limits = limits.clone();
                 let resolution_cache = resolution_cache.clone();
                 let name = name.clone();
                 let version_range = version_range.clone();
@@ -642,14 +711,19 @@ impl DependencyResolver {
                     }
 
                     let mut seen = HashSet::with_capacity(50);
-                    let result = resolve_full_tree_async(client, &name, &version_range, &mut seen)
-                        .await
-                        .map_err(|e| {
-                            PackageManagerError::VersionResolutionFailed(
-                                name.clone(),
-                                format!("Failed to resolve {}: {}", name, e),
-                            )
-                        });
+                    let result = resolve_tree(
+                        client,
+                        &name,
+                        &version_range,
+                        &mut seen,
+                        include_optional,
+                        max_depth,
+                        overrides,
+                        preferred,
+                        limits,
+                    )
+                    .await
+                    .map_err(|e| resolution_error(&name, e));
 
                     if let Ok(ref packages) = result {
                         let mut cache = resolution_cache.lock().await;
@@ -726,7 +800,18 @@ impl DependencyResolver {
             }
 
             let mut seen = HashSet::with_capacity(50);
-            match resolve_full_tree_async(self.client.clone(), name, version_range, &mut seen).await
+            match resolve_tree(
+                self.client.clone(),
+                name,
+                version_range,
+                &mut seen,
+                self.include_optional,
+                self.max_depth,
+                self.overrides.clone(),
+                self.preferred.clone(),
+                self.limits.clone(),
+            )
+            .await
             {
                 Ok(resolved_tree) => {
                     {
@@ -740,10 +825,7 @@ impl DependencyResolver {
                     }
                 }
                 Err(e) => {
-                    return Err(PackageManagerError::VersionResolutionFailed(
-                        name.clone(),
-                        format!("Failed to resolve {}: {}", name, e),
-                    ));
+                    return Err(resolution_error(name, e));
                 }
             }
         }
@@ -756,8 +838,42 @@ impl DependencyResolver {
     }
 }
 
+async fn resolve_tree(
+    client: Arc<reqwest::Client>,
+    name: &str,
+    version_range: &str,
+    seen: &mut HashSet<String>,
+    include_optional: bool,
+    max_depth: Option<usize>,
+    overrides: Arc<Overrides>,
+    preferred: Arc<HashMap<String, Vec<String>>>,
+    limits: Arc<ResolutionLimits>,
+) -> anyhow::Result<Vec<ResolvedPackage>> {
+    pacm_resolver::DependencyResolver::new()
+        .with_optional(include_optional)
+        .with_max_depth(max_depth)
+        .with_overrides(overrides)
+        .with_preferred_versions(preferred)
+        .with_limits(limits)
+        .with_fetch_timer(timing::record_package_resolve)
+        .resolve_full_tree_async(client, name, version_range, seen)
+        .await
+}
+
 impl Default for DependencyResolver {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// Converts a resolver failure into a `PackageManagerError`, keeping the resolution path
+/// when the resolver reported where in the tree it failed.
+pub(crate) fn resolution_error(name: &str, error: anyhow::Error) -> PackageManagerError {
+    match error.downcast::<PackageManagerError>() {
+        Ok(error) => error,
+        Err(error) => PackageManagerError::VersionResolutionFailed(
+            name.to_string(),
+            format!("Failed to resolve {}: {}", name, error),
+        ),
+    }
+}
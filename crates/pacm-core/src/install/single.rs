@@ -1,17 +1,24 @@
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use super::cache::CacheManager;
+use super::duplicates::merge_duplicate_requests;
+use super::exports::warn_broken_entry_points;
 use super::fast_path::{FastPathAnalyzer, InstallationPath};
+use pacm_config::PacmConfig;
 use pacm_error::{PackageManagerError, Result};
+use pacm_lock::PacmLock;
 use pacm_logger;
-use pacm_project::DependencyType;
+use pacm_project::{DependencyType, read_package_json};
 use pacm_resolver::{ResolvedPackage, is_platform_compatible};
 
 use crate::download::PackageDownloader;
 use crate::linker::PackageLinker;
+use crate::linker::npm_compat::NpmCompat;
+use crate::timing;
 
-use super::resolver::DependencyResolver;
+use super::resolver::{DependencyResolver, resolution_error};
 use super::types::CachedPackage;
 
 pub struct SingleInstaller {
@@ -20,6 +27,11 @@ pub struct SingleInstaller {
     cache: CacheManager,
     resolver: DependencyResolver,
     fast_path_analyzer: FastPathAnalyzer,
+    prefer_dedupe: bool,
+    verify_exports: bool,
+    npm_compat: bool,
+    lockfile: bool,
+    move_existing: bool,
 }
 
 impl SingleInstaller {
@@ -29,13 +41,66 @@ impl SingleInstaller {
 
         Self {
             downloader: PackageDownloader::new(),
-            linker: PackageLinker {},
+            linker: PackageLinker::new(),
             cache,
             resolver: DependencyResolver::new(),
             fast_path_analyzer,
+            prefer_dedupe: PacmConfig::get().prefer_dedupe(),
+            verify_exports: PacmConfig::get().verify_exports(),
+            npm_compat: PacmConfig::get().npm_compat(),
+            lockfile: PacmConfig::get().lockfile(),
+            move_existing: false,
         }
     }
 
+    /// Resolves the dependencies of a `--force` reinstall to versions `pacm.lock`
+    /// already has, when their ranges allow it. Defaults to the `prefer-dedupe` setting.
+    #[must_use]
+    pub fn with_prefer_dedupe(mut self, prefer_dedupe: bool) -> Self {
+        self.prefer_dedupe = prefer_dedupe;
+        self
+    }
+
+    /// Warns when an installed package's `main` or `exports` name missing files.
+    /// Defaults to the `verify-exports` setting.
+    #[must_use]
+    pub fn with_verify_exports(mut self, verify_exports: bool) -> Self {
+        self.verify_exports = verify_exports;
+        self
+    }
+
+    /// Also writes `node_modules/.package-lock.json` from `pacm.lock` once installed.
+    /// Defaults to the `npm-compat` setting.
+    #[must_use]
+    pub fn with_npm_compat(mut self, npm_compat: bool) -> Self {
+        self.npm_compat = npm_compat;
+        self
+    }
+
+    /// Resolves and downloads through `client` instead of clients of its own.
+    #[must_use]
+    pub fn with_client(mut self, client: Arc<reqwest::Client>) -> Self {
+        self.downloader = self.downloader.with_client(client.clone());
+        self.resolver = self.resolver.with_client(client);
+        self
+    }
+
+    /// Records installed packages in `pacm.lock`. Defaults to the `lockfile` setting.
+    #[must_use]
+    pub fn with_lockfile(mut self, lockfile: bool) -> Self {
+        self.lockfile = lockfile;
+        self
+    }
+
+    /// Moves a package that is already declared into the section being saved to, as an
+    /// explicit `--dev`, `--optional` or `--peer` does. Off by default, so reinstalling
+    /// a package leaves it where it is.
+    #[must_use]
+    pub fn with_move_existing(mut self, move_existing: bool) -> Self {
+        self.move_existing = move_existing;
+        self
+    }
+
     pub fn install(
         &self,
         project_dir: &str,
@@ -60,7 +125,17 @@ impl SingleInstaller {
             no_save,
             force,
             debug,
-        ))
+        ))?;
+        timing::report_slowest_packages(debug);
+        timing::report_connections(debug);
+
+        if self.verify_exports {
+            warn_broken_entry_points(Path::new(project_dir), [&name.to_string()]);
+        }
+        if self.npm_compat {
+            NpmCompat::write(Path::new(project_dir))?;
+        }
+        Ok(())
     }
 
     pub fn install_batch(
@@ -85,7 +160,20 @@ impl SingleInstaller {
             no_save,
             force,
             debug,
-        ))
+        ))?;
+        timing::report_slowest_packages(debug);
+        timing::report_connections(debug);
+
+        if self.verify_exports {
+            warn_broken_entry_points(
+                Path::new(project_dir),
+                packages.iter().map(|(name, _)| name),
+            );
+        }
+        if self.npm_compat {
+            NpmCompat::write(Path::new(project_dir))?;
+        }
+        Ok(())
     }
 
     async fn install_async(
@@ -96,10 +184,25 @@ impl SingleInstaller {
         dep_type: DependencyType,
         save_exact: bool,
         no_save: bool,
-        _force: bool,
+        force: bool,
         debug: bool,
     ) -> Result<()> {
         let path = PathBuf::from(project_dir);
+        Self::check_not_self(&path, name)?;
+
+        if force {
+            return self
+                .install_forced(
+                    &path,
+                    name,
+                    version_range,
+                    dep_type,
+                    save_exact,
+                    no_save,
+                    debug,
+                )
+                .await;
+        }
 
         if self.check_existing(
             &path,
@@ -196,9 +299,124 @@ impl SingleInstaller {
         }
     }
 
+    /// `--force`: skips the already-installed check, downloads `name` again even when it
+    /// is stored, replacing the store copy, and relinks it over the existing entry.
+    async fn install_forced(
+        &self,
+        project_path: &Path,
+        name: &str,
+        version_range: &str,
+        dep_type: DependencyType,
+        save_exact: bool,
+        no_save: bool,
+        debug: bool,
+    ) -> Result<()> {
+        pacm_logger::status(&format!("Reinstalling {}...", name));
+
+        let deps = vec![(name.to_string(), version_range.to_string())];
+        self.cache.build_index(debug).await?;
+
+        let deduping_resolver = self
+            .prefer_dedupe
+            .then(|| self.lock_preferring_resolver(project_path, name));
+        let resolver = deduping_resolver.as_ref().unwrap_or(&self.resolver);
+
+        let (direct_names, all_resolved_packages) =
+            resolver.resolve_all_parallel(&deps, false, debug).await?;
+        let main_package = all_resolved_packages
+            .values()
+            .find(|pkg| pkg.name == name)
+            .cloned()
+            .ok_or_else(|| PackageManagerError::PackageNotFound(name.to_string()))?;
+
+        let (cached_packages, packages_to_download) = resolver
+            .separate_cached_fast(&all_resolved_packages, &self.cache, debug)
+            .await?;
+        let cached_packages: Vec<CachedPackage> = cached_packages
+            .into_iter()
+            .filter(|cached| cached.name != name)
+            .collect();
+        let compatible_packages_to_download: Vec<ResolvedPackage> = packages_to_download
+            .into_iter()
+            .filter(|pkg| pkg.name != name && is_platform_compatible(&pkg.os, &pkg.cpu))
+            .collect();
+
+        let mut stored_packages = self.build_stored_map(&cached_packages, &all_resolved_packages);
+        if !compatible_packages_to_download.is_empty() {
+            let downloaded = self
+                .downloader
+                .download_parallel(&compatible_packages_to_download, debug)
+                .await?;
+            stored_packages.extend(downloaded);
+        }
+
+        let store_path = self.downloader.redownload(&main_package, debug).await?;
+        stored_packages.insert(
+            format!("{}@{}", main_package.name, main_package.version),
+            (main_package.clone(), store_path),
+        );
+
+        let mut reinstalled = compatible_packages_to_download;
+        reinstalled.push(main_package.clone());
+        self.run_post_install(&stored_packages, &reinstalled, debug)?;
+
+        self.link_all_to_project(project_path, &stored_packages, debug)?;
+
+        if !no_save {
+            self.update_package_json(
+                project_path,
+                name,
+                version_range,
+                dep_type,
+                save_exact,
+                &stored_packages,
+            )?;
+        }
+
+        self.update_lock(project_path, &stored_packages, &direct_names)?;
+
+        pacm_logger::finish(&format!(
+            "{}@{} reinstalled",
+            main_package.name, main_package.version
+        ));
+        self.downloader.report();
+        Ok(())
+    }
+
+    /// Fails when `name` is the project at `path` itself, which would make it depend on
+    /// itself.
+    fn check_not_self(path: &Path, name: &str) -> Result<()> {
+        match read_package_json(path) {
+            Ok(pkg) if pkg.name.as_deref() == Some(name) => {
+                Err(PackageManagerError::DependencyConflict(
+                    name.to_string(),
+                    "a package cannot be installed into itself".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// A resolver that prefers the versions in the project's `pacm.lock`, except for
+    /// `name` itself, which is being updated.
+    fn lock_preferring_resolver(&self, project_path: &Path, name: &str) -> DependencyResolver {
+        let preferred = PacmLock::load(&PacmConfig::get().lockfile_path(project_path))
+            .map(|lock| {
+                lock.packages
+                    .into_iter()
+                    .filter(|(locked_name, _)| locked_name != name)
+                    .map(|(locked_name, locked)| (locked_name, vec![locked.version]))
+                    .collect()
+            })
+            .unwrap_or_default();
+        DependencyResolver::new()
+            .with_client(self.resolver.get_client())
+            .with_preferred_versions(preferred)
+    }
+
     async fn install_instant_link(
         &self,
-        project_path: &PathBuf,
+        project_path: &Path,
         cached_package: &CachedPackage,
         name: &str,
         _version_range: &str,
@@ -223,10 +441,7 @@ impl SingleInstaller {
                     version: cached_package.version.clone(),
                     resolved: cached_package.resolved.clone(),
                     integrity: cached_package.integrity.clone(),
-                    dependencies: HashMap::new(),
-                    optional_dependencies: HashMap::new(),
-                    os: None,
-                    cpu: None,
+                    ..Default::default()
                 },
                 cached_package.store_path.clone(),
             ),
@@ -254,7 +469,7 @@ impl SingleInstaller {
 
     async fn install_cached_with_minimal_deps(
         &self,
-        project_path: &PathBuf,
+        project_path: &Path,
         main_package: &CachedPackage,
         name: &str,
         version_range: &str,
@@ -321,12 +536,13 @@ impl SingleInstaller {
             )
         };
         pacm_logger::finish(&msg);
+        self.downloader.report();
         Ok(())
     }
 
     async fn install_simple_download(
         &self,
-        project_path: &PathBuf,
+        project_path: &Path,
         name: &str,
         version_range: &str,
         dep_type: DependencyType,
@@ -348,12 +564,7 @@ impl SingleInstaller {
             &mut seen,
         )
         .await
-        .map_err(|e| {
-            PackageManagerError::VersionResolutionFailed(
-                name.to_string(),
-                format!("Failed to resolve {}: {}", name, e),
-            )
-        })?;
+        .map_err(|e| resolution_error(name, e))?;
 
         let compatible_packages: Vec<_> = resolved_packages
             .into_iter()
@@ -400,12 +611,13 @@ impl SingleInstaller {
             )
         };
         pacm_logger::finish(&msg);
+        self.downloader.report();
         Ok(())
     }
 
     async fn install_optimized_path(
         &self,
-        project_path: &PathBuf,
+        project_path: &Path,
         name: &str,
         version_range: &str,
         dep_type: DependencyType,
@@ -465,6 +677,7 @@ impl SingleInstaller {
 
         let msg = self.build_finish_msg(name, &cached_packages, &compatible_packages_to_download);
         pacm_logger::finish(&msg);
+        self.downloader.report();
         Ok(())
     }
 
@@ -475,13 +688,33 @@ impl SingleInstaller {
         dep_type: DependencyType,
         save_exact: bool,
         no_save: bool,
-        _force: bool,
+        force: bool,
         debug: bool,
     ) -> Result<()> {
+        let packages = &merge_duplicate_requests(self.resolver.get_client(), packages).await?;
         let package_names: Vec<&str> = packages.iter().map(|(name, _)| name.as_str()).collect();
         pacm_logger::status(&format!("Installing {}", package_names.join(" ")));
 
         let path = PathBuf::from(project_dir);
+        for (name, _) in packages {
+            Self::check_not_self(&path, name)?;
+        }
+
+        if force {
+            for (name, version_range) in packages {
+                self.install_forced(
+                    &path,
+                    name,
+                    version_range,
+                    dep_type,
+                    save_exact,
+                    no_save,
+                    debug,
+                )
+                .await?;
+            }
+            return Ok(());
+        }
 
         let mut existing_packages = Vec::new();
         let mut packages_to_install = Vec::new();
@@ -618,13 +851,14 @@ impl SingleInstaller {
             &stored_packages,
         );
         pacm_logger::finish(&finish_msg);
+        self.downloader.report();
 
         Ok(())
     }
 
     async fn install_batch_fast_cached(
         &self,
-        path: &PathBuf,
+        path: &Path,
         packages_to_install: &[(String, String)],
         dep_type: DependencyType,
         save_exact: bool,
@@ -721,7 +955,7 @@ impl SingleInstaller {
 
     async fn install_batch_full_resolution(
         &self,
-        path: &PathBuf,
+        path: &Path,
         packages_to_install: &[(String, String)],
         dep_type: DependencyType,
         save_exact: bool,
@@ -790,13 +1024,14 @@ impl SingleInstaller {
             &stored_packages,
         );
         pacm_logger::finish(&finish_msg);
+        self.downloader.report();
 
         Ok(())
     }
 
     fn check_existing(
         &self,
-        path: &PathBuf,
+        path: &Path,
         name: &str,
         version_range: &str,
         dep_type: DependencyType,
@@ -809,6 +1044,8 @@ impl SingleInstaller {
             name,
             version_range,
             dep_type,
+            self.move_existing,
+            self.lockfile,
             save_exact,
             no_save,
             debug,
@@ -832,10 +1069,7 @@ impl SingleInstaller {
                     version: cached_pkg.version.clone(),
                     resolved: cached_pkg.resolved.clone(),
                     integrity: cached_pkg.integrity.clone(),
-                    dependencies: HashMap::new(),
-                    optional_dependencies: HashMap::new(),
-                    os: None,
-                    cpu: None,
+                    ..Default::default()
                 });
             stored.insert(key, (resolved_pkg, cached_pkg.store_path.clone()));
         }
@@ -854,7 +1088,7 @@ impl SingleInstaller {
 
     fn link_all_to_project(
         &self,
-        path: &PathBuf,
+        path: &Path,
         stored: &HashMap<String, (ResolvedPackage, PathBuf)>,
         debug: bool,
     ) -> Result<()> {
@@ -863,11 +1097,14 @@ impl SingleInstaller {
 
     fn update_lock(
         &self,
-        path: &PathBuf,
+        path: &Path,
         stored: &HashMap<String, (ResolvedPackage, PathBuf)>,
         direct_names: &HashSet<String>,
     ) -> Result<()> {
-        let lock_path = path.join("pacm.lock");
+        if !self.lockfile {
+            return Ok(());
+        }
+        let lock_path = PacmConfig::get().lockfile_path(path);
         self.linker
             .update_lock_direct(&lock_path, stored, direct_names)
     }
@@ -901,7 +1138,7 @@ impl SingleInstaller {
 
     fn update_package_json(
         &self,
-        path: &PathBuf,
+        path: &Path,
         name: &str,
         version_range: &str,
         dep_type: DependencyType,
@@ -920,7 +1157,7 @@ impl SingleInstaller {
 
     fn update_package_json_batch(
         &self,
-        path: &PathBuf,
+        path: &Path,
         packages: &[(String, String)],
         dep_type: DependencyType,
         save_exact: bool,
@@ -1017,7 +1254,7 @@ impl SingleInstaller {
 
     async fn install_full_path(
         &self,
-        project_path: &PathBuf,
+        project_path: &Path,
         name: &str,
         version_range: &str,
         dep_type: DependencyType,
@@ -1105,6 +1342,7 @@ impl SingleInstaller {
                 )
             };
             pacm_logger::finish(&msg);
+            self.downloader.report();
             return Ok(());
         }
 
@@ -1135,6 +1373,7 @@ impl SingleInstaller {
 
         let msg = self.build_finish_msg(name, &cached_packages, &compatible_packages_to_download);
         pacm_logger::finish(&msg);
+        self.downloader.report();
         Ok(())
     }
 }
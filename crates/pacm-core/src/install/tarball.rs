@@ -0,0 +1,347 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::cache::CacheManager;
+use super::resolver::DependencyResolver;
+use super::utils::InstallUtils;
+use crate::download::PackageDownloader;
+use crate::linker::PackageLinker;
+use crate::pack::Packer;
+use pacm_config::PacmConfig;
+use pacm_error::{PackageManagerError, Result};
+use pacm_logger;
+use pacm_project::{DependencyType, read_package_json, write_package_json};
+use pacm_resolver::{ResolvedPackage, bundled_dependencies, is_platform_compatible};
+use pacm_store::PackageLinker as StoreLinker;
+use pacm_utils::is_directory_spec;
+
+/// Installs packages from `.tgz` files or tarball URLs instead of the registry. The
+/// tarball is stored by integrity and its dependencies are resolved from the registry.
+///
+/// Package directories (`file:` and `link:` specs) are symlinked into `node_modules` and
+/// their dependencies left to the directory itself. With `install-links` they are packed
+/// as for a publish and installed like a tarball instead, but copied into
+/// `node_modules` as a real directory. `pacm.lock` records the first as `link:` and the
+/// second as `file:`.
+pub struct TarballInstaller {
+    downloader: PackageDownloader,
+    linker: PackageLinker,
+    cache: CacheManager,
+    resolver: DependencyResolver,
+    lockfile: bool,
+    install_links: bool,
+}
+
+impl TarballInstaller {
+    pub fn new() -> Self {
+        Self {
+            downloader: PackageDownloader::new(),
+            linker: PackageLinker::new(),
+            cache: CacheManager::new(),
+            resolver: DependencyResolver::new(),
+            lockfile: PacmConfig::get().lockfile(),
+            install_links: PacmConfig::get().install_links(),
+        }
+    }
+
+    /// Resolves and downloads through `client` instead of clients of its own.
+    #[must_use]
+    pub fn with_client(mut self, client: Arc<reqwest::Client>) -> Self {
+        self.downloader = self.downloader.with_client(client.clone());
+        self.resolver = self.resolver.with_client(client);
+        self
+    }
+
+    /// Records installed tarballs in `pacm.lock`. Defaults to the `lockfile` setting.
+    #[must_use]
+    pub fn with_lockfile(mut self, lockfile: bool) -> Self {
+        self.lockfile = lockfile;
+        self
+    }
+
+    /// Copies package directories into `node_modules` instead of symlinking them.
+    /// Defaults to the `install-links` setting.
+    #[must_use]
+    pub fn with_install_links(mut self, install_links: bool) -> Self {
+        self.install_links = install_links;
+        self
+    }
+
+    pub fn install(
+        &self,
+        project_dir: &str,
+        source: &str,
+        dep_type: DependencyType,
+        no_save: bool,
+        debug: bool,
+    ) -> Result<()> {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| {
+            PackageManagerError::NetworkError(format!("Failed to create async runtime: {}", e))
+        })?;
+
+        rt.block_on(self.install_async(project_dir, source, dep_type, no_save, debug))
+            .map(|_| ())
+    }
+
+    /// Installs the tarball at `source` and returns the name of the package it contains.
+    pub async fn install_async(
+        &self,
+        project_dir: &str,
+        source: &str,
+        dep_type: DependencyType,
+        no_save: bool,
+        debug: bool,
+    ) -> Result<String> {
+        let path = PathBuf::from(project_dir);
+        let directory = is_directory_spec(source).then(|| Self::directory_location(source));
+        if let Some(location) = directory
+            && !self.install_links
+        {
+            return self.link_directory(&path, source, location, dep_type, no_save);
+        }
+        let resolved = match directory {
+            Some(location) => format!("file:{location}"),
+            None => Self::lock_source(source),
+        };
+
+        pacm_logger::status(&format!("Installing {}...", source));
+
+        let (store_path, integrity) = match directory {
+            Some(location) => Self::pack_directory(&path.join(location))?,
+            None => {
+                self.downloader
+                    .download_tarball_source(source, &path, debug)
+                    .await?
+            }
+        };
+
+        let manifest = read_package_json(&store_path.join("package")).map_err(|e| {
+            PackageManagerError::PackageJsonError(format!(
+                "Invalid package.json in {}: {}",
+                source, e
+            ))
+        })?;
+        let name = manifest.name.clone().ok_or_else(|| {
+            PackageManagerError::PackageJsonError(format!("{} has no package name", source))
+        })?;
+        let version = manifest
+            .version
+            .clone()
+            .unwrap_or_else(|| "0.0.0".to_string());
+
+        let bundled = fs::read_to_string(store_path.join("package").join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .map(|manifest| bundled_dependencies(&manifest))
+            .unwrap_or_default();
+        let to_map =
+            |deps: &Option<indexmap::IndexMap<String, String>>| -> HashMap<String, String> {
+                deps.iter()
+                    .flatten()
+                    .filter(|(name, _)| !bundled.contains(name))
+                    .map(|(name, range)| (name.clone(), range.clone()))
+                    .collect()
+            };
+        let raw_manifest = serde_json::to_value(&manifest).unwrap_or_default();
+        let main_package = ResolvedPackage {
+            name: name.clone(),
+            version: version.clone(),
+            resolved,
+            integrity,
+            dependencies: to_map(&manifest.dependencies),
+            optional_dependencies: to_map(&manifest.optional_dependencies),
+            bundled_dependencies: bundled.clone(),
+            peer_dependencies: pacm_resolver::peer_dependencies(&raw_manifest),
+            optional_peer_dependencies: pacm_resolver::optional_peer_dependencies(&raw_manifest),
+            ..Default::default()
+        };
+
+        let mut stored_packages = self.resolve_dependencies(&main_package, debug).await?;
+        stored_packages.insert(
+            format!("{}@{}", name, version),
+            (main_package, store_path.clone()),
+        );
+
+        self.linker
+            .link_all_to_project(&path, &stored_packages, debug)?;
+        if directory.is_some() {
+            StoreLinker::copy_directory(
+                &path.join("node_modules"),
+                &name,
+                &store_path.join("package"),
+            )
+            .map_err(|e| PackageManagerError::LinkingFailed(name.clone(), e.to_string()))?;
+        }
+        InstallUtils::run_postinstall_in_project(&path, &stored_packages, debug)?;
+
+        if !no_save {
+            let mut pkg = read_package_json(&path)
+                .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+            pkg.add_dependency(&name, &Self::lock_source(source), dep_type, true);
+            write_package_json(&path, &pkg)
+                .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        }
+
+        if self.lockfile {
+            let direct_names: HashSet<String> = [name.clone()].into_iter().collect();
+            self.linker.update_lock_direct(
+                &PacmConfig::get().lockfile_path(&path),
+                &stored_packages,
+                &direct_names,
+            )?;
+        }
+
+        pacm_logger::finish(&format!("{}@{} installed from {}", name, version, source));
+        self.downloader.report();
+        Ok(name)
+    }
+
+    async fn resolve_dependencies(
+        &self,
+        main_package: &ResolvedPackage,
+        debug: bool,
+    ) -> Result<HashMap<String, (ResolvedPackage, PathBuf)>> {
+        let deps: Vec<(String, String)> = main_package
+            .dependencies
+            .iter()
+            .map(|(name, range)| (name.clone(), range.clone()))
+            .collect();
+        if deps.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        self.cache.build_index(debug).await?;
+        let (cached_packages, packages_to_download, _, all_resolved_packages) = self
+            .resolver
+            .resolve_deps_fast(&deps, &self.cache, debug)
+            .await?;
+
+        let mut stored_packages = HashMap::new();
+        for cached in cached_packages {
+            let key = format!("{}@{}", cached.name, cached.version);
+            let pkg = all_resolved_packages
+                .get(&key)
+                .cloned()
+                .unwrap_or_else(|| ResolvedPackage {
+                    name: cached.name.clone(),
+                    version: cached.version.clone(),
+                    resolved: cached.resolved.clone(),
+                    integrity: cached.integrity.clone(),
+                    ..Default::default()
+                });
+            stored_packages.insert(key, (pkg, cached.store_path));
+        }
+
+        let compatible_packages: Vec<ResolvedPackage> = packages_to_download
+            .into_iter()
+            .filter(|pkg| is_platform_compatible(&pkg.os, &pkg.cpu))
+            .collect();
+        if !compatible_packages.is_empty() {
+            let downloaded = self
+                .downloader
+                .download_parallel(&compatible_packages, debug)
+                .await?;
+            stored_packages.extend(downloaded);
+        }
+
+        Ok(stored_packages)
+    }
+
+    /// Symlinks the package directory at `location` into `node_modules` as is.
+    fn link_directory(
+        &self,
+        path: &Path,
+        source: &str,
+        location: &str,
+        dep_type: DependencyType,
+        no_save: bool,
+    ) -> Result<String> {
+        let dir = path.join(location);
+        let manifest = read_package_json(&dir).map_err(|e| {
+            PackageManagerError::PackageJsonError(format!(
+                "Invalid package.json in {}: {}",
+                source, e
+            ))
+        })?;
+        let name = manifest.name.clone().ok_or_else(|| {
+            PackageManagerError::PackageJsonError(format!("{} has no package name", source))
+        })?;
+        let version = manifest
+            .version
+            .clone()
+            .unwrap_or_else(|| "0.0.0".to_string());
+
+        let target = dir.canonicalize().map_err(|e| {
+            PackageManagerError::IoError(format!("Failed to resolve {}: {}", dir.display(), e))
+        })?;
+        StoreLinker::link_directory(&path.join("node_modules"), &name, &target)
+            .map_err(|e| PackageManagerError::LinkingFailed(name.clone(), e.to_string()))?;
+
+        if !no_save {
+            let mut pkg = read_package_json(path)
+                .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+            pkg.add_dependency(&name, source, dep_type, true);
+            write_package_json(path, &pkg)
+                .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        }
+
+        if self.lockfile {
+            let raw_manifest = serde_json::to_value(&manifest).unwrap_or_default();
+            let linked = ResolvedPackage {
+                name: name.clone(),
+                version: version.clone(),
+                resolved: format!("link:{location}"),
+                peer_dependencies: pacm_resolver::peer_dependencies(&raw_manifest),
+                optional_peer_dependencies: pacm_resolver::optional_peer_dependencies(
+                    &raw_manifest,
+                ),
+                ..Default::default()
+            };
+            let stored = HashMap::from([(format!("{}@{}", name, version), (linked, target))]);
+            self.linker.update_lock_direct(
+                &PacmConfig::get().lockfile_path(path),
+                &stored,
+                &HashSet::from([name.clone()]),
+            )?;
+        }
+
+        pacm_logger::finish(&format!("{}@{} linked from {}", name, version, source));
+        Ok(name)
+    }
+
+    /// Packs the package directory `dir` the way `pacm publish` would and stores the
+    /// result, so only the files it would publish are installed.
+    fn pack_directory(dir: &Path) -> Result<(PathBuf, String)> {
+        let tarball = Packer.pack(dir)?;
+        pacm_store::store_tarball(&tarball.bytes)
+            .map_err(|e| PackageManagerError::StorageFailed(tarball.name.clone(), e.to_string()))
+    }
+
+    /// The path of a `file:` or `link:` directory spec.
+    fn directory_location(source: &str) -> &str {
+        source
+            .strip_prefix("link:")
+            .or_else(|| source.strip_prefix("file:"))
+            .unwrap_or(source)
+    }
+
+    /// The form a tarball source is recorded in: URLs as-is, paths as `file:` specs.
+    fn lock_source(source: &str) -> String {
+        if source.starts_with("file:")
+            || source.starts_with("http://")
+            || source.starts_with("https://")
+        {
+            source.to_string()
+        } else {
+            format!("file:{}", source)
+        }
+    }
+}
+
+impl Default for TarballInstaller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -13,5 +13,5 @@ pub struct CachedPackage {
 #[derive(Debug)]
 pub enum PackageSource {
     Cache(CachedPackage),
-    Download(ResolvedPackage),
+    Download(Box<ResolvedPackage>),
 }
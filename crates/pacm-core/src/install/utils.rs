@@ -1,22 +1,30 @@
 use rayon::prelude::*;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, ExitStatus, Stdio};
 
+use crate::timing::{self, Phase};
+use pacm_config::PacmConfig;
 use pacm_error::{PackageManagerError, Result};
 use pacm_lock::PacmLock;
 use pacm_logger;
 use pacm_project::{DependencyType, read_package_json, write_package_json};
 use pacm_resolver::ResolvedPackage;
+use tokio_util::sync::CancellationToken;
+
+/// How many lines of a failed script's output are shown.
+const SCRIPT_OUTPUT_TAIL_LINES: usize = 20;
 
 pub struct InstallUtils;
 
 impl InstallUtils {
     pub fn check_existing(
-        path: &PathBuf,
+        path: &Path,
         name: &str,
         _version_range: &str,
         dep_type: DependencyType,
+        move_existing: bool,
+        lockfile: bool,
         save_exact: bool,
         no_save: bool,
         debug: bool,
@@ -26,81 +34,105 @@ impl InstallUtils {
 
         if package_dir.exists() {
             let package_json_path = package_dir.join("package.json");
-            if package_json_path.exists() {
-                if let Ok(content) = std::fs::read_to_string(&package_json_path) {
-                    if let Ok(pkg_json) = serde_json::from_str::<serde_json::Value>(&content) {
-                        if let Some(installed_version) =
-                            pkg_json.get("version").and_then(|v| v.as_str())
-                        {
-                            if debug {
-                                pacm_logger::debug(
-                                    &format!(
-                                        "Found existing package {} in node_modules with version {}",
-                                        name, installed_version
-                                    ),
-                                    debug,
-                                );
-                            }
+            if package_json_path.exists()
+                && let Ok(content) = std::fs::read_to_string(&package_json_path)
+                && let Ok(pkg_json) = serde_json::from_str::<serde_json::Value>(&content)
+                && let Some(installed_version) = pkg_json.get("version").and_then(|v| v.as_str())
+            {
+                if debug {
+                    pacm_logger::debug(
+                        &format!(
+                            "Found existing package {} in node_modules with version {}",
+                            name, installed_version
+                        ),
+                        debug,
+                    );
+                }
 
-                            if !no_save {
-                                let mut pkg = read_package_json(path).map_err(|e| {
-                                    PackageManagerError::PackageJsonError(e.to_string())
-                                })?;
-
-                                if pkg.has_dependency(name).is_none() {
-                                    let version_to_save = if save_exact {
-                                        installed_version.to_string()
-                                    } else {
-                                        format!("^{}", installed_version)
-                                    };
-                                    pkg.add_dependency(
-                                        name,
-                                        &version_to_save,
-                                        dep_type,
-                                        save_exact,
-                                    );
-                                    write_package_json(path, &pkg).map_err(|e| {
-                                        PackageManagerError::PackageJsonError(e.to_string())
-                                    })?;
-
-                                    if debug {
-                                        pacm_logger::debug(
-                                            &format!(
-                                                "Added {} to package.json with version {}",
-                                                name, version_to_save
-                                            ),
-                                            debug,
-                                        );
-                                    }
-                                } else {
-                                    if debug {
-                                        pacm_logger::debug(
-                                            &format!(
-                                                "Package {} already exists in package.json, not modifying version",
-                                                name
-                                            ),
-                                            debug,
-                                        );
-                                    }
-                                }
-                            }
+                if !no_save {
+                    let mut pkg = read_package_json(path)
+                        .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
 
-                            pacm_logger::finish(&format!(
-                                "{} is already installed (found in node_modules)",
-                                name
-                            ));
-                            return Ok(true);
+                    if pkg.has_dependency(name).is_none() {
+                        let version_to_save =
+                            PacmConfig::get().save_range(installed_version, save_exact);
+                        pkg.add_dependency(name, &version_to_save, dep_type, true);
+                        write_package_json(path, &pkg)
+                            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+
+                        if debug {
+                            pacm_logger::debug(
+                                &format!(
+                                    "Added {} to package.json with version {}",
+                                    name, version_to_save
+                                ),
+                                debug,
+                            );
                         }
+                    } else if let Some(previous_type) = move_existing
+                        .then(|| pkg.move_dependency(name, dep_type))
+                        .flatten()
+                    {
+                        pacm_logger::status(&format!(
+                            "Moving {} from {} to {}...",
+                            name,
+                            previous_type.as_str(),
+                            dep_type.as_str()
+                        ));
+                        write_package_json(path, &pkg)
+                            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+                        if lockfile {
+                            Self::move_lock_workspace_dep(path, name, dep_type)?;
+                        }
+
+                        pacm_logger::finish(&format!("moved {} to {}", name, dep_type.as_str()));
+                        return Ok(true);
+                    } else if debug {
+                        pacm_logger::debug(
+                            &format!(
+                                "Package {} already exists in package.json, not modifying version",
+                                name
+                            ),
+                            debug,
+                        );
                     }
                 }
+
+                pacm_logger::finish(&format!(
+                    "{} is already installed (found in node_modules)",
+                    name
+                ));
+                return Ok(true);
             }
         }
 
         Ok(false)
     }
 
+    fn move_lock_workspace_dep(path: &Path, name: &str, dep_type: DependencyType) -> Result<()> {
+        let lock_path = PacmConfig::get().lockfile_path(path);
+        if !lock_path.exists() {
+            return Ok(());
+        }
+
+        let mut lockfile = PacmLock::load(&lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+
+        let Some(version) = lockfile.get_package(name).map(|pkg| pkg.version.clone()) else {
+            return Ok(());
+        };
+
+        let mut workspace_deps = HashMap::new();
+        workspace_deps.insert(name.to_string(), version);
+        lockfile.update_workspace_deps("", &workspace_deps, dep_type.as_str());
+
+        lockfile
+            .save(&lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))
+    }
+
     pub fn update_pkg_json(
-        path: &PathBuf,
+        path: &Path,
         name: &str,
         version_range: &str,
         dep_type: DependencyType,
@@ -116,15 +148,15 @@ impl InstallUtils {
             .map(|(_, (pkg, _))| &pkg.version)
             .map_or(version_range, |v| v);
 
-        let version_to_save = if save_exact {
-            target_version.to_string()
-        } else if version_range.starts_with('^') || version_range.starts_with('~') {
-            version_range.to_string()
-        } else {
-            format!("^{}", target_version)
-        };
+        let version_to_save =
+            if !save_exact && (version_range.starts_with('^') || version_range.starts_with('~')) {
+                version_range.to_string()
+            } else {
+                PacmConfig::get().save_range(target_version, save_exact)
+            };
 
-        pkg.add_dependency(name, &version_to_save, dep_type, save_exact);
+        // The range is final here, so `add_dependency` must not add its own prefix.
+        pkg.add_dependency(name, &version_to_save, dep_type, true);
 
         write_package_json(path, &pkg)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
@@ -141,7 +173,7 @@ impl InstallUtils {
         let mut pkg = read_package_json(path)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
 
-        pkg.remove_dependency(name);
+        // Replaces the range wherever it was declared, keeping the package bundled.
         pkg.add_dependency(name, version_range, dep_type, false);
 
         write_package_json(path, &pkg)
@@ -150,6 +182,21 @@ impl InstallUtils {
         Ok(())
     }
 
+    /// Marks `names` as `bundledDependencies`. Nothing is written unless every name is
+    /// declared in a dependencies section.
+    pub fn bundle_in_pkg_json(path: &Path, names: &[String]) -> Result<()> {
+        let mut pkg = read_package_json(path)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+
+        for name in names {
+            pkg.add_bundled_dependency(name)
+                .map_err(PackageManagerError::PackageJsonError)?;
+        }
+
+        write_package_json(path, &pkg)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))
+    }
+
     pub fn run_postinstall(
         packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
         debug: bool,
@@ -168,15 +215,15 @@ impl InstallUtils {
             );
         }
 
-        for (_key, (pkg, store_path)) in packages {
-            Self::run_single_postinstall(&pkg.name, store_path, debug)?;
-        }
-
-        Ok(())
+        timing::timed(Phase::Postinstall, || {
+            packages.values().try_for_each(|(pkg, store_path)| {
+                Self::run_single_postinstall(&pkg.name, store_path, debug)
+            })
+        })
     }
 
     pub fn run_postinstall_in_project(
-        project_dir: &PathBuf,
+        project_dir: &Path,
         packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
         debug: bool,
     ) -> Result<()> {
@@ -196,12 +243,14 @@ impl InstallUtils {
 
         let project_node_modules = project_dir.join("node_modules");
 
-        let results: Vec<_> = packages
-            .par_iter()
-            .map(|(_key, (pkg, _store_path))| {
-                Self::run_single_postinstall_in_project(&pkg.name, &project_node_modules, debug)
-            })
-            .collect();
+        let results: Vec<_> = timing::timed(Phase::Postinstall, || {
+            packages
+                .par_iter()
+                .map(|(_key, (pkg, _store_path))| {
+                    Self::run_single_postinstall_in_project(&pkg.name, &project_node_modules, debug)
+                })
+                .collect()
+        });
 
         for result in results {
             result?;
@@ -229,7 +278,7 @@ impl InstallUtils {
         Ok(())
     }
 
-    fn run_single_postinstall(package_name: &str, store_path: &PathBuf, debug: bool) -> Result<()> {
+    fn run_single_postinstall(package_name: &str, store_path: &Path, debug: bool) -> Result<()> {
         let package_dir = store_path.join("package");
         let package_json_path = package_dir.join("package.json");
 
@@ -243,58 +292,57 @@ impl InstallUtils {
         let package_json: serde_json::Value = serde_json::from_str(&content)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
 
-        if let Some(scripts) = package_json.get("scripts").and_then(|s| s.as_object()) {
-            if let Some(postinstall) = scripts.get("postinstall").and_then(|s| s.as_str()) {
-                pacm_logger::status(&format!(
-                    "Running postinstall for {} in directory: {}",
-                    package_name,
-                    package_dir.display()
-                ));
-
-                if debug {
-                    pacm_logger::debug(
-                        &format!("Running postinstall for {}: {}", package_name, postinstall),
-                        debug,
-                    );
-                }
+        if let Some(scripts) = package_json.get("scripts").and_then(|s| s.as_object())
+            && let Some(postinstall) = scripts.get("postinstall").and_then(|s| s.as_str())
+        {
+            pacm_logger::status(&format!(
+                "Running postinstall for {} in directory: {}",
+                package_name,
+                package_dir.display()
+            ));
 
-                let status = if cfg!(target_os = "windows") {
-                    Command::new("cmd")
-                        .args(["/C", postinstall])
-                        .current_dir(&package_dir)
-                        .status()
-                } else {
-                    Command::new("sh")
-                        .args(["-c", postinstall])
-                        .current_dir(&package_dir)
-                        .status()
-                };
+            if debug {
+                pacm_logger::debug(
+                    &format!("Running postinstall for {}: {}", package_name, postinstall),
+                    debug,
+                );
+            }
 
-                match status {
-                    Ok(exit_status) => {
-                        if !exit_status.success() {
-                            pacm_logger::warn(&format!(
-                                "Postinstall script failed for {} with exit code: {}",
-                                package_name,
-                                exit_status.code().unwrap_or(-1)
-                            ));
-                        } else if debug {
-                            pacm_logger::debug(
-                                &format!(
-                                    "Postinstall script completed successfully for {}",
-                                    package_name
-                                ),
-                                debug,
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        pacm_logger::warn(&format!(
-                            "Failed to execute postinstall script for {}: {}",
-                            package_name, e
+            let mut cmd = if cfg!(target_os = "windows") {
+                let mut cmd = Command::new("cmd");
+                cmd.args(["/C", postinstall]);
+                cmd
+            } else {
+                let mut cmd = Command::new("sh");
+                cmd.args(["-c", postinstall]);
+                cmd
+            };
+            cmd.current_dir(&package_dir);
+
+            match Self::run_script(&mut cmd, debug) {
+                Ok((exit_status, output)) => {
+                    if !exit_status.success() {
+                        pacm_logger::warn(&Self::script_failure(
+                            &format!("Postinstall script failed for {}", package_name),
+                            exit_status,
+                            &output,
                         ));
+                    } else if debug {
+                        pacm_logger::debug(
+                            &format!(
+                                "Postinstall script completed successfully for {}",
+                                package_name
+                            ),
+                            debug,
+                        );
                     }
                 }
+                Err(e) => {
+                    pacm_logger::warn(&format!(
+                        "Failed to execute postinstall script for {}: {}",
+                        package_name, e
+                    ));
+                }
             }
         }
 
@@ -466,17 +514,17 @@ impl InstallUtils {
                     cmd.env("PATH", new_path);
                 }
 
-                let status = cmd.status();
+                let result = Self::run_script(&mut cmd, debug);
 
                 let _ = std::fs::remove_dir_all(&temp_package_dir);
 
-                match status {
-                    Ok(exit_status) => {
+                match result {
+                    Ok((exit_status, output)) => {
                         if !exit_status.success() {
-                            pacm_logger::warn(&format!(
-                                "Postinstall script failed for {} with exit code: {}",
-                                package_name,
-                                exit_status.code().unwrap_or(-1)
+                            pacm_logger::warn(&Self::script_failure(
+                                &format!("Postinstall script failed for {}", package_name),
+                                exit_status,
+                                &output,
                             ));
                         } else if debug {
                             pacm_logger::debug(
@@ -506,7 +554,85 @@ impl InstallUtils {
         Ok(())
     }
 
-    fn copy_dir_contents(src: &PathBuf, dst: &PathBuf) -> std::io::Result<()> {
+    /// Runs the lifecycle script `stage` of `package_name`, whose command is `script`, in
+    /// `dir`. A failure is reported as a warning, like a dependency's postinstall.
+    pub fn run_lifecycle_script(
+        package_name: &str,
+        stage: &str,
+        script: &str,
+        dir: &Path,
+        debug: bool,
+    ) {
+        pacm_logger::status(&format!("Running {} for {}...", stage, package_name));
+        pacm_logger::debug(&format!("{} {}: {}", package_name, stage, script), debug);
+
+        let mut cmd = if cfg!(target_os = "windows") {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", script]);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", script]);
+            cmd
+        };
+        cmd.current_dir(dir);
+
+        match Self::run_script(&mut cmd, debug) {
+            Ok((exit_status, output)) if !exit_status.success() => {
+                pacm_logger::warn(&Self::script_failure(
+                    &format!("{} script failed for {}", stage, package_name),
+                    exit_status,
+                    &output,
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => pacm_logger::warn(&format!(
+                "Failed to execute {} script for {}: {}",
+                stage, package_name, e
+            )),
+        }
+    }
+
+    /// Runs a lifecycle script. In debug mode its output streams straight to the terminal;
+    /// otherwise it is captured so that successful scripts stay quiet, and returned for
+    /// failure reports.
+    fn run_script(cmd: &mut Command, debug: bool) -> std::io::Result<(ExitStatus, String)> {
+        if debug {
+            return cmd.status().map(|status| (status, String::new()));
+        }
+
+        let output = cmd.stdin(Stdio::null()).output()?;
+        // Most scripts report errors on stderr, but some only ever print to stdout.
+        let printed = if output.stderr.iter().any(|b| !b.is_ascii_whitespace()) {
+            &output.stderr
+        } else {
+            &output.stdout
+        };
+        Ok((output.status, String::from_utf8_lossy(printed).into_owned()))
+    }
+
+    /// The failure warning for a script: the exit code, followed by the last lines the
+    /// script printed.
+    fn script_failure(message: &str, status: ExitStatus, output: &str) -> String {
+        let mut report = format!(
+            "{} with exit code: {}",
+            message,
+            status.code().unwrap_or(-1)
+        );
+
+        let lines: Vec<&str> = output.lines().filter(|l| !l.trim().is_empty()).collect();
+        let skipped = lines.len().saturating_sub(SCRIPT_OUTPUT_TAIL_LINES);
+        if skipped > 0 {
+            report.push_str(&format!("\n  ... {} earlier lines omitted", skipped));
+        }
+        for line in &lines[skipped..] {
+            report.push_str("\n  ");
+            report.push_str(line);
+        }
+        report
+    }
+
+    fn copy_dir_contents(src: &Path, dst: &Path) -> std::io::Result<()> {
         if !src.is_dir() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
@@ -558,13 +684,7 @@ impl InstallUtils {
             return Ok(None);
         }
 
-        let safe_package_name = if name.starts_with('@') {
-            name.replace('@', "_at_").replace('/', "_slash_")
-        } else {
-            name.to_string()
-        };
-
-        let package_dir = npm_dir.join(&safe_package_name);
+        let package_dir = npm_dir.join(pacm_store::store_safe_name(name));
 
         if !package_dir.exists() {
             if debug {
@@ -578,7 +698,7 @@ impl InstallUtils {
         match std::fs::read_dir(&package_dir) {
             Ok(version_entries) => {
                 for version_entry in version_entries.flatten() {
-                    if version_entry.file_type().map_or(false, |ft| ft.is_dir()) {
+                    if version_entry.file_type().is_ok_and(|ft| ft.is_dir()) {
                         let version = version_entry.file_name().to_string_lossy().to_string();
                         let store_path = version_entry.path();
                         let package_path = store_path.join("package");
@@ -618,7 +738,7 @@ impl InstallUtils {
     }
 
     pub fn check_existing_pkgs(
-        path: &PathBuf,
+        path: &Path,
         deps: &[(String, String)],
         use_lockfile: bool,
         debug: bool,
@@ -631,7 +751,7 @@ impl InstallUtils {
             return Ok(deps.to_vec());
         }
 
-        let lock_path = path.join("pacm.lock");
+        let lock_path = PacmConfig::get().lockfile_path(path);
         let lockfile = if lock_path.exists() && use_lockfile {
             Some(
                 PacmLock::load(&lock_path)
@@ -648,53 +768,49 @@ impl InstallUtils {
 
             if package_dir.exists() {
                 let package_json_path = package_dir.join("package.json");
-                if package_json_path.exists() {
-                    if let Ok(content) = std::fs::read_to_string(&package_json_path) {
-                        if let Ok(pkg_json) = serde_json::from_str::<serde_json::Value>(&content) {
-                            if let Some(installed_version) =
-                                pkg_json.get("version").and_then(|v| v.as_str())
-                            {
-                                if let Some(ref lockfile) = lockfile {
-                                    if let Some(lock_dep) = lockfile.get_dependency(name) {
-                                        if lock_dep.version == *version
-                                            && installed_version == *version
-                                        {
-                                            if debug {
-                                                pacm_logger::debug(
-                                                    &format!(
-                                                        "Package {} already correctly installed in node_modules (verified with lockfile)",
-                                                        name
-                                                    ),
-                                                    debug,
-                                                );
-                                            }
-                                            continue;
-                                        }
-                                    }
-                                } else {
-                                    if debug {
-                                        pacm_logger::debug(
-                                            &format!(
-                                                "Package {} found in node_modules with version {}",
-                                                name, installed_version
-                                            ),
-                                            debug,
-                                        );
-                                    }
-                                    continue;
-                                }
-
-                                if debug {
-                                    pacm_logger::debug(
-                                        &format!(
-                                            "Package {} needs update: {} -> {}",
-                                            name, installed_version, version
-                                        ),
-                                        debug,
-                                    );
-                                }
+                if package_json_path.exists()
+                    && let Ok(content) = std::fs::read_to_string(&package_json_path)
+                    && let Ok(pkg_json) = serde_json::from_str::<serde_json::Value>(&content)
+                    && let Some(installed_version) =
+                        pkg_json.get("version").and_then(|v| v.as_str())
+                {
+                    if let Some(ref lockfile) = lockfile {
+                        if let Some(lock_dep) = lockfile.get_dependency(name)
+                            && lock_dep.version == *version
+                            && installed_version == *version
+                        {
+                            if debug {
+                                pacm_logger::debug(
+                                    &format!(
+                                        "Package {} already correctly installed in node_modules (verified with lockfile)",
+                                        name
+                                    ),
+                                    debug,
+                                );
                             }
+                            continue;
                         }
+                    } else {
+                        if debug {
+                            pacm_logger::debug(
+                                &format!(
+                                    "Package {} found in node_modules with version {}",
+                                    name, installed_version
+                                ),
+                                debug,
+                            );
+                        }
+                        continue;
+                    }
+
+                    if debug {
+                        pacm_logger::debug(
+                            &format!(
+                                "Package {} needs update: {} -> {}",
+                                name, installed_version, version
+                            ),
+                            debug,
+                        );
                     }
                 }
             }
@@ -704,4 +820,62 @@ impl InstallUtils {
 
         Ok(remaining_deps)
     }
+
+    pub fn check_cancelled(cancel: Option<&CancellationToken>) -> Result<()> {
+        if cancel.is_some_and(CancellationToken::is_cancelled) {
+            Err(PackageManagerError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Undoes what an interrupted install left in the project. Store writes are atomic and
+    /// the lockfile is only written after linking, so the only partial state is in
+    /// `node_modules`: one the install created is removed, while one that existed before
+    /// may have been partly relinked and is left for a rerun to finish.
+    pub fn cleanup_cancelled_install(path: &Path, had_node_modules: bool) {
+        if had_node_modules {
+            pacm_logger::warn(
+                "Installation cancelled, node_modules may be partially updated; run the install again to finish it",
+            );
+            return;
+        }
+
+        let node_modules = path.join("node_modules");
+        if node_modules.exists()
+            && let Err(e) = std::fs::remove_dir_all(&node_modules)
+        {
+            pacm_logger::warn(&format!(
+                "Failed to remove partially created node_modules: {}",
+                e
+            ));
+            return;
+        }
+        pacm_logger::warn("Installation cancelled, no changes were made to the project");
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_script_reports_tail_of_stderr() {
+        let mut cmd = Command::new("sh");
+        cmd.args([
+            "-c",
+            "echo progress; for i in $(seq 1 25); do echo \"line $i\" >&2; done; exit 3",
+        ]);
+
+        let (status, output) = InstallUtils::run_script(&mut cmd, false).unwrap();
+        let report =
+            InstallUtils::script_failure("Postinstall script failed for x", status, &output);
+
+        assert!(report.starts_with("Postinstall script failed for x with exit code: 3\n"));
+        assert!(report.contains("... 5 earlier lines omitted"));
+        assert!(report.contains("\n  line 6\n"));
+        assert!(report.ends_with("\n  line 25"));
+        assert!(!report.contains("line 5\n"));
+        assert!(!report.contains("progress"));
+    }
 }
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use pacm_error::{PackageManagerError, Result};
+use rayon::prelude::*;
+
+use pacm_project::{
+    DependencySelection, DependencyType, PackageJson, WorkspaceMember, check_self_dependency,
+    check_workspace_cycles, workspace_range, workspace_waves,
+};
+use pacm_resolver::satisfies;
+use pacm_utils::{absolute_spec, is_directory_spec, is_tarball_spec, is_workspace_spec};
+
+use super::utils::InstallUtils;
+
+/// The lifecycle scripts run in each member once the install has linked everything, in
+/// the order npm runs them.
+const MEMBER_SCRIPTS: [&str; 3] = ["install", "postinstall", "prepare"];
+
+/// A dependency a member declares, with the member it came from.
+struct MemberSpec<'a> {
+    member: &'a WorkspaceMember,
+    spec: String,
+}
+
+/// The workspace members of the project being installed. Members are linked into the root
+/// `node_modules` rather than fetched, and their own registry dependencies are installed
+/// at the root next to the project's, as npm does.
+#[derive(Debug, Default)]
+pub struct WorkspaceInstall {
+    members: Vec<WorkspaceMember>,
+}
+
+impl WorkspaceInstall {
+    /// The members of the project at `root`; empty when it declares no workspaces.
+    pub fn discover(root: &Path) -> Result<Self> {
+        let members = pacm_project::discover_workspaces(root)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        Ok(Self { members })
+    }
+
+    pub fn is_member(&self, name: &str) -> bool {
+        self.members.iter().any(|m| m.name == name)
+    }
+
+    /// Fails when the root or a member lists itself as a dependency, or members depend on
+    /// each other in a cycle, in any section.
+    pub fn check_cycles(&self, root_pkg: &PackageJson) -> Result<()> {
+        check_self_dependency(root_pkg)?;
+        for member in &self.members {
+            check_self_dependency(&member.package_json)?;
+        }
+        check_workspace_cycles(&self.members)
+    }
+
+    /// Fails when a `workspace:` dependency of the root or a member names no member, or
+    /// one whose version it does not accept.
+    pub fn check_protocol_deps(
+        &self,
+        root_pkg: &PackageJson,
+        selection: DependencySelection,
+    ) -> Result<()> {
+        let manifests =
+            std::iter::once(root_pkg).chain(self.members.iter().map(|m| &m.package_json));
+
+        for pkg in manifests {
+            for (name, spec) in pkg.get_dependencies(selection) {
+                if !is_workspace_spec(&spec) {
+                    continue;
+                }
+                let member = self
+                    .members
+                    .iter()
+                    .find(|m| m.name == name)
+                    .ok_or_else(|| {
+                        PackageManagerError::PackageNotFound(format!("{name}@{spec}"))
+                    })?;
+                let version = member.package_json.version.as_deref().unwrap_or("0.0.0");
+                let range = workspace_range(&spec, version).unwrap_or_default();
+                if !satisfies(version, &range) {
+                    return Err(PackageManagerError::DependencyConflict(
+                        name,
+                        format!("{spec} does not match the workspace version {version}"),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Registry dependencies of the members that `root_deps` does not already declare,
+    /// keyed by name. They are installed once at the root, so members that ask for the
+    /// same package with different ranges are a conflict; declaring it in the root
+    /// `package.json` settles it.
+    pub fn member_dependencies(
+        &self,
+        root_deps: &HashMap<String, String>,
+        selection: DependencySelection,
+    ) -> Result<HashMap<String, String>> {
+        Ok(self
+            .member_specs(root_deps, selection)?
+            .into_iter()
+            .filter(|(_, dep)| !is_local_spec(&dep.spec))
+            .map(|(name, dep)| (name, dep.spec))
+            .collect())
+    }
+
+    /// `file:`, tarball and directory dependencies of the members that `root_deps` does
+    /// not already declare, keyed by name, with the section the member lists them in.
+    /// Paths are resolved against the member that declares them, since the install runs
+    /// at the root.
+    pub fn member_tarball_deps(
+        &self,
+        root_deps: &HashMap<String, String>,
+        selection: DependencySelection,
+    ) -> Result<HashMap<String, (String, DependencyType)>> {
+        Ok(self
+            .member_specs(root_deps, selection)?
+            .into_iter()
+            .filter(|(_, dep)| is_local_spec(&dep.spec))
+            .map(|(name, dep)| {
+                let dep_type = dep
+                    .member
+                    .package_json
+                    .has_dependency(&name)
+                    .unwrap_or(DependencyType::Dependencies);
+                (name, (dep.spec, dep_type))
+            })
+            .collect())
+    }
+
+    /// Every dependency of the members that is neither in `root_deps` nor another member,
+    /// with local paths made absolute. Fails when two members disagree on one.
+    fn member_specs(
+        &self,
+        root_deps: &HashMap<String, String>,
+        selection: DependencySelection,
+    ) -> Result<HashMap<String, MemberSpec<'_>>> {
+        let mut deps: HashMap<String, MemberSpec<'_>> = HashMap::new();
+        for member in &self.members {
+            for (name, spec) in member.package_json.get_dependencies(selection) {
+                if root_deps.contains_key(&name)
+                    || self.is_member(&name)
+                    || is_workspace_spec(&spec)
+                {
+                    continue;
+                }
+                let spec = if is_local_spec(&spec) {
+                    absolute_spec(&spec, &member.path)
+                } else {
+                    spec
+                };
+                match deps.get(&name) {
+                    Some(first) if first.spec != spec => {
+                        return Err(PackageManagerError::DependencyConflict(
+                            name,
+                            format!(
+                                "workspace members {} ({}) and {} ({}) ask for different versions; declare it in the root package.json to pick one",
+                                first.member.name, first.spec, member.name, spec
+                            ),
+                        ));
+                    }
+                    Some(_) => {}
+                    None => {
+                        deps.insert(name, MemberSpec { member, spec });
+                    }
+                }
+            }
+        }
+        Ok(deps)
+    }
+
+    /// Links every member into `root/node_modules`.
+    pub fn link(&self, root: &Path) -> Result<()> {
+        let node_modules = root.join("node_modules");
+        self.members.par_iter().try_for_each(|member| {
+            pacm_store::PackageLinker::link_directory(&node_modules, &member.name, &member.path)
+                .map_err(|e| PackageManagerError::LinkingFailed(member.name.clone(), e.to_string()))
+        })
+    }
+
+    /// Runs the members' [lifecycle scripts](MEMBER_SCRIPTS) in waves that follow the
+    /// dependencies between members: a member's scripts run once those of the members it
+    /// depends on have finished, so a member can build against the output of the ones it
+    /// uses. The members of one wave run in parallel.
+    pub fn run_scripts(&self, debug: bool) {
+        let waves = workspace_waves(self.members.clone());
+        if debug {
+            for (index, wave) in waves.iter().enumerate() {
+                let names: Vec<&str> = wave.iter().map(|m| m.name.as_str()).collect();
+                pacm_logger::debug(
+                    &format!("Workspace wave {}: {}", index + 1, names.join(", ")),
+                    debug,
+                );
+            }
+        }
+
+        for wave in &waves {
+            wave.par_iter().for_each(|member| {
+                let Some(scripts) = &member.package_json.scripts else {
+                    return;
+                };
+                for stage in MEMBER_SCRIPTS {
+                    if let Some(script) = scripts.get(stage) {
+                        InstallUtils::run_lifecycle_script(
+                            &member.name,
+                            stage,
+                            script,
+                            &member.path,
+                            debug,
+                        );
+                    }
+                }
+            });
+        }
+    }
+}
+
+fn is_local_spec(spec: &str) -> bool {
+    is_tarball_spec(spec) || is_directory_spec(spec)
+}
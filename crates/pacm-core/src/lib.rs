@@ -1,21 +1,49 @@
+pub mod audit;
 pub mod clean;
 pub mod download;
+pub mod export;
+pub mod global;
+pub mod import;
 pub mod init;
 pub mod install;
 pub mod linker;
 pub mod list;
+pub mod outdated;
+pub mod pack;
+pub mod publish;
 pub mod remove;
+pub mod store;
+pub mod timing;
 pub mod update;
+pub mod update_notifier;
+pub mod verify;
+pub mod version_pin;
+pub mod why;
 
+pub use audit::AuditManager;
 pub use clean::CleanManager;
+pub use export::LockfileExporter;
+pub use global::GlobalManager;
+pub use import::{ForeignLockfile, ImportedLock, LockfileImporter};
 pub use init::InitManager;
-pub use install::InstallManager;
+pub use install::{InstallManager, InstallOptions, LockfileChanges};
 pub use list::ListManager;
+pub use outdated::OutdatedManager;
+pub use pack::{PackedTarball, Packer};
+pub use publish::{PublishOptions, Publisher};
 pub use remove::RemoveManager;
+pub use store::{StaleEntry, StoreCollector, StoreInspector, StorePrefetcher};
 pub use update::UpdateManager;
+pub use update_notifier::UpdateNotifier;
+pub use verify::VerifyManager;
+pub use version_pin::check_pinned_version;
+pub use why::{Chain, Orphan, WhyManager, orphans, reverse_dependencies};
 
+pub use tokio_util::sync::CancellationToken;
+
+use pacm_config::PacmConfig;
 use pacm_error::Result;
-use pacm_project::DependencyType;
+use pacm_project::{DependencySelection, DependencyType};
 
 pub fn init_project(
     project_dir: &str,
@@ -35,34 +63,99 @@ pub fn init_interactive(project_dir: &str, yes: bool) -> anyhow::Result<()> {
         .map_err(|e| anyhow::anyhow!(e))
 }
 
+/// Reads the configuration with the rc files of `project_dir`, which the install
+/// functions below may be given instead of the working directory.
+fn load_config(project_dir: &str) -> anyhow::Result<()> {
+    PacmConfig::init_for_project(std::path::Path::new(project_dir))
+        .map(|_| ())
+        .map_err(|e| anyhow::anyhow!(pacm_error::PackageManagerError::ConfigError(e)))
+}
+
+/// Installs everything `package.json` declares in the sections `options.selection`
+/// covers, down to `options.max_depth`.
+pub fn install_all_with(project_dir: &str, options: &InstallOptions) -> anyhow::Result<()> {
+    load_config(project_dir)?;
+    let manager =
+        InstallManager::with_selection(options.selection).with_max_depth(options.max_depth);
+    manager
+        .install_all(project_dir, options.debug)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+#[deprecated(note = "use `install_all_with` and `InstallOptions`")]
 pub fn install_all(project_dir: &str, debug: bool) -> anyhow::Result<()> {
-    let manager = InstallManager::new();
+    install_all_with(project_dir, &InstallOptions::new().debug(debug))
+}
+
+#[deprecated(note = "use `install_all_with` with `InstallOptions::selection`")]
+pub fn install_all_selected(
+    project_dir: &str,
+    selection: DependencySelection,
+    debug: bool,
+) -> anyhow::Result<()> {
+    install_all_with(
+        project_dir,
+        &InstallOptions::new().selection(selection).debug(debug),
+    )
+}
+
+/// Async counterpart of [`install_all_with`] for embedders that already run a tokio
+/// runtime. Cancelling `cancel` aborts the install between batches and cleans up the
+/// partial state, exactly like interrupting `pacm install`.
+pub async fn install_all_async(
+    project_dir: &str,
+    options: &InstallOptions,
+    cancel: Option<&CancellationToken>,
+) -> anyhow::Result<()> {
+    load_config(project_dir)?;
+    let manager =
+        InstallManager::with_selection(options.selection).with_max_depth(options.max_depth);
     manager
-        .install_all(project_dir, debug)
+        .install_all_async(project_dir, options.debug, cancel)
+        .await
         .map_err(|e| anyhow::anyhow!(e))
 }
 
-pub fn install_single(
+/// Installs `name` at the newest version `version_range` allows and saves it as
+/// `options` describe.
+pub fn install_single_with(
     project_dir: &str,
     name: &str,
     version_range: &str,
-    debug: bool,
+    options: &InstallOptions,
 ) -> anyhow::Result<()> {
-    let manager = InstallManager::new();
+    load_config(project_dir)?;
+    let manager = InstallManager::new().with_move_existing(options.move_existing);
     manager
         .install_single(
             project_dir,
             name,
             version_range,
-            DependencyType::Dependencies,
-            false, // save_exact
-            false, // no_save
-            false, // force
-            debug,
+            options.dep_type,
+            options.save_exact,
+            options.no_save,
+            options.force,
+            options.debug,
         )
         .map_err(|e| anyhow::anyhow!(e))
 }
 
+#[deprecated(note = "use `install_single_with` and `InstallOptions`")]
+pub fn install_single(
+    project_dir: &str,
+    name: &str,
+    version_range: &str,
+    debug: bool,
+) -> anyhow::Result<()> {
+    install_single_with(
+        project_dir,
+        name,
+        version_range,
+        &InstallOptions::new().debug(debug),
+    )
+}
+
+#[deprecated(note = "use `install_single_with` and `InstallOptions`")]
 pub fn install_enhanced(
     project_dir: &str,
     name: &str,
@@ -73,21 +166,38 @@ pub fn install_enhanced(
     force: bool,
     debug: bool,
 ) -> anyhow::Result<()> {
-    let manager = InstallManager::new();
+    let options = InstallOptions::new()
+        .dep_type(dep_type)
+        .save_exact(save_exact)
+        .no_save(no_save)
+        .force(force)
+        .debug(debug);
+    install_single_with(project_dir, name, version_range, &options)
+}
+
+/// Installs several `(name, version_range)` pairs at once, saving them as `options`
+/// describe.
+pub fn install_multiple_with(
+    project_dir: &str,
+    packages: &[(String, String)],
+    options: &InstallOptions,
+) -> anyhow::Result<()> {
+    load_config(project_dir)?;
+    let manager = InstallManager::new().with_move_existing(options.move_existing);
     manager
-        .install_single(
+        .install_multiple(
             project_dir,
-            name,
-            version_range,
-            dep_type,
-            save_exact,
-            no_save,
-            force,
-            debug,
+            packages,
+            options.dep_type,
+            options.save_exact,
+            options.no_save,
+            options.force,
+            options.debug,
         )
         .map_err(|e| anyhow::anyhow!(e))
 }
 
+#[deprecated(note = "use `install_multiple_with` and `InstallOptions`")]
 pub fn install_multiple(
     project_dir: &str,
     packages: &[(String, String)], // (name, version_range) pairs
@@ -97,17 +207,33 @@ pub fn install_multiple(
     force: bool,
     debug: bool,
 ) -> anyhow::Result<()> {
+    let options = InstallOptions::new()
+        .dep_type(dep_type)
+        .save_exact(save_exact)
+        .no_save(no_save)
+        .force(force)
+        .debug(debug);
+    install_multiple_with(project_dir, packages, &options)
+}
+
+/// Installs a package from a `.tgz` path (relative to `project_dir`) or tarball URL.
+pub fn install_tarball(
+    project_dir: &str,
+    source: &str,
+    dep_type: DependencyType,
+    no_save: bool,
+    debug: bool,
+) -> anyhow::Result<()> {
+    load_config(project_dir)?;
     let manager = InstallManager::new();
     manager
-        .install_multiple(
-            project_dir,
-            packages,
-            dep_type,
-            save_exact,
-            no_save,
-            force,
-            debug,
-        )
+        .install_tarball(project_dir, source, dep_type, no_save, debug)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Adds already declared dependencies to `bundledDependencies` in `package.json`.
+pub fn bundle_deps(project_dir: &str, names: &[String]) -> anyhow::Result<()> {
+    install::utils::InstallUtils::bundle_in_pkg_json(std::path::Path::new(project_dir), names)
         .map_err(|e| anyhow::anyhow!(e))
 }
 
@@ -179,10 +305,79 @@ pub fn update_deps(project_dir: &str, packages: &[String], debug: bool) -> anyho
         .map_err(|e| anyhow::anyhow!(e))
 }
 
-pub fn list_deps(project_dir: &str, tree: bool, depth: Option<u32>) -> anyhow::Result<()> {
+pub fn list_deps(
+    project_dir: &str,
+    tree: bool,
+    depth: Option<u32>,
+    section: Option<DependencyType>,
+    json: bool,
+) -> anyhow::Result<()> {
     let manager = ListManager;
     manager
-        .list_deps(project_dir, tree, depth)
+        .list_deps(project_dir, tree, depth, section, json)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+pub fn list_range_conflicts(project_dir: &str) -> anyhow::Result<()> {
+    let manager = ListManager;
+    manager
+        .list_range_conflicts(project_dir)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// The directory `-g` commands operate on, created on first use.
+pub fn global_dir() -> anyhow::Result<String> {
+    let prefix = GlobalManager::prefix().map_err(|e| anyhow::anyhow!(e))?;
+    Ok(prefix.to_string_lossy().into_owned())
+}
+
+pub fn list_global() -> anyhow::Result<()> {
+    GlobalManager.list().map_err(|e| anyhow::anyhow!(e))
+}
+
+pub fn outdated(project_dir: &str, json: bool, debug: bool) -> anyhow::Result<()> {
+    OutdatedManager::new()
+        .outdated(project_dir, json, debug)
+        .map(|_| ())
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Prints what changed in `pacm.lock` since `before`, a result of
+/// [`LockfileChanges::locked_versions`], as a list or as JSON.
+pub fn print_lockfile_changes(
+    project_dir: &str,
+    before: &std::collections::BTreeMap<String, String>,
+    json: bool,
+) -> anyhow::Result<LockfileChanges> {
+    let after = LockfileChanges::locked_versions(std::path::Path::new(project_dir));
+    let changes = LockfileChanges::between(before, &after);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&changes)?);
+    } else {
+        changes.print();
+    }
+    Ok(changes)
+}
+
+/// Checks `node_modules` against `pacm.lock` without installing anything.
+pub fn verify_install(project_dir: &str) -> anyhow::Result<()> {
+    VerifyManager
+        .verify(project_dir)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+pub fn why(project_dir: &str, package: &str, json: bool) -> anyhow::Result<()> {
+    WhyManager
+        .explain(project_dir, package, json)
+        .map(|_| ())
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Lists locked packages no dependency leads to, failing when there are any.
+pub fn why_orphans(project_dir: &str, json: bool) -> anyhow::Result<()> {
+    WhyManager
+        .list_orphans(project_dir, json)
+        .map(|_| ())
         .map_err(|e| anyhow::anyhow!(e))
 }
 
@@ -197,3 +392,227 @@ pub fn clean_node_modules(project_dir: &str, debug: bool) -> anyhow::Result<()>
         .clean_node_modules(project_dir, debug)
         .map_err(|e| anyhow::anyhow!(e))
 }
+
+pub fn store_path() -> anyhow::Result<()> {
+    StoreInspector.print_path().map_err(|e| anyhow::anyhow!(e))
+}
+
+pub fn store_stats() -> anyhow::Result<()> {
+    StoreInspector.print_stats().map_err(|e| anyhow::anyhow!(e))
+}
+
+pub fn store_versions(package_name: &str) -> anyhow::Result<()> {
+    StoreInspector
+        .print_versions(package_name)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Stores `name@version_range`, and with `deps` its dependency tree, without a project.
+pub fn store_add(name: &str, version_range: &str, deps: bool, debug: bool) -> anyhow::Result<()> {
+    let added = StorePrefetcher::new(deps)
+        .add(name, version_range, debug)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    for (name, version) in &added {
+        println!("{}@{}", name, version);
+    }
+    pacm_logger::finish(&format!("{} packages in the store", added.len()));
+    Ok(())
+}
+
+/// The store entries unused for `days` that neither `projects` nor any project installed
+/// from this store still uses, see [`StoreCollector`].
+pub fn store_gc_candidates(days: u64, projects: &[String]) -> anyhow::Result<Vec<StaleEntry>> {
+    let store_base = pacm_store::get_store_path();
+    let mut collector = StoreCollector::new(days);
+    collector
+        .keep_registered_projects(&store_base)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    for project in projects {
+        collector
+            .keep_project(std::path::Path::new(project))
+            .map_err(|e| anyhow::anyhow!(e))?;
+    }
+    collector
+        .stale(&store_base, std::time::SystemTime::now())
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Removes `entries` from the store, returning the bytes reclaimed.
+pub fn store_gc(days: u64, entries: &[StaleEntry]) -> anyhow::Result<u64> {
+    StoreCollector::new(days)
+        .remove(entries)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Writes the project's tarball, as `pacm publish` would upload it, to `project_dir`.
+/// With `dry_run` only the summary is printed.
+pub fn pack(project_dir: &str, dry_run: bool) -> anyhow::Result<()> {
+    let tarball = Packer
+        .pack(std::path::Path::new(project_dir))
+        .map_err(|e| anyhow::anyhow!(e))?;
+    Packer.print_summary(&tarball);
+    if dry_run {
+        return Ok(());
+    }
+
+    let path = std::path::Path::new(project_dir).join(tarball.filename());
+    std::fs::write(&path, &tarball.bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    pacm_logger::finish(&format!("Packed {}", tarball.filename()));
+    Ok(())
+}
+
+/// Seeds `pacm.lock` from `from`, or from the npm or Yarn lockfile found in the project.
+/// An existing `pacm.lock` is only replaced with `force`.
+pub fn import_lockfile(project_dir: &str, from: Option<&str>, force: bool) -> anyhow::Result<()> {
+    let project_dir = std::path::Path::new(project_dir);
+    let lock_path = PacmConfig::get().lockfile_path(project_dir);
+    if lock_path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists; pass --force to replace it",
+            PacmConfig::get().lockfile_name()
+        );
+    }
+
+    let (path, format) = match from {
+        Some(from) => {
+            let path = project_dir.join(from);
+            let format = ForeignLockfile::from_path(&path).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{} is not a package-lock.json, npm-shrinkwrap.json or yarn.lock",
+                    from
+                )
+            })?;
+            (path, format)
+        }
+        None => ForeignLockfile::detect(project_dir).ok_or_else(|| {
+            anyhow::anyhow!("No package-lock.json, npm-shrinkwrap.json or yarn.lock to import")
+        })?,
+    };
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let pkg = pacm_project::read_package_json(project_dir).map_err(|e| {
+        anyhow::anyhow!(pacm_error::PackageManagerError::PackageJsonError(
+            e.to_string()
+        ))
+    })?;
+
+    let imported = LockfileImporter
+        .import(&content, format, &pkg)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    for warning in &imported.warnings {
+        pacm_logger::warn(warning);
+    }
+    imported
+        .lockfile
+        .save(&lock_path)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", lock_path.display(), e))?;
+    pacm_logger::finish(&format!(
+        "Imported {} packages from {} into {}",
+        imported.lockfile.packages.len(),
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        PacmConfig::get().lockfile_name()
+    ));
+    Ok(())
+}
+
+/// Writes `pacm.lock` as `format` to `output`, by default the `package-lock.json` or
+/// `yarn.lock` of the project. An existing file is only replaced with `force`.
+pub fn export_lockfile(
+    project_dir: &str,
+    format: ForeignLockfile,
+    output: Option<&str>,
+    force: bool,
+) -> anyhow::Result<()> {
+    let project_dir = std::path::Path::new(project_dir);
+    let lock_path = PacmConfig::get().lockfile_path(project_dir);
+    if !lock_path.exists() {
+        anyhow::bail!(
+            "No {} to export; run pacm install first",
+            PacmConfig::get().lockfile_name()
+        );
+    }
+    let default_output = match format {
+        ForeignLockfile::Npm => "package-lock.json",
+        ForeignLockfile::Yarn => "yarn.lock",
+    };
+    let path = project_dir.join(output.unwrap_or(default_output));
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists; pass --force to replace it",
+            path.display()
+        );
+    }
+
+    let lockfile = pacm_lock::PacmLock::load(&lock_path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", lock_path.display(), e))?;
+    let pkg = pacm_project::read_package_json(project_dir).map_err(|e| {
+        anyhow::anyhow!(pacm_error::PackageManagerError::PackageJsonError(
+            e.to_string()
+        ))
+    })?;
+    let content = LockfileExporter
+        .export(&lockfile, &pkg, format)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    std::fs::write(&path, content)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+    pacm_logger::finish(&format!(
+        "Exported {} packages from {} to {}",
+        lockfile.packages.len(),
+        PacmConfig::get().lockfile_name(),
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    Ok(())
+}
+
+pub fn publish(project_dir: &str, options: &PublishOptions) -> anyhow::Result<()> {
+    let tarball = Publisher::new()
+        .publish(std::path::Path::new(project_dir), options)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    Packer.print_summary(&tarball);
+    if !options.dry_run {
+        pacm_logger::finish(&format!(
+            "Published {}@{} with tag {}",
+            tarball.name, tarball.version, options.tag
+        ));
+    }
+    Ok(())
+}
+
+pub fn audit(project_dir: &str, json: bool, audit_level: &str, debug: bool) -> anyhow::Result<()> {
+    let level = audit::Severity::parse(audit_level)
+        .ok_or_else(|| anyhow::anyhow!("Invalid audit level '{}'", audit_level))?;
+    let manager = AuditManager::new();
+    manager
+        .audit(project_dir, json, level, debug)
+        .map(|_| ())
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Prints the vulnerability summary of `pacm install --audit`. Nothing is printed when
+/// the advisories cannot be fetched in time.
+pub fn print_audit_summary(project_dir: &str, debug: bool) {
+    let Some(metadata) = AuditManager::new().summary(project_dir, debug) else {
+        return;
+    };
+    if metadata.total() > 0 {
+        pacm_logger::warn(&metadata.summary_line());
+    } else {
+        pacm_logger::info(&metadata.summary_line());
+    }
+}
+
+pub fn audit_fix(
+    project_dir: &str,
+    json: bool,
+    audit_level: &str,
+    debug: bool,
+) -> anyhow::Result<()> {
+    let level = audit::Severity::parse(audit_level)
+        .ok_or_else(|| anyhow::anyhow!("Invalid audit level '{}'", audit_level))?;
+    let manager = AuditManager::new();
+    manager
+        .fix(project_dir, json, level, debug)
+        .map(|_| ())
+        .map_err(|e| anyhow::anyhow!(e))
+}
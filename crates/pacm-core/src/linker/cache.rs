@@ -6,7 +6,7 @@ use crate::install::CachedPackage;
 use pacm_error::Result;
 use pacm_logger;
 use pacm_resolver::ResolvedPackage;
-use pacm_store::link_package;
+use pacm_store::{link_package, long_path};
 
 pub struct CacheLinker;
 
@@ -40,7 +40,7 @@ impl CacheLinker {
                         );
                     }
 
-                    for (dep_name, _dep_range) in &resolved_pkg.dependencies {
+                    for dep_name in Self::linked_dep_names(resolved_pkg) {
                         let dep_link_path = get_dep_link_path(&package_node_modules, dep_name);
 
                         if !dep_link_path.exists() || !is_valid_package_link(&dep_link_path, debug)
@@ -83,6 +83,15 @@ impl CacheLinker {
         Ok(())
     }
 
+    /// Dependencies that get a link in the package's own `node_modules`. Bundled ones
+    /// already live there as real directories from the tarball and must not be replaced.
+    fn linked_dep_names(resolved_pkg: &ResolvedPackage) -> impl Iterator<Item = &String> {
+        resolved_pkg
+            .dependencies
+            .keys()
+            .filter(|name| !resolved_pkg.bundled_dependencies.contains(name))
+    }
+
     fn relink_deps(
         cached_pkg: &CachedPackage,
         resolved_pkg: &ResolvedPackage,
@@ -99,7 +108,7 @@ impl CacheLinker {
             debug,
         );
 
-        if let Err(e) = std::fs::create_dir_all(&package_node_modules) {
+        if let Err(e) = std::fs::create_dir_all(long_path(&package_node_modules)) {
             pacm_logger::debug(
                 &format!(
                     "Failed to create node_modules for {}: {}",
@@ -110,7 +119,7 @@ impl CacheLinker {
             return Ok(());
         }
 
-        for (dep_name, _dep_range) in &resolved_pkg.dependencies {
+        for dep_name in Self::linked_dep_names(resolved_pkg) {
             if let Some((_, dep_store_path)) = all_stored_packages
                 .iter()
                 .find(|(key, _)| key.starts_with(&format!("{}@", dep_name)))
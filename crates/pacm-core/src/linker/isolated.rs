@@ -0,0 +1,365 @@
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use pacm_error::{PackageManagerError, Result};
+use pacm_logger;
+use pacm_project::read_package_json;
+use pacm_resolver::{ResolvedPackage, satisfies};
+use pacm_store::{PackageLinker as StoreLinker, StoreManager, long_path};
+
+/// Directory under `node_modules` that holds one entry per `name@version`.
+pub const VIRTUAL_STORE_DIR: &str = ".pacm";
+
+/// Lays out `node_modules` so that packages only see what they depend on:
+///
+/// ```text
+/// node_modules/
+///   a -> .pacm/a@1.0.0/node_modules/a
+///   .pacm/
+///     a@1.0.0/node_modules/a/       hard links to <store>/npm/a/1.0.0/package
+///     a@1.0.0/node_modules/b -> .pacm/b@2.0.0/node_modules/b
+///     b@2.0.0/node_modules/b/       hard links to <store>/npm/b/2.0.0/package
+/// ```
+///
+/// Only direct dependencies get a top-level link, so nothing can require a package it
+/// did not declare. The entries themselves are hard-linked rather than symlinked: Node
+/// resolves a module's dependencies from its real path, which for a symlink into the
+/// store would be the store instead of the entry's `node_modules`.
+pub struct IsolatedLinker;
+
+impl IsolatedLinker {
+    pub fn link_all_deps(
+        project_dir: &Path,
+        stored_packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
+        debug: bool,
+    ) -> Result<()> {
+        pacm_logger::status("Linking all packages to project (isolated node_modules)...");
+
+        let node_modules = project_dir.join("node_modules");
+        let virtual_store = node_modules.join(VIRTUAL_STORE_DIR);
+
+        let results: Vec<_> = stored_packages
+            .par_iter()
+            .map(|(_, (pkg, store_path))| {
+                if debug {
+                    pacm_logger::debug(
+                        &format!(
+                            "Linking {}@{} into the virtual store",
+                            pkg.name, pkg.version
+                        ),
+                        debug,
+                    );
+                }
+
+                Self::link_virtual_entry(&virtual_store, pkg, store_path, stored_packages).map_err(
+                    |e| {
+                        pacm_logger::error(&format!(
+                            "Failed to link {}@{}: {}",
+                            pkg.name, pkg.version, e
+                        ));
+                        PackageManagerError::LinkingFailed(pkg.name.clone(), e.to_string())
+                    },
+                )
+            })
+            .collect();
+
+        for result in results {
+            result?;
+        }
+
+        for pkg in Self::top_level_packages(project_dir, stored_packages) {
+            let target = Self::entry_path(&virtual_store, pkg);
+            StoreLinker::link_directory(&long_path(&node_modules), &pkg.name, &target).map_err(
+                |e| {
+                    pacm_logger::error(&format!(
+                        "Failed to link {}@{}: {}",
+                        pkg.name, pkg.version, e
+                    ));
+                    PackageManagerError::LinkingFailed(pkg.name.clone(), e.to_string())
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The package directory inside the virtual store, e.g.
+    /// `node_modules/.pacm/@types+node@20.0.0/node_modules/@types/node`.
+    pub fn entry_path(virtual_store: &Path, pkg: &ResolvedPackage) -> PathBuf {
+        Self::entry_node_modules(virtual_store, pkg).join(&pkg.name)
+    }
+
+    /// The `node_modules` that `pkg` resolves its dependencies from. Scoped names use `+`
+    /// in place of the slash to keep one directory per entry.
+    fn entry_node_modules(virtual_store: &Path, pkg: &ResolvedPackage) -> PathBuf {
+        virtual_store
+            .join(format!("{}@{}", pkg.name.replace('/', "+"), pkg.version))
+            .join("node_modules")
+    }
+
+    fn link_virtual_entry(
+        virtual_store: &Path,
+        pkg: &ResolvedPackage,
+        store_path: &Path,
+        stored_packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
+    ) -> std::io::Result<()> {
+        // Entries nest a whole `node_modules` inside the project's, so they reach Windows'
+        // `MAX_PATH` first. Link targets stay in plain form; the linker normalises them.
+        let entry_node_modules = long_path(&Self::entry_node_modules(virtual_store, pkg));
+
+        StoreLinker::hard_link_directory(
+            &entry_node_modules,
+            &pkg.name,
+            &long_path(&store_path.join("package")),
+        )?;
+        // Only feeds `pacm store gc`, so a read-only store does not fail the link.
+        let _ = StoreManager::mark_used(store_path);
+
+        for (dep_name, range) in pkg.dependencies.iter().chain(&pkg.optional_dependencies) {
+            if pkg.bundled_dependencies.contains(dep_name) || dep_name == &pkg.name {
+                continue;
+            }
+            // Optional dependencies that were skipped for this platform have no entry.
+            if let Some(dep) = Self::find_stored(stored_packages, dep_name, range) {
+                let target = Self::entry_path(virtual_store, dep);
+                StoreLinker::link_directory(&entry_node_modules, dep_name, &target)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Picks the stored version of `name` that satisfies `range`, or any stored version
+    /// when none does, such as for dist-tags and URLs.
+    fn find_stored<'a>(
+        stored_packages: &'a HashMap<String, (ResolvedPackage, PathBuf)>,
+        name: &str,
+        range: &str,
+    ) -> Option<&'a ResolvedPackage> {
+        let mut candidates = stored_packages
+            .values()
+            .map(|(pkg, _)| pkg)
+            .filter(|pkg| pkg.name == name)
+            .peekable();
+        let fallback = *candidates.peek()?;
+        Some(
+            candidates
+                .find(|pkg| satisfies(&pkg.version, range))
+                .unwrap_or(fallback),
+        )
+    }
+
+    /// Packages linked directly into `node_modules`: everything `package.json` declares,
+    /// plus packages nothing else depends on, which covers `--no-save` installs.
+    fn top_level_packages<'a>(
+        project_dir: &Path,
+        stored_packages: &'a HashMap<String, (ResolvedPackage, PathBuf)>,
+    ) -> Vec<&'a ResolvedPackage> {
+        let declared = read_package_json(project_dir)
+            .map(|pkg| pkg.get_all_dependencies())
+            .unwrap_or_default();
+
+        let required: HashSet<&str> = stored_packages
+            .values()
+            .flat_map(|(pkg, _)| {
+                pkg.dependencies
+                    .keys()
+                    .chain(pkg.optional_dependencies.keys())
+                    .filter(move |dep| *dep != &pkg.name)
+            })
+            .map(String::as_str)
+            .collect();
+
+        let mut top_level: HashMap<&str, &ResolvedPackage> = HashMap::new();
+        for (name, range) in &declared {
+            if let Some(pkg) = Self::find_stored(stored_packages, name, range) {
+                top_level.insert(&pkg.name, pkg);
+            }
+        }
+        for (pkg, _) in stored_packages.values() {
+            if !required.contains(pkg.name.as_str()) {
+                top_level.entry(&pkg.name).or_insert(pkg);
+            }
+        }
+        top_level.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_testutil::resolved_package;
+    use std::fs;
+
+    fn store(
+        stored: &mut HashMap<String, (ResolvedPackage, PathBuf)>,
+        store_base: &Path,
+        name: &str,
+        version: &str,
+        dependencies: &[(&str, &str)],
+    ) {
+        let store_path = store_base.join(format!("{}@{}", name.replace('/', "+"), version));
+        fs::create_dir_all(store_path.join("package")).unwrap();
+        fs::write(
+            store_path.join("package").join("package.json"),
+            serde_json::json!({ "name": name, "version": version }).to_string(),
+        )
+        .unwrap();
+        fs::write(
+            store_path.join("package").join("index.js"),
+            "module.exports = {};",
+        )
+        .unwrap();
+
+        let pkg = resolved_package(name, version, dependencies);
+        stored.insert(format!("{}@{}", name, version), (pkg, store_path));
+    }
+
+    fn linked_version(path: &Path) -> String {
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path.join("package.json")).unwrap()).unwrap();
+        manifest["version"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_isolated_layout_hides_transitive_dependencies() {
+        pacm_logger::init_logger(true);
+        let store_base = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        fs::write(
+            project.path().join("package.json"),
+            r#"{ "name": "app", "version": "1.0.0", "dependencies": { "a": "^1.0.0" } }"#,
+        )
+        .unwrap();
+
+        let mut stored = HashMap::new();
+        store(
+            &mut stored,
+            store_base.path(),
+            "a",
+            "1.0.0",
+            &[("@scope/b", "^2.0.0")],
+        );
+        store(&mut stored, store_base.path(), "@scope/b", "2.1.0", &[]);
+
+        IsolatedLinker::link_all_deps(project.path(), &stored, false).unwrap();
+
+        let node_modules = project.path().join("node_modules");
+        let virtual_store = node_modules.join(VIRTUAL_STORE_DIR);
+        let a_entry = virtual_store.join("a@1.0.0").join("node_modules");
+        let b_entry = virtual_store.join("@scope+b@2.1.0").join("node_modules");
+
+        assert_eq!(
+            fs::read_link(node_modules.join("a")).unwrap(),
+            a_entry.join("a")
+        );
+        assert!(!node_modules.join("@scope").exists());
+
+        assert!(!a_entry.join("a").is_symlink());
+        assert_eq!(linked_version(&a_entry.join("a")), "1.0.0");
+        assert_eq!(
+            fs::read_link(a_entry.join("@scope").join("b")).unwrap(),
+            b_entry.join("@scope").join("b")
+        );
+        assert_eq!(linked_version(&a_entry.join("@scope").join("b")), "2.1.0");
+        assert!(!b_entry.join("a").exists());
+    }
+
+    #[test]
+    fn test_isolated_layout_keeps_conflicting_versions_apart() {
+        pacm_logger::init_logger(true);
+        let store_base = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        fs::write(
+            project.path().join("package.json"),
+            r#"{ "name": "app", "version": "1.0.0", "dependencies": { "a": "^1.0.0", "b": "^2.0.0" } }"#,
+        )
+        .unwrap();
+
+        let mut stored = HashMap::new();
+        store(
+            &mut stored,
+            store_base.path(),
+            "a",
+            "1.0.0",
+            &[("b", "^1.0.0")],
+        );
+        store(&mut stored, store_base.path(), "b", "1.4.0", &[]);
+        store(&mut stored, store_base.path(), "b", "2.0.0", &[]);
+
+        IsolatedLinker::link_all_deps(project.path(), &stored, false).unwrap();
+
+        let node_modules = project.path().join("node_modules");
+        let a_entry = node_modules
+            .join(VIRTUAL_STORE_DIR)
+            .join("a@1.0.0")
+            .join("node_modules");
+
+        assert_eq!(linked_version(&node_modules.join("b")), "2.0.0");
+        assert_eq!(linked_version(&a_entry.join("b")), "1.4.0");
+    }
+
+    #[test]
+    fn test_isolated_packages_only_resolve_what_they_declare() {
+        if std::process::Command::new("node")
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            eprintln!("node is not installed, skipping");
+            return;
+        }
+        pacm_logger::init_logger(true);
+        let store_base = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        fs::write(
+            project.path().join("package.json"),
+            r#"{ "name": "app", "version": "1.0.0", "dependencies": { "a": "^1.0.0", "x": "^1.0.0" } }"#,
+        )
+        .unwrap();
+
+        let mut stored = HashMap::new();
+        store(
+            &mut stored,
+            store_base.path(),
+            "a",
+            "1.0.0",
+            &[("b", "^1.0.0")],
+        );
+        store(&mut stored, store_base.path(), "b", "1.0.0", &[]);
+        store(
+            &mut stored,
+            store_base.path(),
+            "x",
+            "1.0.0",
+            &[("c", "^1.0.0")],
+        );
+        store(&mut stored, store_base.path(), "c", "1.0.0", &[]);
+
+        IsolatedLinker::link_all_deps(project.path(), &stored, false).unwrap();
+
+        // Node loads `a` from its real path, and `c` is only a dependency of `x`, so `a`
+        // must not find it.
+        let script = "const a = require('fs').realpathSync(process.argv[1]); \
+            const r = require('module').createRequire(a); \
+            for (const name of process.argv.slice(2)) { \
+                try { r.resolve(name); console.log(name + ' found'); } \
+                catch { console.log(name + ' missing'); } \
+            }";
+        let output = std::process::Command::new("node")
+            .arg("-e")
+            .arg(script)
+            .arg(project.path().join("node_modules/a/index.js"))
+            .args(["b", "c"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .collect::<Vec<_>>(),
+            ["b found", "c missing"]
+        );
+    }
+}
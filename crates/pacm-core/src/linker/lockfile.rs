@@ -1,32 +1,24 @@
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use pacm_config::PacmConfig;
 use pacm_error::{PackageManagerError, Result};
 use pacm_lock::{LockDependency, LockPackage, PacmLock};
+use pacm_project::{DependencyType, read_package_json};
 use pacm_resolver::ResolvedPackage;
+use pacm_store::StoreManager;
 
 pub struct LockfileManager;
 
 impl LockfileManager {
     pub fn update_all(
         lock_path: &Path,
-        stored_packages: &HashMap<String, (ResolvedPackage, std::path::PathBuf)>,
+        stored_packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
     ) -> Result<()> {
         let mut lockfile = PacmLock::load(lock_path)
             .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
 
-        for (_key, (pkg, _)) in stored_packages {
-            lockfile.update_package(
-                &pkg.name,
-                LockPackage {
-                    version: pkg.version.clone(),
-                    resolved: pkg.resolved.clone(),
-                    integrity: pkg.integrity.clone(),
-                    dependencies: pkg.dependencies.clone(),
-                    optional_dependencies: pkg.optional_dependencies.clone(),
-                },
-            );
-        }
+        Self::update_packages(&mut lockfile, stored_packages);
 
         lockfile
             .save(lock_path)
@@ -37,34 +29,31 @@ impl LockfileManager {
 
     pub fn update_direct_only(
         lock_path: &Path,
-        stored_packages: &HashMap<String, (ResolvedPackage, std::path::PathBuf)>,
+        stored_packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
         direct_package_names: &HashSet<String>,
     ) -> Result<()> {
         let mut lockfile = PacmLock::load(lock_path)
             .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+        let package_json = lock_path
+            .parent()
+            .and_then(|project_dir| read_package_json(project_dir).ok());
 
         for name in direct_package_names {
             if let Some((_key, (pkg, _))) =
                 stored_packages.iter().find(|(_, (p, _))| &p.name == name)
             {
+                let dep_type = package_json
+                    .as_ref()
+                    .and_then(|package_json| package_json.has_dependency(name))
+                    .unwrap_or(DependencyType::Dependencies);
+
                 let mut workspace_deps = HashMap::new();
                 workspace_deps.insert(pkg.name.clone(), pkg.version.clone());
-                lockfile.update_workspace_deps("", &workspace_deps, "dependencies");
+                lockfile.update_workspace_deps("", &workspace_deps, dep_type.as_str());
             }
         }
 
-        for (_key, (pkg, _)) in stored_packages {
-            lockfile.update_package(
-                &pkg.name,
-                LockPackage {
-                    version: pkg.version.clone(),
-                    resolved: pkg.resolved.clone(),
-                    integrity: pkg.integrity.clone(),
-                    dependencies: pkg.dependencies.clone(),
-                    optional_dependencies: pkg.optional_dependencies.clone(),
-                },
-            );
-        }
+        Self::update_packages(&mut lockfile, stored_packages);
 
         lockfile
             .save(lock_path)
@@ -75,27 +64,93 @@ impl LockfileManager {
 
     pub fn update_from_lockfile_install(
         lock_path: &Path,
-        stored_packages: &HashMap<String, (ResolvedPackage, std::path::PathBuf)>,
+        stored_packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
     ) -> Result<()> {
         let mut lockfile = PacmLock::load(lock_path)
             .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
 
-        for (_key, (pkg, _)) in stored_packages {
-            lockfile.update_package(
-                &pkg.name,
-                LockPackage {
-                    version: pkg.version.clone(),
-                    resolved: pkg.resolved.clone(),
-                    integrity: pkg.integrity.clone(),
-                    dependencies: pkg.dependencies.clone(),
-                    optional_dependencies: pkg.optional_dependencies.clone(),
-                },
-            );
+        Self::update_packages(&mut lockfile, stored_packages);
+
+        lockfile
+            .save(lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Records whether the lockfile only covers a depth-limited part of the tree.
+    pub fn set_partial(lock_path: &Path, partial: bool) -> Result<()> {
+        let mut lockfile = PacmLock::load(lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+        if lockfile.partial == partial {
+            return Ok(());
         }
 
+        lockfile.partial = partial;
         lockfile
             .save(lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))
+    }
+
+    fn update_packages(
+        lockfile: &mut PacmLock,
+        stored_packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
+    ) {
+        for (pkg, store_path) in stored_packages.values() {
+            let integrity = Self::integrity(lockfile, pkg, store_path);
+            lockfile.update_package(&pkg.name, Self::lock_package(pkg, integrity));
+        }
+    }
+
+    /// The integrity to lock for `pkg`. Packages resolved without one, through the fast
+    /// paths or from registries that do not publish it, fall back to the integrity the
+    /// store recorded when extracting the tarball, then to what the lockfile already
+    /// holds for the same version.
+    fn integrity(lockfile: &PacmLock, pkg: &ResolvedPackage, store_path: &Path) -> String {
+        if !pkg.integrity.is_empty() {
+            return pkg.integrity.clone();
+        }
+        StoreManager::stored_integrity(store_path)
+            .or_else(|| {
+                lockfile
+                    .get_package(&pkg.name)
+                    .filter(|locked| locked.version == pkg.version && !locked.integrity.is_empty())
+                    .map(|locked| locked.integrity.clone())
+            })
+            .unwrap_or_default()
+    }
+
+    fn lock_package(pkg: &ResolvedPackage, integrity: String) -> LockPackage {
+        LockPackage {
+            version: pkg.version.clone(),
+            resolved: PacmConfig::get().lock_resolved(&pkg.name, &pkg.resolved),
+            integrity,
+            dependencies: pkg.dependencies.clone(),
+            optional_dependencies: pkg.optional_dependencies.clone(),
+            peer_dependencies: pkg.peer_dependencies.clone(),
+            optional_peer_dependencies: pkg.optional_peer_dependencies.clone(),
+        }
+    }
+
+    /// Points packages at the tarball URLs recorded in the lockfile, rebuilding relative
+    /// entries against the active registry.
+    pub fn apply_locked_urls(lock_path: &Path, packages: &mut [ResolvedPackage]) -> Result<()> {
+        if !lock_path.exists() {
+            return Ok(());
+        }
+
+        let lockfile = PacmLock::load(lock_path)
             .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+        let config = PacmConfig::get();
+
+        for pkg in packages {
+            if let Some(locked) = lockfile.get_package(&pkg.name)
+                && locked.version == pkg.version
+                && !locked.resolved.is_empty()
+            {
+                pkg.resolved = config.absolute_resolved(&pkg.name, &locked.resolved);
+            }
+        }
 
         Ok(())
     }
@@ -1,20 +1,67 @@
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use crate::install::CachedPackage;
+use crate::timing::{self, Phase};
+use pacm_config::{NodeLinker, PacmConfig};
 use pacm_error::Result;
 use pacm_lock::LockDependency;
 use pacm_project::DependencyType;
 use pacm_resolver::ResolvedPackage;
+use pacm_store::{ProjectRegistry, get_store_path};
+use pacm_symcap::SystemCapabilities;
 
 use super::cache::CacheLinker;
+use super::isolated::IsolatedLinker;
 use super::lockfile::LockfileManager;
 use super::project::ProjectLinker;
 use super::store::StoreLinker;
 
-pub struct PackageLinker;
+/// The threads that link packages into `node_modules`, capped at
+/// [`SystemCapabilities::optimal_parallel_links`] and kept apart from the global rayon
+/// pool, so linking neither floods the filesystem nor waits behind other parallel work.
+/// `None` when the pool could not be started, in which case the global pool is used.
+fn link_pool() -> Option<&'static rayon::ThreadPool> {
+    static POOL: OnceLock<Option<rayon::ThreadPool>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(SystemCapabilities::get().optimal_parallel_links)
+            .thread_name(|index| format!("pacm-link-{index}"))
+            .build()
+            .ok()
+    })
+    .as_ref()
+}
+
+/// Runs `link` on the linking pool.
+pub fn in_link_pool<T: Send>(link: impl FnOnce() -> T + Send) -> T {
+    match link_pool() {
+        Some(pool) => pool.install(link),
+        None => link(),
+    }
+}
+
+pub struct PackageLinker {
+    node_linker: NodeLinker,
+}
+
+impl Default for PackageLinker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl PackageLinker {
+    /// A linker using the configured `node-linker`.
+    pub fn new() -> Self {
+        Self::with_node_linker(PacmConfig::get().node_linker())
+    }
+
+    pub fn with_node_linker(node_linker: NodeLinker) -> Self {
+        Self { node_linker }
+    }
+
     pub fn link_deps_to_store(
         &self,
         stored_packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
@@ -39,6 +86,7 @@ impl PackageLinker {
         direct_package_names: &HashSet<String>,
         debug: bool,
     ) -> Result<()> {
+        Self::register_project(project_dir);
         ProjectLinker::link_direct_deps(project_dir, stored_packages, direct_package_names, debug)
     }
 
@@ -48,7 +96,17 @@ impl PackageLinker {
         stored_packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
         debug: bool,
     ) -> Result<()> {
-        ProjectLinker::link_all_deps(project_dir, stored_packages, debug)
+        Self::register_project(project_dir);
+        timing::timed(Phase::Linking, || {
+            in_link_pool(|| match self.node_linker {
+                NodeLinker::Hoisted => {
+                    ProjectLinker::link_all_deps(project_dir, stored_packages, debug)
+                }
+                NodeLinker::Isolated => {
+                    IsolatedLinker::link_all_deps(project_dir, stored_packages, debug)
+                }
+            })
+        })
     }
 
     pub fn link_single_to_project(
@@ -58,9 +116,17 @@ impl PackageLinker {
         stored_packages: &HashMap<String, (ResolvedPackage, PathBuf)>,
         debug: bool,
     ) -> Result<()> {
+        Self::register_project(project_dir);
         ProjectLinker::link_single_pkg(project_dir, package_name, stored_packages, debug)
     }
 
+    /// Lets `pacm store gc` find the project, so it keeps what the project links however
+    /// long ago that was. Failing to record it is not fatal; gc then only keeps the project's
+    /// packages when it is passed with `--project`.
+    fn register_project(project_dir: &Path) {
+        let _ = ProjectRegistry::register(&get_store_path(), project_dir);
+    }
+
     pub fn update_lockfile(
         &self,
         lock_path: &Path,
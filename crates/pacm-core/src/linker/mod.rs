@@ -1,7 +1,10 @@
 pub mod cache;
+pub mod isolated;
 pub mod lockfile;
 pub mod manager;
+pub mod npm_compat;
 pub mod project;
 pub mod store;
 
+pub use isolated::IsolatedLinker;
 pub use manager::PackageLinker;
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use serde_json::{Map, Value, json};
+
+use pacm_config::{NodeLinker, PacmConfig};
+use pacm_error::{PackageManagerError, Result};
+use pacm_lock::{LockPackage, PacmLock};
+use pacm_project::read_package_json;
+
+use super::isolated::VIRTUAL_STORE_DIR;
+
+/// The file under `node_modules` where npm records the tree it installed.
+pub const NPM_HIDDEN_LOCKFILE: &str = ".package-lock.json";
+
+/// Translates `pacm.lock` into npm's hidden lockfile, `node_modules/.package-lock.json`,
+/// for bundlers and editors that read it to learn what is installed. Only install paths,
+/// versions, `resolved`, `integrity` and dependency ranges are carried over.
+pub struct NpmCompat;
+
+impl NpmCompat {
+    /// Writes the hidden lockfile of the project at `project_dir`. Does nothing without a
+    /// `pacm.lock` or `node_modules` to describe.
+    pub fn write(project_dir: &Path) -> Result<()> {
+        let lock_path = PacmConfig::get().lockfile_path(project_dir);
+        let node_modules = project_dir.join("node_modules");
+        if !lock_path.exists() || !node_modules.is_dir() {
+            return Ok(());
+        }
+
+        let lockfile = PacmLock::load(&lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+        let pkg = read_package_json(project_dir)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let document = Self::lockfile(
+            &lockfile,
+            pkg.name.as_deref().unwrap_or_default(),
+            pkg.version.as_deref().unwrap_or_default(),
+            PacmConfig::get().node_linker(),
+        );
+
+        let path = node_modules.join(NPM_HIDDEN_LOCKFILE);
+        let content = serde_json::to_string_pretty(&document)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+        fs::write(&path, content + "\n").map_err(|e| {
+            PackageManagerError::IoError(format!("Failed to write {}: {}", path.display(), e))
+        })
+    }
+
+    /// The lockfile v3 document for `lockfile`, with packages at the paths `node_linker`
+    /// puts them.
+    pub fn lockfile(
+        lockfile: &PacmLock,
+        name: &str,
+        version: &str,
+        node_linker: NodeLinker,
+    ) -> Value {
+        let direct: HashSet<&String> = lockfile
+            .workspaces
+            .get("")
+            .map(|root| {
+                root.dependencies
+                    .keys()
+                    .chain(root.dev_dependencies.keys())
+                    .chain(root.optional_dependencies.keys())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut packages = BTreeMap::new();
+        for (pkg_name, package) in &lockfile.packages {
+            let top_level = format!("node_modules/{pkg_name}");
+            if let Some(target) = package.resolved.strip_prefix("link:") {
+                let target = target.trim_start_matches("./");
+                packages.insert(top_level, json!({ "resolved": target, "link": true }));
+                continue;
+            }
+
+            let entry = Self::entry(pkg_name, package);
+            match node_linker {
+                NodeLinker::Hoisted => {
+                    packages.insert(top_level, entry);
+                }
+                NodeLinker::Isolated => {
+                    let path = format!(
+                        "node_modules/{}/{}@{}/node_modules/{}",
+                        VIRTUAL_STORE_DIR,
+                        pkg_name.replace('/', "+"),
+                        package.version,
+                        pkg_name
+                    );
+                    if direct.contains(pkg_name) {
+                        packages.insert(top_level, json!({ "resolved": path, "link": true }));
+                    }
+                    packages.insert(path, entry);
+                }
+            }
+        }
+
+        json!({
+            "name": name,
+            "version": version,
+            "lockfileVersion": 3,
+            "requires": true,
+            "packages": packages,
+        })
+    }
+
+    fn entry(name: &str, package: &LockPackage) -> Value {
+        let mut entry = Map::new();
+        entry.insert("version".to_string(), json!(package.version));
+        let resolved = PacmConfig::get().absolute_resolved(name, &package.resolved);
+        if !resolved.is_empty() {
+            entry.insert("resolved".to_string(), json!(resolved));
+        }
+        if !package.integrity.is_empty() {
+            entry.insert("integrity".to_string(), json!(package.integrity));
+        }
+
+        let sections = [
+            ("dependencies", &package.dependencies),
+            ("optionalDependencies", &package.optional_dependencies),
+        ];
+        for (key, deps) in sections {
+            if !deps.is_empty() {
+                let sorted: BTreeMap<_, _> = deps.iter().collect();
+                entry.insert(key.to_string(), json!(sorted));
+            }
+        }
+
+        // npm lists optional peers with the required ones and marks them in the meta.
+        let peers: BTreeMap<_, _> = package
+            .peer_dependencies
+            .iter()
+            .chain(&package.optional_peer_dependencies)
+            .collect();
+        if !peers.is_empty() {
+            entry.insert("peerDependencies".to_string(), json!(peers));
+        }
+        if !package.optional_peer_dependencies.is_empty() {
+            let meta: BTreeMap<_, _> = package
+                .optional_peer_dependencies
+                .keys()
+                .map(|peer| (peer, json!({ "optional": true })))
+                .collect();
+            entry.insert("peerDependenciesMeta".to_string(), json!(meta));
+        }
+
+        Value::Object(entry)
+    }
+}
@@ -4,8 +4,8 @@ use std::path::{Path, PathBuf};
 
 use pacm_error::{PackageManagerError, Result};
 use pacm_logger;
-use pacm_project::DependencyType;
-use pacm_resolver::ResolvedPackage;
+use pacm_project::{DependencyType, read_package_json};
+use pacm_resolver::{ResolvedPackage, satisfies};
 use pacm_store::link_package;
 
 pub struct ProjectLinker;
@@ -64,10 +64,13 @@ impl ProjectLinker {
         pacm_logger::status("Linking all packages to project (flat node_modules)...");
 
         let project_node_modules = project_dir.join("node_modules");
+        let root_ranges = read_package_json(project_dir)
+            .map(|pkg| pkg.get_all_dependencies())
+            .unwrap_or_default();
 
-        let results: Vec<_> = stored_packages
+        let results: Vec<_> = Self::one_version_per_name(stored_packages, &root_ranges)
             .par_iter()
-            .map(|(_, (pkg, store_path))| {
+            .map(|(pkg, store_path)| {
                 if debug {
                     pacm_logger::debug(
                         &format!("Linking {}@{} to project", pkg.name, pkg.version),
@@ -112,6 +115,38 @@ impl ProjectLinker {
         Ok(())
     }
 
+    /// The package linked at each `node_modules/<name>` when the graph holds several
+    /// versions: the one matching the project's own range in `root_ranges`, so its
+    /// `require` gets what `package.json` asks for, and the highest otherwise. Linking two
+    /// versions to the same path in parallel would race on removing and recreating the link.
+    fn one_version_per_name<'a>(
+        stored_packages: &'a HashMap<String, (ResolvedPackage, PathBuf)>,
+        root_ranges: &HashMap<String, String>,
+    ) -> Vec<(&'a ResolvedPackage, &'a PathBuf)> {
+        let mut by_name: HashMap<&str, (&ResolvedPackage, &PathBuf)> = HashMap::new();
+        for (pkg, store_path) in stored_packages.values() {
+            let preferred = match by_name.get(pkg.name.as_str()) {
+                None => true,
+                Some((linked, _)) => {
+                    let fits_root = |version: &str| {
+                        root_ranges
+                            .get(&pkg.name)
+                            .is_some_and(|range| satisfies(version, range))
+                    };
+                    match (fits_root(&pkg.version), fits_root(&linked.version)) {
+                        (true, false) => true,
+                        (false, true) => false,
+                        _ => is_newer(&pkg.version, &linked.version),
+                    }
+                }
+            };
+            if preferred {
+                by_name.insert(&pkg.name, (pkg, store_path));
+            }
+        }
+        by_name.into_values().collect()
+    }
+
     pub fn link_single_pkg(
         project_dir: &Path,
         package_name: &str,
@@ -125,14 +160,13 @@ impl ProjectLinker {
             .iter()
             .find(|(key, _)| key.starts_with(&format!("{}@", package_name)))
             .map(|(_, (pkg, store_path))| (pkg, store_path))
+            && let Err(e) = link_package(&project_node_modules, &pkg.name, store_path)
         {
-            if let Err(e) = link_package(&project_node_modules, &pkg.name, store_path) {
-                pacm_logger::error(&format!("Failed to link {}: {}", pkg.name, e));
-                return Err(PackageManagerError::LinkingFailed(
-                    pkg.name.clone(),
-                    e.to_string(),
-                ));
-            }
+            pacm_logger::error(&format!("Failed to link {}: {}", pkg.name, e));
+            return Err(PackageManagerError::LinkingFailed(
+                pkg.name.clone(),
+                e.to_string(),
+            ));
         }
 
         Ok(())
@@ -158,3 +192,89 @@ impl ProjectLinker {
         Ok(())
     }
 }
+
+/// Whether `version` sorts after `other`, by semver when both parse.
+fn is_newer(version: &str, other: &str) -> bool {
+    match (
+        semver::Version::parse(version),
+        semver::Version::parse(other),
+    ) {
+        (Ok(version), Ok(other)) => version > other,
+        _ => version > other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_testutil::resolved_package;
+    use std::fs;
+
+    /// A package extracted under `store_base`, with its version written to a file so
+    /// tests can tell which copy was linked.
+    fn stored_package(store_base: &Path, name: &str, version: &str) -> (ResolvedPackage, PathBuf) {
+        let store_path = store_base.join(format!("{name}@{version}"));
+        fs::create_dir_all(store_path.join("package")).unwrap();
+        fs::write(store_path.join("package").join("version"), version).unwrap();
+
+        (resolved_package(name, version, &[]), store_path)
+    }
+
+    fn linked_version(project: &Path, name: &str) -> String {
+        fs::read_to_string(project.join("node_modules").join(name).join("version")).unwrap()
+    }
+
+    #[test]
+    fn test_hoisted_layout_links_each_name_once() {
+        pacm_logger::init_logger(true);
+        let store_base = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+
+        let mut stored = HashMap::new();
+        for index in 0..200 {
+            let (name, version) = match index {
+                0 => ("dup".to_string(), "1.10.0"),
+                1 => ("dup".to_string(), "1.9.0"),
+                _ => (format!("pkg-{index}"), "1.0.0"),
+            };
+            let package = stored_package(store_base.path(), &name, version);
+            stored.insert(format!("{name}@{version}"), package);
+        }
+
+        ProjectLinker::link_all_deps(project.path(), &stored, false).unwrap();
+
+        let node_modules = project.path().join("node_modules");
+        assert_eq!(fs::read_dir(&node_modules).unwrap().count(), 199);
+        assert_eq!(linked_version(project.path(), "dup"), "1.10.0");
+    }
+
+    #[test]
+    fn test_hoisted_layout_prefers_the_projects_own_range() {
+        pacm_logger::init_logger(true);
+        let store_base = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        fs::write(
+            project.path().join("package.json"),
+            r#"{ "name": "app", "dependencies": { "debug": "^2.6.0" } }"#,
+        )
+        .unwrap();
+
+        let mut stored = HashMap::new();
+        // Something in the graph needs a newer copy of a direct dependency, and two
+        // versions of a package the project does not depend on itself.
+        for (name, version) in [
+            ("debug", "2.6.9"),
+            ("debug", "4.3.4"),
+            ("ms", "2.0.0"),
+            ("ms", "2.1.3"),
+        ] {
+            let package = stored_package(store_base.path(), name, version);
+            stored.insert(format!("{name}@{version}"), package);
+        }
+
+        ProjectLinker::link_all_deps(project.path(), &stored, false).unwrap();
+
+        assert_eq!(linked_version(project.path(), "debug"), "2.6.9");
+        assert_eq!(linked_version(project.path(), "ms"), "2.1.3");
+    }
+}
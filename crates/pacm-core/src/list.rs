@@ -1,67 +1,513 @@
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 
+use owo_colors::{OwoColorize, Stream::Stdout};
+
+use pacm_config::PacmConfig;
 use pacm_error::{PackageManagerError, Result};
+use pacm_lock::PacmLock;
 use pacm_logger;
-use pacm_project::read_package_json;
+use pacm_project::{DependencyManager, DependencyType, read_package_json};
+use pacm_resolver::{ResolvedPackage, TreeNode, satisfies};
+
+/// A locked package whose single locked version does not satisfy every dependent's
+/// range. `versions` maps the locked version, or the unmatched range for dependents it
+/// cannot satisfy, to the packages requiring it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RangeConflict {
+    pub name: String,
+    pub versions: BTreeMap<String, Vec<String>>,
+}
 
 pub struct ListManager;
 
 impl ListManager {
-    pub fn list_deps(&self, project_dir: &str, tree: bool, _depth: Option<u32>) -> Result<()> {
+    /// Lists the project's dependencies, or with `section` only that section's direct
+    /// dependencies and everything they install (`--prod` / `--dev`). `tree` and `json`
+    /// print the locked dependency tree instead, `depth` levels deep.
+    pub fn list_deps(
+        &self,
+        project_dir: &str,
+        tree: bool,
+        depth: Option<u32>,
+        section: Option<DependencyType>,
+        json: bool,
+    ) -> Result<()> {
         let path = PathBuf::from(project_dir);
         let pkg = read_package_json(&path)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
 
-        if tree {
-            self.show_dependency_tree()
+        if tree || json {
+            self.show_dependency_tree(&path, &pkg, section, depth, json)
+        } else if let Some(section) = section {
+            self.show_section(&path, &pkg, section)
         } else {
             self.show_flat_list(&pkg)
         }
     }
 
-    fn show_dependency_tree(&self) -> Result<()> {
-        pacm_logger::info("Dependency tree:");
-        pacm_logger::info("Tree view not yet implemented");
+    /// Prints `section`'s direct dependencies and their transitive dependencies at the
+    /// versions `pacm.lock` installs. Without a lockfile only the declared ranges are
+    /// known, so just the direct dependencies are printed.
+    fn show_section(
+        &self,
+        path: &Path,
+        pkg: &pacm_project::PackageJson,
+        section: DependencyType,
+    ) -> Result<()> {
+        let title = match section {
+            DependencyType::DevDependencies => "DevDependencies",
+            _ => "Dependencies",
+        };
+
+        let lock_path = PacmConfig::get().lockfile_path(path);
+        if !lock_path.exists() {
+            pacm_logger::warn(&format!(
+                "No {} found, listing declared ranges only",
+                PacmConfig::get().lockfile_name()
+            ));
+            pacm_logger::info(&format!("{title}:"));
+            for (name, range) in DependencyManager::section(pkg, section)
+                .into_iter()
+                .flatten()
+            {
+                println!("  {} {}", name, range);
+            }
+            return Ok(());
+        }
+
+        let lockfile = PacmLock::load(&lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+        let packages = section_closure(&lockfile, section);
+        pacm_logger::info(&format!(
+            "{title} and what they install ({} packages):",
+            packages.len()
+        ));
+        for (name, version) in &packages {
+            println!(
+                "  {} {}",
+                name,
+                version.if_supports_color(Stdout, |text| text.dimmed())
+            );
+        }
         Ok(())
     }
 
+    /// Prints every locked package whose version does not satisfy all of its dependents
+    /// and fails when any are found, so it can be used as a CI check.
+    pub fn list_range_conflicts(&self, project_dir: &str) -> Result<()> {
+        let path = PathBuf::from(project_dir);
+        let pkg = read_package_json(&path)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let lock_path = PacmConfig::get().lockfile_path(&path);
+        if !lock_path.exists() {
+            pacm_logger::warn(&format!(
+                "No {} found, run `pacm install` first",
+                PacmConfig::get().lockfile_name()
+            ));
+            return Ok(());
+        }
+
+        let lockfile = PacmLock::load(&lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+        let conflicts = find_range_conflicts(&lockfile, pkg.name.as_deref().unwrap_or("(root)"));
+
+        if conflicts.is_empty() {
+            pacm_logger::finish(&format!(
+                "checked {} packages, found no range conflicts",
+                lockfile.packages.len()
+            ));
+            return Ok(());
+        }
+
+        for conflict in &conflicts {
+            println!(
+                "{}",
+                conflict.name.if_supports_color(Stdout, |text| text.bold())
+            );
+            for (version, dependents) in &conflict.versions {
+                println!(
+                    "  {}  required by {}",
+                    version.if_supports_color(Stdout, |text| text.yellow()),
+                    dependents.join(", ")
+                );
+            }
+        }
+
+        pacm_logger::warn(&format!(
+            "found {} packages whose locked version does not satisfy every dependent",
+            conflicts.len()
+        ));
+        Err(PackageManagerError::RangeConflictsFound(conflicts.len()))
+    }
+
+    /// Prints the tree below the root workspace's locked dependencies, or only those in
+    /// `section`, as text or JSON.
+    fn show_dependency_tree(
+        &self,
+        path: &Path,
+        pkg: &pacm_project::PackageJson,
+        section: Option<DependencyType>,
+        depth: Option<u32>,
+        json: bool,
+    ) -> Result<()> {
+        let Some(tree) = self.dependency_tree(path, pkg, section, depth)? else {
+            pacm_logger::warn(&format!(
+                "No {} or node_modules found, run `pacm install` first",
+                PacmConfig::get().lockfile_name()
+            ));
+            return Ok(());
+        };
+
+        if json {
+            let output = serde_json::to_string_pretty(&tree)
+                .map_err(|e| PackageManagerError::IoError(e.to_string()))?;
+            println!("{output}");
+        } else {
+            pacm_logger::info("Dependency tree:");
+            for line in pacm_resolver::tree_lines(&tree) {
+                println!("{line}");
+            }
+        }
+        Ok(())
+    }
+
+    /// The tree `list --tree` prints, built from `pacm.lock` and, for packages the
+    /// lockfile lacks, the `package.json` of the copy Node would load from
+    /// `node_modules`. It never contacts the registry, so it works offline. `None` when
+    /// the project has neither a lockfile nor `node_modules`.
+    pub fn dependency_tree(
+        &self,
+        path: &Path,
+        pkg: &pacm_project::PackageJson,
+        section: Option<DependencyType>,
+        depth: Option<u32>,
+    ) -> Result<Option<Vec<TreeNode>>> {
+        let lock_path = PacmConfig::get().lockfile_path(path);
+        let lockfile = if lock_path.exists() {
+            PacmLock::load(&lock_path)
+                .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?
+        } else if path.join("node_modules").is_dir() {
+            PacmLock::default()
+        } else {
+            return Ok(None);
+        };
+
+        let sections = match section {
+            Some(section) => vec![section],
+            None => vec![
+                DependencyType::Dependencies,
+                DependencyType::DevDependencies,
+                DependencyType::OptionalDependencies,
+            ],
+        };
+        let roots: HashSet<String> = sections
+            .into_iter()
+            .flat_map(|section| {
+                let locked = root_section(&lockfile, section)
+                    .into_iter()
+                    .flat_map(|deps| deps.keys());
+                let declared = DependencyManager::section(pkg, section)
+                    .into_iter()
+                    .flat_map(|deps| deps.keys());
+                locked.chain(declared).cloned().collect::<Vec<_>>()
+            })
+            .collect();
+        let mut packages = locked_packages(&lockfile);
+        add_installed_packages(path, &roots, &mut packages);
+
+        Ok(Some(pacm_resolver::dependency_tree(
+            &packages, &roots, depth,
+        )))
+    }
+
     fn show_flat_list(&self, pkg: &pacm_project::PackageJson) -> Result<()> {
-        if let Some(deps) = &pkg.dependencies {
-            if !deps.is_empty() {
-                pacm_logger::info("Dependencies:");
-                for (name, version) in deps {
-                    println!("  {} {}", name, version);
-                }
+        if let Some(deps) = &pkg.dependencies
+            && !deps.is_empty()
+        {
+            pacm_logger::info("Dependencies:");
+            for (name, version) in deps {
+                println!("  {} {}", name, version);
             }
         }
 
-        if let Some(dev_deps) = &pkg.dev_dependencies {
-            if !dev_deps.is_empty() {
-                pacm_logger::info("DevDependencies:");
-                for (name, version) in dev_deps {
-                    println!("  {} {}", name, version);
-                }
+        if let Some(dev_deps) = &pkg.dev_dependencies
+            && !dev_deps.is_empty()
+        {
+            pacm_logger::info("DevDependencies:");
+            for (name, version) in dev_deps {
+                println!("  {} {}", name, version);
             }
         }
 
-        if let Some(peer_deps) = &pkg.peer_dependencies {
-            if !peer_deps.is_empty() {
-                pacm_logger::info("PeerDependencies:");
-                for (name, version) in peer_deps {
-                    println!("  {} {}", name, version);
-                }
+        if let Some(peer_deps) = &pkg.peer_dependencies
+            && !peer_deps.is_empty()
+        {
+            pacm_logger::info("PeerDependencies:");
+            for (name, version) in peer_deps {
+                println!("  {} {}", name, version);
             }
         }
 
-        if let Some(opt_deps) = &pkg.optional_dependencies {
-            if !opt_deps.is_empty() {
-                pacm_logger::info("OptionalDependencies:");
-                for (name, version) in opt_deps {
-                    println!("  {} {}", name, version);
-                }
+        if let Some(opt_deps) = &pkg.optional_dependencies
+            && !opt_deps.is_empty()
+        {
+            pacm_logger::info("OptionalDependencies:");
+            for (name, version) in opt_deps {
+                println!("  {} {}", name, version);
             }
         }
 
         Ok(())
     }
 }
+
+/// The root project's locked direct dependencies in `section`, and every package they
+/// depend on, directly or not, mapped to its locked version.
+pub fn section_closure(lockfile: &PacmLock, section: DependencyType) -> BTreeMap<String, String> {
+    let roots = root_section(lockfile, section);
+
+    let mut packages = BTreeMap::new();
+    let mut pending: Vec<&String> = roots.into_iter().flat_map(|deps| deps.keys()).collect();
+    while let Some(name) = pending.pop() {
+        if packages.contains_key(name) {
+            continue;
+        }
+        let Some(locked) = lockfile.get_package(name) else {
+            continue;
+        };
+        packages.insert(name.clone(), locked.version.clone());
+        pending.extend(
+            locked
+                .dependencies
+                .keys()
+                .chain(locked.optional_dependencies.keys()),
+        );
+    }
+    packages
+}
+
+/// The root workspace's locked dependencies in `section`.
+fn root_section(lockfile: &PacmLock, section: DependencyType) -> Option<&HashMap<String, String>> {
+    lockfile.workspaces.get("").and_then(|root| match section {
+        DependencyType::Dependencies => Some(&root.dependencies),
+        DependencyType::DevDependencies => Some(&root.dev_dependencies),
+        DependencyType::PeerDependencies => Some(&root.peer_dependencies),
+        DependencyType::OptionalDependencies => Some(&root.optional_dependencies),
+        DependencyType::Bundled => None,
+    })
+}
+
+/// The locked packages as the resolver describes them, keyed by `name@version`.
+fn locked_packages(lockfile: &PacmLock) -> HashMap<String, ResolvedPackage> {
+    lockfile
+        .packages
+        .iter()
+        .map(|(name, locked)| {
+            let pkg = ResolvedPackage {
+                name: name.clone(),
+                version: locked.version.clone(),
+                resolved: locked.resolved.clone(),
+                integrity: locked.integrity.clone(),
+                dependencies: locked.dependencies.clone(),
+                optional_dependencies: locked.optional_dependencies.clone(),
+                peer_dependencies: locked.peer_dependencies.clone(),
+                optional_peer_dependencies: locked.optional_peer_dependencies.clone(),
+                ..Default::default()
+            };
+            (pkg.to_string(), pkg)
+        })
+        .collect()
+}
+
+/// Adds the packages below `roots` that `pacm.lock` does not list, read from the
+/// `package.json` Node would load for each: the nearest `node_modules/<name>` above the
+/// directory of the package that depends on it.
+fn add_installed_packages(
+    path: &Path,
+    roots: &HashSet<String>,
+    packages: &mut HashMap<String, ResolvedPackage>,
+) {
+    let Ok(modules) = path.join("node_modules").canonicalize() else {
+        return;
+    };
+    let locked: HashSet<String> = packages.values().map(|pkg| pkg.name.clone()).collect();
+    let mut pending: Vec<(String, PathBuf)> = roots
+        .iter()
+        .map(|name| (name.clone(), path.to_path_buf()))
+        .collect();
+    for pkg in packages.values() {
+        let missing: Vec<&String> = pkg
+            .dependencies
+            .keys()
+            .chain(pkg.optional_dependencies.keys())
+            .filter(|name| !locked.contains(*name))
+            .collect();
+        if missing.is_empty() {
+            continue;
+        }
+        let from = installed_dir(&modules, path, &pkg.name).unwrap_or_else(|| path.to_path_buf());
+        pending.extend(missing.into_iter().map(|name| (name.clone(), from.clone())));
+    }
+
+    let mut visited = HashSet::new();
+    while let Some((name, from)) = pending.pop() {
+        if locked.contains(&name) {
+            continue;
+        }
+        let Some(dir) = installed_dir(&modules, &from, &name) else {
+            continue;
+        };
+        if !visited.insert(dir.clone()) {
+            continue;
+        }
+        let Ok(manifest) = read_package_json(&dir) else {
+            continue;
+        };
+
+        let section =
+            |deps: Option<indexmap::IndexMap<String, String>>| -> HashMap<String, String> {
+                deps.into_iter().flatten().collect()
+            };
+        let pkg = ResolvedPackage {
+            name: name.clone(),
+            version: manifest.version.unwrap_or_default(),
+            dependencies: section(manifest.dependencies),
+            optional_dependencies: section(manifest.optional_dependencies),
+            peer_dependencies: section(manifest.peer_dependencies),
+            ..Default::default()
+        };
+        pending.extend(
+            pkg.dependencies
+                .keys()
+                .chain(pkg.optional_dependencies.keys())
+                .map(|dep| (dep.clone(), dir.clone())),
+        );
+        packages.insert(pkg.to_string(), pkg);
+    }
+}
+
+/// Where Node finds `name` from `from`: the nearest `node_modules/<name>` in `from` or
+/// above it. Links within `modules`, like the isolated layout's links into `.pacm`, are
+/// followed, but not those into the store, whose directories have no `node_modules`.
+fn installed_dir(modules: &Path, from: &Path, name: &str) -> Option<PathBuf> {
+    let mut dir = from
+        .ancestors()
+        .map(|dir| dir.join("node_modules").join(name))
+        .find(|dir| dir.join("package.json").is_file())?;
+    while let Ok(target) = fs::read_link(&dir) {
+        let target = dir.parent()?.join(target);
+        let target = match (target.parent().map(Path::canonicalize), target.file_name()) {
+            (Some(Ok(parent)), Some(file_name)) => parent.join(file_name),
+            _ => break,
+        };
+        if !target.starts_with(modules) || target == dir {
+            break;
+        }
+        dir = target;
+    }
+    Some(dir)
+}
+
+/// Groups each locked package's dependents by the version they end up with. Dependents
+/// whose range the locked version does not satisfy are grouped under that range, and
+/// only packages with such dependents are returned. The lockfile keeps one version per
+/// name, so these are ranges left unmet rather than extra installed copies.
+pub fn find_range_conflicts(lockfile: &PacmLock, root_name: &str) -> Vec<RangeConflict> {
+    let mut conflicts: Vec<RangeConflict> = lockfile
+        .reverse_dependencies()
+        .into_iter()
+        .filter_map(|(name, dependents)| {
+            let locked = lockfile.get_package(&name)?;
+            let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+            for dependent in dependents {
+                let version = if satisfies(&locked.version, &dependent.range) {
+                    locked.version.clone()
+                } else {
+                    dependent.range
+                };
+                let requirer = if dependent.direct && dependent.name.is_empty() {
+                    root_name.to_string()
+                } else {
+                    dependent.name
+                };
+                versions.entry(version).or_default().push(requirer);
+            }
+
+            (versions.len() > 1).then_some(RangeConflict { name, versions })
+        })
+        .collect();
+
+    conflicts.sort_by(|a, b| a.name.cmp(&b.name));
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_lock::LockPackage;
+    use std::collections::HashMap;
+
+    fn lock_package(version: &str, dependencies: &[(&str, &str)]) -> LockPackage {
+        LockPackage {
+            version: version.to_string(),
+            resolved: String::new(),
+            integrity: String::new(),
+            dependencies: dependencies
+                .iter()
+                .map(|(name, range)| (name.to_string(), range.to_string()))
+                .collect(),
+            optional_dependencies: HashMap::new(),
+            peer_dependencies: HashMap::new(),
+            optional_peer_dependencies: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_find_range_conflicts_groups_by_satisfied_version() {
+        let mut lockfile = PacmLock::default();
+        let direct = HashMap::from([
+            ("app-a".to_string(), "1.0.0".to_string()),
+            ("app-b".to_string(), "1.0.0".to_string()),
+        ]);
+        lockfile.update_workspace_deps("", &direct, "dependencies");
+        lockfile.update_package("app-a", lock_package("1.0.0", &[("shared", "^2.0.0")]));
+        lockfile.update_package("app-b", lock_package("1.0.0", &[("shared", "^1.0.0")]));
+        lockfile.update_package("shared", lock_package("2.1.0", &[]));
+
+        let conflicts = find_range_conflicts(&lockfile, "root");
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].name, "shared");
+        assert_eq!(conflicts[0].versions["2.1.0"], vec!["app-a"]);
+        assert_eq!(conflicts[0].versions["^1.0.0"], vec!["app-b"]);
+    }
+
+    #[test]
+    fn test_section_closure_follows_only_that_section() {
+        let mut lockfile = PacmLock::default();
+        let prod = HashMap::from([("server".to_string(), "1.0.0".to_string())]);
+        let dev = HashMap::from([("test-runner".to_string(), "1.0.0".to_string())]);
+        lockfile.update_workspace_deps("", &prod, "dependencies");
+        lockfile.update_workspace_deps("", &dev, "devDependencies");
+        lockfile.update_package("server", lock_package("1.0.0", &[("shared", "^1.0.0")]));
+        lockfile.update_package(
+            "test-runner",
+            lock_package("1.0.0", &[("shared", "^1.0.0"), ("dev-only", "^2.0.0")]),
+        );
+        lockfile.update_package("shared", lock_package("1.2.0", &[]));
+        lockfile.update_package("dev-only", lock_package("2.0.0", &[]));
+
+        let prod = section_closure(&lockfile, DependencyType::Dependencies);
+        let dev = section_closure(&lockfile, DependencyType::DevDependencies);
+
+        assert_eq!(prod.keys().collect::<Vec<_>>(), ["server", "shared"]);
+        assert_eq!(
+            dev.keys().collect::<Vec<_>>(),
+            ["dev-only", "shared", "test-runner"]
+        );
+        assert_eq!(dev["dev-only"], "2.0.0");
+    }
+}
@@ -0,0 +1,300 @@
+use futures::future::join_all;
+use std::path::Path;
+use std::sync::Arc;
+
+use owo_colors::{OwoColorize, Stream::Stdout};
+use serde::Serialize;
+
+use pacm_config::PacmConfig;
+use pacm_error::{PackageManagerError, Result};
+use pacm_lock::PacmLock;
+use pacm_logger;
+use pacm_project::read_package_json;
+use pacm_registry::PackageInfo;
+use pacm_resolver::VersionDiff;
+use pacm_resolver::semver::resolve_version;
+
+/// A dependency with a newer version available, as printed by `pacm outdated --json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct OutdatedPackage {
+    pub name: String,
+    /// The installed version, or `None` when the package is not installed.
+    pub current: Option<String>,
+    /// The newest version the declared range allows.
+    pub wanted: String,
+    pub latest: String,
+    /// How big the step from `current` to `latest` is.
+    #[serde(rename = "type")]
+    pub kind: Option<VersionDiff>,
+    /// The project that declares the dependency.
+    pub dependent: String,
+}
+
+impl OutdatedPackage {
+    /// Whether `latest` lies outside the declared range, so reinstalling within the range
+    /// does not reach it.
+    pub fn latest_out_of_range(&self) -> bool {
+        match (
+            semver::Version::parse(&self.latest),
+            semver::Version::parse(&self.wanted),
+        ) {
+            (Ok(latest), Ok(wanted)) => latest > wanted,
+            _ => self.latest != self.wanted,
+        }
+    }
+}
+
+/// The highest version of the package that `range` allows, using the same comparators
+/// as resolution. Unlike `latest`, this is what an update within the range installs.
+fn wanted_version(info: &PackageInfo, range: &str) -> Option<String> {
+    resolve_version(&info.versions, range, &info.dist_tags).ok()
+}
+
+pub struct OutdatedManager {
+    client: Arc<reqwest::Client>,
+}
+
+impl OutdatedManager {
+    pub fn new() -> Self {
+        let client = pacm_registry::configure_client(reqwest::Client::builder())
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+
+        Self {
+            client: Arc::new(client),
+        }
+    }
+
+    /// Compares every declared dependency with the registry and prints those that have
+    /// a newer version, as a table or as JSON.
+    pub fn outdated(
+        &self,
+        project_dir: &str,
+        json: bool,
+        debug: bool,
+    ) -> Result<Vec<OutdatedPackage>> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+        let outdated = rt.block_on(self.check(Path::new(project_dir), json, debug))?;
+
+        if json {
+            let output = serde_json::to_string_pretty(&outdated)
+                .map_err(|e| PackageManagerError::IoError(e.to_string()))?;
+            println!("{output}");
+        } else if outdated.is_empty() {
+            pacm_logger::finish("all dependencies are up to date");
+        } else {
+            Self::print_table(&outdated);
+        }
+
+        Ok(outdated)
+    }
+
+    async fn check(&self, path: &Path, quiet: bool, debug: bool) -> Result<Vec<OutdatedPackage>> {
+        let pkg = read_package_json(path)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let lockfile = PacmLock::load(&PacmConfig::get().lockfile_path(path)).ok();
+        let dependent = pkg.name.clone().unwrap_or_else(|| "(root)".to_string());
+
+        // Tarballs and directories have no registry versions to compare with.
+        let mut declared: Vec<(String, String)> = pkg
+            .get_all_dependencies()
+            .into_iter()
+            .filter(|(_, spec)| {
+                !pacm_utils::is_tarball_spec(spec) && !pacm_utils::is_directory_spec(spec)
+            })
+            .collect();
+        declared.sort();
+
+        if !quiet {
+            pacm_logger::status(&format!("Checking {} dependencies...", declared.len()));
+        }
+
+        let lookups = join_all(declared.iter().map(|(name, range)| {
+            pacm_resolver::package_info_async(self.client.clone(), name, range)
+        }))
+        .await;
+
+        let mut outdated = Vec::new();
+        for ((name, range), info) in declared.into_iter().zip(lookups) {
+            let info = match info {
+                Ok(info) => info,
+                Err(e) => {
+                    pacm_logger::debug(&format!("Could not look up {name}: {e}"), debug);
+                    continue;
+                }
+            };
+
+            let Some(latest) = info.dist_tags.get("latest").cloned() else {
+                continue;
+            };
+            let current = Self::installed_version(path, &name, lockfile.as_ref());
+            let wanted = wanted_version(&info, &range)
+                .or_else(|| current.clone())
+                .unwrap_or_else(|| latest.clone());
+
+            let parsed_current = current
+                .as_deref()
+                .and_then(|v| semver::Version::parse(v).ok());
+            let newer = |version: &str| match (&parsed_current, semver::Version::parse(version)) {
+                (Some(current), Ok(version)) => version > *current,
+                _ => false,
+            };
+            if current.is_some() && !newer(&wanted) && !newer(&latest) {
+                continue;
+            }
+
+            let kind = match (&parsed_current, semver::Version::parse(&latest)) {
+                (Some(current), Ok(latest)) => VersionDiff::between(current, &latest),
+                _ => None,
+            };
+
+            outdated.push(OutdatedPackage {
+                name,
+                current,
+                wanted,
+                latest,
+                kind,
+                dependent: dependent.clone(),
+            });
+        }
+
+        Ok(outdated)
+    }
+
+    /// The version in `node_modules`, falling back to the lockfile.
+    fn installed_version(path: &Path, name: &str, lockfile: Option<&PacmLock>) -> Option<String> {
+        std::fs::read_to_string(path.join("node_modules").join(name).join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|manifest| manifest.get("version")?.as_str().map(str::to_string))
+            .or_else(|| Some(lockfile?.get_package(name)?.version.clone()))
+    }
+
+    fn print_table(outdated: &[OutdatedPackage]) {
+        let width = |f: fn(&OutdatedPackage) -> usize, header: &str| {
+            outdated.iter().map(f).max().unwrap_or(0).max(header.len())
+        };
+        let name_width = width(|p| p.name.len(), "Package");
+        let current_width = width(
+            |p| p.current.as_deref().unwrap_or("missing").len(),
+            "Current",
+        );
+        let wanted_width = width(|p| p.wanted.len(), "Wanted");
+        let latest_width = width(|p| p.latest.len(), "Latest");
+
+        println!(
+            "{}",
+            format!(
+                "{:name_width$}  {:current_width$}  {:wanted_width$}  {:latest_width$}  Type",
+                "Package", "Current", "Wanted", "Latest"
+            )
+            .if_supports_color(Stdout, |text| text.bold())
+        );
+
+        for pkg in outdated {
+            let kind = pkg.kind.map_or("", |kind| kind.as_str());
+            let kind = match pkg.kind {
+                Some(VersionDiff::Major) => kind
+                    .if_supports_color(Stdout, |text| text.bright_red())
+                    .to_string(),
+                Some(VersionDiff::Minor) => kind
+                    .if_supports_color(Stdout, |text| text.bright_yellow())
+                    .to_string(),
+                Some(VersionDiff::Patch) => kind
+                    .if_supports_color(Stdout, |text| text.bright_green())
+                    .to_string(),
+                Some(VersionDiff::Prerelease) => kind
+                    .if_supports_color(Stdout, |text| text.bright_magenta())
+                    .to_string(),
+                None => String::new(),
+            };
+            // Padded before colouring so the escape codes do not count towards the width.
+            let wanted = format!("{:wanted_width$}", pkg.wanted);
+            let latest = format!("{:latest_width$}", pkg.latest);
+            let latest = if pkg.latest_out_of_range() {
+                latest
+                    .if_supports_color(Stdout, |text| text.bright_magenta())
+                    .to_string()
+            } else {
+                latest
+            };
+            println!(
+                "{:name_width$}  {:current_width$}  {}  {}  {}",
+                pkg.name,
+                pkg.current.as_deref().unwrap_or("missing"),
+                wanted.if_supports_color(Stdout, |text| text.bright_green()),
+                latest,
+                kind
+            );
+        }
+
+        let out_of_range = outdated
+            .iter()
+            .filter(|pkg| pkg.latest_out_of_range())
+            .count();
+        if out_of_range > 0 {
+            println!();
+            println!(
+                "{}",
+                format!(
+                    "{out_of_range} package(s) have a latest version outside the range in package.json; change the range to update past Wanted"
+                ).if_supports_color(Stdout, |text| text.dimmed())
+            );
+        }
+    }
+}
+
+impl Default for OutdatedManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn info(versions: &[&str], latest: &str) -> PackageInfo {
+        PackageInfo {
+            versions: versions
+                .iter()
+                .map(|version| (version.to_string(), serde_json::json!({})))
+                .collect(),
+            dist_tags: HashMap::from([("latest".to_string(), latest.to_string())]),
+        }
+    }
+
+    fn outdated(wanted: &str, latest: &str) -> OutdatedPackage {
+        OutdatedPackage {
+            name: "pkg".to_string(),
+            current: Some("1.0.0".to_string()),
+            wanted: wanted.to_string(),
+            latest: latest.to_string(),
+            kind: None,
+            dependent: "app".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_wanted_is_highest_version_in_range() {
+        let info = info(
+            &["1.0.0", "1.2.0", "1.3.0-beta.1", "1.4.1", "2.0.0"],
+            "2.0.0",
+        );
+
+        assert_eq!(wanted_version(&info, "^1.0.0").as_deref(), Some("1.4.1"));
+        assert_eq!(wanted_version(&info, "~1.2.0").as_deref(), Some("1.2.0"));
+        assert_eq!(wanted_version(&info, "latest").as_deref(), Some("2.0.0"));
+        assert_eq!(wanted_version(&info, "^3.0.0"), None);
+    }
+
+    #[test]
+    fn test_latest_out_of_range_only_when_past_wanted() {
+        assert!(outdated("1.4.1", "2.0.0").latest_out_of_range());
+        assert!(!outdated("1.4.1", "1.4.1").latest_out_of_range());
+        // A `latest` tag behind the range's newest prerelease is still within reach.
+        assert!(!outdated("2.0.0-rc.1", "1.4.1").latest_out_of_range());
+    }
+}
@@ -0,0 +1,576 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use owo_colors::{OwoColorize, Stream::Stdout};
+
+use pacm_config::PacmConfig;
+use pacm_constants::RC_FILES;
+use pacm_error::{PackageManagerError, Result};
+use pacm_logger;
+use pacm_project::{PackageJson, rewrite_workspace_protocol, workspace_members_of};
+use pacm_utils::{glob_match, is_workspace_spec};
+
+/// Never packed, wherever they are in the project. Neither are the rc files, which may
+/// hold registry tokens, `.env` files, or the lockfile under its configured name.
+const ALWAYS_IGNORED: [&str; 7] = [
+    ".git",
+    ".svn",
+    ".hg",
+    "node_modules",
+    ".DS_Store",
+    "npm-debug.log",
+    "package-lock.json",
+];
+
+/// The modification time npm gives every tarball entry, so packing the same files twice
+/// gives the same bytes.
+const ENTRY_MTIME: u64 = 499_162_500;
+
+/// A project packed the way it would be published.
+#[derive(Debug)]
+pub struct PackedTarball {
+    pub name: String,
+    pub version: String,
+    /// `package.json` as published.
+    pub manifest: serde_json::Value,
+    /// Packed files relative to the project, with their sizes, sorted.
+    pub files: Vec<(String, u64)>,
+    pub bytes: Vec<u8>,
+}
+
+impl PackedTarball {
+    /// The file name npm gives this tarball, as in `scope-name-1.0.0.tgz`.
+    #[must_use]
+    pub fn filename(&self) -> String {
+        let name = self.name.trim_start_matches('@').replace('/', "-");
+        format!("{}-{}.tgz", name, self.version)
+    }
+
+    /// The `sha512-` integrity of the tarball.
+    #[must_use]
+    pub fn integrity(&self) -> String {
+        pacm_store::integrity_of(&self.bytes)
+    }
+}
+
+/// Builds the tarball `pacm pack` writes and `pacm publish` uploads. With a `files`
+/// field only the paths it lists are packed, plus `package.json`, the README and the
+/// license; without one everything is, except what the root `.npmignore` (or the
+/// `.gitignore` when there is none) excludes. Version control directories,
+/// `node_modules`, lockfiles, rc files and `.env` files are never packed. `files`
+/// entries are paths, not glob patterns. In a workspace member, `workspace:` specs are
+/// replaced with the version of the member they name.
+pub struct Packer;
+
+impl Packer {
+    pub fn pack(&self, project_dir: &Path) -> Result<PackedTarball> {
+        let manifest_path = project_dir.join("package.json");
+        let content = fs::read_to_string(&manifest_path).map_err(|e| {
+            PackageManagerError::PackageJsonError(format!(
+                "Failed to read {}: {}",
+                manifest_path.display(),
+                e
+            ))
+        })?;
+        let mut manifest: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let rewritten = Self::rewrite_workspace_specs(project_dir, &mut manifest)?;
+        let field = |key: &str| {
+            manifest[key]
+                .as_str()
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    PackageManagerError::PackageJsonError(format!(
+                        "package.json needs a {key} to be packed"
+                    ))
+                })
+        };
+        let (name, version) = (field("name")?, field("version")?);
+
+        let mut files = Vec::new();
+        for path in self.file_list(project_dir, &manifest)? {
+            let relative = path
+                .strip_prefix(project_dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            files.push((relative, path));
+        }
+        files.sort();
+        files.dedup();
+
+        let encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        let mut listed = Vec::with_capacity(files.len());
+        for (relative, path) in &files {
+            let contents = if rewritten && relative == "package.json" {
+                let mut contents = serde_json::to_vec_pretty(&manifest)
+                    .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+                contents.push(b'\n');
+                contents
+            } else {
+                fs::read(path).map_err(|e| {
+                    PackageManagerError::IoError(format!(
+                        "Failed to read {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?
+            };
+            let mut header = tar::Header::new_ustar();
+            header.set_size(contents.len() as u64);
+            header.set_mode(if is_executable(path) { 0o755 } else { 0o644 });
+            header.set_mtime(ENTRY_MTIME);
+            header.set_cksum();
+            builder
+                .append_data(
+                    &mut header,
+                    format!("package/{relative}"),
+                    contents.as_slice(),
+                )
+                .map_err(|e| PackageManagerError::IoError(e.to_string()))?;
+            listed.push((relative.clone(), contents.len() as u64));
+        }
+        let bytes = builder
+            .into_inner()
+            .and_then(GzEncoder::finish)
+            .map_err(|e| PackageManagerError::IoError(e.to_string()))?;
+
+        Ok(PackedTarball {
+            name,
+            version,
+            manifest,
+            files: listed,
+            bytes,
+        })
+    }
+
+    /// Replaces `workspace:` specs in `manifest` with the versions of the workspace
+    /// members they refer to, as no consumer of the tarball could install them. Returns
+    /// whether anything was replaced.
+    fn rewrite_workspace_specs(
+        project_dir: &Path,
+        manifest: &mut serde_json::Value,
+    ) -> Result<bool> {
+        const SECTIONS: [&str; 4] = [
+            "dependencies",
+            "devDependencies",
+            "peerDependencies",
+            "optionalDependencies",
+        ];
+        let uses_workspace_protocol = SECTIONS.iter().any(|section| {
+            manifest[section].as_object().is_some_and(|deps| {
+                deps.values()
+                    .filter_map(serde_json::Value::as_str)
+                    .any(is_workspace_spec)
+            })
+        });
+        if !uses_workspace_protocol {
+            return Ok(false);
+        }
+
+        let mut pkg: PackageJson = serde_json::from_value(manifest.clone())
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let members = workspace_members_of(project_dir).unwrap_or_default();
+        rewrite_workspace_protocol(&mut pkg, &members)?;
+        let rewritten = serde_json::to_value(&pkg)
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        for section in SECTIONS {
+            if !rewritten[section].is_null() {
+                manifest[section] = rewritten[section].clone();
+            }
+        }
+        Ok(true)
+    }
+
+    /// Prints what `tarball` holds.
+    pub fn print_summary(&self, tarball: &PackedTarball) {
+        println!(
+            "{} {}@{}",
+            "package".if_supports_color(Stdout, |text| text.bold()),
+            tarball.name,
+            tarball
+                .version
+                .if_supports_color(Stdout, |text| text.bright_cyan())
+        );
+        for (path, size) in &tarball.files {
+            println!(
+                "  {:>9}  {}",
+                format_size(*size).if_supports_color(Stdout, |text| text.bright_black()),
+                path
+            );
+        }
+        println!(
+            "{}  {}",
+            "files".if_supports_color(Stdout, |text| text.bold()),
+            tarball.files.len()
+        );
+        println!(
+            "{}   {}",
+            "size".if_supports_color(Stdout, |text| text.bold()),
+            format_size(tarball.bytes.len() as u64)
+        );
+        println!(
+            "{}  {}",
+            "integrity".if_supports_color(Stdout, |text| text.bold()),
+            tarball.integrity()
+        );
+    }
+
+    fn file_list(&self, project_dir: &Path, manifest: &serde_json::Value) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let Some(listed) = manifest["files"].as_array() else {
+            let rules = IgnoreRules::load(project_dir);
+            collect_files(project_dir, project_dir, &rules, &mut files)?;
+            return Ok(files);
+        };
+
+        for entry in listed.iter().filter_map(serde_json::Value::as_str) {
+            let entry = entry
+                .trim_start_matches("./")
+                .trim_end_matches("/**")
+                .trim_end_matches('/');
+            let path = project_dir.join(entry);
+            if path.is_dir() {
+                collect_files(project_dir, &path, &IgnoreRules::default(), &mut files)?;
+            } else if path.is_file() {
+                if !path
+                    .file_name()
+                    .is_some_and(|name| is_always_ignored(&name.to_string_lossy()))
+                {
+                    files.push(path);
+                }
+            } else {
+                pacm_logger::warn(&format!("{entry} is listed in files but does not exist"));
+            }
+        }
+
+        let entries = fs::read_dir(project_dir).map_err(|e| {
+            PackageManagerError::IoError(format!("Failed to read {}: {}", project_dir.display(), e))
+        })?;
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_lowercase();
+            if entry.path().is_file()
+                && (file_name == "package.json"
+                    || file_name.starts_with("readme")
+                    || file_name.starts_with("license")
+                    || file_name.starts_with("licence"))
+            {
+                files.push(entry.path());
+            }
+        }
+        Ok(files)
+    }
+}
+
+/// Whether a file or directory called `name` is left out of every tarball.
+fn is_always_ignored(name: &str) -> bool {
+    ALWAYS_IGNORED.contains(&name)
+        || RC_FILES.contains(&name)
+        || name.starts_with(".env")
+        || name == PacmConfig::get().lockfile_name()
+        || name.ends_with(".tgz")
+}
+
+/// Collects the files under `dir` that neither the fixed list nor `rules` exclude.
+fn collect_files(
+    project_dir: &Path,
+    dir: &Path,
+    rules: &IgnoreRules,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let entries = fs::read_dir(dir).map_err(|e| {
+        PackageManagerError::IoError(format!("Failed to read {}: {}", dir.display(), e))
+    })?;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if is_always_ignored(&name) {
+            continue;
+        }
+
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let relative = path
+            .strip_prefix(project_dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        if rules.is_ignored(&relative, file_type.is_dir()) {
+            continue;
+        }
+        if file_type.is_dir() {
+            collect_files(project_dir, &path, rules, files)?;
+        } else if file_type.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The patterns of an ignore file, in the `.gitignore` syntax npm reads `.npmignore` with.
+#[derive(Default)]
+struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+struct IgnoreRule {
+    pattern: String,
+    /// A `!` rule, which brings back what an earlier one excluded.
+    negated: bool,
+    /// A rule ending in `/`, which only matches directories.
+    dir_only: bool,
+    /// A rule with a `/` before its end, matched against the whole path rather than
+    /// the name alone.
+    anchored: bool,
+}
+
+impl IgnoreRules {
+    /// The rules of `.npmignore` in `project_dir`, or of `.gitignore` when there is none.
+    fn load(project_dir: &Path) -> Self {
+        [".npmignore", ".gitignore"]
+            .iter()
+            .find_map(|file| fs::read_to_string(project_dir.join(file)).ok())
+            .map(|content| Self::parse(&content))
+            .unwrap_or_default()
+    }
+
+    fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let dir_only = line.ends_with('/');
+                let line = line.trim_end_matches('/');
+                let anchored = line.contains('/');
+                IgnoreRule {
+                    pattern: line.trim_start_matches('/').to_string(),
+                    negated,
+                    dir_only,
+                    anchored,
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Whether the path `relative` to the project is excluded. The last rule that
+    /// matches decides.
+    fn is_ignored(&self, relative: &str, is_dir: bool) -> bool {
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let target = if rule.anchored { relative } else { name };
+            if glob_match(&rule.pattern, target) {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{bytes} B")
+    } else {
+        format!("{:.1} kB", bytes as f64 / 1024.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, path: &str, contents: &str) {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn packed_paths(dir: &Path) -> Vec<String> {
+        Packer
+            .pack(dir)
+            .unwrap()
+            .files
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    #[test]
+    fn test_pack_honors_files_and_ignores() {
+        let project = tempfile::tempdir().unwrap();
+        write(project.path(), "index.js", "");
+        write(project.path(), "README.md", "");
+        write(project.path(), "lib/util.js", "");
+        write(project.path(), "test/util.test.js", "");
+        write(project.path(), "node_modules/dep/index.js", "");
+        write(project.path(), ".git/HEAD", "");
+        write(project.path(), PacmConfig::get().lockfile_name(), "");
+        write(project.path(), "widget-1.0.0.tgz", "");
+
+        write(
+            project.path(),
+            "package.json",
+            r#"{ "name": "widget", "version": "1.0.0" }"#,
+        );
+        assert_eq!(
+            packed_paths(project.path()),
+            [
+                "README.md",
+                "index.js",
+                "lib/util.js",
+                "package.json",
+                "test/util.test.js"
+            ]
+        );
+
+        write(
+            project.path(),
+            "package.json",
+            r#"{ "name": "@acme/widget", "version": "1.0.0", "files": ["lib/", "index.js"] }"#,
+        );
+        assert_eq!(
+            packed_paths(project.path()),
+            ["README.md", "index.js", "lib/util.js", "package.json"]
+        );
+
+        let first = Packer.pack(project.path()).unwrap();
+        assert_eq!(first.filename(), "acme-widget-1.0.0.tgz");
+        assert_eq!(first.bytes, Packer.pack(project.path()).unwrap().bytes);
+    }
+
+    #[test]
+    fn test_pack_leaves_out_credentials_and_ignored_files() {
+        let project = tempfile::tempdir().unwrap();
+        write(project.path(), "index.js", "");
+        write(
+            project.path(),
+            ".pacmrc",
+            "//registry.npmjs.org/:_authToken=secret-token\n",
+        );
+        write(project.path(), ".npmrc", "");
+        write(project.path(), ".env", "API_KEY=secret");
+        write(project.path(), ".env.local", "");
+        write(project.path(), "lib/.env", "");
+        write(project.path(), "lib/util.js", "");
+        write(project.path(), "test/util.test.js", "");
+        write(project.path(), "debug.log", "");
+        write(project.path(), "keep.log", "");
+        write(project.path(), "coverage/index.html", "");
+        write(
+            project.path(),
+            "package.json",
+            r#"{ "name": "widget", "version": "1.0.0" }"#,
+        );
+
+        write(project.path(), ".gitignore", "coverage/\n");
+        assert_eq!(
+            packed_paths(project.path()),
+            [
+                ".gitignore",
+                "debug.log",
+                "index.js",
+                "keep.log",
+                "lib/util.js",
+                "package.json",
+                "test/util.test.js"
+            ]
+        );
+
+        // A `.npmignore` replaces the `.gitignore` rather than adding to it.
+        write(
+            project.path(),
+            ".npmignore",
+            "# not published\n/test/\n*.log\n!keep.log\n.gitignore\n.npmignore\n",
+        );
+        let tarball = Packer.pack(project.path()).unwrap();
+        let paths: Vec<_> = tarball
+            .files
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "coverage/index.html",
+                "index.js",
+                "keep.log",
+                "lib/util.js",
+                "package.json"
+            ]
+        );
+
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(tarball.bytes.as_slice()));
+        for entry in archive.entries().unwrap() {
+            let path = entry.unwrap().path().unwrap().into_owned();
+            assert!(!path.ends_with(".pacmrc"), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_pack_replaces_workspace_specs_of_siblings() {
+        let root = tempfile::tempdir().unwrap();
+        write(
+            root.path(),
+            "package.json",
+            r#"{ "name": "root", "private": true, "workspaces": ["packages/*"] }"#,
+        );
+        write(
+            root.path(),
+            "packages/core/package.json",
+            r#"{ "name": "@acme/core", "version": "2.3.0" }"#,
+        );
+        write(
+            root.path(),
+            "packages/app/package.json",
+            r#"{
+                "name": "@acme/app",
+                "version": "1.0.0",
+                "bin": "cli.js",
+                "dependencies": { "@acme/core": "workspace:^", "ms": "^2.1.0" },
+                "devDependencies": { "@acme/core": "workspace:*" }
+            }"#,
+        );
+
+        let tarball = Packer.pack(&root.path().join("packages/app")).unwrap();
+        assert_eq!(tarball.manifest["dependencies"]["@acme/core"], "^2.3.0");
+        assert_eq!(tarball.manifest["dependencies"]["ms"], "^2.1.0");
+        assert_eq!(tarball.manifest["devDependencies"]["@acme/core"], "2.3.0");
+        assert_eq!(tarball.manifest["bin"], "cli.js");
+
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(tarball.bytes.as_slice()));
+        let mut packed = String::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            if entry.path().unwrap().to_string_lossy() == "package/package.json" {
+                std::io::Read::read_to_string(&mut entry, &mut packed).unwrap();
+            }
+        }
+        assert!(packed.contains("^2.3.0"), "{packed}");
+        assert!(!packed.contains("workspace:"), "{packed}");
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use pacm_config::PacmConfig;
+use pacm_error::{PackageManagerError, Result};
+use pacm_logger;
+use pacm_project::PackageJson;
+
+use crate::pack::{PackedTarball, Packer};
+
+/// How `pacm publish` was asked to publish.
+#[derive(Debug, Clone)]
+pub struct PublishOptions {
+    /// The dist-tag pointing at the published version.
+    pub tag: String,
+    /// `public` or `restricted`; falls back to `publishConfig.access`.
+    pub access: Option<String>,
+    /// Packs and reports without contacting the registry.
+    pub dry_run: bool,
+    /// Publishes even when the registry already has the version.
+    pub force: bool,
+}
+
+impl Default for PublishOptions {
+    fn default() -> Self {
+        Self {
+            tag: "latest".to_string(),
+            access: None,
+            dry_run: false,
+            force: false,
+        }
+    }
+}
+
+/// Packs a project with [`Packer`] and uploads it to its registry: the one in
+/// `publishConfig.registry`, or else the configured registry for its name.
+pub struct Publisher {
+    client: Arc<reqwest::Client>,
+}
+
+impl Publisher {
+    pub fn new() -> Self {
+        let client = pacm_registry::configure_client(reqwest::Client::builder())
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+
+        Self {
+            client: Arc::new(client),
+        }
+    }
+
+    pub fn publish(&self, project_dir: &Path, options: &PublishOptions) -> Result<PackedTarball> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+        rt.block_on(self.publish_async(project_dir, options))
+    }
+
+    pub async fn publish_async(
+        &self,
+        project_dir: &Path,
+        options: &PublishOptions,
+    ) -> Result<PackedTarball> {
+        let tarball = Packer.pack(project_dir)?;
+        let pkg: PackageJson = serde_json::from_value(tarball.manifest.clone())
+            .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let spec = format!("{}@{}", tarball.name, tarball.version);
+
+        if tarball.manifest["private"] == serde_json::Value::Bool(true) {
+            return Err(PackageManagerError::PublishFailed(
+                spec,
+                "package.json is marked private".to_string(),
+            ));
+        }
+        if semver::Version::parse(&tarball.version).is_err() {
+            return Err(PackageManagerError::PublishFailed(
+                spec,
+                format!("{} is not a valid semver version", tarball.version),
+            ));
+        }
+        // A tag that is itself a version would be read as one by installs.
+        if options.tag.is_empty()
+            || semver::Version::parse(options.tag.trim_start_matches('v')).is_ok()
+        {
+            return Err(PackageManagerError::PublishFailed(
+                spec,
+                format!("'{}' cannot be used as a tag", options.tag),
+            ));
+        }
+
+        let registry = pkg.publish_registry().map_or_else(
+            || PacmConfig::get().registry_for(&tarball.name).to_string(),
+            str::to_string,
+        );
+        let access = options.access.as_deref().or(pkg.publish_access());
+        let document = pacm_registry::publish_document(
+            &registry,
+            &tarball.manifest,
+            &tarball.bytes,
+            &options.tag,
+            access,
+        )
+        .map_err(|e| PackageManagerError::PublishFailed(spec.clone(), e.to_string()))?;
+
+        if options.dry_run {
+            pacm_logger::info(&format!(
+                "Would publish {} to {} with tag {} (dry run)",
+                spec, registry, options.tag
+            ));
+            return Ok(tarball);
+        }
+
+        let published =
+            pacm_registry::published_versions_async(self.client.clone(), &registry, &tarball.name)
+                .await
+                .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+        if published.contains(&tarball.version) {
+            if !options.force {
+                return Err(PackageManagerError::AlreadyPublished(spec));
+            }
+            pacm_logger::warn(&format!("{spec} is already published, publishing over it"));
+        }
+
+        pacm_registry::publish_async(self.client.clone(), &registry, &tarball.name, &document)
+            .await
+            .map_err(|e| PackageManagerError::PublishFailed(spec, e.to_string()))?;
+        Ok(tarball)
+    }
+}
+
+impl Default for Publisher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
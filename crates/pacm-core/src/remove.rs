@@ -1,10 +1,11 @@
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use pacm_config::PacmConfig;
 use pacm_error::{PackageManagerError, Result};
 use pacm_lock::PacmLock;
 use pacm_logger;
-use pacm_project::{read_package_json, write_package_json};
+use pacm_project::{DependencyType, read_package_json, write_package_json};
 
 pub struct RemoveManager;
 
@@ -29,13 +30,59 @@ impl RemoveManager {
         self.remove_with_transitive_deps(project_dir, names, dev_only, debug)
     }
 
+    /// Replaces each glob in `names`, such as `@acme/*`, with the direct dependencies it
+    /// matches, in `devDependencies` only when `dev_only`. Every pattern must match at
+    /// least one, and what it matched is printed so a broad pattern does not go unnoticed.
+    fn expand_patterns(
+        pkg: &pacm_project::PackageJson,
+        names: &[String],
+        dev_only: bool,
+    ) -> Result<Vec<String>> {
+        let sections: &[DependencyType] = if dev_only {
+            &[DependencyType::DevDependencies]
+        } else {
+            &DependencyType::ALL
+        };
+
+        let mut expanded = Vec::new();
+        for name in names {
+            if !pacm_utils::is_glob(name) {
+                if !expanded.contains(name) {
+                    expanded.push(name.clone());
+                }
+                continue;
+            }
+
+            let mut matched = Vec::new();
+            for dep_type in sections {
+                for dep in pkg.dependency_names(*dep_type) {
+                    if pacm_utils::glob_match(name, &dep) && !matched.contains(&dep) {
+                        matched.push(dep);
+                    }
+                }
+            }
+            if matched.is_empty() {
+                return Err(PackageManagerError::NoPatternMatch(name.clone()));
+            }
+
+            pacm_logger::status(&format!("{} matches {}", name, matched.join(", ")));
+            for dep in matched {
+                if !expanded.contains(&dep) {
+                    expanded.push(dep);
+                }
+            }
+        }
+
+        Ok(expanded)
+    }
+
     fn find_transitive_dependencies(
         &self,
-        project_dir: &PathBuf,
+        project_dir: &Path,
         packages_to_remove: &[String],
         debug: bool,
     ) -> Result<Vec<String>> {
-        let lock_path = project_dir.join("pacm.lock");
+        let lock_path = PacmConfig::get().lockfile_path(project_dir);
 
         if !lock_path.exists() {
             if debug {
@@ -55,34 +102,10 @@ impl RemoveManager {
 
         let mut remaining_direct_deps = HashSet::new();
 
-        if let Some(deps) = &pkg.dependencies {
-            for name in deps.keys() {
-                if !packages_to_remove.contains(name) {
-                    remaining_direct_deps.insert(name.clone());
-                }
-            }
-        }
-
-        if let Some(dev_deps) = &pkg.dev_dependencies {
-            for name in dev_deps.keys() {
-                if !packages_to_remove.contains(name) {
-                    remaining_direct_deps.insert(name.clone());
-                }
-            }
-        }
-
-        if let Some(peer_deps) = &pkg.peer_dependencies {
-            for name in peer_deps.keys() {
-                if !packages_to_remove.contains(name) {
-                    remaining_direct_deps.insert(name.clone());
-                }
-            }
-        }
-
-        if let Some(opt_deps) = &pkg.optional_dependencies {
-            for name in opt_deps.keys() {
-                if !packages_to_remove.contains(name) {
-                    remaining_direct_deps.insert(name.clone());
+        for dep_type in DependencyType::ALL {
+            for name in pkg.dependency_names(dep_type) {
+                if !packages_to_remove.contains(&name) {
+                    remaining_direct_deps.insert(name);
                 }
             }
         }
@@ -140,39 +163,38 @@ impl RemoveManager {
 
                     let package_json_path = package_dir.join("package.json");
                     if package_json_path.exists() {
-                        if let Ok(content) = std::fs::read_to_string(&package_json_path) {
-                            if let Ok(pkg_json) =
+                        if let Ok(content) = std::fs::read_to_string(&package_json_path)
+                            && let Ok(pkg_json) =
                                 serde_json::from_str::<serde_json::Value>(&content)
+                        {
+                            let mut deps = HashSet::new();
+
+                            if let Some(dependencies) =
+                                pkg_json.get("dependencies").and_then(|d| d.as_object())
                             {
-                                let mut deps = HashSet::new();
-
-                                if let Some(dependencies) =
-                                    pkg_json.get("dependencies").and_then(|d| d.as_object())
-                                {
-                                    for dep_name in dependencies.keys() {
-                                        deps.insert(dep_name.clone());
-                                    }
+                                for dep_name in dependencies.keys() {
+                                    deps.insert(dep_name.clone());
                                 }
+                            }
 
-                                if let Some(opt_dependencies) = pkg_json
-                                    .get("optionalDependencies")
-                                    .and_then(|d| d.as_object())
-                                {
-                                    for dep_name in opt_dependencies.keys() {
-                                        deps.insert(dep_name.clone());
-                                    }
+                            if let Some(opt_dependencies) = pkg_json
+                                .get("optionalDependencies")
+                                .and_then(|d| d.as_object())
+                            {
+                                for dep_name in opt_dependencies.keys() {
+                                    deps.insert(dep_name.clone());
                                 }
+                            }
 
-                                if let Some(peer_dependencies) =
-                                    pkg_json.get("peerDependencies").and_then(|d| d.as_object())
-                                {
-                                    for dep_name in peer_dependencies.keys() {
-                                        deps.insert(dep_name.clone());
-                                    }
+                            if let Some(peer_dependencies) =
+                                pkg_json.get("peerDependencies").and_then(|d| d.as_object())
+                            {
+                                for dep_name in peer_dependencies.keys() {
+                                    deps.insert(dep_name.clone());
                                 }
-
-                                dependency_graph.insert(package_name.to_string(), deps);
                             }
+
+                            dependency_graph.insert(package_name.to_string(), deps);
                         }
                     } else if debug {
                         pacm_logger::debug(
@@ -205,12 +227,12 @@ impl RemoveManager {
             let mut next_visit = HashSet::new();
 
             for package_name in &to_visit {
-                if needed_packages.insert(package_name.clone()) {
-                    if let Some(deps) = dependency_graph.get(package_name) {
-                        for dep in deps {
-                            if !needed_packages.contains(dep) {
-                                next_visit.insert(dep.clone());
-                            }
+                if needed_packages.insert(package_name.clone())
+                    && let Some(deps) = dependency_graph.get(package_name)
+                {
+                    for dep in deps {
+                        if !needed_packages.contains(dep) {
+                            next_visit.insert(dep.clone());
                         }
                     }
                 }
@@ -238,26 +260,7 @@ impl RemoveManager {
                 }
 
                 if !needed_packages.contains(package_name) {
-                    let is_direct_dependency = pkg
-                        .dependencies
-                        .as_ref()
-                        .map(|deps| deps.contains_key(package_name))
-                        .unwrap_or(false)
-                        || pkg
-                            .dev_dependencies
-                            .as_ref()
-                            .map(|deps| deps.contains_key(package_name))
-                            .unwrap_or(false)
-                        || pkg
-                            .peer_dependencies
-                            .as_ref()
-                            .map(|deps| deps.contains_key(package_name))
-                            .unwrap_or(false)
-                        || pkg
-                            .optional_dependencies
-                            .as_ref()
-                            .map(|deps| deps.contains_key(package_name))
-                            .unwrap_or(false);
+                    let is_direct_dependency = pkg.has_dependency(package_name).is_some();
 
                     if !is_direct_dependency {
                         transitive_to_remove.push(package_name.clone());
@@ -279,26 +282,7 @@ impl RemoveManager {
                     }
 
                     if !needed_packages.contains(package_name) {
-                        let is_direct_dependency = pkg
-                            .dependencies
-                            .as_ref()
-                            .map(|deps| deps.contains_key(package_name))
-                            .unwrap_or(false)
-                            || pkg
-                                .dev_dependencies
-                                .as_ref()
-                                .map(|deps| deps.contains_key(package_name))
-                                .unwrap_or(false)
-                            || pkg
-                                .peer_dependencies
-                                .as_ref()
-                                .map(|deps| deps.contains_key(package_name))
-                                .unwrap_or(false)
-                            || pkg
-                                .optional_dependencies
-                                .as_ref()
-                                .map(|deps| deps.contains_key(package_name))
-                                .unwrap_or(false);
+                        let is_direct_dependency = pkg.has_dependency(package_name).is_some();
 
                         if !is_direct_dependency {
                             transitive_to_remove.push(package_name.to_string());
@@ -344,6 +328,7 @@ impl RemoveManager {
         let path = PathBuf::from(project_dir);
         let mut pkg = read_package_json(&path)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let names = &Self::expand_patterns(&pkg, names, dev_only)?;
 
         let mut packages_to_remove = Vec::new();
         let mut not_installed = Vec::new();
@@ -442,9 +427,7 @@ impl RemoveManager {
 
         for name in &packages_to_remove {
             if dev_only {
-                if let Some(dev_deps) = &mut pkg.dev_dependencies {
-                    dev_deps.shift_remove(name);
-                }
+                pkg.remove_dependency_from(name, DependencyType::DevDependencies);
             } else {
                 pkg.remove_dependency(name);
             }
@@ -457,7 +440,7 @@ impl RemoveManager {
         let package_names: Vec<&str> = all_packages_to_remove.iter().map(|s| s.as_str()).collect();
         self.update_lockfile_after_batch_removal(&path, &package_names)?;
 
-        self.cleanup_empty_dependency_sections(&mut pkg);
+        pkg.remove_empty_dependency_sections();
 
         write_package_json(&path, &pkg)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
@@ -512,6 +495,7 @@ impl RemoveManager {
         let path = PathBuf::from(project_dir);
         let mut pkg = read_package_json(&path)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let names = &Self::expand_patterns(&pkg, names, dev_only)?;
 
         let mut packages_to_remove = Vec::new();
         let mut not_installed = Vec::new();
@@ -548,9 +532,7 @@ impl RemoveManager {
 
         for name in &packages_to_remove {
             if dev_only {
-                if let Some(dev_deps) = &mut pkg.dev_dependencies {
-                    dev_deps.shift_remove(*name);
-                }
+                pkg.remove_dependency_from(name, DependencyType::DevDependencies);
             } else {
                 pkg.remove_dependency(name);
             }
@@ -563,7 +545,7 @@ impl RemoveManager {
         let package_names: Vec<&str> = packages_to_remove.iter().map(|s| s.as_str()).collect();
         self.update_lockfile_after_batch_removal(&path, &package_names)?;
 
-        self.cleanup_empty_dependency_sections(&mut pkg);
+        pkg.remove_empty_dependency_sections();
 
         write_package_json(&path, &pkg)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
@@ -602,6 +584,7 @@ impl RemoveManager {
         let path = PathBuf::from(project_dir);
         let pkg = read_package_json(&path)
             .map_err(|e| PackageManagerError::PackageJsonError(e.to_string()))?;
+        let names = &Self::expand_patterns(&pkg, names, dev_only)?;
 
         let mut packages_to_remove = Vec::new();
         let mut not_installed = Vec::new();
@@ -636,16 +619,11 @@ impl RemoveManager {
         println!("\n📦 Direct packages ({}):", packages_to_remove.len());
         for package in &packages_to_remove {
             let dep_type = if dev_only {
-                "devDependency"
+                DependencyType::DevDependencies.singular()
             } else {
                 pkg.has_dependency(package)
-                    .map(|dt| match dt {
-                        pacm_project::DependencyType::Dependencies => "dependency",
-                        pacm_project::DependencyType::DevDependencies => "devDependency",
-                        pacm_project::DependencyType::PeerDependencies => "peerDependency",
-                        pacm_project::DependencyType::OptionalDependencies => "optionalDependency",
-                    })
-                    .unwrap_or("dependency")
+                    .map(DependencyType::singular)
+                    .unwrap_or_else(|| "dependency".to_string())
             };
             println!("  - {} ({})", package, dep_type);
         }
@@ -675,12 +653,7 @@ impl RemoveManager {
         Ok(())
     }
 
-    fn remove_from_node_modules(
-        &self,
-        project_dir: &PathBuf,
-        name: &str,
-        debug: bool,
-    ) -> Result<()> {
+    fn remove_from_node_modules(&self, project_dir: &Path, name: &str, debug: bool) -> Result<()> {
         let project_node_modules = project_dir.join("node_modules");
         let package_path = if name.starts_with('@') {
             if let Some(slash_pos) = name.find('/') {
@@ -694,47 +667,21 @@ impl RemoveManager {
             project_node_modules.join(name)
         };
 
-        if package_path.exists() {
-            if let Err(e) = std::fs::remove_dir_all(&package_path) {
-                pacm_logger::debug(&format!("Failed to remove package directory: {}", e), debug);
-                return Err(PackageManagerError::LinkingFailed(
-                    name.to_string(),
-                    format!("Failed to remove directory: {}", e),
-                ));
-            }
+        if package_path.exists()
+            && let Err(e) = std::fs::remove_dir_all(&package_path)
+        {
+            pacm_logger::debug(&format!("Failed to remove package directory: {}", e), debug);
+            return Err(PackageManagerError::LinkingFailed(
+                name.to_string(),
+                format!("Failed to remove directory: {}", e),
+            ));
         }
 
         Ok(())
     }
 
-    fn cleanup_empty_dependency_sections(&self, pkg: &mut pacm_project::PackageJson) {
-        if let Some(deps) = &pkg.dependencies {
-            if deps.is_empty() {
-                pkg.dependencies = None;
-            }
-        }
-
-        if let Some(dev_deps) = &pkg.dev_dependencies {
-            if dev_deps.is_empty() {
-                pkg.dev_dependencies = None;
-            }
-        }
-
-        if let Some(peer_deps) = &pkg.peer_dependencies {
-            if peer_deps.is_empty() {
-                pkg.peer_dependencies = None;
-            }
-        }
-
-        if let Some(opt_deps) = &pkg.optional_dependencies {
-            if opt_deps.is_empty() {
-                pkg.optional_dependencies = None;
-            }
-        }
-    }
-
-    fn cleanup_empty_lockfile(&self, project_dir: &PathBuf) -> Result<()> {
-        let lock_path = project_dir.join("pacm.lock");
+    fn cleanup_empty_lockfile(&self, project_dir: &Path) -> Result<()> {
+        let lock_path = PacmConfig::get().lockfile_path(project_dir);
 
         if !lock_path.exists() {
             return Ok(());
@@ -763,7 +710,7 @@ impl RemoveManager {
         Ok(())
     }
 
-    fn cleanup_empty_node_modules(&self, project_dir: &PathBuf) -> Result<()> {
+    fn cleanup_empty_node_modules(&self, project_dir: &Path) -> Result<()> {
         let node_modules = project_dir.join("node_modules");
 
         if !node_modules.exists() {
@@ -807,10 +754,10 @@ impl RemoveManager {
 
     fn update_lockfile_after_batch_removal(
         &self,
-        project_dir: &PathBuf,
+        project_dir: &Path,
         names: &[&str],
     ) -> Result<()> {
-        let lock_path = project_dir.join("pacm.lock");
+        let lock_path = PacmConfig::get().lockfile_path(project_dir);
 
         if !lock_path.exists() {
             return Ok(());
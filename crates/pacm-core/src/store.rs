@@ -0,0 +1,503 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use owo_colors::{OwoColorize, Stream::Stdout};
+
+use pacm_config::PacmConfig;
+use pacm_error::{PackageManagerError, Result};
+use pacm_lock::PacmLock;
+use pacm_logger;
+use pacm_store::{
+    PathResolver, ProjectRegistry, StoreManager, get_store_path, package_name_from_store,
+};
+
+use crate::clean::CleanManager;
+use crate::download::PackageDownloader;
+use crate::install::resolver::DependencyResolver;
+use pacm_resolver::ResolvedPackage;
+
+/// Disk usage of the package store. `versions` counts every `name@version` extracted
+/// under `npm/`; packages installed from tarballs only add to `total_size`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StoreStats {
+    pub total_size: u64,
+    pub packages: usize,
+    pub versions: usize,
+}
+
+pub struct StoreInspector;
+
+impl StoreInspector {
+    pub fn print_path(&self) -> Result<()> {
+        println!("{}", get_store_path().display());
+        Ok(())
+    }
+
+    pub fn print_stats(&self) -> Result<()> {
+        let store_path = get_store_path();
+        let stats = self.stats(&store_path)?;
+
+        println!(
+            "{}  {}",
+            format!("{:<8}", "path").if_supports_color(Stdout, |text| text.bold()),
+            store_path.display()
+        );
+        println!(
+            "{}  {:.2} MB",
+            format!("{:<8}", "size").if_supports_color(Stdout, |text| text.bold()),
+            stats.total_size as f64 / 1024.0 / 1024.0
+        );
+        println!(
+            "{}  {}",
+            format!("{:<8}", "packages").if_supports_color(Stdout, |text| text.bold()),
+            stats.packages
+        );
+        println!(
+            "{}  {}",
+            format!("{:<8}", "versions").if_supports_color(Stdout, |text| text.bold()),
+            stats.versions
+        );
+        Ok(())
+    }
+
+    pub fn print_versions(&self, package_name: &str) -> Result<()> {
+        let versions = self.versions(&get_store_path(), package_name)?;
+        if versions.is_empty() {
+            pacm_logger::info(&format!("{} is not in the store", package_name));
+            return Ok(());
+        }
+
+        for version in versions {
+            println!(
+                "{}@{}",
+                package_name,
+                version.if_supports_color(Stdout, |text| text.bright_cyan())
+            );
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self, store_base: &Path) -> Result<StoreStats> {
+        if !store_base.exists() {
+            return Ok(StoreStats::default());
+        }
+
+        let mut stats = StoreStats {
+            total_size: CleanManager::new().calculate_directory_size(&store_base.to_path_buf())?,
+            ..StoreStats::default()
+        };
+
+        for package_dir in Self::subdirs(&store_base.join("npm"))? {
+            let versions = Self::subdirs(&package_dir)?.len();
+            if versions > 0 {
+                stats.packages += 1;
+                stats.versions += versions;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Lists the stored versions of `package_name`, lowest first.
+    pub fn versions(&self, store_base: &Path, package_name: &str) -> Result<Vec<String>> {
+        let package_base = PathResolver::get_package_base_path(store_base, package_name);
+        let mut versions: Vec<String> = Self::subdirs(&package_base)?
+            .iter()
+            .filter_map(|dir| dir.file_name()?.to_str().map(str::to_string))
+            .collect();
+
+        versions.sort_by(
+            |a, b| match (semver::Version::parse(a), semver::Version::parse(b)) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            },
+        );
+        Ok(versions)
+    }
+
+    pub(crate) fn subdirs(dir: &Path) -> Result<Vec<PathBuf>> {
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(dir).map_err(|e| {
+            PackageManagerError::IoError(format!("Failed to read {}: {}", dir.display(), e))
+        })?;
+        Ok(entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect())
+    }
+}
+
+/// A stored version that no project has linked for longer than `pacm store gc` keeps.
+#[derive(Debug, PartialEq, Eq)]
+pub struct StaleEntry {
+    pub name: String,
+    pub version: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Finds and removes store entries unused for a number of days. Any version locked or
+/// linked by one of the projects passed to [`StoreCollector::keep_project`], or by a
+/// project an install registered with the store, is kept however long ago it was linked.
+/// Packages installed from tarballs are never collected.
+pub struct StoreCollector {
+    max_idle: Duration,
+    keep: HashSet<(String, String)>,
+}
+
+impl StoreCollector {
+    pub fn new(days: u64) -> Self {
+        Self {
+            max_idle: Duration::from_secs(days * 24 * 60 * 60),
+            keep: HashSet::new(),
+        }
+    }
+
+    /// Keeps every package locked in `project_dir`'s `pacm.lock`.
+    pub fn keep_project(&mut self, project_dir: &Path) -> Result<()> {
+        let lock_path = PacmConfig::get().lockfile_path(project_dir);
+        if !lock_path.exists() {
+            return Err(PackageManagerError::LockfileError(format!(
+                "{} has no {}",
+                project_dir.display(),
+                PacmConfig::get().lockfile_name()
+            )));
+        }
+        let lockfile = PacmLock::load(&lock_path).map_err(|e| {
+            PackageManagerError::LockfileError(format!(
+                "Failed to read {}: {}",
+                lock_path.display(),
+                e
+            ))
+        })?;
+
+        for (key, locked) in &lockfile.packages {
+            let name = key
+                .strip_suffix(&format!("@{}", locked.version))
+                .unwrap_or(key);
+            self.keep.insert((name.to_string(), locked.version.clone()));
+        }
+        Ok(())
+    }
+
+    /// Keeps what every project registered with the store at `store_base` still uses,
+    /// see [`ProjectRegistry`]. Projects that no longer exist are dropped from the
+    /// registry.
+    pub fn keep_registered_projects(&mut self, store_base: &Path) -> Result<()> {
+        for project_dir in ProjectRegistry::projects(store_base) {
+            if !project_dir.join("package.json").exists() {
+                let _ = ProjectRegistry::forget(store_base, &project_dir);
+                continue;
+            }
+            if PacmConfig::get().lockfile_path(&project_dir).exists() {
+                self.keep_project(&project_dir)?;
+            }
+            self.keep_linked(store_base, &project_dir.join("node_modules"));
+        }
+        Ok(())
+    }
+
+    /// Keeps every store entry a symlink below `node_modules` points into, for projects
+    /// installed without a lockfile.
+    fn keep_linked(&mut self, store_base: &Path, node_modules: &Path) {
+        let Ok(packages_dir) = store_base.join("npm").canonicalize() else {
+            return;
+        };
+        let Ok(entries) = fs::read_dir(node_modules) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_symlink() {
+                let Ok(target) = path.canonicalize() else {
+                    continue;
+                };
+                let mut parts = target
+                    .strip_prefix(&packages_dir)
+                    .into_iter()
+                    .flat_map(Path::components);
+                if let (Some(safe_name), Some(version)) = (parts.next(), parts.next()) {
+                    self.keep.insert((
+                        package_name_from_store(&safe_name.as_os_str().to_string_lossy()),
+                        version.as_os_str().to_string_lossy().into_owned(),
+                    ));
+                }
+            } else if file_type.is_dir() {
+                // Scopes, `.pacm` and the `node_modules` nested in it. Package directories
+                // copied in with `install-links` hold nothing from the store.
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let nested = path.join("node_modules");
+                if name.starts_with('@') || name == ".pacm" {
+                    self.keep_linked(store_base, &path);
+                } else if node_modules.ends_with(".pacm") {
+                    self.keep_linked(store_base, &nested);
+                }
+            }
+        }
+    }
+
+    /// The entries under `store_base` last used before `now` minus the idle time, by name
+    /// and version.
+    pub fn stale(&self, store_base: &Path, now: SystemTime) -> Result<Vec<StaleEntry>> {
+        let mut stale = Vec::new();
+        for package_dir in StoreInspector::subdirs(&store_base.join("npm"))? {
+            let Some(safe_name) = package_dir.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let name = package_name_from_store(safe_name);
+
+            for version_dir in StoreInspector::subdirs(&package_dir)? {
+                let Some(version) = version_dir.file_name().and_then(|v| v.to_str()) else {
+                    continue;
+                };
+                if self.keep.contains(&(name.clone(), version.to_string()))
+                    || !self.is_stale(&version_dir, now)
+                {
+                    continue;
+                }
+
+                stale.push(StaleEntry {
+                    name: name.clone(),
+                    version: version.to_string(),
+                    size: CleanManager::new().calculate_directory_size(&version_dir)?,
+                    path: version_dir,
+                });
+            }
+        }
+
+        stale.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        Ok(stale)
+    }
+
+    /// Removes `entries`, skipping any an install linked since they were listed, and
+    /// returns the bytes reclaimed. Package directories left empty are removed too.
+    pub fn remove(&self, entries: &[StaleEntry]) -> Result<u64> {
+        let now = SystemTime::now();
+        let mut reclaimed = 0;
+        for entry in entries {
+            if !self.is_stale(&entry.path, now) {
+                continue;
+            }
+
+            fs::remove_dir_all(&entry.path).map_err(|e| {
+                PackageManagerError::IoError(format!(
+                    "Failed to remove {}: {}",
+                    entry.path.display(),
+                    e
+                ))
+            })?;
+            reclaimed += entry.size;
+
+            if let Some(package_dir) = entry.path.parent() {
+                // Fails while other versions remain, which is what we want.
+                let _ = fs::remove_dir(package_dir);
+            }
+        }
+        Ok(reclaimed)
+    }
+
+    fn is_stale(&self, version_dir: &Path, now: SystemTime) -> bool {
+        StoreManager::last_used(version_dir).is_some_and(|last_used| {
+            now.duration_since(last_used).unwrap_or_default() >= self.max_idle
+        })
+    }
+}
+
+/// Downloads packages into the store without a project, so a store filled on a
+/// connected machine can be copied to offline ones for `--offline` installs.
+pub struct StorePrefetcher {
+    resolver: DependencyResolver,
+    downloader: PackageDownloader,
+}
+
+impl StorePrefetcher {
+    /// With `deps`, the whole dependency tree of each added package is stored too.
+    pub fn new(deps: bool) -> Self {
+        let max_depth = if deps { None } else { Some(0) };
+        Self {
+            resolver: DependencyResolver::new().with_max_depth(max_depth),
+            downloader: PackageDownloader::new(),
+        }
+    }
+
+    /// Resolves `name@version_range` and stores what is missing. Returns every
+    /// `(name, version)` the store now holds for it, sorted. Packages for other platforms
+    /// are stored as well, since the store may be used elsewhere.
+    pub fn add(
+        &self,
+        name: &str,
+        version_range: &str,
+        debug: bool,
+    ) -> Result<Vec<(String, String)>> {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| {
+            PackageManagerError::NetworkError(format!("Failed to create async runtime: {}", e))
+        })?;
+        rt.block_on(self.add_async(name, version_range, debug))
+    }
+
+    pub async fn add_async(
+        &self,
+        name: &str,
+        version_range: &str,
+        debug: bool,
+    ) -> Result<Vec<(String, String)>> {
+        let deps = [(name.to_string(), version_range.to_string())];
+        let (_, resolved) = self
+            .resolver
+            .resolve_all_parallel(&deps, false, debug)
+            .await?;
+        let packages: Vec<ResolvedPackage> = resolved.into_values().collect();
+
+        let stored = self.downloader.download_parallel(&packages, debug).await?;
+        self.downloader.report();
+
+        let mut added: Vec<(String, String)> = stored
+            .into_values()
+            .map(|(pkg, _)| (pkg.name, pkg.version))
+            .collect();
+        added.sort();
+        Ok(added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_version(store_base: &Path, name: &str, version: &str, size: usize) {
+        let package_dir = PathResolver::get_package_path(store_base, name, version).join("package");
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(package_dir.join("index.js"), vec![b'x'; size]).unwrap();
+    }
+
+    #[test]
+    fn test_stats_and_versions() {
+        let store = tempfile::tempdir().unwrap();
+        store_version(store.path(), "left-pad", "1.10.0", 100);
+        store_version(store.path(), "left-pad", "1.2.0", 100);
+        store_version(store.path(), "@types/node", "20.0.0", 50);
+        fs::create_dir_all(store.path().join("npm").join("empty")).unwrap();
+
+        let inspector = StoreInspector;
+        assert_eq!(
+            inspector.stats(store.path()).unwrap(),
+            StoreStats {
+                total_size: 250,
+                packages: 2,
+                versions: 3,
+            }
+        );
+        assert_eq!(
+            inspector.versions(store.path(), "left-pad").unwrap(),
+            vec!["1.2.0", "1.10.0"]
+        );
+        assert_eq!(
+            inspector.versions(store.path(), "@types/node").unwrap(),
+            vec!["20.0.0"]
+        );
+        assert!(
+            inspector
+                .versions(store.path(), "missing")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_collector_keeps_recent_and_locked_versions() {
+        let store = tempfile::tempdir().unwrap();
+        store_version(store.path(), "left-pad", "1.2.0", 100);
+        store_version(store.path(), "left-pad", "1.3.0", 100);
+        store_version(store.path(), "@types/node", "20.0.0", 50);
+
+        let project = tempfile::tempdir().unwrap();
+        fs::write(
+            project.path().join("pacm.lock"),
+            r#"{"lockfileVersion":1,"workspaces":{},"packages":{"@types/node":{"version":"20.0.0","resolved":"","integrity":""}}}"#,
+        )
+        .unwrap();
+
+        let mut collector = StoreCollector::new(30);
+        collector.keep_project(project.path()).unwrap();
+        let later = SystemTime::now() + Duration::from_secs(31 * 24 * 60 * 60);
+        let stale = collector.stale(store.path(), later).unwrap();
+        let stale_versions: Vec<_> = stale
+            .iter()
+            .map(|entry| format!("{}@{}", entry.name, entry.version))
+            .collect();
+        assert_eq!(stale_versions, ["left-pad@1.2.0", "left-pad@1.3.0"]);
+        assert!(
+            collector
+                .stale(store.path(), SystemTime::now())
+                .unwrap()
+                .is_empty()
+        );
+
+        let left_pad = PathResolver::get_package_base_path(store.path(), "left-pad");
+        assert_eq!(StoreCollector::new(0).remove(&stale[..1]).unwrap(), 100);
+        assert!(left_pad.join("1.3.0").exists());
+        assert_eq!(StoreCollector::new(0).remove(&stale[1..]).unwrap(), 100);
+        assert!(!left_pad.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collector_keeps_what_registered_projects_link() {
+        let store = tempfile::tempdir().unwrap();
+        store_version(store.path(), "linked-pkg", "1.0.0", 100);
+        store_version(store.path(), "@scope/nested", "2.0.0", 100);
+        store_version(store.path(), "unused-pkg", "1.0.0", 100);
+
+        // Installed without a lockfile, long before the collection runs.
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join("package.json"), "{}").unwrap();
+        let node_modules = project.path().join("node_modules");
+        fs::create_dir_all(node_modules.join("@scope")).unwrap();
+        std::os::unix::fs::symlink(
+            PathResolver::get_package_path(store.path(), "linked-pkg", "1.0.0").join("package"),
+            node_modules.join("linked-pkg"),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(
+            PathResolver::get_package_path(store.path(), "@scope/nested", "2.0.0").join("package"),
+            node_modules.join("@scope/nested"),
+        )
+        .unwrap();
+        ProjectRegistry::register(store.path(), project.path()).unwrap();
+
+        let removed = tempfile::tempdir().unwrap();
+        ProjectRegistry::register(store.path(), removed.path()).unwrap();
+        let removed_dir = removed.path().canonicalize().unwrap();
+        drop(removed);
+
+        let mut collector = StoreCollector::new(30);
+        collector.keep_registered_projects(store.path()).unwrap();
+        let later = SystemTime::now() + Duration::from_secs(31 * 24 * 60 * 60);
+        let stale: Vec<_> = collector
+            .stale(store.path(), later)
+            .unwrap()
+            .iter()
+            .map(|entry| format!("{}@{}", entry.name, entry.version))
+            .collect();
+        assert_eq!(stale, ["unused-pkg@1.0.0"]);
+        assert!(!ProjectRegistry::projects(store.path()).contains(&removed_dir));
+    }
+
+    #[test]
+    fn test_stats_of_missing_store() {
+        let store = tempfile::tempdir().unwrap();
+        assert_eq!(
+            StoreInspector.stats(&store.path().join("missing")).unwrap(),
+            StoreStats::default()
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use pacm_logger;
+
+/// The steps of an install that `--timing` reports on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    CacheIndex,
+    Resolution,
+    Download,
+    Extraction,
+    Linking,
+    Postinstall,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 6] = [
+        Phase::CacheIndex,
+        Phase::Resolution,
+        Phase::Download,
+        Phase::Extraction,
+        Phase::Linking,
+        Phase::Postinstall,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::CacheIndex => "cache index",
+            Phase::Resolution => "resolution",
+            Phase::Download => "download",
+            Phase::Extraction => "extraction",
+            Phase::Linking => "linking",
+            Phase::Postinstall => "postinstall",
+        }
+    }
+}
+
+/// Time spent per phase, accumulated from any thread.
+pub struct Timings {
+    nanos: [AtomicU64; Phase::ALL.len()],
+}
+
+impl Timings {
+    pub const fn new() -> Self {
+        Self {
+            nanos: [const { AtomicU64::new(0) }; Phase::ALL.len()],
+        }
+    }
+
+    pub fn record(&self, phase: Phase, elapsed: Duration) {
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.nanos[phase as usize].fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// The time recorded for each phase since the last call, which resets the counters.
+    pub fn take(&self) -> Vec<(Phase, Duration)> {
+        Phase::ALL
+            .iter()
+            .map(|&phase| {
+                let nanos = self.nanos[phase as usize].swap(0, Ordering::Relaxed);
+                (phase, Duration::from_nanos(nanos))
+            })
+            .collect()
+    }
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static TIMINGS: Timings = Timings::new();
+
+/// Adds `elapsed` to the time this process spent in `phase`. Extraction runs inside
+/// parallel downloads, so its total is the sum over all packages rather than wall time.
+pub fn record(phase: Phase, elapsed: Duration) {
+    TIMINGS.record(phase, elapsed);
+}
+
+/// Runs `f` and records how long it took as time spent in `phase`.
+pub fn timed<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    record(phase, start.elapsed());
+    result
+}
+
+/// How long one package took to resolve and download, and how many bytes it downloaded.
+/// Resolution time is the package's own metadata lookups, not its dependencies'.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PackageTiming {
+    pub resolve: Duration,
+    pub download: Duration,
+    pub size: u64,
+}
+
+impl PackageTiming {
+    fn total(&self) -> Duration {
+        self.resolve + self.download
+    }
+}
+
+/// Time spent per package, keyed by name so every version of a package adds up.
+pub struct PackageTimings {
+    packages: Mutex<BTreeMap<String, PackageTiming>>,
+}
+
+impl PackageTimings {
+    pub const fn new() -> Self {
+        Self {
+            packages: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn record_resolve(&self, name: &str, elapsed: Duration) {
+        self.update(name, |timing| timing.resolve += elapsed);
+    }
+
+    pub fn record_download(&self, name: &str, elapsed: Duration, size: usize) {
+        self.update(name, |timing| {
+            timing.download += elapsed;
+            timing.size += size as u64;
+        });
+    }
+
+    /// The `limit` packages that took longest to resolve and download, slowest first.
+    /// Resets the recorded timings.
+    pub fn take_slowest(&self, limit: usize) -> Vec<(String, PackageTiming)> {
+        let packages = std::mem::take(&mut *self.packages.lock().unwrap());
+        let mut slowest: Vec<_> = packages.into_iter().collect();
+        slowest.sort_by(|(a_name, a), (b_name, b)| {
+            b.total().cmp(&a.total()).then_with(|| a_name.cmp(b_name))
+        });
+        slowest.truncate(limit);
+        slowest
+    }
+
+    fn update(&self, name: &str, change: impl FnOnce(&mut PackageTiming)) {
+        let mut packages = self.packages.lock().unwrap();
+        change(packages.entry(name.to_string()).or_default());
+    }
+}
+
+impl Default for PackageTimings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static PACKAGE_TIMINGS: PackageTimings = PackageTimings::new();
+
+/// Adds `elapsed`, one metadata lookup, to the time spent resolving `name`.
+pub fn record_package_resolve(name: &str, elapsed: Duration) {
+    PACKAGE_TIMINGS.record_resolve(name, elapsed);
+}
+
+/// Adds a download of `size` bytes that took `elapsed` to the time spent on `name`.
+pub fn record_package_download(name: &str, elapsed: Duration, size: usize) {
+    PACKAGE_TIMINGS.record_download(name, elapsed, size);
+}
+
+/// Prints the ten packages the install spent longest on, in debug mode, to point at a
+/// single dependency that slows the whole install down.
+pub fn report_slowest_packages(debug: bool) {
+    let slowest = PACKAGE_TIMINGS.take_slowest(10);
+    if !debug || slowest.is_empty() {
+        return;
+    }
+
+    pacm_logger::debug("Slowest packages (resolve, download, size):", debug);
+    let name_width = slowest
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, timing) in slowest {
+        pacm_logger::debug(
+            &format!(
+                "  {:name_width$}  {:>6} ms  {:>6} ms  {:>8.1} kB",
+                name,
+                timing.resolve.as_millis(),
+                timing.download.as_millis(),
+                timing.size as f64 / 1024.0
+            ),
+            debug,
+        );
+    }
+}
+
+/// Prints how many of the install's requests reused a kept-alive connection, in debug
+/// mode, to show whether the registry or a proxy in between closes connections early.
+pub fn report_connections(debug: bool) {
+    let stats = pacm_registry::connections::take();
+    if !debug || stats.requests == 0 {
+        return;
+    }
+
+    pacm_logger::debug(
+        &format!(
+            "HTTP connections: {} requests, {} new connections, {} reused ({:.0}%)",
+            stats.requests,
+            stats.connections,
+            stats.reused(),
+            stats.reused() as f64 * 100.0 / stats.requests as f64
+        ),
+        debug,
+    );
+}
+
+/// Prints the recorded phases next to the install's total time, for `--timing`.
+pub fn report(total: Duration) {
+    let timings: Vec<(&str, Duration)> = TIMINGS
+        .take()
+        .into_iter()
+        .map(|(phase, elapsed)| (phase.label(), elapsed))
+        .collect();
+    pacm_logger::timing(&timings, total);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings_accumulate_per_phase_and_reset() {
+        let timings = Timings::new();
+        timings.record(Phase::Linking, Duration::from_millis(5));
+        timings.record(Phase::Linking, Duration::from_millis(7));
+
+        let taken = timings.take();
+        assert_eq!(taken.len(), Phase::ALL.len());
+        assert!(taken.contains(&(Phase::Linking, Duration::from_millis(12))));
+        assert!(taken.contains(&(Phase::Download, Duration::ZERO)));
+        assert!(timings.take().iter().all(|(_, elapsed)| elapsed.is_zero()));
+    }
+
+    #[test]
+    fn test_slowest_packages_sorted_by_total_time() {
+        let timings = PackageTimings::new();
+        timings.record_resolve("fast", Duration::from_millis(2));
+        timings.record_resolve("slow", Duration::from_millis(30));
+        timings.record_download("slow", Duration::from_millis(5), 1024);
+        timings.record_download("middle", Duration::from_millis(10), 2048);
+        timings.record_download("middle", Duration::from_millis(10), 2048);
+
+        let slowest = timings.take_slowest(2);
+        assert_eq!(
+            slowest,
+            [
+                (
+                    "slow".to_string(),
+                    PackageTiming {
+                        resolve: Duration::from_millis(30),
+                        download: Duration::from_millis(5),
+                        size: 1024,
+                    }
+                ),
+                (
+                    "middle".to_string(),
+                    PackageTiming {
+                        resolve: Duration::ZERO,
+                        download: Duration::from_millis(20),
+                        size: 4096,
+                    }
+                ),
+            ]
+        );
+        assert!(timings.take_slowest(10).is_empty());
+    }
+}
@@ -16,6 +16,14 @@ impl UpdateManager {
         }
     }
 
+    /// Prefers versions already in `pacm.lock` over the highest satisfying ones for the
+    /// dependencies of updated packages. Defaults to the `prefer-dedupe` setting.
+    #[must_use]
+    pub fn with_prefer_dedupe(mut self, prefer_dedupe: bool) -> Self {
+        self.install_manager = self.install_manager.with_prefer_dedupe(prefer_dedupe);
+        self
+    }
+
     pub fn update_deps(&self, project_dir: &str, packages: &[String], debug: bool) -> Result<()> {
         let path = PathBuf::from(project_dir);
         let pkg = read_package_json(&path)
@@ -46,8 +54,8 @@ impl UpdateManager {
         for (name, _current_range) in all_deps {
             pacm_logger::status(&format!("Updating {}...", name));
 
-            if let Some(dep_type) = pkg.has_dependency(&name) {
-                if let Err(e) = self.install_manager.install_single(
+            if let Some(dep_type) = pkg.has_dependency(&name)
+                && let Err(e) = self.install_manager.install_single(
                     project_dir,
                     &name,
                     "latest",
@@ -56,9 +64,9 @@ impl UpdateManager {
                     false, // no_save
                     true,  // force
                     debug,
-                ) {
-                    pacm_logger::error(&format!("Failed to update {}: {}", name, e));
-                }
+                )
+            {
+                pacm_logger::error(&format!("Failed to update {}: {}", name, e));
             }
         }
 
@@ -0,0 +1,206 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use pacm_config::{NetworkMode, PacmConfig};
+use pacm_constants::{RELEASES_API_URL, USER_AGENT, VERSION};
+use pacm_error::{PackageManagerError, Result};
+use pacm_store::get_store_path;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+const STATE_FILE: &str = ".update-check.json";
+
+/// The outcome of the last release check, kept in the store between runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheckState {
+    checked_at: u64,
+    latest: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+}
+
+/// Tells the user when a newer pacm release is out. Releases are looked up at most once
+/// a day on a background thread; the notice printed after a command only uses what is
+/// already on disk, so a slow or unreachable server never delays pacm.
+pub struct UpdateNotifier {
+    state_path: PathBuf,
+    releases_url: String,
+}
+
+impl UpdateNotifier {
+    /// Starts the notifier for this run and kicks off a check if one is due. Returns
+    /// `None` when `update-notifier=false` is configured, in offline mode, or when
+    /// running in CI.
+    pub fn start() -> Option<Self> {
+        let config = PacmConfig::get();
+        if config.get_value("update-notifier") == Some("false")
+            || config.network_mode() == NetworkMode::Offline
+            || std::env::var_os("CI").is_some()
+        {
+            return None;
+        }
+
+        let notifier = Self::new(get_store_path().join(STATE_FILE), RELEASES_API_URL);
+        if notifier.is_due(now()) {
+            let background = Self::new(notifier.state_path.clone(), &notifier.releases_url);
+            std::thread::spawn(move || {
+                let _ = background.check();
+            });
+        }
+        Some(notifier)
+    }
+
+    pub fn new(state_path: PathBuf, releases_url: &str) -> Self {
+        Self {
+            state_path,
+            releases_url: releases_url.to_string(),
+        }
+    }
+
+    /// Prints a one-line notice to stderr if the last check found a newer release.
+    /// stdout is left alone, since it may carry a `--json` document.
+    pub fn print_notice(&self) {
+        if let Some(latest) = self.available_update() {
+            eprintln!("pacm {} available (you have {})", latest, VERSION);
+        }
+    }
+
+    /// The latest release, if it is newer than this build.
+    pub fn available_update(&self) -> Option<String> {
+        let latest = Self::read_state(&self.state_path).latest?;
+        let newer = semver::Version::parse(&latest).ok()? > semver::Version::parse(VERSION).ok()?;
+        newer.then_some(latest)
+    }
+
+    fn is_due(&self, now: u64) -> bool {
+        now.saturating_sub(Self::read_state(&self.state_path).checked_at)
+            >= CHECK_INTERVAL.as_secs()
+    }
+
+    /// Looks up the latest release and records it. A failed lookup is recorded too, so
+    /// an offline machine is not retried on every command; the last known release is
+    /// kept in that case.
+    pub fn check(&self) -> Result<()> {
+        let mut state = Self::read_state(&self.state_path);
+        let fetched = self.fetch_latest();
+
+        state.checked_at = now();
+        if let Ok(latest) = &fetched {
+            state.latest = Some(latest.clone());
+        }
+        Self::write_state(&self.state_path, &state)?;
+
+        fetched.map(|_| ())
+    }
+
+    fn fetch_latest(&self) -> Result<String> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+
+        rt.block_on(async {
+            let client = pacm_registry::configure_client(reqwest::Client::builder())
+                .timeout(CHECK_TIMEOUT)
+                .user_agent(USER_AGENT)
+                .build()
+                .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+
+            pacm_registry::connections::record_request();
+            let release: Release = client
+                .get(&self.releases_url)
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| PackageManagerError::NetworkError(e.to_string()))?;
+
+            Ok(release.tag_name.trim_start_matches('v').to_string())
+        })
+    }
+
+    fn read_state(path: &Path) -> CheckState {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes through a temporary file so an interrupted write never leaves a torn state
+    /// file behind; the background thread may be cut off when pacm exits.
+    fn write_state(path: &Path, state: &CheckState) -> Result<()> {
+        let io_error = |e: std::io::Error| {
+            PackageManagerError::IoError(format!("Failed to write {}: {}", path.display(), e))
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        let content = serde_json::to_string(state)
+            .map_err(|e| PackageManagerError::IoError(e.to_string()))?;
+        fs::write(&temp_path, content).map_err(io_error)?;
+        fs::rename(&temp_path, path).map_err(io_error)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_testutil::{MockServer, Response};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_check_records_latest_release() {
+        let server = MockServer::start(Arc::new(|_| {
+            Response::json(&serde_json::json!({ "tag_name": "v999.0.0" }))
+        }))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let notifier = UpdateNotifier::new(dir.path().join(STATE_FILE), &server.url());
+
+        assert!(notifier.is_due(now()));
+        assert_eq!(notifier.available_update(), None);
+
+        notifier.check().unwrap();
+
+        assert!(!notifier.is_due(now()));
+        assert!(notifier.is_due(now() + CHECK_INTERVAL.as_secs()));
+        assert_eq!(notifier.available_update().as_deref(), Some("999.0.0"));
+    }
+
+    #[test]
+    fn test_failed_check_keeps_last_known_release() {
+        let server = MockServer::start(Arc::new(|_| Response::not_found())).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let state_path = dir.path().join(STATE_FILE);
+        fs::write(&state_path, r#"{ "checked_at": 0, "latest": "0.0.1" }"#).unwrap();
+        let notifier = UpdateNotifier::new(state_path, &server.url());
+
+        assert!(notifier.check().is_err());
+
+        assert!(!notifier.is_due(now()));
+        assert_eq!(
+            UpdateNotifier::read_state(&notifier.state_path)
+                .latest
+                .as_deref(),
+            Some("0.0.1")
+        );
+        assert_eq!(notifier.available_update(), None);
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use owo_colors::{OwoColorize, Stream::Stdout};
+
+use pacm_config::PacmConfig;
+use pacm_error::{PackageManagerError, Result};
+use pacm_lock::{LockPackage, PacmLock};
+use pacm_logger;
+use pacm_store::{StoreManager, store_safe_name};
+
+use crate::install::utils::InstallUtils;
+use crate::linker::isolated::VIRTUAL_STORE_DIR;
+
+/// How an installed package differs from its lockfile entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// Nothing is installed under the package's name.
+    Missing,
+    /// A different version is installed; holds that version, or `None` when its
+    /// `package.json` cannot be read.
+    Version(Option<String>),
+    /// The package links to a store entry extracted from a different tarball than the
+    /// one the lockfile records.
+    Integrity,
+}
+
+/// A locked package that `node_modules` does not match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub name: String,
+    pub locked: String,
+    pub problem: Problem,
+}
+
+pub struct VerifyManager;
+
+impl VerifyManager {
+    /// Checks every package in `pacm.lock` against `node_modules` without changing
+    /// either, printing each mismatch. Fails when any are found so it can be used as a
+    /// CI precondition.
+    pub fn verify(&self, project_dir: &str) -> Result<()> {
+        let path = PathBuf::from(project_dir);
+        let lock_path = PacmConfig::get().lockfile_path(&path);
+        if !lock_path.exists() {
+            return Err(PackageManagerError::LockfileError(format!(
+                "no {} found, run `pacm install` first",
+                PacmConfig::get().lockfile_name()
+            )));
+        }
+
+        let lockfile = PacmLock::load(&lock_path)
+            .map_err(|e| PackageManagerError::LockfileError(e.to_string()))?;
+        let mismatches = find_mismatches(&path, &lockfile)?;
+
+        if mismatches.is_empty() {
+            pacm_logger::finish(&format!(
+                "checked {} packages, node_modules matches {}",
+                lockfile.packages.len(),
+                PacmConfig::get().lockfile_name()
+            ));
+            return Ok(());
+        }
+
+        for mismatch in &mismatches {
+            let problem = match &mismatch.problem {
+                Problem::Missing => "not installed".to_string(),
+                Problem::Version(Some(installed)) => format!("{installed} installed"),
+                Problem::Version(None) => "installed version unknown".to_string(),
+                Problem::Integrity => "installed from a different tarball".to_string(),
+            };
+            println!(
+                "{}@{}  {}",
+                mismatch.name.if_supports_color(Stdout, |text| text.bold()),
+                mismatch.locked,
+                problem.if_supports_color(Stdout, |text| text.yellow())
+            );
+        }
+
+        pacm_logger::warn(&format!(
+            "found {} packages that do not match {}",
+            mismatches.len(),
+            PacmConfig::get().lockfile_name()
+        ));
+        Err(PackageManagerError::LockfileMismatch(mismatches.len()))
+    }
+}
+
+/// The locked packages that are missing from `node_modules` under `project_dir` or
+/// installed differently, sorted by name. Packages are looked up in the isolated layout's
+/// virtual store first and at the top of `node_modules` otherwise. Top-level packages
+/// that the install's own up-to-date check accepts only have their integrity compared;
+/// that check reads the lockfile saved in `project_dir`.
+pub fn find_mismatches(project_dir: &Path, lockfile: &PacmLock) -> Result<Vec<Mismatch>> {
+    let node_modules = project_dir.join("node_modules");
+    let locked_versions: Vec<(String, String)> = lockfile
+        .packages
+        .iter()
+        .map(|(name, locked)| (name.clone(), locked.version.clone()))
+        .collect();
+    let outdated: HashSet<String> =
+        InstallUtils::check_existing_pkgs(project_dir, &locked_versions, true, false)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+
+    let mut mismatches: Vec<Mismatch> = lockfile
+        .packages
+        .iter()
+        .filter_map(|(name, locked)| {
+            let isolated = node_modules
+                .join(VIRTUAL_STORE_DIR)
+                .join(format!("{}@{}", name, locked.version))
+                .join("node_modules")
+                .join(name);
+            let problem = if isolated.exists() {
+                check_package(&isolated, name, locked)
+            } else if outdated.contains(name) {
+                check_package(&node_modules.join(name), name, locked)
+            } else {
+                check_integrity(&node_modules.join(name), name, locked)
+            };
+
+            problem.map(|problem| Mismatch {
+                name: name.clone(),
+                locked: locked.version.clone(),
+                problem,
+            })
+        })
+        .collect();
+
+    mismatches.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(mismatches)
+}
+
+fn check_package(dir: &Path, name: &str, locked: &LockPackage) -> Option<Problem> {
+    if !dir.exists() {
+        return Some(Problem::Missing);
+    }
+
+    let installed = fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|pkg| pkg.get("version")?.as_str().map(str::to_string));
+    if installed.as_deref() != Some(locked.version.as_str()) {
+        return Some(Problem::Version(installed));
+    }
+
+    check_integrity(dir, name, locked)
+}
+
+/// Compares the tarball `dir` was extracted from with the one `locked` records. Store
+/// entries remember the integrity of their tarball; entries stored before pacm recorded
+/// it fall back to the store path, which names the artifact it holds.
+fn check_integrity(dir: &Path, name: &str, locked: &LockPackage) -> Option<Problem> {
+    let target = dir.canonicalize().ok()?;
+    let stored = target.parent().and_then(StoreManager::stored_integrity);
+    let differs = match stored {
+        Some(stored) if !locked.integrity.is_empty() => stored != locked.integrity,
+        _ => links_to_other_artifact(&target, name, locked),
+    };
+    differs.then_some(Problem::Integrity)
+}
+
+/// Whether `target`, the resolved `node_modules` entry, is a store directory for another
+/// artifact than `locked`. Registry packages are stored by name and version, tarballs by
+/// the SHA-512 of their bytes, so the store path itself identifies what was extracted.
+/// Directories outside the store cannot be checked and are accepted.
+fn links_to_other_artifact(target: &Path, name: &str, locked: &LockPackage) -> bool {
+    let Some(entry) = target.parent() else {
+        return false;
+    };
+    let component = |path: &Path| {
+        path.file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let Some(kind_dir) = entry.parent() else {
+        return false;
+    };
+
+    if component(kind_dir) == "tarball" {
+        return match sha512_hex(&locked.integrity) {
+            Some(hash) => component(entry) != hash,
+            None => false,
+        };
+    }
+
+    match kind_dir.parent() {
+        Some(npm_dir) if component(npm_dir) == "npm" => {
+            component(kind_dir) != store_safe_name(name) || component(entry) != locked.version
+        }
+        _ => false,
+    }
+}
+
+/// The hex digest in a `sha512-` integrity string, as tarball store entries are named.
+fn sha512_hex(integrity: &str) -> Option<String> {
+    let encoded = integrity.strip_prefix("sha512-")?;
+    let digest = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .ok()?;
+    Some(digest.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_lock::LockDependency;
+    use pacm_testutil::TempProject;
+    use serde_json::json;
+
+    fn locked(version: &str, integrity: &str) -> LockPackage {
+        LockPackage {
+            version: version.to_string(),
+            resolved: String::new(),
+            integrity: integrity.to_string(),
+            dependencies: Default::default(),
+            optional_dependencies: Default::default(),
+            peer_dependencies: Default::default(),
+            optional_peer_dependencies: Default::default(),
+        }
+    }
+
+    fn write_package(dir: &Path, name: &str, version: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join("package.json"),
+            json!({ "name": name, "version": version }).to_string(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_reports_missing_outdated_and_relinked_packages() {
+        let project = TempProject::new(&json!({ "name": "verify-test" }));
+        let node_modules = project.path().join("node_modules");
+        write_package(&node_modules.join("ok"), "ok", "1.0.0");
+        write_package(&node_modules.join("stale"), "stale", "1.0.0");
+
+        let tarball = pacm_testutil::build_tarball(&json!({ "name": "tgz", "version": "1.0.0" }));
+        let store = project.path().join("store");
+        let other_entry = store.join("tarball").join("0123abcd").join("package");
+        write_package(&other_entry, "tgz", "1.0.0");
+        pacm_store::PackageLinker::link_directory(&node_modules, "tgz", &other_entry).unwrap();
+        // Same name and version as locked, but extracted from another tarball.
+        let swapped_entry = store.join("npm").join("swapped").join("1.0.0");
+        write_package(&swapped_entry.join("package"), "swapped", "1.0.0");
+        fs::write(swapped_entry.join("integrity"), "sha512-republished").unwrap();
+        pacm_store::PackageLinker::link_directory(
+            &node_modules,
+            "swapped",
+            &swapped_entry.join("package"),
+        )
+        .unwrap();
+
+        let mut lockfile = PacmLock::default();
+        lockfile
+            .packages
+            .insert("ok".to_string(), locked("1.0.0", ""));
+        lockfile
+            .packages
+            .insert("stale".to_string(), locked("2.0.0", ""));
+        lockfile
+            .packages
+            .insert("gone".to_string(), locked("1.0.0", ""));
+        lockfile.packages.insert(
+            "tgz".to_string(),
+            locked("1.0.0", &pacm_testutil::integrity(&tarball)),
+        );
+        lockfile
+            .packages
+            .insert("swapped".to_string(), locked("1.0.0", "sha512-locked"));
+        for (name, package) in &lockfile.packages {
+            lockfile.dependencies.insert(
+                name.clone(),
+                LockDependency {
+                    version: package.version.clone(),
+                    resolved: String::new(),
+                    integrity: package.integrity.clone(),
+                },
+            );
+        }
+        lockfile
+            .save(&PacmConfig::get().lockfile_path(project.path()))
+            .unwrap();
+
+        let problems: Vec<_> = find_mismatches(project.path(), &lockfile)
+            .unwrap()
+            .into_iter()
+            .map(|mismatch| (mismatch.name, mismatch.problem))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                ("gone".to_string(), Problem::Missing),
+                (
+                    "stale".to_string(),
+                    Problem::Version(Some("1.0.0".to_string()))
+                ),
+                ("swapped".to_string(), Problem::Integrity),
+                ("tgz".to_string(), Problem::Integrity),
+            ]
+        );
+    }
+}
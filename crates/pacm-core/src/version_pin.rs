@@ -0,0 +1,30 @@
+use std::path::Path;
+
+use pacm_config::PacmConfig;
+use pacm_constants::VERSION;
+use pacm_error::{PackageManagerError, Result};
+use pacm_logger;
+use pacm_project::read_package_json;
+
+/// Compares the pacm version the project's `packageManager` field pins with the running
+/// one. A mismatch is a warning, or an error with `strict-version=true`. Projects without
+/// a `package.json`, or pinning another package manager, are not checked.
+pub fn check_pinned_version(project_dir: &Path) -> Result<()> {
+    let Ok(pkg) = read_package_json(project_dir) else {
+        return Ok(());
+    };
+    let Some(pinned) = pkg.pinned_pacm_version() else {
+        return Ok(());
+    };
+    if pinned == VERSION {
+        return Ok(());
+    }
+
+    let mismatch =
+        PackageManagerError::PacmVersionMismatch(pinned.to_string(), VERSION.to_string());
+    if PacmConfig::get().strict_version() {
+        return Err(mismatch);
+    }
+    pacm_logger::warn(&mismatch.to_string());
+    Ok(())
+}
@@ -0,0 +1,351 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use owo_colors::{OwoColorize, Stream::Stdout};
+use serde::Serialize;
+
+use pacm_config::PacmConfig;
+use pacm_error::{PackageManagerError, Result};
+use pacm_lock::{Dependent, PacmLock};
+use pacm_logger;
+use pacm_project::read_package_json;
+
+/// Why a package is installed, as printed by `pacm why --json`.
+#[derive(Debug, Serialize)]
+pub struct WhyReport {
+    pub name: String,
+    pub version: String,
+    /// The paths from a workspace to the package, see [`reverse_dependencies`].
+    pub chains: Vec<Chain>,
+}
+
+/// A path of dependents leading to a package, ending with the package itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Chain {
+    /// The workspace that declares the first dependency, given by its path (empty for
+    /// the root project), then each package down to the target. With `deduped` it starts
+    /// at a package instead.
+    pub packages: Vec<String>,
+    /// The chain starts at a package whose own chains are listed earlier, so they are
+    /// not repeated.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deduped: bool,
+}
+
+/// A locked package no workspace dependency leads to, as printed by
+/// `pacm why --orphans --json`.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Orphan {
+    pub name: String,
+    pub version: String,
+}
+
+pub struct WhyManager;
+
+impl WhyManager {
+    pub fn explain(&self, project_dir: &str, package: &str, json: bool) -> Result<WhyReport> {
+        let path = Path::new(project_dir);
+        let lockfile = Self::load_lockfile(path)?;
+        let locked = lockfile
+            .get_package(package)
+            .ok_or_else(|| PackageManagerError::PackageNotFound(package.to_string()))?;
+
+        let root_name = read_package_json(path)
+            .ok()
+            .and_then(|pkg| pkg.name)
+            .unwrap_or_else(|| "(root)".to_string());
+        let chains = reverse_dependencies(&lockfile, package)
+            .into_iter()
+            .map(|mut chain| {
+                if !chain.deduped && chain.packages[0].is_empty() {
+                    chain.packages[0] = root_name.clone();
+                }
+                chain
+            })
+            .collect();
+
+        let report = WhyReport {
+            name: package.to_string(),
+            version: locked.version.clone(),
+            chains,
+        };
+
+        if json {
+            let output = serde_json::to_string_pretty(&report)
+                .map_err(|e| PackageManagerError::IoError(e.to_string()))?;
+            println!("{output}");
+        } else {
+            Self::print_report(&report);
+        }
+
+        Ok(report)
+    }
+
+    /// Prints the locked packages that no dependency chain from a workspace reaches, as
+    /// candidates for cleaning up. Read-only, and fails when any are found so it can be
+    /// used as a CI check.
+    pub fn list_orphans(&self, project_dir: &str, json: bool) -> Result<Vec<Orphan>> {
+        let lockfile = Self::load_lockfile(Path::new(project_dir))?;
+        let orphans: Vec<Orphan> = orphans(&lockfile)
+            .into_iter()
+            .filter_map(|name| {
+                let version = lockfile.get_package(&name)?.version.clone();
+                Some(Orphan { name, version })
+            })
+            .collect();
+
+        if json {
+            let output = serde_json::to_string_pretty(&orphans)
+                .map_err(|e| PackageManagerError::IoError(e.to_string()))?;
+            println!("{output}");
+        } else if orphans.is_empty() {
+            pacm_logger::finish(&format!(
+                "checked {} packages, found no orphans",
+                lockfile.packages.len()
+            ));
+        } else {
+            for orphan in &orphans {
+                println!(
+                    "{}@{}",
+                    orphan.name.if_supports_color(Stdout, |text| text.bold()),
+                    orphan
+                        .version
+                        .if_supports_color(Stdout, |text| text.dimmed())
+                );
+            }
+            pacm_logger::warn(&format!(
+                "found {} packages that no dependency leads to",
+                orphans.len()
+            ));
+        }
+
+        if orphans.is_empty() {
+            Ok(orphans)
+        } else {
+            Err(PackageManagerError::OrphansFound(orphans.len()))
+        }
+    }
+
+    fn load_lockfile(path: &Path) -> Result<PacmLock> {
+        let lock_path = PacmConfig::get().lockfile_path(path);
+        if !lock_path.exists() {
+            return Err(PackageManagerError::LockfileError(format!(
+                "No {} found, run `pacm install` first",
+                PacmConfig::get().lockfile_name()
+            )));
+        }
+
+        PacmLock::load(&lock_path).map_err(|e| PackageManagerError::LockfileError(e.to_string()))
+    }
+
+    fn print_report(report: &WhyReport) {
+        println!(
+            "{}@{}",
+            report.name.if_supports_color(Stdout, |text| text.bold()),
+            report
+                .version
+                .if_supports_color(Stdout, |text| text.dimmed())
+        );
+        if report.chains.is_empty() {
+            println!("  nothing depends on it");
+            return;
+        }
+
+        for chain in &report.chains {
+            let (target, dependents) = chain.packages.split_last().unwrap_or((&report.name, &[]));
+            let mut dependents = dependents.to_vec();
+            if chain.deduped {
+                dependents[0] = format!(
+                    "{} {}",
+                    dependents[0],
+                    "(deduped)".if_supports_color(Stdout, |text| text.dimmed())
+                );
+            }
+            println!(
+                "  {} > {}",
+                dependents.join(" > "),
+                target.if_supports_color(Stdout, |text| text.bright_cyan())
+            );
+        }
+    }
+}
+
+/// The dependency chains that lead to `target`, walking dependents in name order. Each
+/// package's own chains are listed once; a later chain reaching it again stops there and
+/// is marked `deduped`, so shared dependents do not multiply the output. Cycles are
+/// followed only once per chain.
+pub fn reverse_dependencies(lock: &PacmLock, target: &str) -> Vec<Chain> {
+    let graph = lock.reverse_dependencies();
+    let mut chains = Vec::new();
+    let mut visiting = HashSet::from([target.to_string()]);
+    let mut expanded = HashSet::from([target.to_string()]);
+    let mut path = vec![target.to_string()];
+
+    collect_chains(
+        &graph,
+        target,
+        &mut visiting,
+        &mut expanded,
+        &mut path,
+        &mut chains,
+    );
+    chains
+}
+
+/// Names of the locked packages that no workspace dependency leads to, sorted. A package
+/// is reachable once a workspace or a reachable package depends on it, so orphans that
+/// only depend on each other are still found.
+pub fn orphans(lock: &PacmLock) -> Vec<String> {
+    let graph = lock.reverse_dependencies();
+    let mut reachable: HashSet<&str> = HashSet::new();
+
+    loop {
+        let before = reachable.len();
+        for name in lock.packages.keys() {
+            let reached = graph.get(name).is_some_and(|dependents| {
+                dependents.iter().any(|dependent| {
+                    dependent.direct || reachable.contains(dependent.name.as_str())
+                })
+            });
+            if reached {
+                reachable.insert(name);
+            }
+        }
+        if reachable.len() == before {
+            break;
+        }
+    }
+
+    let mut orphans: Vec<String> = lock
+        .packages
+        .keys()
+        .filter(|name| !reachable.contains(name.as_str()))
+        .cloned()
+        .collect();
+    orphans.sort();
+    orphans
+}
+
+fn collect_chains(
+    graph: &HashMap<String, Vec<Dependent>>,
+    name: &str,
+    visiting: &mut HashSet<String>,
+    expanded: &mut HashSet<String>,
+    path: &mut Vec<String>,
+    chains: &mut Vec<Chain>,
+) {
+    let Some(dependents) = graph.get(name) else {
+        return;
+    };
+    let mut dependents: Vec<&Dependent> = dependents.iter().collect();
+    dependents.sort_by(|a, b| (!a.direct, &a.name).cmp(&(!b.direct, &b.name)));
+    dependents.dedup_by(|a, b| a.direct == b.direct && a.name == b.name);
+
+    let chain = |path: &[String], first: &str, deduped| {
+        let mut packages = path.to_vec();
+        packages.push(first.to_string());
+        packages.reverse();
+        Chain { packages, deduped }
+    };
+    for dependent in dependents {
+        if dependent.direct {
+            chains.push(chain(path, &dependent.name, false));
+        } else if visiting.contains(&dependent.name) {
+            continue;
+        } else if !expanded.insert(dependent.name.clone()) {
+            chains.push(chain(path, &dependent.name, true));
+        } else {
+            visiting.insert(dependent.name.clone());
+            path.push(dependent.name.clone());
+            collect_chains(graph, &dependent.name, visiting, expanded, path, chains);
+            path.pop();
+            visiting.remove(&dependent.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_lock::LockPackage;
+
+    fn lock_package(dependencies: &[&str]) -> LockPackage {
+        LockPackage {
+            version: "1.0.0".to_string(),
+            resolved: String::new(),
+            integrity: String::new(),
+            dependencies: dependencies
+                .iter()
+                .map(|name| (name.to_string(), "^1.0.0".to_string()))
+                .collect(),
+            optional_dependencies: HashMap::new(),
+            peer_dependencies: HashMap::new(),
+            optional_peer_dependencies: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_reverse_dependencies_lists_shared_dependents_once() {
+        let mut lockfile = PacmLock::default();
+        let direct = HashMap::from([
+            ("a".to_string(), "1.0.0".to_string()),
+            ("target".to_string(), "1.0.0".to_string()),
+        ]);
+        lockfile.update_workspace_deps("", &direct, "dependencies");
+        lockfile.update_package("a", lock_package(&["b", "c"]));
+        lockfile.update_package("b", lock_package(&["target"]));
+        // c and d depend on each other.
+        lockfile.update_package("c", lock_package(&["d"]));
+        lockfile.update_package("d", lock_package(&["c", "target"]));
+        lockfile.update_package("target", lock_package(&[]));
+
+        let chains = reverse_dependencies(&lockfile, "target");
+        let chains: Vec<(Vec<&str>, bool)> = chains
+            .iter()
+            .map(|chain| {
+                let packages = chain.packages.iter().map(String::as_str).collect();
+                (packages, chain.deduped)
+            })
+            .collect();
+
+        // a's chains are listed under b, so the path through c stops at a.
+        assert_eq!(
+            chains,
+            vec![
+                (vec!["", "target"], false),
+                (vec!["", "a", "b", "target"], false),
+                (vec!["a", "c", "d", "target"], true),
+            ]
+        );
+        assert_eq!(
+            reverse_dependencies(&lockfile, "a"),
+            vec![Chain {
+                packages: vec![String::new(), "a".to_string()],
+                deduped: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_orphans_are_unreachable_from_workspaces() {
+        let mut lockfile = PacmLock::default();
+        let direct = HashMap::from([("a".to_string(), "1.0.0".to_string())]);
+        lockfile.update_workspace_deps("", &direct, "dependencies");
+        let peers = HashMap::from([("peer".to_string(), "1.0.0".to_string())]);
+        lockfile.update_workspace_deps("", &peers, "peerDependencies");
+        lockfile.update_package("a", lock_package(&["b"]));
+        lockfile.update_package("b", lock_package(&[]));
+        // A peer of the project is installed for it, along with what it depends on.
+        lockfile.update_package("peer", lock_package(&["peer-dep"]));
+        lockfile.update_package("peer-dep", lock_package(&[]));
+        lockfile.update_package("stale", lock_package(&["b", "stale-dep"]));
+        // Depending on each other does not keep a pair installed.
+        lockfile.update_package("stale-dep", lock_package(&["stale-cycle"]));
+        lockfile.update_package("stale-cycle", lock_package(&["stale-dep"]));
+
+        assert_eq!(
+            orphans(&lockfile),
+            vec!["stale", "stale-cycle", "stale-dep"]
+        );
+    }
+}
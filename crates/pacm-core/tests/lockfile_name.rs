@@ -0,0 +1,49 @@
+//! Runs in its own process: `lockfile-name` is read from the configuration, which is
+//! loaded once per process.
+
+use pacm_core::InstallOptions;
+use pacm_testutil::{MockRegistry, TempProject};
+use serde_json::{Value, json};
+
+#[test]
+fn test_custom_lockfile_name_end_to_end() {
+    let registry = MockRegistry::shared();
+    pacm_config::PacmConfig::init_with_overrides(&[("lockfile-name", "pacm-lock.json")]);
+    registry
+        .publish("named-lock-leaf", "1.1.0", &[])
+        .publish("named-lock-app", "2.0.0", &[("named-lock-leaf", "^1.0.0")])
+        .publish("named-lock-extra", "0.3.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "named-lock-test",
+        "version": "1.0.0",
+        "dependencies": { "named-lock-app": "^2.0.0" },
+    }));
+    let lock_path = project.path().join("pacm-lock.json");
+    let locked = |name: &str| {
+        let lockfile: Value =
+            serde_json::from_str(&std::fs::read_to_string(&lock_path).unwrap()).unwrap();
+        lockfile["packages"][name]["version"]
+            .as_str()
+            .map(str::to_string)
+    };
+
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert!(!project.path().join("pacm.lock").exists());
+    assert_eq!(locked("named-lock-leaf").as_deref(), Some("1.1.0"));
+
+    pacm_core::install_single_with(
+        project.dir(),
+        "named-lock-extra",
+        "^0.3.0",
+        &InstallOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(locked("named-lock-extra").as_deref(), Some("0.3.0"));
+    pacm_core::verify_install(project.dir()).unwrap();
+
+    pacm_core::remove_dep(project.dir(), "named-lock-extra", false, false).unwrap();
+    assert_eq!(locked("named-lock-extra"), None);
+    assert_eq!(locked("named-lock-app").as_deref(), Some("2.0.0"));
+    assert!(!project.path().join("pacm.lock").exists());
+}
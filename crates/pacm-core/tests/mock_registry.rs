@@ -0,0 +1,2013 @@
+use pacm_core::audit::Severity;
+use pacm_core::{CancellationToken, InstallOptions};
+use pacm_project::{DependencySelection, DependencyType};
+use pacm_resolver::ResolutionLimits;
+use pacm_testutil::{MockRegistry, TempProject, resolved_package};
+use serde_json::json;
+
+fn locked_version(project: &TempProject, name: &str) -> Option<String> {
+    project.lockfile()?["packages"][name]["version"]
+        .as_str()
+        .map(str::to_string)
+}
+
+#[test]
+fn test_install_resolves_highest_matching_version() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("resolve-dep", "1.0.0", &[])
+        .publish("resolve-dep", "1.4.2", &[])
+        .publish("resolve-dep", "2.0.0", &[])
+        .publish("resolve-app", "1.0.0", &[("resolve-dep", "^1.0.0")]);
+
+    let project = TempProject::new(&json!({ "name": "resolve-test", "version": "1.0.0" }));
+    pacm_core::install_single_with(
+        project.dir(),
+        "resolve-app",
+        "^1.0.0",
+        &InstallOptions::new(),
+    )
+    .unwrap();
+
+    assert!(project.has_module("resolve-app"));
+    assert_eq!(
+        locked_version(&project, "resolve-dep").as_deref(),
+        Some("1.4.2")
+    );
+    assert_eq!(
+        project.package_json()["dependencies"]["resolve-app"],
+        json!("^1.0.0")
+    );
+}
+
+#[test]
+fn test_install_all_from_package_json() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("all-left", "1.0.0", &[])
+        .publish("all-right", "3.1.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "install-all-test",
+        "version": "1.0.0",
+        "dependencies": { "all-left": "^1.0.0" },
+        "devDependencies": { "all-right": "~3.1.0" },
+    }));
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+
+    assert!(project.has_module("all-left"));
+    assert!(project.has_module("all-right"));
+    assert_eq!(
+        locked_version(&project, "all-right").as_deref(),
+        Some("3.1.0")
+    );
+}
+
+#[test]
+fn test_plain_install_keeps_dev_dependency_in_place() {
+    let registry = MockRegistry::shared();
+    registry.publish("stay-dev", "1.0.0", &[]);
+
+    let project = TempProject::new(&json!({ "name": "stay-dev-test", "version": "1.0.0" }));
+    let dev = InstallOptions::new().dep_type(DependencyType::DevDependencies);
+    pacm_core::install_single_with(project.dir(), "stay-dev", "^1.0.0", &dev).unwrap();
+
+    pacm_core::install_single_with(project.dir(), "stay-dev", "^1.0.0", &InstallOptions::new())
+        .unwrap();
+    let manifest = project.package_json();
+    assert_eq!(manifest["devDependencies"]["stay-dev"], "^1.0.0");
+    assert!(manifest["dependencies"].get("stay-dev").is_none());
+
+    // Asking for the section explicitly, like `--save-prod`, does move it.
+    let prod = InstallOptions::new()
+        .dep_type(DependencyType::Dependencies)
+        .move_existing(true);
+    pacm_core::install_single_with(project.dir(), "stay-dev", "^1.0.0", &prod).unwrap();
+    let manifest = project.package_json();
+    assert_eq!(manifest["dependencies"]["stay-dev"], "^1.0.0");
+    assert!(manifest["devDependencies"].get("stay-dev").is_none());
+}
+
+#[test]
+fn test_moving_a_section_without_lockfile_leaves_the_lockfile_alone() {
+    let registry = MockRegistry::shared();
+    registry.publish("move-nolock", "1.0.0", &[]);
+
+    let project = TempProject::new(&json!({ "name": "move-nolock-test", "version": "1.0.0" }));
+    let dev = InstallOptions::new().dep_type(DependencyType::DevDependencies);
+    pacm_core::install_single_with(project.dir(), "move-nolock", "^1.0.0", &dev).unwrap();
+    let locked_before = project.lockfile();
+
+    pacm_core::install::single::SingleInstaller::new()
+        .with_lockfile(false)
+        .with_move_existing(true)
+        .install(
+            project.dir(),
+            "move-nolock",
+            "^1.0.0",
+            DependencyType::Dependencies,
+            false,
+            false,
+            false,
+            false,
+        )
+        .unwrap();
+
+    assert_eq!(
+        project.package_json()["dependencies"]["move-nolock"],
+        "^1.0.0"
+    );
+    assert_eq!(project.lockfile(), locked_before);
+}
+
+#[test]
+fn test_remove_drops_package() {
+    let registry = MockRegistry::shared();
+    registry.publish("remove-me", "0.3.0", &[]);
+
+    let project = TempProject::new(&json!({ "name": "remove-test", "version": "1.0.0" }));
+    pacm_core::install_single_with(project.dir(), "remove-me", "^0.3.0", &InstallOptions::new())
+        .unwrap();
+    assert!(project.has_module("remove-me"));
+
+    pacm_core::remove_dep(project.dir(), "remove-me", false, false).unwrap();
+
+    assert!(!project.has_module("remove-me"));
+    assert!(
+        project.package_json()["dependencies"]
+            .get("remove-me")
+            .is_none()
+    );
+}
+
+#[test]
+fn test_remove_pattern_drops_whole_scope() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("globrm-shared", "1.0.0", &[])
+        .publish("@globrm/core", "1.0.0", &[("globrm-shared", "^1.0.0")])
+        .publish("@globrm/utils", "1.0.0", &[])
+        .publish("globrm-keep", "1.0.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "remove-pattern-test",
+        "version": "1.0.0",
+        "dependencies": { "@globrm/core": "^1.0.0", "globrm-keep": "^1.0.0" },
+        "devDependencies": { "@globrm/utils": "^1.0.0" },
+    }));
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert!(project.has_module("globrm-shared"));
+
+    let unmatched = pacm_core::remove_dep(project.dir(), "@nothing/*", false, false);
+    assert!(unmatched.is_err());
+    assert!(project.has_module("@globrm/core"));
+
+    pacm_core::remove_dep(project.dir(), "@globrm/*", false, false).unwrap();
+
+    let manifest = project.package_json();
+    assert_eq!(manifest["dependencies"], json!({ "globrm-keep": "^1.0.0" }));
+    assert!(manifest.get("devDependencies").is_none());
+    assert!(!project.has_module("@globrm/core"));
+    assert!(!project.has_module("@globrm/utils"));
+    assert!(!project.has_module("globrm-shared"));
+    assert!(project.has_module("globrm-keep"));
+    assert_eq!(locked_version(&project, "globrm-shared"), None);
+}
+
+#[test]
+fn test_unknown_package_fails() {
+    MockRegistry::shared();
+
+    let project = TempProject::new(&json!({ "name": "missing-test", "version": "1.0.0" }));
+    let result = pacm_core::install_single_with(
+        project.dir(),
+        "does-not-exist",
+        "^1.0.0",
+        &InstallOptions::new(),
+    );
+
+    assert!(result.is_err());
+    assert!(!project.has_module("does-not-exist"));
+}
+
+#[test]
+fn test_audit_reports_fix_and_exit_level() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("audit-vuln", "1.0.0", &[])
+        .publish("audit-vuln", "1.0.1", &[])
+        .publish("audit-vuln", "1.0.2", &[])
+        .add_advisory("audit-vuln", 42, "moderate", "<1.0.2");
+
+    let project = TempProject::new(&json!({ "name": "audit-test", "version": "1.0.0" }));
+    pacm_core::install_single_with(project.dir(), "audit-vuln", "1.0.0", &InstallOptions::new())
+        .unwrap();
+
+    assert!(pacm_core::audit(project.dir(), true, "moderate", false).is_err());
+
+    let report = pacm_core::AuditManager::new()
+        .audit(project.dir(), true, Severity::High, false)
+        .unwrap();
+    assert_eq!(report.vulnerabilities.len(), 1);
+    assert_eq!(report.vulnerabilities[0].id, 42);
+    assert_eq!(
+        report.vulnerabilities[0].fix_available.as_deref(),
+        Some("1.0.2")
+    );
+}
+
+#[test]
+fn test_audit_summary_counts_installed_vulnerabilities() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("summary-vuln", "2.0.0", &[])
+        .publish("summary-safe", "1.0.0", &[])
+        .add_advisory("summary-vuln", 77, "high", "<3.0.0")
+        .add_advisory("summary-vuln", 78, "moderate", ">=2.0.0")
+        .add_advisory("summary-safe", 79, "critical", "<1.0.0");
+
+    let project = TempProject::new(&json!({
+        "name": "audit-summary-test",
+        "version": "1.0.0",
+        "dependencies": { "summary-vuln": "^2.0.0", "summary-safe": "^1.0.0" },
+    }));
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+
+    let summary = pacm_core::AuditManager::new()
+        .summary(project.dir(), false)
+        .unwrap();
+    assert_eq!(summary.dependencies, 2);
+    assert_eq!(
+        summary.summary_line(),
+        "found 2 vulnerabilities (1 high, 1 moderate), run `pacm audit` for details"
+    );
+}
+
+#[test]
+fn test_parallel_installs_share_store_safely() {
+    let registry = MockRegistry::shared();
+    registry.publish("parallel-leaf", "2.0.0", &[]).publish(
+        "parallel-root",
+        "1.0.0",
+        &[("parallel-leaf", "^2.0.0")],
+    );
+
+    let projects: Vec<TempProject> = (0..6)
+        .map(|i| TempProject::new(&json!({ "name": format!("parallel-{i}"), "version": "1.0.0" })))
+        .collect();
+
+    std::thread::scope(|scope| {
+        for project in &projects {
+            scope.spawn(move || {
+                pacm_core::install_single_with(
+                    project.dir(),
+                    "parallel-root",
+                    "^1.0.0",
+                    &InstallOptions::new(),
+                )
+                .unwrap();
+            });
+        }
+    });
+
+    for project in &projects {
+        assert!(project.has_module("parallel-root"));
+        assert_eq!(
+            locked_version(project, "parallel-leaf").as_deref(),
+            Some("2.0.0")
+        );
+    }
+}
+
+#[test]
+fn test_install_from_store_keeps_transitive_deps() {
+    let registry = MockRegistry::shared();
+    registry.publish("stored-leaf", "1.2.0", &[]).publish(
+        "stored-root",
+        "1.0.0",
+        &[("stored-leaf", "^1.0.0")],
+    );
+
+    for i in 0..2 {
+        let project =
+            TempProject::new(&json!({ "name": format!("stored-{i}"), "version": "1.0.0" }));
+        pacm_core::install_single_with(
+            project.dir(),
+            "stored-root",
+            "^1.0.0",
+            &InstallOptions::new(),
+        )
+        .unwrap();
+
+        assert!(project.has_module("stored-leaf"));
+        assert_eq!(
+            locked_version(&project, "stored-leaf").as_deref(),
+            Some("1.2.0")
+        );
+    }
+}
+
+#[test]
+fn test_install_all_respects_omitted_sections() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("select-prod", "1.0.0", &[])
+        .publish("select-dev", "1.0.0", &[])
+        .publish("select-optional", "1.0.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "selection-test",
+        "version": "1.0.0",
+        "dependencies": { "select-prod": "^1.0.0" },
+        "devDependencies": { "select-dev": "^1.0.0" },
+        "optionalDependencies": { "select-optional": "^1.0.0" },
+    }));
+
+    let production = DependencySelection::from_flags(&[], &[], true).unwrap();
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new().selection(production))
+        .unwrap();
+    assert!(project.has_module("select-prod"));
+    assert!(project.has_module("select-optional"));
+    assert!(!project.has_module("select-dev"));
+
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert!(project.has_module("select-dev"));
+
+    std::fs::remove_dir_all(project.path().join("node_modules")).unwrap();
+    let omit_optional =
+        DependencySelection::from_flags(&[], &["optional".to_string()], true).unwrap();
+    pacm_core::install_all_with(
+        project.dir(),
+        &InstallOptions::new().selection(omit_optional),
+    )
+    .unwrap();
+    assert!(project.has_module("select-prod"));
+    assert!(!project.has_module("select-dev"));
+    assert!(!project.has_module("select-optional"));
+}
+
+#[test]
+fn test_cancelled_install_leaves_project_untouched() {
+    let registry = MockRegistry::shared();
+    registry.publish("cancel-me", "1.0.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "cancel-test",
+        "version": "1.0.0",
+        "dependencies": { "cancel-me": "^1.0.0" },
+    }));
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let result = rt.block_on(pacm_core::install_all_async(
+        project.dir(),
+        &InstallOptions::new(),
+        Some(&cancel),
+    ));
+
+    assert!(result.unwrap_err().to_string().contains("cancelled"));
+    assert!(!project.path().join("node_modules").exists());
+    assert!(project.lockfile().is_none());
+}
+
+#[test]
+fn test_install_local_tarball() {
+    let registry = MockRegistry::shared();
+    registry.publish("tarball-dep", "1.2.0", &[]);
+
+    let project = TempProject::new(&json!({ "name": "tarball-test", "version": "1.0.0" }));
+    let tarball = pacm_testutil::build_tarball(&json!({
+        "name": "local-tarball",
+        "version": "0.9.0",
+        "dependencies": { "tarball-dep": "^1.0.0" },
+    }));
+    std::fs::write(project.path().join("local-tarball-0.9.0.tgz"), &tarball).unwrap();
+
+    pacm_core::install_tarball(
+        project.dir(),
+        "./local-tarball-0.9.0.tgz",
+        DependencyType::Dependencies,
+        false,
+        false,
+    )
+    .unwrap();
+
+    assert!(project.has_module("local-tarball"));
+    assert!(project.has_module("tarball-dep"));
+    assert_eq!(
+        project.package_json()["dependencies"]["local-tarball"],
+        json!("file:./local-tarball-0.9.0.tgz")
+    );
+    let lockfile = project.lockfile().unwrap();
+    assert_eq!(
+        lockfile["packages"]["local-tarball"]["resolved"],
+        json!("file:./local-tarball-0.9.0.tgz")
+    );
+    assert_eq!(
+        lockfile["packages"]["local-tarball"]["integrity"],
+        json!(pacm_testutil::integrity(&tarball))
+    );
+
+    std::fs::remove_dir_all(project.path().join("node_modules")).unwrap();
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+
+    assert!(project.has_module("local-tarball"));
+    assert!(project.has_module("tarball-dep"));
+}
+
+#[test]
+fn test_depth_limited_install_is_partial() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("shallow-leaf", "1.0.0", &[])
+        .publish("shallow-mid", "1.0.0", &[("shallow-leaf", "^1.0.0")])
+        .publish("shallow-top", "1.0.0", &[("shallow-mid", "^1.0.0")]);
+
+    let project = TempProject::new(&json!({
+        "name": "shallow-test",
+        "version": "1.0.0",
+        "dependencies": { "shallow-top": "^1.0.0" },
+    }));
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let install = |max_depth| {
+        rt.block_on(pacm_core::install_all_async(
+            project.dir(),
+            &InstallOptions::new().max_depth(max_depth),
+            None,
+        ))
+    };
+
+    install(Some(1)).unwrap();
+    assert!(project.has_module("shallow-top"));
+    assert!(project.has_module("shallow-mid"));
+    assert!(!project.has_module("shallow-leaf"));
+    assert_eq!(project.lockfile().unwrap()["partial"], json!(true));
+
+    install(None).unwrap();
+    assert!(project.has_module("shallow-leaf"));
+    assert!(project.lockfile().unwrap().get("partial").is_none());
+}
+
+#[test]
+fn test_resolution_carries_deprecation_message() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish_manifest(json!({
+            "name": "deprecated-dep",
+            "version": "1.0.0",
+            "deprecated": "use fresh-dep instead",
+        }))
+        .publish("deprecated-app", "1.0.0", &[("deprecated-dep", "^1.0.0")]);
+
+    let mut seen = std::collections::HashSet::new();
+    let resolved = pacm_resolver::resolve_full_tree("deprecated-app", "^1.0.0", &mut seen).unwrap();
+
+    let deprecation = |name: &str| {
+        resolved
+            .iter()
+            .find(|pkg| pkg.name == name)
+            .and_then(|pkg| pkg.deprecated.clone())
+    };
+    assert_eq!(
+        deprecation("deprecated-dep").as_deref(),
+        Some("use fresh-dep instead")
+    );
+    assert_eq!(deprecation("deprecated-app"), None);
+
+    let project = TempProject::new(&json!({ "name": "deprecated-test", "version": "1.0.0" }));
+    pacm_core::install_single_with(
+        project.dir(),
+        "deprecated-app",
+        "^1.0.0",
+        &InstallOptions::new(),
+    )
+    .unwrap();
+    assert!(project.has_module("deprecated-dep"));
+}
+
+#[test]
+fn test_bundled_dependency_is_not_fetched() {
+    let registry = MockRegistry::shared();
+    let bundled_manifest = br#"{ "name": "bundled-inner", "version": "1.0.0" }"#;
+    registry.publish_with_files(
+        json!({
+            "name": "bundled-outer",
+            "version": "1.0.0",
+            "dependencies": { "bundled-inner": "^1.0.0" },
+            "bundledDependencies": ["bundled-inner"],
+        }),
+        &[
+            ("node_modules/bundled-inner/package.json", bundled_manifest),
+            (
+                "node_modules/bundled-inner/index.js",
+                b"module.exports = 1;\n",
+            ),
+        ],
+    );
+
+    let project = TempProject::new(&json!({ "name": "bundled-test", "version": "1.0.0" }));
+    pacm_core::install_single_with(
+        project.dir(),
+        "bundled-outer",
+        "^1.0.0",
+        &InstallOptions::new(),
+    )
+    .unwrap();
+
+    assert!(project.has_module("bundled-outer"));
+    assert!(
+        project
+            .path()
+            .join("node_modules/bundled-outer/node_modules/bundled-inner/package.json")
+            .is_file()
+    );
+    assert!(
+        !registry
+            .requests()
+            .iter()
+            .any(|request| request.contains("bundled-inner"))
+    );
+    assert!(locked_version(&project, "bundled-inner").is_none());
+
+    std::fs::remove_dir_all(project.path().join("node_modules")).unwrap();
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert!(
+        project
+            .path()
+            .join("node_modules/bundled-outer/node_modules/bundled-inner/package.json")
+            .is_file()
+    );
+}
+
+#[test]
+fn test_forced_install_refetches_cached_package() {
+    let registry = MockRegistry::shared();
+    registry.publish("forced-pkg", "1.0.0", &[]);
+    let tarball_requests = || {
+        registry
+            .requests()
+            .iter()
+            .filter(|request| request.ends_with("forced-pkg-1.0.0.tgz"))
+            .count()
+    };
+
+    let project = TempProject::new(&json!({ "name": "forced-test", "version": "1.0.0" }));
+    pacm_core::install_single_with(
+        project.dir(),
+        "forced-pkg",
+        "^1.0.0",
+        &InstallOptions::new(),
+    )
+    .unwrap();
+    let fetched = tarball_requests();
+
+    let index = project.path().join("node_modules/forced-pkg/index.js");
+    std::fs::remove_file(&index).unwrap();
+
+    pacm_core::install_single_with(
+        project.dir(),
+        "forced-pkg",
+        "^1.0.0",
+        &InstallOptions::new().force(true),
+    )
+    .unwrap();
+
+    assert_eq!(tarball_requests(), fetched + 1);
+    assert!(index.is_file());
+    assert_eq!(
+        project.package_json()["dependencies"]["forced-pkg"],
+        json!("^1.0.0")
+    );
+}
+
+#[test]
+fn test_warm_resolution_cache_skips_registry() {
+    let registry = MockRegistry::shared();
+    registry.publish("warm-leaf", "1.1.0", &[]).publish(
+        "warm-root",
+        "2.0.0",
+        &[("warm-leaf", "^1.0.0")],
+    );
+    let warm_requests = || {
+        registry
+            .requests()
+            .iter()
+            .filter(|request| request.contains("warm-"))
+            .count()
+    };
+
+    let project = TempProject::new(&json!({
+        "name": "warm-test",
+        "version": "1.0.0",
+        "dependencies": { "warm-root": "^2.0.0" },
+    }));
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert!(project.has_module("warm-leaf"));
+    let cold = warm_requests();
+    assert!(cold > 0);
+
+    // Start over like a new process would: no project state, no metadata in memory.
+    std::fs::remove_dir_all(project.path().join("node_modules")).unwrap();
+    std::fs::remove_file(project.path().join("pacm.lock")).unwrap();
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(pacm_registry::clear_package_cache());
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+
+    assert_eq!(warm_requests(), cold);
+    assert!(project.has_module("warm-root"));
+    assert!(project.has_module("warm-leaf"));
+    assert_eq!(
+        locked_version(&project, "warm-leaf").as_deref(),
+        Some("1.1.0")
+    );
+}
+
+#[test]
+fn test_resolution_error_names_dependency_path() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("path-leaf", "1.0.0", &[])
+        .publish("path-c", "3.0.0", &[("path-leaf", "^2.0.0")])
+        .publish("path-b", "2.0.0", &[("path-c", "^3.0.0")])
+        .publish("path-a", "1.0.0", &[("path-b", "^2.0.0")]);
+
+    let project = TempProject::new(&json!({ "name": "path-test", "version": "1.0.0" }));
+    let error =
+        pacm_core::install_single_with(project.dir(), "path-a", "^1.0.0", &InstallOptions::new())
+            .unwrap_err();
+
+    let message = error.to_string();
+    assert!(
+        message
+            .contains("path-leaf@^2.0.0 (required by path-a@1.0.0 > path-b@2.0.0 > path-c@3.0.0)"),
+        "{message}"
+    );
+    assert!(!project.has_module("path-a"));
+}
+
+#[test]
+fn test_install_peers_adds_missing_peer_dependencies() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("peers-core", "1.0.0", &[])
+        .publish("peers-core", "2.3.0", &[])
+        .publish("peers-core", "3.0.0", &[])
+        .publish("peers-extra", "1.0.0", &[])
+        .publish_manifest(json!({
+            "name": "peers-plugin",
+            "version": "1.0.0",
+            "peerDependencies": { "peers-core": "^2.0.0", "peers-extra": "^1.0.0" },
+            "peerDependenciesMeta": { "peers-extra": { "optional": true } },
+        }))
+        .publish_manifest(json!({
+            "name": "peers-other",
+            "version": "1.0.0",
+            "peerDependencies": { "peers-core": ">=2.1.0" },
+        }));
+
+    let project = TempProject::new(&json!({
+        "name": "peers-test",
+        "version": "1.0.0",
+        "dependencies": { "peers-plugin": "^1.0.0", "peers-other": "^1.0.0" },
+    }));
+    pacm_core::install::bulk::BulkInstaller::new()
+        .with_install_peers(true)
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    assert!(project.has_module("peers-core"));
+    assert_eq!(
+        locked_version(&project, "peers-core").as_deref(),
+        Some("2.3.0")
+    );
+    assert!(!project.has_module("peers-extra"));
+    assert!(
+        project.package_json()["dependencies"]
+            .get("peers-core")
+            .is_none()
+    );
+}
+
+#[test]
+fn test_conflicting_peer_ranges_name_requirers() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("peerconf-core", "1.0.0", &[])
+        .publish("peerconf-core", "2.0.0", &[])
+        .publish_manifest(json!({
+            "name": "peerconf-old",
+            "version": "1.0.0",
+            "peerDependencies": { "peerconf-core": "^1.0.0" },
+        }))
+        .publish_manifest(json!({
+            "name": "peerconf-new",
+            "version": "1.0.0",
+            "peerDependencies": { "peerconf-core": "^2.0.0" },
+        }));
+
+    let project = TempProject::new(&json!({
+        "name": "peerconf-test",
+        "version": "1.0.0",
+        "dependencies": { "peerconf-old": "^1.0.0", "peerconf-new": "^1.0.0" },
+    }));
+    let error = pacm_core::install::bulk::BulkInstaller::new()
+        .with_install_peers(true)
+        .install_all(project.dir(), false)
+        .unwrap_err();
+
+    let message = error.to_string();
+    assert!(message.contains("peerconf-core"), "{message}");
+    assert!(
+        message.contains("peerconf-new requires ^2.0.0, peerconf-old requires ^1.0.0"),
+        "{message}"
+    );
+}
+
+#[test]
+fn test_outdated_classifies_available_upgrades() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("outdated-major", "1.0.0", &[])
+        .publish("outdated-minor", "3.1.0", &[])
+        .publish("outdated-fresh", "0.4.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "outdated-test",
+        "version": "1.0.0",
+        "dependencies": {
+            "outdated-major": "^1.0.0",
+            "outdated-minor": "^3.1.0",
+            "outdated-fresh": "^0.4.0",
+        },
+    }));
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+
+    registry
+        .publish("outdated-major", "1.0.5", &[])
+        .publish("outdated-major", "2.0.0", &[])
+        .publish("outdated-minor", "3.2.0", &[]);
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(pacm_registry::clear_package_cache());
+
+    let outdated = pacm_core::OutdatedManager::new()
+        .outdated(project.dir(), true, false)
+        .unwrap();
+    let report = serde_json::to_value(&outdated).unwrap();
+
+    assert_eq!(
+        report,
+        json!([
+            {
+                "name": "outdated-major",
+                "current": "1.0.0",
+                "wanted": "1.0.5",
+                "latest": "2.0.0",
+                "type": "major",
+                "dependent": "outdated-test",
+            },
+            {
+                "name": "outdated-minor",
+                "current": "3.1.0",
+                "wanted": "3.2.0",
+                "latest": "3.2.0",
+                "type": "minor",
+                "dependent": "outdated-test",
+            },
+        ])
+    );
+}
+
+#[test]
+fn test_outdated_wanted_stays_within_declared_range() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("wanted-tilde", "1.2.0", &[])
+        .publish("wanted-caret", "1.0.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "wanted-test",
+        "version": "1.0.0",
+        "dependencies": { "wanted-tilde": "~1.2.0", "wanted-caret": "^1.0.0" },
+    }));
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+
+    registry
+        .publish("wanted-tilde", "1.3.0", &[])
+        .publish("wanted-caret", "1.1.0", &[]);
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(pacm_registry::clear_package_cache());
+
+    let outdated = pacm_core::OutdatedManager::new()
+        .outdated(project.dir(), true, false)
+        .unwrap();
+    let versions: Vec<_> = outdated
+        .iter()
+        .map(|pkg| {
+            (
+                pkg.name.as_str(),
+                pkg.wanted.as_str(),
+                pkg.latest.as_str(),
+                pkg.latest_out_of_range(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        versions,
+        [
+            ("wanted-caret", "1.1.0", "1.1.0", false),
+            ("wanted-tilde", "1.2.0", "1.3.0", true),
+        ]
+    );
+}
+
+#[test]
+fn test_nested_override_applies_to_one_path_only() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("ovr-shared", "1.1.0", &[])
+        .publish("ovr-shared", "2.0.0", &[])
+        .publish("ovr-left", "1.0.0", &[("ovr-shared", "^1.0.0")])
+        .publish("ovr-right", "1.0.0", &[("ovr-shared", "^1.0.0")])
+        .publish(
+            "ovr-app",
+            "1.0.0",
+            &[("ovr-left", "^1.0.0"), ("ovr-right", "^1.0.0")],
+        );
+
+    let overrides = pacm_resolver::Overrides::parse(&json!({
+        "ovr-left": { "ovr-shared": "2.0.0" },
+    }));
+    let mut seen = std::collections::HashSet::new();
+    let resolved = pacm_resolver::DependencyResolver::new()
+        .with_overrides(std::sync::Arc::new(overrides))
+        .resolve_full_tree("ovr-app", "^1.0.0", &mut seen)
+        .unwrap();
+
+    let package = |name: &str| resolved.iter().find(|pkg| pkg.name == name).unwrap();
+    let mut shared: Vec<_> = resolved
+        .iter()
+        .filter(|pkg| pkg.name == "ovr-shared")
+        .map(|pkg| pkg.version.as_str())
+        .collect();
+    shared.sort();
+    assert_eq!(shared, vec!["1.1.0", "2.0.0"]);
+    assert_eq!(package("ovr-left").dependencies["ovr-shared"], "2.0.0");
+    assert_eq!(package("ovr-right").dependencies["ovr-shared"], "^1.0.0");
+}
+
+#[test]
+fn test_fetch_timer_reports_each_package_once() {
+    static FETCHED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+    fn record(name: &str, _: std::time::Duration) {
+        FETCHED.lock().unwrap().push(name.to_string());
+    }
+
+    let registry = MockRegistry::shared();
+    registry
+        .publish("timer-leaf", "1.0.0", &[])
+        .publish("timer-mid", "1.0.0", &[("timer-leaf", "^1.0.0")])
+        .publish("timer-app", "1.0.0", &[("timer-mid", "^1.0.0")]);
+
+    let mut seen = std::collections::HashSet::new();
+    pacm_resolver::DependencyResolver::new()
+        .with_fetch_timer(record)
+        .resolve_full_tree("timer-app", "^1.0.0", &mut seen)
+        .unwrap();
+
+    let mut fetched = FETCHED.lock().unwrap().clone();
+    fetched.sort();
+    assert_eq!(fetched, vec!["timer-app", "timer-leaf", "timer-mid"]);
+}
+
+#[test]
+fn test_install_applies_path_override() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("ovr-path-leaf", "1.0.0", &[])
+        .publish("ovr-path-leaf", "2.0.0", &[])
+        .publish("ovr-path-mid", "1.0.0", &[("ovr-path-leaf", "^1.0.0")]);
+
+    let project = TempProject::new(&json!({
+        "name": "ovr-path-test",
+        "version": "1.0.0",
+        "dependencies": { "ovr-path-mid": "^1.0.0" },
+        "overrides": { "ovr-path-mid>ovr-path-leaf": "2.0.0" },
+    }));
+    pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    assert_eq!(
+        locked_version(&project, "ovr-path-leaf").as_deref(),
+        Some("2.0.0")
+    );
+}
+
+#[test]
+fn test_path_overrides_that_split_a_package_are_rejected() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("ovr-split-leaf", "1.0.0", &[])
+        .publish("ovr-split-leaf", "2.0.0", &[])
+        .publish("ovr-split-left", "1.0.0", &[("ovr-split-leaf", "*")])
+        .publish("ovr-split-right", "1.0.0", &[("ovr-split-leaf", "*")]);
+
+    let project = TempProject::new(&json!({
+        "name": "ovr-split-test",
+        "version": "1.0.0",
+        "dependencies": { "ovr-split-left": "^1.0.0", "ovr-split-right": "^1.0.0" },
+        "overrides": {
+            "ovr-split-left>ovr-split-leaf": "1.0.0",
+            "ovr-split-right>ovr-split-leaf": "2.0.0",
+        },
+    }));
+    let error = pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        pacm_error::PackageManagerError::DependencyConflict(ref name, _)
+            if name == "ovr-split-leaf"
+    ));
+
+    let project = TempProject::new(&json!({
+        "name": "ovr-split-test",
+        "version": "1.0.0",
+        "dependencies": { "ovr-split-left": "^1.0.0", "ovr-split-right": "^1.0.0" },
+        "overrides": {
+            "ovr-split-left>ovr-split-leaf": "1.0.0",
+            "ovr-split-right>ovr-split-leaf": "1.0.0",
+        },
+    }));
+    pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    assert_eq!(
+        locked_version(&project, "ovr-split-leaf").as_deref(),
+        Some("1.0.0")
+    );
+}
+
+#[test]
+fn test_lockfile_records_required_and_optional_peers() {
+    let registry = MockRegistry::shared();
+    registry.publish_manifest(json!({
+        "name": "peermeta-plugin",
+        "version": "1.0.0",
+        "peerDependencies": { "peermeta-host": "^1.0.0", "peermeta-extra": "^2.0.0" },
+        "peerDependenciesMeta": { "peermeta-extra": { "optional": true } },
+    }));
+
+    let project = TempProject::new(&json!({
+        "name": "peermeta-test",
+        "version": "1.0.0",
+        "dependencies": { "peermeta-plugin": "^1.0.0" },
+    }));
+    pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    let locked = &project.lockfile().unwrap()["packages"]["peermeta-plugin"];
+    assert_eq!(
+        locked["peer_dependencies"],
+        json!({ "peermeta-host": "^1.0.0" })
+    );
+    assert_eq!(
+        locked["optional_peer_dependencies"],
+        json!({ "peermeta-extra": "^2.0.0" })
+    );
+}
+
+#[test]
+fn test_install_dedupes_reconcilable_ranges() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("dedupe-extra", "1.0.0", &[])
+        .publish("dedupe-shared", "1.2.9", &[])
+        .publish("dedupe-shared", "1.5.0", &[("dedupe-extra", "^1.0.0")])
+        .publish("dedupe-a", "1.0.0", &[("dedupe-shared", "^1.0.0")])
+        .publish("dedupe-b", "1.0.0", &[("dedupe-shared", "~1.2.0")]);
+
+    let project = TempProject::new(&json!({
+        "name": "dedupe-test",
+        "version": "1.0.0",
+        "dependencies": { "dedupe-a": "^1.0.0", "dedupe-b": "^1.0.0" },
+    }));
+    pacm_core::install::bulk::BulkInstaller::new()
+        .with_dedupe(true)
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    assert_eq!(
+        locked_version(&project, "dedupe-shared").as_deref(),
+        Some("1.2.9")
+    );
+    assert!(!project.has_module("dedupe-extra"));
+    assert!(locked_version(&project, "dedupe-extra").is_none());
+}
+
+fn write_workspace_member(project: &TempProject, dir: &str, manifest: &serde_json::Value) {
+    let member = project.path().join("packages").join(dir);
+    std::fs::create_dir_all(&member).unwrap();
+    std::fs::write(
+        member.join("package.json"),
+        serde_json::to_string_pretty(manifest).unwrap(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_install_links_workspace_protocol_members() {
+    let registry = MockRegistry::shared();
+    registry.publish("wsproto-registry-dep", "1.0.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "wsproto-root",
+        "version": "1.0.0",
+        "workspaces": ["packages/*"],
+    }));
+    write_workspace_member(
+        &project,
+        "utils",
+        &json!({ "name": "wsproto-utils", "version": "1.2.0" }),
+    );
+    write_workspace_member(
+        &project,
+        "app",
+        &json!({
+            "name": "wsproto-app",
+            "version": "1.0.0",
+            "dependencies": {
+                "wsproto-utils": "workspace:^",
+                "wsproto-registry-dep": "^1.0.0",
+            },
+        }),
+    );
+
+    pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    let linked = project.path().join("node_modules").join("wsproto-utils");
+    assert!(linked.symlink_metadata().unwrap().file_type().is_symlink());
+    assert!(project.has_module("wsproto-app"));
+    assert!(project.has_module("wsproto-registry-dep"));
+    assert_eq!(
+        locked_version(&project, "wsproto-registry-dep").as_deref(),
+        Some("1.0.0")
+    );
+    assert!(locked_version(&project, "wsproto-utils").is_none());
+}
+
+#[test]
+fn test_install_links_diamond_workspace_graph() {
+    MockRegistry::shared();
+
+    let project = TempProject::new(&json!({
+        "name": "wsdiamond-root",
+        "version": "1.0.0",
+        "workspaces": ["packages/*"],
+    }));
+    let members = [
+        ("core", json!({})),
+        ("left", json!({ "wsdiamond-core": "workspace:*" })),
+        ("right", json!({ "wsdiamond-core": "workspace:*" })),
+        (
+            "app",
+            json!({ "wsdiamond-left": "workspace:*", "wsdiamond-right": "workspace:*" }),
+        ),
+    ];
+    for (dir, dependencies) in members {
+        write_workspace_member(
+            &project,
+            dir,
+            &json!({
+                "name": format!("wsdiamond-{dir}"),
+                "version": "1.0.0",
+                "dependencies": dependencies,
+                "scripts": { "postinstall": format!("echo {dir} >> ../../built.log") },
+            }),
+        );
+    }
+
+    pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    for dir in ["core", "left", "right", "app"] {
+        let linked = project
+            .path()
+            .join("node_modules")
+            .join(format!("wsdiamond-{dir}"));
+        assert!(
+            linked.symlink_metadata().unwrap().file_type().is_symlink(),
+            "{dir}"
+        );
+    }
+
+    // Each member is built after the members it depends on.
+    if cfg!(unix) {
+        let built = std::fs::read_to_string(project.path().join("built.log")).unwrap();
+        let built: Vec<&str> = built.lines().collect();
+        assert_eq!(built.len(), 4, "{built:?}");
+        assert_eq!(built[0], "core");
+        assert_eq!(built[3], "app");
+    }
+}
+
+#[test]
+fn test_workspace_protocol_rejects_mismatched_member_version() {
+    let project = TempProject::new(&json!({
+        "name": "wsproto-mismatch-root",
+        "version": "1.0.0",
+        "workspaces": ["packages/*"],
+    }));
+    write_workspace_member(
+        &project,
+        "utils",
+        &json!({ "name": "wsproto-mismatch-utils", "version": "1.2.0" }),
+    );
+    write_workspace_member(
+        &project,
+        "app",
+        &json!({
+            "name": "wsproto-mismatch-app",
+            "version": "1.0.0",
+            "dependencies": { "wsproto-mismatch-utils": "workspace:^2.0.0" },
+        }),
+    );
+
+    let error = pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        pacm_error::PackageManagerError::DependencyConflict(ref name, _)
+            if name == "wsproto-mismatch-utils"
+    ));
+}
+
+#[test]
+fn test_conflicting_member_ranges_are_reported_until_the_root_picks_one() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("wsconflict-dep", "1.0.0", &[])
+        .publish("wsconflict-dep", "2.0.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "wsconflict-root",
+        "version": "1.0.0",
+        "workspaces": ["packages/*"],
+    }));
+    for (dir, range) in [("old", "^1.0.0"), ("new", "^2.0.0")] {
+        write_workspace_member(
+            &project,
+            dir,
+            &json!({
+                "name": format!("wsconflict-{dir}"),
+                "version": "1.0.0",
+                "dependencies": { "wsconflict-dep": range },
+            }),
+        );
+    }
+
+    let error = pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        pacm_error::PackageManagerError::DependencyConflict(ref name, _)
+            if name == "wsconflict-dep"
+    ));
+
+    let mut manifest = project.package_json();
+    manifest["dependencies"] = json!({ "wsconflict-dep": "^2.0.0" });
+    std::fs::write(
+        project.path().join("package.json"),
+        serde_json::to_string_pretty(&manifest).unwrap(),
+    )
+    .unwrap();
+    pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap();
+    assert_eq!(
+        locked_version(&project, "wsconflict-dep").as_deref(),
+        Some("2.0.0")
+    );
+}
+
+#[test]
+fn test_member_directory_dependencies_resolve_against_the_member() {
+    MockRegistry::shared();
+
+    let project = TempProject::new(&json!({
+        "name": "wsfile-root",
+        "version": "1.0.0",
+        "workspaces": ["packages/*"],
+    }));
+    let lib = project.path().join("libs").join("lib");
+    std::fs::create_dir_all(&lib).unwrap();
+    std::fs::write(
+        lib.join("package.json"),
+        json!({ "name": "wsfile-lib", "version": "1.0.0" }).to_string(),
+    )
+    .unwrap();
+    write_workspace_member(
+        &project,
+        "app",
+        &json!({
+            "name": "wsfile-app",
+            "version": "1.0.0",
+            "dependencies": { "wsfile-lib": "file:../../libs/lib" },
+        }),
+    );
+
+    pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    assert!(project.has_module("wsfile-app"));
+    assert!(
+        project
+            .path()
+            .join("node_modules/wsfile-lib/package.json")
+            .exists()
+    );
+}
+
+#[test]
+fn test_install_check_passes_after_install_and_fails_on_drift() {
+    let registry = MockRegistry::shared();
+    registry.publish("verify-leaf", "1.0.0", &[]).publish(
+        "verify-root",
+        "1.0.0",
+        &[("verify-leaf", "^1.0.0")],
+    );
+
+    let project = TempProject::new(&json!({
+        "name": "verify-install-test",
+        "version": "1.0.0",
+        "dependencies": { "verify-root": "^1.0.0" },
+    }));
+    pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    pacm_core::VerifyManager.verify(project.dir()).unwrap();
+
+    std::fs::remove_file(project.path().join("node_modules").join("verify-leaf")).unwrap();
+    assert!(matches!(
+        pacm_core::VerifyManager.verify(project.dir()),
+        Err(pacm_error::PackageManagerError::LockfileMismatch(1))
+    ));
+}
+
+#[test]
+fn test_install_tarball_from_url_without_extension() {
+    let registry = MockRegistry::shared();
+    registry.publish("url-tarball-dep", "1.0.0", &[]);
+
+    let tarball = pacm_testutil::build_tarball(&json!({
+        "name": "url-tarball",
+        "version": "3.1.0",
+        "dependencies": { "url-tarball-dep": "^1.0.0" },
+    }));
+    let server = {
+        let tarball = tarball.clone();
+        pacm_testutil::MockServer::start(std::sync::Arc::new(move |_| {
+            pacm_testutil::Response::bytes(tarball.clone())
+        }))
+        .unwrap()
+    };
+    let url = format!("{}artifacts/download?id=42", server.url());
+
+    let project = TempProject::new(&json!({
+        "name": "url-tarball-test",
+        "version": "1.0.0",
+        "dependencies": { "url-tarball": url },
+    }));
+    pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    assert!(project.has_module("url-tarball"));
+    assert!(project.has_module("url-tarball-dep"));
+    let lockfile = project.lockfile().unwrap();
+    assert_eq!(lockfile["packages"]["url-tarball"]["resolved"], json!(url));
+    assert_eq!(
+        lockfile["packages"]["url-tarball"]["integrity"],
+        json!(pacm_testutil::integrity(&tarball))
+    );
+}
+
+fn update_with_prefer_dedupe(prefix: &str, prefer_dedupe: bool) -> TempProject {
+    let (app, shared, extra) = (
+        format!("{prefix}-app"),
+        format!("{prefix}-shared"),
+        format!("{prefix}-extra"),
+    );
+    MockRegistry::shared()
+        .publish(&extra, "1.0.0", &[])
+        .publish(&shared, "1.0.0", &[])
+        .publish(&shared, "1.1.0", &[(&extra, "^1.0.0")])
+        .publish(&app, "1.0.0", &[(&shared, "1.0.0")])
+        .publish(&app, "1.1.0", &[(&shared, "^1.0.0")]);
+
+    let project = TempProject::new(&json!({
+        "name": format!("{prefix}-test"),
+        "version": "1.0.0",
+        "dependencies": { app.clone(): "1.0.0" },
+    }));
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert_eq!(locked_version(&project, &shared).as_deref(), Some("1.0.0"));
+
+    pacm_core::UpdateManager::new()
+        .with_prefer_dedupe(prefer_dedupe)
+        .update_deps(project.dir(), std::slice::from_ref(&app), false)
+        .unwrap();
+    assert_eq!(locked_version(&project, &app).as_deref(), Some("1.1.0"));
+    project
+}
+
+#[test]
+fn test_prefer_dedupe_update_keeps_locked_versions() {
+    let project = update_with_prefer_dedupe("prefdedupe-on", true);
+
+    assert_eq!(
+        locked_version(&project, "prefdedupe-on-shared").as_deref(),
+        Some("1.0.0")
+    );
+    assert!(locked_version(&project, "prefdedupe-on-extra").is_none());
+    assert!(!project.has_module("prefdedupe-on-extra"));
+}
+
+#[test]
+fn test_default_update_takes_highest_satisfying_version() {
+    let project = update_with_prefer_dedupe("prefdedupe-off", false);
+
+    assert_eq!(
+        locked_version(&project, "prefdedupe-off-shared").as_deref(),
+        Some("1.1.0")
+    );
+    assert!(locked_version(&project, "prefdedupe-off-extra").is_some());
+}
+
+#[test]
+fn test_install_rejects_self_dependency() {
+    let project = TempProject::new(&json!({
+        "name": "selfdep-app",
+        "version": "1.0.0",
+        "dependencies": { "selfdep-app": "^1.0.0" },
+    }));
+
+    let error = pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        pacm_error::PackageManagerError::DependencyConflict(ref name, _) if name == "selfdep-app"
+    ));
+
+    let error = pacm_core::install_single_with(
+        project.dir(),
+        "selfdep-app",
+        "^1.0.0",
+        &InstallOptions::new(),
+    )
+    .unwrap_err();
+    assert!(
+        error.to_string().contains("installed into itself"),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_install_rejects_workspace_dev_dependency_cycle() {
+    let project = TempProject::new(&json!({
+        "name": "devcycle-root",
+        "version": "1.0.0",
+        "workspaces": ["packages/*"],
+    }));
+    write_workspace_member(
+        &project,
+        "a",
+        &json!({
+            "name": "devcycle-a",
+            "version": "1.0.0",
+            "devDependencies": { "devcycle-b": "workspace:*" },
+        }),
+    );
+    write_workspace_member(
+        &project,
+        "b",
+        &json!({
+            "name": "devcycle-b",
+            "version": "1.0.0",
+            "devDependencies": { "devcycle-a": "workspace:*" },
+        }),
+    );
+
+    let error = pacm_core::install::bulk::BulkInstaller::new()
+        .install_all(project.dir(), false)
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        pacm_error::PackageManagerError::DependencyConflict(ref name, _) if name == "devcycle-a"
+    ));
+    assert!(
+        error
+            .to_string()
+            .contains("devcycle-a > devcycle-b > devcycle-a"),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_store_add_prefetches_without_a_project() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("prefetch-leaf", "1.0.0", &[])
+        .publish("prefetch-root", "1.0.0", &[])
+        .publish("prefetch-root", "1.1.0", &[("prefetch-leaf", "^1.0.0")]);
+    let stored = |name| {
+        pacm_core::StoreInspector
+            .versions(&pacm_store::get_store_path(), name)
+            .unwrap()
+    };
+
+    let added = pacm_core::StorePrefetcher::new(false)
+        .add("prefetch-root", "^1.0.0", false)
+        .unwrap();
+    assert_eq!(
+        added,
+        vec![("prefetch-root".to_string(), "1.1.0".to_string())]
+    );
+    assert!(stored("prefetch-leaf").is_empty());
+
+    let added = pacm_core::StorePrefetcher::new(true)
+        .add("prefetch-root", "^1.0.0", false)
+        .unwrap();
+    assert_eq!(added.len(), 2);
+    assert_eq!(stored("prefetch-leaf"), vec!["1.0.0"]);
+    assert_eq!(stored("prefetch-root"), vec!["1.1.0"]);
+}
+
+#[test]
+fn test_failed_install_resumes_from_journal() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("journal-leaf", "1.0.0", &[])
+        .publish("journal-root", "1.0.0", &[("journal-leaf", "^1.0.0")])
+        .withhold_tarball("journal-leaf", "1.0.0", true);
+
+    let project = TempProject::new(&json!({
+        "name": "journal-test",
+        "version": "1.0.0",
+        "dependencies": { "journal-root": "^1.0.0" },
+    }));
+    let journal_path = project.path().join("node_modules/.pacm-journal");
+
+    assert!(pacm_core::install_all_with(project.dir(), &InstallOptions::new()).is_err());
+    let journal: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&journal_path).unwrap()).unwrap();
+    let mut journaled: Vec<_> = journal["packages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pkg| format!("{}@{}", pkg["name"], pkg["version"]).replace('"', ""))
+        .collect();
+    journaled.sort();
+    assert_eq!(journaled, ["journal-leaf@1.0.0", "journal-root@1.0.0"]);
+    assert_eq!(journal["stages"]["journal-root@1.0.0"], "stored");
+    assert!(journal["stages"].get("journal-leaf@1.0.0").is_none());
+    assert!(project.lockfile().is_none());
+
+    // A newer leaf would win a fresh resolution, so installing 1.0.0 shows the rerun
+    // took the graph from the journal.
+    tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(pacm_registry::clear_package_cache());
+    registry
+        .publish("journal-leaf", "1.1.0", &[])
+        .withhold_tarball("journal-leaf", "1.0.0", false);
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+
+    assert!(project.has_module("journal-root"));
+    assert_eq!(
+        locked_version(&project, "journal-leaf").as_deref(),
+        Some("1.0.0")
+    );
+    assert!(!journal_path.exists());
+}
+
+#[test]
+fn test_no_lockfile_install_leaves_no_lockfile() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("nolock-leaf", "1.0.0", &[])
+        .publish("nolock-root", "1.0.0", &[("nolock-leaf", "^1.0.0")])
+        .publish("nolock-extra", "1.0.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "nolock-test",
+        "version": "1.0.0",
+        "dependencies": { "nolock-root": "^1.0.0", "nolock-extra": "^1.0.0" },
+    }));
+    pacm_core::install::bulk::BulkInstaller::new()
+        .with_lockfile(false)
+        .install_all(project.dir(), false)
+        .unwrap();
+
+    assert!(project.has_module("nolock-root"));
+    assert!(project.has_module("nolock-leaf"));
+    assert!(!project.path().join("pacm.lock").exists());
+
+    pacm_core::remove_dep(project.dir(), "nolock-extra", false, false).unwrap();
+    assert!(!project.has_module("nolock-extra"));
+    assert!(!project.path().join("pacm.lock").exists());
+
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert_eq!(
+        locked_version(&project, "nolock-leaf").as_deref(),
+        Some("1.0.0")
+    );
+}
+
+#[test]
+fn test_download_falls_back_to_dist_tarball_on_404() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("@mirror/layout", "1.0.0", &[])
+        .relocate_tarball("@mirror/layout", "1.0.0");
+
+    // A lockfile or fast path would carry the conventional URL, which this mirror 404s.
+    let pkg = pacm_resolver::ResolvedPackage {
+        resolved: pacm_config::PacmConfig::get().tarball_url("@mirror/layout", "1.0.0"),
+        ..resolved_package("@mirror/layout", "1.0.0", &[])
+    };
+    let tarball = tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(pacm_core::download::client::DownloadClient::new().download_tarball(&pkg, true))
+        .unwrap();
+
+    assert!(!tarball.is_empty());
+    let requests = registry.requests();
+    assert!(requests.contains(&"GET /@mirror/layout/-/layout-1.0.0.tgz".to_string()));
+    assert!(requests.contains(&"GET /mirror/layout-1.0.0.tgz".to_string()));
+}
+
+#[test]
+fn test_optional_download_failure_is_skipped() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("skip-opt-native", "1.0.0", &[])
+        .publish("skip-opt-direct", "1.0.0", &[])
+        .publish("skip-opt-shared", "1.0.0", &[])
+        .publish_manifest(json!({
+            "name": "skip-opt-host",
+            "version": "1.0.0",
+            "dependencies": { "skip-opt-shared": "^1.0.0" },
+            "optionalDependencies": { "skip-opt-native": "^1.0.0" },
+        }))
+        .withhold_tarball("skip-opt-native", "1.0.0", true)
+        .withhold_tarball("skip-opt-direct", "1.0.0", true);
+
+    let project = TempProject::new(&json!({
+        "name": "skip-opt-test",
+        "version": "1.0.0",
+        "dependencies": { "skip-opt-host": "^1.0.0" },
+        "optionalDependencies": { "skip-opt-direct": "^1.0.0" },
+    }));
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+
+    assert!(project.has_module("skip-opt-host"));
+    assert!(project.has_module("skip-opt-shared"));
+    assert!(!project.has_module("skip-opt-native"));
+    assert!(!project.has_module("skip-opt-direct"));
+    assert_eq!(locked_version(&project, "skip-opt-native"), None);
+    assert_eq!(locked_version(&project, "skip-opt-direct"), None);
+    assert_eq!(
+        locked_version(&project, "skip-opt-shared").as_deref(),
+        Some("1.0.0")
+    );
+}
+
+#[test]
+fn test_lockfile_backfills_missing_integrity() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("blank-integrity-leaf", "1.0.0", &[])
+        .publish(
+            "blank-integrity-root",
+            "1.0.0",
+            &[("blank-integrity-leaf", "^1.0.0")],
+        )
+        .strip_integrity("blank-integrity-leaf", "1.0.0")
+        .strip_integrity("blank-integrity-root", "1.0.0");
+
+    let manifest = json!({
+        "name": "blank-integrity-test",
+        "version": "1.0.0",
+        "dependencies": { "blank-integrity-root": "^1.0.0" },
+    });
+    // The first install downloads both tarballs, the second links them from the store.
+    for project in [TempProject::new(&manifest), TempProject::new(&manifest)] {
+        pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+
+        let lockfile = project.lockfile().unwrap();
+        let packages = lockfile["packages"].as_object().unwrap();
+        assert_eq!(packages.len(), 2);
+        for (name, package) in packages {
+            let integrity = package["integrity"].as_str().unwrap_or_default();
+            assert!(integrity.starts_with("sha512-"), "{name}: {integrity:?}");
+        }
+    }
+}
+
+#[test]
+fn test_install_rejects_conflicting_duplicate_requests() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("dup-react", "17.0.2", &[])
+        .publish("dup-react", "18.2.0", &[]);
+
+    let project = TempProject::new(&json!({ "name": "dup-conflict-test", "version": "1.0.0" }));
+    let error = pacm_core::InstallManager::new()
+        .install_multiple(
+            project.dir(),
+            &[
+                ("dup-react".to_string(), "^17.0.0".to_string()),
+                ("dup-react".to_string(), "^18.0.0".to_string()),
+            ],
+            DependencyType::Dependencies,
+            false,
+            false,
+            false,
+            false,
+        )
+        .unwrap_err();
+
+    assert!(
+        matches!(
+            error,
+            pacm_error::PackageManagerError::DependencyConflict(ref name, ref details)
+                if name == "dup-react" && details.contains("dup-react@^17.0.0 and dup-react@^18.0.0")
+        ),
+        "{error}"
+    );
+    assert!(!project.has_module("dup-react"));
+}
+
+#[test]
+fn test_install_merges_compatible_duplicate_requests() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("dup-lodash", "4.16.0", &[])
+        .publish("dup-lodash", "4.17.21", &[])
+        .publish("dup-lodash", "5.0.0", &[]);
+
+    let project = TempProject::new(&json!({ "name": "dup-merge-test", "version": "1.0.0" }));
+    pacm_core::install_multiple_with(
+        project.dir(),
+        &[
+            ("dup-lodash".to_string(), "latest".to_string()),
+            ("dup-lodash".to_string(), "^4.0.0".to_string()),
+            ("dup-lodash".to_string(), "<4.17.0 || >=4.17.20".to_string()),
+        ],
+        &InstallOptions::new(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        locked_version(&project, "dup-lodash").as_deref(),
+        Some("4.17.21")
+    );
+    assert_eq!(
+        project.package_json()["dependencies"]["dup-lodash"],
+        json!("^4.0.0 <4.17.0 || ^4.0.0 >=4.17.20")
+    );
+}
+
+#[test]
+fn test_install_resolves_again_when_package_json_drifts_from_lockfile() {
+    let registry = MockRegistry::shared();
+    registry
+        .publish("drift-leaf", "1.0.0", &[])
+        .publish("drift-leaf", "2.0.0", &[])
+        .publish("drift-lib", "1.0.0", &[("drift-leaf", "^1.0.0")])
+        .publish("drift-lib", "2.0.0", &[("drift-leaf", "^2.0.0")])
+        .publish("drift-lib", "2.1.0", &[("drift-leaf", "^2.0.0")])
+        .publish("drift-other", "1.0.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "drift-test",
+        "version": "1.0.0",
+        "dependencies": { "drift-lib": "^1.0.0", "drift-other": "^1.0.0" },
+    }));
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert_eq!(
+        locked_version(&project, "drift-lib").as_deref(),
+        Some("1.0.0")
+    );
+
+    let set_range = |range: &str| {
+        let mut manifest = project.package_json();
+        manifest["dependencies"]["drift-lib"] = json!(range);
+        std::fs::write(
+            project.path().join("package.json"),
+            serde_json::to_string_pretty(&manifest).unwrap(),
+        )
+        .unwrap();
+    };
+
+    // A range the locked version still satisfies keeps it.
+    set_range("^1.0.0 || ^2.0.0");
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert_eq!(
+        locked_version(&project, "drift-lib").as_deref(),
+        Some("1.0.0")
+    );
+
+    set_range("^2.0.0");
+    let error = pacm_core::install::bulk::BulkInstaller::new()
+        .with_frozen_lockfile(true)
+        .install_all(project.dir(), false)
+        .unwrap_err();
+    assert!(
+        matches!(error, pacm_error::PackageManagerError::LockfileError(ref msg) if msg.contains("drift-lib")),
+        "{error}"
+    );
+
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert_eq!(
+        locked_version(&project, "drift-lib").as_deref(),
+        Some("2.1.0")
+    );
+    assert_eq!(
+        locked_version(&project, "drift-leaf").as_deref(),
+        Some("2.0.0")
+    );
+    assert_eq!(
+        locked_version(&project, "drift-other").as_deref(),
+        Some("1.0.0")
+    );
+}
+
+#[test]
+fn test_publish_uploads_a_package_that_installs() {
+    let registry = MockRegistry::shared();
+    let library = TempProject::new(&json!({
+        "name": "pub-widget",
+        "version": "1.0.0",
+        "main": "index.js",
+        "publishConfig": { "registry": registry.url() },
+    }));
+    std::fs::write(
+        library.path().join("index.js"),
+        "module.exports = 'widget';\n",
+    )
+    .unwrap();
+
+    pacm_core::Publisher::new()
+        .publish(library.path(), &pacm_core::PublishOptions::default())
+        .unwrap();
+    assert!(registry.requests().contains(&"PUT /pub-widget".to_string()));
+
+    let error = pacm_core::Publisher::new()
+        .publish(library.path(), &pacm_core::PublishOptions::default())
+        .unwrap_err();
+    assert!(
+        matches!(error, pacm_error::PackageManagerError::AlreadyPublished(ref spec) if spec == "pub-widget@1.0.0"),
+        "{error}"
+    );
+
+    let project = TempProject::new(&json!({ "name": "pub-consumer", "version": "1.0.0" }));
+    pacm_core::install_single_with(
+        project.dir(),
+        "pub-widget",
+        "^1.0.0",
+        &InstallOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(project.path().join("node_modules/pub-widget/index.js")).unwrap(),
+        "module.exports = 'widget';\n"
+    );
+}
+
+#[test]
+fn test_install_links_copies_directory_dependencies() {
+    let registry = MockRegistry::shared();
+    registry.publish("links-leaf", "1.0.0", &[]);
+
+    let project = TempProject::new(&json!({
+        "name": "links-test",
+        "version": "1.0.0",
+        "dependencies": { "links-local": "file:./packages/local" },
+    }));
+    let local = project.path().join("packages/local");
+    std::fs::create_dir_all(&local).unwrap();
+    std::fs::write(
+        local.join("package.json"),
+        json!({
+            "name": "links-local",
+            "version": "0.1.0",
+            "dependencies": { "links-leaf": "^1.0.0" },
+        })
+        .to_string(),
+    )
+    .unwrap();
+    std::fs::write(local.join("index.js"), "module.exports = 'local';\n").unwrap();
+    let installed = project.path().join("node_modules/links-local");
+    let resolved = |project: &TempProject| {
+        project.lockfile().unwrap()["packages"]["links-local"]["resolved"].clone()
+    };
+
+    pacm_core::install::bulk::BulkInstaller::new()
+        .with_install_links(false)
+        .install_all(project.dir(), false)
+        .unwrap();
+    assert!(
+        installed
+            .symlink_metadata()
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+    assert!(!project.has_module("links-leaf"));
+    assert_eq!(resolved(&project), json!("link:./packages/local"));
+
+    pacm_core::install::bulk::BulkInstaller::new()
+        .with_install_links(true)
+        .install_all(project.dir(), false)
+        .unwrap();
+    let metadata = installed.symlink_metadata().unwrap();
+    assert!(metadata.is_dir() && !metadata.file_type().is_symlink());
+    assert_eq!(
+        std::fs::read_to_string(installed.join("index.js")).unwrap(),
+        "module.exports = 'local';\n"
+    );
+    assert!(project.has_module("links-leaf"));
+    assert_eq!(resolved(&project), json!("file:./packages/local"));
+}
+
+#[test]
+fn test_resolution_limits_stop_runaway_trees() {
+    let registry = MockRegistry::shared();
+    let depth = 30;
+    for level in 0..depth {
+        let name = format!("deep-chain-{level}");
+        let next = format!("deep-chain-{}", level + 1);
+        if level + 1 < depth {
+            registry.publish(&name, "1.0.0", &[(&next, "^1.0.0")]);
+        } else {
+            registry.publish(&name, "1.0.0", &[]);
+        }
+    }
+    // A cycle is cut where it closes rather than followed into the depth limit.
+    registry
+        .publish("deep-cycle-a", "1.0.0", &[("deep-cycle-b", "^1.0.0")])
+        .publish("deep-cycle-b", "1.0.0", &[("deep-cycle-a", "^1.0.0")]);
+
+    let project = TempProject::new(&json!({
+        "name": "deep-test",
+        "version": "1.0.0",
+        "dependencies": { "deep-chain-0": "^1.0.0", "deep-cycle-a": "^1.0.0" },
+    }));
+    let install = |limits: ResolutionLimits| {
+        pacm_core::install::bulk::BulkInstaller::new()
+            .with_resolution_limits(limits)
+            .install_all(project.dir(), false)
+    };
+
+    let message = install(ResolutionLimits::new(10, 100_000))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        message.contains("Stopped resolving at deep-chain-10@1.0.0")
+            && message.contains("max-resolution-depth (10)"),
+        "{message}"
+    );
+    assert!(!project.has_module("deep-chain-0"));
+
+    let message = install(ResolutionLimits::new(100, 20))
+        .unwrap_err()
+        .to_string();
+    assert!(message.contains("max-packages (20)"), "{message}");
+
+    install(ResolutionLimits::new(100, 100)).unwrap();
+    assert!(project.has_module("deep-chain-29"));
+    assert!(project.has_module("deep-cycle-b"));
+}
+
+#[test]
+fn test_npm_compat_writes_hidden_lockfile() {
+    let registry = MockRegistry::shared();
+    registry.publish("npmcompat-leaf", "1.2.0", &[]).publish(
+        "npmcompat-app",
+        "2.0.0",
+        &[("npmcompat-leaf", "^1.0.0")],
+    );
+
+    let project = TempProject::new(&json!({
+        "name": "npmcompat-test",
+        "version": "0.3.0",
+        "dependencies": { "npmcompat-app": "^2.0.0" },
+    }));
+    let hidden_lockfile = project.path().join("node_modules/.package-lock.json");
+
+    pacm_core::install::bulk::BulkInstaller::new()
+        .with_npm_compat(false)
+        .install_all(project.dir(), false)
+        .unwrap();
+    assert!(!hidden_lockfile.exists());
+
+    pacm_core::install::bulk::BulkInstaller::new()
+        .with_npm_compat(true)
+        .install_all(project.dir(), false)
+        .unwrap();
+    let document: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&hidden_lockfile).unwrap()).unwrap();
+    assert_eq!(document["name"], json!("npmcompat-test"));
+    assert_eq!(document["version"], json!("0.3.0"));
+    assert_eq!(document["lockfileVersion"], json!(3));
+    assert_eq!(document["requires"], json!(true));
+
+    let packages = document["packages"].as_object().unwrap();
+    assert_eq!(packages.len(), 2, "{packages:?}");
+    let app = &packages["node_modules/npmcompat-app"];
+    assert_eq!(app["version"], json!("2.0.0"));
+    assert_eq!(app["dependencies"], json!({ "npmcompat-leaf": "^1.0.0" }));
+    let leaf = &packages["node_modules/npmcompat-leaf"];
+    assert_eq!(leaf["version"], json!("1.2.0"));
+    assert!(
+        leaf["resolved"]
+            .as_str()
+            .unwrap()
+            .ends_with("/npmcompat-leaf/-/npmcompat-leaf-1.2.0.tgz"),
+        "{leaf}"
+    );
+    assert!(leaf["integrity"].as_str().unwrap().starts_with("sha512-"));
+}
+
+#[test]
+fn test_list_tree_reads_node_modules_without_registry() {
+    let registry = MockRegistry::shared();
+    registry.publish("offline-list-leaf", "1.2.0", &[]).publish(
+        "offline-list-app",
+        "1.0.0",
+        &[("offline-list-leaf", "^1.0.0")],
+    );
+    let requests = || {
+        registry
+            .requests()
+            .iter()
+            .filter(|request| request.contains("offline-list-"))
+            .count()
+    };
+
+    let project = TempProject::new(&json!({
+        "name": "offline-list-test",
+        "version": "1.0.0",
+        "dependencies": { "offline-list-app": "^1.0.0" },
+    }));
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    let fetched = requests();
+
+    // A lockfile that lost an entry, and then no lockfile at all.
+    let mut lockfile = project.lockfile().unwrap();
+    lockfile["packages"]
+        .as_object_mut()
+        .unwrap()
+        .remove("offline-list-leaf");
+    let lock_path = project.path().join("pacm.lock");
+    std::fs::write(&lock_path, lockfile.to_string()).unwrap();
+    let pkg = pacm_project::read_package_json(project.path()).unwrap();
+    for remove_lockfile in [false, true] {
+        if remove_lockfile {
+            std::fs::remove_file(&lock_path).unwrap();
+        }
+        let tree = pacm_core::ListManager
+            .dependency_tree(project.path(), &pkg, None, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            pacm_resolver::tree_lines(&tree),
+            ["offline-list-app@1.0.0", "└── offline-list-leaf@1.2.0"]
+        );
+        pacm_core::list_deps(project.dir(), true, None, None, false).unwrap();
+    }
+
+    assert_eq!(requests(), fetched);
+}
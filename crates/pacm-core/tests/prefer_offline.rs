@@ -0,0 +1,47 @@
+//! Runs in its own process: `network-mode` is read from the configuration, which is
+//! loaded once per process.
+
+use std::collections::HashMap;
+
+use pacm_registry::{MetadataCache, MetadataSource, PackageInfo};
+use pacm_testutil::MockRegistry;
+use serde_json::json;
+
+#[test]
+fn test_prefer_offline_asks_the_registry_only_on_a_miss() {
+    let registry = MockRegistry::shared();
+    pacm_config::PacmConfig::init_with_overrides(&[("network-mode", "prefer-offline")]);
+    registry
+        .publish("preferoff-pkg", "1.0.0", &[])
+        .publish("preferoff-pkg", "1.1.0", &[])
+        .publish("preferoff-pkg", "2.0.0", &[]);
+    let metadata_requests = || {
+        registry
+            .requests()
+            .iter()
+            .filter(|request| *request == "GET /preferoff-pkg")
+            .count()
+    };
+
+    // An old copy that predates 1.1.0 and 2.0.0, stale under the test `cache-max-age=0`.
+    let saved = PackageInfo {
+        versions: json!({ "1.0.0": { "name": "preferoff-pkg", "version": "1.0.0" } }),
+        dist_tags: HashMap::from([("latest".to_string(), "1.0.0".to_string())]),
+    };
+    MetadataCache::global()
+        .write(
+            "preferoff-pkg",
+            &MetadataSource::configured("preferoff-pkg"),
+            &saved,
+            None,
+        )
+        .unwrap();
+
+    let info = pacm_resolver::package_info("preferoff-pkg", "^1.0.0").unwrap();
+    assert_eq!(info.versions, saved.versions);
+    assert_eq!(metadata_requests(), 0);
+
+    let info = pacm_resolver::package_info("preferoff-pkg", "^2.0.0").unwrap();
+    assert!(info.versions.get("2.0.0").is_some());
+    assert_eq!(metadata_requests(), 1);
+}
@@ -0,0 +1,43 @@
+//! Runs in its own process: the configuration is loaded once per process, and these
+//! tests check which project's rc files it is loaded from.
+
+use pacm_core::InstallOptions;
+use pacm_testutil::{MockRegistry, TempProject};
+use serde_json::json;
+
+#[test]
+fn test_install_reads_rc_files_of_the_target_project() {
+    let registry = MockRegistry::shared();
+    registry.publish("project-rc-dep", "1.0.0", &[]);
+
+    let manifest = |name: &str| {
+        json!({
+            "name": name,
+            "version": "1.0.0",
+            "dependencies": { "project-rc-dep": "^1.0.0" },
+        })
+    };
+    let project = TempProject::new(&manifest("project-rc-test"));
+    std::fs::write(
+        project.path().join(".npmrc"),
+        "lockfile-name=project-lock.json\n",
+    )
+    .unwrap();
+
+    // The test's working directory is not the project, so its settings only apply when
+    // they are read from the directory being installed into.
+    pacm_core::install_all_with(project.dir(), &InstallOptions::new()).unwrap();
+    assert!(project.path().join("project-lock.json").exists());
+    assert!(!project.path().join("pacm.lock").exists());
+
+    let plain = TempProject::new(&manifest("project-rc-plain"));
+    pacm_core::install_all_with(plain.dir(), &InstallOptions::new()).unwrap();
+
+    // Another project's own rc file cannot take effect any more, which is an error
+    // rather than an install with the wrong registry or credentials.
+    let other = TempProject::new(&manifest("project-rc-other"));
+    std::fs::write(other.path().join(".pacmrc"), "lockfile-name=other.lock\n").unwrap();
+    let error = pacm_core::install_all_with(other.dir(), &InstallOptions::new()).unwrap_err();
+    assert!(error.to_string().contains(".pacmrc"), "{error}");
+    assert!(!other.path().join("node_modules").exists());
+}
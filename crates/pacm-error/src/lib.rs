@@ -4,6 +4,14 @@ use std::fmt;
 pub enum PackageManagerError {
     PackageNotFound(String),
     VersionResolutionFailed(String, String),
+    /// A package in the dependency tree could not be resolved. `path` lists the packages
+    /// that led to it as `name@version`, starting at the direct dependency.
+    DependencyResolutionFailed {
+        name: String,
+        range: String,
+        path: Vec<String>,
+        reason: String,
+    },
     DownloadFailed(String, String),
     StorageFailed(String, String),
     LinkingFailed(String, String),
@@ -11,10 +19,35 @@ pub enum PackageManagerError {
     PackageJsonError(String),
     PackageJsonExists(String),
     NetworkError(String),
+    ConfigError(String),
     InvalidPackageSpec(String),
     DependencyConflict(String, String),
     NoCompatibleVersions(String),
     IoError(String),
+    VulnerabilitiesFound(usize, String),
+    Cancelled,
+    RangeConflictsFound(usize),
+    /// A downloaded package failed `--audit-signatures`: `(name@version, reason)`.
+    SignatureInvalid(String, String),
+    /// `package.json` pins another pacm version: `(pinned, running)`.
+    PacmVersionMismatch(String, String),
+    LockfileMismatch(usize),
+    /// A `pacm remove` pattern that matches none of the project's dependencies.
+    NoPatternMatch(String),
+    /// `pacm why --orphans` found locked packages nothing depends on.
+    OrphansFound(usize),
+    /// `pacm publish` found `name@version` on the registry already.
+    AlreadyPublished(String),
+    /// `pacm publish` of `(name@version, reason)` was refused.
+    PublishFailed(String, String),
+    /// Resolution reached `package` through `path` after the tree grew past the `limit`
+    /// of a `setting` such as `max-packages`.
+    ResolutionLimitExceeded {
+        setting: &'static str,
+        limit: usize,
+        package: String,
+        path: Vec<String>,
+    },
 }
 
 impl fmt::Display for PackageManagerError {
@@ -29,6 +62,18 @@ impl fmt::Display for PackageManagerError {
             Self::VersionResolutionFailed(name, range) => {
                 write!(f, "Failed to resolve version for {name}@{range}")
             }
+            Self::DependencyResolutionFailed {
+                name,
+                range,
+                path,
+                reason,
+            } => {
+                write!(f, "Failed to resolve {name}@{range}")?;
+                if !path.is_empty() {
+                    write!(f, " (required by {})", path.join(" > "))?;
+                }
+                write!(f, ": {reason}")
+            }
             Self::DownloadFailed(name, version) => {
                 write!(f, "Failed to download {name}@{version}")
             }
@@ -47,6 +92,9 @@ impl fmt::Display for PackageManagerError {
             Self::NetworkError(msg) => {
                 write!(f, "Network error: {msg}")
             }
+            Self::ConfigError(msg) => {
+                write!(f, "Configuration error: {msg}")
+            }
             Self::InvalidPackageSpec(spec) => {
                 write!(f, "Invalid package specification: {spec}")
             }
@@ -59,6 +107,76 @@ impl fmt::Display for PackageManagerError {
             Self::IoError(msg) => {
                 write!(f, "IO error: {msg}")
             }
+            Self::VulnerabilitiesFound(count, level) => {
+                write!(
+                    f,
+                    "Found {count} vulnerabilities at or above {level} severity"
+                )
+            }
+            Self::Cancelled => {
+                write!(f, "Installation cancelled")
+            }
+            Self::RangeConflictsFound(count) => {
+                write!(
+                    f,
+                    "Found {count} packages whose locked version does not satisfy every dependent"
+                )
+            }
+            Self::SignatureInvalid(package, reason) => {
+                write!(f, "Registry signature of {package} is invalid: {reason}")
+            }
+            Self::PacmVersionMismatch(pinned, running) => {
+                write!(
+                    f,
+                    "package.json pins pacm@{pinned} in packageManager, but this is pacm {running}"
+                )
+            }
+            Self::LockfileMismatch(count) => {
+                write!(f, "Found {count} packages that do not match the lockfile")
+            }
+            Self::NoPatternMatch(pattern) => {
+                write!(f, "No dependency matches '{pattern}'")
+            }
+            Self::OrphansFound(count) => {
+                write!(
+                    f,
+                    "Found {count} locked packages that no dependency leads to"
+                )
+            }
+            Self::AlreadyPublished(package) => {
+                write!(
+                    f,
+                    "{package} is already published; bump the version or pass --force"
+                )
+            }
+            Self::PublishFailed(package, reason) => {
+                write!(f, "Failed to publish {package}: {reason}")
+            }
+            Self::ResolutionLimitExceeded {
+                setting,
+                limit,
+                package,
+                path,
+            } => {
+                write!(
+                    f,
+                    "Stopped resolving at {package}: the dependency tree exceeds {setting} ({limit})"
+                )?;
+                // A runaway chain can be thousands of packages long, so only its ends are named.
+                if path.len() > 6 {
+                    let (head, tail) = (&path[..3], &path[path.len() - 3..]);
+                    write!(
+                        f,
+                        " (required by {} > ... {} more ... > {})",
+                        head.join(" > "),
+                        path.len() - 6,
+                        tail.join(" > ")
+                    )?;
+                } else if !path.is_empty() {
+                    write!(f, " (required by {})", path.join(" > "))?;
+                }
+                write!(f, "; raise {setting} if this tree is expected")
+            }
         }
     }
 }
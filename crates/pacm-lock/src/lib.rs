@@ -1,5 +1,25 @@
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, io, path::Path};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{fs, io};
+
+/// The `lockfileVersion` this pacm writes, and the newest it reads.
+pub const LOCKFILE_VERSION: u32 = 2;
+
+/// Upgrades a lockfile of version `from` to the next one. `apply` describes what it
+/// changed, or returns `None` when the document needed nothing beyond the version bump.
+struct Migration {
+    from: u32,
+    apply: fn(&mut Value) -> Option<&'static str>,
+}
+
+/// Older lockfiles go through every migration after their own version, in order.
+const MIGRATIONS: [Migration; 1] = [Migration {
+    from: 1,
+    apply: move_legacy_dependencies,
+}];
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LockDependency {
@@ -17,6 +37,11 @@ pub struct LockPackage {
     pub dependencies: HashMap<String, String>,
     #[serde(skip_serializing_if = "HashMap::is_empty", default)]
     pub optional_dependencies: HashMap<String, String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub peer_dependencies: HashMap<String, String>,
+    /// Peers marked optional in `peerDependenciesMeta`.
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub optional_peer_dependencies: HashMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -31,12 +56,24 @@ pub struct WorkspaceInfo {
     pub optional_dependencies: HashMap<String, String>,
 }
 
+/// A package or workspace that declares a dependency on another locked package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependent {
+    /// Package name, or the workspace path when `direct` is set.
+    pub name: String,
+    pub range: String,
+    pub direct: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PacmLock {
     #[serde(rename = "lockfileVersion")]
     pub lockfile_version: u32,
     pub workspaces: HashMap<String, WorkspaceInfo>,
     pub packages: HashMap<String, LockPackage>,
+    /// Set by depth-limited installs, whose lockfile does not describe the full tree.
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub partial: bool,
 
     // Legacy field for backward compatibility
     #[serde(skip_serializing_if = "HashMap::is_empty", default)]
@@ -46,7 +83,7 @@ pub struct PacmLock {
 impl Default for PacmLock {
     fn default() -> Self {
         Self {
-            lockfile_version: 1,
+            lockfile_version: LOCKFILE_VERSION,
             workspaces: {
                 let mut map = HashMap::new();
                 map.insert(
@@ -61,52 +98,87 @@ impl Default for PacmLock {
                 map
             },
             packages: HashMap::new(),
+            partial: false,
             dependencies: HashMap::new(), // Legacy field
         }
     }
 }
 
 impl PacmLock {
+    /// Reads the lockfile at `path`, upgrading it from an older `lockfileVersion` if need
+    /// be, or returns an empty one when there is no file.
     pub fn load(path: &Path) -> io::Result<Self> {
         if path.exists() {
             let content = fs::read_to_string(path)?;
-            let mut lockfile: Self = serde_json::from_str(&content)?;
+            let (lockfile, migrations) = Self::parse(&content)?;
 
-            if !lockfile.dependencies.is_empty() && lockfile.packages.is_empty() {
-                lockfile.migrate_from_legacy();
+            // A command loads the lockfile several times before saving it upgraded.
+            static REPORTED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+            let mut reported = REPORTED.lock().unwrap();
+            if !migrations.is_empty() && !reported.iter().any(|reported| reported == path) {
+                reported.push(path.to_path_buf());
+                let name = path.file_name().map_or_else(
+                    || path.display().to_string(),
+                    |name| name.to_string_lossy().into_owned(),
+                );
+                // On stderr, so the notice never ends up in `--json` output.
+                for migration in migrations {
+                    eprintln!("Migrated {name} {migration}");
+                }
             }
-
             Ok(lockfile)
         } else {
             Ok(Self::default())
         }
     }
 
+    /// Parses a lockfile, running the migrations from its `lockfileVersion` up to
+    /// [`LOCKFILE_VERSION`]. Also returns what each migration that changed the document
+    /// did. A lockfile of a later
+    /// version is an error, since its fields may no longer mean what this pacm expects.
+    pub fn parse(content: &str) -> io::Result<(Self, Vec<String>)> {
+        let mut document: Value = serde_json::from_str(content)?;
+        let mut migrations = Vec::new();
+
+        if let Some(mut version) = document.get("lockfileVersion").and_then(Value::as_u64) {
+            if version > u64::from(LOCKFILE_VERSION) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "lockfile was written by a newer pacm (lockfileVersion {version}, this pacm reads up to {LOCKFILE_VERSION}); upgrade pacm to use it"
+                    ),
+                ));
+            }
+            for migration in MIGRATIONS {
+                if version == u64::from(migration.from) {
+                    let change = (migration.apply)(&mut document);
+                    version += 1;
+                    document["lockfileVersion"] = Value::from(version);
+                    if let Some(change) = change {
+                        migrations.push(format!(
+                            "from lockfileVersion {} to {version}: {change}",
+                            migration.from
+                        ));
+                    }
+                }
+            }
+            if version != u64::from(LOCKFILE_VERSION) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown lockfileVersion {version}"),
+                ));
+            }
+        }
+
+        Ok((serde_json::from_value(document)?, migrations))
+    }
+
     pub fn save(&self, path: &Path) -> io::Result<()> {
         let content = serde_json::to_string_pretty(self)?;
         fs::write(path, content)?;
         Ok(())
     }
 
-    fn migrate_from_legacy(&mut self) {
-        for (key, legacy_dep) in &self.dependencies {
-            if let Some(at_pos) = key.rfind('@') {
-                let package_name = &key[..at_pos];
-                self.packages.insert(
-                    package_name.to_string(),
-                    LockPackage {
-                        version: legacy_dep.version.clone(),
-                        resolved: legacy_dep.resolved.clone(),
-                        integrity: legacy_dep.integrity.clone(),
-                        dependencies: HashMap::new(),
-                        optional_dependencies: HashMap::new(),
-                    },
-                );
-            }
-        }
-        self.dependencies.clear();
-    }
-
     pub fn update_workspace_deps(
         &mut self,
         workspace: &str,
@@ -123,6 +195,13 @@ impl PacmLock {
                 optional_dependencies: HashMap::new(),
             });
 
+        for name in deps.keys() {
+            workspace_info.dependencies.remove(name);
+            workspace_info.dev_dependencies.remove(name);
+            workspace_info.peer_dependencies.remove(name);
+            workspace_info.optional_dependencies.remove(name);
+        }
+
         match dep_type {
             "dependencies" => workspace_info.dependencies.extend(deps.clone()),
             "devDependencies" => workspace_info.dev_dependencies.extend(deps.clone()),
@@ -147,6 +226,8 @@ impl PacmLock {
                     integrity: dep.integrity,
                     dependencies: HashMap::new(),
                     optional_dependencies: HashMap::new(),
+                    peer_dependencies: HashMap::new(),
+                    optional_peer_dependencies: HashMap::new(),
                 },
             );
         }
@@ -191,6 +272,45 @@ impl PacmLock {
         &self.packages
     }
 
+    /// Maps every package name to the workspaces and locked packages that depend on it.
+    /// A workspace's peer dependencies count, since pacm installs them for the workspace;
+    /// those of locked packages are skipped, since the package never installs them itself.
+    #[must_use]
+    pub fn reverse_dependencies(&self) -> HashMap<String, Vec<Dependent>> {
+        let mut graph: HashMap<String, Vec<Dependent>> = HashMap::new();
+
+        for (workspace, info) in &self.workspaces {
+            for (name, range) in info
+                .dependencies
+                .iter()
+                .chain(&info.dev_dependencies)
+                .chain(&info.peer_dependencies)
+                .chain(&info.optional_dependencies)
+            {
+                graph.entry(name.clone()).or_default().push(Dependent {
+                    name: workspace.clone(),
+                    range: range.clone(),
+                    direct: true,
+                });
+            }
+        }
+
+        for (pkg_name, pkg) in &self.packages {
+            for (name, range) in pkg.dependencies.iter().chain(&pkg.optional_dependencies) {
+                graph.entry(name.clone()).or_default().push(Dependent {
+                    name: pkg_name.clone(),
+                    range: range.clone(),
+                    direct: false,
+                });
+            }
+        }
+
+        for dependents in graph.values_mut() {
+            dependents.sort_by(|a, b| (!a.direct, &a.name).cmp(&(!b.direct, &b.name)));
+        }
+        graph
+    }
+
     pub fn remove_workspace_dep(&mut self, workspace: &str, name: &str) {
         if let Some(workspace_info) = self.workspaces.get_mut(workspace) {
             workspace_info.dependencies.remove(name);
@@ -200,3 +320,168 @@ impl PacmLock {
         }
     }
 }
+
+/// Version 1 lockfiles could list packages in a top-level `dependencies` map keyed by
+/// `name@version`, without their dependencies. Those entries become `packages`, unless the
+/// lockfile already has packages, which then describe the same tree in full.
+fn move_legacy_dependencies(document: &mut Value) -> Option<&'static str> {
+    let Some(Value::Object(legacy)) = document
+        .as_object_mut()
+        .and_then(|document| document.remove("dependencies"))
+    else {
+        return None;
+    };
+    if legacy.is_empty() {
+        return None;
+    }
+    let packages = document.as_object_mut().map(|document| {
+        document
+            .entry("packages")
+            .or_insert_with(|| Value::Object(Default::default()))
+    });
+    let Some(Value::Object(packages)) = packages else {
+        return None;
+    };
+    if !packages.is_empty() {
+        return Some(
+            "dropped the name@version entries of `dependencies`, which `packages` already covers",
+        );
+    }
+
+    for (key, entry) in legacy {
+        if let Some(at_pos) = key.rfind('@') {
+            packages.insert(key[..at_pos].to_string(), entry);
+        }
+    }
+    Some("moved the name@version entries of `dependencies` into `packages`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_workspace_deps_moves_between_sections() {
+        let mut lockfile = PacmLock::default();
+        let deps = HashMap::from([("lodash".to_string(), "4.17.21".to_string())]);
+
+        lockfile.update_workspace_deps("", &deps, "dependencies");
+        lockfile.update_workspace_deps("", &deps, "devDependencies");
+
+        let workspace = &lockfile.workspaces[""];
+        assert!(!workspace.dependencies.contains_key("lodash"));
+        assert_eq!(workspace.dev_dependencies.get("lodash").unwrap(), "4.17.21");
+    }
+
+    #[test]
+    fn test_reverse_dependencies_lists_workspaces_and_packages() {
+        let mut lockfile = PacmLock::default();
+        let deps = HashMap::from([("react".to_string(), "18.2.0".to_string())]);
+        lockfile.update_workspace_deps("", &deps, "dependencies");
+        lockfile.update_package(
+            "react",
+            LockPackage {
+                version: "18.2.0".to_string(),
+                resolved: String::new(),
+                integrity: String::new(),
+                dependencies: HashMap::from([("loose-envify".to_string(), "^1.1.0".to_string())]),
+                optional_dependencies: HashMap::new(),
+                peer_dependencies: HashMap::new(),
+                optional_peer_dependencies: HashMap::new(),
+            },
+        );
+
+        let graph = lockfile.reverse_dependencies();
+
+        assert_eq!(
+            graph["react"],
+            vec![Dependent {
+                name: String::new(),
+                range: "18.2.0".to_string(),
+                direct: true,
+            }]
+        );
+        assert_eq!(graph["loose-envify"][0].name, "react");
+        assert!(!graph["loose-envify"][0].direct);
+    }
+
+    #[test]
+    fn test_migrates_legacy_dependencies_from_version_1() {
+        let (lockfile, migrations) = PacmLock::parse(
+            r#"{
+                "lockfileVersion": 1,
+                "workspaces": {},
+                "packages": {},
+                "dependencies": {
+                    "@scope/lib@1.2.0": { "version": "1.2.0", "resolved": "https://r/lib.tgz", "integrity": "sha512-a" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(lockfile.lockfile_version, LOCKFILE_VERSION);
+        assert!(lockfile.dependencies.is_empty());
+        let package = lockfile.get_package("@scope/lib").unwrap();
+        assert_eq!(package.version, "1.2.0");
+        assert_eq!(package.resolved, "https://r/lib.tgz");
+        assert_eq!(
+            migrations,
+            [
+                "from lockfileVersion 1 to 2: moved the name@version entries of `dependencies` into `packages`"
+            ]
+        );
+
+        // A version 1 lockfile written with `packages` keeps them as they are.
+        let (lockfile, migrations) = PacmLock::parse(
+            r#"{
+                "lockfileVersion": 1,
+                "workspaces": {},
+                "packages": { "ms": { "version": "2.1.3", "resolved": "", "integrity": "" } },
+                "dependencies": { "ms@2.0.0": { "version": "2.0.0", "resolved": "", "integrity": "" } }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(lockfile.get_package("ms").unwrap().version, "2.1.3");
+        assert!(lockfile.dependencies.is_empty());
+        assert_eq!(
+            migrations,
+            [
+                "from lockfileVersion 1 to 2: dropped the name@version entries of `dependencies`, which `packages` already covers"
+            ]
+        );
+
+        // Without legacy entries there is nothing to report.
+        let (lockfile, migrations) = PacmLock::parse(
+            r#"{ "lockfileVersion": 1, "workspaces": {}, "packages": {}, "dependencies": {} }"#,
+        )
+        .unwrap();
+        assert_eq!(lockfile.lockfile_version, LOCKFILE_VERSION);
+        assert!(migrations.is_empty());
+    }
+
+    #[test]
+    fn test_current_version_loads_as_is_and_newer_versions_fail() {
+        let current = serde_json::to_string(&PacmLock::default()).unwrap();
+        let (lockfile, migrations) = PacmLock::parse(&current).unwrap();
+        assert_eq!(lockfile.lockfile_version, LOCKFILE_VERSION);
+        assert!(migrations.is_empty());
+
+        let newer = current.replace(
+            &format!("\"lockfileVersion\":{LOCKFILE_VERSION}"),
+            &format!("\"lockfileVersion\":{}", LOCKFILE_VERSION + 1),
+        );
+        let error = PacmLock::parse(&newer).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("lockfile was written by a newer pacm"),
+            "{error}"
+        );
+
+        let unknown = current.replace(
+            &format!("\"lockfileVersion\":{LOCKFILE_VERSION}"),
+            "\"lockfileVersion\":0",
+        );
+        assert!(PacmLock::parse(&unknown).is_err());
+    }
+}
@@ -0,0 +1,41 @@
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// Whether output is colored, from `--color`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Colors even when output is piped, e.g. into `less -R`.
+    Always,
+    /// Colors a terminal, unless `NO_COLOR` is set.
+    #[default]
+    Auto,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output to stdout should carry colors.
+    #[must_use]
+    pub fn enabled(self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(Self::Always),
+            "auto" => Ok(Self::Auto),
+            "never" => Ok(Self::Never),
+            other => Err(format!("unknown color choice: {other}")),
+        }
+    }
+}
@@ -1,15 +1,81 @@
 use crossterm::{ExecutableCommand, cursor, terminal};
-use owo_colors::OwoColorize;
-use std::io::{self, Write};
-use std::sync::{Arc, Mutex, OnceLock};
-use std::time::Instant;
+use owo_colors::{OwoColorize, Style};
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
 
+pub mod color;
+pub mod reporter;
+
+pub use color::ColorChoice;
+pub use reporter::{
+    GithubActionsReporter, JsonReporter, Reporter, ReporterKind, SilentReporter, Verbosity,
+};
+
+/// How many recent progress updates the ETA is averaged over.
+const ETA_SAMPLES: usize = 10;
+
+/// The default reporter: colored output with a single live progress line.
 pub struct Logger {
     start_time: Instant,
     quiet: bool,
+    color: bool,
+    /// Whether the progress line is drawn and redrawn in place, which takes a terminal
+    /// and escape codes.
+    live: bool,
     current_line: Arc<Mutex<String>>,
+    progress_rate: Mutex<ProgressRate>,
 }
 
+/// Recent `(time, completed)` samples of the running progress, for the ETA.
+#[derive(Default)]
+struct ProgressRate {
+    total: usize,
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl ProgressRate {
+    /// Records that `current` of `total` are done at `now` and estimates the time left
+    /// from the rate over the recent samples. A new total or a count going backwards
+    /// starts over; until two samples apart in both time and count exist there is no
+    /// estimate.
+    fn record(&mut self, now: Instant, current: usize, total: usize) -> Option<Duration> {
+        let restarted = self.total != total
+            || self
+                .samples
+                .back()
+                .is_some_and(|(_, completed)| current < *completed);
+        if restarted {
+            self.samples.clear();
+            self.total = total;
+        }
+
+        self.samples.push_back((now, current));
+        if self.samples.len() > ETA_SAMPLES {
+            self.samples.pop_front();
+        }
+
+        let (first_at, first_completed) = *self.samples.front()?;
+        let completed = current.checked_sub(first_completed).filter(|n| *n > 0)?;
+        let elapsed = now.duration_since(first_at);
+        if elapsed.is_zero() || current >= total {
+            return None;
+        }
+        Some(elapsed.mul_f64((total - current) as f64 / completed as f64))
+    }
+}
+
+fn format_eta(remaining: Duration) -> String {
+    let secs = remaining.as_secs().max(1);
+    if secs < 60 {
+        format!("~{secs}s remaining")
+    } else {
+        format!("~{}m {}s remaining", secs / 60, secs % 60)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogLevel {
     Info,
     Success,
@@ -19,39 +85,78 @@ pub enum LogLevel {
     Shell,
 }
 
+impl LogLevel {
+    /// Whether an active progress line is drawn again below the message. Warnings come
+    /// from tasks that keep running under it; an error ends the operation the line was
+    /// tracking, so redrawing it would leave a stale line behind.
+    fn keeps_progress(self) -> bool {
+        matches!(self, Self::Warning)
+    }
+}
+
 impl Logger {
     #[must_use]
     pub fn new(quiet: bool) -> Self {
+        let color = ColorChoice::Auto.enabled();
         Self {
             start_time: Instant::now(),
             quiet,
+            color,
+            live: color && io::stdout().is_terminal(),
             current_line: Arc::new(Mutex::new(String::new())),
+            progress_rate: Mutex::new(ProgressRate::default()),
         }
     }
 
-    fn clear_current_line(&self) {
-        if self.quiet {
-            return;
+    /// Colors output only when `color` is set. Defaults to `--color=auto`. Without colors
+    /// the progress line is not drawn either, so no escape codes reach the output.
+    #[must_use]
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self.live = color && io::stdout().is_terminal();
+        self
+    }
+
+    /// `text` in `style`, or as it is when colors are off.
+    fn paint(&self, text: impl std::fmt::Display, style: Style) -> String {
+        if self.color {
+            text.style(style).to_string()
+        } else {
+            text.to_string()
         }
+    }
 
-        let mut stdout = io::stdout();
-        let _ = stdout.execute(cursor::MoveToColumn(0));
-        let _ = stdout.execute(terminal::Clear(terminal::ClearType::CurrentLine));
+    /// Runs `f` with exclusive access to the terminal. Every write goes through here so
+    /// parallel tasks cannot interleave with the progress line.
+    fn with_terminal(&self, f: impl FnOnce(&mut String, &mut io::StdoutLock<'_>)) {
+        let mut line = self
+            .current_line
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut stdout = io::stdout().lock();
+        f(&mut line, &mut stdout);
         let _ = stdout.flush();
     }
 
+    fn clear_current_line(&self, stdout: &mut io::StdoutLock<'_>) {
+        if self.live {
+            let _ = stdout.execute(cursor::MoveToColumn(0));
+            let _ = stdout.execute(terminal::Clear(terminal::ClearType::CurrentLine));
+        }
+    }
+
+    /// Replaces the progress line with `message`. Only drawn on a live terminal, since
+    /// elsewhere the updates would pile up on one line.
     pub fn update_line(&self, message: &str) {
-        if self.quiet {
+        if self.quiet || !self.live {
             return;
         }
 
-        self.clear_current_line();
-        print!("{message}");
-        let _ = io::stdout().flush();
-
-        if let Ok(mut line) = self.current_line.lock() {
+        self.with_terminal(|line, stdout| {
+            self.clear_current_line(stdout);
+            let _ = write!(stdout, "{message}");
             *line = message.to_string();
-        }
+        });
     }
 
     pub fn finish_line(&self, message: &str) {
@@ -59,12 +164,11 @@ impl Logger {
             return;
         }
 
-        self.clear_current_line();
-        println!("{message}");
-
-        if let Ok(mut line) = self.current_line.lock() {
+        self.with_terminal(|line, stdout| {
+            self.clear_current_line(stdout);
+            let _ = writeln!(stdout, "{message}");
             line.clear();
-        }
+        });
     }
 
     pub fn log(&self, level: LogLevel, message: &str) {
@@ -72,40 +176,55 @@ impl Logger {
             return;
         }
 
-        self.clear_current_line();
+        let keep_progress = level.keeps_progress() && !self.quiet;
 
-        let (prefix, colored_message) = match level {
+        let (prefix, prefix_style, message_style) = match level {
             LogLevel::Info => (
-                "pacm".bright_cyan().bold().to_string(),
-                message.white().to_string(),
+                "pacm",
+                Style::new().bright_cyan().bold(),
+                Style::new().white(),
             ),
             LogLevel::Success => (
-                "✓".bright_green().bold().to_string(),
-                message.bright_green().to_string(),
+                "✓",
+                Style::new().bright_green().bold(),
+                Style::new().bright_green(),
             ),
             LogLevel::Warning => (
-                "⚠".bright_yellow().bold().to_string(),
-                message.bright_yellow().to_string(),
+                "⚠",
+                Style::new().bright_yellow().bold(),
+                Style::new().bright_yellow(),
             ),
             LogLevel::Error => (
-                "✗".bright_red().bold().to_string(),
-                message.bright_red().to_string(),
+                "✗",
+                Style::new().bright_red().bold(),
+                Style::new().bright_red(),
             ),
             LogLevel::Debug => (
-                "•".bright_black().bold().to_string(),
-                message.bright_black().to_string(),
+                "•",
+                Style::new().bright_black().bold(),
+                Style::new().bright_black(),
             ),
             LogLevel::Shell => (
-                "$".bright_blue().bold().to_string(),
-                message.bright_black().to_string(),
+                "$",
+                Style::new().bright_blue().bold(),
+                Style::new().bright_black(),
             ),
         };
-
-        println!("{prefix} {colored_message}");
-
-        if let Ok(mut line) = self.current_line.lock() {
-            line.clear();
-        }
+        let prefix = self.paint(prefix, prefix_style);
+        let colored_message = self.paint(message, message_style);
+
+        self.with_terminal(|line, stdout| {
+            if !self.quiet {
+                self.clear_current_line(stdout);
+            }
+            let _ = writeln!(stdout, "{prefix} {colored_message}");
+
+            if keep_progress && self.live && !line.is_empty() {
+                let _ = write!(stdout, "{line}");
+            } else {
+                line.clear();
+            }
+        });
     }
 
     pub fn finish(&self, message: &str) {
@@ -118,9 +237,9 @@ impl Logger {
 
         let final_message = format!(
             "{} {} {}",
-            "✓".bright_green().bold(),
-            message.bright_green(),
-            format!("[{time_str}]").bright_black()
+            self.paint("✓", Style::new().bright_green().bold()),
+            self.paint(message, Style::new().bright_green()),
+            self.paint(format!("[{time_str}]"), Style::new().bright_black())
         );
 
         self.finish_line(&final_message);
@@ -134,15 +253,32 @@ impl Logger {
         let spinner = spinners.get(current % spinners.len()).unwrap_or(&"◐");
 
         let progress_text = if total > 0 {
+            let eta = self
+                .progress_rate
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .record(Instant::now(), current, total)
+                .map(|remaining| {
+                    format!(
+                        " {}",
+                        self.paint(format_eta(remaining), Style::new().bright_black())
+                    )
+                })
+                .unwrap_or_default();
             format!(
-                "{} {} ({}/{})",
-                spinner.bright_cyan(),
-                message.bright_white(),
-                current.to_string().bright_cyan().bold(),
-                total.to_string().bright_white()
+                "{} {} ({}/{}){}",
+                self.paint(spinner, Style::new().bright_cyan()),
+                self.paint(message, Style::new().bright_white()),
+                self.paint(current, Style::new().bright_cyan().bold()),
+                self.paint(total, Style::new().bright_white()),
+                eta
             )
         } else {
-            format!("{} {}", spinner.bright_cyan(), message.bright_white())
+            format!(
+                "{} {}",
+                self.paint(spinner, Style::new().bright_cyan()),
+                self.paint(message, Style::new().bright_white())
+            )
         };
 
         self.update_line(&progress_text);
@@ -153,7 +289,11 @@ impl Logger {
             return;
         }
 
-        let status_msg = format!("{} {}", "◦".bright_cyan(), message.bright_white());
+        let status_msg = format!(
+            "{} {}",
+            self.paint("◦", Style::new().bright_cyan()),
+            self.paint(message, Style::new().bright_white())
+        );
         self.update_line(&status_msg);
     }
 
@@ -182,60 +322,184 @@ impl Logger {
     pub fn shell(&self, command: &str) {
         self.log(LogLevel::Shell, command);
     }
+
+    /// A script name with its command dimmed on the line below, the way `npm run` lists
+    /// them.
+    pub fn script(&self, name: &str, command: &str) {
+        if self.quiet {
+            return;
+        }
+
+        let entry = format!(
+            "  {}\n    {}",
+            self.paint(name, Style::new().bright_cyan().bold()),
+            self.paint(command, Style::new().bright_black())
+        );
+        self.finish_line(&entry);
+    }
 }
 
-static LOGGER: OnceLock<Logger> = OnceLock::new();
+impl Reporter for Logger {
+    fn log(&self, level: LogLevel, message: &str) {
+        Logger::log(self, level, message);
+    }
+
+    fn status(&self, message: &str) {
+        Logger::status(self, message);
+    }
+
+    fn progress(&self, message: &str, current: usize, total: usize) {
+        Logger::progress(self, message, current, total);
+    }
+
+    fn finish(&self, message: &str) {
+        Logger::finish(self, message);
+    }
+
+    fn script(&self, name: &str, command: &str) {
+        Logger::script(self, name, command);
+    }
+}
+
+static REPORTER: OnceLock<Box<dyn Reporter>> = OnceLock::new();
 
 pub fn init_logger(quiet: bool) {
-    let _ = LOGGER.set(Logger::new(quiet));
+    let verbosity = if quiet {
+        Verbosity::Quiet
+    } else {
+        Verbosity::Normal
+    };
+    init_reporter(ReporterKind::Pretty, verbosity, ColorChoice::Auto);
 }
 
-fn get_logger() -> &'static Logger {
-    LOGGER
-        .get()
-        .unwrap_or_else(|| panic!("Logger not initialized. Call init_logger() first."))
+/// Selects how output is written for the rest of the process. Only the first call has
+/// an effect.
+pub fn init_reporter(kind: ReporterKind, verbosity: Verbosity, color: ColorChoice) {
+    if REPORTER.set(kind.build(verbosity, color)).is_ok() {
+        // Output styled outside the reporter follows the same choice.
+        owo_colors::set_override(color.enabled());
+    }
 }
 
-pub fn update_line(message: &str) {
-    get_logger().update_line(message);
+fn get_reporter() -> &'static dyn Reporter {
+    REPORTER
+        .get()
+        .map(Box::as_ref)
+        .unwrap_or_else(|| panic!("Logger not initialized. Call init_logger() first."))
 }
 
 pub fn status(message: &str) {
-    get_logger().status(message);
+    get_reporter().status(message);
 }
 
 pub fn info(message: &str) {
-    get_logger().info(message);
+    get_reporter().log(LogLevel::Info, message);
 }
 
 pub fn success(message: &str) {
-    get_logger().success(message);
+    get_reporter().log(LogLevel::Success, message);
 }
 
 pub fn warn(message: &str) {
-    get_logger().warn(message);
+    get_reporter().log(LogLevel::Warning, message);
 }
 
 pub fn error(message: &str) {
-    get_logger().error(message);
+    get_reporter().log(LogLevel::Error, message);
 }
 
 pub fn debug(message: &str, debug_enabled: bool) {
-    get_logger().debug(message, debug_enabled);
+    if debug_enabled {
+        get_reporter().log(LogLevel::Debug, message);
+    }
 }
 
 pub fn shell(command: &str) {
-    get_logger().shell(command);
+    get_reporter().log(LogLevel::Shell, command);
 }
 
 pub fn progress(message: &str, current: usize, total: usize) {
-    get_logger().progress(message, current, total);
+    get_reporter().progress(message, current, total);
 }
 
 pub fn finish(message: &str) {
-    get_logger().finish(message);
+    get_reporter().finish(message);
+}
+
+pub fn resolve_start(count: usize) {
+    get_reporter().resolve_start(count);
+}
+
+pub fn resolve_done(count: usize) {
+    get_reporter().resolve_done(count);
 }
 
-pub fn finish_line(message: &str) {
-    get_logger().finish_line(message);
+pub fn download_progress(current: usize, total: usize) {
+    get_reporter().download_progress(current, total);
+}
+
+pub fn package_installed(name: &str, version: &str) {
+    get_reporter().package_installed(name, version);
+}
+
+pub fn script(name: &str, command: &str) {
+    get_reporter().script(name, command);
+}
+
+pub fn timing(phases: &[(&str, Duration)], total: Duration) {
+    get_reporter().timing(phases, total);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta_uses_recent_rate_and_waits_for_samples() {
+        let start = Instant::now();
+        let mut rate = ProgressRate::default();
+
+        assert_eq!(rate.record(start, 0, 100), None);
+        assert_eq!(
+            rate.record(start + Duration::from_secs(2), 10, 100),
+            Some(Duration::from_secs(18))
+        );
+        assert_eq!(
+            rate.record(start + Duration::from_secs(4), 20, 100),
+            Some(Duration::from_secs(16))
+        );
+
+        // A new progress run does not reuse the old samples.
+        assert_eq!(rate.record(start + Duration::from_secs(5), 1, 10), None);
+        assert_eq!(rate.record(start + Duration::from_secs(5), 10, 10), None);
+
+        assert_eq!(format_eta(Duration::from_millis(300)), "~1s remaining");
+        assert_eq!(format_eta(Duration::from_secs(125)), "~2m 5s remaining");
+    }
+
+    #[test]
+    fn test_only_warnings_keep_the_progress_line() {
+        assert!(LogLevel::Warning.keeps_progress());
+        assert!(!LogLevel::Error.keeps_progress());
+        assert!(!LogLevel::Info.keeps_progress());
+    }
+
+    #[test]
+    fn test_color_choice_controls_styling() {
+        let style = Style::new().bright_green();
+        assert_eq!(
+            Logger::new(false).with_color(false).paint("done", style),
+            "done"
+        );
+        assert_eq!(
+            Logger::new(false).with_color(true).paint("done", style),
+            "\x1b[92mdone\x1b[0m"
+        );
+
+        assert_eq!("always".parse(), Ok(ColorChoice::Always));
+        assert_eq!("never".parse(), Ok(ColorChoice::Never));
+        assert!(ColorChoice::Always.enabled());
+        assert!(!ColorChoice::Never.enabled());
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+    }
 }
@@ -0,0 +1,341 @@
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+use crate::{ColorChoice, LogLevel, Logger};
+
+/// Receives everything pacm wants to tell the user. Install logic only reports what
+/// happened; the reporter decides what that looks like. The lifecycle events default to
+/// plain status updates, so a reporter only needs the four required methods.
+pub trait Reporter: Send + Sync {
+    fn log(&self, level: LogLevel, message: &str);
+
+    /// A transient update about the current step, replaced by the next one.
+    fn status(&self, message: &str);
+
+    fn progress(&self, message: &str, current: usize, total: usize);
+
+    /// The command is done; `message` summarizes the outcome.
+    fn finish(&self, message: &str);
+
+    fn resolve_start(&self, count: usize) {
+        self.status(&format!("Resolving {count} dependencies..."));
+    }
+
+    fn resolve_done(&self, count: usize) {
+        self.status(&format!("Resolved {count} packages"));
+    }
+
+    fn download_progress(&self, current: usize, total: usize) {
+        self.progress("Downloading packages", current, total);
+    }
+
+    fn package_installed(&self, _name: &str, _version: &str) {}
+
+    /// One `package.json` script, for `pacm run` without a script name.
+    fn script(&self, name: &str, command: &str) {
+        self.log(LogLevel::Info, &format!("{name}: {command}"));
+    }
+
+    /// How long each phase of an install took, for `--timing`.
+    fn timing(&self, phases: &[(&str, Duration)], total: Duration) {
+        for line in timing_table(phases, total) {
+            self.log(LogLevel::Info, &line);
+        }
+    }
+}
+
+/// The rows of the `--timing` table: one per phase with its share of `total`, then the
+/// total itself.
+pub fn timing_table(phases: &[(&str, Duration)], total: Duration) -> Vec<String> {
+    let width = phases
+        .iter()
+        .map(|(label, _)| label.len())
+        .chain(["total".len()])
+        .max()
+        .unwrap_or_default();
+    let share = |elapsed: Duration| {
+        if total.is_zero() {
+            0.0
+        } else {
+            elapsed.as_secs_f64() / total.as_secs_f64() * 100.0
+        }
+    };
+
+    let mut rows: Vec<String> = phases
+        .iter()
+        .map(|(label, elapsed)| {
+            format!(
+                "{label:<width$}  {:>9.1}ms  {:>5.1}%",
+                elapsed.as_secs_f64() * 1000.0,
+                share(*elapsed)
+            )
+        })
+        .collect();
+    rows.push(format!(
+        "{:<width$}  {:>9.1}ms",
+        "total",
+        total.as_secs_f64() * 1000.0
+    ));
+    rows
+}
+
+/// The reporters selectable with `--reporter`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReporterKind {
+    #[default]
+    Pretty,
+    Json,
+    GithubActions,
+}
+
+/// How much a reporter prints, from `--quiet` and `--silent`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    #[default]
+    Normal,
+    /// Only errors.
+    Quiet,
+    /// Nothing at all; the exit code tells how the command went.
+    Silent,
+}
+
+impl ReporterKind {
+    /// The reporter of this kind. Only the pretty one colors its output.
+    pub fn build(self, verbosity: Verbosity, color: ColorChoice) -> Box<dyn Reporter> {
+        let quiet = verbosity == Verbosity::Quiet;
+        match self {
+            _ if verbosity == Verbosity::Silent => Box::new(SilentReporter),
+            Self::Pretty => Box::new(Logger::new(quiet).with_color(color.enabled())),
+            Self::Json => Box::new(JsonReporter::new(quiet)),
+            Self::GithubActions => Box::new(GithubActionsReporter::new(quiet)),
+        }
+    }
+}
+
+impl FromStr for ReporterKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            "github" | "github-actions" => Ok(Self::GithubActions),
+            other => Err(format!("unknown reporter: {other}")),
+        }
+    }
+}
+
+fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Info => "info",
+        LogLevel::Success => "success",
+        LogLevel::Warning => "warning",
+        LogLevel::Error => "error",
+        LogLevel::Debug => "debug",
+        LogLevel::Shell => "shell",
+    }
+}
+
+/// Writes one JSON object per line, each with an `event` field, for tools that consume
+/// pacm's output.
+pub struct JsonReporter {
+    start_time: Instant,
+    quiet: bool,
+}
+
+impl JsonReporter {
+    #[must_use]
+    pub fn new(quiet: bool) -> Self {
+        Self {
+            start_time: Instant::now(),
+            quiet,
+        }
+    }
+
+    fn emit(&self, event: &Value) {
+        if !self.quiet || event["level"] == "error" {
+            println!("{event}");
+        }
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn log(&self, level: LogLevel, message: &str) {
+        self.emit(&json!({ "event": "log", "level": level_name(level), "message": message }));
+    }
+
+    fn status(&self, message: &str) {
+        self.emit(&json!({ "event": "status", "message": message }));
+    }
+
+    fn progress(&self, message: &str, current: usize, total: usize) {
+        self.emit(&json!({
+            "event": "progress",
+            "message": message,
+            "current": current,
+            "total": total,
+        }));
+    }
+
+    fn finish(&self, message: &str) {
+        self.emit(&json!({
+            "event": "finished",
+            "message": message,
+            "elapsed_ms": self.start_time.elapsed().as_millis() as u64,
+        }));
+    }
+
+    fn resolve_start(&self, count: usize) {
+        self.emit(&json!({ "event": "resolve_start", "count": count }));
+    }
+
+    fn resolve_done(&self, count: usize) {
+        self.emit(&json!({ "event": "resolve_done", "count": count }));
+    }
+
+    fn download_progress(&self, current: usize, total: usize) {
+        self.emit(&json!({ "event": "download_progress", "current": current, "total": total }));
+    }
+
+    fn package_installed(&self, name: &str, version: &str) {
+        self.emit(&json!({ "event": "package_installed", "name": name, "version": version }));
+    }
+
+    fn script(&self, name: &str, command: &str) {
+        self.emit(&json!({ "event": "script", "name": name, "command": command }));
+    }
+
+    fn timing(&self, phases: &[(&str, Duration)], total: Duration) {
+        let phases: serde_json::Map<String, Value> = phases
+            .iter()
+            .map(|(label, elapsed)| (label.to_string(), json!(elapsed.as_millis() as u64)))
+            .collect();
+        self.emit(&json!({
+            "event": "timing",
+            "phases_ms": phases,
+            "total_ms": total.as_millis() as u64,
+        }));
+    }
+}
+
+/// Plain output for GitHub Actions logs. Warnings and errors become workflow commands,
+/// so they show up as annotations on the run; progress updates are dropped because the
+/// log cannot redraw a line.
+pub struct GithubActionsReporter {
+    start_time: Instant,
+    quiet: bool,
+}
+
+impl GithubActionsReporter {
+    #[must_use]
+    pub fn new(quiet: bool) -> Self {
+        Self {
+            start_time: Instant::now(),
+            quiet,
+        }
+    }
+
+    fn format(level: LogLevel, message: &str) -> String {
+        // Workflow commands end at the first newline unless it is escaped.
+        let escaped = || {
+            message
+                .replace('%', "%25")
+                .replace('\r', "%0D")
+                .replace('\n', "%0A")
+        };
+        match level {
+            LogLevel::Warning => format!("::warning::{}", escaped()),
+            LogLevel::Error => format!("::error::{}", escaped()),
+            LogLevel::Debug => format!("::debug::{}", escaped()),
+            LogLevel::Shell => format!("$ {message}"),
+            LogLevel::Info | LogLevel::Success => message.to_string(),
+        }
+    }
+}
+
+impl Reporter for GithubActionsReporter {
+    fn log(&self, level: LogLevel, message: &str) {
+        if !self.quiet || level == LogLevel::Error {
+            println!("{}", Self::format(level, message));
+        }
+    }
+
+    fn status(&self, _message: &str) {}
+
+    fn progress(&self, _message: &str, _current: usize, _total: usize) {}
+
+    fn finish(&self, message: &str) {
+        if !self.quiet {
+            println!(
+                "{message} [{:.2}s]",
+                self.start_time.elapsed().as_secs_f64()
+            );
+        }
+    }
+
+    fn resolve_done(&self, count: usize) {
+        if !self.quiet {
+            println!("Resolved {count} packages");
+        }
+    }
+}
+
+/// Prints nothing, for `--silent`.
+pub struct SilentReporter;
+
+impl Reporter for SilentReporter {
+    fn log(&self, _level: LogLevel, _message: &str) {}
+
+    fn status(&self, _message: &str) {}
+
+    fn progress(&self, _message: &str, _current: usize, _total: usize) {}
+
+    fn finish(&self, _message: &str) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_annotations_escape_multiline_messages() {
+        assert_eq!(
+            GithubActionsReporter::format(LogLevel::Warning, "left-pad is deprecated\n100% sure"),
+            "::warning::left-pad is deprecated%0A100%25 sure"
+        );
+        assert_eq!(
+            GithubActionsReporter::format(LogLevel::Error, "install failed"),
+            "::error::install failed"
+        );
+        assert_eq!(
+            GithubActionsReporter::format(LogLevel::Info, "50% done"),
+            "50% done"
+        );
+        assert_eq!(
+            "github".parse::<ReporterKind>(),
+            Ok(ReporterKind::GithubActions)
+        );
+        assert!("teamcity".parse::<ReporterKind>().is_err());
+    }
+
+    #[test]
+    fn test_timing_table_aligns_phases_and_shares() {
+        let rows = timing_table(
+            &[
+                ("resolution", Duration::from_millis(150)),
+                ("linking", Duration::from_millis(50)),
+            ],
+            Duration::from_millis(200),
+        );
+        assert_eq!(
+            rows,
+            vec![
+                "resolution      150.0ms   75.0%",
+                "linking          50.0ms   25.0%",
+                "total           200.0ms",
+            ]
+        );
+    }
+}
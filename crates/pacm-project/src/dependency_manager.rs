@@ -11,81 +11,438 @@ impl DependencyManager {
         dep_type: DependencyType,
         save_exact: bool,
     ) {
-        let version_string = if save_exact {
-            version.to_string()
-        } else if version.starts_with('^') || version.starts_with('~') || version.contains('-') {
+        let version_string = if save_exact
+            || version.starts_with('^')
+            || version.starts_with('~')
+            || version.contains('-')
+        {
             version.to_string()
         } else {
             format!("^{version}")
         };
 
-        Self::remove_dep(package_json, name);
+        Self::remove_ranges(package_json, name);
+
+        // A bundled package keeps its range in `dependencies`.
+        let range_type = if dep_type == DependencyType::Bundled {
+            DependencyType::Dependencies
+        } else {
+            dep_type
+        };
+        if let Some(section) = Self::section_mut(package_json, range_type) {
+            section
+                .get_or_insert_with(IndexMap::new)
+                .insert(name.to_string(), version_string);
+        }
+        if dep_type == DependencyType::Bundled {
+            Self::push_bundled(package_json, name);
+        }
+    }
+
+    /// Moves `name` into `dep_type`, keeping its current version range.
+    /// Returns the section it was moved out of, or `None` if nothing changed.
+    pub fn move_dep(
+        package_json: &mut PackageJson,
+        name: &str,
+        dep_type: DependencyType,
+    ) -> Option<DependencyType> {
+        let current_type = Self::has_dep(package_json, name)?;
+        if current_type == dep_type {
+            return None;
+        }
+
+        let version =
+            Self::section(package_json, current_type).and_then(|deps| deps.get(name).cloned())?;
+
+        Self::add_dep(package_json, name, &version, dep_type, true);
 
+        Some(current_type)
+    }
+
+    /// The name-to-range map of `dep_type`. `bundledDependencies` has none, since it only
+    /// lists names.
+    #[must_use]
+    pub fn section(
+        package_json: &PackageJson,
+        dep_type: DependencyType,
+    ) -> Option<&IndexMap<String, String>> {
         match dep_type {
-            DependencyType::Dependencies => {
-                package_json
-                    .dependencies
-                    .get_or_insert_with(IndexMap::new)
-                    .insert(name.to_string(), version_string);
+            DependencyType::Dependencies => package_json.dependencies.as_ref(),
+            DependencyType::DevDependencies => package_json.dev_dependencies.as_ref(),
+            DependencyType::PeerDependencies => package_json.peer_dependencies.as_ref(),
+            DependencyType::OptionalDependencies => package_json.optional_dependencies.as_ref(),
+            DependencyType::Bundled => None,
+        }
+    }
+
+    fn section_mut(
+        package_json: &mut PackageJson,
+        dep_type: DependencyType,
+    ) -> Option<&mut Option<IndexMap<String, String>>> {
+        match dep_type {
+            DependencyType::Dependencies => Some(&mut package_json.dependencies),
+            DependencyType::DevDependencies => Some(&mut package_json.dev_dependencies),
+            DependencyType::PeerDependencies => Some(&mut package_json.peer_dependencies),
+            DependencyType::OptionalDependencies => Some(&mut package_json.optional_dependencies),
+            DependencyType::Bundled => None,
+        }
+    }
+
+    /// The names listed in `dep_type`, in file order.
+    #[must_use]
+    pub fn names(package_json: &PackageJson, dep_type: DependencyType) -> Vec<String> {
+        match dep_type {
+            DependencyType::Bundled => Self::bundled(package_json),
+            _ => Self::section(package_json, dep_type)
+                .map(|deps| deps.keys().cloned().collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    #[must_use]
+    pub fn contains(package_json: &PackageJson, name: &str, dep_type: DependencyType) -> bool {
+        match dep_type {
+            DependencyType::Bundled => Self::bundled(package_json)
+                .iter()
+                .any(|bundled_name| bundled_name == name),
+            _ => Self::section(package_json, dep_type).is_some_and(|deps| deps.contains_key(name)),
+        }
+    }
+
+    /// Drops every dependency section that has no entries left.
+    pub fn clear_empty(package_json: &mut PackageJson) {
+        for dep_type in DependencyType::ALL {
+            Self::clear_if_empty(package_json, dep_type);
+        }
+    }
+
+    fn clear_if_empty(package_json: &mut PackageJson, dep_type: DependencyType) {
+        match Self::section_mut(package_json, dep_type) {
+            Some(section) => {
+                if section.as_ref().is_some_and(IndexMap::is_empty) {
+                    *section = None;
+                }
             }
-            DependencyType::DevDependencies => {
-                package_json
-                    .dev_dependencies
-                    .get_or_insert_with(IndexMap::new)
-                    .insert(name.to_string(), version_string);
+            // `bundleDependencies: true` bundles everything, so only an empty list goes.
+            None => {
+                for key in ["bundledDependencies", "bundleDependencies"] {
+                    if package_json
+                        .other
+                        .get(key)
+                        .and_then(serde_json::Value::as_array)
+                        .is_some_and(Vec::is_empty)
+                    {
+                        package_json.other.shift_remove(key);
+                    }
+                }
             }
-            DependencyType::PeerDependencies => {
-                package_json
-                    .peer_dependencies
-                    .get_or_insert_with(IndexMap::new)
-                    .insert(name.to_string(), version_string);
+        }
+    }
+
+    /// Removes `name` from every section, `bundledDependencies` included.
+    pub fn remove_dep(package_json: &mut PackageJson, name: &str) {
+        for dep_type in DependencyType::ALL {
+            Self::remove_from(package_json, name, dep_type);
+        }
+    }
+
+    /// Removes `name` from `dep_type` only, dropping the section once it is empty.
+    pub fn remove_from(package_json: &mut PackageJson, name: &str, dep_type: DependencyType) {
+        match Self::section_mut(package_json, dep_type) {
+            Some(section) => {
+                if let Some(deps) = section {
+                    deps.shift_remove(name);
+                }
+                Self::clear_if_empty(package_json, dep_type);
             }
-            DependencyType::OptionalDependencies => {
-                package_json
-                    .optional_dependencies
-                    .get_or_insert_with(IndexMap::new)
-                    .insert(name.to_string(), version_string);
+            None => Self::remove_bundled(package_json, name),
+        }
+    }
+
+    /// Removes the range of `name` while leaving it bundled, for when it is saved again.
+    fn remove_ranges(package_json: &mut PackageJson, name: &str) {
+        for dep_type in DependencyType::ALL {
+            if dep_type != DependencyType::Bundled {
+                Self::remove_from(package_json, name, dep_type);
             }
         }
     }
 
-    pub fn remove_dep(package_json: &mut PackageJson, name: &str) {
-        if let Some(deps) = &mut package_json.dependencies {
-            deps.shift_remove(name);
+    /// Adds `name` to `bundledDependencies`. Bundled packages ship inside the published
+    /// tarball, so the name must also be declared in one of the dependency sections.
+    pub fn add_bundled(package_json: &mut PackageJson, name: &str) -> Result<(), String> {
+        if Self::has_dep(package_json, name).is_none() {
+            return Err(format!(
+                "Cannot bundle '{name}': it is not listed in any dependencies section"
+            ));
         }
-        if let Some(dev_deps) = &mut package_json.dev_dependencies {
-            dev_deps.shift_remove(name);
+
+        Self::push_bundled(package_json, name);
+        Ok(())
+    }
+
+    fn push_bundled(package_json: &mut PackageJson, name: &str) {
+        let mut bundled = Self::bundled(package_json);
+        if !bundled.iter().any(|bundled_name| bundled_name == name) {
+            bundled.push(name.to_string());
         }
-        if let Some(peer_deps) = &mut package_json.peer_dependencies {
-            peer_deps.shift_remove(name);
+        Self::set_bundled(package_json, bundled);
+    }
+
+    pub fn remove_bundled(package_json: &mut PackageJson, name: &str) {
+        let mut bundled = Self::bundled(package_json);
+        if let Some(index) = bundled.iter().position(|bundled_name| bundled_name == name) {
+            bundled.remove(index);
+            Self::set_bundled(package_json, bundled);
         }
-        if let Some(opt_deps) = &mut package_json.optional_dependencies {
-            opt_deps.shift_remove(name);
+    }
+
+    /// The names in `bundledDependencies`, or the older `bundleDependencies` spelling.
+    #[must_use]
+    pub fn bundled(package_json: &PackageJson) -> Vec<String> {
+        package_json
+            .other
+            .get("bundledDependencies")
+            .or_else(|| package_json.other.get("bundleDependencies"))
+            .and_then(serde_json::Value::as_array)
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(|name| name.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Writes `bundled` back under the spelling the file already uses, dropping the field
+    /// once it is empty.
+    fn set_bundled(package_json: &mut PackageJson, bundled: Vec<String>) {
+        let key = if package_json.other.contains_key("bundleDependencies") {
+            "bundleDependencies"
+        } else {
+            "bundledDependencies"
+        };
+        if bundled.is_empty() {
+            package_json.other.shift_remove(key);
+        } else {
+            package_json
+                .other
+                .insert(key.to_string(), serde_json::Value::from(bundled));
         }
     }
 
+    /// The section declaring `name`. Sections holding a range are checked first, so a
+    /// bundled package is reported by where its range lives.
     #[must_use]
     pub fn has_dep(package_json: &PackageJson, name: &str) -> Option<DependencyType> {
-        if let Some(deps) = &package_json.dependencies {
-            if deps.contains_key(name) {
-                return Some(DependencyType::Dependencies);
-            }
-        }
-        if let Some(dev_deps) = &package_json.dev_dependencies {
-            if dev_deps.contains_key(name) {
-                return Some(DependencyType::DevDependencies);
-            }
-        }
-        if let Some(peer_deps) = &package_json.peer_dependencies {
-            if peer_deps.contains_key(name) {
-                return Some(DependencyType::PeerDependencies);
+        DependencyType::ALL
+            .into_iter()
+            .find(|dep_type| Self::contains(package_json, name, *dep_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGED_TYPES: [DependencyType; 4] = [
+        DependencyType::Dependencies,
+        DependencyType::DevDependencies,
+        DependencyType::PeerDependencies,
+        DependencyType::OptionalDependencies,
+    ];
+
+    fn package_json_with(name: &str, range: &str, dep_type: DependencyType) -> PackageJson {
+        let mut pkg: PackageJson = serde_json::from_str(r#"{"name": "app"}"#).unwrap();
+        DependencyManager::add_dep(
+            &mut pkg,
+            "keep",
+            "^1.0.0",
+            DependencyType::Dependencies,
+            true,
+        );
+        DependencyManager::add_dep(&mut pkg, name, range, dep_type, true);
+        pkg
+    }
+
+    fn sections_containing(pkg: &PackageJson, name: &str) -> Vec<DependencyType> {
+        DependencyType::ALL
+            .into_iter()
+            .filter(|dep_type| DependencyManager::contains(pkg, name, *dep_type))
+            .collect()
+    }
+
+    #[test]
+    fn test_move_dep_every_direction() {
+        for from in RANGED_TYPES {
+            for to in RANGED_TYPES {
+                if from == to {
+                    continue;
+                }
+
+                let mut pkg = package_json_with("lodash", "~4.17.0", from);
+                let moved = DependencyManager::move_dep(&mut pkg, "lodash", to);
+
+                assert_eq!(moved, Some(from), "{from:?} -> {to:?}");
+                assert_eq!(
+                    sections_containing(&pkg, "lodash"),
+                    vec![to],
+                    "{from:?} -> {to:?}"
+                );
+                assert_eq!(
+                    DependencyManager::section(&pkg, to).and_then(|deps| deps.get("lodash")),
+                    Some(&"~4.17.0".to_string()),
+                    "{from:?} -> {to:?}"
+                );
+                assert_eq!(
+                    DependencyManager::has_dep(&pkg, "keep"),
+                    Some(DependencyType::Dependencies)
+                );
             }
         }
-        if let Some(opt_deps) = &package_json.optional_dependencies {
-            if opt_deps.contains_key(name) {
-                return Some(DependencyType::OptionalDependencies);
-            }
+    }
+
+    #[test]
+    fn test_move_dep_same_section_is_noop() {
+        let mut pkg = package_json_with("lodash", "^4.17.21", DependencyType::DevDependencies);
+        assert_eq!(
+            DependencyManager::move_dep(&mut pkg, "lodash", DependencyType::DevDependencies),
+            None
+        );
+        assert_eq!(
+            sections_containing(&pkg, "lodash"),
+            vec![DependencyType::DevDependencies]
+        );
+    }
+
+    #[test]
+    fn test_move_dep_missing_package() {
+        let mut pkg = package_json_with("lodash", "^4.17.21", DependencyType::Dependencies);
+        assert_eq!(
+            DependencyManager::move_dep(&mut pkg, "react", DependencyType::DevDependencies),
+            None
+        );
+        assert!(pkg.dev_dependencies.is_none());
+    }
+
+    #[test]
+    fn test_move_dep_drops_emptied_section() {
+        let mut pkg = package_json_with("lodash", "^4.17.21", DependencyType::PeerDependencies);
+        DependencyManager::move_dep(&mut pkg, "lodash", DependencyType::DevDependencies);
+        assert!(pkg.peer_dependencies.is_none());
+    }
+
+    #[test]
+    fn test_bundled_requires_declared_dependency() {
+        let mut pkg = package_json_with("lodash", "^4.17.21", DependencyType::Dependencies);
+
+        assert!(DependencyManager::add_bundled(&mut pkg, "react").is_err());
+        assert!(!pkg.other.contains_key("bundledDependencies"));
+
+        DependencyManager::add_bundled(&mut pkg, "lodash").unwrap();
+        DependencyManager::add_bundled(&mut pkg, "keep").unwrap();
+        DependencyManager::add_bundled(&mut pkg, "lodash").unwrap();
+        assert_eq!(DependencyManager::bundled(&pkg), vec!["lodash", "keep"]);
+
+        DependencyManager::remove_bundled(&mut pkg, "lodash");
+        DependencyManager::remove_bundled(&mut pkg, "keep");
+        assert!(!pkg.other.contains_key("bundledDependencies"));
+    }
+
+    #[test]
+    fn test_round_trip_every_type() {
+        for dep_type in DependencyType::ALL {
+            let pkg = package_json_with("lodash", "^4.17.21", dep_type);
+            let written = serde_json::to_string(&pkg).unwrap();
+            let mut pkg: PackageJson = serde_json::from_str(&written).unwrap();
+
+            assert!(
+                written.contains(dep_type.as_str()),
+                "{dep_type:?}: {written}"
+            );
+            assert!(
+                DependencyManager::contains(&pkg, "lodash", dep_type),
+                "{dep_type:?}"
+            );
+            let range_type = DependencyManager::has_dep(&pkg, "lodash").unwrap();
+            assert_eq!(
+                DependencyManager::section(&pkg, range_type).and_then(|deps| deps.get("lodash")),
+                Some(&"^4.17.21".to_string()),
+                "{dep_type:?}"
+            );
+
+            DependencyManager::remove_dep(&mut pkg, "lodash");
+            assert_eq!(
+                DependencyManager::has_dep(&pkg, "lodash"),
+                None,
+                "{dep_type:?}"
+            );
+            assert_eq!(
+                DependencyManager::names(&pkg, DependencyType::Dependencies),
+                vec!["keep"]
+            );
+            let written = serde_json::to_string(&pkg).unwrap();
+            assert!(
+                dep_type == DependencyType::Dependencies || !written.contains(dep_type.as_str()),
+                "{dep_type:?}: {written}"
+            );
         }
-        None
+    }
+
+    #[test]
+    fn test_bundled_dep_keeps_range_in_dependencies() {
+        let mut pkg = package_json_with("lodash", "^4.17.21", DependencyType::DevDependencies);
+        DependencyManager::add_dep(
+            &mut pkg,
+            "lodash",
+            "4.17.21",
+            DependencyType::Bundled,
+            false,
+        );
+
+        assert_eq!(
+            sections_containing(&pkg, "lodash"),
+            vec![DependencyType::Dependencies, DependencyType::Bundled]
+        );
+        assert!(pkg.dev_dependencies.is_none());
+
+        // Saving the range again leaves the package bundled.
+        DependencyManager::add_dep(
+            &mut pkg,
+            "lodash",
+            "^4.18.0",
+            DependencyType::Dependencies,
+            true,
+        );
+        assert_eq!(DependencyManager::bundled(&pkg), vec!["lodash"]);
+
+        DependencyManager::remove_from(&mut pkg, "lodash", DependencyType::Bundled);
+        assert_eq!(
+            sections_containing(&pkg, "lodash"),
+            vec![DependencyType::Dependencies]
+        );
+    }
+
+    #[test]
+    fn test_clear_empty_keeps_bundle_everything_flag() {
+        let mut pkg: PackageJson = serde_json::from_str(
+            r#"{"name": "app", "devDependencies": {}, "bundledDependencies": [], "bundleDependencies": true}"#,
+        )
+        .unwrap();
+        DependencyManager::clear_empty(&mut pkg);
+
+        assert!(pkg.dev_dependencies.is_none());
+        assert!(!pkg.other.contains_key("bundledDependencies"));
+        assert_eq!(
+            pkg.other.get("bundleDependencies"),
+            Some(&serde_json::Value::Bool(true))
+        );
+    }
+
+    #[test]
+    fn test_singular_section_names() {
+        assert_eq!(DependencyType::Dependencies.singular(), "dependency");
+        assert_eq!(DependencyType::DevDependencies.singular(), "devDependency");
+        assert_eq!(DependencyType::Bundled.singular(), "bundledDependency");
     }
 }
@@ -1,10 +1,16 @@
 pub mod dependency_manager;
 pub mod io;
 pub mod package_json;
+pub mod workspace;
 
 pub use dependency_manager::DependencyManager;
 pub use io::{read_package_json, write_package_json};
-pub use package_json::{DependencyType, PackageJson};
+pub use package_json::{DependencySelection, DependencyType, PackageJson};
+pub use workspace::{
+    WorkspaceMember, check_self_dependency, check_workspace_cycles, discover_workspaces,
+    find_workspace_cycle, rewrite_workspace_protocol, sort_workspaces, workspace_members_of,
+    workspace_patterns, workspace_range, workspace_waves,
+};
 
 impl PackageJson {
     pub fn add_dependency(
@@ -17,10 +23,44 @@ impl PackageJson {
         DependencyManager::add_dep(self, name, version, dep_type, save_exact);
     }
 
+    pub fn move_dependency(
+        &mut self,
+        name: &str,
+        dep_type: DependencyType,
+    ) -> Option<DependencyType> {
+        DependencyManager::move_dep(self, name, dep_type)
+    }
+
     pub fn remove_dependency(&mut self, name: &str) {
         DependencyManager::remove_dep(self, name);
     }
 
+    pub fn add_bundled_dependency(&mut self, name: &str) -> Result<(), String> {
+        DependencyManager::add_bundled(self, name)
+    }
+
+    pub fn remove_dependency_from(&mut self, name: &str, dep_type: DependencyType) {
+        DependencyManager::remove_from(self, name, dep_type);
+    }
+
+    pub fn remove_empty_dependency_sections(&mut self) {
+        DependencyManager::clear_empty(self);
+    }
+
+    #[must_use]
+    pub fn dependency_names(&self, dep_type: DependencyType) -> Vec<String> {
+        DependencyManager::names(self, dep_type)
+    }
+
+    pub fn remove_bundled_dependency(&mut self, name: &str) {
+        DependencyManager::remove_bundled(self, name);
+    }
+
+    #[must_use]
+    pub fn bundled_dependencies(&self) -> Vec<String> {
+        DependencyManager::bundled(self)
+    }
+
     #[must_use]
     pub fn has_dependency(&self, name: &str) -> Option<DependencyType> {
         DependencyManager::has_dep(self, name)
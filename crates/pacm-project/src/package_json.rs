@@ -32,32 +32,272 @@ pub struct PackageJson {
     pub other: IndexMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DependencyType {
     Dependencies,
     DevDependencies,
     PeerDependencies,
     OptionalDependencies,
+    /// `bundledDependencies`, which only lists names. Each is also declared, with its
+    /// range, in one of the other sections.
+    Bundled,
+}
+
+impl DependencyType {
+    /// Every dependency section of a `package.json`, the ones holding ranges first.
+    pub const ALL: [DependencyType; 5] = [
+        DependencyType::Dependencies,
+        DependencyType::DevDependencies,
+        DependencyType::PeerDependencies,
+        DependencyType::OptionalDependencies,
+        DependencyType::Bundled,
+    ];
+
+    /// The `package.json` field name for this section.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DependencyType::Dependencies => "dependencies",
+            DependencyType::DevDependencies => "devDependencies",
+            DependencyType::PeerDependencies => "peerDependencies",
+            DependencyType::OptionalDependencies => "optionalDependencies",
+            DependencyType::Bundled => "bundledDependencies",
+        }
+    }
+
+    /// How a single entry of this section is named in messages, e.g. `devDependency`.
+    #[must_use]
+    pub fn singular(self) -> String {
+        let section = self.as_str();
+        format!("{}y", &section[..section.len() - "ies".len()])
+    }
+}
+
+/// The dependency sections an install covers. `dependencies` are always installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DependencySelection {
+    pub dev: bool,
+    pub optional: bool,
+    pub peer: bool,
+}
+
+impl Default for DependencySelection {
+    fn default() -> Self {
+        Self {
+            dev: true,
+            optional: true,
+            peer: false,
+        }
+    }
+}
+
+impl DependencySelection {
+    /// Builds a selection from npm-style `--include` / `--omit` values (`dev`, `optional`,
+    /// `peer`, comma separated or repeated). `production` is shorthand for `--omit=dev`,
+    /// and a section named in both `--include` and `--omit` is included.
+    pub fn from_flags(
+        include: &[String],
+        omit: &[String],
+        production: bool,
+    ) -> Result<Self, String> {
+        let mut selection = Self::default();
+        if production {
+            selection.dev = false;
+        }
+        for kind in split_kinds(omit) {
+            selection.set(kind, false)?;
+        }
+        for kind in split_kinds(include) {
+            selection.set(kind, true)?;
+        }
+        Ok(selection)
+    }
+
+    fn set(&mut self, kind: &str, value: bool) -> Result<(), String> {
+        match kind {
+            "dev" => self.dev = value,
+            "optional" => self.optional = value,
+            "peer" => self.peer = value,
+            _ => {
+                return Err(format!(
+                    "Unknown dependency type '{kind}', expected dev, optional or peer"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn includes(&self, dep_type: DependencyType) -> bool {
+        match dep_type {
+            // Bundled packages are installed along with the section declaring their range.
+            DependencyType::Dependencies | DependencyType::Bundled => true,
+            DependencyType::DevDependencies => self.dev,
+            DependencyType::OptionalDependencies => self.optional,
+            DependencyType::PeerDependencies => self.peer,
+        }
+    }
+}
+
+fn split_kinds(values: &[String]) -> impl Iterator<Item = &str> {
+    values
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|kind| !kind.is_empty())
 }
 
 impl PackageJson {
+    /// Dependencies from every section in the default selection.
     #[must_use]
     pub fn get_all_dependencies(&self) -> HashMap<String, String> {
-        let mut all_deps = HashMap::new();
+        self.get_dependencies(DependencySelection::default())
+    }
 
-        if let Some(deps) = &self.dependencies {
-            all_deps.extend(deps.iter().map(|(k, v)| (k.clone(), v.clone())));
-        }
-        if let Some(dev_deps) = &self.dev_dependencies {
-            all_deps.extend(dev_deps.iter().map(|(k, v)| (k.clone(), v.clone())));
+    /// Dependencies from the sections `selection` covers. When a name appears in several
+    /// sections, `dependencies` wins over the others.
+    #[must_use]
+    pub fn get_dependencies(&self, selection: DependencySelection) -> HashMap<String, String> {
+        let sections = [
+            (DependencyType::PeerDependencies, &self.peer_dependencies),
+            (
+                DependencyType::OptionalDependencies,
+                &self.optional_dependencies,
+            ),
+            (DependencyType::DevDependencies, &self.dev_dependencies),
+            (DependencyType::Dependencies, &self.dependencies),
+        ];
+
+        let mut all_deps = HashMap::new();
+        for (dep_type, deps) in sections {
+            if let Some(deps) = deps
+                && selection.includes(dep_type)
+            {
+                all_deps.extend(deps.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
         }
 
         all_deps
     }
 
+    /// The pacm version the `packageManager` field pins, as in `"pacm@1.2.3"`, without a
+    /// corepack hash suffix (`+sha512.…`). `None` when the field is missing or names
+    /// another package manager.
+    #[must_use]
+    pub fn pinned_pacm_version(&self) -> Option<&str> {
+        let pinned = self.other.get("packageManager")?.as_str()?;
+        let version = pinned.strip_prefix("pacm@")?;
+        let version = version
+            .split_once('+')
+            .map_or(version, |(version, _)| version);
+        (!version.is_empty()).then_some(version)
+    }
+
+    /// The registry `publishConfig.registry` sends this package to instead of the
+    /// configured one, without a trailing slash. Only publishing should use it.
+    #[must_use]
+    pub fn publish_registry(&self) -> Option<&str> {
+        let registry = self.publish_config("registry")?.trim_end_matches('/');
+        (!registry.is_empty()).then_some(registry)
+    }
+
+    /// The `publishConfig.access` of the package, `public` or `restricted`.
+    #[must_use]
+    pub fn publish_access(&self) -> Option<&str> {
+        self.publish_config("access")
+            .filter(|access| matches!(*access, "public" | "restricted"))
+    }
+
+    fn publish_config(&self, key: &str) -> Option<&str> {
+        self.other.get("publishConfig")?.get(key)?.as_str()
+    }
+
     pub fn save(&self, path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
         let content = serde_json::to_string_pretty(self)?;
         std::fs::write(path, content)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_selection_from_flags() {
+        assert_eq!(
+            DependencySelection::from_flags(&[], &[], false).unwrap(),
+            DependencySelection::default()
+        );
+
+        let selection =
+            DependencySelection::from_flags(&[], &strings(&["dev,optional"]), false).unwrap();
+        assert!(!selection.dev && !selection.optional && !selection.peer);
+
+        let selection =
+            DependencySelection::from_flags(&strings(&["dev", "peer"]), &strings(&["dev"]), true)
+                .unwrap();
+        assert!(selection.dev && selection.optional && selection.peer);
+
+        assert!(DependencySelection::from_flags(&strings(&["prod"]), &[], false).is_err());
+    }
+
+    #[test]
+    fn test_get_dependencies_respects_selection() {
+        let pkg: PackageJson = serde_json::from_str(
+            r#"{
+                "dependencies": {"a": "^1.0.0"},
+                "devDependencies": {"b": "^2.0.0"},
+                "optionalDependencies": {"c": "^3.0.0"},
+                "peerDependencies": {"d": "^4.0.0"}
+            }"#,
+        )
+        .unwrap();
+
+        let mut names: Vec<_> = pkg.get_all_dependencies().into_keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["a", "b", "c"]);
+
+        let production = DependencySelection::from_flags(&[], &[], true).unwrap();
+        let mut names: Vec<_> = pkg.get_dependencies(production).into_keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["a", "c"]);
+    }
+
+    #[test]
+    fn test_pinned_pacm_version() {
+        let pinned = |field: &str| {
+            let pkg: PackageJson =
+                serde_json::from_value(serde_json::json!({ "packageManager": field })).unwrap();
+            pkg.pinned_pacm_version().map(str::to_string)
+        };
+
+        assert_eq!(pinned("pacm@1.2.3").as_deref(), Some("1.2.3"));
+        assert_eq!(pinned("pacm@1.2.3+sha512.abc").as_deref(), Some("1.2.3"));
+        assert_eq!(pinned("pnpm@9.0.0"), None);
+        assert_eq!(pinned("pacm"), None);
+    }
+
+    #[test]
+    fn test_publish_config() {
+        let pkg: PackageJson = serde_json::from_value(serde_json::json!({
+            "publishConfig": {
+                "registry": "https://npm.example.com/",
+                "access": "public"
+            }
+        }))
+        .unwrap();
+        assert_eq!(pkg.publish_registry(), Some("https://npm.example.com"));
+        assert_eq!(pkg.publish_access(), Some("public"));
+
+        let pkg: PackageJson =
+            serde_json::from_value(serde_json::json!({ "publishConfig": { "access": "open" } }))
+                .unwrap();
+        assert_eq!(pkg.publish_registry(), None);
+        assert_eq!(pkg.publish_access(), None);
+    }
+}
@@ -0,0 +1,565 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use pacm_error::PackageManagerError;
+use pacm_utils::glob_match;
+
+use crate::io::read_package_json;
+use crate::package_json::PackageJson;
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceMember {
+    pub name: String,
+    pub path: PathBuf,
+    pub package_json: PackageJson,
+}
+
+impl WorkspaceMember {
+    /// Names of all packages this member depends on, across every dependency section.
+    #[must_use]
+    pub fn dependency_names(&self) -> HashSet<String> {
+        let pkg = &self.package_json;
+        [
+            &pkg.dependencies,
+            &pkg.dev_dependencies,
+            &pkg.peer_dependencies,
+            &pkg.optional_dependencies,
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|deps| deps.keys().cloned())
+        .collect()
+    }
+}
+
+/// Reads the `workspaces` field of the root `package.json`, accepting both the array
+/// form and the `{ "packages": [...] }` object form.
+#[must_use]
+pub fn workspace_patterns(root_pkg: &PackageJson) -> Vec<String> {
+    let Some(workspaces) = root_pkg.other.get("workspaces") else {
+        return Vec::new();
+    };
+
+    let patterns = workspaces
+        .as_array()
+        .or_else(|| workspaces.get("packages").and_then(|p| p.as_array()));
+
+    patterns
+        .map(|patterns| {
+            patterns
+                .iter()
+                .filter_map(|p| p.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Discovers workspace members below `root`, in the order their patterns list them.
+pub fn discover_workspaces(root: &Path) -> anyhow::Result<Vec<WorkspaceMember>> {
+    let root_pkg = read_package_json(root)?;
+    let patterns = workspace_patterns(&root_pkg);
+
+    let (excludes, includes): (Vec<_>, Vec<_>) = patterns.iter().partition(|p| p.starts_with('!'));
+    let excludes: Vec<String> = excludes
+        .iter()
+        .map(|p| normalize_pattern(&p[1..]))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut members = Vec::new();
+
+    for pattern in includes {
+        let pattern = normalize_pattern(pattern);
+        let mut dirs = Vec::new();
+        expand_pattern(root, &pattern, &mut dirs);
+        dirs.sort();
+
+        for dir in dirs {
+            let relative = relative_path(root, &dir);
+            if excludes.iter().any(|ex| glob_match(ex, &relative)) {
+                continue;
+            }
+            if !dir.join("package.json").exists() || !seen.insert(dir.clone()) {
+                continue;
+            }
+
+            let package_json = read_package_json(&dir)?;
+            let name = package_json
+                .name
+                .clone()
+                .unwrap_or_else(|| relative.clone());
+            members.push(WorkspaceMember {
+                name,
+                path: dir,
+                package_json,
+            });
+        }
+    }
+
+    Ok(members)
+}
+
+/// The members of the workspace `dir` belongs to, from the nearest ancestor whose
+/// `workspaces` include it. `None` when `dir` is not a workspace member.
+#[must_use]
+pub fn workspace_members_of(dir: &Path) -> Option<Vec<WorkspaceMember>> {
+    let dir = dir.canonicalize().ok()?;
+    dir.ancestors().skip(1).find_map(|root| {
+        let root_pkg = read_package_json(root).ok()?;
+        if workspace_patterns(&root_pkg).is_empty() {
+            return None;
+        }
+        let members = discover_workspaces(root).ok()?;
+        members
+            .iter()
+            .any(|member| member.path.canonicalize().is_ok_and(|path| path == dir))
+            .then_some(members)
+    })
+}
+
+/// Orders members so that every member comes after the workspace members it depends on.
+/// Members that take part in a cycle keep their discovery order at the end.
+#[must_use]
+pub fn sort_workspaces(members: Vec<WorkspaceMember>) -> Vec<WorkspaceMember> {
+    workspace_waves(members).into_iter().flatten().collect()
+}
+
+/// Groups members into waves: every member lands in a later wave than the workspace
+/// members it depends on, so the members of one wave do not depend on each other and can
+/// be processed in parallel. Members keep their discovery order within a wave, and those
+/// that take part in a cycle form the last wave.
+#[must_use]
+pub fn workspace_waves(members: Vec<WorkspaceMember>) -> Vec<Vec<WorkspaceMember>> {
+    let names: HashSet<String> = members.iter().map(|m| m.name.clone()).collect();
+    let mut pending: HashMap<String, HashSet<String>> = members
+        .iter()
+        .map(|m| {
+            let deps = m
+                .dependency_names()
+                .into_iter()
+                .filter(|d| names.contains(d) && *d != m.name)
+                .collect();
+            (m.name.clone(), deps)
+        })
+        .collect();
+
+    let mut remaining = members;
+    let mut waves = Vec::new();
+
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<_>, Vec<_>) = remaining
+            .into_iter()
+            .partition(|m| pending.get(&m.name).is_none_or(HashSet::is_empty));
+
+        if ready.is_empty() {
+            waves.push(blocked);
+            break;
+        }
+
+        for member in &ready {
+            for deps in pending.values_mut() {
+                deps.remove(&member.name);
+            }
+        }
+
+        waves.push(ready);
+        remaining = blocked;
+    }
+
+    waves
+}
+
+/// The range a `workspace:` dependency accepts from a member at `version`: `workspace:*`
+/// pins the exact version, `workspace:^` and `workspace:~` prefix it, and anything else
+/// is a range of its own. This is also what the spec becomes in a published manifest.
+/// Returns `None` for other specs.
+#[must_use]
+pub fn workspace_range(spec: &str, version: &str) -> Option<String> {
+    let range = spec.strip_prefix("workspace:")?;
+    Some(match range {
+        "" | "*" => version.to_string(),
+        "^" | "~" => format!("{range}{version}"),
+        range => range.to_string(),
+    })
+}
+
+/// Replaces the `workspace:` specs in every dependency section of `pkg` with the
+/// concrete range of the member they refer to, for packing and publishing.
+pub fn rewrite_workspace_protocol(
+    pkg: &mut PackageJson,
+    members: &[WorkspaceMember],
+) -> Result<(), PackageManagerError> {
+    let sections = [
+        &mut pkg.dependencies,
+        &mut pkg.dev_dependencies,
+        &mut pkg.peer_dependencies,
+        &mut pkg.optional_dependencies,
+    ];
+
+    for deps in sections.into_iter().flatten() {
+        for (name, spec) in deps.iter_mut() {
+            if !pacm_utils::is_workspace_spec(spec) {
+                continue;
+            }
+            let version = members
+                .iter()
+                .find(|m| m.name == *name)
+                .and_then(|m| m.package_json.version.as_deref())
+                .ok_or_else(|| PackageManagerError::PackageNotFound(format!("{name}@{spec}")))?;
+            if let Some(range) = workspace_range(spec, version) {
+                *spec = range;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fails with a `DependencyConflict` when `pkg` lists its own name in any dependency
+/// section, which no install can satisfy with anything but the package itself.
+pub fn check_self_dependency(pkg: &PackageJson) -> Result<(), PackageManagerError> {
+    let Some(name) = pkg.name.as_deref() else {
+        return Ok(());
+    };
+    match pkg.has_dependency(name) {
+        Some(section) => Err(PackageManagerError::DependencyConflict(
+            name.to_string(),
+            format!("the package lists itself in {}", section.as_str()),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Fails with a `DependencyConflict` naming the members involved when workspace members
+/// depend on each other in a cycle, since no member of it can be handled first.
+pub fn check_workspace_cycles(members: &[WorkspaceMember]) -> Result<(), PackageManagerError> {
+    let Some(cycle) = find_workspace_cycle(members) else {
+        return Ok(());
+    };
+
+    Err(PackageManagerError::DependencyConflict(
+        cycle[0].clone(),
+        format!(
+            "workspace packages depend on each other in a cycle: {}",
+            cycle.join(" > ")
+        ),
+    ))
+}
+
+/// The first cycle between workspace members, as the names along it with the first one
+/// repeated at the end, e.g. `["a", "b", "a"]`. Members depending on themselves are
+/// ignored, like in [`sort_workspaces`].
+#[must_use]
+pub fn find_workspace_cycle(members: &[WorkspaceMember]) -> Option<Vec<String>> {
+    let graph: HashMap<&str, Vec<String>> = members
+        .iter()
+        .map(|m| {
+            let mut deps: Vec<String> = m
+                .dependency_names()
+                .into_iter()
+                .filter(|d| *d != m.name && members.iter().any(|other| other.name == *d))
+                .collect();
+            deps.sort();
+            (m.name.as_str(), deps)
+        })
+        .collect();
+
+    let mut done = HashSet::new();
+    for member in members {
+        let mut path = Vec::new();
+        if let Some(cycle) = walk_for_cycle(&graph, &member.name, &mut path, &mut done) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+fn walk_for_cycle(
+    graph: &HashMap<&str, Vec<String>>,
+    name: &str,
+    path: &mut Vec<String>,
+    done: &mut HashSet<String>,
+) -> Option<Vec<String>> {
+    if let Some(start) = path.iter().position(|n| n == name) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(name.to_string());
+        return Some(cycle);
+    }
+    if done.contains(name) {
+        return None;
+    }
+
+    path.push(name.to_string());
+    for dep in graph.get(name).into_iter().flatten() {
+        if let Some(cycle) = walk_for_cycle(graph, dep, path, done) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    done.insert(name.to_string());
+    None
+}
+
+fn normalize_pattern(pattern: &str) -> String {
+    pattern
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn relative_path(root: &Path, dir: &Path) -> String {
+    dir.strip_prefix(root)
+        .unwrap_or(dir)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn expand_pattern(base: &Path, pattern: &str, out: &mut Vec<PathBuf>) {
+    let (segment, rest) = match pattern.split_once('/') {
+        Some((segment, rest)) => (segment, Some(rest)),
+        None => (pattern, None),
+    };
+
+    let next = |dir: PathBuf, out: &mut Vec<PathBuf>| match rest {
+        Some(rest) => expand_pattern(&dir, rest, out),
+        None => out.push(dir),
+    };
+
+    if segment == "**" {
+        let mut stack = vec![base.to_path_buf()];
+        while let Some(dir) = stack.pop() {
+            stack.extend(child_dirs(&dir));
+            next(dir, out);
+        }
+    } else if segment.contains(['*', '?']) {
+        for dir in child_dirs(base) {
+            let matches = dir
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| glob_match(segment, n));
+            if matches {
+                next(dir, out);
+            }
+        }
+    } else if segment.is_empty() || segment == "." {
+        next(base.to_path_buf(), out);
+    } else {
+        let dir = base.join(segment);
+        if dir.is_dir() {
+            next(dir, out);
+        }
+    }
+}
+
+fn child_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|ft| ft.is_dir()))
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name != "node_modules" && !name.starts_with('.')
+        })
+        .map(|entry| entry.path())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_package(dir: &Path, content: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("package.json"), content).unwrap();
+    }
+
+    #[test]
+    fn test_discover_and_sort_workspaces() {
+        let root = std::env::temp_dir().join(format!("pacm-workspace-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        write_package(
+            &root,
+            r#"{"name": "root", "workspaces": ["packages/*", "!packages/ignored"]}"#,
+        );
+        write_package(
+            &root.join("packages/app"),
+            r#"{"name": "app", "dependencies": {"lib": "workspace:*"}}"#,
+        );
+        write_package(
+            &root.join("packages/lib"),
+            r#"{"name": "lib", "devDependencies": {"utils": "^1.0.0"}}"#,
+        );
+        write_package(&root.join("packages/utils"), r#"{"name": "utils"}"#);
+        write_package(&root.join("packages/ignored"), r#"{"name": "ignored"}"#);
+        fs::create_dir_all(root.join("packages/no-manifest")).unwrap();
+
+        let members = discover_workspaces(&root).unwrap();
+        let discovered: Vec<_> = members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(discovered, vec!["app", "lib", "utils"]);
+
+        let sorted: Vec<_> = sort_workspaces(members)
+            .into_iter()
+            .map(|m| m.name)
+            .collect();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(sorted, vec!["utils", "lib", "app"]);
+    }
+
+    fn member(name: &str, deps: &[&str]) -> WorkspaceMember {
+        let deps: serde_json::Map<_, _> = deps
+            .iter()
+            .map(|dep| (dep.to_string(), "workspace:*".into()))
+            .collect();
+        WorkspaceMember {
+            name: name.to_string(),
+            path: PathBuf::from(name),
+            package_json: serde_json::from_value(
+                serde_json::json!({ "name": name, "dependencies": deps }),
+            )
+            .unwrap(),
+        }
+    }
+
+    fn wave_names(waves: &[Vec<WorkspaceMember>]) -> Vec<Vec<&str>> {
+        waves
+            .iter()
+            .map(|wave| wave.iter().map(|m| m.name.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_diamond_workspaces_form_parallel_waves() {
+        let waves = workspace_waves(vec![
+            member("app", &["left", "right"]),
+            member("left", &["core"]),
+            member("right", &["core", "react"]),
+            member("core", &[]),
+        ]);
+
+        assert_eq!(
+            wave_names(&waves),
+            vec![vec!["core"], vec!["left", "right"], vec!["app"]]
+        );
+    }
+
+    #[test]
+    fn test_cyclic_workspaces_form_last_wave() {
+        let waves = workspace_waves(vec![
+            member("a", &["b"]),
+            member("b", &["a"]),
+            member("c", &[]),
+        ]);
+
+        assert_eq!(wave_names(&waves), vec![vec!["c"], vec!["a", "b"]]);
+        assert!(workspace_waves(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_workspace_cycle_is_a_dependency_conflict() {
+        let root =
+            std::env::temp_dir().join(format!("pacm-workspace-cycle-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        write_package(&root, r#"{"name": "root", "workspaces": ["packages/*"]}"#);
+        write_package(
+            &root.join("packages/a"),
+            r#"{"name": "a", "dependencies": {"b": "workspace:*", "a": "workspace:*"}}"#,
+        );
+        write_package(
+            &root.join("packages/b"),
+            r#"{"name": "b", "devDependencies": {"a": "workspace:*"}}"#,
+        );
+        write_package(
+            &root.join("packages/c"),
+            r#"{"name": "c", "dependencies": {"a": "workspace:*"}}"#,
+        );
+
+        let members = discover_workspaces(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            find_workspace_cycle(&members),
+            Some(vec!["a".to_string(), "b".to_string(), "a".to_string()])
+        );
+        let error = check_workspace_cycles(&members).unwrap_err();
+        assert!(
+            matches!(&error, PackageManagerError::DependencyConflict(name, _) if name == "a"),
+            "{error}"
+        );
+        assert!(error.to_string().contains("a > b > a"), "{error}");
+
+        let acyclic: Vec<_> = members.into_iter().filter(|m| m.name != "b").collect();
+        assert!(check_workspace_cycles(&acyclic).is_ok());
+    }
+
+    #[test]
+    fn test_self_dependency_is_a_dependency_conflict() {
+        let pkg: PackageJson =
+            serde_json::from_str(r#"{"name": "loop", "devDependencies": {"loop": "^1.0.0"}}"#)
+                .unwrap();
+        let error = check_self_dependency(&pkg).unwrap_err();
+        assert!(
+            matches!(&error, PackageManagerError::DependencyConflict(name, _) if name == "loop"),
+            "{error}"
+        );
+        assert!(error.to_string().contains("devDependencies"), "{error}");
+
+        let pkg: PackageJson =
+            serde_json::from_str(r#"{"name": "fine", "dependencies": {"other": "^1.0.0"}}"#)
+                .unwrap();
+        assert!(check_self_dependency(&pkg).is_ok());
+    }
+
+    #[test]
+    fn test_workspace_protocol_rewrites_to_member_version() {
+        assert_eq!(
+            workspace_range("workspace:*", "1.2.0").as_deref(),
+            Some("1.2.0")
+        );
+        assert_eq!(
+            workspace_range("workspace:^", "1.2.0").as_deref(),
+            Some("^1.2.0")
+        );
+        assert_eq!(
+            workspace_range("workspace:~", "1.2.0").as_deref(),
+            Some("~1.2.0")
+        );
+        assert_eq!(
+            workspace_range("workspace:>=1.0.0", "1.2.0").as_deref(),
+            Some(">=1.0.0")
+        );
+        assert_eq!(workspace_range("^1.0.0", "1.2.0"), None);
+
+        let utils = WorkspaceMember {
+            name: "@acme/utils".to_string(),
+            path: PathBuf::from("packages/utils"),
+            package_json: serde_json::from_str(r#"{"name": "@acme/utils", "version": "1.2.0"}"#)
+                .unwrap(),
+        };
+        let mut app: PackageJson = serde_json::from_str(
+            r#"{"dependencies": {"@acme/utils": "workspace:^", "left-pad": "^1.0.0"},
+                "devDependencies": {"@acme/missing": "workspace:*"}}"#,
+        )
+        .unwrap();
+        assert!(rewrite_workspace_protocol(&mut app, std::slice::from_ref(&utils)).is_err());
+
+        app.dev_dependencies = None;
+        rewrite_workspace_protocol(&mut app, &[utils]).unwrap();
+        let deps = app.dependencies.unwrap();
+        assert_eq!(deps["@acme/utils"], "^1.2.0");
+        assert_eq!(deps["left-pad"], "^1.0.0");
+    }
+
+    #[test]
+    fn test_workspace_patterns_object_form() {
+        let pkg: PackageJson =
+            serde_json::from_str(r#"{"workspaces": {"packages": ["apps/*"]}}"#).unwrap();
+        assert_eq!(workspace_patterns(&pkg), vec!["apps/*".to_string()]);
+    }
+}
@@ -0,0 +1,120 @@
+//! Compares parsing a large registry document the way `fetch_metadata` used to, decoding
+//! the body into a `String` and parsing that, with `parse_packument` on the raw bytes.
+//! Reports the time per parse and the peak heap use of each. Run with
+//! `cargo bench -p pacm-registry --bench packument_parse`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+const VERSIONS: usize = 4000;
+const RUNS: u32 = 10;
+
+/// Tracks the bytes currently allocated and the most allocated at once.
+struct PeakAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc;
+
+/// The document as the old path deserialized it.
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Packument {
+    #[serde(rename = "dist-tags", default)]
+    dist_tags: HashMap<String, String>,
+    #[serde(default)]
+    versions: Value,
+}
+
+/// An abbreviated document shaped like the one for `@types/node`.
+fn document() -> Vec<u8> {
+    let versions: serde_json::Map<String, Value> = (0..VERSIONS)
+        .map(|i| {
+            let version = format!("{}.{}.{}", i / 400, (i / 20) % 20, i % 20);
+            let manifest = json!({
+                "name": "@types/big",
+                "version": version,
+                "dependencies": { "undici-types": "~6.19.2" },
+                "dist": {
+                    "integrity": format!("sha512-{}", "A".repeat(86)),
+                    "shasum": "0".repeat(40),
+                    "tarball": format!("https://registry.npmjs.org/@types/big/-/big-{version}.tgz"),
+                    "fileCount": 120,
+                    "unpackedSize": 2_000_000,
+                },
+                "engines": { "node": ">=18" },
+            });
+            (version, manifest)
+        })
+        .collect();
+    json!({
+        "name": "@types/big",
+        "dist-tags": { "latest": "9.19.19" },
+        "modified": "2024-01-01T00:00:00.000Z",
+        "versions": versions,
+    })
+    .to_string()
+    .into_bytes()
+}
+
+fn measure(body: &[u8], parse: impl Fn(&[u8])) -> (Duration, usize) {
+    let mut total = Duration::ZERO;
+    let mut peak = 0;
+    for _ in 0..RUNS {
+        let baseline = CURRENT.load(Ordering::Relaxed);
+        PEAK.store(baseline, Ordering::Relaxed);
+        let start = Instant::now();
+        parse(body);
+        total += start.elapsed();
+        peak = peak.max(PEAK.load(Ordering::Relaxed) - baseline);
+    }
+    (total / RUNS, peak)
+}
+
+fn main() {
+    let body = document();
+
+    let (text_time, text_peak) = measure(&body, |body| {
+        // What `Response::text` does with a UTF-8 body.
+        let text = String::from_utf8_lossy(body).into_owned();
+        let packument: Packument = serde_json::from_str(&text).unwrap();
+        std::hint::black_box(packument);
+    });
+    let (bytes_time, bytes_peak) = measure(&body, |body| {
+        std::hint::black_box(pacm_registry::parse_packument(body).unwrap());
+    });
+
+    let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    println!("{VERSIONS} versions, {:.1} MiB document", mib(body.len()));
+    println!(
+        "text + from_str:  {text_time:?}, peak {:.1} MiB",
+        mib(text_peak)
+    );
+    println!(
+        "parse_packument:  {bytes_time:?}, peak {:.1} MiB",
+        mib(bytes_peak)
+    );
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use pacm_config::PacmConfig;
+use pacm_constants::USER_AGENT;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Advisory {
+    pub id: u64,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub severity: String,
+    #[serde(default)]
+    pub vulnerable_versions: String,
+}
+
+/// Queries the registry's bulk advisory endpoint for the given `name => versions` map.
+pub async fn fetch_advisories_async(
+    client: Arc<reqwest::Client>,
+    packages: &BTreeMap<String, Vec<String>>,
+) -> anyhow::Result<HashMap<String, Vec<Advisory>>> {
+    if packages.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let config = PacmConfig::get();
+    let url = format!("{}-/npm/v1/security/advisories/bulk", config.registry);
+
+    let mut request = client
+        .post(&url)
+        .header("Accept", "application/json")
+        .header("User-Agent", USER_AGENT)
+        .json(packages);
+    if let Some(auth) = config.auth_header_for(&url) {
+        request = request.header("Authorization", auth);
+    }
+
+    crate::connections::record_request();
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to query advisories: {}", e))?
+        .error_for_status()
+        .map_err(|e| anyhow::anyhow!("Advisory request failed: {}", e))?;
+
+    resp.json::<HashMap<String, Vec<Advisory>>>()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to parse advisory response: {}", e))
+}
@@ -0,0 +1,117 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+use pacm_config::PacmConfig;
+
+static GLOBAL: OnceLock<Arc<CircuitBreaker>> = OnceLock::new();
+
+/// A retry budget shared by every request to the registry in this process. Once
+/// `threshold` requests in a row have failed, further requests fail immediately for
+/// `cool_down` instead of each running through its own retries. The first request after
+/// the cool-down goes through again, and a single success closes the breaker.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cool_down: Duration,
+    consecutive_failures: AtomicU32,
+    open_until: Mutex<Option<Instant>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cool_down: Duration) -> Self {
+        Self {
+            threshold,
+            cool_down,
+            consecutive_failures: AtomicU32::new(0),
+            open_until: Mutex::new(None),
+        }
+    }
+
+    /// The breaker used for registry metadata and tarball requests, configured with
+    /// `fetch-failure-threshold` and `fetch-cooldown`.
+    pub fn global() -> Arc<Self> {
+        GLOBAL
+            .get_or_init(|| {
+                let config = PacmConfig::get();
+                Arc::new(Self::new(
+                    config.fetch_failure_threshold(),
+                    config.fetch_cooldown(),
+                ))
+            })
+            .clone()
+    }
+
+    /// Fails while the breaker is open.
+    pub fn check(&self) -> anyhow::Result<()> {
+        let open_until = *self
+            .open_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match open_until {
+            Some(until) if Instant::now() < until => Err(self.open_error()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        *self
+            .open_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    /// Counts a failed request and returns whether the breaker is now open.
+    pub fn record_failure(&self) -> bool {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures < self.threshold {
+            return false;
+        }
+
+        *self
+            .open_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now() + self.cool_down);
+        true
+    }
+
+    pub fn open_error(&self) -> anyhow::Error {
+        anyhow::anyhow!(
+            "registry appears to be down, aborting early ({} requests failed in a row)",
+            self.consecutive_failures.load(Ordering::Relaxed)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_after_threshold_and_closes_on_success() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+
+        assert!(!breaker.record_failure());
+        assert!(breaker.check().is_ok());
+        assert!(breaker.record_failure());
+        assert!(
+            breaker
+                .check()
+                .unwrap_err()
+                .to_string()
+                .starts_with("registry appears to be down")
+        );
+
+        breaker.record_success();
+        assert!(breaker.check().is_ok());
+        assert!(!breaker.record_failure());
+    }
+
+    #[test]
+    fn test_breaker_lets_requests_through_after_cool_down() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+
+        assert!(breaker.record_failure());
+        assert!(breaker.check().is_ok());
+    }
+}
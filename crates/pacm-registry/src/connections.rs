@@ -0,0 +1,130 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+
+/// Requests sent and connections opened since the last [`take`], to tell whether
+/// keep-alive works with the registry or proxy in between.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    pub requests: u64,
+    pub connections: u64,
+}
+
+impl ConnectionStats {
+    /// Requests that went over a connection an earlier request opened.
+    #[must_use]
+    pub fn reused(&self) -> u64 {
+        self.requests.saturating_sub(self.connections)
+    }
+}
+
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+static CONNECTIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Counts a request about to be sent by a client from [`crate::configure_client`].
+pub fn record_request() {
+    REQUESTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The counts since the last call, which resets them.
+pub fn take() -> ConnectionStats {
+    ConnectionStats {
+        requests: REQUESTS.swap(0, Ordering::Relaxed),
+        connections: CONNECTIONS.swap(0, Ordering::Relaxed),
+    }
+}
+
+/// A connector layer that counts every connection the client's pool opens. reqwest only
+/// asks its connector for a connection when no idle one can be reused.
+#[derive(Clone, Copy)]
+pub struct CountConnections {
+    opened: &'static AtomicU64,
+}
+
+impl CountConnections {
+    /// Counts into `opened` instead of the totals [`take`] reports.
+    #[must_use]
+    pub fn with_counter(opened: &'static AtomicU64) -> Self {
+        Self { opened }
+    }
+}
+
+impl Default for CountConnections {
+    fn default() -> Self {
+        Self::with_counter(&CONNECTIONS)
+    }
+}
+
+impl<S> tower::Layer<S> for CountConnections {
+    type Service = CountedConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountedConnector {
+            inner,
+            opened: self.opened,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CountedConnector<S> {
+    inner: S,
+    opened: &'static AtomicU64,
+}
+
+impl<S, R> tower::Service<R> for CountedConnector<S>
+where
+    S: tower::Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let connecting = self.inner.call(request);
+        let opened = self.opened;
+        Box::pin(async move {
+            let connection = connecting.await?;
+            opened.fetch_add(1, Ordering::Relaxed);
+            Ok(connection)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pacm_testutil::{MockServer, Response};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_counts_opened_connections() {
+        static OPENED: AtomicU64 = AtomicU64::new(0);
+        // The mock server closes every connection after answering, so none is reused.
+        let server =
+            MockServer::start(Arc::new(|_| Response::json(&serde_json::json!({})))).unwrap();
+        let client = reqwest::Client::builder()
+            .connector_layer(CountConnections::with_counter(&OPENED))
+            .build()
+            .unwrap();
+
+        for _ in 0..2 {
+            client.get(server.url()).send().await.unwrap();
+        }
+        assert_eq!(OPENED.load(Ordering::Relaxed), 2);
+        assert_eq!(
+            ConnectionStats {
+                requests: 5,
+                connections: 2
+            }
+            .reused(),
+            3
+        );
+    }
+}
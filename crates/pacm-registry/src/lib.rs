@@ -1,45 +1,231 @@
+pub mod advisories;
+pub mod breaker;
+pub mod connections;
+pub mod metadata_cache;
+pub mod publish;
+pub mod signatures;
+pub mod stored;
+
+pub use advisories::{Advisory, fetch_advisories_async};
+pub use breaker::CircuitBreaker;
+pub use connections::{ConnectionStats, CountConnections};
+pub use metadata_cache::{CachedMetadata, MetadataCache, MetadataSource};
+pub use publish::{publish_async, publish_document, published_versions_async};
+pub use signatures::{
+    RegistryKeys, RegistrySignature, SignatureCheck, dist_signatures, fetch_signing_keys_async,
+};
+pub use stored::{stored_package_info, stored_package_info_in};
+
+use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
 use tokio::sync::Mutex;
 
-use pacm_constants::{MAX_ATTEMPTS, USER_AGENT};
+use pacm_config::PacmConfig;
+use pacm_constants::{MAX_ATTEMPTS, MAX_REDIRECTS, USER_AGENT};
+
+/// Asks for the abbreviated "corgi" document, which carries only what installs need and
+/// is a fraction of the size for packages with many versions. Registries that do not
+/// serve it answer with the full document instead.
+const ABBREVIATED_ACCEPT: &str =
+    "application/vnd.npm.install-v1+json; q=1.0, application/json; q=0.8, */*";
+const FULL_ACCEPT: &str = "application/json";
 
 lazy_static::lazy_static! {
     static ref PACKAGE_CACHE: Arc<Mutex<HashMap<String, PackageInfo>>> = Arc::new(Mutex::new(HashMap::with_capacity(5000)));
+    /// One lock per package being fetched, so concurrent lookups of the same name wait
+    /// for the first one instead of repeating the request.
+    static ref IN_FLIGHT: std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>> = std::sync::Mutex::new(HashMap::new());
+    /// Registry origins whose metadata requests were redirected to another host, with
+    /// the origin they now answer from.
+    static ref MOVED_REGISTRIES: std::sync::Mutex<HashMap<String, String>> = std::sync::Mutex::new(HashMap::new());
+}
+
+/// Forgets all package metadata fetched so far in this process.
+pub async fn clear_package_cache() {
+    PACKAGE_CACHE.lock().await.clear();
 }
 
+/// Metadata for `name`. A copy saved within `cache-max-age` is used as is; an older one
+/// is revalidated with the registry, which only resends the document if it changed.
 pub async fn fetch_package_info_async(
     client: Arc<reqwest::Client>,
     name: &str,
 ) -> anyhow::Result<PackageInfo> {
+    let config = PacmConfig::get();
+    let source = MetadataSource::configured(name);
+    let auth_header = config.auth_header_for(&source.url);
+
+    fetch_cached(
+        &client,
+        name,
+        &source,
+        auth_header.as_deref(),
+        &MetadataCache::global(),
+        config.cache_max_age(),
+    )
+    .await
+}
+
+/// Metadata for resolving the exact version `version` of `name`. Published versions never
+/// change, so a saved copy listing `version` is used however old it is.
+pub async fn fetch_pinned_package_info_async(
+    client: Arc<reqwest::Client>,
+    name: &str,
+    version: &str,
+) -> anyhow::Result<PackageInfo> {
+    if let Some(cached_info) = PACKAGE_CACHE.lock().await.get(name) {
+        return Ok(cached_info.clone());
+    }
+    if let Some(saved) = MetadataCache::global()
+        .read(name, &MetadataSource::configured(name))
+        .filter(|info| info.versions.get(version).is_some())
     {
-        let cache = PACKAGE_CACHE.lock().await;
-        if let Some(cached_info) = cache.get(name) {
-            return Ok(cached_info.clone());
+        return Ok(saved);
+    }
+
+    fetch_package_info_async(client, name).await
+}
+
+/// Returns the metadata fetched earlier in this process, or the copy saved to `disk` from
+/// `source` while it is younger than `max_age`, or else fetches it and saves it. Only one task fetches a
+/// given name at a time; the others wait and take its result from memory.
+async fn fetch_cached(
+    client: &reqwest::Client,
+    name: &str,
+    source: &MetadataSource,
+    auth_header: Option<&str>,
+    disk: &MetadataCache,
+    max_age: Duration,
+) -> anyhow::Result<PackageInfo> {
+    if let Some(cached_info) = PACKAGE_CACHE.lock().await.get(name) {
+        return Ok(cached_info.clone());
+    }
+
+    let name_lock = IN_FLIGHT
+        .lock()
+        .unwrap()
+        .entry(name.to_string())
+        .or_default()
+        .clone();
+    let _fetching = name_lock.lock().await;
+
+    if let Some(cached_info) = PACKAGE_CACHE.lock().await.get(name) {
+        return Ok(cached_info.clone());
+    }
+
+    // The disk copy only saves a later run some work, so failing to write it is not an
+    // error for this one.
+    let fetched = match disk.read_entry(name, source) {
+        Some(saved) if saved.is_fresh(max_age, SystemTime::now()) => Ok(saved.info),
+        saved => {
+            let etag = saved.as_ref().and_then(|saved| saved.etag.as_deref());
+            match fetch_metadata(
+                client,
+                name,
+                &source.url,
+                auth_header,
+                etag,
+                &CircuitBreaker::global(),
+            )
+            .await
+            {
+                Ok(Fetched::Modified { info, etag }) => {
+                    let _ = disk.write(name, source, &info, etag.as_deref());
+                    Ok(info)
+                }
+                Ok(Fetched::NotModified) => {
+                    let _ = disk.touch(name);
+                    saved.map(|saved| saved.info).ok_or_else(|| {
+                        anyhow::anyhow!("Registry sent 304 for {} with nothing cached", name)
+                    })
+                }
+                Err(e) => Err(e),
+            }
         }
+    };
+    if let Ok(package_info) = &fetched {
+        PACKAGE_CACHE
+            .lock()
+            .await
+            .insert(name.to_string(), package_info.clone());
     }
+    IN_FLIGHT.lock().unwrap().remove(name);
 
-    let encoded_name = urlencoding::encode(name);
-    let url = format!("https://registry.npmjs.org/{encoded_name}");
+    fetched
+}
 
+/// What the registry answered a metadata request with.
+#[derive(Debug)]
+enum Fetched {
+    Modified {
+        info: PackageInfo,
+        etag: Option<String>,
+    },
+    /// The document matches the `ETag` sent along.
+    NotModified,
+}
+
+/// Fetches the packument at `url`, retrying transient failures until `breaker` reports
+/// the registry as down. With `etag`, the registry may answer that the saved copy is
+/// still current instead of resending it. Retries after an unreadable document ask
+/// caches in between to go back to the registry, so a proxy that stored a truncated
+/// body does not send the same one again.
+async fn fetch_metadata(
+    client: &reqwest::Client,
+    name: &str,
+    url: &str,
+    auth_header: Option<&str>,
+    etag: Option<&str>,
+    breaker: &CircuitBreaker,
+) -> anyhow::Result<Fetched> {
     let mut attempts = 0;
     let max_attempts = MAX_ATTEMPTS;
+    let accept = if PacmConfig::get().full_metadata() {
+        FULL_ACCEPT
+    } else {
+        ABBREVIATED_ACCEPT
+    };
+    let mut bust_cache = false;
 
     loop {
+        breaker.check()?;
         attempts += 1;
 
-        let resp_result = client
-            .get(&url)
-            .header("Accept", "application/json")
-            .header("User-Agent", USER_AGENT)
-            .send()
-            .await;
+        let mut request = client
+            .get(url)
+            .header("Accept", accept)
+            .header("User-Agent", USER_AGENT);
+        if let Some(auth) = auth_header {
+            request = request.header("Authorization", auth);
+        }
+        if let Some(etag) = etag {
+            request = request.header("If-None-Match", etag);
+        }
+        if bust_cache {
+            request = request
+                .header("Cache-Control", "no-cache")
+                .header("Pragma", "no-cache");
+        }
+
+        connections::record_request();
+        let resp_result = request.send().await;
 
         let resp = match resp_result {
             Ok(resp) => resp,
+            // Retrying would only walk the same redirects again, and a registry stuck in
+            // a loop is as unusable as one that is down.
+            Err(e) if e.is_redirect() => {
+                if breaker.record_failure() {
+                    return Err(breaker.open_error());
+                }
+                return Err(anyhow::anyhow!("too many redirects fetching {}", name));
+            }
             Err(e) => {
-                if attempts < max_attempts {
+                if should_retry(breaker, attempts, max_attempts)? {
                     let delay = std::cmp::min(1000 * u64::from(attempts), 5000);
                     tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
                     continue;
@@ -56,14 +242,29 @@ pub async fn fetch_package_info_async(
             }
         };
 
+        if resp.status().is_success() || resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+            note_moved_registry(url, resp.url());
+        }
+        if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+            breaker.record_success();
+            return Ok(Fetched::NotModified);
+        }
+        let etag = resp
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
         let resp = match resp.error_for_status() {
             Ok(resp) => resp,
             Err(e) => {
-                if attempts < max_attempts
-                    && (e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS)
-                        || e.status() == Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
-                        || e.status() == Some(reqwest::StatusCode::SERVICE_UNAVAILABLE))
-                {
+                let transient = e.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS)
+                    || e.status() == Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+                    || e.status() == Some(reqwest::StatusCode::SERVICE_UNAVAILABLE);
+                if !transient {
+                    // The registry answered, it just has nothing for us.
+                    breaker.record_success();
+                } else if should_retry(breaker, attempts, max_attempts)? {
                     tokio::time::sleep(std::time::Duration::from_millis(
                         1000 * u64::from(attempts),
                     ))
@@ -74,26 +275,29 @@ pub async fn fetch_package_info_async(
             }
         };
 
-        let text = match resp.text().await {
-            Ok(text) => text,
+        // Parsed from the raw body: decoding it into a `String` first would hold a second
+        // copy of documents that run to tens of megabytes.
+        let body = match resp.bytes().await {
+            Ok(body) => body,
             Err(e) => {
-                if attempts < max_attempts {
+                if should_retry(breaker, attempts, max_attempts)? {
                     tokio::time::sleep(std::time::Duration::from_millis(500 * u64::from(attempts)))
                         .await;
                     continue;
                 }
                 return Err(anyhow::anyhow!(
-                    "Failed to read response text for {}: {}",
+                    "Failed to read response body for {}: {}",
                     name,
                     e
                 ));
             }
         };
 
-        let json: Value = match serde_json::from_str(&text) {
-            Ok(json) => json,
+        let info = match parse_packument(&body) {
+            Ok(info) => info,
             Err(e) => {
-                if attempts < max_attempts {
+                if should_retry(breaker, attempts, max_attempts)? {
+                    bust_cache = true;
                     tokio::time::sleep(std::time::Duration::from_millis(500 * u64::from(attempts)))
                         .await;
                     continue;
@@ -101,40 +305,91 @@ pub async fn fetch_package_info_async(
                 return Err(anyhow::anyhow!(
                     "Failed to parse JSON for {} (response length: {}): {}",
                     name,
-                    text.len(),
+                    body.len(),
                     e
                 ));
             }
         };
 
-        let dist_tags: HashMap<String, String> = serde_json::from_value(
-            json.get("dist-tags")
-                .cloned()
-                .unwrap_or_else(|| serde_json::Value::Object(serde_json::Map::new())),
-        )
-        .map_err(|e| anyhow::anyhow!("Failed to parse dist-tags for {}: {}", name, e))?;
-
-        let package_info = PackageInfo {
-            versions: json
-                .get("versions")
-                .cloned()
-                .unwrap_or_else(|| serde_json::Value::Object(serde_json::Map::new())),
-            dist_tags,
-        };
+        breaker.record_success();
+        return Ok(Fetched::Modified { info, etag });
+    }
+}
 
-        {
-            let mut cache = PACKAGE_CACHE.lock().await;
-            cache.insert(name.to_string(), package_info.clone());
-        }
+/// Remembers that the registry serving `requested` redirected it to the same path on
+/// another host, so tarballs addressed to the old host are fetched from the new one.
+fn note_moved_registry(requested: &str, final_url: &reqwest::Url) {
+    let Ok(requested) = reqwest::Url::parse(requested) else {
+        return;
+    };
+    let (from, to) = (requested.origin(), final_url.origin());
+    if from != to && requested.path() == final_url.path() {
+        MOVED_REGISTRIES
+            .lock()
+            .unwrap()
+            .insert(from.ascii_serialization(), to.ascii_serialization());
+    }
+}
+
+/// `url` on the host its registry's metadata was last redirected to, or unchanged when
+/// that registry has not moved. Callers look up credentials for the returned URL, so
+/// the old host's token is not carried over: the request is only authenticated when a
+/// token is configured for the new host as well.
+pub fn redirected_url(url: &str) -> String {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return url.to_string();
+    };
+    let origin = parsed.origin().ascii_serialization();
+    match MOVED_REGISTRIES.lock().unwrap().get(&origin) {
+        Some(moved) => match parsed.as_str().strip_prefix(origin.as_str()) {
+            Some(rest) => format!("{moved}{rest}"),
+            None => url.to_string(),
+        },
+        None => url.to_string(),
+    }
+}
 
-        return Ok(package_info);
+/// The parts of a registry document resolution reads. Everything else, such as the
+/// `readme` and `time` of a full document, is skipped while parsing.
+#[derive(Deserialize)]
+struct Packument {
+    #[serde(rename = "dist-tags", default)]
+    dist_tags: HashMap<String, String>,
+    #[serde(default = "empty_versions")]
+    versions: Value,
+}
+
+fn empty_versions() -> Value {
+    Value::Object(serde_json::Map::new())
+}
+
+/// Reads the metadata in a registry document from its undecoded bytes.
+pub fn parse_packument(body: &[u8]) -> serde_json::Result<PackageInfo> {
+    let packument: Packument = serde_json::from_slice(body)?;
+    Ok(PackageInfo {
+        versions: packument.versions,
+        dist_tags: packument.dist_tags,
+    })
+}
+
+/// Counts a failed attempt against `breaker` and decides whether to try again. Fails
+/// straight away once the breaker opens, so a registry that is down costs a handful of
+/// requests rather than every package running through its retries.
+fn should_retry(
+    breaker: &CircuitBreaker,
+    attempts: u32,
+    max_attempts: u32,
+) -> anyhow::Result<bool> {
+    if breaker.record_failure() {
+        return Err(breaker.open_error());
     }
+    Ok(attempts < max_attempts)
 }
 
 pub fn fetch_package_info(name: &str) -> anyhow::Result<PackageInfo> {
     let rt = tokio::runtime::Runtime::new()?;
     let client = Arc::new(
-        reqwest::Client::builder()
+        configure_client(reqwest::Client::builder())
             .pool_max_idle_per_host(25)
             .pool_idle_timeout(Some(std::time::Duration::from_secs(90)))
             .timeout(std::time::Duration::from_secs(45))
@@ -148,8 +403,617 @@ pub fn fetch_package_info(name: &str) -> anyhow::Result<PackageInfo> {
     rt.block_on(fetch_package_info_async(client, name))
 }
 
+/// Applies the `proxy` / `https-proxy`, `strict-ssl` and `cafile` settings from
+/// `.pacmrc` / `.npmrc` to a client builder, and counts the connections it opens for
+/// [`connections::take`]. Warnings about the TLS settings are printed
+/// for the first client only, since every command builds several.
+pub fn configure_client(builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+    static TLS_WARNED: AtomicBool = AtomicBool::new(false);
+
+    let config = PacmConfig::get();
+    let warned = TLS_WARNED.swap(true, Ordering::Relaxed);
+    let mut builder = configure_tls(builder, config, &mut |message| {
+        if !warned {
+            pacm_logger::warn(&message);
+        }
+    });
+
+    if let Some(proxy) = config.proxy.as_deref()
+        && let Ok(proxy) = reqwest::Proxy::http(proxy)
+    {
+        builder = builder.proxy(proxy);
+    }
+    if let Some(proxy) = config.proxy_for("https://")
+        && let Ok(proxy) = reqwest::Proxy::https(proxy)
+    {
+        builder = builder.proxy(proxy);
+    }
+
+    builder
+        .redirect(redirect_policy())
+        .connector_layer(CountConnections::default())
+}
+
+/// Follows at most `MAX_REDIRECTS` hops, and stops as soon as a URL comes round again so
+/// a mirror redirecting in a loop fails on its second visit rather than its tenth.
+fn redirect_policy() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() > MAX_REDIRECTS || attempt.previous().contains(attempt.url()) {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Applies `strict-ssl` and `cafile`, reporting through `warn` that verification is off
+/// or that the CA file could not be used. An unusable CA file is skipped rather than
+/// failing, so the registry's public certificates still work.
+fn configure_tls(
+    mut builder: reqwest::ClientBuilder,
+    config: &PacmConfig,
+    warn: &mut dyn FnMut(String),
+) -> reqwest::ClientBuilder {
+    if !config.strict_ssl() {
+        warn(
+            "strict-ssl=false: TLS certificates are not verified, so registry traffic can be \
+             intercepted or tampered with"
+                .to_string(),
+        );
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    if let Some(cafile) = config.cafile() {
+        let certificates = std::fs::read(&cafile)
+            .map_err(|e| e.to_string())
+            .and_then(|pem| reqwest::Certificate::from_pem_bundle(&pem).map_err(|e| e.to_string()));
+        match certificates {
+            Ok(certificates) if !certificates.is_empty() => {
+                for certificate in certificates {
+                    builder = builder.add_root_certificate(certificate);
+                }
+            }
+            Ok(_) => warn(format!(
+                "Ignoring cafile {}: it holds no certificates",
+                cafile.display()
+            )),
+            Err(e) => warn(format!("Ignoring cafile {}: {e}", cafile.display())),
+        }
+    }
+
+    builder
+}
+
 #[derive(Clone, Debug)]
 pub struct PackageInfo {
     pub versions: Value,
     pub dist_tags: HashMap<String, String>,
 }
+
+impl PackageInfo {
+    /// The `dist.tarball` URL the registry lists for `version`.
+    #[must_use]
+    pub fn dist_tarball(&self, version: &str) -> Option<&str> {
+        self.versions
+            .get(version)?
+            .get("dist")?
+            .get("tarball")?
+            .as_str()
+            .filter(|url| !url.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn modified(fetched: Fetched) -> PackageInfo {
+        match fetched {
+            Fetched::Modified { info, .. } => info,
+            Fetched::NotModified => panic!("expected the registry to send the document"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_down_registry_fails_fast() {
+        // Nothing listens on a port that was just released, so every request is refused.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let url = |name: &str| format!("http://127.0.0.1:{port}/{name}");
+        let client = reqwest::Client::new();
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+
+        let error = fetch_metadata(&client, "down-a", &url("down-a"), None, None, &breaker)
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().starts_with("registry appears to be down"),
+            "{error}"
+        );
+
+        let started = Instant::now();
+        let error = fetch_metadata(&client, "down-b", &url("down-b"), None, None, &breaker)
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().starts_with("registry appears to be down"),
+            "{error}"
+        );
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_fetches_write_one_cache_file() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let requests = Arc::new(AtomicUsize::new(0));
+        let server = {
+            let requests = requests.clone();
+            pacm_testutil::MockServer::start(Arc::new(move |_| {
+                requests.fetch_add(1, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(50));
+                pacm_testutil::Response::json(&serde_json::json!({
+                    "name": "concurrent-meta",
+                    "dist-tags": { "latest": "1.0.0" },
+                    "versions": { "1.0.0": { "name": "concurrent-meta", "version": "1.0.0" } },
+                }))
+            }))
+            .unwrap()
+        };
+        let cache_dir = tempfile::tempdir().unwrap();
+        let disk = MetadataCache::new(cache_dir.path());
+        let client = reqwest::Client::new();
+        let source = abbreviated(format!("{}concurrent-meta", server.url()));
+
+        let fetches = (0..16).map(|_| {
+            fetch_cached(
+                &client,
+                "concurrent-meta",
+                &source,
+                None,
+                &disk,
+                Duration::ZERO,
+            )
+        });
+        for result in futures::future::join_all(fetches).await {
+            assert_eq!(result.unwrap().dist_tags["latest"], "1.0.0");
+        }
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        let files: Vec<_> = std::fs::read_dir(cache_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, vec!["concurrent-meta.json"]);
+        let saved = disk.read("concurrent-meta", &source).unwrap();
+        assert_eq!(saved.versions["1.0.0"]["version"], "1.0.0");
+    }
+
+    #[tokio::test]
+    async fn test_requests_abbreviated_metadata_and_accepts_full_document() {
+        let accept = Arc::new(std::sync::Mutex::new(None));
+        let server = {
+            let accept = accept.clone();
+            pacm_testutil::MockServer::start(Arc::new(move |req| {
+                *accept.lock().unwrap() = req.header("Accept").map(str::to_string);
+                // A registry without abbreviated metadata sends the full document.
+                pacm_testutil::Response::json(&serde_json::json!({
+                    "name": "full-meta",
+                    "readme": "# full-meta",
+                    "time": { "1.0.0": "2020-01-01T00:00:00.000Z" },
+                    "maintainers": [{ "name": "someone" }],
+                    "dist-tags": { "latest": "1.0.0" },
+                    "versions": { "1.0.0": { "name": "full-meta", "version": "1.0.0" } },
+                }))
+            }))
+            .unwrap()
+        };
+        let url = format!("{}full-meta", server.url());
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+
+        let info = modified(
+            fetch_metadata(
+                &reqwest::Client::new(),
+                "full-meta",
+                &url,
+                None,
+                None,
+                &breaker,
+            )
+            .await
+            .unwrap(),
+        );
+        assert_eq!(info.dist_tags["latest"], "1.0.0");
+        assert_eq!(info.versions["1.0.0"]["version"], "1.0.0");
+        assert!(
+            accept
+                .lock()
+                .unwrap()
+                .as_deref()
+                .is_some_and(|accept| accept.starts_with("application/vnd.npm.install-v1+json")),
+            "{:?}",
+            accept.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parses_abbreviated_document() {
+        let tarball = "https://registry.example/corgi-meta/-/corgi-meta-2.0.0.tgz";
+        let server = pacm_testutil::MockServer::start(Arc::new(move |_| pacm_testutil::Response {
+            content_type: "application/vnd.npm.install-v1+json",
+            ..pacm_testutil::Response::json(&serde_json::json!({
+                "name": "corgi-meta",
+                "modified": "2024-05-01T00:00:00.000Z",
+                "dist-tags": { "latest": "2.0.0", "next": "3.0.0-beta.1" },
+                "versions": {
+                    "2.0.0": {
+                        "name": "corgi-meta",
+                        "version": "2.0.0",
+                        "dependencies": { "left-pad": "^1.3.0" },
+                        "optionalDependencies": { "fsevents": "^2.3.0" },
+                        "engines": { "node": ">=18" },
+                        "os": ["darwin", "linux"],
+                        "cpu": ["x64", "arm64"],
+                        "hasInstallScript": true,
+                        "dist": {
+                            "tarball": tarball,
+                            "integrity": "sha512-abc",
+                            "shasum": "0123456789abcdef"
+                        }
+                    },
+                    "3.0.0-beta.1": {
+                        "name": "corgi-meta",
+                        "version": "3.0.0-beta.1",
+                        "deprecated": "use 2.x",
+                        "dist": { "tarball": "", "integrity": "sha512-def" }
+                    }
+                }
+            }))
+        }))
+        .unwrap();
+        let url = format!("{}corgi-meta", server.url());
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+
+        let info = modified(
+            fetch_metadata(
+                &reqwest::Client::new(),
+                "corgi-meta",
+                &url,
+                None,
+                None,
+                &breaker,
+            )
+            .await
+            .unwrap(),
+        );
+        assert_eq!(info.dist_tags["latest"], "2.0.0");
+        assert_eq!(info.dist_tags["next"], "3.0.0-beta.1");
+        let stable = &info.versions["2.0.0"];
+        assert_eq!(stable["dependencies"]["left-pad"], "^1.3.0");
+        assert_eq!(stable["optionalDependencies"]["fsevents"], "^2.3.0");
+        assert_eq!(stable["engines"]["node"], ">=18");
+        assert_eq!(stable["os"], serde_json::json!(["darwin", "linux"]));
+        assert_eq!(stable["dist"]["integrity"], "sha512-abc");
+        assert_eq!(info.dist_tarball("2.0.0"), Some(tarball));
+        assert_eq!(info.versions["3.0.0-beta.1"]["deprecated"], "use 2.x");
+        assert_eq!(info.dist_tarball("3.0.0-beta.1"), None);
+    }
+
+    #[test]
+    fn test_saved_metadata_is_fresh_until_max_age() {
+        let now = SystemTime::now();
+        let saved = |age: u64| CachedMetadata {
+            info: PackageInfo {
+                versions: empty_versions(),
+                dist_tags: HashMap::new(),
+            },
+            etag: None,
+            fetched: now - Duration::from_secs(age),
+        };
+        let max_age = Duration::from_secs(300);
+
+        assert!(saved(0).is_fresh(max_age, now));
+        assert!(saved(299).is_fresh(max_age, now));
+        assert!(!saved(300).is_fresh(max_age, now));
+        assert!(!saved(0).is_fresh(Duration::ZERO, now));
+        let from_the_future = CachedMetadata {
+            fetched: now + Duration::from_secs(60),
+            ..saved(0)
+        };
+        assert!(!from_the_future.is_fresh(max_age, now));
+    }
+
+    #[tokio::test]
+    async fn test_revalidates_saved_metadata_once_stale() {
+        let if_none_match = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let server = {
+            let if_none_match = if_none_match.clone();
+            pacm_testutil::MockServer::start(Arc::new(move |req| {
+                let etag = req.header("If-None-Match").map(str::to_string);
+                if_none_match.lock().unwrap().push(etag.clone());
+                if etag.as_deref() == Some("\"v1\"") {
+                    return pacm_testutil::Response::not_modified();
+                }
+                pacm_testutil::Response::json(&serde_json::json!({
+                    "dist-tags": { "latest": "1.0.0" },
+                    "versions": { "1.0.0": { "name": "revalidate-meta", "version": "1.0.0" } },
+                }))
+                .with_header("ETag", "\"v1\"")
+            }))
+            .unwrap()
+        };
+        let cache_dir = tempfile::tempdir().unwrap();
+        let disk = MetadataCache::new(cache_dir.path());
+        let client = reqwest::Client::new();
+        let source = abbreviated(format!("{}revalidate-meta", server.url()));
+        let (client, source_ref, disk_ref) = (&client, &source, &disk);
+        let fetch = |max_age| async move {
+            PACKAGE_CACHE.lock().await.remove("revalidate-meta");
+            fetch_cached(
+                client,
+                "revalidate-meta",
+                source_ref,
+                None,
+                disk_ref,
+                max_age,
+            )
+            .await
+            .unwrap()
+        };
+
+        fetch(Duration::from_secs(60)).await;
+        assert_eq!(
+            disk.read_entry("revalidate-meta", &source)
+                .unwrap()
+                .etag
+                .as_deref(),
+            Some("\"v1\"")
+        );
+
+        // Within the window the saved copy is used without asking the registry.
+        fetch(Duration::from_secs(60)).await;
+        assert_eq!(if_none_match.lock().unwrap().len(), 1);
+
+        let info = fetch(Duration::ZERO).await;
+        assert_eq!(info.dist_tags["latest"], "1.0.0");
+        assert_eq!(
+            *if_none_match.lock().unwrap(),
+            vec![None, Some("\"v1\"".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retries_truncated_document_past_caches() {
+        let cache_control = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let server = {
+            let cache_control = cache_control.clone();
+            pacm_testutil::MockServer::start(Arc::new(move |req| {
+                let mut seen = cache_control.lock().unwrap();
+                seen.push(req.header("Cache-Control").map(str::to_string));
+                let response = pacm_testutil::Response::json(&serde_json::json!({
+                    "dist-tags": { "latest": "1.0.0" },
+                    "versions": { "1.0.0": { "name": "truncated-meta", "version": "1.0.0" } },
+                }));
+                if seen.len() == 1 {
+                    // A proxy handing out a body it stored half-way.
+                    let body = response.body[..response.body.len() / 2].to_vec();
+                    return pacm_testutil::Response { body, ..response };
+                }
+                response
+            }))
+            .unwrap()
+        };
+        let url = format!("{}truncated-meta", server.url());
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+
+        let info = modified(
+            fetch_metadata(
+                &reqwest::Client::new(),
+                "truncated-meta",
+                &url,
+                None,
+                None,
+                &breaker,
+            )
+            .await
+            .unwrap(),
+        );
+        assert_eq!(info.dist_tags["latest"], "1.0.0");
+        assert_eq!(
+            *cache_control.lock().unwrap(),
+            vec![None, Some("no-cache".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_saved_metadata_is_replaced() {
+        let server = pacm_testutil::MockServer::start(Arc::new(|_| {
+            pacm_testutil::Response::json(&serde_json::json!({
+                "dist-tags": { "latest": "2.0.0" },
+                "versions": { "2.0.0": { "name": "corrupt-meta", "version": "2.0.0" } },
+            }))
+        }))
+        .unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let disk = MetadataCache::new(cache_dir.path());
+        let source = abbreviated(format!("{}corrupt-meta", server.url()));
+        std::fs::write(disk.path("corrupt-meta"), "{\"versions\": {\"2.0").unwrap();
+
+        assert!(disk.read_entry("corrupt-meta", &source).is_none());
+        assert!(!disk.path("corrupt-meta").exists());
+
+        std::fs::write(disk.path("corrupt-meta"), "{\"versions\": {\"2.0").unwrap();
+        let info = fetch_cached(
+            &reqwest::Client::new(),
+            "corrupt-meta",
+            &source,
+            None,
+            &disk,
+            Duration::from_secs(60),
+        )
+        .await
+        .unwrap();
+        assert_eq!(info.dist_tags["latest"], "2.0.0");
+        assert_eq!(
+            disk.read("corrupt-meta", &source).unwrap().dist_tags["latest"],
+            "2.0.0"
+        );
+    }
+
+    fn abbreviated(url: String) -> MetadataSource {
+        MetadataSource { url, full: false }
+    }
+
+    #[test]
+    fn test_saved_metadata_only_serves_its_own_source() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let disk = MetadataCache::new(cache_dir.path());
+        let info = PackageInfo {
+            versions: serde_json::json!({ "1.0.0": { "name": "source-meta", "version": "1.0.0" } }),
+            dist_tags: HashMap::from([("latest".to_string(), "1.0.0".to_string())]),
+        };
+        let public = abbreviated("https://registry.npmjs.org/source-meta".to_string());
+        disk.write("source-meta", &public, &info, None).unwrap();
+
+        assert!(disk.read("source-meta", &public).is_some());
+        let mirror = abbreviated("https://npm.internal.test/source-meta".to_string());
+        assert!(disk.read("source-meta", &mirror).is_none());
+        let full = MetadataSource {
+            full: true,
+            ..public.clone()
+        };
+        assert!(disk.read("source-meta", &full).is_none());
+        // A mismatch is only a miss; the copy stays until a fetch replaces it.
+        assert!(disk.path("source-meta").exists());
+    }
+
+    /// A self-signed certificate for `registry.internal.test`.
+    const INTERNAL_CA: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBmTCCAT+gAwIBAgIUW1t+p+VkKPcsWz8O9539UJEN2XQwCgYIKoZIzj0EAwIw
+ITEfMB0GA1UEAwwWcmVnaXN0cnkuaW50ZXJuYWwudGVzdDAgFw0yNjEwMTYxNzAy
+MjNaGA8yMTI2MDkyMjE3MDIyM1owITEfMB0GA1UEAwwWcmVnaXN0cnkuaW50ZXJu
+YWwudGVzdDBZMBMGByqGSM49AgEGCCqGSM49AwEHA0IABHo5Zb91ZWR+80DfMc+D
+79BOkwUNqLb7JysNu/bFgICPeUq7HhtYArgV9naeCmFFJjlaNrW/5nlNu1iimQz+
+zsyjUzBRMB0GA1UdDgQWBBTipGmPRYS0h3QcJoRkGTqMn16JwDAfBgNVHSMEGDAW
+gBTipGmPRYS0h3QcJoRkGTqMn16JwDAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49
+BAMCA0gAMEUCIF+ugBy0bZKOyIoPk/lL/sabbIGgJZrby58sDBQIT1fMAiEAoNPz
+Cgu7WB8Uk9zqu7oUzfMt38FywsOo4WV6aFFEGrA=
+-----END CERTIFICATE-----
+";
+
+    fn tls_warnings(
+        settings: &[(&str, String)],
+    ) -> (reqwest::Result<reqwest::Client>, Vec<String>) {
+        let mut config = PacmConfig::default();
+        for (key, value) in settings {
+            config.set(key, value.clone());
+        }
+        let mut warnings = Vec::new();
+        let client = configure_tls(reqwest::Client::builder(), &config, &mut |message| {
+            warnings.push(message)
+        })
+        .build();
+        (client, warnings)
+    }
+
+    #[test]
+    fn test_custom_ca_is_trusted_without_warnings() {
+        let dir = tempfile::tempdir().unwrap();
+        let cafile = dir.path().join("internal-ca.pem");
+        std::fs::write(&cafile, INTERNAL_CA).unwrap();
+
+        let (client, warnings) = tls_warnings(&[("cafile", cafile.display().to_string())]);
+        assert!(client.is_ok());
+        assert!(warnings.is_empty(), "{warnings:?}");
+
+        let (client, warnings) = tls_warnings(&[(
+            "cafile",
+            dir.path().join("missing.pem").display().to_string(),
+        )]);
+        assert!(client.is_ok());
+        assert!(warnings[0].starts_with("Ignoring cafile"), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_disabling_strict_ssl_is_warned_about() {
+        let (client, warnings) = tls_warnings(&[("strict-ssl", "false".to_string())]);
+        assert!(client.is_ok());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("strict-ssl=false"));
+
+        let (_, warnings) = tls_warnings(&[("strict-ssl", "true".to_string())]);
+        assert!(warnings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_redirect_loop_fails_without_retrying() {
+        let requests = Arc::new(std::sync::Mutex::new(0));
+        let server = {
+            let requests = requests.clone();
+            pacm_testutil::MockServer::start(Arc::new(move |req| {
+                *requests.lock().unwrap() += 1;
+                // A misconfigured mirror bouncing between two of its own paths.
+                if req.path == "/loop-meta" {
+                    pacm_testutil::Response::redirect("/loop-meta/")
+                } else {
+                    pacm_testutil::Response::redirect("/loop-meta")
+                }
+            }))
+            .unwrap()
+        };
+        let url = format!("{}loop-meta", server.url());
+        let client = configure_client(reqwest::Client::builder())
+            .build()
+            .unwrap();
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        assert!(!breaker.record_failure());
+
+        let error = fetch_metadata(&client, "loop-meta", &url, None, None, &breaker)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "too many redirects fetching loop-meta");
+        assert_eq!(*requests.lock().unwrap(), 2);
+        // The loop counts against the breaker rather than clearing earlier failures.
+        assert!(breaker.record_failure());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_redirect_moves_tarballs_to_new_registry() {
+        let moved = pacm_testutil::MockServer::start(Arc::new(|_| {
+            pacm_testutil::Response::json(&serde_json::json!({
+                "dist-tags": { "latest": "1.0.0" },
+                "versions": { "1.0.0": { "name": "moved-meta", "version": "1.0.0" } },
+            }))
+        }))
+        .unwrap();
+        let moved_url = moved.url();
+        let old = pacm_testutil::MockServer::start(Arc::new(move |req| {
+            pacm_testutil::Response::redirect(&format!(
+                "{}{}",
+                moved_url.trim_end_matches('/'),
+                req.path
+            ))
+        }))
+        .unwrap();
+        let tarball = |registry: &str| format!("{registry}moved-meta/-/moved-meta-1.0.0.tgz");
+        assert_eq!(redirected_url(&tarball(&old.url())), tarball(&old.url()));
+
+        let client = configure_client(reqwest::Client::builder())
+            .build()
+            .unwrap();
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        let url = format!("{}moved-meta", old.url());
+        let info = modified(
+            fetch_metadata(&client, "moved-meta", &url, None, None, &breaker)
+                .await
+                .unwrap(),
+        );
+        assert_eq!(info.dist_tags["latest"], "1.0.0");
+        assert_eq!(redirected_url(&tarball(&old.url())), tarball(&moved.url()));
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use serde_json::{Value, json};
+
+use pacm_config::PacmConfig;
+use pacm_store::{get_cache_path, store_safe_name};
+
+use crate::PackageInfo;
+
+/// A saved document, along with what is needed to decide whether to revalidate it.
+#[derive(Debug, Clone)]
+pub struct CachedMetadata {
+    pub info: PackageInfo,
+    /// The registry's `ETag` for the document, sent back as `If-None-Match`.
+    pub etag: Option<String>,
+    /// When the registry last sent or confirmed the document.
+    pub fetched: SystemTime,
+}
+
+impl CachedMetadata {
+    /// Whether the document is younger than `max_age` at `now`. One dated in the future,
+    /// after a clock change, is treated as stale.
+    #[must_use]
+    pub fn is_fresh(&self, max_age: Duration, now: SystemTime) -> bool {
+        now.duration_since(self.fetched)
+            .is_ok_and(|age| age < max_age)
+    }
+}
+
+/// Which document a saved copy is: the URL it was fetched from, which names the
+/// registry, and whether it is the full document or the abbreviated one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataSource {
+    pub url: String,
+    pub full: bool,
+}
+
+impl MetadataSource {
+    /// The document the configuration fetches for `name`.
+    #[must_use]
+    pub fn configured(name: &str) -> Self {
+        let config = PacmConfig::get();
+        Self {
+            url: config.package_url(name, &urlencoding::encode(name)),
+            full: config.full_metadata(),
+        }
+    }
+}
+
+/// Package metadata saved across runs, one `<name>.json` per package under
+/// `<cache>/metadata`. Each file records the [`MetadataSource`] it came from, and a copy
+/// saved from another registry or in the other form reads as missing; the next fetch
+/// replaces it. Files are written to a temporary file next to their final path
+/// and renamed into place, so readers only ever see a complete file, even while several
+/// installs refresh the same package.
+#[derive(Debug, Clone)]
+pub struct MetadataCache {
+    dir: PathBuf,
+}
+
+impl MetadataCache {
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The cache under the configured cache directory.
+    #[must_use]
+    pub fn global() -> Self {
+        Self::new(get_cache_path().join("metadata"))
+    }
+
+    #[must_use]
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", store_safe_name(name)))
+    }
+
+    /// The metadata saved for `name` from `source`, or `None` when there is none or it
+    /// cannot be read.
+    #[must_use]
+    pub fn read(&self, name: &str, source: &MetadataSource) -> Option<PackageInfo> {
+        self.read_entry(name, source).map(|entry| entry.info)
+    }
+
+    /// Like [`Self::read`], with the `ETag` and the time the file was last written or
+    /// [touched](Self::touch). A file that cannot be parsed is deleted, so the next
+    /// fetch saves a good copy in its place.
+    #[must_use]
+    pub fn read_entry(&self, name: &str, source: &MetadataSource) -> Option<CachedMetadata> {
+        let path = self.path(name);
+        let fetched = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+        let content = fs::read_to_string(&path).ok()?;
+        let Ok(mut value) = serde_json::from_str::<Value>(&content) else {
+            let _ = fs::remove_file(&path);
+            return None;
+        };
+        let same_source = value.get("url").and_then(Value::as_str) == Some(&source.url)
+            && value.get("full").and_then(Value::as_bool) == Some(source.full);
+        if !same_source {
+            return None;
+        }
+
+        let entry = Self::parse(&mut value, fetched);
+        if entry.is_none() {
+            let _ = fs::remove_file(&path);
+        }
+        entry
+    }
+
+    fn parse(value: &mut Value, fetched: SystemTime) -> Option<CachedMetadata> {
+        let dist_tags: HashMap<String, String> =
+            serde_json::from_value(value.get_mut("dist-tags")?.take()).ok()?;
+
+        Some(CachedMetadata {
+            etag: value
+                .get("etag")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            info: PackageInfo {
+                versions: value.get_mut("versions")?.take(),
+                dist_tags,
+            },
+            fetched,
+        })
+    }
+
+    pub fn write(
+        &self,
+        name: &str,
+        source: &MetadataSource,
+        info: &PackageInfo,
+        etag: Option<&str>,
+    ) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut content = json!({
+            "url": source.url,
+            "full": source.full,
+            "versions": info.versions,
+            "dist-tags": info.dist_tags,
+        });
+        if let Some(etag) = etag {
+            content["etag"] = json!(etag);
+        }
+
+        let mut file = tempfile::NamedTempFile::new_in(&self.dir)?;
+        file.write_all(content.to_string().as_bytes())?;
+        file.persist(self.path(name)).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    /// When a document was last added to or replaced in the cache, from the directory's
+    /// modification time. Revalidations only [touch](Self::touch) their file and leave it
+    /// alone, so it moves only when the saved metadata itself changes.
+    #[must_use]
+    pub fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.dir)
+            .and_then(|meta| meta.modified())
+            .ok()
+    }
+
+    /// Marks the saved metadata for `name` as just confirmed by the registry.
+    pub fn touch(&self, name: &str) -> io::Result<()> {
+        fs::File::options()
+            .write(true)
+            .open(self.path(name))?
+            .set_modified(SystemTime::now())
+    }
+}
@@ -0,0 +1,200 @@
+use base64::Engine;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+use pacm_config::PacmConfig;
+use pacm_constants::USER_AGENT;
+
+/// `registry` with the trailing slash the URLs built from it expect.
+fn registry_base(registry: &str) -> String {
+    format!("{}/", registry.trim_end_matches('/'))
+}
+
+/// The versions of `name` published on `registry`, empty for a package it has never
+/// seen. Always asks the registry, since a cached document may predate the last publish.
+pub async fn published_versions_async(
+    client: Arc<reqwest::Client>,
+    registry: &str,
+    name: &str,
+) -> anyhow::Result<Vec<String>> {
+    let url = format!("{}{}", registry_base(registry), urlencoding::encode(name));
+    let mut request = client
+        .get(&url)
+        .header("Accept", "application/vnd.npm.install-v1+json")
+        .header("Cache-Control", "no-cache")
+        .header("User-Agent", USER_AGENT);
+    if let Some(auth) = PacmConfig::get().auth_header_for(&url) {
+        request = request.header("Authorization", auth);
+    }
+
+    crate::connections::record_request();
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to look up {} on {}: {}", name, registry, e))?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    }
+    let document: Value = resp
+        .error_for_status()
+        .map_err(|e| anyhow::anyhow!("Failed to look up {}: {}", name, e))?
+        .json()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to parse the document of {}: {}", name, e))?;
+
+    Ok(document["versions"]
+        .as_object()
+        .map(|versions| versions.keys().cloned().collect())
+        .unwrap_or_default())
+}
+
+/// The document registries expect in a publish request: `manifest`, the project's
+/// `package.json`, as the one new version with its `dist` filled in, tagged `tag`, and
+/// `tarball` attached.
+pub fn publish_document(
+    registry: &str,
+    manifest: &Value,
+    tarball: &[u8],
+    tag: &str,
+    access: Option<&str>,
+) -> anyhow::Result<Value> {
+    let name = manifest["name"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("package.json has no name"))?;
+    let version = manifest["version"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("package.json has no version"))?;
+    let basename = name.rsplit('/').next().unwrap_or(name);
+    let filename = format!("{basename}-{version}.tgz");
+
+    let mut version_manifest = manifest.clone();
+    version_manifest["_id"] = json!(format!("{name}@{version}"));
+    version_manifest["dist"] = json!({
+        "integrity": pacm_store::integrity_of(tarball),
+        "shasum": shasum(tarball),
+        "tarball": format!("{}{}/-/{}", registry_base(registry), name, filename),
+    });
+
+    Ok(json!({
+        "_id": name,
+        "name": name,
+        "description": manifest.get("description").cloned().unwrap_or_default(),
+        "dist-tags": { tag: version },
+        "versions": { version: version_manifest },
+        "access": access,
+        "_attachments": {
+            filename: {
+                "content_type": "application/octet-stream",
+                "data": base64::engine::general_purpose::STANDARD.encode(tarball),
+                "length": tarball.len(),
+            }
+        },
+    }))
+}
+
+/// The hex SHA-1 of `bytes`, which registries still list as `dist.shasum`.
+fn shasum(bytes: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, bytes)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Sends `document`, built by [`publish_document`], to `registry`. Authentication
+/// failures name the registry the token is configured for, since a missing or read-only
+/// token is the usual reason a publish is refused.
+pub async fn publish_async(
+    client: Arc<reqwest::Client>,
+    registry: &str,
+    name: &str,
+    document: &Value,
+) -> anyhow::Result<()> {
+    let url = format!("{}{}", registry_base(registry), urlencoding::encode(name));
+    let mut request = client
+        .put(&url)
+        .header("Accept", "application/json")
+        .header("User-Agent", USER_AGENT)
+        .json(document);
+    let auth = PacmConfig::get().auth_header_for(&url);
+    if let Some(auth) = &auth {
+        request = request.header("Authorization", auth);
+    }
+
+    crate::connections::record_request();
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to publish to {}: {}", registry, e))?;
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let body = resp.text().await.unwrap_or_default();
+    let reason = serde_json::from_str::<Value>(&body)
+        .ok()
+        .and_then(|body| {
+            body.get("error")
+                .or_else(|| body.get("message"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .unwrap_or(body);
+    match status {
+        reqwest::StatusCode::UNAUTHORIZED if auth.is_none() => Err(anyhow::anyhow!(
+            "{} needs a token to publish; set //<host>/:_authToken in .npmrc",
+            registry
+        )),
+        reqwest::StatusCode::UNAUTHORIZED => Err(anyhow::anyhow!(
+            "{} did not accept the configured token: {}",
+            registry,
+            reason
+        )),
+        reqwest::StatusCode::FORBIDDEN => Err(anyhow::anyhow!(
+            "{} refused the publish (403), the token may not have publish rights for {}: {}",
+            registry,
+            name,
+            reason
+        )),
+        _ => Err(anyhow::anyhow!(
+            "{} answered the publish with {}: {}",
+            registry,
+            status,
+            reason
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_document() {
+        let manifest = json!({ "name": "@acme/widget", "version": "1.2.0" });
+        let document = publish_document(
+            "https://npm.example.com",
+            &manifest,
+            b"tarball",
+            "next",
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(document["dist-tags"], json!({ "next": "1.2.0" }));
+        let dist = &document["versions"]["1.2.0"]["dist"];
+        assert_eq!(
+            dist["tarball"],
+            json!("https://npm.example.com/@acme/widget/-/widget-1.2.0.tgz")
+        );
+        assert_eq!(
+            dist["shasum"],
+            json!("e10f6e70661d167ef514ab6e6d98607438c6a8c6")
+        );
+        assert_eq!(
+            document["_attachments"]["widget-1.2.0.tgz"]["data"],
+            json!("dGFyYmFsbA==")
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use base64::Engine;
+use ring::signature::{ECDSA_P256_SHA256_ASN1, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use pacm_config::PacmConfig;
+use pacm_constants::USER_AGENT;
+
+/// The only key type npm-compatible registries sign with.
+const ECDSA_P256: &str = "ecdsa-sha2-nistp256";
+
+/// DER header of a P-256 `SubjectPublicKeyInfo`; the uncompressed point follows it.
+const P256_SPKI_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+/// One entry of a version's `dist.signatures`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RegistrySignature {
+    pub keyid: String,
+    pub sig: String,
+}
+
+/// The registry signatures in a version's manifest, empty when it has none.
+pub fn dist_signatures(version_data: &Value) -> Vec<RegistrySignature> {
+    version_data
+        .get("dist")
+        .and_then(|dist| dist.get("signatures"))
+        .and_then(|signatures| serde_json::from_value(signatures.clone()).ok())
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SigningKey {
+    pub keyid: String,
+    #[serde(default)]
+    pub keytype: String,
+    /// Base64 of the DER `SubjectPublicKeyInfo`.
+    pub key: String,
+}
+
+/// What checking a package's registry signatures found.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignatureCheck {
+    Verified,
+    /// The package carries no signatures, or its registry publishes no keys.
+    Unsigned,
+    Invalid(String),
+}
+
+/// The signing keys a registry publishes at `/-/npm/v1/keys`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct RegistryKeys {
+    pub keys: Vec<SigningKey>,
+}
+
+impl RegistryKeys {
+    /// Checks `signatures` over `<name>@<version>:<integrity>`, the message npm registries
+    /// sign. Every signature must verify against the key it names.
+    pub fn verify(
+        &self,
+        name: &str,
+        version: &str,
+        integrity: &str,
+        signatures: &[RegistrySignature],
+    ) -> SignatureCheck {
+        if signatures.is_empty() || self.keys.is_empty() {
+            return SignatureCheck::Unsigned;
+        }
+
+        let message = format!("{name}@{version}:{integrity}");
+        for signature in signatures {
+            let Some(key) = self.keys.iter().find(|key| key.keyid == signature.keyid) else {
+                return SignatureCheck::Invalid(format!(
+                    "no registry key matches keyid {}",
+                    signature.keyid
+                ));
+            };
+            if let Err(reason) = verify_signature(key, &message, &signature.sig) {
+                return SignatureCheck::Invalid(reason);
+            }
+        }
+        SignatureCheck::Verified
+    }
+}
+
+fn verify_signature(key: &SigningKey, message: &str, sig: &str) -> Result<(), String> {
+    if key.keytype != ECDSA_P256 {
+        return Err(format!(
+            "key {} has unsupported type {}",
+            key.keyid, key.keytype
+        ));
+    }
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let spki = engine
+        .decode(&key.key)
+        .map_err(|e| format!("key {} is not valid base64: {e}", key.keyid))?;
+    let point = spki
+        .strip_prefix(P256_SPKI_PREFIX.as_slice())
+        .ok_or_else(|| format!("key {} is not a P-256 public key", key.keyid))?;
+    let sig = engine
+        .decode(sig)
+        .map_err(|e| format!("signature is not valid base64: {e}"))?;
+
+    UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, point)
+        .verify(message.as_bytes(), &sig)
+        .map_err(|_| format!("signature does not match key {}", key.keyid))
+}
+
+/// Fetches the signing keys of `registry`. A registry without the keys endpoint has no
+/// keys, so its packages count as unsigned rather than failing the install.
+pub async fn fetch_signing_keys_async(
+    client: &reqwest::Client,
+    registry: &str,
+) -> anyhow::Result<RegistryKeys> {
+    let url = format!("{registry}-/npm/v1/keys");
+    let mut request = client
+        .get(&url)
+        .header("Accept", "application/json")
+        .header("User-Agent", USER_AGENT);
+    if let Some(auth) = PacmConfig::get().auth_header_for(&url) {
+        request = request.header("Authorization", auth);
+    }
+
+    crate::connections::record_request();
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to fetch registry keys: {}", e))?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(RegistryKeys::default());
+    }
+
+    resp.error_for_status()
+        .map_err(|e| anyhow::anyhow!("Registry keys request failed: {}", e))?
+        .json::<RegistryKeys>()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to parse registry keys: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{ECDSA_P256_SHA256_ASN1_SIGNING, EcdsaKeyPair, KeyPair};
+    use std::sync::Arc;
+
+    fn key_pair() -> EcdsaKeyPair {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap()
+    }
+
+    fn signing_key(keyid: &str, pair: &EcdsaKeyPair) -> serde_json::Value {
+        let spki = [P256_SPKI_PREFIX.as_slice(), pair.public_key().as_ref()].concat();
+        serde_json::json!({
+            "keyid": keyid,
+            "keytype": ECDSA_P256,
+            "scheme": ECDSA_P256,
+            "key": base64::engine::general_purpose::STANDARD.encode(spki),
+        })
+    }
+
+    fn sign(keyid: &str, pair: &EcdsaKeyPair, message: &str) -> RegistrySignature {
+        let sig = pair.sign(&SystemRandom::new(), message.as_bytes()).unwrap();
+        RegistrySignature {
+            keyid: keyid.to_string(),
+            sig: base64::engine::general_purpose::STANDARD.encode(sig.as_ref()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verifies_signatures_against_fetched_keys() {
+        let pair = key_pair();
+        let keys = serde_json::json!({ "keys": [signing_key("SHA256:test", &pair)] });
+        let server = pacm_testutil::MockServer::start(Arc::new(move |req| {
+            if req.path == "/-/npm/v1/keys" {
+                pacm_testutil::Response::json(&keys)
+            } else {
+                pacm_testutil::Response::not_found()
+            }
+        }))
+        .unwrap();
+        let client = reqwest::Client::new();
+        let keys = fetch_signing_keys_async(&client, &server.url())
+            .await
+            .unwrap();
+
+        let signed = vec![sign("SHA256:test", &pair, "signed@1.0.0:sha512-abc")];
+        assert_eq!(
+            keys.verify("signed", "1.0.0", "sha512-abc", &signed),
+            SignatureCheck::Verified
+        );
+        assert!(matches!(
+            keys.verify("signed", "1.0.0", "sha512-tampered", &signed),
+            SignatureCheck::Invalid(_)
+        ));
+        let unknown = vec![sign("SHA256:other", &pair, "signed@1.0.0:sha512-abc")];
+        assert!(matches!(
+            keys.verify("signed", "1.0.0", "sha512-abc", &unknown),
+            SignatureCheck::Invalid(_)
+        ));
+        assert_eq!(
+            keys.verify("signed", "1.0.0", "sha512-abc", &[]),
+            SignatureCheck::Unsigned
+        );
+    }
+
+    #[test]
+    fn test_reads_dist_signatures() {
+        let version = serde_json::json!({
+            "dist": { "signatures": [{ "keyid": "SHA256:a", "sig": "MEUC" }] }
+        });
+        assert_eq!(
+            dist_signatures(&version),
+            vec![RegistrySignature {
+                keyid: "SHA256:a".to_string(),
+                sig: "MEUC".to_string(),
+            }]
+        );
+        assert!(dist_signatures(&serde_json::json!({ "dist": {} })).is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde_json::{Map, Value, json};
+
+use pacm_config::PacmConfig;
+use pacm_store::{PathResolver, get_store_path};
+
+use crate::PackageInfo;
+
+/// Metadata for `name` built from the versions already in the store, for resolving
+/// without the registry. Returns `None` when no version of it is stored.
+pub fn stored_package_info(name: &str) -> Option<PackageInfo> {
+    stored_package_info_in(&get_store_path(), name)
+}
+
+/// Like [`stored_package_info`], for the store at `store_base`. Each stored
+/// `package.json` stands in for its registry entry; the tarball URL is derived from the
+/// registry, and `latest` points at the highest stored version.
+pub fn stored_package_info_in(store_base: &Path, name: &str) -> Option<PackageInfo> {
+    let package_base = PathResolver::get_package_base_path(store_base, name);
+    let mut versions = Map::new();
+
+    for entry in fs::read_dir(package_base).ok()?.flatten() {
+        let version = entry.file_name().to_string_lossy().into_owned();
+        let Some(mut manifest) =
+            fs::read_to_string(entry.path().join("package").join("package.json"))
+                .ok()
+                .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        else {
+            continue;
+        };
+
+        if let Some(manifest) = manifest.as_object_mut() {
+            manifest.entry("dist").or_insert_with(
+                || json!({ "tarball": PacmConfig::get().tarball_url(name, &version) }),
+            );
+        }
+        versions.insert(version, manifest);
+    }
+
+    let latest = versions
+        .keys()
+        .filter_map(|version| semver::Version::parse(version).ok())
+        .max()?;
+
+    Some(PackageInfo {
+        versions: Value::Object(versions),
+        dist_tags: HashMap::from([("latest".to_string(), latest.to_string())]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(store_base: &Path, name: &str, version: &str) {
+        let package_dir = PathResolver::get_package_path(store_base, name, version).join("package");
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(
+            package_dir.join("package.json"),
+            json!({ "name": name, "version": version, "dependencies": { "dep": "^1.0.0" } })
+                .to_string(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_stored_package_info_lists_stored_versions() {
+        let store_base = tempfile::tempdir().unwrap();
+        store(store_base.path(), "@scope/pkg", "1.2.0");
+        store(store_base.path(), "@scope/pkg", "1.10.0");
+
+        let info = stored_package_info_in(store_base.path(), "@scope/pkg").unwrap();
+
+        assert_eq!(info.dist_tags["latest"], "1.10.0");
+        assert_eq!(info.versions["1.2.0"]["dependencies"]["dep"], "^1.0.0");
+        assert!(
+            info.versions["1.2.0"]["dist"]["tarball"]
+                .as_str()
+                .unwrap()
+                .ends_with("/@scope/pkg/-/pkg-1.2.0.tgz")
+        );
+        assert!(stored_package_info_in(store_base.path(), "missing").is_none());
+    }
+}
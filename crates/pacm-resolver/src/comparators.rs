@@ -1,4 +1,5 @@
 use semver::Version;
+use serde::Serialize;
 
 #[derive(Debug, Clone)]
 pub enum Comparator {
@@ -66,3 +67,62 @@ impl Range {
         self.comparators.iter().all(|comp| comp.matches(version))
     }
 }
+
+/// The most significant part that changes between two versions, as reported by
+/// `pacm outdated`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionDiff {
+    Major,
+    Minor,
+    Patch,
+    Prerelease,
+}
+
+impl VersionDiff {
+    /// Classifies the step from `from` to `to`, or returns `None` when `to` is not newer.
+    /// Versions that only differ in their prerelease tag count as `Prerelease`.
+    pub fn between(from: &Version, to: &Version) -> Option<Self> {
+        if to <= from {
+            return None;
+        }
+
+        Some(if to.major != from.major {
+            Self::Major
+        } else if to.minor != from.minor {
+            Self::Minor
+        } else if to.patch != from.patch {
+            Self::Patch
+        } else {
+            Self::Prerelease
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Major => "major",
+            Self::Minor => "minor",
+            Self::Patch => "patch",
+            Self::Prerelease => "prerelease",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(from: &str, to: &str) -> Option<VersionDiff> {
+        VersionDiff::between(&Version::parse(from).unwrap(), &Version::parse(to).unwrap())
+    }
+
+    #[test]
+    fn test_version_diff_picks_most_significant_change() {
+        assert_eq!(diff("1.2.3", "2.0.0"), Some(VersionDiff::Major));
+        assert_eq!(diff("1.2.3", "1.3.0"), Some(VersionDiff::Minor));
+        assert_eq!(diff("1.2.3", "1.2.4"), Some(VersionDiff::Patch));
+        assert_eq!(diff("1.2.3-beta.1", "1.2.3"), Some(VersionDiff::Prerelease));
+        assert_eq!(diff("1.2.3", "1.2.3"), None);
+        assert_eq!(diff("2.0.0", "1.9.9"), None);
+    }
+}
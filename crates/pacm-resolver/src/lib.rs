@@ -1,16 +1,31 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
+use pacm_registry::RegistrySignature;
+use serde::{Deserialize, Serialize};
+
 pub mod comparators;
+pub mod limits;
+pub mod overrides;
 pub mod platform;
 pub mod resolver;
 pub mod semver;
+pub mod source;
+pub mod tree;
 pub mod version_utils;
 
+pub use crate::semver::satisfies;
+pub use comparators::VersionDiff;
+pub use limits::ResolutionLimits;
+pub use overrides::Overrides;
 pub use platform::{get_current_cpu, get_current_os, is_platform_compatible};
-pub use resolver::DependencyResolver;
+pub use resolver::{
+    DependencyResolver, bundled_dependencies, optional_peer_dependencies, peer_dependencies,
+};
+pub use source::{MetadataPlan, package_info, package_info_async};
+pub use tree::{TreeNode, dependency_tree, print_tree, tree_lines};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct ResolvedPackage {
     pub name: String,
     pub version: String,
@@ -20,6 +35,18 @@ pub struct ResolvedPackage {
     pub optional_dependencies: HashMap<String, String>, // Name => version range
     pub os: Option<Vec<String>>,               // OS requirements (e.g., ["win32", "darwin"])
     pub cpu: Option<Vec<String>>,              // CPU requirements (e.g., ["x64", "arm64"])
+    pub deprecated: Option<String>,            // Registry deprecation message
+    pub bundled_dependencies: Vec<String>,     // Shipped inside the tarball, never fetched
+    pub peer_dependencies: HashMap<String, String>, // Required peers: name => version range
+    pub optional_peer_dependencies: HashMap<String, String>, // Peers marked optional in peerDependenciesMeta
+    #[serde(default)]
+    pub signatures: Vec<RegistrySignature>, // dist.signatures, checked by --audit-signatures
+}
+
+impl std::fmt::Display for ResolvedPackage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
 }
 
 pub fn resolve_full_tree(
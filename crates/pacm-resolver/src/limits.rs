@@ -0,0 +1,62 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use pacm_config::PacmConfig;
+use pacm_error::PackageManagerError;
+
+/// Caps on the size of a resolution, shared by every resolver working on the same
+/// install. The `seen` sets only stop a package from being resolved twice in one branch,
+/// so a chain of distinct versions, each depending on the next, would otherwise be
+/// followed until memory or the stack runs out.
+pub struct ResolutionLimits {
+    max_depth: usize,
+    max_packages: usize,
+    packages: Mutex<HashSet<String>>,
+}
+
+impl ResolutionLimits {
+    pub fn new(max_depth: usize, max_packages: usize) -> Self {
+        Self {
+            max_depth,
+            max_packages,
+            packages: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// The limits from the `max-resolution-depth` and `max-packages` settings.
+    pub fn from_config() -> Self {
+        let config = PacmConfig::get();
+        Self::new(config.max_resolution_depth(), config.max_packages())
+    }
+
+    /// Counts `key`, a `name@version` reached through `path`, failing once the tree is
+    /// deeper or holds more distinct packages than allowed.
+    pub(crate) fn admit(&self, key: &str, path: &[String]) -> Result<(), PackageManagerError> {
+        if path.len() >= self.max_depth {
+            return Err(PackageManagerError::ResolutionLimitExceeded {
+                setting: "max-resolution-depth",
+                limit: self.max_depth,
+                package: key.to_string(),
+                path: path.to_vec(),
+            });
+        }
+
+        let mut packages = self.packages.lock().unwrap_or_else(|e| e.into_inner());
+        if !packages.contains(key) && packages.len() >= self.max_packages {
+            return Err(PackageManagerError::ResolutionLimitExceeded {
+                setting: "max-packages",
+                limit: self.max_packages,
+                package: key.to_string(),
+                path: path.to_vec(),
+            });
+        }
+        packages.insert(key.to_string());
+        Ok(())
+    }
+}
+
+impl Default for ResolutionLimits {
+    fn default() -> Self {
+        Self::from_config()
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+use crate::semver::satisfies;
+
+/// A package in an override key, optionally limited to versions in `range`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Selector {
+    name: String,
+    range: Option<String>,
+}
+
+impl Selector {
+    fn parse(key: &str) -> Self {
+        // The first character is skipped so `@scope/name` is not mistaken for a range.
+        match key.char_indices().skip(1).find(|(_, c)| *c == '@') {
+            Some((at, _)) => Self {
+                name: key[..at].to_string(),
+                range: Some(key[at + 1..].to_string()),
+            },
+            None => Self {
+                name: key.to_string(),
+                range: None,
+            },
+        }
+    }
+
+    /// Whether the resolved package `name@version` is selected.
+    fn matches(&self, resolved: &str) -> bool {
+        let (name, version) = match resolved.char_indices().skip(1).find(|(_, c)| *c == '@') {
+            Some((at, _)) => (&resolved[..at], &resolved[at + 1..]),
+            None => (resolved, ""),
+        };
+        self.name == name
+            && self
+                .range
+                .as_deref()
+                .is_none_or(|range| satisfies(version, range))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OverrideRule {
+    /// Packages the dependency has to be reached through, outermost first.
+    parents: Vec<Selector>,
+    name: String,
+    range: String,
+}
+
+/// The `overrides` field of the root `package.json`. An entry replaces the range every
+/// dependent asks for:
+///
+/// ```json
+/// "overrides": {
+///   "bar": "1.2.3",
+///   "foo": { "bar": "2.0.0" },
+///   "baz@^1.0.0>bar": "1.0.0"
+/// }
+/// ```
+///
+/// A nested object or `>` path limits the override to `bar` reached through `foo`, at
+/// any depth below it; a `"."` key inside an object overrides the parent itself. Parents
+/// may carry a version range. When several entries match, the one naming the most
+/// parents wins. Installs reject path-scoped entries that leave a package resolved at
+/// two versions, since `node_modules` holds one copy per name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Overrides {
+    rules: Vec<OverrideRule>,
+}
+
+impl Overrides {
+    pub fn parse(overrides: &Value) -> Self {
+        let mut rules = Vec::new();
+        Self::collect(&[], overrides, &mut rules);
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn collect(parents: &[Selector], value: &Value, rules: &mut Vec<OverrideRule>) {
+        let Some(entries) = value.as_object() else {
+            return;
+        };
+
+        for (key, value) in entries {
+            let mut chain = parents.to_vec();
+            if key != "." {
+                chain.extend(key.split('>').map(|part| Selector::parse(part.trim())));
+            }
+
+            match value {
+                Value::String(range) => {
+                    if let Some((target, parents)) = chain.split_last() {
+                        rules.push(OverrideRule {
+                            parents: parents.to_vec(),
+                            name: target.name.clone(),
+                            range: range.clone(),
+                        });
+                    }
+                }
+                Value::Object(_) => Self::collect(&chain, value, rules),
+                _ => {}
+            }
+        }
+    }
+
+    /// The range to use for `name` when it is required below `path`, the resolved
+    /// `name@version` of each package from the direct dependency down.
+    pub fn range_for(&self, name: &str, path: &[String]) -> Option<&str> {
+        let mut best: Option<&OverrideRule> = None;
+        for rule in self.rules.iter().filter(|rule| rule.name == name) {
+            if Self::reached_through(&rule.parents, path)
+                && best.is_none_or(|best| rule.parents.len() > best.parents.len())
+            {
+                best = Some(rule);
+            }
+        }
+        best.map(|rule| rule.range.as_str())
+    }
+
+    /// Names overridden only below some parent. Dependents elsewhere keep their own
+    /// range, so these can resolve to more than one version.
+    pub fn scoped_names(&self) -> HashSet<&str> {
+        self.rules
+            .iter()
+            .filter(|rule| !rule.parents.is_empty())
+            .map(|rule| rule.name.as_str())
+            .collect()
+    }
+
+    /// Replaces the ranges in `dependencies` of the package at the end of `path`.
+    pub fn apply(&self, dependencies: &mut HashMap<String, String>, path: &[String]) {
+        if self.is_empty() {
+            return;
+        }
+        for (name, range) in dependencies.iter_mut() {
+            if let Some(overridden) = self.range_for(name, path) {
+                *range = overridden.to_string();
+            }
+        }
+    }
+
+    fn reached_through(parents: &[Selector], path: &[String]) -> bool {
+        let mut path = path.iter();
+        parents
+            .iter()
+            .all(|parent| path.any(|resolved| parent.matches(resolved)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn test_nested_and_path_overrides_only_apply_below_their_parent() {
+        let overrides = Overrides::parse(&serde_json::json!({
+            "flat": "1.0.0",
+            "foo": { ".": "3.0.0", "bar": "2.0.0" },
+            "@scope/pkg@^1.0.0>bar": "1.5.0",
+            "outer": { "inner": { "bar": "0.1.0" } },
+        }));
+
+        assert_eq!(overrides.range_for("flat", &[]), Some("1.0.0"));
+        assert_eq!(overrides.range_for("foo", &[]), Some("3.0.0"));
+        assert_eq!(overrides.range_for("bar", &[]), None);
+        assert_eq!(
+            overrides.range_for("bar", &path(&["foo@3.0.0", "mid@1.0.0"])),
+            Some("2.0.0")
+        );
+        assert_eq!(overrides.range_for("bar", &path(&["baz@1.0.0"])), None);
+        assert_eq!(
+            overrides.range_for("bar", &path(&["@scope/pkg@1.2.0"])),
+            Some("1.5.0")
+        );
+        assert_eq!(
+            overrides.range_for("bar", &path(&["@scope/pkg@2.0.0"])),
+            None
+        );
+        assert_eq!(
+            overrides.range_for("bar", &path(&["foo@3.0.0", "outer@1.0.0", "inner@1.0.0"])),
+            Some("0.1.0")
+        );
+        assert_eq!(
+            overrides.range_for("bar", &path(&["inner@1.0.0", "outer@1.0.0"])),
+            None
+        );
+    }
+}
@@ -12,20 +12,18 @@ pub fn is_platform_compatible(
     let current_os = get_current_os();
     let current_cpu = get_current_cpu();
 
-    if let Some(os_requirements) = os_list {
-        if !os_requirements.is_empty() {
-            if !is_platform_field_compatible(&current_os, os_requirements) {
-                return false;
-            }
-        }
+    if let Some(os_requirements) = os_list
+        && !os_requirements.is_empty()
+        && !is_platform_field_compatible(&current_os, os_requirements)
+    {
+        return false;
     }
 
-    if let Some(cpu_requirements) = cpu_list {
-        if !cpu_requirements.is_empty() {
-            if !is_platform_field_compatible(&current_cpu, cpu_requirements) {
-                return false;
-            }
-        }
+    if let Some(cpu_requirements) = cpu_list
+        && !cpu_requirements.is_empty()
+        && !is_platform_field_compatible(&current_cpu, cpu_requirements)
+    {
+        return false;
     }
 
     true
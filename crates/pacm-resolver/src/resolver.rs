@@ -1,46 +1,179 @@
 use futures::future::join_all;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
 use crate::ResolvedPackage;
+use crate::limits::ResolutionLimits;
+use crate::overrides::Overrides;
 use crate::platform::is_platform_compatible;
-use crate::semver::resolve_version;
+use crate::semver::resolve_version_preferring;
+use crate::source::{package_info, package_info_async};
+use pacm_error::PackageManagerError;
 use pacm_logger;
-use pacm_registry::{fetch_package_info, fetch_package_info_async};
 
 pub struct DependencyResolver {
     resolution_cache: Arc<Mutex<HashMap<String, Vec<ResolvedPackage>>>>,
+    include_optional: bool,
+    max_depth: Option<usize>,
+    overrides: Arc<Overrides>,
+    preferred: Arc<HashMap<String, Vec<String>>>,
+    limits: Arc<ResolutionLimits>,
+    fetch_timer: Option<fn(&str, Duration)>,
+    path: Vec<String>,
 }
 
 impl DependencyResolver {
     pub fn new() -> Self {
         Self {
             resolution_cache: Arc::new(Mutex::new(HashMap::with_capacity(1000))), // Pre-allocate capacity
+            include_optional: true,
+            max_depth: None,
+            overrides: Arc::new(Overrides::default()),
+            preferred: Arc::new(HashMap::new()),
+            limits: Arc::new(ResolutionLimits::default()),
+            fetch_timer: None,
+            path: Vec::new(),
         }
     }
 
+    /// Controls whether `optionalDependencies` are descended into.
+    #[must_use]
+    pub fn with_optional(mut self, include_optional: bool) -> Self {
+        self.include_optional = include_optional;
+        self
+    }
+
+    /// Limits how many levels below the requested package are resolved. Depth 0 resolves
+    /// only the package itself; `None` resolves the full tree.
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Replaces the ranges of the dependencies selected by the root `overrides`.
+    #[must_use]
+    pub fn with_overrides(mut self, overrides: Arc<Overrides>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Versions already in the tree, by name. A range that allows one of them resolves to
+    /// it rather than to the highest version, so the tree gains fewer new versions.
+    #[must_use]
+    pub fn with_preferred_versions(mut self, preferred: Arc<HashMap<String, Vec<String>>>) -> Self {
+        self.preferred = preferred;
+        self
+    }
+
+    /// Counts the packages resolved against `limits`, which other resolvers of the same
+    /// install may share. Defaults to the `max-resolution-depth` and `max-packages`
+    /// settings.
+    #[must_use]
+    pub fn with_limits(mut self, limits: Arc<ResolutionLimits>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Reports how long each package's own metadata lookup took, for every package in
+    /// the tree. Resolving its dependencies is not included.
+    #[must_use]
+    pub fn with_fetch_timer(mut self, timer: fn(&str, Duration)) -> Self {
+        self.fetch_timer = Some(timer);
+        self
+    }
+
+    /// The resolver for the dependencies of `parent`, or `None` once the depth limit is
+    /// reached. It remembers the chain of parents for error messages.
+    fn child_resolver(&self, parent: &str) -> Option<DependencyResolver> {
+        if self.max_depth == Some(0) {
+            return None;
+        }
+
+        let mut child = DependencyResolver::new()
+            .with_optional(self.include_optional)
+            .with_max_depth(self.max_depth.map(|depth| depth - 1))
+            .with_overrides(self.overrides.clone())
+            .with_preferred_versions(self.preferred.clone())
+            .with_limits(self.limits.clone());
+        child.fetch_timer = self.fetch_timer;
+        child.path = self.path.clone();
+        child.path.push(parent.to_string());
+        Some(child)
+    }
+
+    fn record_fetch(&self, name: &str, started: Instant) {
+        if let Some(timer) = self.fetch_timer {
+            timer(name, started.elapsed());
+        }
+    }
+
+    fn select_version(
+        &self,
+        name: &str,
+        pkg_data: &pacm_registry::PackageInfo,
+        version_range: &str,
+    ) -> Result<String, String> {
+        let preferred = self
+            .preferred
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        resolve_version_preferring(
+            &pkg_data.versions,
+            version_range,
+            &pkg_data.dist_tags,
+            preferred,
+        )
+    }
+
+    /// The error for `name@version_range` failing to resolve at this point of the tree.
+    fn resolution_failed(
+        &self,
+        name: &str,
+        version_range: &str,
+        reason: impl std::fmt::Display,
+    ) -> anyhow::Error {
+        PackageManagerError::DependencyResolutionFailed {
+            name: name.to_string(),
+            range: version_range.to_string(),
+            path: self.path.clone(),
+            reason: reason.to_string(),
+        }
+        .into()
+    }
+
     pub fn resolve_full_tree(
         &self,
         name: &str,
         version_range: &str,
         seen: &mut HashSet<String>,
     ) -> anyhow::Result<Vec<ResolvedPackage>> {
+        let version_range = self
+            .overrides
+            .range_for(name, &self.path)
+            .unwrap_or(version_range);
         let mut resolved = vec![];
 
-        let pkg_data = fetch_package_info(name)?;
-        let selected_version =
-            resolve_version(&pkg_data.versions, version_range, &pkg_data.dist_tags)
-                .map_err(|e| anyhow::anyhow!("Cannot resolve version for {}: {}", name, e))?;
+        let started = Instant::now();
+        let pkg_data = package_info(name, version_range);
+        self.record_fetch(name, started);
+        let pkg_data = pkg_data.map_err(|e| self.resolution_failed(name, version_range, e))?;
+        let selected_version = self
+            .select_version(name, &pkg_data, version_range)
+            .map_err(|e| self.resolution_failed(name, version_range, e))?;
         let version_data = &pkg_data.versions[&selected_version];
 
         let key = format!("{}@{}", name, selected_version);
         if seen.contains(&key) {
             return Ok(vec![]); // Cycle detected → ignore
         }
+        self.limits.admit(&key, &self.path)?;
         seen.insert(key.clone());
 
-        let dependencies: HashMap<String, String> = version_data
+        let mut dependencies: HashMap<String, String> = version_data
             .get("dependencies")
             .and_then(|d| d.as_object())
             .map(|deps| {
@@ -50,7 +183,7 @@ impl DependencyResolver {
             })
             .unwrap_or_default();
 
-        let optional_dependencies: HashMap<String, String> = version_data
+        let mut optional_dependencies: HashMap<String, String> = version_data
             .get("optionalDependencies")
             .and_then(|d| d.as_object())
             .map(|deps| {
@@ -60,6 +193,18 @@ impl DependencyResolver {
             })
             .unwrap_or_default();
 
+        let bundled = bundled_dependencies(version_data);
+        for name in &bundled {
+            dependencies.remove(name);
+            optional_dependencies.remove(name);
+        }
+
+        let mut dependent_path = self.path.clone();
+        dependent_path.push(key.clone());
+        self.overrides.apply(&mut dependencies, &dependent_path);
+        self.overrides
+            .apply(&mut optional_dependencies, &dependent_path);
+
         let os = version_data
             .get("os")
             .and_then(|os| os.as_array())
@@ -95,17 +240,32 @@ impl DependencyResolver {
             optional_dependencies,
             os,
             cpu,
+            deprecated: deprecation_message(version_data),
+            bundled_dependencies: bundled,
+            peer_dependencies: peer_dependencies(version_data),
+            optional_peer_dependencies: optional_peer_dependencies(version_data),
+            signatures: pacm_registry::dist_signatures(version_data),
         };
 
         resolved.push(resolved_pkg.clone());
 
+        let Some(child) = self.child_resolver(&key) else {
+            return Ok(resolved);
+        };
+
         for (dep_name, dep_range) in dependencies {
-            let sub = self.resolve_full_tree(&dep_name, &dep_range, seen)?;
+            let sub = child.resolve_full_tree(&dep_name, &dep_range, seen)?;
             resolved.extend(sub);
         }
 
-        for (dep_name, dep_range) in &resolved_pkg.optional_dependencies {
-            match self.resolve_full_tree(dep_name, dep_range, seen) {
+        let optional_dependencies = if self.include_optional {
+            resolved_pkg.optional_dependencies.clone()
+        } else {
+            HashMap::new()
+        };
+
+        for (dep_name, dep_range) in &optional_dependencies {
+            match child.resolve_full_tree(dep_name, dep_range, seen) {
                 Ok(sub) => {
                     let mut all_compatible = true;
                     for pkg in &sub {
@@ -142,6 +302,10 @@ impl DependencyResolver {
         version_range: &str,
         seen: &mut HashSet<String>,
     ) -> anyhow::Result<Vec<ResolvedPackage>> {
+        let version_range = self
+            .overrides
+            .range_for(name, &self.path)
+            .unwrap_or(version_range);
         let cache_key = format!("{}@{}", name, version_range);
 
         {
@@ -163,23 +327,27 @@ impl DependencyResolver {
         }
 
         let mut resolved = Vec::with_capacity(50); // Pre-allocate capacity
-        let pkg_data = fetch_package_info_async(client.clone(), name)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to fetch package info for {}: {}", name, e))?;
+        let started = Instant::now();
+        let pkg_data = package_info_async(client.clone(), name, version_range).await;
+        self.record_fetch(name, started);
+        let pkg_data = pkg_data.map_err(|e| self.resolution_failed(name, version_range, e))?;
 
-        let selected_version =
-            resolve_version(&pkg_data.versions, version_range, &pkg_data.dist_tags)
-                .map_err(|e| anyhow::anyhow!("Cannot resolve version for {}: {}", name, e))?;
+        let selected_version = self
+            .select_version(name, &pkg_data, version_range)
+            .map_err(|e| self.resolution_failed(name, version_range, e))?;
 
         let version_data = &pkg_data.versions[&selected_version];
 
         let key = format!("{}@{}", name, selected_version);
-        if seen.contains(&key) {
+        // Each dependency starts with a `seen` set of its own, so a cycle only shows up as
+        // the package already being resolved further up the path.
+        if seen.contains(&key) || self.path.contains(&key) {
             return Ok(vec![]); // Cycle detected → ignore
         }
+        self.limits.admit(&key, &self.path)?;
         seen.insert(key.clone());
 
-        let dependencies: HashMap<String, String> = version_data
+        let mut dependencies: HashMap<String, String> = version_data
             .get("dependencies")
             .and_then(|d| d.as_object())
             .map(|deps| {
@@ -189,7 +357,7 @@ impl DependencyResolver {
             })
             .unwrap_or_default();
 
-        let optional_dependencies: HashMap<String, String> = version_data
+        let mut optional_dependencies: HashMap<String, String> = version_data
             .get("optionalDependencies")
             .and_then(|d| d.as_object())
             .map(|deps| {
@@ -199,6 +367,18 @@ impl DependencyResolver {
             })
             .unwrap_or_default();
 
+        let bundled = bundled_dependencies(version_data);
+        for name in &bundled {
+            dependencies.remove(name);
+            optional_dependencies.remove(name);
+        }
+
+        let mut dependent_path = self.path.clone();
+        dependent_path.push(key.clone());
+        self.overrides.apply(&mut dependencies, &dependent_path);
+        self.overrides
+            .apply(&mut optional_dependencies, &dependent_path);
+
         let os = version_data
             .get("os")
             .and_then(|os| os.as_array())
@@ -234,6 +414,11 @@ impl DependencyResolver {
             optional_dependencies,
             os,
             cpu,
+            deprecated: deprecation_message(version_data),
+            bundled_dependencies: bundled,
+            peer_dependencies: peer_dependencies(version_data),
+            optional_peer_dependencies: optional_peer_dependencies(version_data),
+            signatures: pacm_registry::dist_signatures(version_data),
         };
 
         resolved.push(resolved_pkg);
@@ -241,11 +426,11 @@ impl DependencyResolver {
         if !dependencies.is_empty() {
             let dep_tasks: Vec<_> = dependencies
                 .into_iter()
-                .map(|(dep_name, dep_range)| {
+                .filter_map(|(dep_name, dep_range)| {
                     let client_clone = client.clone();
-                    let resolver = DependencyResolver::new();
+                    let resolver = self.child_resolver(&key)?;
 
-                    async move {
+                    Some(async move {
                         let mut local_seen = HashSet::with_capacity(100); // Pre-allocate
                         resolver
                             .resolve_full_tree_async(
@@ -255,7 +440,7 @@ impl DependencyResolver {
                                 &mut local_seen,
                             )
                             .await
-                    }
+                    })
                 })
                 .collect();
 
@@ -272,25 +457,23 @@ impl DependencyResolver {
                             }
                         }
                     }
-                    Err(e) => {
-                        pacm_logger::debug(&format!("Failed to resolve dependency: {}", e), false);
-                    }
+                    Err(e) => return Err(e),
                 }
             }
         }
 
         let current_pkg = resolved.last().unwrap(); // We just pushed it
-        if !current_pkg.optional_dependencies.is_empty() {
+        if self.include_optional && !current_pkg.optional_dependencies.is_empty() {
             let optional_dep_tasks: Vec<_> = current_pkg
                 .optional_dependencies
                 .iter()
-                .map(|(dep_name, dep_range)| {
+                .filter_map(|(dep_name, dep_range)| {
                     let client_clone = client.clone();
-                    let resolver = DependencyResolver::new();
+                    let resolver = self.child_resolver(&key)?;
                     let dep_name = dep_name.clone();
                     let dep_range = dep_range.clone();
 
-                    async move {
+                    Some(async move {
                         let mut local_seen = HashSet::with_capacity(100);
                         let result = resolver
                             .resolve_full_tree_async(
@@ -302,7 +485,7 @@ impl DependencyResolver {
                             .await;
 
                         (dep_name, result)
-                    }
+                    })
                 })
                 .collect();
 
@@ -355,3 +538,70 @@ impl Default for DependencyResolver {
         Self::new()
     }
 }
+
+/// Reads the registry's `deprecated` field. Registries sometimes send `false` or an empty
+/// string for packages that are not deprecated.
+fn deprecation_message(version_data: &serde_json::Value) -> Option<String> {
+    version_data
+        .get("deprecated")
+        .and_then(|d| d.as_str())
+        .filter(|message| !message.is_empty())
+        .map(str::to_string)
+}
+
+/// Names listed in `bundledDependencies` (or the older `bundleDependencies`). These ship
+/// inside the package's own tarball, so they are neither resolved nor downloaded. `true`
+/// bundles every entry of `dependencies`.
+pub fn bundled_dependencies(manifest: &serde_json::Value) -> Vec<String> {
+    let bundled = manifest
+        .get("bundledDependencies")
+        .or_else(|| manifest.get("bundleDependencies"));
+
+    match bundled {
+        Some(serde_json::Value::Array(names)) => names
+            .iter()
+            .filter_map(|name| name.as_str())
+            .map(str::to_string)
+            .collect(),
+        Some(serde_json::Value::Bool(true)) => manifest
+            .get("dependencies")
+            .and_then(|d| d.as_object())
+            .map(|deps| deps.keys().cloned().collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Entries of `peerDependencies`, minus those `peerDependenciesMeta` marks as optional.
+pub fn peer_dependencies(manifest: &serde_json::Value) -> HashMap<String, String> {
+    peers_by_optionality(manifest, false)
+}
+
+/// Entries of `peerDependencies` that `peerDependenciesMeta` marks as optional. A missing
+/// optional peer is not an error; it is only used when something else installs it.
+pub fn optional_peer_dependencies(manifest: &serde_json::Value) -> HashMap<String, String> {
+    peers_by_optionality(manifest, true)
+}
+
+fn peers_by_optionality(manifest: &serde_json::Value, optional: bool) -> HashMap<String, String> {
+    let is_optional = |name: &str| {
+        manifest
+            .get("peerDependenciesMeta")
+            .and_then(|meta| meta.get(name))
+            .and_then(|meta| meta.get("optional"))
+            .and_then(|optional| optional.as_bool())
+            .unwrap_or(false)
+    };
+
+    manifest
+        .get("peerDependencies")
+        .and_then(|peers| peers.as_object())
+        .map(|peers| {
+            peers
+                .iter()
+                .filter(|(name, _)| is_optional(name) == optional)
+                .filter_map(|(name, range)| Some((name.clone(), range.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
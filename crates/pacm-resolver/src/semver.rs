@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
 
 use crate::comparators::{Comparator, Range};
@@ -127,6 +128,24 @@ fn extract_version_and_remaining(input: &str) -> Result<(String, &str), String>
     Ok((version_str, remaining))
 }
 
+/// Returns whether `version` falls inside the npm range `range`.
+pub fn satisfies(version: &str, range: &str) -> bool {
+    let Ok(version) = semver::Version::parse(version) else {
+        return false;
+    };
+
+    parse_npm_semver_ranges(range)
+        .map(|ranges| ranges.iter().any(|r| r.matches(&version)))
+        .unwrap_or(false)
+}
+
+/// Orders candidates from the highest version down. Versions that compare equal, which
+/// only differ in build metadata npm ignores, fall back to their exact string so the pick
+/// does not depend on the order the registry listed them in.
+fn newest_first(a: &(semver::Version, String), b: &(semver::Version, String)) -> Ordering {
+    b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1))
+}
+
 pub fn resolve_version(
     available_versions: &serde_json::Value,
     range: &str,
@@ -147,7 +166,7 @@ pub fn resolve_version(
         .filter_map(|v_str| Version::parse(v_str).ok().map(|v| (v, v_str.clone())))
         .collect();
 
-    candidates.sort_by(|a, b| b.0.cmp(&a.0));
+    candidates.sort_by(newest_first);
 
     let allows_prerelease = range.contains('-');
     let filtered: Vec<(Version, String)> = candidates
@@ -167,3 +186,67 @@ pub fn resolve_version(
         Err(format!("No matching version found for range '{}'", range))
     }
 }
+
+/// Like [`resolve_version`], but picks the highest of `preferred` that `range` allows and
+/// the registry still lists before considering newer versions. Dist-tags are resolved as
+/// usual.
+pub fn resolve_version_preferring(
+    available_versions: &serde_json::Value,
+    range: &str,
+    dist_tags: &HashMap<String, String>,
+    preferred: &[String],
+) -> Result<String, String> {
+    if !dist_tags.contains_key(range)
+        && let Some((_, version)) = preferred
+            .iter()
+            .filter(|version| available_versions.get(version.as_str()).is_some())
+            .filter(|version| satisfies(version, range))
+            .filter_map(|version| Some((semver::Version::parse(version).ok()?, version.clone())))
+            .min_by(newest_first)
+    {
+        return Ok(version);
+    }
+
+    resolve_version(available_versions, range, dist_tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferred_version_wins_when_range_allows_it() {
+        let versions = serde_json::json!({ "1.0.0": {}, "1.2.0": {}, "1.4.0": {}, "2.0.0": {} });
+        let tags = HashMap::from([("latest".to_string(), "2.0.0".to_string())]);
+        let preferred = ["1.2.0".to_string(), "1.3.0".to_string()];
+
+        let pick = |range| resolve_version_preferring(&versions, range, &tags, &preferred);
+        assert_eq!(pick("^1.0.0").as_deref(), Ok("1.2.0"));
+        assert_eq!(pick("~1.4.0").as_deref(), Ok("1.4.0"));
+        assert_eq!(pick("latest").as_deref(), Ok("2.0.0"));
+        assert_eq!(
+            resolve_version_preferring(&versions, "^1.0.0", &tags, &[]).as_deref(),
+            Ok("1.4.0")
+        );
+    }
+
+    #[test]
+    fn test_equal_versions_resolve_the_same_in_any_listing_order() {
+        let listings = [
+            r#"{ "1.0.0+build.b": {}, "1.0.0+build.a": {}, "1.0.0-rc.1": {}, "0.9.0": {} }"#,
+            r#"{ "0.9.0": {}, "1.0.0+build.a": {}, "1.0.0-rc.1": {}, "1.0.0+build.b": {} }"#,
+            r#"{ "1.0.0-rc.1": {}, "1.0.0+build.a": {}, "0.9.0": {}, "1.0.0+build.b": {} }"#,
+        ];
+        let tags = HashMap::new();
+
+        let picks: Vec<String> = listings
+            .iter()
+            .map(|listing| {
+                let versions: serde_json::Value = serde_json::from_str(listing).unwrap();
+                resolve_version(&versions, "^1.0.0", &tags).unwrap()
+            })
+            .collect();
+        assert!(picks.iter().all(|pick| pick == &picks[0]), "{picks:?}");
+        assert!(picks[0].starts_with("1.0.0+build."));
+    }
+}
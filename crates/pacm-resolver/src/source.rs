@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use pacm_config::{NetworkMode, PacmConfig};
+use pacm_registry::{
+    MetadataCache, MetadataSource, PackageInfo, fetch_package_info, fetch_package_info_async,
+    fetch_pinned_package_info_async, stored_package_info,
+};
+
+use crate::semver::resolve_version;
+
+/// Where the metadata for one `name@range` comes from under a [`NetworkMode`].
+#[derive(Debug)]
+pub enum MetadataPlan {
+    /// What is known locally has a matching version, so the registry is not asked.
+    Local(PackageInfo),
+    /// Ask the registry.
+    Registry,
+}
+
+impl MetadataPlan {
+    /// Decides how to look up `name@range` given what is known locally. `local` only
+    /// counts when one of its versions satisfies `range`.
+    pub fn new(
+        mode: NetworkMode,
+        name: &str,
+        range: &str,
+        local: Option<PackageInfo>,
+    ) -> anyhow::Result<Self> {
+        let local = local.filter(|info| satisfies_range(info, range));
+
+        match (mode, local) {
+            (NetworkMode::Online, _) => Ok(Self::Registry),
+            (NetworkMode::Offline, None) => Err(anyhow::anyhow!(
+                "no version of {name} matching {range} is in the store, and offline mode does not allow downloading it"
+            )),
+            (_, Some(local)) => Ok(Self::Local(local)),
+            (_, None) => Ok(Self::Registry),
+        }
+    }
+}
+
+fn satisfies_range(info: &PackageInfo, range: &str) -> bool {
+    resolve_version(&info.versions, range, &info.dist_tags).is_ok()
+}
+
+/// What is known about `name@range` without the registry: the versions in the store,
+/// and under `prefer-offline` first the metadata saved by an earlier fetch, however old.
+fn local_package_info(mode: NetworkMode, name: &str, range: &str) -> Option<PackageInfo> {
+    match mode {
+        NetworkMode::Online => None,
+        NetworkMode::PreferOffline => MetadataCache::global()
+            .read(name, &MetadataSource::configured(name))
+            .filter(|info| satisfies_range(info, range))
+            .or_else(|| stored_package_info(name)),
+        NetworkMode::Offline | NetworkMode::OfflineThenOnline => stored_package_info(name),
+    }
+}
+
+/// Metadata for resolving `name@range`, from the registry or the store as the configured
+/// `network-mode` allows.
+pub async fn package_info_async(
+    client: Arc<reqwest::Client>,
+    name: &str,
+    range: &str,
+) -> anyhow::Result<PackageInfo> {
+    let mode = PacmConfig::get().network_mode();
+    let local = local_package_info(mode, name, range);
+
+    match MetadataPlan::new(mode, name, range, local)? {
+        MetadataPlan::Local(info) => Ok(info),
+        MetadataPlan::Registry if semver::Version::parse(range).is_ok() => {
+            fetch_pinned_package_info_async(client, name, range).await
+        }
+        MetadataPlan::Registry => fetch_package_info_async(client, name).await,
+    }
+}
+
+/// Blocking counterpart of [`package_info_async`].
+pub fn package_info(name: &str, range: &str) -> anyhow::Result<PackageInfo> {
+    let mode = PacmConfig::get().network_mode();
+    let local = local_package_info(mode, name, range);
+
+    match MetadataPlan::new(mode, name, range, local)? {
+        MetadataPlan::Local(info) => Ok(info),
+        MetadataPlan::Registry => fetch_package_info(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn stored(versions: &[&str]) -> PackageInfo {
+        PackageInfo {
+            versions: versions
+                .iter()
+                .map(|version| (version.to_string(), serde_json::json!({})))
+                .collect(),
+            dist_tags: HashMap::new(),
+        }
+    }
+
+    fn is_local(plan: &MetadataPlan) -> bool {
+        matches!(plan, MetadataPlan::Local(_))
+    }
+
+    #[test]
+    fn test_missing_package_under_each_mode() {
+        for missing in [None, Some(stored(&["1.0.0"]))] {
+            for mode in [
+                NetworkMode::Online,
+                NetworkMode::PreferOffline,
+                NetworkMode::OfflineThenOnline,
+            ] {
+                let plan = MetadataPlan::new(mode, "pkg", "^2.0.0", missing.clone()).unwrap();
+                assert!(matches!(plan, MetadataPlan::Registry), "{mode:?}");
+            }
+
+            let error = MetadataPlan::new(NetworkMode::Offline, "pkg", "^2.0.0", missing)
+                .unwrap_err()
+                .to_string();
+            assert!(error.contains("no version of pkg matching ^2.0.0 is in the store"));
+        }
+    }
+
+    #[test]
+    fn test_stored_package_under_each_mode() {
+        let present = || Some(stored(&["1.0.0", "1.4.0"]));
+
+        let plan = MetadataPlan::new(NetworkMode::Online, "pkg", "^1.0.0", present()).unwrap();
+        assert!(!is_local(&plan));
+
+        for mode in [
+            NetworkMode::PreferOffline,
+            NetworkMode::Offline,
+            NetworkMode::OfflineThenOnline,
+        ] {
+            let plan = MetadataPlan::new(mode, "pkg", "^1.0.0", present()).unwrap();
+            assert!(is_local(&plan), "{mode:?}");
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::{ResolvedPackage, satisfies};
+
+/// A package in a dependency tree with the dependencies it pulls in, as rendered by
+/// [`tree_lines`] and serialized for JSON output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<TreeNode>,
+    /// The package is one of its own ancestors, so its dependencies are not repeated.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub circular: bool,
+    /// The package's dependencies are shown where it first appears in the tree, so they
+    /// are not repeated here.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deduped: bool,
+}
+
+/// Builds the tree below the packages named in `roots`, following plain and optional
+/// dependencies to the version of each that satisfies its range. `depth` limits how many
+/// levels below the roots are included; `Some(0)` keeps only the roots. A package's
+/// dependencies are expanded once, where it first appears; later occurrences are marked
+/// `deduped`.
+pub fn dependency_tree(
+    packages: &HashMap<String, ResolvedPackage>,
+    roots: &HashSet<String>,
+    depth: Option<u32>,
+) -> Vec<TreeNode> {
+    let mut by_name: HashMap<&str, Vec<&ResolvedPackage>> = HashMap::new();
+    for pkg in packages.values() {
+        by_name.entry(&pkg.name).or_default().push(pkg);
+    }
+    for versions in by_name.values_mut() {
+        versions.sort_by_key(|pkg| semver::Version::parse(&pkg.version).ok());
+    }
+
+    let mut roots: Vec<&String> = roots.iter().collect();
+    roots.sort();
+    let mut ancestors = Vec::new();
+    let mut expanded = HashSet::new();
+    roots
+        .into_iter()
+        .flat_map(|name| by_name.get(name.as_str()).into_iter().flatten())
+        .map(|pkg| build_node(pkg, &by_name, depth, &mut ancestors, &mut expanded))
+        .collect()
+}
+
+fn build_node<'a>(
+    pkg: &'a ResolvedPackage,
+    by_name: &HashMap<&str, Vec<&'a ResolvedPackage>>,
+    depth: Option<u32>,
+    ancestors: &mut Vec<&'a ResolvedPackage>,
+    expanded: &mut HashSet<(&'a str, &'a str)>,
+) -> TreeNode {
+    let circular = ancestors
+        .iter()
+        .any(|ancestor| ancestor.name == pkg.name && ancestor.version == pkg.version);
+    let has_dependencies = !pkg.dependencies.is_empty() || !pkg.optional_dependencies.is_empty();
+    let deduped = !circular
+        && depth != Some(0)
+        && has_dependencies
+        && !expanded.insert((&pkg.name, &pkg.version));
+    let mut node = TreeNode {
+        name: pkg.name.clone(),
+        version: pkg.version.clone(),
+        dependencies: Vec::new(),
+        circular,
+        deduped,
+    };
+    if circular || deduped || depth == Some(0) {
+        return node;
+    }
+
+    let mut dependencies: Vec<(&String, &String)> = pkg
+        .dependencies
+        .iter()
+        .chain(&pkg.optional_dependencies)
+        .collect();
+    dependencies.sort();
+    ancestors.push(pkg);
+    for (name, range) in dependencies {
+        let Some(versions) = by_name.get(name.as_str()) else {
+            continue;
+        };
+        let target = versions
+            .iter()
+            .rev()
+            .find(|candidate| satisfies(&candidate.version, range))
+            .or_else(|| versions.last());
+        if let Some(target) = target {
+            let child_depth = depth.map(|depth| depth - 1);
+            node.dependencies.push(build_node(
+                target,
+                by_name,
+                child_depth,
+                ancestors,
+                expanded,
+            ));
+        }
+    }
+    ancestors.pop();
+    node
+}
+
+/// Renders `nodes` as an indented tree, one package per line.
+pub fn tree_lines(nodes: &[TreeNode]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for node in nodes {
+        lines.push(node_label(node));
+        push_children(&node.dependencies, "", &mut lines);
+    }
+    lines
+}
+
+fn push_children(children: &[TreeNode], prefix: &str, lines: &mut Vec<String>) {
+    for (index, child) in children.iter().enumerate() {
+        let last = index + 1 == children.len();
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        lines.push(format!("{prefix}{branch}{}", node_label(child)));
+        push_children(&child.dependencies, &format!("{prefix}{indent}"), lines);
+    }
+}
+
+fn node_label(node: &TreeNode) -> String {
+    if node.circular {
+        format!("{}@{} (circular)", node.name, node.version)
+    } else if node.deduped {
+        format!("{}@{} (deduped)", node.name, node.version)
+    } else {
+        format!("{}@{}", node.name, node.version)
+    }
+}
+
+/// Prints the dependency tree below `roots`, see [`dependency_tree`].
+pub fn print_tree(
+    packages: &HashMap<String, ResolvedPackage>,
+    roots: &HashSet<String>,
+    depth: Option<u32>,
+) {
+    for line in tree_lines(&dependency_tree(packages, roots, depth)) {
+        println!("{line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, dependencies: &[(&str, &str)]) -> ResolvedPackage {
+        ResolvedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            dependencies: dependencies
+                .iter()
+                .map(|(name, range)| (name.to_string(), range.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tree_follows_matching_versions_and_stops_at_cycles_and_repeats() {
+        let packages: HashMap<String, ResolvedPackage> = [
+            package("app", "1.0.0", &[("lib", "^2.0.0"), ("util", "^1.0.0")]),
+            package("lib", "1.0.0", &[]),
+            package("lib", "2.1.0", &[("util", "^1.0.0")]),
+            package("util", "1.2.0", &[("lib", "^2.0.0")]),
+        ]
+        .into_iter()
+        .map(|pkg| (pkg.to_string(), pkg))
+        .collect();
+        let roots = HashSet::from(["app".to_string()]);
+
+        assert_eq!(
+            tree_lines(&dependency_tree(&packages, &roots, None)),
+            [
+                "app@1.0.0",
+                "├── lib@2.1.0",
+                "│   └── util@1.2.0",
+                "│       └── lib@2.1.0 (circular)",
+                "└── util@1.2.0 (deduped)",
+            ]
+        );
+        assert_eq!(
+            tree_lines(&dependency_tree(&packages, &roots, Some(1))),
+            ["app@1.0.0", "├── lib@2.1.0", "└── util@1.2.0"]
+        );
+    }
+}
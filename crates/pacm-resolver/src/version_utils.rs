@@ -3,7 +3,7 @@ use semver::Version;
 pub fn parse_partial_version(version_str: &str) -> Result<Version, String> {
     let cleaned = version_str.trim();
 
-    if cleaned == "*" || cleaned == "" {
+    if cleaned == "*" || cleaned.is_empty() {
         return Ok(Version::new(0, 0, 0));
     }
 
@@ -1,160 +1,351 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use pacm_logger;
-use pacm_project::read_package_json;
+use pacm_project::{
+    PackageJson, check_workspace_cycles, discover_workspaces, read_package_json, sort_workspaces,
+};
+use pacm_utils::glob_match;
+use serde_json::Value;
 
+/// Runs `script_name` with its `pre` and `post` hooks. Fails when the script is not
+/// defined or any step exits non-zero, so `pacm run` exits non-zero too.
 pub fn run_script(project_dir: &str, script_name: &str) -> anyhow::Result<()> {
     let path = PathBuf::from(project_dir);
     let pkg = read_package_json(&path)?;
 
-    if let Some(scripts) = pkg.scripts {
-        if let Some(script) = scripts.get(script_name) {
-            pacm_logger::shell(script);
-
-            let status = if cfg!(target_os = "windows") {
-                Command::new("cmd")
-                    .args(["/C", script])
-                    .current_dir(&path)
-                    .status()?
-            } else {
-                Command::new("sh")
-                    .arg("-c")
-                    .arg(script)
-                    .current_dir(&path)
-                    .status()?
-            };
-
-            if status.success() {
-                pacm_logger::success(&format!("Script '{}' executed successfully!", script_name));
-            } else {
-                pacm_logger::error(&format!(
-                    "Script '{}' failed with exit code: {}",
-                    script_name,
-                    status.code().unwrap_or(-1)
-                ));
-            }
-        } else {
-            pacm_logger::error(&format!(
-                "Script '{}' not found in package.json",
-                script_name
-            ));
-        }
-    } else {
+    let Some(scripts) = &pkg.scripts else {
         pacm_logger::error("No scripts defined in package.json");
+        anyhow::bail!("No scripts defined in package.json");
+    };
+    if !scripts.contains_key(script_name) {
+        pacm_logger::error(&format!(
+            "Script '{}' not found in package.json",
+            script_name
+        ));
+        anyhow::bail!("Script '{}' not found in package.json", script_name);
     }
 
+    if !run_script_with_hooks(&path, &pkg, script_name)? {
+        anyhow::bail!("Script '{}' failed", script_name);
+    }
+    pacm_logger::success(&format!("Script '{}' executed successfully!", script_name));
     Ok(())
 }
 
-pub fn start_application(project_dir: &str) -> anyhow::Result<()> {
-    let path = PathBuf::from(project_dir);
-    let pkg = read_package_json(&path)?;
+/// Lists the scripts in the project's `package.json` with their commands, in the order
+/// they are defined.
+pub fn list_scripts(project_dir: &str) -> anyhow::Result<()> {
+    let pkg = read_package_json(Path::new(project_dir))?;
 
-    if let Some(scripts) = &pkg.scripts {
-        if let Some(start_script) = scripts.get("start") {
-            pacm_logger::shell(start_script);
-
-            let status = if cfg!(target_os = "windows") {
-                Command::new("cmd")
-                    .args(["/C", start_script])
-                    .current_dir(&path)
-                    .status()?
-            } else {
-                Command::new("sh")
-                    .arg("-c")
-                    .arg(start_script)
-                    .current_dir(&path)
-                    .status()?
-            };
-
-            if status.success() {
-                pacm_logger::success("Start script executed successfully!");
-            } else {
-                pacm_logger::error(&format!(
-                    "Start script failed with exit code: {}",
-                    status.code().unwrap_or(-1)
-                ));
+    match &pkg.scripts {
+        Some(scripts) if !scripts.is_empty() => {
+            let project = pkg.name.as_deref().unwrap_or("this project");
+            pacm_logger::info(&format!("Scripts available in {project}:"));
+            for (name, command) in scripts {
+                pacm_logger::script(name, command);
             }
-            return Ok(());
         }
+        _ => pacm_logger::info("No scripts defined in package.json"),
     }
 
-    if let Some(main) = &pkg.main {
-        let main_path = path.join(main);
-        if main_path.exists() {
-            let command = format!("node {}", main);
-            pacm_logger::shell(&command);
-
-            let status = if cfg!(target_os = "windows") {
-                Command::new("cmd")
-                    .args(["/C", &command])
-                    .current_dir(&path)
-                    .status()?
-            } else {
-                Command::new("sh")
-                    .arg("-c")
-                    .arg(&command)
-                    .current_dir(&path)
-                    .status()?
-            };
-
-            if status.success() {
-                pacm_logger::success("Application started successfully!");
-            } else {
-                pacm_logger::error(&format!(
-                    "Application failed to start with exit code: {}",
-                    status.code().unwrap_or(-1)
-                ));
-            }
-        } else {
-            pacm_logger::error(&format!("Main entry point '{}' does not exist", main));
+    Ok(())
+}
+
+/// Runs `pre<script>`, `<script>` and `post<script>` in `path`, stopping at the first
+/// failing step. Returns whether every step succeeded.
+pub fn run_script_with_hooks(
+    path: &Path,
+    pkg: &PackageJson,
+    script_name: &str,
+) -> anyhow::Result<bool> {
+    let Some(scripts) = &pkg.scripts else {
+        return Ok(false);
+    };
+
+    let steps = [
+        format!("pre{script_name}"),
+        script_name.to_string(),
+        format!("post{script_name}"),
+    ];
+
+    for step in &steps {
+        let Some(script) = scripts.get(step) else {
+            continue;
+        };
+
+        pacm_logger::shell(script);
+        let status = shell_command(script, path).status()?;
+
+        if !status.success() {
+            pacm_logger::error(&format!(
+                "Script '{}' failed with exit code: {}",
+                step,
+                status.code().unwrap_or(-1)
+            ));
+            return Ok(false);
         }
-    } else {
-        // Try common entry points if no main is specified
-        let common_entries = ["index.js", "app.js", "server.js", "main.js"];
-        let mut found = false;
-
-        for entry in &common_entries {
-            let entry_path = path.join(entry);
-            if entry_path.exists() {
-                pacm_logger::info(&format!("No main entry point specified, trying: {}", entry));
-
-                let command = format!("node {}", entry);
-                pacm_logger::shell(&command);
-
-                let status = if cfg!(target_os = "windows") {
-                    Command::new("cmd")
-                        .args(["/C", &command])
-                        .current_dir(&path)
-                        .status()?
-                } else {
-                    Command::new("sh")
-                        .arg("-c")
-                        .arg(&command)
-                        .current_dir(&path)
-                        .status()?
-                };
-
-                if status.success() {
-                    pacm_logger::success("Application started successfully!");
-                } else {
-                    pacm_logger::error(&format!(
-                        "Application failed to start with exit code: {}",
-                        status.code().unwrap_or(-1)
-                    ));
-                }
-                found = true;
+    }
+
+    Ok(true)
+}
+
+/// Runs `script_name` in every workspace member that defines it, in dependency order.
+/// With `bail`, stops at the first failing member. Members that depend on each other in
+/// a cycle have no such order, so nothing is run.
+pub fn run_workspaces_script(
+    root_dir: &str,
+    script_name: &str,
+    filter: Option<&str>,
+    bail: bool,
+) -> anyhow::Result<()> {
+    let root = PathBuf::from(root_dir);
+    let members = discover_workspaces(&root)?;
+    check_workspace_cycles(&members)?;
+    let members = sort_workspaces(members);
+
+    if members.is_empty() {
+        pacm_logger::error("No workspaces defined in package.json");
+        anyhow::bail!("No workspaces defined in package.json");
+    }
+
+    let members: Vec<_> = members
+        .into_iter()
+        .filter(|m| filter.is_none_or(|f| glob_match(f, &m.name)))
+        .filter(|m| {
+            m.package_json
+                .scripts
+                .as_ref()
+                .is_some_and(|s| s.contains_key(script_name))
+        })
+        .collect();
+
+    if members.is_empty() {
+        pacm_logger::warn(&format!(
+            "No matching workspaces define a '{}' script",
+            script_name
+        ));
+        return Ok(());
+    }
+
+    let mut passed = Vec::new();
+    let mut failed = Vec::new();
+
+    for member in &members {
+        pacm_logger::info(&format!("{} › {}", member.name, script_name));
+
+        if run_script_with_hooks(&member.path, &member.package_json, script_name)? {
+            passed.push(member.name.as_str());
+        } else {
+            failed.push(member.name.as_str());
+            if bail {
                 break;
             }
         }
+    }
+
+    let skipped = members.len() - passed.len() - failed.len();
+    if failed.is_empty() {
+        pacm_logger::success(&format!(
+            "Script '{}' succeeded in {} workspaces",
+            script_name,
+            passed.len()
+        ));
+        return Ok(());
+    }
+
+    let mut summary = format!(
+        "Script '{}' failed in {} of {} workspaces: {}",
+        script_name,
+        failed.len(),
+        members.len(),
+        failed.join(", ")
+    );
+    if skipped > 0 {
+        summary.push_str(&format!(" ({} not run)", skipped));
+    }
+    pacm_logger::error(&summary);
+
+    anyhow::bail!("Script '{}' failed in {}", script_name, failed.join(", "))
+}
+
+fn shell_command(script: &str, dir: &Path) -> Command {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", script]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    };
+    command.current_dir(dir);
+    command
+}
+
+/// Files `pacm start` tries, in order, when there is no `start` script: `main`, the
+/// root `exports` entry, `module`, then the usual entry file names.
+fn entry_candidates(pkg: &PackageJson) -> Vec<String> {
+    let exports = pkg.other.get("exports").and_then(root_export);
+    let module = pkg.other.get("module").and_then(Value::as_str);
+
+    let mut candidates: Vec<String> = Vec::new();
+    let declared = [pkg.main.as_deref(), exports, module];
+    let common = ["index.js", "app.js", "server.js", "main.js"];
+    for entry in declared.into_iter().flatten().chain(common) {
+        let entry = entry.strip_prefix("./").unwrap_or(entry);
+        if !candidates.iter().any(|candidate| candidate == entry) {
+            candidates.push(entry.to_string());
+        }
+    }
+    candidates
+}
+
+/// The file `exports` maps the package root to. `exports` may be that path itself, a map
+/// of subpaths with `"."` for the root, or a map of conditions for the root; conditions
+/// can nest, and the first one Node would pick when running a file wins.
+fn root_export(exports: &Value) -> Option<&str> {
+    match exports {
+        Value::String(target) => Some(target),
+        Value::Array(targets) => targets.iter().find_map(root_export),
+        Value::Object(map) if map.keys().any(|key| key.starts_with('.')) => {
+            map.get(".").and_then(root_export)
+        }
+        Value::Object(_) => export_condition(exports),
+        _ => None,
+    }
+}
+
+fn export_condition(target: &Value) -> Option<&str> {
+    match target {
+        Value::String(target) => Some(target),
+        Value::Array(targets) => targets.iter().find_map(export_condition),
+        Value::Object(conditions) => ["node", "require", "import", "default"]
+            .iter()
+            .filter_map(|condition| conditions.get(*condition))
+            .find_map(export_condition),
+        _ => None,
+    }
+}
+
+pub fn start_application(project_dir: &str) -> anyhow::Result<()> {
+    let path = PathBuf::from(project_dir);
+    let pkg = read_package_json(&path)?;
+
+    if let Some(scripts) = &pkg.scripts
+        && let Some(start_script) = scripts.get("start")
+    {
+        pacm_logger::shell(start_script);
+
+        let status = if cfg!(target_os = "windows") {
+            Command::new("cmd")
+                .args(["/C", start_script])
+                .current_dir(&path)
+                .status()?
+        } else {
+            Command::new("sh")
+                .arg("-c")
+                .arg(start_script)
+                .current_dir(&path)
+                .status()?
+        };
 
-        if !found {
-            pacm_logger::error(
-                "No start script found and no main entry point available. Please define a 'start' script in package.json or specify a 'main' field.",
-            );
+        if status.success() {
+            pacm_logger::success("Start script executed successfully!");
+        } else {
+            pacm_logger::error(&format!(
+                "Start script failed with exit code: {}",
+                status.code().unwrap_or(-1)
+            ));
         }
+        return Ok(());
+    }
+
+    let candidates = entry_candidates(&pkg);
+    let Some(entry) = candidates.iter().find(|entry| path.join(entry).is_file()) else {
+        let message = format!(
+            "No start script and no entry point to run. Tried: the 'start' script, {}. \
+             Define a 'start' script in package.json or point 'main' at your entry file.",
+            candidates.join(", ")
+        );
+        pacm_logger::error(&message);
+        anyhow::bail!(message);
+    };
+    if pkg.main.as_deref() != Some(entry.as_str()) {
+        pacm_logger::info(&format!("No start script, running entry point: {entry}"));
+    }
+
+    let command = format!("node {entry}");
+    pacm_logger::shell(&command);
+    let status = shell_command(&command, &path).status()?;
+    if status.success() {
+        pacm_logger::success("Application started successfully!");
+    } else {
+        pacm_logger::error(&format!(
+            "Application failed to start with exit code: {}",
+            status.code().unwrap_or(-1)
+        ));
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(manifest: Value) -> PackageJson {
+        serde_json::from_value(manifest).unwrap()
+    }
+
+    #[test]
+    fn test_entry_candidates_include_exports_and_module() {
+        let pkg = package(serde_json::json!({
+            "name": "esm-app",
+            "exports": { ".": { "import": "./dist/index.mjs", "types": "./dist/index.d.ts" } },
+            "module": "./dist/index.mjs"
+        }));
+        assert_eq!(
+            entry_candidates(&pkg),
+            [
+                "dist/index.mjs",
+                "index.js",
+                "app.js",
+                "server.js",
+                "main.js"
+            ]
+        );
+
+        let pkg = package(serde_json::json!({
+            "main": "lib/main.js",
+            "exports": { "node": { "require": "./lib/node.cjs" }, "default": "./lib/web.js" }
+        }));
+        assert_eq!(entry_candidates(&pkg)[..2], ["lib/main.js", "lib/node.cjs"]);
+
+        let pkg = package(serde_json::json!({ "exports": { "./feature": "./feature.js" } }));
+        assert_eq!(entry_candidates(&pkg)[0], "index.js");
+    }
+
+    #[test]
+    fn test_start_finds_exports_entry_without_main() {
+        pacm_logger::init_logger(true);
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{ "name": "exports-only", "exports": "./src/cli.js" }"#,
+        )
+        .unwrap();
+        let project = dir.path().to_str().unwrap();
+
+        let error = start_application(project).unwrap_err().to_string();
+        assert!(error.contains("src/cli.js, index.js"), "{error}");
+
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src").join("cli.js"), "").unwrap();
+        let pkg = read_package_json(dir.path()).unwrap();
+        let candidates = entry_candidates(&pkg);
+        let entry = candidates
+            .iter()
+            .find(|entry| dir.path().join(entry).is_file());
+        assert_eq!(entry.map(String::as_str), Some("src/cli.js"));
+    }
+}
@@ -1,10 +1,19 @@
+pub mod long_path;
 pub mod package_linker;
 pub mod path_resolver;
+pub mod projects;
+pub mod pruner;
 pub mod store_manager;
 
+pub use long_path::{long_path, normal_path};
 pub use package_linker::PackageLinker;
-pub use path_resolver::PathResolver;
+pub use path_resolver::{PathResolver, package_name_from_store, store_safe_name};
+pub use projects::ProjectRegistry;
+pub use pruner::StorePruner;
 pub use store_manager::StoreManager;
 
 pub use package_linker::link_package;
-pub use store_manager::{get_store_path, store_package};
+pub use store_manager::{
+    get_cache_path, get_global_path, get_store_path, integrity_of, replace_package, store_package,
+    store_tarball,
+};
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+
+/// `path` in a form the filesystem accepts however deep it is. On Windows that is the
+/// `\\?\` extended-length form, which lifts the 260 character `MAX_PATH` limit that deep
+/// `node_modules` trees and store entries run into; relative paths are made absolute
+/// first, since only absolute paths can carry the prefix. Elsewhere `path` is returned
+/// unchanged.
+#[must_use]
+pub fn long_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        let Ok(absolute) = std::path::absolute(path) else {
+            return path.to_path_buf();
+        };
+        match absolute.to_str().and_then(extended_length) {
+            Some(extended) => PathBuf::from(extended),
+            None => absolute,
+        }
+    }
+
+    #[cfg(not(windows))]
+    {
+        path.to_path_buf()
+    }
+}
+
+/// `path` without a `\\?\` extended-length prefix, the form to store in link targets
+/// and hand back to callers. Node resolves modules through the paths links point at, and
+/// a prefixed path compares unequal to the plain one, so the same module could load
+/// twice. Paths without the prefix are returned unchanged.
+#[must_use]
+pub fn normal_path(path: &Path) -> PathBuf {
+    match path.to_str().and_then(without_extended_length) {
+        Some(normal) => PathBuf::from(normal),
+        None => path.to_path_buf(),
+    }
+}
+
+/// The plain form of an extended-length Windows path: `\\?\C:\a` becomes `C:\a` and
+/// `\\?\UNC\server\share\a` becomes `\\server\share\a`. Anything else yields `None`.
+fn without_extended_length(path: &str) -> Option<String> {
+    let rest = path.strip_prefix(r"\\?\")?;
+    match rest.strip_prefix(r"UNC\") {
+        Some(unc) => Some(format!(r"\\{unc}")),
+        None => Some(rest.to_string()),
+    }
+}
+
+/// The extended-length form of an absolute Windows path: `C:\a` becomes `\\?\C:\a` and
+/// `\\server\share\a` becomes `\\?\UNC\server\share\a`. Paths that already use a `\\?\`
+/// or `\\.\` prefix are kept, and anything else yields `None`.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn extended_length(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return Some(path.to_string());
+    }
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{unc}"));
+    }
+
+    let bytes = path.as_bytes();
+    let is_drive =
+        bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\';
+    is_drive.then(|| format!(r"\\?\{path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extended_length_prefixes_drive_and_unc_paths() {
+        assert_eq!(
+            extended_length(r"C:\Users\me\.pacm\store").as_deref(),
+            Some(r"\\?\C:\Users\me\.pacm\store")
+        );
+        assert_eq!(
+            extended_length(r"\\build\share\app\node_modules").as_deref(),
+            Some(r"\\?\UNC\build\share\app\node_modules")
+        );
+        assert_eq!(
+            extended_length(r"\\?\C:\already").as_deref(),
+            Some(r"\\?\C:\already")
+        );
+        assert_eq!(extended_length(r"relative\path"), None);
+    }
+
+    #[test]
+    fn test_normal_path_drops_the_extended_length_prefix() {
+        for path in [
+            r"C:\Users\me\.pacm\store",
+            r"\\build\share\app\node_modules",
+        ] {
+            let extended = extended_length(path).unwrap();
+            assert_eq!(normal_path(Path::new(&extended)), Path::new(path));
+        }
+        assert_eq!(
+            normal_path(Path::new("/home/me/.pacm/store")),
+            Path::new("/home/me/.pacm/store")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_links_and_stores_beyond_max_path() {
+        let root = tempfile::tempdir().unwrap();
+        let mut deep = root.path().to_path_buf();
+        while deep.as_os_str().len() <= 300 {
+            deep = deep.join("node_modules").join("nested-package-name");
+        }
+
+        let package = long_path(&deep.join("package"));
+        std::fs::create_dir_all(&package).unwrap();
+        std::fs::write(package.join("index.js"), "module.exports = 1;").unwrap();
+
+        crate::PackageLinker::link_directory(&deep.join("node_modules"), "deep", &package).unwrap();
+        let link = long_path(&deep.join("node_modules").join("deep"));
+        assert_eq!(
+            std::fs::read_to_string(link.join("index.js")).unwrap(),
+            "module.exports = 1;"
+        );
+        // The link points at the plain path, which is what Node resolves through.
+        let target = std::fs::read_link(&link).unwrap();
+        assert!(
+            !target.to_string_lossy().starts_with(r"\\?\"),
+            "{}",
+            target.display()
+        );
+
+        let tarball = {
+            let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::default(),
+            ));
+            let mut header = tar::Header::new_gnu();
+            header.set_size(0);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, "package/index.js", std::io::empty())
+                .unwrap();
+            builder.into_inner().unwrap().finish().unwrap()
+        };
+        let stored =
+            crate::StoreManager::store_package_in(&deep.join("store"), "deep", "1.0.0", &tarball)
+                .unwrap();
+        assert!(
+            !stored.to_string_lossy().starts_with(r"\\?\"),
+            "{}",
+            stored.display()
+        );
+        assert!(long_path(&stored).join("index.js").exists());
+    }
+}
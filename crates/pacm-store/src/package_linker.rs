@@ -6,6 +6,9 @@ use std::{
     sync::atomic::{AtomicUsize, Ordering},
 };
 
+use crate::long_path::{long_path, normal_path};
+use crate::store_manager::StoreManager;
+
 pub struct PackageLinker;
 
 impl PackageLinker {
@@ -13,7 +16,7 @@ impl PackageLinker {
         project_node_modules: &Path,
         packages: &HashMap<String, &Path>,
     ) -> io::Result<()> {
-        fs::create_dir_all(project_node_modules)?;
+        fs::create_dir_all(long_path(project_node_modules))?;
 
         let counter = AtomicUsize::new(0);
 
@@ -23,7 +26,7 @@ impl PackageLinker {
                 let result = Self::link_package(project_node_modules, package_name, store_path);
 
                 let current = counter.fetch_add(1, Ordering::Relaxed) + 1;
-                if current % 50 == 0 {
+                if current.is_multiple_of(50) {
                     eprintln!("Linked {}/{} packages", current, packages.len());
                 }
 
@@ -40,17 +43,98 @@ impl PackageLinker {
         package_name: &str,
         store_path: &Path,
     ) -> io::Result<()> {
-        let dest = Self::get_package_destination(project_node_modules, package_name);
-
-        Self::ensure_parent_directory_exists(&dest)?;
-        Self::remove_existing_package(&dest)?;
-
         let updated_store_path = match store_path.canonicalize() {
             Ok(canonical_path) => canonical_path.join("package"),
             Err(_) => store_path.join("package"),
         };
 
-        Self::create_symlink(&updated_store_path, &dest)?;
+        Self::link_directory(project_node_modules, package_name, &updated_store_path)?;
+        // Only feeds `pacm store gc`, so a read-only store does not fail the link.
+        let _ = StoreManager::mark_used(store_path);
+        Ok(())
+    }
+
+    /// Links `node_modules/<package_name>` to `target`, an already extracted package
+    /// directory, replacing whatever was there.
+    pub fn link_directory(
+        project_node_modules: &Path,
+        package_name: &str,
+        target: &Path,
+    ) -> io::Result<()> {
+        let dest = long_path(&Self::get_package_destination(
+            project_node_modules,
+            package_name,
+        ));
+
+        Self::ensure_parent_directory_exists(&dest)?;
+        Self::remove_existing_package(&dest)?;
+
+        let target = normal_path(target);
+        match Self::create_symlink(&target, &dest) {
+            // Another install linked the same path between the removal and now.
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                Self::remove_existing_package(&dest)?;
+                Self::create_symlink(&target, &dest)
+            }
+            result => result,
+        }
+    }
+
+    /// Copies `source`, an extracted package directory, to
+    /// `node_modules/<package_name>` as a real directory, replacing whatever was there.
+    pub fn copy_directory(
+        project_node_modules: &Path,
+        package_name: &str,
+        source: &Path,
+    ) -> io::Result<()> {
+        let dest = long_path(&Self::get_package_destination(
+            project_node_modules,
+            package_name,
+        ));
+
+        Self::ensure_parent_directory_exists(&dest)?;
+        Self::remove_existing_package(&dest)?;
+        fs::create_dir_all(&dest)?;
+        fs_extra::dir::copy(
+            long_path(source),
+            &dest,
+            &fs_extra::dir::CopyOptions::new().content_only(true),
+        )
+        .map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    /// Recreates `source`, an extracted package directory, at
+    /// `node_modules/<package_name>` as a real directory whose files are hard links to
+    /// the originals, replacing whatever was there. Files that cannot be hard-linked,
+    /// such as across filesystems, are copied. Unlike a symlink, Node resolves the
+    /// package's own dependencies from where it is linked, not from the store.
+    pub fn hard_link_directory(
+        project_node_modules: &Path,
+        package_name: &str,
+        source: &Path,
+    ) -> io::Result<()> {
+        let dest = long_path(&Self::get_package_destination(
+            project_node_modules,
+            package_name,
+        ));
+
+        Self::ensure_parent_directory_exists(&dest)?;
+        Self::remove_existing_package(&dest)?;
+        Self::hard_link_tree(&long_path(source), &dest)
+    }
+
+    fn hard_link_tree(source: &Path, dest: &Path) -> io::Result<()> {
+        fs::create_dir_all(dest)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            let target = dest.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                Self::hard_link_tree(&entry.path(), &target)?;
+            } else if fs::hard_link(entry.path(), &target).is_err() {
+                fs::copy(entry.path(), &target)?;
+            }
+        }
         Ok(())
     }
 
@@ -80,7 +164,8 @@ impl PackageLinker {
     }
 
     fn remove_existing_package(dest: &Path) -> io::Result<()> {
-        if dest.exists() {
+        // `exists` follows links, so a dangling link has to be caught separately.
+        if dest.exists() || dest.is_symlink() {
             if dest.is_dir() {
                 fs::remove_dir_all(dest)?;
             } else {
@@ -1,5 +1,28 @@
 use std::path::{Path, PathBuf};
 
+/// The directory name `package_name` is stored under in `<store>/npm`. Scoped names
+/// become `_at_scope_slash_name` so that every package is a single directory level.
+/// Everything that reads or writes the store has to go through this, or scoped
+/// packages are missed and downloaded again.
+#[must_use]
+pub fn store_safe_name(package_name: &str) -> String {
+    if package_name.starts_with('@') {
+        package_name.replace('@', "_at_").replace('/', "_slash_")
+    } else {
+        package_name.to_string()
+    }
+}
+
+/// The package name stored under the directory `safe_name`, reversing [`store_safe_name`].
+#[must_use]
+pub fn package_name_from_store(safe_name: &str) -> String {
+    if safe_name.starts_with("_at_") {
+        safe_name.replace("_at_", "@").replace("_slash_", "/")
+    } else {
+        safe_name.to_string()
+    }
+}
+
 pub struct PathResolver;
 
 impl PathResolver {
@@ -34,11 +57,7 @@ impl PathResolver {
 
     #[must_use]
     pub fn sanitize_package_name(package_name: &str) -> String {
-        if package_name.starts_with('@') {
-            package_name.replace('@', "_at_").replace('/', "_slash_")
-        } else {
-            package_name.to_string()
-        }
+        store_safe_name(package_name)
     }
 
     #[must_use]
@@ -51,3 +70,18 @@ impl PathResolver {
         Self::get_package_directory(store_path).join("node_modules")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_safe_name_round_trips() {
+        for name in ["@types/node", "left-pad", "@babel/plugin-transform-runtime"] {
+            assert_eq!(package_name_from_store(&store_safe_name(name)), name);
+        }
+        assert_eq!(store_safe_name("@types/node"), "_at_types_slash_node");
+        assert_eq!(store_safe_name("left-pad"), "left-pad");
+        assert_eq!(package_name_from_store("left_slash_pad"), "left_slash_pad");
+    }
+}
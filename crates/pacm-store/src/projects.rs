@@ -0,0 +1,80 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+/// The projects that have linked packages from a store, kept as one file per project
+/// under `<store>/projects`, named by a hash of the project path and holding the path.
+/// `pacm store gc` keeps whatever these projects still use, however long ago they were
+/// installed.
+pub struct ProjectRegistry;
+
+impl ProjectRegistry {
+    /// Records that `project_dir` links packages from the store at `store_base`.
+    pub fn register(store_base: &Path, project_dir: &Path) -> io::Result<()> {
+        let project_dir = project_dir.canonicalize()?;
+        let entry = Self::entry(store_base, &project_dir);
+        if entry.exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(store_base.join("projects"))?;
+        fs::write(entry, project_dir.to_string_lossy().as_bytes())
+    }
+
+    /// Every registered project, whether or not it still exists.
+    #[must_use]
+    pub fn projects(store_base: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(store_base.join("projects")) else {
+            return Vec::new();
+        };
+        let mut projects: Vec<PathBuf> = entries
+            .flatten()
+            .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+            .map(PathBuf::from)
+            .collect();
+        projects.sort();
+        projects
+    }
+
+    /// Drops `project_dir` from the registry, once it no longer exists.
+    pub fn forget(store_base: &Path, project_dir: &Path) -> io::Result<()> {
+        match fs::remove_file(Self::entry(store_base, project_dir)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn entry(store_base: &Path, project_dir: &Path) -> PathBuf {
+        let hash = Sha256::digest(project_dir.to_string_lossy().as_bytes());
+        let name: String = hash[..16]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        store_base.join("projects").join(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registers_each_project_once() {
+        let store = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        let project_dir = project.path().canonicalize().unwrap();
+
+        ProjectRegistry::register(store.path(), project.path()).unwrap();
+        ProjectRegistry::register(store.path(), &project_dir).unwrap();
+        assert_eq!(
+            ProjectRegistry::projects(store.path()),
+            std::slice::from_ref(&project_dir)
+        );
+
+        ProjectRegistry::forget(store.path(), &project_dir).unwrap();
+        assert!(ProjectRegistry::projects(store.path()).is_empty());
+        ProjectRegistry::forget(store.path(), &project_dir).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    fs, io,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use serde_json::Value;
+
+/// Directories that only matter while developing a package.
+const PRUNED_DIRS: &[&str] = &[
+    ".github",
+    "__mocks__",
+    "__tests__",
+    "benchmark",
+    "benchmarks",
+    "coverage",
+    "doc",
+    "docs",
+    "example",
+    "examples",
+    "test",
+    "tests",
+];
+
+/// File name endings that are never loaded at runtime.
+const PRUNED_SUFFIXES: &[&str] = &[
+    ".markdown",
+    ".md",
+    ".map",
+    ".spec.js",
+    ".spec.ts",
+    ".test.js",
+    ".test.ts",
+];
+
+static PRUNED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Removes documentation, tests and source maps from extracted packages, for
+/// `prune-store=true`. Anything reachable from `main`, `module`, `types`, `browser`,
+/// `bin` or `exports` is kept even when it matches a pattern, as are license files and
+/// bundled `node_modules`.
+pub struct StorePruner;
+
+impl StorePruner {
+    /// Prunes the extracted package in `package_dir` and returns the bytes removed.
+    pub fn prune(package_dir: &Path) -> io::Result<u64> {
+        let manifest = fs::read_to_string(package_dir.join("package.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or(Value::Null);
+        let referenced = Self::referenced_paths(&manifest);
+
+        let saved = Self::prune_dir(package_dir, "", &referenced)?;
+        PRUNED_BYTES.fetch_add(saved, Ordering::Relaxed);
+        Ok(saved)
+    }
+
+    /// Returns the bytes pruned since the last call.
+    pub fn take_pruned_bytes() -> u64 {
+        PRUNED_BYTES.swap(0, Ordering::Relaxed)
+    }
+
+    fn prune_dir(dir: &Path, relative: &str, referenced: &[String]) -> io::Result<u64> {
+        let mut saved = 0;
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = entry.path();
+            let relative = if relative.is_empty() {
+                name.clone()
+            } else {
+                format!("{relative}/{name}")
+            };
+            let file_type = entry.file_type()?;
+
+            if file_type.is_dir() {
+                if name == "node_modules" {
+                    continue;
+                }
+                if PRUNED_DIRS.contains(&name.as_str())
+                    && !Self::is_referenced(&relative, referenced)
+                {
+                    saved += Self::size_of(&path)?;
+                    fs::remove_dir_all(&path)?;
+                } else {
+                    saved += Self::prune_dir(&path, &relative, referenced)?;
+                }
+            } else if file_type.is_file()
+                && Self::is_prunable_file(&name)
+                && !Self::is_referenced(&relative, referenced)
+            {
+                saved += entry.metadata()?.len();
+                fs::remove_file(&path)?;
+            }
+        }
+
+        Ok(saved)
+    }
+
+    fn is_prunable_file(name: &str) -> bool {
+        let lower = name.to_ascii_lowercase();
+        let is_license = ["license", "licence", "copying"]
+            .iter()
+            .any(|prefix| lower.starts_with(prefix));
+        !is_license && PRUNED_SUFFIXES.iter().any(|suffix| lower.ends_with(suffix))
+    }
+
+    /// Whether `relative` is an entry point, lies under one, or contains one. Entry points
+    /// are matched as prefixes so that extensionless `main` values and `exports`
+    /// patterns like `./lib/*` keep everything they could resolve to.
+    fn is_referenced(relative: &str, referenced: &[String]) -> bool {
+        referenced.iter().any(|entry| {
+            relative.starts_with(entry.as_str())
+                || entry
+                    .strip_prefix(relative)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    fn referenced_paths(manifest: &Value) -> Vec<String> {
+        let mut paths = Vec::new();
+        for field in [
+            "main", "module", "types", "typings", "browser", "bin", "exports",
+        ] {
+            if let Some(value) = manifest.get(field) {
+                Self::collect_strings(value, &mut paths);
+            }
+        }
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let path = path.split('*').next().unwrap_or_default();
+                path.trim_start_matches("./").to_string()
+            })
+            .collect()
+    }
+
+    fn collect_strings(value: &Value, out: &mut Vec<String>) {
+        match value {
+            Value::String(s) => out.push(s.clone()),
+            Value::Array(items) => items.iter().for_each(|v| Self::collect_strings(v, out)),
+            Value::Object(map) => map.values().for_each(|v| Self::collect_strings(v, out)),
+            _ => {}
+        }
+    }
+
+    fn size_of(path: &Path) -> io::Result<u64> {
+        let metadata = fs::symlink_metadata(path)?;
+        if !metadata.is_dir() {
+            return Ok(metadata.len());
+        }
+
+        let mut size = 0;
+        for entry in fs::read_dir(path)? {
+            size += Self::size_of(&entry?.path())?;
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, relative: &str, size: usize) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![b'x'; size]).unwrap();
+    }
+
+    #[test]
+    fn test_prune_keeps_entry_points() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("package.json"),
+            serde_json::json!({
+                "name": "pruned",
+                "main": "./docs/index",
+                "bin": { "pruned": "bin/cli.md" },
+                "exports": { ".": "./docs/index.js", "./test/*": "./test/*.js" },
+            })
+            .to_string(),
+        )
+        .unwrap();
+        write(root, "docs/index.js", 10);
+        write(root, "test/helper.js", 10);
+        write(root, "bin/cli.md", 10);
+        write(root, "LICENSE.md", 10);
+        write(root, "README.md", 100);
+        write(root, "lib/index.js", 10);
+        write(root, "lib/index.js.map", 200);
+        write(root, "lib/index.test.js", 50);
+        write(root, "tests/unit.js", 300);
+        write(root, "node_modules/bundled/README.md", 10);
+
+        let saved = StorePruner::prune(root).unwrap();
+
+        assert_eq!(saved, 650);
+        for kept in [
+            "docs/index.js",
+            "test/helper.js",
+            "bin/cli.md",
+            "LICENSE.md",
+            "lib/index.js",
+            "node_modules/bundled/README.md",
+        ] {
+            assert!(root.join(kept).exists(), "{kept} was pruned");
+        }
+        for pruned in [
+            "README.md",
+            "lib/index.js.map",
+            "lib/index.test.js",
+            "tests",
+        ] {
+            assert!(!root.join(pruned).exists(), "{pruned} was kept");
+        }
+    }
+}
@@ -1,17 +1,65 @@
+use base64::Engine;
+use sha2::{Digest, Sha512};
 use std::{
     fs, io,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime},
 };
 
+use pacm_config::PacmConfig;
+
+use crate::long_path::long_path;
+use crate::path_resolver::store_safe_name;
+use crate::pruner::StorePruner;
+
+/// Written next to a stored package's `package/` directory, holding the `sha512-`
+/// integrity of the tarball it was extracted from.
+const INTEGRITY_FILE: &str = "integrity";
+
+/// Written next to a stored package's `package/` directory; its modification time is
+/// when a project last linked the package, see [`StoreManager::last_used`].
+const LAST_USED_FILE: &str = "last-used";
+
+/// How stale the last use of a package may get before linking it records a new one, so
+/// installs do not rewrite every store entry they touch.
+const LAST_USED_GRANULARITY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// See [`StoreManager::generation`].
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
 pub struct StoreManager;
 
 impl StoreManager {
+    /// The store of extracted packages: `store-dir` when configured, otherwise
+    /// `~/.pacm/store`.
     #[must_use]
     pub fn get_store_path() -> PathBuf {
+        PacmConfig::get().store_dir().unwrap_or_else(|| {
+            dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".pacm")
+                .join("store")
+        })
+    }
+
+    /// The cache of re-fetchable data such as registry metadata: `cache-dir` when
+    /// configured, otherwise the store itself.
+    #[must_use]
+    pub fn get_cache_path() -> PathBuf {
+        PacmConfig::get()
+            .cache_dir()
+            .unwrap_or_else(Self::get_store_path)
+    }
+
+    /// The prefix that `-g` commands operate on. It is laid out like a project, with its
+    /// own `package.json`, `pacm.lock` and `node_modules`.
+    #[must_use]
+    pub fn get_global_path() -> PathBuf {
         dirs::home_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join(".pacm")
-            .join("store")
+            .join("global")
     }
 
     pub fn store_package(
@@ -19,53 +67,205 @@ impl StoreManager {
         version: &str,
         tarball_bytes: &[u8],
     ) -> io::Result<PathBuf> {
-        let safe_package_name = Self::sanitize_package_name(package_name);
-        let package_path = Self::get_store_path()
+        Self::store_package_in(
+            &Self::get_store_path(),
+            package_name,
+            version,
+            tarball_bytes,
+        )
+    }
+
+    /// Extracts a tarball into `store_base`. Writers of the same package serialize on a
+    /// lock file under `.locks/`, and the package only appears at its final path once it
+    /// is fully extracted, so concurrent installs never observe a partial directory.
+    pub fn store_package_in(
+        store_base: &Path,
+        package_name: &str,
+        version: &str,
+        tarball_bytes: &[u8],
+    ) -> io::Result<PathBuf> {
+        let safe_package_name = store_safe_name(package_name);
+        let package_path = store_base
             .join("npm")
             .join(&safe_package_name)
             .join(version);
 
-        if package_path.exists() {
-            return Ok(package_path);
+        Self::store_locked(
+            &long_path(store_base),
+            &long_path(&package_path),
+            &format!("{safe_package_name}@{version}"),
+            tarball_bytes,
+        )?;
+        Ok(package_path)
+    }
+
+    /// Extracts a tarball that has no registry identity, such as a local file or an
+    /// arbitrary URL, keyed by the SHA-512 of its bytes. Returns the store path along
+    /// with the tarball's `sha512-` integrity string.
+    pub fn store_tarball_in(
+        store_base: &Path,
+        tarball_bytes: &[u8],
+    ) -> io::Result<(PathBuf, String)> {
+        let digest = Sha512::digest(tarball_bytes);
+        let hash = format!("{digest:x}");
+        let integrity = format!(
+            "sha512-{}",
+            base64::engine::general_purpose::STANDARD.encode(digest)
+        );
+
+        let package_path = store_base.join("tarball").join(&hash);
+        Self::store_locked(
+            &long_path(store_base),
+            &long_path(&package_path),
+            &format!("tarball-{hash}"),
+            tarball_bytes,
+        )?;
+        Ok((package_path, integrity))
+    }
+
+    /// Extracts `package_name@version` again even when it is already stored, replacing
+    /// the existing directory. This repairs store entries that were modified or damaged.
+    pub fn replace_package_in(
+        store_base: &Path,
+        package_name: &str,
+        version: &str,
+        tarball_bytes: &[u8],
+    ) -> io::Result<PathBuf> {
+        let safe_package_name = store_safe_name(package_name);
+        let package_path = store_base
+            .join("npm")
+            .join(&safe_package_name)
+            .join(version);
+        let (long_base, long_package_path) = (long_path(store_base), long_path(&package_path));
+
+        let _lock = Self::lock(&long_base, &format!("{safe_package_name}@{version}"))?;
+
+        // Move the old copy aside first; it is deleted when `old` is dropped.
+        let staging_root = long_base.join(".tmp");
+        fs::create_dir_all(&staging_root)?;
+        let old = tempfile::tempdir_in(&staging_root)?;
+        if long_package_path.exists() {
+            fs::rename(&long_package_path, old.path().join("package"))?;
         }
 
-        Self::extract_and_store_package(&package_path, tarball_bytes)?;
+        Self::extract_and_store_package(&long_base, &long_package_path, tarball_bytes)?;
         Ok(package_path)
     }
 
-    fn sanitize_package_name(package_name: &str) -> String {
-        if package_name.starts_with('@') {
-            package_name.replace('@', "_at_").replace('/', "_slash_")
-        } else {
-            package_name.to_string()
+    fn store_locked(
+        store_base: &Path,
+        package_path: &Path,
+        lock_name: &str,
+        tarball_bytes: &[u8],
+    ) -> io::Result<()> {
+        if package_path.exists() {
+            return Ok(());
         }
+
+        let _lock = Self::lock(store_base, lock_name)?;
+
+        // Another writer may have finished while we were waiting for the lock.
+        if package_path.exists() {
+            return Ok(());
+        }
+
+        Self::extract_and_store_package(store_base, package_path, tarball_bytes)
     }
 
-    fn extract_and_store_package(path: &Path, tarball_bytes: &[u8]) -> io::Result<()> {
+    /// Takes the exclusive lock for `lock_name`, held until the returned file is dropped.
+    fn lock(store_base: &Path, lock_name: &str) -> io::Result<fs::File> {
+        let lock_dir = store_base.join(".locks");
+        fs::create_dir_all(&lock_dir)?;
+        let lock_file = fs::File::create(lock_dir.join(format!("{lock_name}.lock")))?;
+        lock_file.lock()?;
+        Ok(lock_file)
+    }
+
+    /// The integrity of the tarball the package at `store_path` was extracted from.
+    /// `None` for packages stored before pacm recorded it.
+    #[must_use]
+    pub fn stored_integrity(store_path: &Path) -> Option<String> {
+        let integrity = fs::read_to_string(store_path.join(INTEGRITY_FILE)).ok()?;
+        let integrity = integrity.trim();
+        (!integrity.is_empty()).then(|| integrity.to_string())
+    }
+
+    /// When a project last linked the package at `store_path`. Packages stored before
+    /// pacm tracked this fall back to when they were stored.
+    #[must_use]
+    pub fn last_used(store_path: &Path) -> Option<SystemTime> {
+        fs::metadata(store_path.join(LAST_USED_FILE))
+            .or_else(|_| fs::metadata(store_path))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Records that the package at `store_path` was just linked into a project. Only
+    /// touches the store once a day per package.
+    pub fn mark_used(store_path: &Path) -> io::Result<()> {
+        let marker = store_path.join(LAST_USED_FILE);
+        let now = SystemTime::now();
+        if let Ok(modified) = fs::metadata(&marker).and_then(|metadata| metadata.modified())
+            && now.duration_since(modified).unwrap_or_default() < LAST_USED_GRANULARITY
+        {
+            return Ok(());
+        }
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&marker)?;
+        file.set_modified(now)
+    }
+
+    /// Counts the packages this process has written to any store, so an index of the
+    /// store can tell whether it is still complete.
+    #[must_use]
+    pub fn generation() -> u64 {
+        GENERATION.load(Ordering::Relaxed)
+    }
+
+    fn extract_and_store_package(
+        store_base: &Path,
+        path: &Path,
+        tarball_bytes: &[u8],
+    ) -> io::Result<()> {
+        if !tarball_bytes.starts_with(&[0x1f, 0x8b]) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected a gzip tarball",
+            ));
+        }
+
         let temp_dir = tempfile::tempdir()?;
+        let unpack_dir = long_path(temp_dir.path());
         let tar = flate2::read::GzDecoder::new(tarball_bytes);
         let mut archive = tar::Archive::new(tar);
-        archive.unpack(temp_dir.path())?;
-
-        fs::create_dir_all(path)?;
+        archive.unpack(&unpack_dir)?;
 
-        let entries: Vec<_> = fs::read_dir(temp_dir.path())?.collect::<Result<Vec<_>, _>>()?;
+        let entries: Vec<_> = fs::read_dir(&unpack_dir)?.collect::<Result<Vec<_>, _>>()?;
 
         let extracted_package_dir = if entries.len() == 1 {
             if let Some(entry) = entries.first() {
                 if entry.file_type()?.is_dir() {
                     entry.path()
                 } else {
-                    temp_dir.path().to_path_buf()
+                    unpack_dir.clone()
                 }
             } else {
-                temp_dir.path().to_path_buf()
+                unpack_dir.clone()
             }
         } else {
-            temp_dir.path().to_path_buf()
+            unpack_dir.clone()
         };
 
-        let final_package_dir = path.join("package");
+        // Stage inside the store so the final rename stays on one filesystem.
+        let staging_root = store_base.join(".tmp");
+        fs::create_dir_all(&staging_root)?;
+        let staging = tempfile::tempdir_in(&staging_root)?;
+
+        let final_package_dir = staging.path().join("package");
         fs::create_dir_all(&final_package_dir)?;
 
         fs_extra::dir::copy(
@@ -75,17 +275,52 @@ impl StoreManager {
                 .overwrite(true)
                 .content_only(true),
         )
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        .map_err(io::Error::other)?;
+
+        if PacmConfig::get().prune_store() {
+            StorePruner::prune(&final_package_dir)?;
+        }
+        fs::write(
+            staging.path().join(INTEGRITY_FILE),
+            integrity_of(tarball_bytes),
+        )?;
+        fs::write(staging.path().join(LAST_USED_FILE), "")?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(staging.keep(), path)?;
+        GENERATION.fetch_add(1, Ordering::Relaxed);
 
         Ok(())
     }
 }
 
+/// The `sha512-` subresource integrity string of `tarball_bytes`, as registries publish
+/// in `dist.integrity`.
+#[must_use]
+pub fn integrity_of(tarball_bytes: &[u8]) -> String {
+    format!(
+        "sha512-{}",
+        base64::engine::general_purpose::STANDARD.encode(Sha512::digest(tarball_bytes))
+    )
+}
+
 #[must_use]
 pub fn get_store_path() -> PathBuf {
     StoreManager::get_store_path()
 }
 
+#[must_use]
+pub fn get_cache_path() -> PathBuf {
+    StoreManager::get_cache_path()
+}
+
+#[must_use]
+pub fn get_global_path() -> PathBuf {
+    StoreManager::get_global_path()
+}
+
 pub fn store_package(
     package_name: &str,
     version: &str,
@@ -93,3 +328,120 @@ pub fn store_package(
 ) -> io::Result<PathBuf> {
     StoreManager::store_package(package_name, version, tarball_bytes)
 }
+
+pub fn replace_package(
+    package_name: &str,
+    version: &str,
+    tarball_bytes: &[u8],
+) -> io::Result<PathBuf> {
+    StoreManager::replace_package_in(
+        &StoreManager::get_store_path(),
+        package_name,
+        version,
+        tarball_bytes,
+    )
+}
+
+pub fn store_tarball(tarball_bytes: &[u8]) -> io::Result<(PathBuf, String)> {
+    StoreManager::store_tarball_in(&StoreManager::get_store_path(), tarball_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_parallel_stores_of_same_package() {
+        let store = tempfile::tempdir().unwrap();
+        let store_base = Arc::new(store.path().to_path_buf());
+        let tarball = Arc::new(pacm_testutil::build_tarball(&serde_json::json!({
+            "name": "@scope/racy",
+            "version": "1.0.0",
+        })));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let store_base = store_base.clone();
+                let tarball = tarball.clone();
+                thread::spawn(move || {
+                    StoreManager::store_package_in(&store_base, "@scope/racy", "1.0.0", &tarball)
+                        .unwrap()
+                })
+            })
+            .collect();
+
+        let paths: Vec<PathBuf> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert!(paths.windows(2).all(|w| w[0] == w[1]));
+
+        let package_dir = paths[0].join("package");
+        let manifest = fs::read_to_string(package_dir.join("package.json")).unwrap();
+        assert!(manifest.contains("@scope/racy"));
+        assert!(package_dir.join("index.js").exists());
+
+        let leftovers = fs::read_dir(store_base.join(".tmp")).unwrap().count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_store_tarball_is_keyed_by_integrity() {
+        let store = tempfile::tempdir().unwrap();
+        let tarball = pacm_testutil::build_tarball(&serde_json::json!({
+            "name": "local-pkg",
+            "version": "0.1.0",
+        }));
+
+        let (path, integrity) = StoreManager::store_tarball_in(store.path(), &tarball).unwrap();
+
+        assert_eq!(integrity, pacm_testutil::integrity(&tarball));
+        assert!(path.starts_with(store.path().join("tarball")));
+        assert!(path.join("package").join("package.json").exists());
+
+        let (again, _) = StoreManager::store_tarball_in(store.path(), &tarball).unwrap();
+        assert_eq!(path, again);
+        assert_eq!(StoreManager::stored_integrity(&path), Some(integrity));
+    }
+
+    #[test]
+    fn test_html_is_rejected_before_extraction() {
+        let store = tempfile::tempdir().unwrap();
+
+        let error = StoreManager::store_package_in(
+            store.path(),
+            "html-pkg",
+            "1.0.0",
+            b"<!DOCTYPE html><html>Bad gateway</html>",
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "expected a gzip tarball");
+        assert!(!store.path().join("npm").join("html-pkg").exists());
+    }
+
+    #[test]
+    fn test_scoped_package_is_stored_where_readers_look() {
+        let store = tempfile::tempdir().unwrap();
+        let tarball = pacm_testutil::build_tarball(&serde_json::json!({
+            "name": "@types/node",
+            "version": "20.1.0",
+        }));
+
+        let path = StoreManager::store_package_in(store.path(), "@types/node", "20.1.0", &tarball)
+            .unwrap();
+
+        assert_eq!(
+            path,
+            crate::PathResolver::get_package_path(store.path(), "@types/node", "20.1.0")
+        );
+        let stored_name = path
+            .parent()
+            .unwrap()
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert_eq!(crate::package_name_from_store(stored_name), "@types/node");
+    }
+}
@@ -11,6 +11,9 @@ pub struct SystemCapabilities {
     pub optimal_cache_batch_size: usize,
     pub max_concurrent_network_requests: usize,
     pub optimal_dependency_batch_size: usize,
+    /// Threads linking packages into `node_modules`. Linking is bound by the filesystem,
+    /// so more threads than this only contend on the same directories.
+    pub optimal_parallel_links: usize,
 }
 
 static SYSTEM_CAPS: OnceLock<SystemCapabilities> = OnceLock::new();
@@ -23,11 +26,12 @@ impl SystemCapabilities {
 
             let available_memory_gb = Self::get_available_memory();
 
-            let optimal_parallel_downloads = (logical_cores * 4).min(32).max(8);
-            let optimal_parallel_resolutions = (logical_cores * 6).min(48).max(12);
+            let optimal_parallel_downloads = (logical_cores * 4).clamp(8, 32);
+            let optimal_parallel_resolutions = (logical_cores * 6).clamp(12, 48);
             let optimal_cache_batch_size = (available_memory_gb * 200.0) as usize;
-            let max_concurrent_network_requests = (logical_cores * 8).min(64).max(16);
-            let optimal_dependency_batch_size = (logical_cores * 2).min(16).max(4);
+            let max_concurrent_network_requests = (logical_cores * 8).clamp(16, 64);
+            let optimal_dependency_batch_size = (logical_cores * 2).clamp(4, 16);
+            let optimal_parallel_links = (logical_cores * 2).clamp(2, 16);
 
             SystemCapabilities {
                 cpu_cores,
@@ -38,6 +42,7 @@ impl SystemCapabilities {
                 optimal_cache_batch_size,
                 max_concurrent_network_requests,
                 optimal_dependency_batch_size,
+                optimal_parallel_links,
             }
         })
     }
@@ -81,7 +86,7 @@ impl SystemCapabilities {
                     // Convert bytes to GB and use 50% of available memory
                     let available_gb =
                         (memory_status.ull_avail_phys as f64) / (1024.0 * 1024.0 * 1024.0);
-                    (available_gb * 0.5).max(2.0).min(32.0)
+                    (available_gb * 0.5).clamp(2.0, 32.0)
                 } else {
                     8.0 // Fallback for Windows
                 }
@@ -91,13 +96,12 @@ impl SystemCapabilities {
         {
             if let Ok(meminfo) = std::fs::read_to_string("/proc/meminfo") {
                 for line in meminfo.lines() {
-                    if line.starts_with("MemAvailable:") {
-                        if let Some(kb_str) = line.split_whitespace().nth(1) {
-                            if let Ok(kb) = kb_str.parse::<u64>() {
-                                let gb = (kb as f64) / (1024.0 * 1024.0);
-                                return (gb * 0.5).max(2.0).min(32.0);
-                            }
-                        }
+                    if line.starts_with("MemAvailable:")
+                        && let Some(kb_str) = line.split_whitespace().nth(1)
+                        && let Ok(kb) = kb_str.parse::<u64>()
+                    {
+                        let gb = (kb as f64) / (1024.0 * 1024.0);
+                        return (gb * 0.5).clamp(2.0, 32.0);
                     }
                 }
             }
@@ -114,7 +118,7 @@ impl SystemCapabilities {
             return total_items;
         }
 
-        let batch_size = (total_items / self.logical_cores).max(1).min(8);
+        let batch_size = (total_items / self.logical_cores).clamp(1, 8);
         batch_size.min(total_items)
     }
 
@@ -0,0 +1,85 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use pacm_config::PacmConfig;
+use serde_json::Value;
+use tempfile::TempDir;
+
+/// Moves `HOME` to a fresh directory whose `.npmrc` points at `registry_url`, which also
+/// keeps the package store out of the real home directory. Saved metadata is always
+/// revalidated, since tests publish new versions between installs.
+///
+/// Has to run before anything calls `PacmConfig::get()`, and before other threads start
+/// reading the environment.
+pub fn use_registry(registry_url: &str) -> PathBuf {
+    let home = tempfile::Builder::new()
+        .prefix("pacm-test-home-")
+        .tempdir()
+        .expect("failed to create test home")
+        .keep();
+
+    fs::write(
+        home.join(".npmrc"),
+        format!("registry={registry_url}\ncache-max-age=0\n"),
+    )
+    .expect("failed to write test .npmrc");
+
+    unsafe {
+        env::set_var("HOME", &home);
+        env::set_var("USERPROFILE", &home);
+    }
+
+    home
+}
+
+/// A throwaway project directory, removed when dropped.
+pub struct TempProject {
+    dir: TempDir,
+}
+
+impl TempProject {
+    /// Creates a project whose `package.json` is `manifest`.
+    pub fn new(manifest: &Value) -> Self {
+        let dir = tempfile::Builder::new()
+            .prefix("pacm-test-project-")
+            .tempdir()
+            .expect("failed to create test project");
+        fs::write(
+            dir.path().join("package.json"),
+            serde_json::to_string_pretty(manifest).unwrap(),
+        )
+        .expect("failed to write package.json");
+
+        Self { dir }
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The project path as the `&str` the `pacm_core` entry points expect.
+    #[must_use]
+    pub fn dir(&self) -> &str {
+        self.dir.path().to_str().expect("non UTF-8 temp path")
+    }
+
+    #[must_use]
+    pub fn package_json(&self) -> Value {
+        let content = fs::read_to_string(self.path().join("package.json"))
+            .expect("failed to read package.json");
+        serde_json::from_str(&content).expect("invalid package.json")
+    }
+
+    #[must_use]
+    pub fn lockfile(&self) -> Option<Value> {
+        let content = fs::read_to_string(PacmConfig::get().lockfile_path(self.path())).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    #[must_use]
+    pub fn has_module(&self, name: &str) -> bool {
+        self.path().join("node_modules").join(name).exists()
+    }
+}
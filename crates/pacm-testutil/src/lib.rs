@@ -0,0 +1,11 @@
+pub mod env;
+pub mod package;
+pub mod registry;
+pub mod server;
+pub mod tarball;
+
+pub use env::{TempProject, use_registry};
+pub use package::resolved_package;
+pub use registry::MockRegistry;
+pub use server::{MockServer, Request, Response};
+pub use tarball::{build_tarball, build_tarball_with_files, integrity};
@@ -0,0 +1,20 @@
+use pacm_resolver::ResolvedPackage;
+
+/// A resolved `name@version` depending on `(name, range)` pairs, with every other field
+/// left at its default. Tests that need more set it with struct update syntax.
+#[must_use]
+pub fn resolved_package(
+    name: &str,
+    version: &str,
+    dependencies: &[(&str, &str)],
+) -> ResolvedPackage {
+    ResolvedPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        dependencies: dependencies
+            .iter()
+            .map(|(name, range)| (name.to_string(), range.to_string()))
+            .collect(),
+        ..Default::default()
+    }
+}
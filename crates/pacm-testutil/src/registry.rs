@@ -0,0 +1,299 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use base64::Engine;
+use serde_json::{Value, json};
+
+use crate::env::use_registry;
+use crate::server::{MockServer, Request, Response};
+use crate::tarball::{build_tarball_with_files, integrity};
+
+#[derive(Default)]
+struct RegistryState {
+    packages: BTreeMap<String, PackageEntry>,
+    tarballs: HashMap<String, Vec<u8>>,
+    withheld: HashSet<String>,
+    advisories: BTreeMap<String, Vec<Value>>,
+    requests: Vec<String>,
+}
+
+#[derive(Default)]
+struct PackageEntry {
+    versions: BTreeMap<String, Value>,
+    dist_tags: BTreeMap<String, String>,
+}
+
+/// An in-memory npm registry serving packument metadata, tarballs and bulk advisories.
+pub struct MockRegistry {
+    server: MockServer,
+    state: Arc<Mutex<RegistryState>>,
+}
+
+static SHARED: OnceLock<MockRegistry> = OnceLock::new();
+
+impl MockRegistry {
+    pub fn start() -> Self {
+        let state = Arc::new(Mutex::new(RegistryState::default()));
+        let handler_state = state.clone();
+
+        let server = MockServer::start(Arc::new(move |request: &Request| {
+            let mut state = handler_state.lock().unwrap();
+            state
+                .requests
+                .push(format!("{} {}", request.method, request.path));
+            handle(&mut state, request)
+        }))
+        .expect("failed to start mock registry");
+
+        Self { server, state }
+    }
+
+    /// Returns a registry shared by the whole test binary, with the process configured to
+    /// use it and a quiet logger installed. The configuration is loaded once per process,
+    /// so every test in a binary that talks to a registry has to go through this.
+    pub fn shared() -> &'static MockRegistry {
+        SHARED.get_or_init(|| {
+            pacm_logger::init_logger(true);
+            let registry = Self::start();
+            use_registry(&registry.url());
+            registry
+        })
+    }
+
+    #[must_use]
+    pub fn url(&self) -> String {
+        self.server.url()
+    }
+
+    /// Publishes `name@version` with the given runtime dependencies and tags it `latest`.
+    pub fn publish(&self, name: &str, version: &str, dependencies: &[(&str, &str)]) -> &Self {
+        let dependencies: serde_json::Map<String, Value> = dependencies
+            .iter()
+            .map(|(dep, range)| ((*dep).to_string(), json!(range)))
+            .collect();
+
+        self.publish_manifest(json!({
+            "name": name,
+            "version": version,
+            "main": "index.js",
+            "dependencies": dependencies,
+        }))
+    }
+
+    /// Publishes an arbitrary `package.json` manifest. `name` and `version` are required.
+    pub fn publish_manifest(&self, manifest: Value) -> &Self {
+        self.publish_with_files(manifest, &[])
+    }
+
+    /// Publishes `manifest` with extra files in its tarball, see [`build_tarball_with_files`].
+    pub fn publish_with_files(&self, manifest: Value, extra_files: &[(&str, &[u8])]) -> &Self {
+        let name = manifest["name"]
+            .as_str()
+            .expect("manifest needs a name")
+            .to_string();
+        let version = manifest["version"]
+            .as_str()
+            .expect("manifest needs a version")
+            .to_string();
+
+        let tarball = build_tarball_with_files(&manifest, extra_files);
+        let basename = name.rsplit('/').next().unwrap_or(&name);
+        let tarball_path = format!("{name}/-/{basename}-{version}.tgz");
+
+        let mut manifest = manifest;
+        manifest["dist"] = json!({
+            "tarball": format!("{}{}", self.url(), tarball_path),
+            "integrity": integrity(&tarball),
+        });
+
+        let mut state = self.state.lock().unwrap();
+        state.tarballs.insert(tarball_path, tarball);
+        let entry = state.packages.entry(name).or_default();
+        entry.versions.insert(version.clone(), manifest);
+        entry.dist_tags.insert("latest".to_string(), version);
+        drop(state);
+
+        self
+    }
+
+    /// Answers requests for the tarball of `name@version` with a 404 while `withheld`,
+    /// so an install fails partway through its downloads.
+    pub fn withhold_tarball(&self, name: &str, version: &str, withheld: bool) -> &Self {
+        let basename = name.rsplit('/').next().unwrap_or(name);
+        let tarball_path = format!("{name}/-/{basename}-{version}.tgz");
+        let mut state = self.state.lock().unwrap();
+        if withheld {
+            state.withheld.insert(tarball_path);
+        } else {
+            state.withheld.remove(&tarball_path);
+        }
+        drop(state);
+        self
+    }
+
+    /// Moves the tarball of `name@version` to `mirror/<basename>-<version>.tgz` and points
+    /// its `dist.tarball` there, like a mirror that does not serve the conventional
+    /// `<name>/-/` layout.
+    pub fn relocate_tarball(&self, name: &str, version: &str) -> &Self {
+        let basename = name.rsplit('/').next().unwrap_or(name);
+        let conventional = format!("{name}/-/{basename}-{version}.tgz");
+        let relocated = format!("mirror/{basename}-{version}.tgz");
+        let url = format!("{}{}", self.url(), relocated);
+
+        let mut state = self.state.lock().unwrap();
+        let tarball = state
+            .tarballs
+            .remove(&conventional)
+            .expect("relocated package must be published");
+        state.tarballs.insert(relocated, tarball);
+        let entry = state.packages.get_mut(name).unwrap();
+        entry.versions.get_mut(version).unwrap()["dist"]["tarball"] = json!(url);
+        drop(state);
+        self
+    }
+
+    /// Drops `dist.integrity` from `name@version`, like packages published before
+    /// registries recorded one.
+    pub fn strip_integrity(&self, name: &str, version: &str) -> &Self {
+        let mut state = self.state.lock().unwrap();
+        let entry = state.packages.get_mut(name).unwrap();
+        if let Some(dist) = entry.versions.get_mut(version).unwrap()["dist"].as_object_mut() {
+            dist.remove("integrity");
+        }
+        drop(state);
+        self
+    }
+
+    pub fn tag(&self, name: &str, tag: &str, version: &str) -> &Self {
+        let mut state = self.state.lock().unwrap();
+        state
+            .packages
+            .entry(name.to_string())
+            .or_default()
+            .dist_tags
+            .insert(tag.to_string(), version.to_string());
+        drop(state);
+        self
+    }
+
+    pub fn add_advisory(
+        &self,
+        name: &str,
+        id: u64,
+        severity: &str,
+        vulnerable_versions: &str,
+    ) -> &Self {
+        let mut state = self.state.lock().unwrap();
+        state
+            .advisories
+            .entry(name.to_string())
+            .or_default()
+            .push(json!({
+                "id": id,
+                "title": format!("Advisory {id} for {name}"),
+                "url": format!("https://example.com/advisories/{id}"),
+                "severity": severity,
+                "vulnerable_versions": vulnerable_versions,
+            }));
+        drop(state);
+        self
+    }
+
+    /// Every request received so far, as `"<METHOD> <path>"`.
+    #[must_use]
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+fn handle(state: &mut RegistryState, request: &Request) -> Response {
+    let path = request.path.trim_start_matches('/');
+    let path = urlencoding::decode(path)
+        .map(|p| p.into_owned())
+        .unwrap_or_else(|_| path.to_string());
+
+    match request.method.as_str() {
+        "POST" if path == "-/npm/v1/security/advisories/bulk" => {
+            let queried: BTreeMap<String, Value> =
+                serde_json::from_slice(&request.body).unwrap_or_default();
+            let found: serde_json::Map<String, Value> = queried
+                .keys()
+                .filter_map(|name| {
+                    state
+                        .advisories
+                        .get(name)
+                        .map(|advisories| (name.clone(), json!(advisories)))
+                })
+                .collect();
+            Response::json(&Value::Object(found))
+        }
+        "GET" if path.contains("/-/") || path.starts_with("mirror/") => state
+            .tarballs
+            .get(&path)
+            .filter(|_| !state.withheld.contains(&path))
+            .map_or_else(Response::not_found, |bytes| Response::bytes(bytes.clone())),
+        "GET" => state
+            .packages
+            .get(&path)
+            .map_or_else(Response::not_found, |entry| {
+                Response::json(&json!({
+                    "name": path,
+                    "dist-tags": entry.dist_tags,
+                    "versions": entry.versions,
+                }))
+            }),
+        "PUT" => publish(state, &path, &request.body),
+        _ => Response::not_found(),
+    }
+}
+
+/// Accepts a `pacm publish` document, refusing versions that are already published the
+/// way the npm registry does.
+fn publish(state: &mut RegistryState, name: &str, body: &[u8]) -> Response {
+    let Ok(document) = serde_json::from_slice::<Value>(body) else {
+        return Response::status(400, &json!({ "error": "invalid publish document" }));
+    };
+    let entry = state.packages.entry(name.to_string()).or_default();
+    let versions = document["versions"]
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    if versions
+        .keys()
+        .any(|version| entry.versions.contains_key(version))
+    {
+        return Response::status(
+            403,
+            &json!({ "error": "cannot publish over the previously published versions" }),
+        );
+    }
+
+    for (version, manifest) in versions {
+        entry.versions.insert(version, manifest);
+    }
+    if let Some(tags) = document["dist-tags"].as_object() {
+        for (tag, version) in tags {
+            entry.dist_tags.insert(
+                tag.clone(),
+                version.as_str().unwrap_or_default().to_string(),
+            );
+        }
+    }
+
+    let basename = name.rsplit('/').next().unwrap_or(name);
+    if let Some(attachments) = document["_attachments"].as_object() {
+        for (filename, attachment) in attachments {
+            let data = attachment["data"].as_str().unwrap_or_default();
+            if let Some(version) = filename
+                .strip_prefix(&format!("{basename}-"))
+                .and_then(|rest| rest.strip_suffix(".tgz"))
+                && let Ok(bytes) = base64::engine::general_purpose::STANDARD.decode(data)
+            {
+                state
+                    .tarballs
+                    .insert(format!("{name}/-/{basename}-{version}.tgz"), bytes);
+            }
+        }
+    }
+    Response::json(&json!({ "ok": true }))
+}
@@ -0,0 +1,229 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+    /// Headers sent besides `Content-Type` and `Content-Length`.
+    pub headers: Vec<(String, String)>,
+    /// Closes the connection after this many body bytes, while still advertising the
+    /// full `Content-Length`, to simulate a dropped download.
+    pub truncate_at: Option<usize>,
+}
+
+impl Response {
+    #[must_use]
+    pub fn json(body: &serde_json::Value) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: body.to_string().into_bytes(),
+            headers: Vec::new(),
+            truncate_at: None,
+        }
+    }
+
+    #[must_use]
+    pub fn bytes(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/octet-stream",
+            body,
+            headers: Vec::new(),
+            truncate_at: None,
+        }
+    }
+
+    /// A `206 Partial Content` response carrying `body`.
+    #[must_use]
+    pub fn partial(body: Vec<u8>) -> Self {
+        Self {
+            status: 206,
+            ..Self::bytes(body)
+        }
+    }
+
+    /// A `304 Not Modified` response, which has no body.
+    #[must_use]
+    pub fn not_modified() -> Self {
+        Self {
+            status: 304,
+            ..Self::bytes(Vec::new())
+        }
+    }
+
+    #[must_use]
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    #[must_use]
+    pub fn truncated(mut self, truncate_at: usize) -> Self {
+        self.truncate_at = Some(truncate_at);
+        self
+    }
+
+    /// A JSON response with an arbitrary status.
+    #[must_use]
+    pub fn status(status: u16, body: &serde_json::Value) -> Self {
+        Self {
+            status,
+            ..Self::json(body)
+        }
+    }
+
+    /// A `302` pointing at `location`.
+    #[must_use]
+    pub fn redirect(location: &str) -> Self {
+        Self {
+            status: 302,
+            ..Self::bytes(Vec::new())
+        }
+        .with_header("Location", location)
+    }
+
+    #[must_use]
+    pub fn not_found() -> Self {
+        Self {
+            status: 404,
+            content_type: "application/json",
+            body: br#"{"error":"Not found"}"#.to_vec(),
+            headers: Vec::new(),
+            truncate_at: None,
+        }
+    }
+}
+
+pub type Handler = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// A minimal HTTP/1.1 server on `127.0.0.1` that answers every request with `handler`
+/// and closes the connection afterwards.
+pub struct MockServer {
+    port: u16,
+}
+
+impl MockServer {
+    pub fn start(handler: Handler) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let handler = handler.clone();
+                thread::spawn(move || {
+                    let _ = serve(stream, &handler);
+                });
+            }
+        });
+
+        Ok(Self { port })
+    }
+
+    #[must_use]
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    #[must_use]
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}/", self.port)
+    }
+}
+
+fn serve(stream: TcpStream, handler: &Handler) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or("/").to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let content_length = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let request = Request {
+        method,
+        path,
+        headers,
+        body,
+    };
+    let response = handler(&request);
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len()
+    )?;
+    for (name, value) in &response.headers {
+        write!(stream, "{name}: {value}\r\n")?;
+    }
+    write!(stream, "\r\n")?;
+    let sent = response
+        .truncate_at
+        .map_or(response.body.len(), |limit| limit.min(response.body.len()));
+    stream.write_all(&response.body[..sent])?;
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        206 => "Partial Content",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,64 @@
+use base64::Engine;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use serde_json::Value;
+use sha2::{Digest, Sha512};
+
+/// Builds a gzipped npm tarball with `package/package.json` and a `package/index.js`
+/// that exports the package's `name@version`.
+#[must_use]
+pub fn build_tarball(manifest: &Value) -> Vec<u8> {
+    build_tarball_with_files(manifest, &[])
+}
+
+/// Like [`build_tarball`], with extra `(path, contents)` entries relative to `package/`,
+/// e.g. bundled dependencies under `node_modules/`.
+#[must_use]
+pub fn build_tarball_with_files(manifest: &Value, extra_files: &[(&str, &[u8])]) -> Vec<u8> {
+    let name = manifest["name"].as_str().unwrap_or_default();
+    let version = manifest["version"].as_str().unwrap_or_default();
+
+    let mut files = vec![
+        (
+            "package/package.json".to_string(),
+            serde_json::to_vec_pretty(manifest).unwrap_or_default(),
+        ),
+        (
+            "package/index.js".to_string(),
+            format!("module.exports = \"{name}@{version}\";\n").into_bytes(),
+        ),
+    ];
+    files.extend(
+        extra_files
+            .iter()
+            .map(|(path, contents)| (format!("package/{path}"), contents.to_vec())),
+    );
+
+    let encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, contents.as_slice())
+            .expect("failed to append tarball entry");
+    }
+
+    builder
+        .into_inner()
+        .and_then(GzEncoder::finish)
+        .expect("failed to finish tarball")
+}
+
+/// Computes the `sha512-<base64>` subresource integrity string for `bytes`.
+#[must_use]
+pub fn integrity(bytes: &[u8]) -> String {
+    let digest = Sha512::digest(bytes);
+    format!(
+        "sha512-{}",
+        base64::engine::general_purpose::STANDARD.encode(digest)
+    )
+}
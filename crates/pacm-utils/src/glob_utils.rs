@@ -0,0 +1,74 @@
+/// Matches `text` against a glob `pattern`.
+///
+/// `*` and `?` never cross a `/`, while `**` matches any number of characters
+/// including `/`.
+#[must_use]
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_from(&pattern, &text)
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let mut rest = &pattern[2..];
+            if rest.first() == Some(&'/') {
+                rest = &rest[1..];
+                // `**/` may also match zero directories.
+                if match_from(rest, text) {
+                    return true;
+                }
+            }
+            (0..=text.len()).any(|i| match_from(rest, &text[i..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if match_from(rest, &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => !text.is_empty() && text[0] != '/' && match_from(&pattern[1..], &text[1..]),
+        Some(c) => !text.is_empty() && text[0] == *c && match_from(&pattern[1..], &text[1..]),
+    }
+}
+
+#[must_use]
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_star_stays_in_segment() {
+        assert!(glob_match("packages/*", "packages/core"));
+        assert!(!glob_match("packages/*", "packages/core/src"));
+        assert!(glob_match("@scope/*", "@scope/utils"));
+        assert!(!glob_match("@scope/*", "@other/utils"));
+    }
+
+    #[test]
+    fn test_double_star_crosses_segments() {
+        assert!(glob_match("packages/**", "packages/a/b"));
+        assert!(glob_match("**/core", "core"));
+        assert!(glob_match("**/core", "packages/nested/core"));
+    }
+
+    #[test]
+    fn test_question_mark_and_literals() {
+        assert!(glob_match("app-?", "app-1"));
+        assert!(!glob_match("app-?", "app-10"));
+        assert!(glob_match("web", "web"));
+        assert!(!glob_match("web", "website"));
+    }
+}
@@ -1,7 +1,12 @@
+pub mod glob_utils;
 pub mod package_spec;
 pub mod path_utils;
 pub mod version_utils;
 
-pub use package_spec::parse_pkg_spec;
+pub use glob_utils::{glob_match, is_glob};
+pub use package_spec::{
+    PackageSpec, absolute_spec, is_directory_spec, is_tarball_spec, is_workspace_spec,
+    parse_package_spec, parse_pkg_spec,
+};
 pub use path_utils::*;
 pub use version_utils::*;
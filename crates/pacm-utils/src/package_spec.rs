@@ -1,7 +1,9 @@
+use std::path::Path;
+
 #[must_use]
 pub fn parse_pkg_spec(spec: &str) -> (String, String) {
-    if spec.starts_with('@') {
-        if let Some(scope_end) = spec[1..].find('/') {
+    if let Some(scoped) = spec.strip_prefix('@') {
+        if let Some(scope_end) = scoped.find('/') {
             let scope_and_name_end = scope_end + 2;
             if let Some(version_start) = spec[scope_and_name_end..].find('@') {
                 let name = spec[..scope_and_name_end + version_start].to_string();
@@ -23,3 +25,127 @@ pub fn parse_pkg_spec(spec: &str) -> (String, String) {
         }
     }
 }
+
+/// What an `install` argument refers to: a registry package, or a tarball on disk or
+/// behind a URL. `Tarball` also covers package directories, see [`is_directory_spec`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackageSpec {
+    Registry { name: String, version_range: String },
+    Tarball(String),
+}
+
+#[must_use]
+pub fn parse_package_spec(spec: &str) -> PackageSpec {
+    if is_tarball_spec(spec) || is_directory_spec(spec) {
+        PackageSpec::Tarball(spec.to_string())
+    } else {
+        let (name, version_range) = parse_pkg_spec(spec);
+        PackageSpec::Registry {
+            name,
+            version_range,
+        }
+    }
+}
+
+/// Whether `spec` uses the `workspace:` protocol to refer to a workspace member.
+#[must_use]
+pub fn is_workspace_spec(spec: &str) -> bool {
+    spec.starts_with("workspace:")
+}
+
+/// Whether `spec` names a tarball: a `.tgz`/`.tar.gz` path or `file:` spec, or any http(s)
+/// URL, as artifact servers and GitHub archive links rarely end in an extension. URLs of
+/// git repositories are not tarballs.
+#[must_use]
+pub fn is_tarball_spec(spec: &str) -> bool {
+    if spec.starts_with("http://") || spec.starts_with("https://") {
+        let url = spec.split('#').next().unwrap_or(spec);
+        return !url.ends_with(".git");
+    }
+    let spec = spec.strip_prefix("file:").unwrap_or(spec);
+    spec.ends_with(".tgz") || spec.ends_with(".tar.gz")
+}
+
+/// Whether `spec` points at a package directory on disk: any `link:` spec, or a `file:`
+/// spec that is not a tarball.
+#[must_use]
+pub fn is_directory_spec(spec: &str) -> bool {
+    spec.starts_with("link:") || (spec.starts_with("file:") && !is_tarball_spec(spec))
+}
+
+/// `spec` with a relative path resolved against `base`, keeping any `file:` or `link:`
+/// prefix. URLs and absolute paths are returned unchanged. Used where the spec is
+/// installed somewhere other than the directory it was given in, such as the global prefix.
+#[must_use]
+pub fn absolute_spec(spec: &str, base: &Path) -> String {
+    if spec.starts_with("http://") || spec.starts_with("https://") {
+        return spec.to_string();
+    }
+    let (prefix, location) = ["file:", "link:"]
+        .into_iter()
+        .find_map(|prefix| spec.strip_prefix(prefix).map(|rest| (prefix, rest)))
+        .unwrap_or(("", spec));
+    if Path::new(location).is_absolute() {
+        return spec.to_string();
+    }
+    format!("{prefix}{}", base.join(location).display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package_spec_detects_tarballs() {
+        assert_eq!(
+            parse_package_spec("./dist/pkg-1.0.0.tgz"),
+            PackageSpec::Tarball("./dist/pkg-1.0.0.tgz".to_string())
+        );
+        assert_eq!(
+            parse_package_spec("https://example.com/pkg/-/pkg-1.0.0.tar.gz"),
+            PackageSpec::Tarball("https://example.com/pkg/-/pkg-1.0.0.tar.gz".to_string())
+        );
+        assert!(is_tarball_spec("file:../pkg-1.0.0.tgz"));
+        assert!(!is_directory_spec("file:../pkg-1.0.0.tgz"));
+        assert!(is_directory_spec("file:../pkg"));
+        assert!(is_directory_spec("link:../pkg"));
+        assert_eq!(
+            parse_package_spec("link:../pkg"),
+            PackageSpec::Tarball("link:../pkg".to_string())
+        );
+        assert!(is_tarball_spec(
+            "https://codeload.github.com/acme/pkg/tar.gz/refs/tags/v1.0.0"
+        ));
+        assert!(!is_tarball_spec("https://github.com/acme/pkg.git#v1.0.0"));
+        assert!(!is_tarball_spec("^1.0.0"));
+        assert_eq!(
+            parse_package_spec("@scope/pkg@^2.0.0"),
+            PackageSpec::Registry {
+                name: "@scope/pkg".to_string(),
+                version_range: "^2.0.0".to_string(),
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_absolute_spec_resolves_relative_paths_only() {
+        let base = Path::new("/home/user/project");
+        assert_eq!(
+            absolute_spec("./pkg-1.0.0.tgz", base),
+            format!("{}", base.join("./pkg-1.0.0.tgz").display())
+        );
+        assert_eq!(
+            absolute_spec("link:../tool", base),
+            format!("link:{}", base.join("../tool").display())
+        );
+        assert_eq!(
+            absolute_spec("file:/opt/pkg.tgz", base),
+            "file:/opt/pkg.tgz"
+        );
+        assert_eq!(
+            absolute_spec("https://example.com/pkg.tgz", base),
+            "https://example.com/pkg.tgz"
+        );
+    }
+}
@@ -18,6 +18,7 @@ pub fn package_json_path(project_dir: &Path) -> PathBuf {
 }
 
 #[must_use]
+#[deprecated(note = "use `PacmConfig::lockfile_path`, which honours `lockfile-name`")]
 pub fn lock_file_path(project_dir: &Path) -> PathBuf {
     project_dir.join("pacm.lock")
 }